@@ -0,0 +1,307 @@
+//! 命令行模式
+//!
+//! `clash-verge --cli <命令>` 通过本地 HTTP 控制接口操作正在运行的实例，需先开启 `enable_control_api`。
+//! 令牌从 `--token` 或环境变量 `CLASH_VERGE_API_TOKEN` 读取，端口从 `--port` 或
+//! `CLASH_VERGE_API_PORT` 读取，缺省时使用当前实例的默认端口。选项也可写成 `--token=<token>` 形式。
+//!
+//! Windows 发行版为 GUI 程序，输出前会附加到启动它的终端的控制台。
+
+use crate::{constants::network::ports, utils::instance};
+use anyhow::{Result, anyhow, bail};
+use serde_json::{Value, json};
+use std::time::Duration;
+
+const TOKEN_ENV: &str = "CLASH_VERGE_API_TOKEN";
+const PORT_ENV: &str = "CLASH_VERGE_API_PORT";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+const USAGE: &str =
+    "Usage: clash-verge --cli [--token <token>] [--port <port>] [--instance <name> | --data-dir <path>] <command>
+
+Commands:
+  status                      show core, mode, system proxy, TUN and current profile
+  stats                       show speed and traffic totals
+  profiles                    list profiles
+  switch-profile <uid>        activate a profile
+  select-node <group> <node>  select a node in a proxy group
+  toggle-tun [on|off]         toggle or set TUN mode
+  toggle-sysproxy [on|off]    toggle or set the system proxy
+  mode <rule|global|direct>   change the outbound mode";
+
+#[derive(Debug, PartialEq, Eq)]
+enum CliCommand {
+    Status,
+    Stats,
+    Profiles,
+    SwitchProfile(String),
+    SelectNode { group: String, node: String },
+    ToggleTun(Option<bool>),
+    ToggleSystemProxy(Option<bool>),
+    Mode(String),
+    Help,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+struct CliOptions {
+    token: Option<String>,
+    port: Option<u16>,
+}
+
+fn parse_switch(value: Option<&str>) -> Result<Option<bool>> {
+    match value {
+        None => Ok(None),
+        Some("on" | "true" | "1") => Ok(Some(true)),
+        Some("off" | "false" | "0") => Ok(Some(false)),
+        Some(other) => bail!("expected on or off, got {other}"),
+    }
+}
+
+fn parse_args(args: &[String]) -> Result<(CliOptions, CliCommand)> {
+    let mut options = CliOptions::default();
+    let mut rest = Vec::new();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        // `--name=value` 与 `--name value` 等价
+        let (name, inline) = match arg.split_once('=') {
+            Some((name, value)) if name.starts_with("--") => (name, Some(value)),
+            _ => (arg.as_str(), None),
+        };
+        match name {
+            "--token" => options.token = inline.map(str::to_owned).or_else(|| iter.next().cloned()),
+            "--port" => {
+                let port = inline
+                    .or_else(|| iter.next().map(String::as_str))
+                    .ok_or_else(|| anyhow!("--port needs a value"))?;
+                options.port = Some(port.parse()?);
+            }
+            // 由 instance::init_from_args 处理
+            "--instance" | "--data-dir" => {
+                if inline.is_none() {
+                    iter.next();
+                }
+            }
+            _ => rest.push(arg.as_str()),
+        }
+    }
+
+    let command = match rest.as_slice() {
+        [] | ["help" | "--help" | "-h"] => CliCommand::Help,
+        ["status"] => CliCommand::Status,
+        ["stats"] => CliCommand::Stats,
+        ["profiles"] => CliCommand::Profiles,
+        ["switch-profile", uid] => CliCommand::SwitchProfile((*uid).to_owned()),
+        ["select-node", group, node] => CliCommand::SelectNode {
+            group: (*group).to_owned(),
+            node: (*node).to_owned(),
+        },
+        ["toggle-tun", value @ ..] if value.len() <= 1 => CliCommand::ToggleTun(parse_switch(value.first().copied())?),
+        ["toggle-sysproxy", value @ ..] if value.len() <= 1 => {
+            CliCommand::ToggleSystemProxy(parse_switch(value.first().copied())?)
+        }
+        ["mode", mode] => CliCommand::Mode((*mode).to_owned()),
+        _ => bail!("unknown command: {}", rest.join(" ")),
+    };
+    Ok((options, command))
+}
+
+struct ApiClient {
+    client: reqwest::Client,
+    base: String,
+    token: String,
+}
+
+impl ApiClient {
+    fn new(options: CliOptions) -> Result<Self> {
+        let token = options
+            .token
+            .or_else(|| std::env::var(TOKEN_ENV).ok())
+            .filter(|t| !t.is_empty())
+            .ok_or_else(|| anyhow!("missing API token, pass --token or set {TOKEN_ENV}"))?;
+        let port = match options.port {
+            Some(port) => port,
+            None => match std::env::var(PORT_ENV) {
+                Ok(port) => port.parse()?,
+                Err(_) => instance::offset_port(ports::CONTROL_API),
+            },
+        };
+        Ok(Self {
+            client: reqwest::Client::builder().no_proxy().timeout(REQUEST_TIMEOUT).build()?,
+            base: format!("http://127.0.0.1:{port}/api"),
+            token,
+        })
+    }
+
+    async fn call(&self, method: reqwest::Method, path: &str, body: Option<Value>) -> Result<Value> {
+        let mut request = self
+            .client
+            .request(method, format!("{}/{path}", self.base))
+            .bearer_auth(&self.token);
+        if let Some(body) = body {
+            request = request.json(&body);
+        }
+        let response = request
+            .send()
+            .await
+            .map_err(|err| anyhow!("failed to reach Clash Verge, is the control API enabled? ({err})"))?;
+        let status = response.status();
+        let value: Value = response.json().await.unwrap_or(Value::Null);
+        if !status.is_success() {
+            let message = value.get("error").and_then(Value::as_str).unwrap_or_default();
+            bail!("{status}: {message}");
+        }
+        Ok(value)
+    }
+
+    async fn current_flag(&self, key: &str) -> Result<bool> {
+        let status = self.call(reqwest::Method::GET, "status", None).await?;
+        Ok(status.get(key).and_then(Value::as_bool).unwrap_or(false))
+    }
+
+    async fn execute(&self, command: CliCommand) -> Result<Value> {
+        use reqwest::Method;
+
+        match command {
+            CliCommand::Status => self.call(Method::GET, "status", None).await,
+            CliCommand::Stats => self.call(Method::GET, "stats", None).await,
+            CliCommand::Profiles => self.call(Method::GET, "profiles", None).await,
+            CliCommand::SwitchProfile(uid) => {
+                self.call(Method::PUT, "profiles/current", Some(json!({ "uid": uid })))
+                    .await
+            }
+            CliCommand::SelectNode { group, node } => {
+                self.call(
+                    Method::POST,
+                    "proxies/select",
+                    Some(json!({ "group": group, "node": node })),
+                )
+                .await
+            }
+            CliCommand::ToggleTun(enabled) => {
+                let enabled = match enabled {
+                    Some(enabled) => enabled,
+                    None => !self.current_flag("tun").await?,
+                };
+                self.call(Method::PUT, "tun", Some(json!({ "enabled": enabled }))).await
+            }
+            CliCommand::ToggleSystemProxy(enabled) => {
+                let enabled = match enabled {
+                    Some(enabled) => enabled,
+                    None => !self.current_flag("system_proxy").await?,
+                };
+                self.call(Method::PUT, "system-proxy", Some(json!({ "enabled": enabled })))
+                    .await
+            }
+            CliCommand::Mode(mode) => self.call(Method::PUT, "mode", Some(json!({ "mode": mode }))).await,
+            CliCommand::Help => Ok(Value::Null),
+        }
+    }
+}
+
+/// 发行版没有自己的控制台，附加到父进程（终端）的控制台以便输出可见；
+/// 输出已被重定向或不是从终端启动时调用失败，忽略即可
+#[cfg(target_os = "windows")]
+fn attach_parent_console() {
+    use winapi::um::wincon::{ATTACH_PARENT_PROCESS, AttachConsole};
+
+    // SAFETY: 无参数指针，只影响本进程的控制台归属
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+/// 执行命令行模式并返回进程退出码
+pub fn run(args: &[String]) -> i32 {
+    #[cfg(target_os = "windows")]
+    attach_parent_console();
+    instance::init_from_args();
+    let (options, command) = match parse_args(args) {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            eprintln!("{err}\n\n{USAGE}");
+            return 2;
+        }
+    };
+    if command == CliCommand::Help {
+        println!("{USAGE}");
+        return 0;
+    }
+
+    let result = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(anyhow::Error::from)
+        .and_then(|runtime| {
+            runtime.block_on(async {
+                let client = ApiClient::new(options)?;
+                client.execute(command).await
+            })
+        });
+    match result {
+        Ok(value) => {
+            println!("{}", serde_json::to_string_pretty(&value).unwrap_or_default());
+            0
+        }
+        Err(err) => {
+            eprintln!("error: {err}");
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| (*s).to_owned()).collect()
+    }
+
+    #[test]
+    fn test_parse_cli_args() {
+        assert_eq!(
+            parse_args(&args(&["--token", "t", "--port", "4000", "status"])).ok(),
+            Some((
+                CliOptions {
+                    token: Some("t".into()),
+                    port: Some(4000),
+                },
+                CliCommand::Status
+            ))
+        );
+
+        assert_eq!(
+            parse_args(&args(&["--token=a=b", "--port=4000", "--data-dir=/tmp/cv", "stats"])).ok(),
+            Some((
+                CliOptions {
+                    token: Some("a=b".into()),
+                    port: Some(4000),
+                },
+                CliCommand::Stats
+            ))
+        );
+        assert!(parse_args(&args(&["--port=abc", "status"])).is_err());
+
+        assert_eq!(
+            parse_args(&args(&["select-node", "Proxy", "HK 01"]))
+                .map(|(_, c)| c)
+                .ok(),
+            Some(CliCommand::SelectNode {
+                group: "Proxy".into(),
+                node: "HK 01".into(),
+            })
+        );
+        assert_eq!(
+            parse_args(&args(&["--instance", "work", "toggle-tun", "off"]))
+                .map(|(_, c)| c)
+                .ok(),
+            Some(CliCommand::ToggleTun(Some(false)))
+        );
+        assert_eq!(
+            parse_args(&args(&["toggle-sysproxy"])).map(|(_, c)| c).ok(),
+            Some(CliCommand::ToggleSystemProxy(None))
+        );
+        assert_eq!(parse_args(&[]).map(|(_, c)| c).ok(), Some(CliCommand::Help));
+        assert!(parse_args(&args(&["toggle-tun", "maybe"])).is_err());
+        assert!(parse_args(&args(&["unknown"])).is_err());
+    }
+}
@@ -1,10 +1,22 @@
 use super::CmdResult;
-use crate::core::sysopt::Sysopt;
-use crate::utils::resolve::ui::{self, UiReadyStage};
+use crate::core::{
+    notify::{NotificationEvent, notify_event},
+    sysopt::Sysopt,
+    tray::icon_theme,
+};
+use crate::utils::resolve::{
+    startup,
+    ui::{self, UiReadyStage},
+};
 use crate::{
     cmd::StringifyErr as _,
+    config::IVerge,
     feat,
-    utils::dirs::{self, PathBufExec as _},
+    utils::{
+        crash,
+        dirs::{self, PathBufExec as _},
+        logger,
+    },
 };
 use clash_verge_logging::{Type, logging};
 use smartstring::alias::String;
@@ -35,6 +47,78 @@ pub async fn open_logs_dir() -> CmdResult<()> {
     open::that(log_dir).stringify_err()
 }
 
+/// 修改应用日志等级，立即生效并保存
+#[tauri::command]
+pub async fn set_log_level(level: String) -> CmdResult {
+    if logger::parse_level(&level).is_none() {
+        return Err(format!("unsupported log level: {level}").into());
+    }
+    let patch = IVerge {
+        app_log_level: Some(level),
+        ..IVerge::default()
+    };
+    feat::patch_verge(&patch, false)
+        .await
+        .stringify_err_log(|e| logging!(error, Type::Cmd, "Failed to save log level: {e}"))
+}
+
+/// 打包最近 `range_hours` 小时的日志用于反馈问题，返回 zip 文件路径
+#[tauri::command]
+pub async fn collect_logs(range_hours: u32) -> CmdResult<String> {
+    let path = logger::collect_logs(range_hours)
+        .await
+        .stringify_err_log(|e| logging!(error, Type::Cmd, "Failed to collect logs: {e}"))?;
+    Ok(dirs::path_to_str(&path).stringify_err()?.into())
+}
+
+/// 生成脱敏后的诊断包用于反馈问题，返回 zip 文件路径
+#[tauri::command]
+pub async fn generate_diagnostics_bundle() -> CmdResult<String> {
+    let path = feat::generate_diagnostics_bundle()
+        .await
+        .stringify_err_log(|e| logging!(error, Type::Cmd, "Failed to generate diagnostics bundle: {e}"))?;
+    Ok(dirs::path_to_str(&path).stringify_err()?.into())
+}
+
+/// 列出崩溃报告
+#[tauri::command]
+pub fn list_crash_reports() -> Vec<crash::CrashReportSummary> {
+    crash::list_reports()
+}
+
+/// 打开崩溃报告目录
+#[tauri::command]
+pub fn open_crash_reports_dir() -> CmdResult<()> {
+    let dir = crash::report_dir();
+    std::fs::create_dir_all(&dir).stringify_err()?;
+    open::that(dir).stringify_err()
+}
+
+/// 获取本次启动各阶段的执行结果
+#[tauri::command]
+pub fn get_startup_report() -> startup::StartupReport {
+    startup::report()
+}
+
+/// 用户确认后上传指定的崩溃报告
+#[tauri::command]
+pub async fn upload_crash_report(id: String) -> CmdResult {
+    crash::upload_report(&id)
+        .await
+        .stringify_err_log(|e| logging!(error, Type::Cmd, "Failed to upload crash report: {e}"))
+}
+
+/// 将数据目录迁移到新位置，成功后重启应用
+#[tauri::command]
+pub async fn migrate_app_home(new_path: String) -> CmdResult {
+    feat::ensure_unlocked().await?;
+    feat::migrate_app_home(Path::new(new_path.as_str()))
+        .await
+        .stringify_err_log(|e| logging!(error, Type::Cmd, "Failed to migrate app data: {e}"))?;
+    feat::restart_app().await;
+    Ok(())
+}
+
 /// 打开网页链接
 #[tauri::command]
 pub fn open_web_url(url: String) -> CmdResult<()> {
@@ -83,7 +167,7 @@ pub async fn restart_app() -> CmdResult<()> {
 /// 获取便携版标识
 #[tauri::command]
 pub fn get_portable_flag() -> bool {
-    *dirs::PORTABLE_FLAG.get().unwrap_or(&false)
+    dirs::is_portable()
 }
 
 /// 获取应用目录
@@ -229,6 +313,27 @@ pub async fn copy_icon_file(path: String, icon_info: IconInfo) -> CmdResult<Stri
     }
 }
 
+/// 列出托盘图标主题
+#[tauri::command]
+pub async fn list_tray_icon_themes() -> CmdResult<Vec<String>> {
+    icon_theme::list_tray_icon_themes().await.stringify_err()
+}
+
+/// 切换托盘图标主题，传入空字符串恢复内置图标
+#[tauri::command]
+pub async fn set_tray_icon_theme(name: String) -> CmdResult<()> {
+    icon_theme::set_tray_icon_theme(name)
+        .await
+        .stringify_err_log(|e| logging!(error, Type::Tray, "Failed to set tray icon theme: {e}"))
+}
+
+/// 发送一条测试通知，用于确认系统通知权限
+#[tauri::command]
+pub async fn send_test_notification() -> CmdResult<()> {
+    notify_event(NotificationEvent::Test).await;
+    Ok(())
+}
+
 /// 通知UI已准备就绪
 #[tauri::command]
 pub fn notify_ui_ready() {
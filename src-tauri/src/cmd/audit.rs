@@ -0,0 +1,13 @@
+use super::{CmdResult, StringifyErr as _};
+use crate::core::audit::{AuditEntry, AuditLog, AuditLogFilter};
+
+const DEFAULT_QUERY_LIMIT: usize = 500;
+
+/// 查询操作审计日志，按时间从新到旧返回
+#[tauri::command]
+pub async fn query_audit_log(filter: Option<AuditLogFilter>, limit: Option<usize>) -> CmdResult<Vec<AuditEntry>> {
+    let filter = filter.unwrap_or_default();
+    AuditLog::query(&filter, limit.unwrap_or(DEFAULT_QUERY_LIMIT))
+        .await
+        .stringify_err()
+}
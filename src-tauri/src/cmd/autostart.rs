@@ -0,0 +1,13 @@
+use super::{CmdResult, StringifyErr as _};
+use crate::feat::{self, AutostartConfig};
+
+/// 开机自启、静默启动与延迟启动设置
+#[tauri::command]
+pub async fn get_autostart_config() -> CmdResult<AutostartConfig> {
+    Ok(feat::get_autostart_config().await)
+}
+
+#[tauri::command]
+pub async fn set_autostart_config(config: AutostartConfig) -> CmdResult {
+    feat::set_autostart_config(config).await.stringify_err()
+}
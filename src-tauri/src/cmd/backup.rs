@@ -24,6 +24,7 @@ pub async fn delete_local_backup(filename: String) -> CmdResult<()> {
 /// Restore local backup
 #[tauri::command]
 pub async fn restore_local_backup(filename: String) -> CmdResult<()> {
+    feat::ensure_unlocked().await?;
     feat::restore_local_backup(filename).await.stringify_err()
 }
 
@@ -32,3 +33,18 @@ pub async fn restore_local_backup(filename: String) -> CmdResult<()> {
 pub async fn export_local_backup(filename: String, destination: String) -> CmdResult<()> {
     feat::export_local_backup(filename, destination).await.stringify_err()
 }
+
+/// Export all local settings into an encrypted bundle
+#[tauri::command]
+pub async fn export_settings(path: String, password: String, include_subscriptions: bool) -> CmdResult<()> {
+    feat::export_settings(path, password, include_subscriptions)
+        .await
+        .stringify_err()
+}
+
+/// Import settings from an encrypted bundle
+#[tauri::command]
+pub async fn import_settings(path: String, password: String) -> CmdResult<()> {
+    feat::ensure_unlocked().await?;
+    feat::import_settings(path, password).await.stringify_err()
+}
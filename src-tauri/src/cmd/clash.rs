@@ -20,21 +20,41 @@ pub async fn copy_clash_env() -> CmdResult {
     Ok(())
 }
 
+/// 复制指定终端的代理环境变量命令，`shell` 可选 bash/fish/powershell/cmd/nushell/dotenv
+#[tauri::command]
+pub async fn copy_proxy_env(shell: Option<String>) -> CmdResult<String> {
+    feat::copy_proxy_env(shell.as_deref())
+        .await
+        .map(Into::into)
+        .stringify_err_log(|e| logging!(error, Type::ProxyMode, "Failed to copy proxy env: {e}"))
+}
+
 /// 获取Clash信息
 #[tauri::command]
 pub async fn get_clash_info() -> CmdResult<ClashInfo> {
     Ok(Config::clash().await.data_arc().get_client_info())
 }
 
+/// 更换外部控制器密钥，返回新密钥
+#[tauri::command]
+pub async fn rotate_core_secret() -> CmdResult<String> {
+    feat::ensure_unlocked().await?;
+    feat::rotate_core_secret()
+        .await
+        .stringify_err_log(|e| logging!(error, Type::Core, "Failed to rotate core secret: {e}"))
+}
+
 /// 修改Clash配置
 #[tauri::command]
 pub async fn patch_clash_config(payload: Mapping) -> CmdResult {
+    feat::ensure_unlocked().await?;
     feat::patch_clash(&payload).await.stringify_err()
 }
 
 /// 修改Clash模式
 #[tauri::command]
 pub async fn patch_clash_mode(payload: String) -> CmdResult {
+    feat::ensure_unlocked().await?;
     feat::change_clash_mode(payload).await;
     Ok(())
 }
@@ -42,6 +62,7 @@ pub async fn patch_clash_mode(payload: String) -> CmdResult {
 /// 切换Clash核心
 #[tauri::command]
 pub async fn change_clash_core(clash_core: String) -> CmdResult<Option<String>> {
+    feat::ensure_unlocked().await?;
     logging!(info, Type::Config, "changing core to {clash_core}");
 
     match CoreManager::global().change_core(&clash_core).await {
@@ -65,7 +86,7 @@ pub async fn change_clash_core(clash_core: String) -> CmdResult<Option<String>>
             }
         }
         Err(err) => {
-            let error_msg: String = err;
+            let error_msg: String = err.to_string().into();
             logging!(error, Type::Core, "failed to change core: {error_msg}");
             handle::Handle::notice_message("config_core::change_error", error_msg.clone());
             Ok(Some(error_msg))
@@ -121,6 +142,7 @@ pub async fn test_delay(url: String) -> CmdResult<u32> {
 /// 保存DNS配置到单独文件
 #[tauri::command]
 pub async fn save_dns_config(dns_config: Mapping) -> CmdResult {
+    feat::ensure_unlocked().await?;
     use crate::utils::dirs;
     use serde_yaml_ng;
     use tokio::fs;
@@ -139,6 +161,7 @@ pub async fn save_dns_config(dns_config: Mapping) -> CmdResult {
 /// 应用或撤销DNS配置
 #[tauri::command]
 pub async fn apply_dns_config(apply: bool) -> CmdResult {
+    feat::ensure_unlocked().await?;
     if apply {
         // 读取DNS配置文件
         let dns_path = dirs::app_home_dir().stringify_err()?.join(constants::files::DNS_CONFIG);
@@ -0,0 +1,10 @@
+use super::{CmdResult, StringifyErr as _};
+use crate::{core::control_api::ControlApi, feat};
+use smartstring::alias::String;
+
+/// 生成新的本地控制接口令牌，旧令牌立即失效
+#[tauri::command]
+pub async fn regenerate_control_api_token() -> CmdResult<String> {
+    feat::ensure_unlocked().await?;
+    ControlApi::global().regenerate_token().await.stringify_err()
+}
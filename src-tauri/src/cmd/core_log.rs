@@ -0,0 +1,13 @@
+use crate::core::core_log::{CoreLogFilter, CoreLogHub, CoreLogLine};
+
+/// 按条件订阅内核日志，返回缓存中已匹配的日志，之后的新日志通过 `verge://core-log` 事件推送
+#[tauri::command]
+pub fn subscribe_core_logs(filter: CoreLogFilter) -> Vec<CoreLogLine> {
+    CoreLogHub::global().subscribe(filter)
+}
+
+/// 停止推送内核日志
+#[tauri::command]
+pub fn unsubscribe_core_logs() {
+    CoreLogHub::global().unsubscribe();
+}
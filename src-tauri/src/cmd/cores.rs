@@ -0,0 +1,84 @@
+use super::{CmdResult, StringifyErr as _};
+use crate::{
+    core::{
+        CoreManager,
+        geodata::{self, GeoUpdateInfo},
+        manager::{CoreInfo, CoreUpdateInfo},
+    },
+    feat,
+};
+use clash_verge_logging::{Type, logging};
+use smartstring::alias::String;
+
+/// 列出内置与自定义内核及其版本
+#[tauri::command]
+pub async fn list_cores() -> CmdResult<Vec<CoreInfo>> {
+    CoreManager::global().list_cores().await.stringify_err()
+}
+
+/// 注册自定义内核二进制
+#[tauri::command]
+pub async fn register_core(name: String, path: String) -> CmdResult<CoreInfo> {
+    feat::ensure_unlocked().await?;
+    CoreManager::global()
+        .register_core(&name, &path)
+        .await
+        .stringify_err_log(|e| logging!(error, Type::Core, "Failed to register core {name}: {e}"))
+}
+
+/// 用新的二进制升级自定义内核
+#[tauri::command]
+pub async fn upgrade_custom_core(name: String, path: String) -> CmdResult<CoreInfo> {
+    feat::ensure_unlocked().await?;
+    CoreManager::global()
+        .upgrade_custom_core(&name, &path)
+        .await
+        .stringify_err_log(|e| logging!(error, Type::Core, "Failed to upgrade core {name}: {e}"))
+}
+
+#[tauri::command]
+pub async fn unregister_core(name: String) -> CmdResult {
+    feat::ensure_unlocked().await?;
+    CoreManager::global().unregister_core(&name).await.stringify_err()
+}
+
+/// 切换当前使用的内核并重新生成运行配置
+#[tauri::command]
+pub async fn set_active_core(name: String) -> CmdResult<Option<String>> {
+    super::change_clash_core(name).await
+}
+
+/// 查询当前内置内核是否有新版本
+#[tauri::command]
+pub async fn check_core_update() -> CmdResult<CoreUpdateInfo> {
+    CoreManager::global()
+        .check_core_update()
+        .await
+        .stringify_err_log(|e| logging!(warn, Type::Core, "Failed to check core update: {e}"))
+}
+
+/// 升级当前内置内核，进度通过 `core-upgrade-progress` 事件推送
+#[tauri::command]
+pub async fn upgrade_core() -> CmdResult<String> {
+    feat::ensure_unlocked().await?;
+    CoreManager::global()
+        .upgrade_core()
+        .await
+        .stringify_err_log(|e| logging!(error, Type::Core, "Failed to upgrade core: {e}"))
+}
+
+/// 检查 GeoIP / GeoSite / MMDB 数据库是否有更新
+#[tauri::command]
+pub async fn check_geo_updates() -> CmdResult<Vec<GeoUpdateInfo>> {
+    geodata::check_geo_updates()
+        .await
+        .stringify_err_log(|e| logging!(warn, Type::Core, "Failed to check geo updates: {e}"))
+}
+
+/// 更新有变化的数据库文件并重启内核，返回已更新的文件名
+#[tauri::command]
+pub async fn update_geo_databases() -> CmdResult<Vec<String>> {
+    geodata::update_geo_databases()
+        .await
+        .stringify_err_log(|e| logging!(error, Type::Core, "Failed to update geo databases: {e}"))
+}
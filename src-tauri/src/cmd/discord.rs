@@ -4,19 +4,23 @@
 
 
 
+use super::{CmdError, CmdResult};
 use crate::config::Config;
 use crate::core::discord_rpc;
+use crate::core::event_bus::EventBus;
+use crate::core::handle::Handle;
+use crate::core::traffic::TrafficHub;
 use crate::process::AsyncHandler;
+use crate::utils::i18n;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicU64, Ordering};
 use tokio::sync::Mutex;
 use tauri::async_runtime::JoinHandle;
-use serde::Deserialize;
-use futures::StreamExt;
 use crate::utils::dirs::app_home_dir;
 use std::fs;
-use std::time::SystemTime;
+use std::time::{Duration, Instant, SystemTime};
 
+/// Last speeds read from `TrafficHub` while it was live, shown as last-known values during reconnects
 static TRAFFIC_UP: AtomicU64 = AtomicU64::new(0);
 static TRAFFIC_DOWN: AtomicU64 = AtomicU64::new(0);
 
@@ -67,12 +71,26 @@ fn save_traffic_data(up: u64, down: u64) {
 static DISCORD_LOOP_HANDLE: once_cell::sync::Lazy<Arc<Mutex<Option<JoinHandle<()>>>>> =
     once_cell::sync::Lazy::new(|| Arc::new(Mutex::new(None)));
 
-#[derive(Deserialize)]
-struct TrafficData {
-    up: u64,
-    down: u64,
+/// How long to wait for the Discord IPC handshake before giving up
+const DISCORD_CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// How often the displayed node is delay tested, independent of the 1s presence loop
+const LATENCY_TEST_INTERVAL: Duration = Duration::from_secs(30);
+
+const DEFAULT_TEST_URL: &str = "https://www.gstatic.com/generate_204";
+const DEFAULT_TEST_TIMEOUT_MS: u32 = 5000;
+
+/// Last delay test of the node shown in presence, rendered as `{latency}`
+#[derive(Default)]
+struct LatencyCache {
+    node: String,
+    delay: Option<u32>,
+    tested_at: Option<Instant>,
 }
 
+static LATENCY_CACHE: once_cell::sync::Lazy<Mutex<LatencyCache>> =
+    once_cell::sync::Lazy::new(|| Mutex::new(LatencyCache::default()));
+
 /// Helper to format bytes in a human-readable way
 fn format_bytes(bytes: u64) -> String {
     if bytes < 1024 {
@@ -97,9 +115,68 @@ fn format_speed(speed: u64) -> String {
     }
 }
 
+/// Fill `{name}` placeholders in the status template; plugin variables are available as `{plugin.key}`
+fn render_template(template: &str, vars: &[(&str, &str)]) -> String {
+    let mut rendered = template.to_owned();
+    for (key, value) in vars {
+        rendered = rendered.replace(&format!("{{{key}}}"), value);
+    }
+    for (key, value) in crate::core::plugin::PluginManager::global().template_vars() {
+        rendered = rendered.replace(&format!("{{{key}}}"), &value);
+    }
+    rendered
+}
+
+/// Delay test the displayed node when it changed or the cached result is older than the interval
+async fn test_latency() {
+    let node = {
+        let mut cache = LATENCY_CACHE.lock().await;
+        let due = cache.tested_at.is_none_or(|at| at.elapsed() >= LATENCY_TEST_INTERVAL);
+        if cache.node.is_empty() || !due {
+            return;
+        }
+        cache.tested_at = Some(Instant::now());
+        cache.node.clone()
+    };
+
+    let (test_url, timeout) = {
+        let verge = Config::verge().await.latest_arc();
+        (
+            verge.default_latency_test.clone().unwrap_or_else(|| DEFAULT_TEST_URL.into()),
+            verge
+                .default_latency_timeout
+                .and_then(|t| u32::try_from(t).ok())
+                .filter(|&t| t > 0)
+                .unwrap_or(DEFAULT_TEST_TIMEOUT_MS),
+        )
+    };
+    let mihomo = Handle::mihomo().await;
+    let result = mihomo.delay_proxy_by_name(&node, &test_url, timeout).await;
+    drop(mihomo);
+
+    let mut cache = LATENCY_CACHE.lock().await;
+    // The node may have changed while the test was running
+    if cache.node == node {
+        // A zero delay means the test timed out
+        cache.delay = result.ok().map(|r| r.delay).filter(|&delay| delay > 0);
+    }
+}
+
+/// Track the node shown in presence; a new node drops the cached delay so it is tested right away
+async fn set_latency_node(node: &str) -> Option<u32> {
+    let mut cache = LATENCY_CACHE.lock().await;
+    if cache.node != node {
+        *cache = LatencyCache {
+            node: node.to_owned(),
+            ..LatencyCache::default()
+        };
+    }
+    cache.delay
+}
+
 /// Toggle Discord Rich Presence on or off
 #[tauri::command]
-pub async fn toggle_discord_rpc(enabled: bool) -> Result<(), String> {
+pub async fn toggle_discord_rpc(enabled: bool) -> CmdResult {
     if enabled {
         // Get custom app ID from config if set
         let verge = Config::verge().await;
@@ -110,13 +187,18 @@ pub async fn toggle_discord_rpc(enabled: bool) -> Result<(), String> {
         discord_rpc::init_discord_rpc(app_id);
         discord_rpc::connect_discord_rpc();
         
-        // Small delay to allow connection before updating activity
-        tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+        // Wait for the IPC handshake before updating activity
+        let connected = discord_rpc::wait_for_connection(DISCORD_CONNECT_TIMEOUT).await;
         
         // Start the background update loop
         start_discord_update_loop().await;
         
         update_discord_activity().await;
+
+        // Recoverable: calling this again once Discord is running completes the connection
+        if !connected {
+            return Err(CmdError::Unavailable("Discord is not running or could not be reached".into()));
+        }
     } else {
         stop_discord_update_loop().await;
         discord_rpc::shutdown_discord_rpc();
@@ -134,60 +216,35 @@ async fn start_discord_update_loop() {
     }
     
     let loop_handle = AsyncHandler::spawn(|| async move {
-        // Traffic monitor task
-        let traffic_monitor = AsyncHandler::spawn(|| async move {
+        // Speeds come from the shared traffic stream, started here in case nothing else has yet
+        TrafficHub::global().init();
+
+        // Periodic update loop
+        let update_loop = async {
             loop {
-                let clash_info = Config::clash().await.data_arc().get_client_info();
-                let server = clash_info.server;
-                let secret = clash_info.secret.unwrap_or_default();
-                let url = format!("http://{}/traffic", server);
+                tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
                 
-                let client = reqwest::Client::new();
-                let request = client.get(&url);
-                let request = if !secret.is_empty() {
-                    request.header("Authorization", format!("Bearer {}", secret))
-                } else {
-                    request
-                };
-
-                match request.send().await {
-                    Ok(resp) => {
-                        let mut stream = resp.bytes_stream();
-                        while let Some(item) = stream.next().await {
-                            match item {
-                                Ok(bytes) => {
-                                    if let Ok(data) = serde_json::from_slice::<TrafficData>(&bytes) {
-                                        TRAFFIC_UP.store(data.up, Ordering::Relaxed);
-                                        TRAFFIC_DOWN.store(data.down, Ordering::Relaxed);
-                                    }
-                                }
-                                Err(_) => break,
-                            }
-                        }
-                    }
-                    Err(_) => {
-                        // Reset traffic on error
-                        TRAFFIC_UP.store(0, Ordering::Relaxed);
-                        TRAFFIC_DOWN.store(0, Ordering::Relaxed);
-                    }
+                // Re-check if still enabled
+                let verge_data = Config::verge().await.data_arc();
+                if !verge_data.enable_discord_rpc.unwrap_or(false) {
+                    break;
                 }
-                // Wait before reconnecting
-                tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
+                
+                update_discord_activity().await;
             }
-        });
+        };
 
-        // Periodic update loop
-        loop {
-            tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
-            
-            // Re-check if still enabled
-            let verge_data = Config::verge().await.data_arc();
-            if !verge_data.enable_discord_rpc.unwrap_or(false) {
-                traffic_monitor.abort();
-                break;
+        // Latency monitor, its own cadence so presence updates never wait on a delay test
+        let latency_monitor = async {
+            loop {
+                test_latency().await;
+                tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
             }
-            
-            update_discord_activity().await;
+        };
+
+        tokio::select! {
+            _ = update_loop => {}
+            _ = latency_monitor => {}
         }
     });
     
@@ -203,11 +260,34 @@ async fn stop_discord_update_loop() {
     // Also reset traffic data
     TRAFFIC_UP.store(0, Ordering::Relaxed);
     TRAFFIC_DOWN.store(0, Ordering::Relaxed);
+    *LATENCY_CACHE.lock().await = LatencyCache::default();
+}
+
+/// Reconnect Discord RPC and restart the update loop, used after the system wakes up
+pub async fn restart_discord_rpc() {
+    if !Config::verge().await.data_arc().enable_discord_rpc.unwrap_or(false) {
+        return;
+    }
+    discord_rpc::disconnect_discord_rpc();
+    discord_rpc::connect_discord_rpc();
+    start_discord_update_loop().await;
+    update_discord_activity().await;
+}
+
+/// Refresh the activity as soon as the proxy state changes instead of waiting for the next tick
+pub fn listen_state_events() {
+    EventBus::global().listen("discord", |_| update_discord_activity());
+}
+
+/// Whether live traffic speeds are up to date, false while the stream is reconnecting
+#[tauri::command]
+pub fn is_traffic_stream_healthy() -> bool {
+    TrafficHub::global().is_healthy()
 }
 
-/// Manually refresh Discord activity (also used internally when proxy mode changes)
+/// Manually refresh Discord activity
 #[tauri::command]
-pub async fn refresh_discord_activity() -> Result<(), String> {
+pub async fn refresh_discord_activity() -> CmdResult {
     update_discord_activity().await;
     Ok(())
 }
@@ -228,7 +308,13 @@ pub async fn update_discord_activity() {
     // let conn_mode = ... 
 
 
-    // Traffic info
+    // Traffic info, keeping the last live speeds while the stream reconnects
+    let traffic_live = TrafficHub::global().is_healthy();
+    if traffic_live {
+        let speed = TrafficHub::global().latest();
+        TRAFFIC_UP.store(speed.up, Ordering::Relaxed);
+        TRAFFIC_DOWN.store(speed.down, Ordering::Relaxed);
+    }
     let up = TRAFFIC_UP.load(Ordering::Relaxed);
     let down = TRAFFIC_DOWN.load(Ordering::Relaxed);
     
@@ -292,10 +378,20 @@ pub async fn update_discord_activity() {
         });
 
 
-    let details = format!("↑ {} • ↓ {}", 
-        format_speed(up), 
-        format_speed(down)
-    );
+    // Last-known speeds are marked while the traffic stream reconnects
+    let details = if traffic_live {
+        format!("↑ {} • ↓ {}", 
+            format_speed(up), 
+            format_speed(down)
+        )
+    } else {
+        i18n::sync_locale().await;
+        format!("↑ {} • ↓ {} {}", 
+            format_speed(up), 
+            format_speed(down),
+            rust_i18n::t!("discordRpc.reconnecting")
+        )
+    };
 
     // Clash mode (not displayed anymore)
     // let clash = Config::clash().await;
@@ -304,6 +400,8 @@ pub async fn update_discord_activity() {
 
     // Fetch the current selected node
     let mut selected_node = String::new();
+    let mut latency_node = String::new();
+    let mut favorite_node = String::new();
     let mut total_proxies = 0;
     
     if let Ok(proxies) = mihomo.get_proxies().await {
@@ -353,11 +451,36 @@ pub async fn update_discord_activity() {
                 selected_node = current;
             }
         }
+        latency_node = selected_node.clone();
+
+        // Load-balance / url-test groups: show the outbounds active connections actually use
+        if let Some(balanced) = crate::feat::balanced_group(&proxies, &main_group_name) {
+            if let Ok(connections) = mihomo.get_connections().await {
+                let outbounds = crate::feat::effective_outbounds(&proxies, &connections, &balanced);
+                if let Some(first) = outbounds.first() {
+                    latency_node = first.name.to_string();
+                }
+                if !outbounds.is_empty() {
+                    selected_node = outbounds
+                        .iter()
+                        .take(3)
+                        .map(|o| o.name.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                }
+            }
+        }
 
         
         total_proxies = proxies.proxies.len();
+        favorite_node = crate::feat::favorite_node(&proxies).await.to_string();
     }
 
+    let latency = set_latency_node(&latency_node)
+        .await
+        .map(|delay| format!("{delay}ms"))
+        .unwrap_or_default();
+
     // Active connections (optional, not currently displayed but available)
     // let mut active_connections = 0;
     // if let Ok(connections) = mihomo.get_connections().await {
@@ -366,7 +489,26 @@ pub async fn update_discord_activity() {
 
     // Pretty state: "TUN • Rule • Node"
     // State: "All: ↑ 1.2 MB • ↓ 41.7 MB | ProxyName"
-    let state = if !selected_node.is_empty() {
+    let template = Config::verge().await.latest_arc().discord_state_template.clone();
+    let state = if let Some(template) = template.filter(|t| !t.is_empty()) {
+        render_template(&template, &[
+            ("up", &format_speed(up)),
+            ("down", &format_speed(down)),
+            ("total_up", &format_bytes(total_up)),
+            ("total_down", &format_bytes(total_down)),
+            ("node", &selected_node),
+            ("latency", &latency),
+            ("favorite_node", &favorite_node),
+            ("profile", current_profile.as_deref().unwrap_or_default()),
+        ])
+    } else if !selected_node.is_empty() && !latency.is_empty() {
+        format!("All: ↑ {} • ↓ {} | {} • {}", 
+            format_bytes(total_up), 
+            format_bytes(total_down), 
+            selected_node,
+            latency
+        )
+    } else if !selected_node.is_empty() {
         format!("All: ↑ {} • ↓ {} | {}", 
             format_bytes(total_up), 
             format_bytes(total_down), 
@@ -393,14 +535,14 @@ pub async fn update_discord_activity() {
 
 /// Commands to manually unload (stop) Discord RPC
 #[tauri::command]
-pub async fn unload_discord_rpc() -> Result<(), String> {
+pub async fn unload_discord_rpc() -> CmdResult {
     toggle_discord_rpc(false).await?;
     Ok(())
 }
 
 /// Command to manually reload Discord RPC (stop -> wait -> start)
 #[tauri::command]
-pub async fn trigger_discord_rpc_reload() -> Result<(), String> {
+pub async fn trigger_discord_rpc_reload() -> CmdResult {
     toggle_discord_rpc(false).await?;
     tokio::time::sleep(tokio::time::Duration::from_millis(1500)).await;
     toggle_discord_rpc(true).await?;
@@ -408,7 +550,8 @@ pub async fn trigger_discord_rpc_reload() -> Result<(), String> {
 }
 
 /// Initialize Discord RPC on app startup if enabled
-pub async fn init_discord_rpc_on_startup() {
+/// Returns false when Discord RPC is enabled but Discord could not be reached
+pub async fn init_discord_rpc_on_startup() -> bool {
     let verge = Config::verge().await;
     let verge_data = verge.data_arc();
     
@@ -421,10 +564,12 @@ pub async fn init_discord_rpc_on_startup() {
         // Start the background update loop
         start_discord_update_loop().await;
         
-        // Small delay to allow connection
-        tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+        // Wait for the IPC handshake instead of a fixed delay
+        let connected = discord_rpc::wait_for_connection(DISCORD_CONNECT_TIMEOUT).await;
         update_discord_activity().await;
+        return connected;
     }
+    true
 }
 
 /// Shutdown Discord RPC on app exit
@@ -0,0 +1,51 @@
+use super::{CmdResult, StringifyErr as _};
+use crate::feat::{self, DnsPreset, DnsQueryResult, FakeIpStatus};
+use clash_verge_logging::{Type, logging};
+use serde_yaml_ng::Mapping;
+use smartstring::alias::String;
+
+/// 通过内核解析域名，`record_type` 默认为 A
+#[tauri::command]
+pub async fn resolve_via_core(domain: String, record_type: Option<String>) -> CmdResult<DnsQueryResult> {
+    feat::resolve_via_core(&domain, record_type.as_deref())
+        .await
+        .stringify_err_log(|e| logging!(warn, Type::Network, "DNS query for {domain} failed: {e}"))
+}
+
+#[tauri::command]
+pub async fn flush_dns_cache() -> CmdResult {
+    feat::flush_dns_cache()
+        .await
+        .stringify_err_log(|e| logging!(error, Type::Network, "Failed to flush DNS cache: {e}"))
+}
+
+/// 查询 fake-ip 映射与地址池使用情况，`filter` 可为域名或 IP
+#[tauri::command]
+pub async fn get_fakeip_mappings(filter: Option<String>) -> CmdResult<FakeIpStatus> {
+    feat::get_fakeip_mappings(filter.as_deref())
+        .await
+        .stringify_err_log(|e| logging!(warn, Type::Network, "Failed to get fake-ip mappings: {e}"))
+}
+
+/// 切换 DNS 覆写预设（增强模式或加密上游），返回新的 DNS 覆写
+#[tauri::command]
+pub async fn apply_dns_preset(preset: DnsPreset) -> CmdResult<Mapping> {
+    feat::ensure_unlocked().await?;
+    feat::apply_dns_preset(preset)
+        .await
+        .stringify_err_log(|e| logging!(error, Type::Network, "Failed to apply DNS preset: {e}"))
+}
+
+#[tauri::command]
+pub async fn get_nameserver_policy() -> CmdResult<Mapping> {
+    feat::get_nameserver_policy().await.stringify_err()
+}
+
+/// 设置一条 nameserver-policy，`servers` 为空时删除
+#[tauri::command]
+pub async fn set_nameserver_policy(pattern: String, servers: Vec<String>) -> CmdResult<Mapping> {
+    feat::ensure_unlocked().await?;
+    feat::set_nameserver_policy(&pattern, &servers)
+        .await
+        .stringify_err_log(|e| logging!(error, Type::Network, "Failed to set nameserver-policy: {e}"))
+}
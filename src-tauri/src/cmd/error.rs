@@ -0,0 +1,178 @@
+//! 命令层的错误类型
+//!
+//! 序列化为 `{ kind, message, recoverable }`，前端据此决定提示方式以及是否提供重试。
+//! 字符串与 [`anyhow::Error`] 都可以通过 `?` 转换，后者会按错误链中的具体类型归类，`stringify_err` 同样如此。
+
+use crate::{core::mihomo_http::MihomoHttpError, feat::SettingsLocked};
+use serde::{Serialize, Serializer, ser::SerializeStruct as _};
+use smartstring::alias::String;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CmdError {
+    /// 参数或配置内容无效
+    InvalidInput(String),
+    /// 订阅、文件等目标不存在
+    NotFound(String),
+    /// 内核、服务或 Discord 等依赖暂不可用
+    Unavailable(String),
+    /// 网络请求失败
+    Network(String),
+    /// 操作超时
+    Timeout(String),
+    /// 设置已锁定，需先输入 PIN 解锁
+    Locked(String),
+    /// 其他错误
+    Internal(String),
+}
+
+impl CmdError {
+    pub const fn kind(&self) -> &'static str {
+        match self {
+            Self::InvalidInput(_) => "invalid_input",
+            Self::NotFound(_) => "not_found",
+            Self::Unavailable(_) => "unavailable",
+            Self::Network(_) => "network",
+            Self::Timeout(_) => "timeout",
+            Self::Locked(_) => "locked",
+            Self::Internal(_) => "internal",
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            Self::InvalidInput(message)
+            | Self::NotFound(message)
+            | Self::Unavailable(message)
+            | Self::Network(message)
+            | Self::Timeout(message)
+            | Self::Locked(message)
+            | Self::Internal(message) => message,
+        }
+    }
+
+    /// 稍后重试可能成功，前端可提供重试操作
+    pub const fn is_recoverable(&self) -> bool {
+        matches!(self, Self::Unavailable(_) | Self::Network(_) | Self::Timeout(_))
+    }
+}
+
+impl fmt::Display for CmdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+impl std::error::Error for CmdError {}
+
+impl Serialize for CmdError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("CmdError", 3)?;
+        state.serialize_field("kind", self.kind())?;
+        state.serialize_field("message", self.message())?;
+        state.serialize_field("recoverable", &self.is_recoverable())?;
+        state.end()
+    }
+}
+
+impl From<String> for CmdError {
+    fn from(message: String) -> Self {
+        Self::Internal(message)
+    }
+}
+
+impl From<std::string::String> for CmdError {
+    fn from(message: std::string::String) -> Self {
+        Self::Internal(message.into())
+    }
+}
+
+impl From<&str> for CmdError {
+    fn from(message: &str) -> Self {
+        Self::Internal(message.into())
+    }
+}
+
+impl From<MihomoHttpError> for CmdError {
+    fn from(err: MihomoHttpError) -> Self {
+        let message = err.to_string().into();
+        match err {
+            MihomoHttpError::Connect(_) | MihomoHttpError::Unauthorized => Self::Unavailable(message),
+            MihomoHttpError::Timeout => Self::Timeout(message),
+            MihomoHttpError::Status(404) => Self::NotFound(message),
+            MihomoHttpError::Status(status) if status >= 500 => Self::Unavailable(message),
+            MihomoHttpError::Status(_) | MihomoHttpError::Decode(_) => Self::Internal(message),
+        }
+    }
+}
+
+impl From<anyhow::Error> for CmdError {
+    fn from(err: anyhow::Error) -> Self {
+        // 带上完整的错误链，前端能看到根本原因
+        let message: String = format!("{err:#}").into();
+        for cause in err.chain() {
+            if let Some(err) = cause.downcast_ref::<Self>() {
+                return err.clone();
+            }
+            if cause.is::<SettingsLocked>() {
+                return Self::Locked(message);
+            }
+            if let Some(err) = cause.downcast_ref::<MihomoHttpError>() {
+                return match Self::from(err.clone()) {
+                    Self::Internal(_) => Self::Internal(message),
+                    classified => classified,
+                };
+            }
+            if let Some(err) = cause.downcast_ref::<reqwest::Error>() {
+                return if err.is_timeout() {
+                    Self::Timeout(message)
+                } else {
+                    Self::Network(message)
+                };
+            }
+            if cause.is::<tokio::time::error::Elapsed>() {
+                return Self::Timeout(message);
+            }
+            if let Some(err) = cause.downcast_ref::<std::io::Error>() {
+                return match err.kind() {
+                    std::io::ErrorKind::NotFound => Self::NotFound(message),
+                    std::io::ErrorKind::TimedOut => Self::Timeout(message),
+                    std::io::ErrorKind::ConnectionRefused | std::io::ErrorKind::ConnectionReset => {
+                        Self::Unavailable(message)
+                    }
+                    _ => Self::Internal(message),
+                };
+            }
+        }
+        Self::Internal(message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::StringifyErr as _;
+
+    #[test]
+    fn test_cmd_error() {
+        let err = CmdError::from(anyhow::Error::new(std::io::Error::from(std::io::ErrorKind::NotFound)));
+        assert_eq!(err.kind(), "not_found");
+        assert!(!err.is_recoverable());
+
+        let err = CmdError::from(anyhow::Error::new(MihomoHttpError::Timeout).context("fetch traffic"));
+        assert!(matches!(err, CmdError::Timeout(_)));
+        assert_eq!(err.message(), "fetch traffic: mihomo request timed out");
+
+        let value = serde_json::to_value(CmdError::Unavailable("core is not running".into())).unwrap_or_default();
+        assert_eq!(
+            value,
+            serde_json::json!({ "kind": "unavailable", "message": "core is not running", "recoverable": true })
+        );
+        assert_eq!(CmdError::from("oops"), CmdError::Internal("oops".into()));
+
+        let result: Result<(), _> = Err(std::io::Error::from(std::io::ErrorKind::TimedOut));
+        assert!(matches!(result.stringify_err(), Err(CmdError::Timeout(_))));
+        let result: Result<(), _> = Err(CmdError::Locked("locked".into()));
+        assert!(matches!(result.stringify_err(), Err(CmdError::Locked(_))));
+    }
+}
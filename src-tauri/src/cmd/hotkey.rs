@@ -0,0 +1,29 @@
+use super::{CmdResult, StringifyErr as _};
+use crate::{
+    config::IVerge,
+    core::hotkey::{self, Hotkey, HotkeyBinding},
+    feat,
+};
+use clash_verge_logging::{Type, logging};
+use smartstring::alias::String;
+
+/// 获取快捷键及其注册状态
+#[tauri::command]
+pub fn get_hotkeys() -> CmdResult<Vec<HotkeyBinding>> {
+    Ok(Hotkey::global().bindings())
+}
+
+/// 保存快捷键并重新注册，存在冲突时拒绝保存
+#[tauri::command]
+pub async fn set_hotkeys(hotkeys: Vec<String>) -> CmdResult<Vec<HotkeyBinding>> {
+    feat::ensure_unlocked().await?;
+    hotkey::check_conflicts(&hotkeys).stringify_err()?;
+    let patch = IVerge {
+        hotkeys: Some(hotkeys),
+        ..IVerge::default()
+    };
+    feat::patch_verge(&patch, false)
+        .await
+        .stringify_err_log(|e| logging!(error, Type::Hotkey, "Failed to apply hotkeys: {e}"))?;
+    Ok(Hotkey::global().bindings())
+}
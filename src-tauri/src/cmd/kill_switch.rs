@@ -0,0 +1,17 @@
+use super::{CmdResult, StringifyErr as _};
+use crate::core::kill_switch::{KillSwitch, KillSwitchStatus};
+use clash_verge_logging::{Type, logging};
+
+#[tauri::command]
+pub async fn get_kill_switch_status() -> CmdResult<KillSwitchStatus> {
+    Ok(KillSwitch::global().status().await)
+}
+
+/// 手动解除阻断，用于内核长时间无法恢复时找回网络
+#[tauri::command]
+pub async fn release_kill_switch() -> CmdResult {
+    KillSwitch::global()
+        .release()
+        .await
+        .stringify_err_log(|e| logging!(error, Type::Cmd, "Failed to release kill-switch: {e}"))
+}
@@ -0,0 +1,32 @@
+use super::{CmdResult, StringifyErr as _};
+use crate::core::lan_sync::{LanPeer, LanSync, SyncDirection};
+use clash_verge_logging::{Type, logging};
+use smartstring::alias::String;
+
+/// 发现局域网内开启了同步的设备
+#[tauri::command]
+pub async fn discover_peers() -> CmdResult<Vec<LanPeer>> {
+    LanSync::global().discover().await.stringify_err()
+}
+
+/// 生成配对 PIN，供对端设备输入
+#[tauri::command]
+pub async fn start_lan_pairing() -> CmdResult<String> {
+    LanSync::global().start_pairing().await.stringify_err()
+}
+
+#[tauri::command]
+pub async fn pair_with_peer(id: String, pin: String) -> CmdResult<LanPeer> {
+    LanSync::global()
+        .pair(&id, &pin)
+        .await
+        .stringify_err_log(|e| logging!(error, Type::Cmd, "Failed to pair with LAN device: {}", e))
+}
+
+#[tauri::command]
+pub async fn sync_with_peer(id: String, direction: SyncDirection) -> CmdResult<()> {
+    LanSync::global()
+        .sync(&id, direction)
+        .await
+        .stringify_err_log(|e| logging!(error, Type::Cmd, "LAN sync failed: {}", e))
+}
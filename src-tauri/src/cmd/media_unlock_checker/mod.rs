@@ -6,6 +6,8 @@ use tokio::{sync::Mutex, task::JoinSet};
 
 use clash_verge_logging::{Type, logging};
 
+use crate::cmd::{CmdError, CmdResult};
+
 mod bahamut;
 mod bilibili;
 mod chatgpt;
@@ -35,12 +37,12 @@ use tiktok::check_tiktok;
 use youtube::check_youtube_premium;
 
 #[command]
-pub async fn get_unlock_items() -> Result<Vec<UnlockItem>, String> {
+pub async fn get_unlock_items() -> CmdResult<Vec<UnlockItem>> {
     Ok(types::default_unlock_items())
 }
 
 #[command]
-pub async fn check_media_unlock() -> Result<Vec<UnlockItem>, String> {
+pub async fn check_media_unlock() -> CmdResult<Vec<UnlockItem>> {
     let client = match Client::builder()
         .use_rustls_tls()
         .user_agent("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/122.0.0.0 Safari/537.36")
@@ -51,7 +53,7 @@ pub async fn check_media_unlock() -> Result<Vec<UnlockItem>, String> {
         .connection_verbose(true)
         .build() {
         Ok(client) => client,
-        Err(e) => return Err(format!("创建HTTP客户端失败: {e}")),
+        Err(e) => return Err(CmdError::Network(format!("创建HTTP客户端失败: {e}").into())),
     };
 
     let results = Arc::new(Mutex::new(Vec::new()));
@@ -181,7 +183,7 @@ pub async fn check_media_unlock() -> Result<Vec<UnlockItem>, String> {
                 Type::Network,
                 "Failed to unwrap results Arc, references still exist"
             );
-            return Err("Failed to collect results".to_string());
+            return Err("Failed to collect results".into());
         }
     };
 
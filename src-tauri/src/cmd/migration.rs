@@ -0,0 +1,22 @@
+use super::{CmdResult, StringifyErr as _};
+use crate::feat;
+use clash_verge_logging::{Type, logging};
+use smartstring::alias::String;
+use std::path::PathBuf;
+
+/// 导出为上游 clash-verge-rev 可直接使用的数据目录
+#[tauri::command]
+pub async fn export_upstream_data(target: String) -> CmdResult {
+    feat::export_upstream_data(&PathBuf::from(target.as_str()))
+        .await
+        .stringify_err_log(|e| logging!(error, Type::Backup, "Failed to export upstream data: {e}"))
+}
+
+/// 从上游数据目录导入配置与订阅，导入前会自动创建本地备份
+#[tauri::command]
+pub async fn import_upstream_data(source: String) -> CmdResult {
+    feat::ensure_unlocked().await?;
+    feat::import_upstream_data(&PathBuf::from(source.as_str()))
+        .await
+        .stringify_err_log(|e| logging!(error, Type::Backup, "Failed to import upstream data: {e}"))
+}
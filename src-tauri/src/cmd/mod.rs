@@ -1,45 +1,85 @@
 use anyhow::Result;
 use smartstring::alias::String;
 
-pub type CmdResult<T = ()> = Result<T, String>;
+pub type CmdResult<T = ()> = Result<T, CmdError>;
 
 // Command modules
 pub mod app;
+pub mod audit;
+pub mod autostart;
 pub mod backup;
 pub mod clash;
+pub mod control_api;
+pub mod core_log;
+pub mod cores;
 pub mod discord;
+pub mod dns;
+pub mod error;
+pub mod hotkey;
+pub mod kill_switch;
+pub mod lan_sync;
 pub mod lightweight;
 pub mod media_unlock_checker;
+pub mod migration;
 pub mod network;
+pub mod plugin;
 pub mod profile;
 pub mod proxy;
+pub mod rules;
 pub mod runtime;
 pub mod save_profile;
+pub mod schedule;
 pub mod service;
+pub mod settings_lock;
+pub mod setup;
+pub mod speedtest;
+pub mod stats;
 pub mod system;
+pub mod updater;
 pub mod uwp;
 pub mod validate;
 pub mod verge;
 pub mod webdav;
+pub mod webhook;
 
 // Re-export all command functions for backwards compatibility
 pub use app::*;
+pub use audit::*;
+pub use autostart::*;
 pub use backup::*;
 pub use clash::*;
+pub use control_api::*;
+pub use core_log::*;
+pub use cores::*;
 pub use discord::*;
+pub use dns::*;
+pub use error::*;
+pub use hotkey::*;
+pub use kill_switch::*;
+pub use lan_sync::*;
 pub use lightweight::*;
 pub use media_unlock_checker::*;
+pub use migration::*;
 pub use network::*;
+pub use plugin::*;
 pub use profile::*;
 pub use proxy::*;
+pub use rules::*;
 pub use runtime::*;
 pub use save_profile::*;
+pub use schedule::*;
 pub use service::*;
+pub use settings_lock::*;
+pub use setup::*;
+pub use speedtest::*;
+pub use stats::*;
 pub use system::*;
+pub use updater::*;
 pub use uwp::*;
 pub use validate::*;
 pub use verge::*;
 pub use webdav::*;
+pub use webhook::*;
 
 pub trait StringifyErr<T> {
     fn stringify_err(self) -> CmdResult<T>;
@@ -48,9 +88,9 @@ pub trait StringifyErr<T> {
         F: Fn(&str);
 }
 
-impl<T, E: std::fmt::Display> StringifyErr<T> for Result<T, E> {
+impl<T, E: Into<anyhow::Error>> StringifyErr<T> for Result<T, E> {
     fn stringify_err(self) -> CmdResult<T> {
-        self.map_err(|e| e.to_string().into())
+        self.map_err(|e| CmdError::from(e.into()))
     }
 
     fn stringify_err_log<F>(self, log_fn: F) -> CmdResult<T>
@@ -58,9 +98,9 @@ impl<T, E: std::fmt::Display> StringifyErr<T> for Result<T, E> {
         F: Fn(&str),
     {
         self.map_err(|e| {
-            let msg = String::from(e.to_string());
-            log_fn(&msg);
-            msg
+            let err = CmdError::from(e.into());
+            log_fn(err.message());
+            err
         })
     }
 }
@@ -1,9 +1,19 @@
 use super::CmdResult;
-use crate::cmd::StringifyErr as _;
+use crate::{
+    cmd::StringifyErr as _,
+    config::{Config, IVerge},
+    core::{
+        bypass::{self, ProxyBypass},
+        network_monitor::{NetworkMonitor, NetworkState},
+        proxy_scope,
+    },
+    feat,
+};
 use clash_verge_logging::{Type, logging};
 use gethostname::gethostname;
 use network_interface::NetworkInterface;
 use serde_yaml_ng::Mapping;
+use smartstring::alias::String as SmartString;
 use sysproxy::{Autoproxy, Sysproxy};
 use tauri_plugin_clash_verge_sysinfo;
 
@@ -95,3 +105,168 @@ pub fn get_network_interfaces_info() -> CmdResult<Vec<NetworkInterface>> {
 
     Ok(result)
 }
+
+/// 开启 TUN 前的预检：权限、冲突的 VPN 网卡、默认路由与防火墙
+#[tauri::command]
+pub async fn check_tun_readiness() -> CmdResult<feat::TunReadiness> {
+    Ok(feat::check_tun_readiness().await)
+}
+
+/// 可单独应用系统代理的网络服务（macOS）或连接（Windows）
+#[tauri::command]
+pub fn get_sysproxy_interfaces() -> CmdResult<Vec<SmartString>> {
+    proxy_scope::list_proxy_interfaces().stringify_err()
+}
+
+/// 设置应用系统代理的网络服务 / 连接，传入空列表恢复为全部
+#[tauri::command]
+pub async fn set_sysproxy_interfaces(list: Vec<SmartString>) -> CmdResult {
+    feat::ensure_unlocked().await?;
+    let patch = IVerge {
+        sysproxy_interfaces: Some(list),
+        ..IVerge::default()
+    };
+    feat::patch_verge(&patch, false)
+        .await
+        .stringify_err_log(|e| logging!(error, Type::Network, "Failed to apply sysproxy interfaces: {e}"))
+}
+
+/// 开启或关闭 PAC 模式，PAC 文件由内置服务器提供
+#[tauri::command]
+pub async fn enable_pac_mode(enable: bool) -> CmdResult {
+    feat::ensure_unlocked().await?;
+    feat::enable_pac_mode(enable)
+        .await
+        .stringify_err_log(|e| logging!(error, Type::Network, "Failed to switch PAC mode: {e}"))
+}
+
+/// 保存 PAC 模板，支持 `%mixed-port%` 与 `%proxy_host%` 占位符
+#[tauri::command]
+pub async fn set_pac_template(template: SmartString) -> CmdResult {
+    feat::set_pac_template(template)
+        .await
+        .stringify_err_log(|e| logging!(error, Type::Network, "Failed to save PAC template: {e}"))
+}
+
+/// 系统代理的绕过规则
+#[tauri::command]
+pub async fn get_proxy_bypass() -> CmdResult<ProxyBypass> {
+    let verge = Config::verge().await.latest_arc();
+    Ok(ProxyBypass::new(
+        verge.system_proxy_bypass.as_deref().unwrap_or_default(),
+        verge.use_default_bypass.unwrap_or(true),
+    ))
+}
+
+/// 校验并保存绕过规则，`use_default` 为空时保持原设置
+#[tauri::command]
+pub async fn set_proxy_bypass(rules: Vec<SmartString>, use_default: Option<bool>) -> CmdResult<ProxyBypass> {
+    for rule in &rules {
+        bypass::validate_rule(rule).stringify_err()?;
+    }
+    // 以逗号保存规则原文，应用时再按平台转换
+    let raw = rules.iter().map(SmartString::as_str).collect::<Vec<_>>().join(",");
+    let patch = IVerge {
+        system_proxy_bypass: Some(raw.into()),
+        use_default_bypass: use_default,
+        ..IVerge::default()
+    };
+    feat::patch_verge(&patch, false)
+        .await
+        .stringify_err_log(|e| logging!(error, Type::Network, "Failed to save proxy bypass: {e}"))?;
+    get_proxy_bypass().await
+}
+
+/// 局域网共享状态与扫码内容
+#[tauri::command]
+pub async fn get_lan_share_info() -> CmdResult<feat::LanShareInfo> {
+    feat::get_lan_share_info().await.stringify_err()
+}
+
+/// 开启或关闭局域网共享，`interface` 为空时自动选择网卡
+#[tauri::command]
+pub async fn set_lan_sharing(enable: bool, interface: Option<String>, auth: bool) -> CmdResult<feat::LanShareInfo> {
+    feat::ensure_unlocked().await?;
+    feat::set_lan_sharing(enable, interface.as_deref(), auth)
+        .await
+        .stringify_err_log(|e| logging!(error, Type::Network, "Failed to switch LAN sharing: {e}"))
+}
+
+/// 通过局域网连接的设备
+#[tauri::command]
+pub async fn list_lan_clients() -> CmdResult<Vec<feat::LanClient>> {
+    feat::list_lan_clients().await.stringify_err()
+}
+
+/// 检查端口是否被其他程序占用，`ports` 为空时检查当前配置的端口
+#[tauri::command]
+pub async fn check_port_conflicts(ports: Option<Vec<feat::PortUsage>>) -> CmdResult<Vec<feat::PortConflict>> {
+    let ports = match ports {
+        Some(ports) => ports,
+        None => feat::configured_ports().await,
+    };
+    Ok(feat::check_port_conflicts(&ports).await)
+}
+
+/// 为被占用的端口重新分配空闲端口
+#[tauri::command]
+pub async fn auto_reassign_ports() -> CmdResult<Vec<feat::PortReassignment>> {
+    feat::ensure_unlocked().await?;
+    feat::auto_reassign_ports()
+        .await
+        .stringify_err_log(|e| logging!(error, Type::Network, "Failed to reassign ports: {e}"))
+}
+
+/// 检查端口是否意外暴露到局域网，`fix` 为真时改回只监听回环地址
+#[tauri::command]
+pub async fn audit_exposure(fix: Option<bool>) -> CmdResult<feat::ExposureReport> {
+    let fix = fix.unwrap_or(false);
+    if fix {
+        feat::ensure_unlocked().await?;
+    }
+    feat::audit_exposure(fix)
+        .await
+        .stringify_err_log(|e| logging!(error, Type::Network, "Failed to audit exposure: {e}"))
+}
+
+/// 分阶段诊断网络连通性
+#[tauri::command]
+pub async fn run_network_doctor() -> CmdResult<feat::DoctorReport> {
+    Ok(feat::run_network_doctor().await)
+}
+
+/// 经代理访问时的出口 IP、国家与 ASN
+#[tauri::command]
+pub async fn get_exit_ip_info(refresh: Option<bool>) -> CmdResult<feat::ExitIpSnapshot> {
+    feat::get_exit_ip_info(refresh.unwrap_or(false)).await.stringify_err()
+}
+
+/// 内核内存占用，sidecar 模式下包含进程 RSS
+#[tauri::command]
+pub async fn get_core_memory() -> CmdResult<feat::CoreMemory> {
+    feat::get_core_memory().await.stringify_err()
+}
+
+/// 当前网络环境，`refresh` 为真时立即重新检测
+#[tauri::command]
+pub async fn get_network_state(refresh: Option<bool>) -> CmdResult<Option<NetworkState>> {
+    let monitor = NetworkMonitor::global();
+    if refresh.unwrap_or(false) {
+        return Ok(monitor.check().await);
+    }
+    Ok(monitor.current())
+}
+
+/// 连续建立 TCP 连接测量延迟，每次结果通过 `tcp-ping-result` 事件推送
+#[tauri::command]
+pub async fn tcp_ping(host: SmartString, port: Option<u16>, count: Option<u32>) -> CmdResult<feat::PingReport> {
+    feat::tcp_ping(&host, port, count).await.map_err(Into::into)
+}
+
+/// 分段测量到目标的路径（DNS、入口节点、经节点访问），每段结果通过 `trace-route-hop` 事件推送
+#[tauri::command]
+pub async fn trace_route(host: SmartString, via_proxy: Option<bool>) -> CmdResult<Vec<feat::TraceHop>> {
+    feat::trace_route(&host, via_proxy.unwrap_or(true))
+        .await
+        .map_err(Into::into)
+}
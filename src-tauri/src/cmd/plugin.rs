@@ -0,0 +1,44 @@
+use super::{CmdResult, StringifyErr as _};
+use crate::core::plugin::{PluginHealth, PluginInfo, PluginManager, PluginPermission};
+use clash_verge_logging::{Type, logging};
+use smartstring::alias::String;
+use std::path::PathBuf;
+
+/// 从本地目录安装插件，权限需由用户确认后才会启用
+#[tauri::command]
+pub async fn install_plugin(path: String) -> CmdResult<PluginInfo> {
+    PluginManager::global()
+        .install(&PathBuf::from(path.as_str()))
+        .await
+        .stringify_err_log(|e| logging!(error, Type::Cmd, "Failed to install plugin: {e}"))
+}
+
+#[tauri::command]
+pub fn list_plugins() -> CmdResult<Vec<PluginInfo>> {
+    Ok(PluginManager::global().list())
+}
+
+/// 插件运行状况，被看门狗挂起的插件需要重新启用后才会恢复
+#[tauri::command]
+pub fn get_plugin_health() -> CmdResult<Vec<PluginHealth>> {
+    Ok(PluginManager::global().health())
+}
+
+/// 权限提示的答复，未勾选的权限视为拒绝
+#[tauri::command]
+pub async fn grant_plugin_permissions(id: String, permissions: Vec<PluginPermission>) -> CmdResult {
+    PluginManager::global()
+        .grant_permissions(&id, permissions)
+        .await
+        .stringify_err()
+}
+
+#[tauri::command]
+pub async fn set_plugin_enabled(id: String, enabled: bool) -> CmdResult {
+    PluginManager::global().set_enabled(&id, enabled).await.stringify_err()
+}
+
+#[tauri::command]
+pub async fn uninstall_plugin(id: String) -> CmdResult {
+    PluginManager::global().uninstall(&id).await.stringify_err()
+}
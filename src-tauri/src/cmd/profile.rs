@@ -1,15 +1,22 @@
-use super::CmdResult;
 use super::StringifyErr as _;
+use super::{CmdError, CmdResult};
 use crate::{
     config::{
-        Config, IProfiles, PrfItem, PrfOption,
+        Config, IProfiles, PrfItem, PrfLayer, PrfOption,
         profiles::{
             profiles_append_item_with_filedata_safe, profiles_delete_item_safe, profiles_patch_item_safe,
             profiles_reorder_safe, profiles_save_file_safe,
         },
         profiles_append_item_safe,
+        versions::{self, ProfileVersion},
+    },
+    core::{
+        CoreManager,
+        audit::AuditLog,
+        handle, sysopt,
+        timer::{ProfileSchedule, Timer},
+        tray::Tray,
     },
-    core::{CoreManager, handle, timer::Timer, tray::Tray},
     feat,
     module::auto_backup::{AutoBackupManager, AutoBackupTrigger},
     process::AsyncHandler,
@@ -17,7 +24,7 @@ use crate::{
     utils::{dirs, help},
 };
 use clash_verge_draft::SharedBox;
-use clash_verge_logging::{Type, logging};
+use clash_verge_logging::{Type, logging, logging_error};
 use scopeguard::defer;
 use smartstring::alias::String;
 use std::sync::atomic::{AtomicBool, Ordering};
@@ -53,7 +60,7 @@ pub async fn enhance_profiles() -> CmdResult {
                 "Reactivate profiles command failed validation: {}",
                 message.as_str()
             );
-            Err(message)
+            Err(CmdError::InvalidInput(message))
         }
         Err(e) => {
             logging!(error, Type::Cmd, "{}", e);
@@ -65,6 +72,7 @@ pub async fn enhance_profiles() -> CmdResult {
 /// 导入配置文件
 #[tauri::command]
 pub async fn import_profile(url: std::string::String, option: Option<PrfOption>) -> CmdResult {
+    feat::ensure_unlocked().await?;
     logging!(info, Type::Cmd, "[导入订阅] 开始导入: {}", url);
 
     // 直接依赖 PrfItem::from_url 自身的超时/重试逻辑，不再使用 tokio::time::timeout 包裹
@@ -134,6 +142,7 @@ pub async fn reorder_profile(active_id: String, over_id: String) -> CmdResult {
 /// 创建一个新的配置文件
 #[tauri::command]
 pub async fn create_profile(item: PrfItem, file_data: Option<String>) -> CmdResult {
+    feat::ensure_unlocked().await?;
     match profiles_append_item_with_filedata_safe(&item, file_data).await {
         Ok(_) => {
             // 发送配置变更通知
@@ -174,6 +183,7 @@ pub async fn update_profile(index: String, option: Option<PrfOption>) -> CmdResu
 /// 删除配置文件
 #[tauri::command]
 pub async fn delete_profile(index: String) -> CmdResult {
+    feat::ensure_unlocked().await?;
     // 使用Send-safe helper函数
     let should_update = profiles_delete_item_safe(&index).await.stringify_err()?;
     profiles_save_file_safe().await.stringify_err()?;
@@ -299,14 +309,6 @@ async fn handle_success(current_value: Option<&String>) -> CmdResult<bool> {
     Config::profiles().await.apply();
     handle::Handle::refresh_clash();
 
-    if let Err(e) = Tray::global().update_tooltip().await {
-        logging!(warn, Type::Cmd, "Warning: 异步更新托盘提示失败: {e}");
-    }
-
-    if let Err(e) = Tray::global().update_menu().await {
-        logging!(warn, Type::Cmd, "Warning: 异步更新托盘菜单失败: {e}");
-    }
-
     if let Err(e) = profiles_save_file_safe().await {
         logging!(warn, Type::Cmd, "Warning: 异步保存配置文件失败: {e}");
     }
@@ -314,6 +316,9 @@ async fn handle_success(current_value: Option<&String>) -> CmdResult<bool> {
     if let Some(current) = current_value {
         logging!(info, Type::Cmd, "向前端发送配置变更事件: {}", current);
         handle::Handle::notify_profile_changed(current.to_owned());
+        EventBus::global().publish(StateEvent::ProfileSwitched {
+            uid: current.to_owned(),
+        });
     }
 
     Ok(true)
@@ -347,6 +352,26 @@ async fn handle_timeout(current_profile: Option<&String>) -> CmdResult<bool> {
     Ok(false)
 }
 
+/// 检查目标订阅覆盖的运行参数与节点过滤，混合端口不能与其他端口重复或被其他程序占用
+async fn validate_profile_runtime(uid: &String) -> anyhow::Result<()> {
+    let (runtime, nodes) = {
+        let profiles = Config::profiles().await.latest_arc();
+        let option = profiles.get_item(uid)?.option.as_ref();
+        (
+            option.and_then(|option| option.runtime.clone()),
+            option.and_then(|option| option.nodes.clone()),
+        )
+    };
+    if let Some(nodes) = nodes {
+        nodes.validate()?;
+    }
+    if let Some(runtime) = runtime {
+        runtime.validate()?;
+        feat::check_profile_ports(&runtime, true).await?;
+    }
+    Ok(())
+}
+
 async fn perform_config_update(current_value: Option<&String>, current_profile: Option<&String>) -> CmdResult<bool> {
     defer! {
         CURRENT_SWITCHING_PROFILE.store(false, Ordering::Release);
@@ -364,6 +389,7 @@ async fn perform_config_update(current_value: Option<&String>, current_profile:
 /// 修改profiles的配置
 #[tauri::command]
 pub async fn patch_profiles_config(profiles: IProfiles) -> CmdResult<bool> {
+    feat::ensure_unlocked().await?;
     if CURRENT_SWITCHING_PROFILE
         .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
         .is_err()
@@ -388,9 +414,32 @@ pub async fn patch_profiles_config(profiles: IProfiles) -> CmdResult<bool> {
         CURRENT_SWITCHING_PROFILE.store(false, Ordering::Release);
         return Ok(false);
     }
+    if let Some(switch_to_profile) = target_profile
+        && previous_profile.as_ref() != Some(switch_to_profile)
+        && let Err(err) = validate_profile_runtime(switch_to_profile).await
+    {
+        logging!(warn, Type::Cmd, "目标配置的运行参数无效: {err}");
+        handle::Handle::notice_message("config_validate::error", err.to_string());
+        CURRENT_SWITCHING_PROFILE.store(false, Ordering::Release);
+        return Ok(false);
+    }
+    let previous_port = Config::mixed_port().await;
     Config::profiles().await.edit_draft(|d| d.patch_config(&profiles));
 
-    perform_config_update(target_profile, previous_profile.as_ref()).await
+    let switched = perform_config_update(target_profile, previous_profile.as_ref()).await?;
+    if switched && target_profile.is_some() && target_profile != previous_profile.as_ref() {
+        AuditLog::record(
+            "profile.switch",
+            None,
+            previous_profile.as_deref().map(Into::into),
+            target_profile.map(|uid| uid.as_str().into()),
+        );
+    }
+    // 订阅覆盖了混合端口时，系统代理需要指向新端口
+    if switched && Config::mixed_port().await != previous_port {
+        logging_error!(Type::Cmd, sysopt::Sysopt::global().update_sysproxy().await);
+    }
+    Ok(switched)
 }
 
 /// 根据profile name修改profiles
@@ -408,6 +457,7 @@ pub async fn patch_profiles_config_by_profile_index(profile_index: String) -> Cm
 /// 修改某个profile item的
 #[tauri::command]
 pub async fn patch_profile(index: String, profile: PrfItem) -> CmdResult {
+    feat::ensure_unlocked().await?;
     // 保存修改前检查是否有更新 update_interval
     let profiles = Config::profiles().await;
     let should_refresh_timer = if let Ok(old_profile) = profiles.latest_arc().get_item(&index)
@@ -422,6 +472,15 @@ pub async fn patch_profile(index: String, profile: PrfItem) -> CmdResult {
         false
     };
 
+    if let Some(runtime) = profile.option.as_ref().and_then(|option| option.runtime.as_ref()) {
+        runtime
+            .validate()
+            .map_err(|err| CmdError::InvalidInput(err.to_string().into()))?;
+        feat::check_profile_ports(runtime, false)
+            .await
+            .map_err(|err| CmdError::InvalidInput(err.to_string().into()))?;
+    }
+
     profiles_patch_item_safe(&index, &profile).await.stringify_err()?;
 
     // 如果更新间隔或允许自动更新变更，异步刷新定时器
@@ -483,3 +542,89 @@ pub async fn get_next_update_time(uid: String) -> CmdResult<Option<i64>> {
     let next_time = timer.get_next_update_time(&uid).await;
     Ok(next_time)
 }
+
+/// 获取所有自动更新订阅的上次与下次更新时间及失败信息
+#[tauri::command]
+pub async fn get_profile_schedule() -> CmdResult<Vec<ProfileSchedule>> {
+    Ok(Timer::global().get_schedule().await)
+}
+
+/// 获取订阅的已用流量、剩余流量与到期时间
+#[tauri::command]
+pub async fn get_profile_quota(uid: String) -> CmdResult<Option<feat::ProfileQuota>> {
+    feat::get_profile_quota(&uid).await.stringify_err()
+}
+
+/// 从剪贴板导入订阅链接、节点分享链接或 Clash 配置
+#[tauri::command]
+pub async fn import_profile_from_clipboard() -> CmdResult<feat::ImportResult> {
+    feat::ensure_unlocked().await?;
+    feat::import_profile_from_clipboard()
+        .await
+        .stringify_err_log(|e| logging!(error, Type::Cmd, "[剪贴板导入] 失败: {}", e))
+}
+
+/// 从二维码图片导入订阅链接或节点分享链接
+#[tauri::command]
+pub async fn import_profile_from_qr(image_bytes: Vec<u8>) -> CmdResult<feat::ImportResult> {
+    feat::ensure_unlocked().await?;
+    feat::import_profile_from_qr(image_bytes)
+        .await
+        .stringify_err_log(|e| logging!(error, Type::Cmd, "[二维码导入] 失败: {}", e))
+}
+
+/// 列出配置文件的历史版本，最新的在前
+#[tauri::command]
+pub async fn list_profile_versions(uid: String) -> CmdResult<Vec<ProfileVersion>> {
+    versions::list_profile_versions(&uid).await.stringify_err()
+}
+
+/// 将配置文件回滚到指定的历史版本
+#[tauri::command]
+pub async fn rollback_profile(uid: String, version: String) -> CmdResult {
+    feat::ensure_unlocked().await?;
+    feat::rollback_profile(&uid, &version)
+        .await
+        .stringify_err_log(|e| logging!(error, Type::Cmd, "[配置回滚] 失败: {}", e))
+}
+
+/// 手动检查订阅的健康状态，不可用时恢复到上一个版本
+#[tauri::command]
+pub async fn check_profile_health(uid: String) -> CmdResult<feat::ProfileHealth> {
+    feat::check_profile_health(&uid)
+        .await
+        .stringify_err_log(|e| logging!(error, Type::Cmd, "[订阅检查] 失败: {}", e))
+}
+
+/// 用临时内核试运行订阅，检查连通性与节点延迟，不影响当前使用的订阅
+#[tauri::command]
+pub async fn test_profile(uid: String) -> CmdResult<feat::ProfileTestReport> {
+    feat::test_profile(&uid)
+        .await
+        .stringify_err_log(|e| logging!(error, Type::Cmd, "[订阅试运行] 失败: {}", e))
+}
+
+/// 获取订阅的扩展链，未显式设置时返回默认顺序
+#[tauri::command]
+pub async fn get_profile_chain(uid: String) -> CmdResult<Vec<PrfLayer>> {
+    let profiles = Config::profiles().await.latest_arc();
+    Ok(profiles.get_item(&uid).stringify_err()?.current_chain())
+}
+
+/// 设置订阅的扩展链（顺序与启用状态），传入 `None` 恢复默认顺序
+#[tauri::command]
+pub async fn set_profile_chain(uid: String, layers: Option<Vec<PrfLayer>>) -> CmdResult {
+    feat::ensure_unlocked().await?;
+    feat::set_profile_chain(&uid, layers)
+        .await
+        .stringify_err_log(|e| logging!(error, Type::Cmd, "[扩展链] 设置失败: {}", e))
+}
+
+/// 启用或停用扩展链中的某一层
+#[tauri::command]
+pub async fn toggle_profile_layer(uid: String, layer_uid: String, enabled: bool) -> CmdResult {
+    feat::ensure_unlocked().await?;
+    feat::toggle_profile_layer(&uid, &layer_uid, enabled)
+        .await
+        .stringify_err_log(|e| logging!(error, Type::Cmd, "[扩展链] 切换失败: {}", e))
+}
@@ -1,17 +1,34 @@
-use super::CmdResult;
+use super::{CmdResult, StringifyErr as _};
+use crate::{
+    config::Config,
+    core::{
+        event_bus::{EventBus, StateEvent},
+        selection::SelectionMemory,
+        sharelink,
+    },
+    enhance::rules::ProxyChain,
+    feat,
+};
 use clash_verge_logging::{Type, logging};
+use serde_yaml_ng::{Mapping, Value};
+use smartstring::alias::String;
 
-// TODO: 前端通过 emit 发送更新事件, tray 监听更新事件
 /// 同步托盘和GUI的代理选择状态
+///
+/// `group` / `node` 为本次切换的节点，传入时广播节点切换事件，由托盘、Discord、回调与插件各自更新
 #[tauri::command]
-pub async fn sync_tray_proxy_selection() -> CmdResult<()> {
+pub async fn sync_tray_proxy_selection(group: Option<String>, node: Option<String>) -> CmdResult<()> {
     use crate::core::tray::Tray;
 
+    if let (Some(group), Some(node)) = (group, node) {
+        SelectionMemory::global().record(&group, &node).await;
+        EventBus::global().publish(StateEvent::NodeSwitched { group, node });
+        return Ok(());
+    }
+
     match Tray::global().update_menu().await {
         Ok(_) => {
             logging!(info, Type::Cmd, "Tray proxy selection synced successfully");
-            // Update Discord activity when proxy selection changes
-            crate::cmd::discord::update_discord_activity().await;
             Ok(())
         }
         Err(e) => {
@@ -20,3 +37,76 @@ pub async fn sync_tray_proxy_selection() -> CmdResult<()> {
         }
     }
 }
+
+/// 解析粘贴的节点分享链接（可为整段 base64），返回 mihomo proxy 列表
+#[tauri::command]
+pub fn parse_share_links(text: String) -> CmdResult<Vec<Mapping>> {
+    Ok(sharelink::parse_share_links(&text))
+}
+
+/// 将当前运行配置中的节点导出为分享链接
+#[tauri::command]
+pub async fn export_node_share_link(name: String) -> CmdResult<String> {
+    let runtime = Config::runtime().await.latest_arc();
+    let proxy = runtime
+        .config
+        .as_ref()
+        .and_then(|config| config.get("proxies"))
+        .and_then(Value::as_sequence)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_mapping)
+        .find(|proxy| proxy.get("name").and_then(Value::as_str) == Some(name.as_str()))
+        .ok_or_else(|| String::from(format!("proxy not found: {name}")))?;
+    sharelink::to_share_link(proxy).map(String::from).stringify_err()
+}
+
+/// 获取代理链列表
+#[tauri::command]
+pub async fn list_proxy_chains() -> CmdResult<Vec<ProxyChain>> {
+    feat::list_proxy_chains().await.stringify_err()
+}
+
+/// 用已有节点创建代理链，`nodes` 按流量经过的顺序排列，返回修改后的列表
+#[tauri::command]
+pub async fn create_proxy_chain(name: String, nodes: Vec<String>) -> CmdResult<Vec<ProxyChain>> {
+    feat::ensure_unlocked().await?;
+    feat::create_proxy_chain(&name, nodes)
+        .await
+        .stringify_err_log(|e| logging!(error, Type::Config, "Failed to create proxy chain: {e}"))
+}
+
+/// 删除代理链
+#[tauri::command]
+pub async fn delete_proxy_chain(name: String) -> CmdResult<Vec<ProxyChain>> {
+    feat::ensure_unlocked().await?;
+    feat::delete_proxy_chain(&name).await.stringify_err()
+}
+
+/// 获取收藏的代理组与节点
+#[tauri::command]
+pub async fn get_favorites() -> CmdResult<Vec<feat::Favorite>> {
+    feat::get_favorites().await.stringify_err()
+}
+
+/// 收藏或取消收藏代理组/节点，返回操作后是否处于收藏状态
+#[tauri::command]
+pub async fn toggle_favorite(name: String) -> CmdResult<bool> {
+    feat::toggle_favorite(&name)
+        .await
+        .stringify_err_log(|e| logging!(error, Type::Config, "Failed to toggle favorite: {e}"))
+}
+
+/// 在代理组中切换到指定国家/地区延迟最低的节点，`country_code` 为 ISO 3166 代码
+#[tauri::command]
+pub async fn select_node_by_region(group: String, country_code: String) -> CmdResult<feat::RegionSelection> {
+    feat::select_node_by_region(&group, &country_code)
+        .await
+        .stringify_err_log(|e| logging!(warn, Type::Cmd, "Failed to select node by region: {e}"))
+}
+
+/// 代理组实际使用的出站，负载均衡与自动测速组按活动连接统计
+#[tauri::command]
+pub async fn get_effective_outbounds(group: String) -> CmdResult<Vec<feat::EffectiveOutbound>> {
+    feat::get_effective_outbounds(&group).await.stringify_err()
+}
@@ -0,0 +1,114 @@
+use super::{CmdResult, StringifyErr as _};
+use crate::{
+    enhance::rules::AppRoute,
+    feat::{self, InstalledApp, RuleBehavior, RuleMatch, RuleProviderInfo},
+};
+use clash_verge_logging::{Type, logging};
+use smartstring::alias::String;
+
+/// 获取管理的规则
+#[tauri::command]
+pub async fn list_rules() -> CmdResult<Vec<String>> {
+    feat::list_rules().await.stringify_err()
+}
+
+/// 插入一条规则，`position` 为空时追加到末尾，返回修改后的规则
+#[tauri::command]
+pub async fn insert_rule(rule: String, position: Option<usize>) -> CmdResult<Vec<String>> {
+    feat::ensure_unlocked().await?;
+    feat::insert_rule(&rule, position)
+        .await
+        .stringify_err_log(|e| logging!(error, Type::Cmd, "[规则管理] 插入规则失败: {}", e))
+}
+
+#[tauri::command]
+pub async fn delete_rule(index: usize) -> CmdResult<Vec<String>> {
+    feat::ensure_unlocked().await?;
+    feat::delete_rule(index)
+        .await
+        .stringify_err_log(|e| logging!(error, Type::Cmd, "[规则管理] 删除规则失败: {}", e))
+}
+
+#[tauri::command]
+pub async fn move_rule(from: usize, to: usize) -> CmdResult<Vec<String>> {
+    feat::ensure_unlocked().await?;
+    feat::move_rule(from, to)
+        .await
+        .stringify_err_log(|e| logging!(error, Type::Cmd, "[规则管理] 移动规则失败: {}", e))
+}
+
+/// 根据活动连接生成规则并关闭该连接，`by` 为空时按域名或目标 IP 匹配
+#[tauri::command]
+pub async fn create_rule_from_connection(conn_id: String, target: String, by: Option<RuleMatch>) -> CmdResult<String> {
+    feat::ensure_unlocked().await?;
+    feat::create_rule_from_connection(&conn_id, &target, by)
+        .await
+        .stringify_err_log(|e| logging!(error, Type::Cmd, "[规则管理] 从连接创建规则失败: {}", e))
+}
+
+/// 列出规则集及其条目数与更新时间
+#[tauri::command]
+pub async fn list_rule_providers() -> CmdResult<Vec<RuleProviderInfo>> {
+    feat::list_rule_providers()
+        .await
+        .stringify_err_log(|e| logging!(error, Type::Cmd, "[规则集] 获取失败: {}", e))
+}
+
+#[tauri::command]
+pub async fn refresh_rule_provider(name: String) -> CmdResult {
+    feat::refresh_rule_provider(&name)
+        .await
+        .stringify_err_log(|e| logging!(error, Type::Cmd, "[规则集] 刷新失败: {}", e))
+}
+
+#[tauri::command]
+pub async fn add_rule_provider(name: String, url: String, behavior: RuleBehavior) -> CmdResult {
+    feat::ensure_unlocked().await?;
+    feat::add_rule_provider(&name, &url, behavior)
+        .await
+        .stringify_err_log(|e| logging!(error, Type::Cmd, "[规则集] 添加失败: {}", e))
+}
+
+#[tauri::command]
+pub async fn remove_rule_provider(name: String) -> CmdResult {
+    feat::ensure_unlocked().await?;
+    feat::remove_rule_provider(&name)
+        .await
+        .stringify_err_log(|e| logging!(error, Type::Cmd, "[规则集] 删除失败: {}", e))
+}
+
+/// 列出已安装的应用，用于选择按应用分流的目标
+#[tauri::command]
+pub async fn list_installed_apps() -> CmdResult<Vec<InstalledApp>> {
+    feat::list_installed_apps()
+        .await
+        .stringify_err_log(|e| logging!(error, Type::Cmd, "[应用分流] 获取应用列表失败: {}", e))
+}
+
+#[tauri::command]
+pub async fn get_app_routing() -> CmdResult<Vec<AppRoute>> {
+    feat::get_app_routing().await.stringify_err()
+}
+
+/// 替换按应用分流的设置，返回保存后的设置
+#[tauri::command]
+pub async fn set_app_routing(rules: Vec<AppRoute>) -> CmdResult<Vec<AppRoute>> {
+    feat::ensure_unlocked().await?;
+    feat::set_app_routing(rules)
+        .await
+        .stringify_err_log(|e| logging!(error, Type::Cmd, "[应用分流] 保存失败: {}", e))
+}
+
+/// 代理集合与规则集的状态汇总
+#[tauri::command]
+pub async fn get_providers_status() -> CmdResult<Vec<feat::ProviderStatus>> {
+    feat::get_providers_status().await.stringify_err()
+}
+
+/// 更新全部远程代理集合与规则集，进度通过 `providers-update-progress` 事件推送
+#[tauri::command]
+pub async fn update_all_providers() -> CmdResult<Vec<feat::ProviderStatus>> {
+    feat::update_all_providers()
+        .await
+        .stringify_err_log(|e| logging!(error, Type::Cmd, "[集合更新] 更新失败: {}", e))
+}
@@ -1,5 +1,10 @@
 use super::CmdResult;
-use crate::{cmd::StringifyErr as _, config::Config, core::CoreManager};
+use crate::{
+    cmd::StringifyErr as _,
+    config::Config,
+    core::CoreManager,
+    enhance::{self, diff::ConfigChange},
+};
 use anyhow::{Context as _, anyhow};
 use clash_verge_logging::{Type, logging_error};
 use serde_yaml_ng::Mapping;
@@ -29,6 +34,18 @@ pub async fn get_runtime_yaml() -> CmdResult<String> {
         .stringify_err()
 }
 
+/// 预览按当前订阅与扩展配置重新生成后，运行配置将发生的变化，不会应用任何修改；
+/// 传入 `uid` 时预览切换到该订阅后的变化
+#[tauri::command]
+pub async fn preview_config_diff(uid: Option<String>) -> CmdResult<Vec<ConfigChange>> {
+    if let Some(uid) = uid.as_ref() {
+        Config::profiles().await.latest_arc().get_item(uid).stringify_err()?;
+    }
+    let applied = Config::runtime().await.data_arc().config.clone().unwrap_or_default();
+    let (pending, _, _) = enhance::enhance_profile(uid.as_ref()).await;
+    Ok(enhance::diff::diff_config(&applied, &pending))
+}
+
 /// 获取运行时存在的键
 #[tauri::command]
 pub async fn get_runtime_exists() -> CmdResult<HashSet<String>> {
@@ -41,6 +58,13 @@ pub async fn get_runtime_logs() -> CmdResult<HashMap<String, Vec<(String, String
     Ok(Config::runtime().await.latest_arc().chain_logs.clone())
 }
 
+/// 获取某个脚本最近一次执行的控制台输出与异常
+#[tauri::command]
+pub async fn get_script_logs(uid: String) -> CmdResult<Vec<(String, String)>> {
+    let runtime = Config::runtime().await.latest_arc();
+    Ok(runtime.chain_logs.get(&uid).cloned().unwrap_or_default())
+}
+
 #[tauri::command]
 pub async fn get_runtime_proxy_chain_config(proxy_chain_exit_node: String) -> CmdResult<String> {
     let runtime = Config::runtime().await;
@@ -1,21 +1,33 @@
 use super::CmdResult;
 use crate::{
     cmd::StringifyErr as _,
-    config::{Config, PrfItem},
-    core::{CoreManager, handle, validate::CoreConfigValidator},
+    config::{Config, IProfiles, versions},
+    core::{
+        CoreManager, handle,
+        validate::{ConfigDiagnostic, ConfigValidation, CoreConfigValidator, diagnose_yaml, parse_diagnostics},
+    },
+    feat,
     module::auto_backup::{AutoBackupManager, AutoBackupTrigger},
     utils::dirs,
 };
-use clash_verge_logging::{Type, logging};
+use clash_verge_logging::{Type, logging, logging_error};
 use smartstring::alias::String;
 use tokio::fs;
 
+/// 待验证的新内容写入同目录下带该前缀的临时文件，保留扩展名以便按类型验证
+const PENDING_PREFIX: &str = ".pending-";
+
 /// 保存profiles的配置
+///
+/// 保存前先检查 YAML 语法，再把新内容写入临时文件交给内核检查；若该文件属于当前使用的配置链，
+/// 还会让该项临时指向新内容试运行一次增强流程。全部通过后才写入原文件，任一步失败时原文件保持不变，
+/// 并返回带行列号的错误列表
 #[tauri::command]
-pub async fn save_profile_file(index: String, file_data: Option<String>) -> CmdResult {
+pub async fn save_profile_file(index: String, file_data: Option<String>) -> CmdResult<ConfigValidation> {
+    feat::ensure_unlocked().await?;
     let file_data = match file_data {
         Some(d) => d,
-        None => return Ok(()),
+        None => return Ok(ConfigValidation::valid()),
     };
 
     let backup_trigger = match index.as_str() {
@@ -25,54 +37,110 @@ pub async fn save_profile_file(index: String, file_data: Option<String>) -> CmdR
     };
 
     // 在异步操作前获取必要元数据并释放锁
-    let (rel_path, is_merge_file) = {
+    let (rel_path, is_merge_file, is_active) = {
         let profiles = Config::profiles().await;
         let profiles_guard = profiles.latest_arc();
         let item = profiles_guard.get_item(&index).stringify_err()?;
         let is_merge = item.itype.as_ref().is_some_and(|t| t == "merge");
         let path = item.file.clone().ok_or("file field is null")?;
-        (path, is_merge)
+        (path, is_merge, profiles_guard.is_in_current_chain(&index))
     };
 
-    // 读取原始内容（在释放profiles_guard后进行）
-    let original_content = PrfItem {
-        file: Some(rel_path.clone()),
-        ..Default::default()
+    if !rel_path.ends_with(".js")
+        && let Some(diagnostic) = diagnose_yaml(&rel_path, &file_data)
+    {
+        logging!(
+            warn,
+            Type::Config,
+            "[cmd配置save] YAML语法错误，未保存: {}",
+            diagnostic.message
+        );
+        let result = (false, diagnostic.message.clone());
+        crate::cmd::validate::handle_yaml_validation_notice(&result, "YAML配置文件");
+        return Ok(ConfigValidation::invalid(vec![diagnostic]));
     }
-    .read_file()
-    .await
-    .stringify_err()?;
 
     let profiles_dir = dirs::app_profiles_dir().stringify_err()?;
     let file_path = profiles_dir.join(rel_path.as_str());
-    let file_path_str = file_path.to_string_lossy().to_string();
-
-    // 保存新的配置文件
-    fs::write(&file_path, &file_data).await.stringify_err()?;
+    let pending_rel: String = format!("{PENDING_PREFIX}{rel_path}").into();
+    let pending_path = profiles_dir.join(pending_rel.as_str());
+    let pending_path_str = pending_path.to_string_lossy().to_string();
+    fs::write(&pending_path, &file_data).await.stringify_err()?;
+    let _pending = scopeguard::guard(pending_path.clone(), |path| {
+        let _ = std::fs::remove_file(path);
+    });
 
     logging!(
         info,
         Type::Config,
         "[cmd配置save] 开始验证配置文件: {}, 是否为merge文件: {}",
-        file_path_str,
+        file_path.display(),
         is_merge_file
     );
 
-    let changes_applied = if is_merge_file {
-        handle_merge_file(&file_path_str, &file_path, &original_content).await?
+    let error = if is_merge_file {
+        validate_merge_file(&pending_path_str).await?
     } else {
-        handle_full_validation(&file_path_str, &file_path, &original_content).await?
+        validate_full(&pending_path_str).await?
     };
+    if let Some(error_msg) = error {
+        // 错误信息中的临时文件名换回原文件名
+        let error_msg: String = error_msg.replace(pending_rel.as_str(), rel_path.as_str()).into();
+        let mut diagnostics = parse_diagnostics(&rel_path, &error_msg);
+        if diagnostics.is_empty() {
+            diagnostics.push(ConfigDiagnostic {
+                file: rel_path,
+                line: None,
+                column: None,
+                message: error_msg,
+            });
+        }
+        return Ok(ConfigValidation::invalid(diagnostics));
+    }
 
-    if changes_applied && let Some(trigger) = backup_trigger {
-        AutoBackupManager::trigger_backup(trigger);
+    if is_active {
+        match diagnose_with_pending_file(&index, &pending_rel).await {
+            Ok(validation) if !validation.valid => {
+                logging!(warn, Type::Config, "[cmd配置save] 增强后的运行配置验证失败，未保存");
+                if let Some(diagnostic) = validation.diagnostics.first() {
+                    handle::Handle::notice_message("config_validate::error", diagnostic.message.clone());
+                }
+                return Ok(validation);
+            }
+            Ok(_) => {}
+            Err(e) => logging!(warn, Type::Config, "[cmd配置save] 试运行增强流程失败: {}", e),
+        }
     }
 
-    Ok(())
+    // 验证通过后保存新的配置文件
+    logging_error!(Type::Config, versions::snapshot_profile_file(&index, &file_path).await);
+    fs::write(&file_path, &file_data).await.stringify_err()?;
+
+    if is_merge_file {
+        if let Err(e) = CoreManager::global().update_config().await {
+            logging!(warn, Type::Config, "[cmd配置save] 更新整体配置时发生错误: {}", e);
+        } else {
+            handle::Handle::refresh_clash();
+        }
+    }
+    if let Some(trigger) = backup_trigger {
+        AutoBackupManager::trigger_backup(trigger);
+    }
+    Ok(ConfigValidation::valid())
 }
 
-async fn restore_original(file_path: &std::path::Path, original_content: &str) -> Result<(), String> {
-    fs::write(file_path, original_content).await.stringify_err()
+/// 在订阅列表的副本中让该项指向临时文件后试运行增强流程，不触碰共享草稿
+async fn diagnose_with_pending_file(index: &String, pending_rel: &String) -> anyhow::Result<ConfigValidation> {
+    let mut profiles = IProfiles::clone(&Config::profiles().await.latest_arc());
+    if let Some(item) = profiles
+        .items
+        .iter_mut()
+        .flatten()
+        .find(|item| item.uid.as_ref() == Some(index))
+    {
+        item.file = Some(pending_rel.clone());
+    }
+    CoreConfigValidator::diagnose_enhanced_config(&profiles).await
 }
 
 fn is_script_error(err: &str, file_path_str: &str) -> bool {
@@ -82,51 +150,35 @@ fn is_script_error(err: &str, file_path_str: &str) -> bool {
         || err.contains("Failed to read script file")
 }
 
-async fn handle_merge_file(
-    file_path_str: &str,
-    file_path: &std::path::Path,
-    original_content: &str,
-) -> CmdResult<bool> {
+async fn validate_merge_file(file_path_str: &str) -> CmdResult<Option<String>> {
     logging!(info, Type::Config, "[cmd配置save] 检测到merge文件，只进行语法验证");
 
     match CoreConfigValidator::validate_config_file(file_path_str, Some(true)).await {
         Ok((true, _)) => {
             logging!(info, Type::Config, "[cmd配置save] merge文件语法验证通过");
-            if let Err(e) = CoreManager::global().update_config().await {
-                logging!(warn, Type::Config, "[cmd配置save] 更新整体配置时发生错误: {}", e);
-            } else {
-                handle::Handle::refresh_clash();
-            }
-            Ok(true)
+            Ok(None)
         }
         Ok((false, error_msg)) => {
             logging!(warn, Type::Config, "[cmd配置save] merge文件语法验证失败: {}", error_msg);
-            restore_original(file_path, original_content).await?;
             let result = (false, error_msg.clone());
             crate::cmd::validate::handle_yaml_validation_notice(&result, "合并配置文件");
-            Ok(false)
+            Ok(Some(error_msg))
         }
         Err(e) => {
             logging!(error, Type::Config, "[cmd配置save] 验证过程发生错误: {}", e);
-            restore_original(file_path, original_content).await?;
             Err(e.to_string().into())
         }
     }
 }
 
-async fn handle_full_validation(
-    file_path_str: &str,
-    file_path: &std::path::Path,
-    original_content: &str,
-) -> CmdResult<bool> {
+async fn validate_full(file_path_str: &str) -> CmdResult<Option<String>> {
     match CoreConfigValidator::validate_config_file(file_path_str, None).await {
         Ok((true, _)) => {
             logging!(info, Type::Config, "[cmd配置save] 验证成功");
-            Ok(true)
+            Ok(None)
         }
         Ok((false, error_msg)) => {
             logging!(warn, Type::Config, "[cmd配置save] 验证失败: {}", error_msg);
-            restore_original(file_path, original_content).await?;
 
             if error_msg.contains("YAML syntax error")
                 || error_msg.contains("Failed to read file:")
@@ -144,11 +196,10 @@ async fn handle_full_validation(
                 handle::Handle::notice_message("config_validate::error", error_msg.to_owned());
             }
 
-            Ok(false)
+            Ok(Some(error_msg))
         }
         Err(e) => {
             logging!(error, Type::Config, "[cmd配置save] 验证过程发生错误: {}", e);
-            restore_original(file_path, original_content).await?;
             Err(e.to_string().into())
         }
     }
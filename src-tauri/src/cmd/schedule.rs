@@ -0,0 +1,29 @@
+use super::{CmdResult, StringifyErr as _};
+use crate::{
+    config::ISchedule,
+    core::scheduler::{ScheduleInfo, Scheduler},
+    feat,
+};
+use clash_verge_logging::{Type, logging};
+use smartstring::alias::String;
+
+#[tauri::command]
+pub async fn list_schedules() -> CmdResult<Vec<ScheduleInfo>> {
+    Ok(Scheduler::global().list().await)
+}
+
+/// 新建或更新定时任务，`id` 为空时新建
+#[tauri::command]
+pub async fn upsert_schedule(schedule: ISchedule) -> CmdResult<Vec<ScheduleInfo>> {
+    feat::ensure_unlocked().await?;
+    Scheduler::global()
+        .upsert(schedule)
+        .await
+        .stringify_err_log(|e| logging!(error, Type::Cmd, "[定时任务] 保存失败: {}", e))
+}
+
+#[tauri::command]
+pub async fn delete_schedule(id: String) -> CmdResult<Vec<ScheduleInfo>> {
+    feat::ensure_unlocked().await?;
+    Scheduler::global().delete(&id).await.stringify_err()
+}
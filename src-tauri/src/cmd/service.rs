@@ -1,11 +1,10 @@
 use super::{CmdResult, StringifyErr as _};
 use crate::core::service::{self, SERVICE_MANAGER, ServiceStatus};
-use smartstring::SmartString;
 
 async fn execute_service_operation_sync(status: ServiceStatus, op_type: &str) -> CmdResult {
     if let Err(e) = SERVICE_MANAGER.lock().await.handle_service_status(&status).await {
         let emsg = format!("{} Service failed: {}", op_type, e);
-        return Err(SmartString::from(emsg));
+        return Err(emsg.into());
     }
     Ok(())
 }
@@ -30,6 +29,12 @@ pub async fn repair_service() -> CmdResult {
     execute_service_operation_sync(ServiceStatus::ForceReinstallRequired, "Repair").await
 }
 
+/// 服务的安装、版本与运行状态
+#[tauri::command]
+pub async fn service_status() -> CmdResult<service::ServiceInfo> {
+    Ok(service::service_info().await)
+}
+
 #[tauri::command]
 pub async fn is_service_available() -> CmdResult<bool> {
     service::is_service_available().await.stringify_err()?;
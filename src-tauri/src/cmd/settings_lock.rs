@@ -0,0 +1,29 @@
+use super::{CmdError, CmdResult, StringifyErr as _};
+use crate::feat::{self, SettingsLockStatus};
+use smartstring::alias::String;
+
+#[tauri::command]
+pub async fn get_settings_lock_status() -> CmdResult<SettingsLockStatus> {
+    feat::settings_lock_status().await.stringify_err()
+}
+
+/// 输入 PIN 解锁，返回 PIN 是否正确
+#[tauri::command]
+pub async fn unlock_settings(pin: String) -> CmdResult<bool> {
+    feat::unlock_settings(&pin)
+        .await
+        .map_err(|err| CmdError::InvalidInput(err.to_string().into()))
+}
+
+#[tauri::command]
+pub fn lock_settings() {
+    feat::lock_settings();
+}
+
+/// 设置或修改 PIN，`pin` 为空时关闭设置锁；已有 PIN 时需提供当前 PIN 或处于解锁状态
+#[tauri::command]
+pub async fn set_settings_pin(current: Option<String>, pin: Option<String>) -> CmdResult {
+    feat::set_settings_pin(current.as_deref(), pin.as_deref())
+        .await
+        .map_err(|err| CmdError::InvalidInput(err.to_string().into()))
+}
@@ -0,0 +1,16 @@
+use super::{CmdError, CmdResult, StringifyErr as _};
+use crate::feat::{self, SetupStatus, SetupStep};
+
+/// 首次运行引导的进度与各步骤的检查结果
+#[tauri::command]
+pub async fn get_setup_status() -> CmdResult<SetupStatus> {
+    feat::get_setup_status().await.stringify_err()
+}
+
+/// 完成当前引导步骤，检查未通过或未按顺序时返回错误
+#[tauri::command]
+pub async fn complete_setup_step(step: SetupStep) -> CmdResult<SetupStatus> {
+    feat::complete_setup_step(step)
+        .await
+        .map_err(|err| CmdError::InvalidInput(err.to_string().into()))
+}
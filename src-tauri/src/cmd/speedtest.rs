@@ -0,0 +1,24 @@
+use super::{CmdResult, StringifyErr as _};
+use crate::{
+    core::stats::{SpeedtestHistory, SpeedtestRecord},
+    feat,
+};
+use smartstring::alias::String;
+
+const DEFAULT_HISTORY_LIMIT: usize = 100;
+
+/// 经代理测速，`node` 为空时测试当前节点，`server` 为空时使用第一个测速服务器
+#[tauri::command]
+pub async fn run_speedtest(node: Option<String>, server: Option<String>) -> CmdResult<SpeedtestRecord> {
+    feat::run_speedtest(node.as_deref(), server.as_deref())
+        .await
+        .stringify_err()
+}
+
+/// 测速历史，按时间从新到旧返回
+#[tauri::command]
+pub async fn get_speedtest_history(node: Option<String>, limit: Option<usize>) -> CmdResult<Vec<SpeedtestRecord>> {
+    SpeedtestHistory::query(node.as_deref(), limit.unwrap_or(DEFAULT_HISTORY_LIMIT))
+        .await
+        .stringify_err()
+}
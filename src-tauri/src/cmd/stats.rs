@@ -0,0 +1,36 @@
+use super::{CmdResult, StringifyErr as _};
+use crate::core::stats::{
+    ConnectionEvent, ConnectionLog, ConnectionLogFilter, DomainStat, LatencyBucket, StatsRange, query_domain_stats,
+    query_latency_heatmap,
+};
+
+const DEFAULT_QUERY_LIMIT: usize = 500;
+const DEFAULT_TOP_DOMAINS: usize = 20;
+
+/// 查询本地连接日志，按时间从新到旧返回
+#[tauri::command]
+pub async fn query_connection_log(
+    filter: Option<ConnectionLogFilter>,
+    limit: Option<usize>,
+) -> CmdResult<Vec<ConnectionEvent>> {
+    let filter = filter.unwrap_or_default();
+    ConnectionLog::query(&filter, limit.unwrap_or(DEFAULT_QUERY_LIMIT))
+        .await
+        .stringify_err()
+}
+
+/// 按域名聚合连接日志，需开启连接日志才会有数据
+#[tauri::command]
+pub async fn get_domain_stats(top_n: Option<usize>, range: Option<StatsRange>) -> CmdResult<Vec<DomainStat>> {
+    query_domain_stats(top_n.unwrap_or(DEFAULT_TOP_DOMAINS), range.unwrap_or_default())
+        .await
+        .stringify_err()
+}
+
+/// 按节点与时间段聚合连接日志中的延迟采样，用于绘制延迟热力图
+#[tauri::command]
+pub async fn get_latency_heatmap(node: Option<String>, range: Option<StatsRange>) -> CmdResult<Vec<LatencyBucket>> {
+    query_latency_heatmap(node.as_deref(), range.unwrap_or_default())
+        .await
+        .stringify_err()
+}
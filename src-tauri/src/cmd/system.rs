@@ -1,9 +1,10 @@
 use std::sync::Arc;
 
+use super::CmdResult;
 use crate::core::{CoreManager, manager::RunningMode};
 
 /// 获取当前内核运行模式
 #[tauri::command]
-pub async fn get_running_mode() -> Result<Arc<RunningMode>, String> {
+pub async fn get_running_mode() -> CmdResult<Arc<RunningMode>> {
     Ok(CoreManager::global().get_running_mode())
 }
@@ -0,0 +1,28 @@
+use super::{CmdResult, StringifyErr as _};
+use crate::core::app_updater::{AppUpdateInfo, AppUpdater};
+use clash_verge_logging::{Type, logging};
+use smartstring::alias::String;
+
+/// 检查应用更新，`channel` 为空时使用设置中的更新通道
+#[tauri::command]
+pub async fn check_app_update(channel: Option<String>) -> CmdResult<Option<AppUpdateInfo>> {
+    AppUpdater::global()
+        .check(channel.as_deref())
+        .await
+        .stringify_err_log(|e| logging!(error, Type::Cmd, "Failed to check app update: {e}"))
+}
+
+/// 下载并安装已检查到的更新
+#[tauri::command]
+pub async fn install_app_update() -> CmdResult {
+    AppUpdater::global()
+        .install()
+        .await
+        .stringify_err_log(|e| logging!(error, Type::Cmd, "Failed to install app update: {e}"))
+}
+
+/// 推迟提醒当前检查到的版本
+#[tauri::command]
+pub async fn defer_app_update(hours: u32) -> CmdResult {
+    AppUpdater::global().defer(hours).await.stringify_err()
+}
@@ -1,5 +1,8 @@
-use super::CmdResult;
-use crate::core::{handle, validate::CoreConfigValidator};
+use super::{CmdResult, StringifyErr as _};
+use crate::core::{
+    handle,
+    validate::{ConfigValidation, CoreConfigValidator},
+};
 use clash_verge_logging::{Type, logging};
 use smartstring::alias::String;
 
@@ -107,3 +110,13 @@ pub fn handle_yaml_validation_notice(result: &(bool, String), file_type: &str) {
         handle::Handle::notice_message(status, error_msg.to_owned());
     }
 }
+
+/// 完整验证配置并返回带位置信息的错误列表，未指定路径时验证当前生成的运行配置
+#[tauri::command]
+pub async fn validate_config(path: Option<String>) -> CmdResult<ConfigValidation> {
+    let result = match path {
+        Some(path) => CoreConfigValidator::diagnose_config_file(&path).await,
+        None => CoreConfigValidator::diagnose_runtime_config().await,
+    };
+    result.stringify_err_log(|e| logging!(error, Type::Validate, "配置验证过程发生错误: {}", e))
+}
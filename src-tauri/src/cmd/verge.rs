@@ -1,5 +1,9 @@
 use super::CmdResult;
-use crate::{cmd::StringifyErr as _, config::IVerge, feat};
+use crate::{
+    cmd::StringifyErr as _,
+    config::{ConfigPersistence, IVerge},
+    feat,
+};
 use clash_verge_draft::SharedBox;
 
 /// 获取Verge配置
@@ -11,5 +15,17 @@ pub async fn get_verge_config() -> CmdResult<SharedBox<IVerge>> {
 /// 修改Verge配置
 #[tauri::command]
 pub async fn patch_verge_config(payload: IVerge) -> CmdResult {
+    if feat::is_sensitive_patch(&payload) {
+        feat::ensure_unlocked().await?;
+    }
     feat::patch_verge(&payload, false).await.stringify_err()
 }
+
+/// 立即写入尚未落盘的设置，返回此前是否有待写入的修改
+#[tauri::command]
+pub async fn flush_config() -> CmdResult<bool> {
+    let persistence = ConfigPersistence::global();
+    let dirty = persistence.is_dirty();
+    persistence.flush().await.stringify_err()?;
+    Ok(dirty)
+}
@@ -46,5 +46,25 @@ pub async fn delete_webdav_backup(filename: String) -> CmdResult<()> {
 /// 从 WebDAV 恢复备份文件
 #[tauri::command]
 pub async fn restore_webdav_backup(filename: String) -> CmdResult<()> {
+    feat::ensure_unlocked().await?;
     feat::restore_webdav_backup(filename).await.stringify_err()
 }
+
+/// 立即创建备份（设置了备份口令时加密）并上传到 WebDAV，返回备份 id
+#[tauri::command]
+pub async fn backup_now() -> CmdResult<String> {
+    feat::backup_now().await.stringify_err()
+}
+
+/// 列出 WebDAV 上的备份，按时间倒序
+#[tauri::command]
+pub async fn list_backups() -> CmdResult<Vec<feat::RemoteBackupFile>> {
+    feat::list_backups().await.stringify_err()
+}
+
+/// 下载并恢复 WebDAV 备份，加密备份使用本机的备份口令解密
+#[tauri::command]
+pub async fn restore_backup(id: String) -> CmdResult<()> {
+    feat::ensure_unlocked().await?;
+    feat::restore_backup(id).await.stringify_err()
+}
@@ -0,0 +1,23 @@
+use super::{CmdResult, StringifyErr as _};
+use crate::{config::IWebhook, core::webhook::WebhookManager};
+use clash_verge_logging::{Type, logging};
+use smartstring::alias::String;
+
+#[tauri::command]
+pub async fn list_webhooks() -> CmdResult<Vec<IWebhook>> {
+    Ok(WebhookManager::global().list().await)
+}
+
+/// 新增或更新事件回调，`id` 为空时新增
+#[tauri::command]
+pub async fn add_webhook(webhook: IWebhook) -> CmdResult<Vec<IWebhook>> {
+    WebhookManager::global()
+        .add(webhook)
+        .await
+        .stringify_err_log(|e| logging!(error, Type::Cmd, "Failed to save webhook: {}", e))
+}
+
+#[tauri::command]
+pub async fn delete_webhook(id: String) -> CmdResult<Vec<IWebhook>> {
+    WebhookManager::global().delete(&id).await.stringify_err()
+}
@@ -1,7 +1,8 @@
 use crate::config::Config;
 use crate::constants::{network, tun as tun_const};
+use crate::core::secrets;
 use crate::utils::dirs::{ipc_path, path_to_str};
-use crate::utils::{dirs, help};
+use crate::utils::{dirs, help, instance};
 use anyhow::Result;
 use clash_verge_logging::{Type, logging};
 use serde::{Deserialize, Serialize};
@@ -24,7 +25,13 @@ impl IClashTemp {
         };
 
         match map_result {
-            Ok(mut map) => {
+            Ok(map) => {
+                // 解析失败时保留引用，保存时不会覆盖凭据库中的值
+                let unresolved = map.clone();
+                let mut map = secrets::resolve_clash(map).await.unwrap_or_else(|err| {
+                    logging!(error, Type::Config, "Failed to resolve keychain references: {err}");
+                    unresolved
+                });
                 let template_map = Self::template().0;
                 for (key, value) in template_map.into_iter() {
                     if !map.contains_key(&key) {
@@ -62,21 +69,33 @@ impl IClashTemp {
         tun_config.insert("dns-hijack".into(), tun_const::DNS_HIJACK.into());
 
         #[cfg(not(target_os = "windows"))]
-        map.insert("redir-port".into(), network::ports::DEFAULT_REDIR.into());
+        map.insert(
+            "redir-port".into(),
+            instance::offset_port(network::ports::DEFAULT_REDIR).into(),
+        );
         #[cfg(target_os = "linux")]
-        map.insert("tproxy-port".into(), network::ports::DEFAULT_TPROXY.into());
+        map.insert(
+            "tproxy-port".into(),
+            instance::offset_port(network::ports::DEFAULT_TPROXY).into(),
+        );
 
-        map.insert("mixed-port".into(), network::ports::DEFAULT_MIXED.into());
-        map.insert("socks-port".into(), network::ports::DEFAULT_SOCKS.into());
-        map.insert("port".into(), network::ports::DEFAULT_HTTP.into());
+        map.insert(
+            "mixed-port".into(),
+            instance::offset_port(network::ports::DEFAULT_MIXED).into(),
+        );
+        map.insert(
+            "socks-port".into(),
+            instance::offset_port(network::ports::DEFAULT_SOCKS).into(),
+        );
+        map.insert(
+            "port".into(),
+            instance::offset_port(network::ports::DEFAULT_HTTP).into(),
+        );
         map.insert("log-level".into(), "info".into());
         map.insert("allow-lan".into(), false.into());
         map.insert("ipv6".into(), true.into());
         map.insert("mode".into(), "rule".into());
-        map.insert(
-            "external-controller".into(),
-            network::DEFAULT_EXTERNAL_CONTROLLER.into(),
-        );
+        map.insert("external-controller".into(), Self::default_controller().into());
         #[cfg(unix)]
         map.insert(
             "external-controller-unix".into(),
@@ -109,6 +128,23 @@ impl IClashTemp {
         Self(map)
     }
 
+    /// 仅允许应用自身的页面跨域访问外部控制器
+    pub fn app_only_cors() -> Mapping {
+        let mut cors_map = Mapping::new();
+        cors_map.insert("allow-private-network".into(), false.into());
+        cors_map.insert(
+            "allow-origins".into(),
+            vec![
+                "tauri://localhost",
+                "http://tauri.localhost",
+                #[cfg(feature = "verge-dev")]
+                "http://localhost:3000",
+            ]
+            .into(),
+        );
+        cors_map
+    }
+
     fn guard(mut config: Mapping) -> Mapping {
         #[cfg(not(target_os = "windows"))]
         let redir_port = Self::guard_redir_port(&config);
@@ -139,14 +175,20 @@ impl IClashTemp {
         config
     }
 
+    /// 合并补丁，值为 `null` 的键会被移除
     pub fn patch_config(&mut self, patch: &Mapping) {
         for (key, value) in patch.iter() {
-            self.0.insert(key.to_owned(), value.to_owned());
+            if value.is_null() {
+                self.0.remove(key);
+            } else {
+                self.0.insert(key.to_owned(), value.to_owned());
+            }
         }
     }
 
     pub async fn save_config(&self) -> Result<()> {
-        help::save_yaml(&dirs::clash_path()?, &self.0, Some("# Generated by Clash Verge")).await
+        let map = secrets::externalize_clash(&self.0).await?;
+        help::save_yaml(&dirs::clash_path()?, &map, Some("# Generated by Clash Verge")).await
     }
 
     pub fn get_mixed_port(&self) -> u16 {
@@ -188,9 +230,9 @@ impl IClashTemp {
                 Value::Number(val_num) => val_num.as_u64().map(|u| u as u16),
                 _ => None,
             })
-            .unwrap_or(7895);
+            .unwrap_or_else(|| instance::offset_port(network::ports::DEFAULT_REDIR));
         if port == 0 {
-            port = 7895;
+            port = instance::offset_port(network::ports::DEFAULT_REDIR);
         }
         port
     }
@@ -204,9 +246,9 @@ impl IClashTemp {
                 Value::Number(val_num) => val_num.as_u64().map(|u| u as u16),
                 _ => None,
             })
-            .unwrap_or(network::ports::DEFAULT_TPROXY);
+            .unwrap_or_else(|| instance::offset_port(network::ports::DEFAULT_TPROXY));
         if port == 0 {
-            port = network::ports::DEFAULT_TPROXY;
+            port = instance::offset_port(network::ports::DEFAULT_TPROXY);
         }
         port
     }
@@ -220,10 +262,10 @@ impl IClashTemp {
                 Value::Number(val_num) => val_num.as_u64().map(|u| u as u16),
                 _ => None,
             })
-            .unwrap_or(7897);
+            .unwrap_or_else(|| instance::offset_port(network::ports::DEFAULT_MIXED));
 
         if port == 0 {
-            port = 7897;
+            port = instance::offset_port(network::ports::DEFAULT_MIXED);
         }
 
         port
@@ -237,9 +279,9 @@ impl IClashTemp {
                 Value::Number(val_num) => val_num.as_u64().map(|u| u as u16),
                 _ => None,
             })
-            .unwrap_or(7898);
+            .unwrap_or_else(|| instance::offset_port(network::ports::DEFAULT_SOCKS));
         if port == 0 {
-            port = 7898;
+            port = instance::offset_port(network::ports::DEFAULT_SOCKS);
         }
         port
     }
@@ -252,13 +294,22 @@ impl IClashTemp {
                 Value::Number(val_num) => val_num.as_u64().map(|u| u as u16),
                 _ => None,
             })
-            .unwrap_or(7899);
+            .unwrap_or_else(|| instance::offset_port(network::ports::DEFAULT_HTTP));
         if port == 0 {
-            port = 7899;
+            port = instance::offset_port(network::ports::DEFAULT_HTTP);
         }
         port
     }
 
+    /// 按实例偏移后的默认控制器地址
+    fn default_controller() -> String {
+        format!(
+            "127.0.0.1:{}",
+            instance::offset_port(network::ports::DEFAULT_CONTROLLER)
+        )
+        .into()
+    }
+
     pub fn guard_server_ctrl(config: &Mapping) -> String {
         config
             .get("external-controller")
@@ -275,7 +326,7 @@ impl IClashTemp {
                 }
                 None => None,
             })
-            .unwrap_or_else(|| "127.0.0.1:9097".into())
+            .unwrap_or_else(Self::default_controller)
     }
 
     pub fn guard_external_controller(config: &Mapping) -> String {
@@ -308,7 +359,7 @@ impl IClashTemp {
                 }
                 socket.to_string()
             }
-            Err(_) => "127.0.0.1:9097".into(),
+            Err(_) => Self::default_controller(),
         }
     }
 
@@ -319,7 +370,7 @@ impl IClashTemp {
             .and_then(|path| path_to_str(&path).ok().map(|s| s.into()))
             .unwrap_or_else(|| {
                 logging!(error, Type::Config, "Failed to get IPC path");
-                crate::constants::network::DEFAULT_EXTERNAL_CONTROLLER.into()
+                Self::default_controller()
             })
     }
 }
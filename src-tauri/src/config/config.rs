@@ -61,6 +61,22 @@ impl Config {
         Self::global().await.runtime_config.clone()
     }
 
+    /// 实际使用的混合端口：当前订阅覆盖的端口优先，其次为设置中的端口
+    pub async fn mixed_port() -> u16 {
+        let profile_port = Self::profiles()
+            .await
+            .latest_arc()
+            .current_runtime()
+            .and_then(|runtime| runtime.mixed_port);
+        if let Some(port) = profile_port {
+            return port;
+        }
+        match Self::verge().await.latest_arc().verge_mixed_port {
+            Some(port) => port,
+            None => Self::clash().await.latest_arc().get_mixed_port(),
+        }
+    }
+
     /// 初始化订阅
     pub async fn init_config() -> Result<()> {
         Self::ensure_default_profile_items().await?;
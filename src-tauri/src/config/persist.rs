@@ -0,0 +1,170 @@
+//! 配置写入合并
+//!
+//! 界面连续切换多个选项时，每次修改只把对应配置标记为待写入，[`DEBOUNCE`] 内没有新的修改才统一写盘；
+//! `patch_clash` 触发的内核重载同样合并为一次，校验失败时通知前端。[`ConfigPersistence::flush`]
+//! 立即写入所有待写配置并执行待定的重载，打包备份前、退出与重启应用前以及前端 `flush_config` 命令会调用。
+
+use super::Config;
+use crate::{
+    core::{CoreManager, handle},
+    process::AsyncHandler,
+    singleton,
+};
+use anyhow::{Result, anyhow};
+use clash_verge_logging::{Type, logging};
+use parking_lot::Mutex;
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigTarget {
+    Verge,
+    Clash,
+}
+
+/// 待写入的配置与待执行的重载
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct Pending {
+    verge: bool,
+    clash: bool,
+    reload: bool,
+}
+
+impl Pending {
+    const fn mark(&mut self, target: ConfigTarget) {
+        match target {
+            ConfigTarget::Verge => self.verge = true,
+            ConfigTarget::Clash => self.clash = true,
+        }
+    }
+
+    const fn merge(&mut self, other: Self) {
+        self.verge |= other.verge;
+        self.clash |= other.clash;
+        self.reload |= other.reload;
+    }
+
+    const fn is_empty(&self) -> bool {
+        !(self.verge || self.clash || self.reload)
+    }
+}
+
+pub struct ConfigPersistence {
+    pending: Mutex<Pending>,
+    /// 每次修改递增，只有最后一次修改安排的写入会执行
+    generation: AtomicU64,
+    flushing: tokio::sync::Mutex<()>,
+}
+
+singleton!(ConfigPersistence, CONFIG_PERSISTENCE);
+
+impl ConfigPersistence {
+    fn new() -> Self {
+        Self {
+            pending: Mutex::new(Pending::default()),
+            generation: AtomicU64::new(0),
+            flushing: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// 标记配置已修改，稍后写入
+    pub fn mark_dirty(&'static self, target: ConfigTarget) {
+        self.pending.lock().mark(target);
+        self.schedule();
+    }
+
+    /// 稍后按最新配置重载内核
+    pub fn request_reload(&'static self) {
+        self.pending.lock().reload = true;
+        self.schedule();
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        !self.pending.lock().is_empty()
+    }
+
+    fn schedule(&'static self) {
+        let generation = self.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        AsyncHandler::spawn(move || async move {
+            tokio::time::sleep(DEBOUNCE).await;
+            if self.generation.load(Ordering::SeqCst) != generation {
+                return;
+            }
+            if let Err(err) = self.flush().await {
+                logging!(error, Type::Config, "Failed to persist config: {err}");
+                handle::Handle::notice_message("set_config::error", format!("{err}"));
+            }
+        });
+    }
+
+    /// 立即写入所有待写配置并执行待定的重载，写入失败的部分保留到下次
+    pub async fn flush(&self) -> Result<()> {
+        let _guard = self.flushing.lock().await;
+        let pending = std::mem::take(&mut *self.pending.lock());
+        if pending.is_empty() {
+            return Ok(());
+        }
+
+        let mut failed = Pending::default();
+        let mut first_error = None;
+        if pending.verge
+            && let Err(err) = Config::verge().await.data_arc().save_file().await
+        {
+            failed.verge = true;
+            first_error.get_or_insert(err);
+        }
+        if pending.clash
+            && let Err(err) = Config::clash().await.data_arc().save_config().await
+        {
+            failed.clash = true;
+            first_error.get_or_insert(err);
+        }
+        // 校验失败时重试也不会通过，不再保留
+        if pending.reload {
+            match CoreManager::global().update_config().await {
+                Ok((true, _)) => handle::Handle::refresh_clash(),
+                Ok((false, message)) => {
+                    first_error.get_or_insert(anyhow!("{message}"));
+                }
+                Err(err) => {
+                    failed.reload = true;
+                    first_error.get_or_insert(err);
+                }
+            }
+        }
+
+        logging!(debug, Type::Config, "Persisted pending config changes: {pending:?}");
+        self.pending.lock().merge(failed);
+        first_error.map_or(Ok(()), Err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pending() {
+        let mut pending = Pending::default();
+        assert!(pending.is_empty());
+        pending.mark(ConfigTarget::Verge);
+        pending.mark(ConfigTarget::Verge);
+        assert_eq!(
+            pending,
+            Pending {
+                verge: true,
+                ..Pending::default()
+            }
+        );
+
+        pending.merge(Pending {
+            reload: true,
+            ..Pending::default()
+        });
+        assert!(pending.verge && pending.reload && !pending.clash);
+    }
+}
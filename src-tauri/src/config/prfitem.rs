@@ -1,18 +1,35 @@
 use crate::{
     config::profiles,
+    core::{
+        converter::{self, SubscriptionFormat},
+        handle,
+    },
     utils::{
         dirs, help,
-        network::{NetworkManager, ProxyType},
+        network::{DownloadProgress, HttpResponse, NetworkManager, ProxyType, RequestParams},
         tmpl,
     },
 };
 use anyhow::{Context as _, Result, bail};
+use clash_verge_logging::{Type, logging};
 use serde::{Deserialize, Serialize};
 use serde_yaml_ng::Mapping;
 use smartstring::alias::String;
+use std::collections::BTreeMap;
 use std::time::Duration;
+use tauri::Emitter as _;
 use tokio::fs;
 
+/// 远程订阅下载进度事件
+const DOWNLOAD_PROGRESS_EVENT: &str = "profile-download-progress";
+
+#[derive(Debug, Clone, Serialize)]
+struct ProfileDownloadProgress<'a> {
+    url: &'a str,
+    downloaded: u64,
+    total: Option<u64>,
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize, Default)]
 pub struct PrfItem {
     pub uid: Option<String>,
@@ -119,9 +136,133 @@ pub struct PrfOption {
     pub proxies: Option<String>,
 
     pub groups: Option<String>,
+
+    /// for `remote` profile
+    /// 额外的 HTTP 请求头，如部分机场要求的鉴权或设备标识
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub headers: Option<BTreeMap<String, String>>,
+
+    /// for `remote` profile
+    /// 备用下载地址，主地址下载失败时依次尝试
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mirrors: Option<Vec<String>>,
+
+    /// for `remote` profile
+    /// 订阅内容格式，非 Clash 格式会在导入与更新时转换，默认自动识别
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subscription_format: Option<SubscriptionFormat>,
+
+    /// 显式的扩展链，按顺序叠加到订阅上；为空时沿用 rules → proxies → groups → merge → script
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chain: Option<Vec<PrfLayer>>,
+
+    /// 激活该订阅时覆盖的运行参数，如工作与家用订阅使用不同端口
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub runtime: Option<PrfRuntime>,
+
+    /// 节点去重、过滤与重命名，在扩展链之后、交给内核之前执行
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nodes: Option<PrfNodeFilter>,
+}
+
+/// 扩展链中的一层，引用一个 merge / script / rules / proxies / groups 配置项
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct PrfLayer {
+    pub uid: String,
+    #[serde(default = "default_layer_enabled")]
+    pub enabled: bool,
+}
+
+/// 订阅级的运行参数，生成运行配置时覆盖全局 Clash 设置，未设置的项沿用全局设置
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct PrfRuntime {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mixed_port: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub log_level: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ipv6: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tun_stack: Option<String>,
+}
+
+const LOG_LEVELS: [&str; 5] = ["debug", "info", "warning", "error", "silent"];
+const TUN_STACKS: [&str; 3] = ["system", "gvisor", "mixed"];
+
+impl PrfRuntime {
+    /// 只检查取值本身，端口与其他端口是否冲突由 `feat::check_profile_ports` 检查
+    pub fn validate(&self) -> Result<()> {
+        if self.mixed_port == Some(0) {
+            bail!("mixed port must not be 0");
+        }
+        if let Some(level) = self.log_level.as_deref()
+            && !LOG_LEVELS.contains(&level)
+        {
+            bail!("invalid log level \"{level}\"");
+        }
+        if let Some(stack) = self.tun_stack.as_deref()
+            && !TUN_STACKS.iter().any(|s| s.eq_ignore_ascii_case(stack))
+        {
+            bail!("invalid tun stack \"{stack}\"");
+        }
+        Ok(())
+    }
 }
 
+/// 订阅级的节点处理，只作用于配置中直接列出的节点；代理集合的节点由内核拉取，
+/// 只能通过其 `filter` / `exclude-filter` 下发名称过滤
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct PrfNodeFilter {
+    /// 去掉类型、服务器、端口与凭据都相同的重复节点，保留第一个；
+    /// 仅作用于配置中直接列出的节点，代理集合中的节点由内核拉取，不参与去重
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dedupe: Option<bool>,
+    /// 只保留名称匹配的节点，关键字或正则，不区分大小写
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub include: Option<String>,
+    /// 排除名称匹配的节点
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exclude: Option<String>,
+    /// 只保留这些地区（国家代码）的节点，按名称判断，无法判断地区的节点会被排除
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub regions: Option<Vec<String>>,
+    /// 重命名模板，支持 `{name}`、`{flag}`、`{region}` 与 `{index}`，如 `{flag} {name}`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rename: Option<String>,
+}
+
+impl PrfNodeFilter {
+    pub fn validate(&self) -> Result<()> {
+        for pattern in [&self.include, &self.exclude].into_iter().flatten() {
+            if let Err(err) = regex::Regex::new(&format!("(?i){pattern}")) {
+                bail!("invalid node filter \"{pattern}\": {err}");
+            }
+        }
+        if let Some(rename) = self.rename.as_deref()
+            && !rename.contains("{name}")
+            && !rename.contains("{index}")
+        {
+            bail!("the rename template must contain {{name}} or {{index}}");
+        }
+        Ok(())
+    }
+}
+
+/// 可以作为扩展链中一层的配置项类型
+pub const LAYER_TYPES: [&str; 5] = ["rules", "proxies", "groups", "merge", "script"];
+
 impl PrfOption {
+    /// 下载远程订阅时使用的代理
+    pub const fn proxy_type(&self) -> ProxyType {
+        if matches!(self.self_proxy, Some(true)) {
+            ProxyType::Localhost
+        } else if matches!(self.with_proxy, Some(true)) {
+            ProxyType::System
+        } else {
+            ProxyType::None
+        }
+    }
+
     pub fn merge(one: Option<&Self>, other: Option<&Self>) -> Option<Self> {
         match (one, other) {
             (Some(a_ref), Some(b_ref)) => {
@@ -138,7 +279,13 @@ impl PrfOption {
                 result.rules = b_ref.rules.clone().or(result.rules);
                 result.proxies = b_ref.proxies.clone().or(result.proxies);
                 result.groups = b_ref.groups.clone().or(result.groups);
+                result.chain = b_ref.chain.clone().or(result.chain);
+                result.subscription_format = b_ref.subscription_format.or(result.subscription_format);
+                result.headers = b_ref.headers.clone().or(result.headers);
+                result.mirrors = b_ref.mirrors.clone().or(result.mirrors);
                 result.timeout_seconds = b_ref.timeout_seconds.or(result.timeout_seconds);
+                result.runtime = b_ref.runtime.clone().or(result.runtime);
+                result.nodes = b_ref.nodes.clone().or(result.nodes);
                 Some(result)
             }
             (Some(a_ref), None) => Some(a_ref.clone()),
@@ -193,6 +340,8 @@ impl PrfItem {
         let file = format!("{uid}.yaml").into();
         let opt_ref = option.as_ref();
         let update_interval = opt_ref.and_then(|o| o.update_interval);
+        let runtime = opt_ref.and_then(|o| o.runtime.clone());
+        let nodes = opt_ref.and_then(|o| o.nodes.clone());
         let mut merge = opt_ref.and_then(|o| o.merge.clone());
         let mut script = opt_ref.and_then(|o| o.script.clone());
         let mut rules = opt_ref.and_then(|o| o.rules.clone());
@@ -240,6 +389,8 @@ impl PrfItem {
                 rules,
                 proxies,
                 groups,
+                runtime,
+                nodes,
                 ..PrfOption::default()
             }),
             home: None,
@@ -256,11 +407,15 @@ impl PrfItem {
         desc: Option<&String>,
         option: Option<&PrfOption>,
     ) -> Result<Self> {
-        let with_proxy = option.is_some_and(|o| o.with_proxy.unwrap_or(false));
-        let self_proxy = option.is_some_and(|o| o.self_proxy.unwrap_or(false));
+        let proxy_type = option.map_or(ProxyType::None, PrfOption::proxy_type);
         let accept_invalid_certs = option.is_some_and(|o| o.danger_accept_invalid_certs.unwrap_or(false));
         let allow_auto_update = option.map(|o| o.allow_auto_update.unwrap_or(true));
         let user_agent = option.and_then(|o| o.user_agent.clone());
+        let subscription_format = option.and_then(|o| o.subscription_format);
+        let headers = option.and_then(|o| o.headers.clone());
+        let mirrors = option.and_then(|o| o.mirrors.clone());
+        let runtime = option.and_then(|o| o.runtime.clone());
+        let nodes = option.and_then(|o| o.nodes.clone());
         let update_interval = option.and_then(|o| o.update_interval);
         let timeout = option.and_then(|o| o.timeout_seconds).unwrap_or(20);
         let mut merge = option.and_then(|o| o.merge.clone());
@@ -269,20 +424,14 @@ impl PrfItem {
         let mut proxies = option.and_then(|o| o.proxies.clone());
         let mut groups = option.and_then(|o| o.groups.clone());
 
-        // 选择代理类型
-        let proxy_type = if self_proxy {
-            ProxyType::Localhost
-        } else if with_proxy {
-            ProxyType::System
-        } else {
-            ProxyType::None
+        let params = RequestParams {
+            proxy_type,
+            timeout_secs: Some(timeout),
+            user_agent: user_agent.as_deref(),
+            accept_invalid_certs,
+            headers: headers.as_ref(),
         };
-
-        // 使用网络管理器发送请求
-        let resp = match NetworkManager::new()
-            .get_with_interrupt(url, proxy_type, Some(timeout), user_agent.clone(), accept_invalid_certs)
-            .await
-        {
+        let resp = match Self::download_remote(url, mirrors.as_deref().unwrap_or_default(), &params).await {
             Ok(r) => r,
             Err(e) => {
                 tokio::time::sleep(Duration::from_millis(100)).await;
@@ -290,11 +439,6 @@ impl PrfItem {
             }
         };
 
-        let status_code = resp.status();
-        if !status_code.is_success() {
-            bail!("failed to fetch remote profile with status {status_code}")
-        }
-
         let header = resp.headers();
 
         // parse the Subscription UserInfo
@@ -371,8 +515,11 @@ impl PrfItem {
         // process the charset "UTF-8 with BOM"
         let data = data.trim_start_matches('\u{feff}');
 
+        // convert v2ray / sing-box / Surge / Quantumult X subscriptions
+        let data = converter::convert_subscription(data, subscription_format.unwrap_or_default())?;
+
         // check the data whether the valid yaml format
-        let yaml = serde_yaml_ng::from_str::<Mapping>(data).context("the remote profile data is invalid yaml")?;
+        let yaml = serde_yaml_ng::from_str::<Mapping>(&data).context("the remote profile data is invalid yaml")?;
 
         if !yaml.contains_key("proxies") && !yaml.contains_key("proxy-providers") {
             bail!("profile does not contain `proxies` or `proxy-providers`");
@@ -421,6 +568,11 @@ impl PrfItem {
                 proxies,
                 groups,
                 allow_auto_update,
+                subscription_format,
+                headers,
+                mirrors,
+                runtime,
+                nodes,
                 ..PrfOption::default()
             }),
             home,
@@ -429,6 +581,38 @@ impl PrfItem {
         })
     }
 
+    /// 依次尝试订阅地址与备用地址，返回第一个成功的响应
+    ///
+    /// 下载进度以 [`DOWNLOAD_PROGRESS_EVENT`] 事件推送，事件中的 `url` 始终是订阅的主地址
+    async fn download_remote(url: &str, mirrors: &[String], params: &RequestParams<'_>) -> Result<HttpResponse> {
+        let network = NetworkManager::new();
+        let mut last_err = None;
+        for source in std::iter::once(url).chain(mirrors.iter().map(String::as_str)) {
+            let on_progress = |progress: DownloadProgress| {
+                let _ = handle::Handle::app_handle().emit(
+                    DOWNLOAD_PROGRESS_EVENT,
+                    ProfileDownloadProgress {
+                        url,
+                        downloaded: progress.downloaded,
+                        total: progress.total,
+                    },
+                );
+            };
+            let err = match network.download_resumable(source, params, on_progress).await {
+                Ok(resp) if resp.status().is_success() => return Ok(resp),
+                Ok(resp) => anyhow::anyhow!("status {}", resp.status()),
+                Err(err) => err,
+            };
+            if source != url {
+                logging!(warn, Type::Config, "Profile mirror failed: {err}");
+            } else if !mirrors.is_empty() {
+                logging!(warn, Type::Config, "Profile url failed, trying mirrors: {err}");
+            }
+            last_err = Some(err);
+        }
+        Err(last_err.unwrap_or_else(|| anyhow::anyhow!("no download source")))
+    }
+
     /// ## Merge type (enhance)
     /// create the enhanced item by using `merge` rule
     pub fn from_merge(uid: Option<String>) -> Result<Self> {
@@ -562,6 +746,31 @@ impl PrfItem {
     pub fn current_groups(&self) -> Option<&String> {
         self.option.as_ref().and_then(|o| o.groups.as_ref())
     }
+
+    /// 获取订阅的扩展链，未显式设置时按默认顺序由各扩展项生成
+    pub fn current_chain(&self) -> Vec<PrfLayer> {
+        if let Some(chain) = self.option.as_ref().and_then(|o| o.chain.clone()) {
+            return chain;
+        }
+        [
+            self.current_rules(),
+            self.current_proxies(),
+            self.current_groups(),
+            self.current_merge(),
+            self.current_script(),
+        ]
+        .into_iter()
+        .flatten()
+        .map(|uid| PrfLayer {
+            uid: uid.clone(),
+            enabled: true,
+        })
+        .collect()
+    }
+}
+
+const fn default_layer_enabled() -> bool {
+    true
 }
 
 // 向前兼容，默认为订阅启用自动更新
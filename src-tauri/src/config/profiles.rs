@@ -1,10 +1,17 @@
-use super::{PrfOption, prfitem::PrfItem};
-use crate::utils::{
-    dirs::{self, PathBufExec as _},
-    help,
+use super::{
+    PrfOption,
+    prfitem::{LAYER_TYPES, PrfItem, PrfLayer, PrfRuntime},
+    versions,
+};
+use crate::{
+    core::secrets,
+    utils::{
+        dirs::{self, PathBufExec as _},
+        help,
+    },
 };
 use anyhow::{Context as _, Result, bail};
-use clash_verge_logging::{Type, logging};
+use clash_verge_logging::{Type, logging, logging_error};
 use serde::{Deserialize, Serialize};
 use serde_yaml_ng::Mapping;
 use smartstring::alias::String;
@@ -71,7 +78,12 @@ impl IProfiles {
                         item.uid = Some(help::get_uid("d").into());
                     }
                 }
-                profiles
+                // 解析失败时保留引用，保存时不会覆盖凭据库中的值
+                let unresolved = profiles.clone();
+                secrets::resolve_profiles(profiles).await.unwrap_or_else(|err| {
+                    logging!(error, Type::Config, "Failed to resolve keychain references: {err}");
+                    unresolved
+                })
             }
             Err(err) => {
                 logging!(error, Type::Config, "{err}");
@@ -81,7 +93,13 @@ impl IProfiles {
     }
 
     pub async fn save_file(&self) -> Result<()> {
-        help::save_yaml(&dirs::profiles_path()?, self, Some("# Profiles Config for Clash Verge")).await
+        let profiles = secrets::externalize_profiles(self).await?;
+        help::save_yaml(
+            &dirs::profiles_path()?,
+            &profiles,
+            Some("# Profiles Config for Clash Verge"),
+        )
+        .await
     }
 
     /// 只修改current，valid和chain
@@ -104,6 +122,12 @@ impl IProfiles {
         self.current.as_ref()
     }
 
+    /// 当前订阅覆盖的运行参数
+    pub fn current_runtime(&self) -> Option<&PrfRuntime> {
+        let item = self.get_item(self.current.as_ref()?).ok()?;
+        item.option.as_ref()?.runtime.as_ref()
+    }
+
     /// get items ref
     pub const fn get_items(&self) -> Option<&Vec<PrfItem>> {
         self.items.as_ref()
@@ -126,6 +150,34 @@ impl IProfiles {
         bail!("failed to get the profile item \"uid:{}\"", uid_str);
     }
 
+    /// 该配置是否参与当前运行配置的生成：全局扩展、当前订阅或其附属的扩展文件；
+    /// 设置了扩展链时附属文件不再生效，改为检查链中启用的层
+    pub fn is_in_current_chain(&self, uid: &str) -> bool {
+        if matches!(uid, "Merge" | "Script") {
+            return true;
+        }
+        let Some(current) = self.current.as_deref() else {
+            return false;
+        };
+        current == uid
+            || self
+                .get_item(current)
+                .ok()
+                .and_then(|item| item.option.as_ref())
+                .is_some_and(|option| match option.chain.as_ref() {
+                    Some(chain) => chain.iter().any(|layer| layer.enabled && layer.uid.as_str() == uid),
+                    None => [
+                        &option.merge,
+                        &option.script,
+                        &option.rules,
+                        &option.proxies,
+                        &option.groups,
+                    ]
+                    .iter()
+                    .any(|linked| linked.as_deref() == Some(uid)),
+                })
+    }
+
     pub fn get_item_arc(&self, uid: &str) -> Option<Arc<PrfItem>> {
         self.items.as_ref().and_then(|items| {
             items
@@ -216,7 +268,14 @@ impl IProfiles {
                 patch!(each, item, selected);
                 patch!(each, item, extra);
                 patch!(each, item, updated);
+                let chain = each.option.as_ref().and_then(|o| o.chain.clone());
                 patch!(each, item, option);
+                // 编辑订阅选项时不会携带扩展链，沿用原有设置
+                if let Some(option) = each.option.as_mut()
+                    && option.chain.is_none()
+                {
+                    option.chain = chain;
+                }
 
                 self.items = Some(items);
                 return self.save_file().await;
@@ -227,6 +286,42 @@ impl IProfiles {
         bail!("failed to find the profile item \"uid:{uid}\"")
     }
 
+    /// 设置订阅的扩展链，`None` 表示恢复默认顺序；只修改内存中的数据，由调用方保存
+    pub fn set_item_chain(&mut self, uid: &String, chain: Option<Vec<PrfLayer>>) -> Result<()> {
+        if let Some(layers) = chain.as_ref() {
+            for layer in layers {
+                let item = self.get_item(&layer.uid)?;
+                let itype = item.itype.as_deref().unwrap_or_default();
+                if !LAYER_TYPES.contains(&itype) {
+                    bail!(
+                        "profile item \"{}\" of type \"{itype}\" cannot be a chain layer",
+                        layer.uid
+                    );
+                }
+            }
+        }
+
+        let item = self
+            .items
+            .as_mut()
+            .and_then(|items| items.iter_mut().find(|each| each.uid.as_ref() == Some(uid)))
+            .ok_or_else(|| anyhow::anyhow!("failed to find the profile item \"uid:{uid}\""))?;
+        item.option.get_or_insert_with(PrfOption::default).chain = chain;
+        Ok(())
+    }
+
+    /// 被其他订阅的扩展链引用的配置
+    fn is_chain_layer_of_other(&self, uid: &str, owner: &str) -> bool {
+        self.items.iter().flatten().any(|item| {
+            item.uid.as_deref() != Some(owner)
+                && item
+                    .option
+                    .as_ref()
+                    .and_then(|option| option.chain.as_ref())
+                    .is_some_and(|chain| chain.iter().any(|layer| layer.uid.as_str() == uid))
+        })
+    }
+
     /// be used to update the remote item
     /// only patch `updated` `extra` `file_data`
     pub async fn update_item(&mut self, uid: &String, item: &mut PrfItem) -> Result<()> {
@@ -257,6 +352,7 @@ impl IProfiles {
                         each.file = Some(file.clone());
 
                         let path = dirs::app_profiles_dir()?.join(file.as_str());
+                        logging_error!(Type::Config, versions::snapshot_profile_file(uid, &path).await);
 
                         fs::write(&path, file_data.as_bytes())
                             .await
@@ -276,12 +372,20 @@ impl IProfiles {
     pub async fn delete_item(&mut self, uid: &String) -> Result<bool> {
         let current = self.current.as_ref().unwrap_or(uid);
         let current = current.clone();
+        let affects_current = self.is_in_current_chain(uid);
         let item = self.get_item(uid)?;
-        let merge_uid = item.option.as_ref().and_then(|e| e.merge.clone());
-        let script_uid = item.option.as_ref().and_then(|e| e.script.clone());
-        let rules_uid = item.option.as_ref().and_then(|e| e.rules.clone());
-        let proxies_uid = item.option.as_ref().and_then(|e| e.proxies.clone());
-        let groups_uid = item.option.as_ref().and_then(|e| e.groups.clone());
+        // 附属的扩展文件被其他订阅用作扩展链的层时保留
+        let linked = |linked: Option<&String>| {
+            linked
+                .filter(|linked| !self.is_chain_layer_of_other(linked, uid))
+                .cloned()
+        };
+        let option = item.option.as_ref();
+        let merge_uid = linked(option.and_then(|e| e.merge.as_ref()));
+        let script_uid = linked(option.and_then(|e| e.script.as_ref()));
+        let rules_uid = linked(option.and_then(|e| e.rules.as_ref()));
+        let proxies_uid = linked(option.and_then(|e| e.proxies.as_ref()));
+        let groups_uid = linked(option.and_then(|e| e.groups.as_ref()));
         let mut items = self.items.take().unwrap_or_default();
 
         // remove the main item (if exists) and delete its file
@@ -305,6 +409,36 @@ impl IProfiles {
         if let Some(file) = Self::take_item_file_by_uid(&mut items, groups_uid.clone()) {
             let _ = dirs::app_profiles_dir()?.join(file.as_str()).remove_if_exists().await;
         }
+        for versioned in [
+            Some(uid.clone()),
+            merge_uid,
+            script_uid,
+            rules_uid,
+            proxies_uid,
+            groups_uid,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            logging_error!(Type::Config, versions::remove_profile_versions(&versioned).await);
+        }
+
+        // 从其他订阅的扩展链中移除已删除的层
+        let removed: Vec<&String> = [uid]
+            .into_iter()
+            .chain(
+                [&merge_uid, &script_uid, &rules_uid, &proxies_uid, &groups_uid]
+                    .into_iter()
+                    .flatten(),
+            )
+            .collect();
+        for chain in items
+            .iter_mut()
+            .filter_map(|item| item.option.as_mut().and_then(|option| option.chain.as_mut()))
+        {
+            chain.retain(|layer| !removed.contains(&&layer.uid));
+        }
+
         // delete the original uid
         if current == *uid {
             self.current = None;
@@ -318,26 +452,33 @@ impl IProfiles {
 
         self.items = Some(items);
         self.save_file().await?;
-        Ok(current == *uid)
+        Ok(current == *uid || affects_current)
     }
 
     /// 获取current指向的订阅内容
     pub async fn current_mapping(&self) -> Result<Mapping> {
-        match (self.current.as_ref(), self.items.as_ref()) {
-            (Some(current), Some(items)) => {
-                if let Some(item) = items.iter().find(|e| e.uid.as_ref() == Some(current)) {
-                    let file_path = match item.file.as_ref() {
-                        Some(file) => dirs::app_profiles_dir()?.join(file.as_str()),
-                        None => bail!("failed to get the file field"),
-                    };
-                    return help::read_mapping(&file_path).await;
-                }
-                bail!("failed to find the current profile \"uid:{current}\"");
-            }
-            _ => Ok(Mapping::new()),
+        match self.current.as_ref() {
+            Some(current) => self.profile_mapping(current).await,
+            None => Ok(Mapping::new()),
         }
     }
 
+    /// 读取指定订阅的配置内容
+    pub async fn profile_mapping(&self, uid: &String) -> Result<Mapping> {
+        let Some(item) = self
+            .items
+            .as_ref()
+            .and_then(|items| items.iter().find(|e| e.uid.as_ref() == Some(uid)))
+        else {
+            bail!("failed to find the profile \"uid:{uid}\"");
+        };
+        let file_path = match item.file.as_ref() {
+            Some(file) => dirs::app_profiles_dir()?.join(file.as_str()),
+            None => bail!("failed to get the file field"),
+        };
+        help::read_mapping(&file_path).await
+    }
+
     /// 判断profile是否是current指向的
     pub fn is_current_profile_index(&self, index: &String) -> bool {
         self.current.as_ref() == Some(index)
@@ -610,3 +751,14 @@ pub async fn profiles_draft_update_item_safe(index: &String, item: &mut PrfItem)
         })
         .await
 }
+
+pub async fn profiles_set_chain_safe(uid: &String, chain: Option<Vec<PrfLayer>>) -> Result<()> {
+    Config::profiles()
+        .await
+        .with_data_modify(|mut profiles| async move {
+            profiles.set_item_chain(uid, chain)?;
+            profiles.save_file().await?;
+            Ok((profiles, ()))
+        })
+        .await
+}
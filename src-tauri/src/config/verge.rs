@@ -1,7 +1,8 @@
 use crate::config::Config;
 use crate::{
     config::{DEFAULT_PAC, deserialize_encrypted, serialize_encrypted},
-    utils::{dirs, help, i18n},
+    core::{backend::CoreBackend, network_monitor::NetworkKind, secrets},
+    utils::{dirs, help, i18n, instance},
 };
 use anyhow::Result;
 use clash_verge_logging::{Type, logging};
@@ -217,7 +218,7 @@ pub struct IVerge {
     )]
     pub webdav_password: Option<String>,
 
-    #[serde(skip)]
+    /// 在托盘标题与提示中显示实时网速
     pub enable_tray_speed: Option<bool>,
 
     // pub enable_tray_icon: Option<bool>,
@@ -245,6 +246,162 @@ pub struct IVerge {
     /// Discord Application ID (optional, uses default if not set)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub discord_app_id: Option<String>,
+
+    /// 记录连接开启/关闭事件到本地日志
+    pub enable_connection_log: Option<bool>,
+
+    /// 内核崩溃后阻断除内核自身以外的出站流量，直到关闭该功能或退出应用
+    pub enable_kill_switch: Option<bool>,
+
+    /// 用户注册的自定义内核，二进制存放于 cores 目录
+    pub custom_cores: Option<Vec<ICustomCore>>,
+
+    /// 订阅更新后对部分节点做延迟测试
+    pub enable_profile_health_delay_test: Option<bool>,
+
+    /// GeoIP/GeoSite 数据库下载镜像，按顺序尝试，为空时使用内置镜像
+    pub geo_mirrors: Option<Vec<String>>,
+
+    /// 应用系统代理的网络服务（macOS）或连接（Windows），为空时应用到全部
+    pub sysproxy_interfaces: Option<Vec<String>>,
+
+    /// 定期检查经代理访问时的出口 IP，变化时发送事件
+    pub enable_exit_ip_monitor: Option<bool>,
+
+    /// 出口 IP 检查间隔（分钟）
+    pub exit_ip_monitor_interval: Option<u64>,
+
+    /// 定时任务，例如定时切换代理模式或关闭系统代理
+    pub schedules: Option<Vec<ISchedule>>,
+
+    /// 按网络环境执行的规则，按顺序匹配第一条
+    pub network_rules: Option<Vec<INetworkRule>>,
+
+    /// 托盘图标主题，对应 tray-themes 目录下的子目录，为空时使用内置图标
+    pub tray_icon_theme: Option<String>,
+
+    /// 系统通知按类别开关，未设置的类别默认开启
+    pub notification_prefs: Option<INotificationPrefs>,
+
+    /// 事件回调地址列表
+    pub webhooks: Option<Vec<IWebhook>>,
+
+    /// 通过 Telegram 机器人推送事件通知
+    pub enable_telegram_notify: Option<bool>,
+
+    /// Telegram 机器人 token
+    pub telegram_bot_token: Option<String>,
+
+    /// 接收通知的 Telegram 会话 ID，同时只响应该会话发送的命令
+    pub telegram_chat_id: Option<String>,
+
+    /// 响应 Telegram 机器人命令（/status、/switch）
+    pub enable_telegram_commands: Option<bool>,
+
+    /// 备份加密口令，设置后上传到 WebDAV 的备份以此加密 (加密存储)
+    #[serde(
+        serialize_with = "serialize_encrypted",
+        deserialize_with = "deserialize_encrypted",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub backup_password: Option<String>,
+
+    /// 按 auto_backup_interval_hours 定时上传备份到 WebDAV
+    pub enable_webdav_backup_schedule: Option<bool>,
+
+    /// 允许局域网内已配对的设备发现本机并同步配置
+    pub enable_lan_sync: Option<bool>,
+
+    /// 局域网同步服务端口
+    pub lan_sync_port: Option<u16>,
+
+    /// 本机在局域网同步中的设备标识，首次使用时生成
+    pub lan_sync_device_id: Option<String>,
+
+    /// 已配对的局域网同步设备 (加密存储)
+    #[serde(
+        serialize_with = "serialize_encrypted",
+        deserialize_with = "deserialize_encrypted",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub lan_sync_peers: Option<Vec<ILanSyncPeer>>,
+
+    /// 本机在局域网同步中使用的 TLS 证书与私钥 (加密存储)
+    #[serde(
+        serialize_with = "serialize_encrypted",
+        deserialize_with = "deserialize_encrypted",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub lan_sync_identity: Option<ILanSyncIdentity>,
+
+    /// 开启本地 HTTP 控制接口
+    pub enable_control_api: Option<bool>,
+
+    /// 本地 HTTP 控制接口端口
+    pub control_api_port: Option<u16>,
+
+    /// 本地 HTTP 控制接口的访问令牌 (加密存储)
+    #[serde(
+        serialize_with = "serialize_encrypted",
+        deserialize_with = "deserialize_encrypted",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub control_api_token: Option<String>,
+
+    /// 应用日志文件格式，text 或 json，重启后生效
+    pub app_log_format: Option<String>,
+
+    /// 崩溃报告上传地址，为空时不允许上传
+    pub crash_report_url: Option<String>,
+
+    /// 应用更新通道：stable / beta / nightly
+    pub update_channel: Option<String>,
+
+    /// 用户选择稍后提醒的版本
+    pub update_deferred_version: Option<String>,
+
+    /// 在此时间（秒级时间戳）之前不再提醒该版本
+    pub update_deferred_until: Option<i64>,
+
+    /// 外部控制器只接受应用自身页面的跨域请求
+    pub restrict_controller_cors: Option<bool>,
+
+    /// 收藏的代理组与节点名称，显示在托盘菜单中
+    pub favorites: Option<Vec<String>>,
+
+    /// Discord 状态行模板，如 `{node} • {latency}`，未设置时使用内置格式
+    pub discord_state_template: Option<String>,
+
+    /// 按 Wi-Fi 名称分别记忆各代理组选择的节点
+    pub selection_per_network: Option<bool>,
+
+    /// 测速服务器，第一个为默认；为空时使用内置的 Cloudflare 测速地址
+    pub speedtest_servers: Option<Vec<ISpeedtestServer>>,
+
+    /// 开启系统代理时同步设置 WinHTTP 代理（仅 Windows）
+    pub enable_winhttp_proxy_sync: Option<bool>,
+
+    /// Linux 系统代理的设置方式：auto / gnome / kde / environment
+    pub linux_proxy_backend: Option<String>,
+
+    /// 开机自启后延迟启动内核的秒数，等待网络就绪
+    pub auto_launch_delay: Option<u64>,
+
+    /// 托盘提示中显示内核内存占用
+    pub enable_tray_memory: Option<bool>,
+
+    /// 内核内存占用超过该值（MB）时发送 `core-memory-warning` 事件，未设置时不检查
+    pub core_memory_warn_mb: Option<u64>,
+
+    /// 内核进程优先级：low / below_normal / normal / above_normal / high
+    pub core_priority: Option<String>,
+
+    /// 内核进程绑定的 CPU 核心编号，为空时不限制
+    pub core_cpu_affinity: Option<Vec<u32>>,
 }
 
 #[derive(Default, Debug, Clone, Deserialize, Serialize)]
@@ -255,6 +412,116 @@ pub struct IVergeTestItem {
     pub url: Option<String>,
 }
 
+/// 自定义内核，`path` 为复制到 cores 目录后的路径
+#[derive(Default, Debug, Clone, Deserialize, Serialize)]
+pub struct ICustomCore {
+    pub name: String,
+    pub path: String,
+    /// 注册时根据版本输出识别，旧配置缺省为 mihomo
+    #[serde(default)]
+    pub backend: CoreBackend,
+}
+
+/// 定时任务，`cron` 为五段式表达式：分 时 日 月 周
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ISchedule {
+    pub id: String,
+    pub name: Option<String>,
+    pub cron: String,
+    pub action: ScheduleAction,
+    /// 缺省为启用
+    pub enabled: Option<bool>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum ScheduleAction {
+    /// 切换代理模式：rule / global / direct
+    ClashMode(String),
+    SystemProxy(bool),
+    TunMode(bool),
+    /// 在代理组中切换到指定国家/地区延迟最低的节点
+    SelectRegion {
+        group: String,
+        country: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEventKind {
+    ProfileUpdated,
+    NodeSwitched,
+    CoreRestarted,
+    TrafficThreshold,
+}
+
+/// 事件回调，`events` 为空时订阅全部事件
+#[derive(Default, Debug, Clone, Deserialize, Serialize)]
+pub struct IWebhook {
+    #[serde(default)]
+    pub id: String,
+    pub url: String,
+    #[serde(default)]
+    pub events: Vec<WebhookEventKind>,
+    /// 设置后使用 HMAC-SHA256 对请求体签名
+    pub secret: Option<String>,
+    /// 本次运行的总流量（字节）超过该值时触发 `traffic_threshold`
+    pub traffic_threshold: Option<u64>,
+    pub enabled: Option<bool>,
+}
+
+/// 已配对的局域网同步设备，`fingerprint` 为配对时确认的对端证书 SHA-256 指纹
+#[derive(Default, Debug, Clone, Deserialize, Serialize)]
+pub struct ILanSyncPeer {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub fingerprint: String,
+}
+
+/// 局域网同步的自签名证书与 PKCS#8 私钥，均为 base64 编码的 DER
+#[derive(Default, Debug, Clone, Deserialize, Serialize)]
+pub struct ILanSyncIdentity {
+    pub certificate: String,
+    pub private_key: String,
+}
+
+/// 测速服务器，未设置上传地址时跳过上传测试，未设置延迟地址时使用默认的延迟测试地址
+#[derive(Default, Debug, Clone, Deserialize, Serialize)]
+pub struct ISpeedtestServer {
+    pub name: String,
+    pub download_url: String,
+    pub upload_url: Option<String>,
+    pub latency_url: Option<String>,
+}
+
+/// 各类系统通知的开关，`None` 表示开启
+#[derive(Default, Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct INotificationPrefs {
+    pub core_crash: Option<bool>,
+    pub profile_update_failed: Option<bool>,
+    pub subscription_expiring: Option<bool>,
+    pub data_cap: Option<bool>,
+    pub exit_ip_change: Option<bool>,
+}
+
+/// 网络环境规则，设置的条件均满足时执行 `actions`
+#[derive(Default, Debug, Clone, Deserialize, Serialize)]
+pub struct INetworkRule {
+    pub name: Option<String>,
+    pub ssid: Option<String>,
+    pub interface: Option<String>,
+    pub kind: Option<NetworkKind>,
+    /// 是否连接了其他 VPN
+    pub vpn: Option<bool>,
+    pub captive_portal: Option<bool>,
+    #[serde(default)]
+    pub actions: Vec<ScheduleAction>,
+    /// 缺省为启用
+    pub enabled: Option<bool>,
+}
+
 #[derive(Default, Debug, Clone, Deserialize, Serialize)]
 pub struct IVergeTheme {
     pub primary_color: Option<String>,
@@ -279,7 +546,7 @@ impl IVerge {
     pub async fn validate_and_fix_config() -> Result<()> {
         let config_path = dirs::verge_path()?;
         let mut config = match help::read_yaml::<Self>(&config_path).await {
-            Ok(config) => config,
+            Ok(config) => secrets::resolve_verge(config).await?,
             Err(_) => Self::template(),
         };
 
@@ -287,7 +554,7 @@ impl IVerge {
 
         if let Some(ref core) = config.clash_core {
             let core_str = core.trim();
-            if core_str.is_empty() || !Self::VALID_CLASH_CORES.contains(&core_str) {
+            if core_str.is_empty() || !config.is_valid_clash_core(core_str) {
                 logging!(
                     warn,
                     Type::Config,
@@ -310,7 +577,7 @@ impl IVerge {
         // 修正后保存配置
         if needs_fix {
             logging!(info, Type::Config, "正在保存修正后的配置文件...");
-            help::save_yaml(&config_path, &config, Some("# Clash Verge Config")).await?;
+            config.save_file().await?;
             logging!(info, Type::Config, "配置文件修正完成，需要重新加载配置");
 
             Self::reload_config_after_fix(config).await?;
@@ -339,6 +606,15 @@ impl IVerge {
         Ok(())
     }
 
+    /// 内置内核或已注册的自定义内核
+    pub fn is_valid_clash_core(&self, core: &str) -> bool {
+        Self::VALID_CLASH_CORES.contains(&core) || self.get_custom_core(core).is_some()
+    }
+
+    pub fn get_custom_core(&self, core: &str) -> Option<&ICustomCore> {
+        self.custom_cores.as_ref()?.iter().find(|c| c.name == core)
+    }
+
     pub fn get_valid_clash_core(&self) -> String {
         self.clash_core.clone().unwrap_or_else(|| "verge-mihomo".into())
     }
@@ -366,7 +642,12 @@ impl IVerge {
                     {
                         config.start_page = Some(String::from("/"));
                     }
-                    config
+                    // 解析失败时保留引用，保存时不会覆盖凭据库中的值
+                    let unresolved = config.clone();
+                    secrets::resolve_verge(config).await.unwrap_or_else(|err| {
+                        logging!(error, Type::Config, "Failed to resolve keychain references: {err}");
+                        unresolved
+                    })
                 }
                 Err(err) => {
                     logging!(error, Type::Config, "{err}");
@@ -410,17 +691,17 @@ impl IVerge {
             pac_file_content: Some(DEFAULT_PAC.into()),
             proxy_host: Some("127.0.0.1".into()),
             #[cfg(not(target_os = "windows"))]
-            verge_redir_port: Some(7895),
+            verge_redir_port: Some(instance::offset_port(7895)),
             #[cfg(not(target_os = "windows"))]
             verge_redir_enabled: Some(false),
             #[cfg(target_os = "linux")]
-            verge_tproxy_port: Some(7896),
+            verge_tproxy_port: Some(instance::offset_port(7896)),
             #[cfg(target_os = "linux")]
             verge_tproxy_enabled: Some(false),
-            verge_mixed_port: Some(7897),
-            verge_socks_port: Some(7898),
+            verge_mixed_port: Some(instance::offset_port(7897)),
+            verge_socks_port: Some(instance::offset_port(7898)),
             verge_socks_enabled: Some(false),
-            verge_port: Some(7899),
+            verge_port: Some(instance::offset_port(7899)),
             verge_http_enabled: Some(false),
             enable_proxy_guard: Some(false),
             use_default_bypass: Some(true),
@@ -446,13 +727,22 @@ impl IVerge {
             enable_external_controller: Some(false),
             enable_discord_rpc: Some(false),
             discord_app_id: None,
+            enable_connection_log: Some(false),
+            enable_kill_switch: Some(false),
+            enable_profile_health_delay_test: Some(false),
+            enable_webdav_backup_schedule: Some(false),
+            enable_lan_sync: Some(false),
+            lan_sync_port: Some(instance::offset_port(crate::constants::network::ports::LAN_SYNC)),
+            enable_control_api: Some(false),
+            control_api_port: Some(instance::offset_port(crate::constants::network::ports::CONTROL_API)),
             ..Self::default()
         }
     }
 
-    /// Save IVerge App Config
+    /// Save IVerge App Config, sensitive fields are moved to the system keychain
     pub async fn save_file(&self) -> Result<()> {
-        help::save_yaml(&dirs::verge_path()?, &self, Some("# Clash Verge Config")).await
+        let config = secrets::externalize_verge(self).await?;
+        help::save_yaml(&dirs::verge_path()?, &config, Some("# Clash Verge Config")).await
     }
 
     /// patch verge config
@@ -546,26 +836,61 @@ impl IVerge {
         patch!(enable_external_controller);
         patch!(enable_discord_rpc);
         patch!(discord_app_id);
+        patch!(enable_connection_log);
+        patch!(enable_kill_switch);
+        patch!(custom_cores);
+        patch!(enable_profile_health_delay_test);
+        patch!(geo_mirrors);
+        patch!(sysproxy_interfaces);
+        patch!(enable_exit_ip_monitor);
+        patch!(exit_ip_monitor_interval);
+        patch!(schedules);
+        patch!(network_rules);
+        patch!(tray_icon_theme);
+        patch!(notification_prefs);
+        patch!(webhooks);
+        patch!(enable_telegram_notify);
+        patch!(telegram_bot_token);
+        patch!(telegram_chat_id);
+        patch!(enable_telegram_commands);
+        patch!(backup_password);
+        patch!(enable_webdav_backup_schedule);
+        patch!(enable_lan_sync);
+        patch!(lan_sync_port);
+        patch!(lan_sync_device_id);
+        patch!(lan_sync_peers);
+        patch!(lan_sync_identity);
+        patch!(enable_control_api);
+        patch!(control_api_port);
+        patch!(control_api_token);
+        patch!(app_log_format);
+        patch!(crash_report_url);
+        patch!(update_channel);
+        patch!(update_deferred_version);
+        patch!(update_deferred_until);
+        patch!(restrict_controller_cors);
+        patch!(favorites);
+        patch!(discord_state_template);
+        patch!(selection_per_network);
+        patch!(speedtest_servers);
+        patch!(enable_winhttp_proxy_sync);
+        patch!(linux_proxy_backend);
+        patch!(auto_launch_delay);
+        patch!(enable_tray_memory);
+        patch!(core_memory_warn_mb);
+        patch!(core_priority);
+        patch!(core_cpu_affinity);
     }
 
-    pub const fn get_singleton_port() -> u16 {
-        crate::constants::network::ports::SINGLETON_SERVER
+    pub fn get_singleton_port() -> u16 {
+        instance::offset_port(crate::constants::network::ports::SINGLETON_SERVER)
     }
 
     /// 获取日志等级
     pub fn get_log_level(&self) -> LevelFilter {
-        if let Some(level) = self.app_log_level.as_ref() {
-            match level.to_lowercase().as_str() {
-                "silent" => LevelFilter::Off,
-                "error" => LevelFilter::Error,
-                "warn" => LevelFilter::Warn,
-                "info" => LevelFilter::Info,
-                "debug" => LevelFilter::Debug,
-                "trace" => LevelFilter::Trace,
-                _ => LevelFilter::Info,
-            }
-        } else {
-            LevelFilter::Info
-        }
+        self.app_log_level
+            .as_deref()
+            .and_then(crate::utils::logger::parse_level)
+            .unwrap_or(LevelFilter::Info)
     }
 }
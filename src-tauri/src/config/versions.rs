@@ -0,0 +1,104 @@
+//! 配置文件的历史版本
+//!
+//! 订阅更新、编辑器保存与回滚前，会把被覆盖的文件复制到 `profiles/.versions/<uid>/`，
+//! 每个配置（包括其附属的 merge / script 等扩展文件）各自保留最近的若干份。
+
+use crate::utils::dirs;
+use anyhow::{Context as _, Result, bail};
+use serde::Serialize;
+use smartstring::alias::String;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+/// 每个配置保留的历史版本数量
+pub const MAX_PROFILE_VERSIONS: usize = 10;
+const VERSIONS_DIR: &str = ".versions";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProfileVersion {
+    /// 版本号，即保存时的毫秒时间戳
+    pub version: String,
+    /// 保存时间（秒）
+    pub created: i64,
+    pub size: u64,
+}
+
+fn versions_dir(uid: &str) -> Result<PathBuf> {
+    if uid.is_empty() || uid.contains(['/', '\\', '.']) {
+        bail!("invalid profile uid: {uid}");
+    }
+    Ok(dirs::app_profiles_dir()?.join(VERSIONS_DIR).join(uid))
+}
+
+fn version_path(uid: &str, version: &str) -> Result<PathBuf> {
+    if version.is_empty() || !version.bytes().all(|b| b.is_ascii_digit()) {
+        bail!("invalid profile version: {version}");
+    }
+    Ok(versions_dir(uid)?.join(version))
+}
+
+/// 按时间倒序列出某个配置的历史版本
+pub async fn list_profile_versions(uid: &str) -> Result<Vec<ProfileVersion>> {
+    let dir = versions_dir(uid)?;
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut versions = Vec::new();
+    let mut entries = fs::read_dir(&dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let Ok(millis) = name.parse::<i64>() else {
+            continue;
+        };
+        let size = entry.metadata().await.map(|m| m.len()).unwrap_or_default();
+        versions.push(ProfileVersion {
+            version: name.into(),
+            created: millis / 1000,
+            size,
+        });
+    }
+    versions.sort_by(|a, b| b.version.cmp(&a.version));
+    Ok(versions)
+}
+
+/// 读取某个历史版本的内容
+pub async fn read_profile_version(uid: &str, version: &str) -> Result<String> {
+    let path = version_path(uid, version)?;
+    let content = fs::read_to_string(&path)
+        .await
+        .with_context(|| format!("profile version {version} not found"))?;
+    Ok(content.into())
+}
+
+/// 覆盖配置文件前保存当前内容；与最新版本相同时跳过，超出数量上限的旧版本会被删除
+pub async fn snapshot_profile_file(uid: &str, file: &Path) -> Result<()> {
+    let Ok(content) = fs::read(file).await else {
+        return Ok(());
+    };
+    let dir = versions_dir(uid)?;
+    let versions = list_profile_versions(uid).await?;
+    if let Some(latest) = versions.first()
+        && fs::read(dir.join(latest.version.as_str())).await.ok().as_ref() == Some(&content)
+    {
+        return Ok(());
+    }
+
+    fs::create_dir_all(&dir).await?;
+    let version = chrono::Local::now().timestamp_millis().to_string();
+    fs::write(dir.join(&version), &content).await?;
+
+    for stale in versions.iter().skip(MAX_PROFILE_VERSIONS - 1) {
+        let _ = fs::remove_file(dir.join(stale.version.as_str())).await;
+    }
+    Ok(())
+}
+
+/// 删除配置时一并清理其历史版本
+pub async fn remove_profile_versions(uid: &str) -> Result<()> {
+    let dir = versions_dir(uid)?;
+    if dir.exists() {
+        fs::remove_dir_all(dir).await?;
+    }
+    Ok(())
+}
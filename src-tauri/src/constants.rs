@@ -1,8 +1,6 @@
 use std::time::Duration;
 
 pub mod network {
-    pub const DEFAULT_EXTERNAL_CONTROLLER: &str = "127.0.0.1:9097";
-
     pub mod ports {
         #[cfg(not(target_os = "windows"))]
         pub const DEFAULT_REDIR: u16 = 7895;
@@ -11,11 +9,16 @@ pub mod network {
         pub const DEFAULT_MIXED: u16 = 7897;
         pub const DEFAULT_SOCKS: u16 = 7898;
         pub const DEFAULT_HTTP: u16 = 7899;
+        pub const DEFAULT_CONTROLLER: u16 = 9097;
+        pub const SINGBOX_CONTROLLER: u16 = 9098;
 
         #[cfg(not(feature = "verge-dev"))]
         pub const SINGLETON_SERVER: u16 = 33331;
         #[cfg(feature = "verge-dev")]
         pub const SINGLETON_SERVER: u16 = 11233;
+
+        pub const LAN_SYNC: u16 = 33341;
+        pub const CONTROL_API: u16 = 33342;
     }
 }
 
@@ -41,6 +44,9 @@ pub mod files {
     pub const RUNTIME_CONFIG: &str = "clash-verge.yaml";
     pub const CHECK_CONFIG: &str = "clash-verge-check.yaml";
     pub const DNS_CONFIG: &str = "dns_config.yaml";
+    pub const MANAGED_RULES: &str = "managed_rules.yaml";
+    /// 内核的持久化缓存（bbolt）
+    pub const CORE_CACHE: &str = "cache.db";
     pub const WINDOW_STATE: &str = "window_state.json";
 }
 
@@ -0,0 +1,238 @@
+//! 应用自身的更新
+//!
+//! 按 `update_channel` 选择发布通道的 `update.json`，依次尝试镜像与 GitHub，下载的安装包由 updater 插件用
+//! `tauri.conf.json` 中的公钥校验签名，校验失败不会安装。检查结果会缓存，安装时直接使用，
+//! 不再重复请求；用户选择“稍后提醒”后，在到期前检查不再提示同一版本。
+//! 下载进度通过 `app-update-progress` 事件推送。
+//!
+//! 预发布构建的版本号带提交哈希（如 `2.4.3-alpha-abc1234`），无法按 semver 比较先后，
+//! 因此由 [`is_update`] 判断：基础版本更高，或同一基础版本下换了一个构建时才视为更新。
+
+use crate::{
+    config::{Config, IVerge},
+    core::handle,
+    feat, singleton,
+};
+use anyhow::{Result, bail};
+use clash_verge_logging::{Type, logging};
+use parking_lot::Mutex;
+use serde::Serialize;
+use smartstring::alias::String;
+use tauri::{Emitter as _, Url};
+use tauri_plugin_updater::{Update, UpdaterExt as _};
+
+const UPDATER_BASE: &str = "https://github.com/clash-verge-rev/clash-verge-rev/releases/download";
+/// 与 `tauri.conf.json` 中的稳定通道一致，镜像使用 `update-proxy.json`，其中的下载地址同样经过镜像
+const UPDATER_MIRRORS: [&str; 2] = ["https://download.clashverge.dev/", "https://gh-proxy.com/"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpdateChannel {
+    Stable,
+    Beta,
+}
+
+impl UpdateChannel {
+    /// autobuild 不发布 `update.json`，`nightly` 沿用预发布通道
+    pub fn parse(value: &str) -> Result<Self> {
+        match value {
+            "stable" => Ok(Self::Stable),
+            "beta" | "nightly" => Ok(Self::Beta),
+            other => bail!("unsupported update channel: {other}"),
+        }
+    }
+
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Stable => "stable",
+            Self::Beta => "beta",
+        }
+    }
+
+    /// 稳定通道沿用配置文件中的地址，预发布通道使用 `updater-alpha` 标签下的文件
+    fn endpoints(self) -> Option<Vec<std::string::String>> {
+        match self {
+            Self::Stable => None,
+            Self::Beta => {
+                let release = format!("{UPDATER_BASE}/updater-alpha");
+                let mut endpoints: Vec<_> = UPDATER_MIRRORS
+                    .iter()
+                    .map(|mirror| format!("{mirror}{release}/update-proxy.json"))
+                    .collect();
+                endpoints.push(format!("{release}/update.json"));
+                Some(endpoints)
+            }
+        }
+    }
+}
+
+/// 按 (主, 次, 修订) 与预发布标识判断远端版本是否为更新
+fn is_update(current: ((u64, u64, u64), &str), remote: ((u64, u64, u64), &str)) -> bool {
+    match remote.0.cmp(&current.0) {
+        std::cmp::Ordering::Greater => true,
+        std::cmp::Ordering::Less => false,
+        // 正式版不会被同一版本的预发布构建取代
+        std::cmp::Ordering::Equal => !current.1.is_empty() && remote.1 != current.1,
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AppUpdateInfo {
+    pub channel: &'static str,
+    pub current_version: String,
+    pub version: String,
+    pub date: Option<String>,
+    /// 更新日志（Markdown）
+    pub changelog: Option<String>,
+    /// 用户已选择稍后提醒该版本
+    pub deferred: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct AppUpdateProgress {
+    downloaded: u64,
+    total: Option<u64>,
+}
+
+fn is_deferred(verge: &IVerge, version: &str, now: i64) -> bool {
+    verge.update_deferred_version.as_deref() == Some(version)
+        && verge.update_deferred_until.is_some_and(|until| until > now)
+}
+
+pub struct AppUpdater {
+    pending: Mutex<Option<Update>>,
+}
+
+singleton!(AppUpdater, APP_UPDATER);
+
+impl AppUpdater {
+    const fn new() -> Self {
+        Self {
+            pending: Mutex::new(None),
+        }
+    }
+
+    /// 检查更新，`channel` 为空时使用设置中的通道；没有新版本时返回 `None`
+    pub async fn check(&self, channel: Option<&str>) -> Result<Option<AppUpdateInfo>> {
+        let verge = Config::verge().await.latest_arc();
+        let channel = UpdateChannel::parse(channel.or(verge.update_channel.as_deref()).unwrap_or("stable"))?;
+
+        let app = handle::Handle::app_handle();
+        let mut builder = app.updater_builder().version_comparator(|current, remote| {
+            let remote = remote.version;
+            is_update(
+                ((current.major, current.minor, current.patch), current.pre.as_str()),
+                ((remote.major, remote.minor, remote.patch), remote.pre.as_str()),
+            )
+        });
+        if let Some(endpoints) = channel.endpoints() {
+            let endpoints = endpoints
+                .iter()
+                .map(|url| Url::parse(url))
+                .collect::<Result<Vec<_>, _>>()?;
+            builder = builder.endpoints(endpoints)?;
+        }
+        let update = builder.build()?.check().await?;
+        logging!(
+            info,
+            Type::System,
+            "Checked {} channel for app update: {:?}",
+            channel.as_str(),
+            update.as_ref().map(|u| u.version.as_str())
+        );
+
+        let info = update.as_ref().map(|update| AppUpdateInfo {
+            channel: channel.as_str(),
+            current_version: update.current_version.as_str().into(),
+            version: update.version.as_str().into(),
+            date: update.date.map(|date| date.to_string().into()),
+            changelog: update.body.as_deref().map(Into::into),
+            deferred: is_deferred(&verge, &update.version, chrono::Local::now().timestamp()),
+        });
+        *self.pending.lock() = update;
+        Ok(info)
+    }
+
+    /// 下载并安装上一次检查到的更新，签名校验由 updater 插件完成
+    pub async fn install(&self) -> Result<()> {
+        let Some(update) = self.pending.lock().clone() else {
+            bail!("no pending update, check for updates first");
+        };
+
+        let mut downloaded = 0u64;
+        update
+            .download_and_install(
+                |chunk, total| {
+                    downloaded += u64::try_from(chunk).unwrap_or_default();
+                    let _ = handle::Handle::app_handle()
+                        .emit("app-update-progress", AppUpdateProgress { downloaded, total });
+                },
+                || logging!(info, Type::System, "App update downloaded, installing"),
+            )
+            .await?;
+        *self.pending.lock() = None;
+        logging!(info, Type::System, "App updated to {}", update.version);
+        Ok(())
+    }
+
+    /// 稍后提醒：`hours` 小时内检查到同一版本时标记为已推迟
+    pub async fn defer(&self, hours: u32) -> Result<()> {
+        let Some(version) = self.pending.lock().as_ref().map(|u| String::from(u.version.as_str())) else {
+            bail!("no pending update to defer");
+        };
+        let patch = IVerge {
+            update_deferred_version: Some(version),
+            update_deferred_until: Some(chrono::Local::now().timestamp() + i64::from(hours) * 3600),
+            ..IVerge::default()
+        };
+        feat::patch_verge(&patch, false).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_update_channel() {
+        assert_eq!(UpdateChannel::parse("beta").ok(), Some(UpdateChannel::Beta));
+        assert_eq!(UpdateChannel::parse("nightly").ok(), Some(UpdateChannel::Beta));
+        assert!(UpdateChannel::parse("canary").is_err());
+        assert!(UpdateChannel::Stable.endpoints().is_none());
+
+        let endpoints = UpdateChannel::Beta.endpoints().unwrap_or_default();
+        assert_eq!(endpoints.len(), 3);
+        assert!(endpoints.iter().all(|url| Url::parse(url).is_ok()));
+        assert!(
+            endpoints
+                .last()
+                .is_some_and(|url| url.ends_with("/updater-alpha/update.json"))
+        );
+    }
+
+    #[test]
+    fn test_is_update() {
+        let stable = ((2, 4, 3), "");
+        assert!(is_update(stable, ((2, 4, 4), "")));
+        assert!(!is_update(stable, stable));
+        assert!(!is_update(stable, ((2, 4, 3), "alpha-abc1234")));
+        assert!(!is_update(((2, 5, 0), "alpha-abc1234"), stable));
+
+        // 同一基础版本的预发布构建按哈希区分，不比较先后
+        let alpha = ((2, 4, 3), "alpha-abc1234");
+        assert!(is_update(alpha, ((2, 4, 3), "alpha-0f1e2d3")));
+        assert!(!is_update(alpha, alpha));
+        assert!(is_update(alpha, stable));
+    }
+
+    #[test]
+    fn test_is_deferred() {
+        let verge = IVerge {
+            update_deferred_version: Some("2.5.0".into()),
+            update_deferred_until: Some(100),
+            ..IVerge::default()
+        };
+        assert!(is_deferred(&verge, "2.5.0", 50));
+        assert!(!is_deferred(&verge, "2.5.0", 150));
+        assert!(!is_deferred(&verge, "2.5.1", 50));
+    }
+}
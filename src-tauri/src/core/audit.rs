@@ -0,0 +1,307 @@
+//! 操作审计日志
+//!
+//! 记录每次改变状态的操作：来源（界面、托盘、快捷键、定时任务、控制 API、Telegram 机器人或自动规则）、操作内容、
+//! 时间以及修改前后的值，只追加不修改，用于排查"代理为什么在凌晨三点被关掉"这类问题。
+//! 来源通过 [`AuditSource::scope`] 绑定到当前任务，入口处设置一次即可，未设置时视为界面操作。
+
+use crate::{process::AsyncHandler, utils::dirs};
+use anyhow::Result;
+use chrono::Local;
+use clash_verge_logging::{Type, logging_error};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use smartstring::alias::String;
+use std::path::PathBuf;
+use tokio::{io::AsyncWriteExt as _, sync::Mutex};
+
+const LOG_FILE: &str = "audit.jsonl";
+const ROTATED_FILE: &str = "audit.1.jsonl";
+/// 超过该大小时轮转，只保留上一份
+const MAX_FILE_BYTES: u64 = 4 * 1024 * 1024;
+const MAX_QUERY_LIMIT: usize = 5000;
+/// 这些字段（不区分大小写）不记录明文，嵌套在对象或数组中时同样生效，
+/// 如 clash 的 `authentication`、`webhooks[].secret` 以及节点的 `password`、`uuid`
+const REDACTED_KEYS: [&str; 13] = [
+    "authentication",
+    "authorization",
+    "auth-str",
+    "backup_password",
+    "control_api_token",
+    "password",
+    "pre-shared-key",
+    "private-key",
+    "secret",
+    "telegram_bot_token",
+    "token",
+    "uuid",
+    "webdav_password",
+];
+
+tokio::task_local! {
+    static SOURCE: AuditSource;
+}
+
+/// 写入串行化，避免并发追加时行交错
+static WRITE_LOCK: Mutex<()> = Mutex::const_new(());
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditSource {
+    #[default]
+    Ui,
+    Tray,
+    Hotkey,
+    Scheduler,
+    Api,
+    /// Telegram 机器人的命令
+    Telegram,
+    /// 网络规则、守护等自动触发的操作
+    System,
+}
+
+impl AuditSource {
+    /// 在该来源下执行 `fut`，期间记录的操作都归属于该来源
+    pub async fn scope<F: Future>(self, fut: F) -> F::Output {
+        SOURCE.scope(self, fut).await
+    }
+
+    pub fn current() -> Self {
+        SOURCE.try_with(|source| *source).unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub time: i64,
+    pub source: AuditSource,
+    /// 如 `verge.enable_tun_mode`、`node.switch`、`rule.insert`
+    pub action: String,
+    /// 操作对象，如代理组或订阅 uid
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub old: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub new: Option<Value>,
+}
+
+/// `query_audit_log` 的过滤条件，字段均为可选
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct AuditLogFilter {
+    pub source: Option<AuditSource>,
+    /// 操作名前缀，如 `verge.` 或 `node.switch`
+    pub action: Option<String>,
+    pub since: Option<i64>,
+    pub until: Option<i64>,
+}
+
+impl AuditLogFilter {
+    pub fn matches(&self, entry: &AuditEntry) -> bool {
+        self.source.is_none_or(|source| source == entry.source)
+            && self
+                .action
+                .as_deref()
+                .is_none_or(|action| entry.action.starts_with(action))
+            && self.since.is_none_or(|since| entry.time >= since)
+            && self.until.is_none_or(|until| entry.time <= until)
+    }
+}
+
+/// 敏感字段替换为 `***`，其余字段递归处理
+fn redact(key: &str, value: Value) -> Value {
+    if value.is_null() {
+        return value;
+    }
+    if REDACTED_KEYS.iter().any(|name| name.eq_ignore_ascii_case(key)) {
+        return Value::String("***".into());
+    }
+    match value {
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .map(|(key, value)| {
+                    let value = redact(&key, value);
+                    (key, value)
+                })
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.into_iter().map(|item| redact("", item)).collect()),
+        value => value,
+    }
+}
+
+/// 比较修改前的配置与补丁，列出补丁中实际改变的字段 `(字段, 旧值, 新值)`
+fn changed_fields(old: &Value, patch: &Value) -> Vec<(std::string::String, Value, Value)> {
+    let Some(patch) = patch.as_object() else {
+        return Vec::new();
+    };
+    patch
+        .iter()
+        .filter(|(_, new)| !new.is_null())
+        .filter_map(|(key, new)| {
+            let old = old.get(key).cloned().unwrap_or(Value::Null);
+            (old != *new).then(|| (key.clone(), redact(key, old), redact(key, new.clone())))
+        })
+        .collect()
+}
+
+pub struct AuditLog;
+
+impl AuditLog {
+    fn path() -> Result<PathBuf> {
+        Ok(dirs::app_logs_dir()?.join(LOG_FILE))
+    }
+
+    /// 以当前任务的来源记录一次操作，写入在后台完成
+    pub fn record(action: &str, target: Option<&str>, old: Option<Value>, new: Option<Value>) {
+        let entry = AuditEntry {
+            time: Local::now().timestamp(),
+            source: AuditSource::current(),
+            action: action.into(),
+            target: target.map(Into::into),
+            old,
+            new,
+        };
+        AsyncHandler::spawn(move || async move {
+            logging_error!(Type::Config, Self::append(&entry).await);
+        });
+    }
+
+    /// 记录配置补丁中实际改变的字段，每个字段一条，`prefix` 如 `verge` / `clash`
+    pub fn record_patch<T: Serialize, P: Serialize>(prefix: &str, old: &T, patch: &P) {
+        let (Ok(old), Ok(patch)) = (serde_json::to_value(old), serde_json::to_value(patch)) else {
+            return;
+        };
+        for (key, old, new) in changed_fields(&old, &patch) {
+            Self::record(&format!("{prefix}.{key}"), None, Some(old), Some(new));
+        }
+    }
+
+    async fn append(entry: &AuditEntry) -> Result<()> {
+        let _guard = WRITE_LOCK.lock().await;
+        let path = Self::path()?;
+        if let Some(dir) = path.parent() {
+            tokio::fs::create_dir_all(dir).await?;
+        }
+        if tokio::fs::metadata(&path)
+            .await
+            .is_ok_and(|meta| meta.len() > MAX_FILE_BYTES)
+        {
+            tokio::fs::rename(&path, path.with_file_name(ROTATED_FILE)).await?;
+        }
+
+        let mut line = serde_json::to_string(entry)?;
+        line.push('\n');
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .await?;
+        file.write_all(line.as_bytes()).await?;
+        Ok(())
+    }
+
+    /// 从新到旧查询，最多返回 `limit` 条
+    pub async fn query(filter: &AuditLogFilter, limit: usize) -> Result<Vec<AuditEntry>> {
+        let limit = limit.clamp(1, MAX_QUERY_LIMIT);
+        let path = Self::path()?;
+        let mut result = Vec::new();
+        for path in [path.clone(), path.with_file_name(ROTATED_FILE)] {
+            if !path.exists() {
+                continue;
+            }
+            let content = tokio::fs::read_to_string(&path).await?;
+            for line in content.lines().rev() {
+                let Ok(entry) = serde_json::from_str::<AuditEntry>(line) else {
+                    continue;
+                };
+                if filter.matches(&entry) {
+                    result.push(entry);
+                    if result.len() >= limit {
+                        return Ok(result);
+                    }
+                }
+            }
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_changed_fields() {
+        let old = json!({ "enable_tun_mode": true, "webdav_password": "a", "theme_mode": "dark" });
+        let patch = json!({
+            "enable_tun_mode": false,
+            "webdav_password": "b",
+            "theme_mode": "dark",
+            "verge_mixed_port": null
+        });
+
+        let mut changes = changed_fields(&old, &patch);
+        changes.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            changes,
+            vec![
+                ("enable_tun_mode".into(), json!(true), json!(false)),
+                ("webdav_password".into(), json!("***"), json!("***")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_redact_nested_fields() {
+        let old = json!({ "authentication": ["a:b"], "webhooks": [], "pinned_groups": ["A"] });
+        let patch = json!({
+            "authentication": ["user:pass"],
+            "webhooks": [{ "url": "https://example.com", "secret": "s" }],
+            "pinned_groups": ["B"],
+        });
+
+        let mut changes = changed_fields(&old, &patch);
+        changes.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            changes,
+            vec![
+                ("authentication".into(), json!("***"), json!("***")),
+                ("pinned_groups".into(), json!(["A"]), json!(["B"])),
+                (
+                    "webhooks".into(),
+                    json!([]),
+                    json!([{ "url": "https://example.com", "secret": "***" }])
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_filter() {
+        let entry = AuditEntry {
+            time: 100,
+            source: AuditSource::Scheduler,
+            action: "verge.enable_system_proxy".into(),
+            target: None,
+            old: Some(json!(true)),
+            new: Some(json!(false)),
+        };
+        let filter = AuditLogFilter {
+            source: Some(AuditSource::Scheduler),
+            action: Some("verge.".into()),
+            since: Some(50),
+            ..Default::default()
+        };
+        assert!(filter.matches(&entry));
+        assert!(
+            !AuditLogFilter {
+                source: Some(AuditSource::Ui),
+                ..Default::default()
+            }
+            .matches(&entry)
+        );
+        assert_eq!(AuditSource::current(), AuditSource::Ui);
+    }
+}
@@ -0,0 +1,45 @@
+//! 内核后端抽象
+//!
+//! 应用内部始终以 mihomo 配置格式生成运行配置，并通过 mihomo 兼容的控制接口
+//! 获取流量、节点与连接信息。sing-box 后端在启动前把运行配置转换为 sing-box
+//! 格式，并把其 Clash API 桥接到与 mihomo 相同的 IPC 路径上。
+
+mod shim;
+pub mod singbox;
+
+use crate::{config::IVerge, constants::network::ports, utils::instance};
+use serde::{Deserialize, Serialize};
+
+pub use self::shim::ControllerShim;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CoreBackend {
+    #[default]
+    Mihomo,
+    SingBox,
+}
+
+impl CoreBackend {
+    /// 当前选择的内核所使用的后端
+    pub fn of(verge: &IVerge) -> Self {
+        verge
+            .get_custom_core(&verge.get_valid_clash_core())
+            .map_or(Self::Mihomo, |core| core.backend)
+    }
+
+    /// 是否支持通过控制接口热重载配置，不支持时需重启内核
+    pub const fn supports_hot_reload(self) -> bool {
+        matches!(self, Self::Mihomo)
+    }
+
+    /// 是否可交由系统服务启动，服务只认识 mihomo 的启动参数
+    pub const fn supports_service(self) -> bool {
+        matches!(self, Self::Mihomo)
+    }
+}
+
+/// sing-box Clash API 监听的本地端口，仅绑定回环地址
+pub fn singbox_controller_port() -> u16 {
+    instance::offset_port(ports::SINGBOX_CONTROLLER)
+}
@@ -0,0 +1,92 @@
+use crate::{config::IClashTemp, process::AsyncHandler, singleton};
+use anyhow::Result;
+use clash_verge_logging::{Type, logging};
+use parking_lot::Mutex;
+use tauri::async_runtime::JoinHandle;
+use tokio::{io::copy_bidirectional, net::TcpStream};
+
+/// 把 mihomo 的 IPC 控制接口路径转发到 sing-box 的 Clash API 端口，
+/// 使现有的流量、节点和连接查询无需区分后端
+#[derive(Default)]
+pub struct ControllerShim {
+    task: Mutex<Option<JoinHandle<()>>>,
+}
+
+singleton!(ControllerShim, CONTROLLER_SHIM);
+
+async fn forward<S>(mut local: S, port: u16)
+where
+    S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send,
+{
+    match TcpStream::connect(("127.0.0.1", port)).await {
+        Ok(mut remote) => {
+            let _ = copy_bidirectional(&mut local, &mut remote).await;
+        }
+        Err(err) => logging!(debug, Type::Core, "Controller shim connect failed: {err}"),
+    }
+}
+
+impl ControllerShim {
+    fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn start(&self, port: u16) -> Result<()> {
+        self.stop();
+        let ipc_path = IClashTemp::guard_external_controller_ipc();
+        let task = Self::listen(ipc_path.to_string(), port)?;
+        *self.task.lock() = Some(task);
+        logging!(
+            info,
+            Type::Core,
+            "Controller shim forwarding {} -> 127.0.0.1:{}",
+            ipc_path,
+            port
+        );
+        Ok(())
+    }
+
+    pub fn stop(&self) {
+        if let Some(task) = self.task.lock().take() {
+            task.abort();
+        }
+    }
+
+    #[cfg(unix)]
+    fn listen(path: std::string::String, port: u16) -> Result<JoinHandle<()>> {
+        use tokio::net::UnixListener;
+
+        let path = std::path::PathBuf::from(path);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path)?;
+
+        Ok(AsyncHandler::spawn(move || async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                AsyncHandler::spawn(move || forward(stream, port));
+            }
+        }))
+    }
+
+    #[cfg(windows)]
+    fn listen(path: std::string::String, port: u16) -> Result<JoinHandle<()>> {
+        use tokio::net::windows::named_pipe::ServerOptions;
+
+        let mut server = ServerOptions::new().first_pipe_instance(true).create(&path)?;
+
+        Ok(AsyncHandler::spawn(move || async move {
+            loop {
+                if server.connect().await.is_err() {
+                    break;
+                }
+                let Ok(next) = ServerOptions::new().create(&path) else {
+                    break;
+                };
+                let connected = std::mem::replace(&mut server, next);
+                AsyncHandler::spawn(move || forward(connected, port));
+            }
+        }))
+    }
+}
@@ -0,0 +1,768 @@
+//! mihomo 运行配置到 sing-box 配置的转换
+//!
+//! 只覆盖常用的协议、策略组与规则类型，无法转换的节点与规则会被跳过并记录在
+//! [`Conversion::skipped`] 中，引用被跳过节点或策略组的策略组会自动剔除这些成员。
+//! `GEOIP`、`GEOSITE` 规则使用 sing-box 官方的远程规则集，`RULE-SET` 规则由已下载的规则集内容生成内联规则集。
+
+use super::singbox_controller_port;
+use crate::{
+    config::{Config, ConfigType},
+    constants::network::ports,
+    utils::{dirs, help, instance},
+};
+use anyhow::Result;
+use clash_verge_logging::{Type, logging};
+use serde_json::{Map, Value as Json, json};
+use serde_yaml_ng::{Mapping, Value};
+use smartstring::alias::String;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    path::PathBuf,
+};
+
+pub const SINGBOX_CONFIG: &str = "sing-box.json";
+pub const SINGBOX_CHECK_CONFIG: &str = "sing-box-check.json";
+
+const BUILTIN_OUTBOUNDS: [&str; 2] = ["DIRECT", "REJECT"];
+const GEOIP_RULE_SET_URL: &str = "https://raw.githubusercontent.com/SagerNet/sing-geoip/rule-set";
+const GEOSITE_RULE_SET_URL: &str = "https://raw.githubusercontent.com/SagerNet/sing-geosite/rule-set";
+
+/// 转换结果
+pub struct Conversion {
+    pub config: Json,
+    /// 无法转换而被跳过的节点或规则
+    pub skipped: Vec<String>,
+}
+
+fn str_of<'a>(map: &'a Mapping, key: &str) -> Option<&'a str> {
+    map.get(key).and_then(Value::as_str)
+}
+
+fn u64_of(map: &Mapping, key: &str) -> Option<u64> {
+    map.get(key).and_then(Value::as_u64)
+}
+
+fn bool_of(map: &Mapping, key: &str) -> bool {
+    map.get(key).and_then(Value::as_bool).unwrap_or(false)
+}
+
+/// 字符串列表，忽略其中的非字符串项
+fn str_seq<'a>(map: &'a Mapping, key: &str) -> impl Iterator<Item = &'a str> {
+    map.get(key)
+        .and_then(Value::as_sequence)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+}
+
+fn yaml_to_json(value: &Value) -> Json {
+    serde_json::to_value(value).unwrap_or(Json::Null)
+}
+
+fn convert_tls(proxy: &Mapping, force: bool) -> Option<Json> {
+    if !force && !bool_of(proxy, "tls") {
+        return None;
+    }
+    let mut tls = json!({
+        "enabled": true,
+        "insecure": bool_of(proxy, "skip-cert-verify"),
+    });
+    if let Some(sni) = str_of(proxy, "servername").or_else(|| str_of(proxy, "sni")) {
+        tls["server_name"] = sni.into();
+    }
+    if let Some(alpn) = proxy.get("alpn") {
+        tls["alpn"] = yaml_to_json(alpn);
+    }
+    if let Some(reality) = proxy.get("reality-opts").and_then(Value::as_mapping) {
+        tls["reality"] = json!({
+            "enabled": true,
+            "public_key": str_of(reality, "public-key").unwrap_or_default(),
+            "short_id": str_of(reality, "short-id").unwrap_or_default(),
+        });
+        tls["utls"] = json!({
+            "enabled": true,
+            "fingerprint": str_of(proxy, "client-fingerprint").unwrap_or("chrome"),
+        });
+    }
+    Some(tls)
+}
+
+fn convert_transport(proxy: &Mapping) -> Option<Json> {
+    match str_of(proxy, "network")? {
+        "ws" => {
+            let opts = proxy.get("ws-opts").and_then(Value::as_mapping);
+            let mut transport = json!({ "type": "ws" });
+            if let Some(opts) = opts {
+                transport["path"] = str_of(opts, "path").unwrap_or("/").into();
+                if let Some(headers) = opts.get("headers") {
+                    transport["headers"] = yaml_to_json(headers);
+                }
+            }
+            Some(transport)
+        }
+        "grpc" => {
+            let service = proxy
+                .get("grpc-opts")
+                .and_then(Value::as_mapping)
+                .and_then(|opts| str_of(opts, "grpc-service-name"))
+                .unwrap_or_default();
+            Some(json!({ "type": "grpc", "service_name": service }))
+        }
+        "http" | "h2" => Some(json!({ "type": "http" })),
+        _ => None,
+    }
+}
+
+/// 转换单个节点，不支持的协议返回 `None`
+fn convert_proxy(proxy: &Mapping) -> Option<Json> {
+    let name = str_of(proxy, "name")?;
+    let server = str_of(proxy, "server")?;
+    let port = u64_of(proxy, "port")?;
+
+    let mut out = match str_of(proxy, "type")? {
+        "ss" => json!({
+            "type": "shadowsocks",
+            "method": str_of(proxy, "cipher")?,
+            "password": str_of(proxy, "password")?,
+        }),
+        "vmess" => json!({
+            "type": "vmess",
+            "uuid": str_of(proxy, "uuid")?,
+            "alter_id": u64_of(proxy, "alterId").unwrap_or(0),
+            "security": str_of(proxy, "cipher").unwrap_or("auto"),
+        }),
+        "vless" => {
+            let mut vless = json!({ "type": "vless", "uuid": str_of(proxy, "uuid")? });
+            if let Some(flow) = str_of(proxy, "flow") {
+                vless["flow"] = flow.into();
+            }
+            vless
+        }
+        "trojan" => json!({ "type": "trojan", "password": str_of(proxy, "password")? }),
+        "hysteria2" => json!({ "type": "hysteria2", "password": str_of(proxy, "password")? }),
+        "socks5" => json!({
+            "type": "socks",
+            "username": str_of(proxy, "username").unwrap_or_default(),
+            "password": str_of(proxy, "password").unwrap_or_default(),
+        }),
+        "http" => json!({
+            "type": "http",
+            "username": str_of(proxy, "username").unwrap_or_default(),
+            "password": str_of(proxy, "password").unwrap_or_default(),
+        }),
+        _ => return None,
+    };
+
+    out["tag"] = name.into();
+    out["server"] = server.into();
+    out["server_port"] = port.into();
+
+    let kind = str_of(proxy, "type").unwrap_or_default();
+    if let Some(tls) = convert_tls(proxy, matches!(kind, "trojan" | "hysteria2")) {
+        out["tls"] = tls;
+    }
+    if let Some(transport) = convert_transport(proxy) {
+        out["transport"] = transport;
+    }
+    Some(out)
+}
+
+fn convert_group(group: &Mapping, known: &HashSet<&str>) -> Option<Json> {
+    let name = str_of(group, "name")?;
+    let members: Vec<&str> = group
+        .get("proxies")
+        .and_then(Value::as_sequence)
+        .map(|seq| {
+            seq.iter()
+                .filter_map(Value::as_str)
+                .filter(|p| known.contains(p))
+                .collect()
+        })
+        .unwrap_or_default();
+    if members.is_empty() {
+        return None;
+    }
+
+    let group = match str_of(group, "type")? {
+        "select" => json!({ "type": "selector", "outbounds": members }),
+        "url-test" | "fallback" | "load-balance" => json!({
+            "type": "urltest",
+            "outbounds": members,
+            "url": str_of(group, "url").unwrap_or("https://www.gstatic.com/generate_204"),
+            "interval": format!("{}s", u64_of(group, "interval").unwrap_or(300)),
+            "tolerance": u64_of(group, "tolerance").unwrap_or(50),
+        }),
+        _ => return None,
+    };
+    let mut group = group;
+    group["tag"] = name.into();
+    Some(group)
+}
+
+/// 转换策略组，被跳过的策略组会从其他策略组中剔除，剔除后为空的策略组同样被跳过，直到结果不再变化
+fn convert_groups<'a>(groups: &[&'a Mapping], known: &mut HashSet<&'a str>, skipped: &mut Vec<String>) -> Vec<Json> {
+    known.extend(groups.iter().filter_map(|g| str_of(g, "name")));
+    loop {
+        let mut converted = Vec::new();
+        let mut dropped = Vec::new();
+        for group in groups {
+            let name = str_of(group, "name").unwrap_or_default();
+            if !known.contains(name) {
+                continue;
+            }
+            match convert_group(group, known) {
+                Some(out) => converted.push(out),
+                None => dropped.push(name),
+            }
+        }
+        if dropped.is_empty() {
+            skipped.extend(
+                groups
+                    .iter()
+                    .map(|g| str_of(g, "name").unwrap_or_default())
+                    .filter(|name| !known.contains(name))
+                    .map(|name| format!("group: {name}").into()),
+            );
+            return converted;
+        }
+        for name in dropped {
+            known.remove(name);
+        }
+    }
+}
+
+/// 可以直接映射为 sing-box 规则字段的规则类型
+fn match_field(kind: &str, payload: &str) -> Option<(&'static str, Json)> {
+    let field = match kind {
+        "DOMAIN" => "domain",
+        "DOMAIN-SUFFIX" => "domain_suffix",
+        "DOMAIN-KEYWORD" => "domain_keyword",
+        "DOMAIN-REGEX" => "domain_regex",
+        "IP-CIDR" | "IP-CIDR6" => "ip_cidr",
+        "SRC-IP-CIDR" => "source_ip_cidr",
+        "PROCESS-NAME" => "process_name",
+        "DST-PORT" => return Some(("port", payload.parse::<u16>().ok()?.into())),
+        _ => return None,
+    };
+    Some((field, payload.into()))
+}
+
+/// mihomo 域名规则集与 `fake-ip-filter` 中的一项，`+.` 表示域名及其子域名，含通配符的项无法转换
+fn domain_entry(entry: &str) -> Option<(&'static str, Json)> {
+    if let Some(suffix) = entry.strip_prefix("+.") {
+        return Some(("domain_suffix", suffix.into()));
+    }
+    if entry.contains('*') {
+        return None;
+    }
+    if entry.starts_with('.') {
+        return Some(("domain_suffix", entry.into()));
+    }
+    Some(("domain", entry.into()))
+}
+
+/// 把同一字段的条目合并为一条规则，多条规则之间为“或”的关系
+fn group_fields(entries: impl Iterator<Item = (&'static str, Json)>) -> Vec<Json> {
+    let mut fields: BTreeMap<&str, Vec<Json>> = BTreeMap::new();
+    for (field, value) in entries {
+        fields.entry(field).or_default().push(value);
+    }
+    fields
+        .into_iter()
+        .map(|(field, values)| json!({ field: values }))
+        .collect()
+}
+
+/// 规则集提供者中已读取的内容
+pub struct RuleProvider {
+    pub behavior: String,
+    pub payload: Vec<String>,
+}
+
+impl RuleProvider {
+    /// 转换为 sing-box 内联规则集的规则，无法转换的条目被忽略
+    fn headless_rules(&self) -> Vec<Json> {
+        let entries = self.payload.iter().map(|entry| entry.trim());
+        match self.behavior.as_str() {
+            "domain" => group_fields(entries.filter_map(domain_entry)),
+            "ipcidr" => group_fields(entries.map(|entry| ("ip_cidr", entry.into()))),
+            _ => group_fields(entries.filter_map(|entry| {
+                let mut parts = entry.split(',').map(str::trim);
+                match_field(parts.next()?, parts.next()?)
+            })),
+        }
+    }
+}
+
+/// 转换规则时收集用到的规则集
+struct RuleSets<'a> {
+    providers: &'a HashMap<String, RuleProvider>,
+    definitions: Vec<Json>,
+}
+
+impl RuleSets<'_> {
+    fn define(&mut self, tag: std::string::String, definition: impl FnOnce() -> Option<Json>) -> Option<Json> {
+        if !self.definitions.iter().any(|d| d["tag"] == tag.as_str()) {
+            let mut definition = definition()?;
+            definition["tag"] = tag.as_str().into();
+            self.definitions.push(definition);
+        }
+        Some(json!({ "rule_set": [tag] }))
+    }
+
+    /// `GEOIP`、`GEOSITE` 使用 sing-box 官方的远程规则集，`RULE-SET` 读取规则集提供者的内容生成内联规则集
+    fn matcher(&mut self, kind: &str, payload: &str) -> Option<Json> {
+        let code = payload.to_lowercase();
+        match kind {
+            "GEOIP" if code == "lan" => Some(json!({ "ip_is_private": true })),
+            "GEOIP" => self.define(format!("geoip-{code}"), || {
+                Some(json!({
+                    "type": "remote",
+                    "format": "binary",
+                    "url": format!("{GEOIP_RULE_SET_URL}/geoip-{code}.srs"),
+                }))
+            }),
+            "GEOSITE" => self.define(format!("geosite-{code}"), || {
+                Some(json!({
+                    "type": "remote",
+                    "format": "binary",
+                    "url": format!("{GEOSITE_RULE_SET_URL}/geosite-{code}.srs"),
+                }))
+            }),
+            "RULE-SET" => {
+                let providers = self.providers;
+                let provider = providers.get(payload)?;
+                self.define(format!("provider-{payload}"), || {
+                    let rules = provider.headless_rules();
+                    (!rules.is_empty()).then(|| json!({ "type": "inline", "rules": rules }))
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// 转换单条规则，目标不存在或无法转换时返回 `None`
+fn convert_rule(rule: &str, known: &HashSet<&str>, rule_sets: &mut RuleSets) -> Option<Json> {
+    let parts: Vec<&str> = rule.split(',').map(str::trim).collect();
+    let [kind, payload, target, ..] = parts.as_slice() else {
+        return None;
+    };
+    if !known.contains(target) {
+        return None;
+    }
+    let mut out = match match_field(kind, payload) {
+        Some((field, value)) => json!({ field: [value] }),
+        None => rule_sets.matcher(kind, payload)?,
+    };
+    out["outbound"] = (*target).into();
+    Some(out)
+}
+
+/// DNS 服务器地址，`system` 对应 sing-box 的 `local`，`#` 后指定的出站转为 `detour`
+fn convert_nameserver(address: &str, tag: std::string::String, known: &HashSet<&str>) -> Json {
+    let (address, params) = address.split_once('#').unwrap_or((address, ""));
+    let detour = params.split('&').next().unwrap_or_default();
+    let address = if address == "system" { "local" } else { address };
+    let mut server = json!({ "tag": tag, "address": address });
+    if known.contains(detour) {
+        server["detour"] = detour.into();
+    }
+    server
+}
+
+/// 转换 `dns` 段，未启用时返回 `None` 使用系统 DNS；返回值的第二项表示是否启用了 fake-ip
+fn convert_dns(config: &Mapping, known: &HashSet<&str>, skipped: &mut Vec<String>) -> Option<(Json, bool)> {
+    let dns = config.get("dns").and_then(Value::as_mapping)?;
+    if !bool_of(dns, "enable") {
+        return None;
+    }
+
+    let mut servers: Vec<Json> = str_seq(dns, "nameserver")
+        .enumerate()
+        .map(|(i, address)| convert_nameserver(address, format!("nameserver-{i}"), known))
+        .collect();
+    if servers.is_empty() {
+        servers.push(json!({ "tag": "nameserver-0", "address": "local" }));
+    }
+    // default-nameserver 用于解析其他 DNS 服务器的域名
+    if let Some(bootstrap) = str_seq(dns, "default-nameserver").next() {
+        for server in &mut servers {
+            if server["address"]
+                .as_str()
+                .is_some_and(|address| address.contains("://"))
+            {
+                server["address_resolver"] = "bootstrap".into();
+            }
+        }
+        servers.push(convert_nameserver(bootstrap, "bootstrap".into(), known));
+    }
+
+    let mut rules = Vec::new();
+    if let Some(address) = str_seq(dns, "proxy-server-nameserver").next() {
+        servers.push(convert_nameserver(address, "proxy-server".into(), known));
+        rules.push(json!({ "outbound": ["any"], "server": "proxy-server" }));
+    }
+
+    let mut out = json!({ "final": "nameserver-0" });
+    let fake_ip = str_of(dns, "enhanced-mode") == Some("fake-ip");
+    if fake_ip {
+        let filter: Vec<(&'static str, Json)> = str_seq(dns, "fake-ip-filter")
+            .filter_map(|entry| {
+                let converted = domain_entry(entry).filter(|_| !entry.contains(':'));
+                if converted.is_none() {
+                    skipped.push(format!("fake-ip-filter: {entry}").into());
+                }
+                converted
+            })
+            .collect();
+        for mut rule in group_fields(filter.into_iter()) {
+            rule["server"] = "nameserver-0".into();
+            rules.push(rule);
+        }
+        servers.push(json!({ "tag": "fakeip", "address": "fakeip" }));
+        rules.push(json!({ "query_type": ["A", "AAAA"], "server": "fakeip" }));
+        out["fakeip"] = json!({
+            "enabled": true,
+            "inet4_range": str_of(dns, "fake-ip-range").unwrap_or("198.18.0.1/16"),
+        });
+    }
+    // mihomo 的 `ipv6` 默认开启，`dns.ipv6` 默认关闭
+    let ipv6 = config.get("ipv6").and_then(Value::as_bool).unwrap_or(true) && bool_of(dns, "ipv6");
+    if !ipv6 {
+        out["strategy"] = "ipv4_only".into();
+    }
+    out["servers"] = servers.into();
+    out["rules"] = rules.into();
+    Some((out, fake_ip))
+}
+
+/// 将 mihomo 格式的运行配置转换为 sing-box 配置，`providers` 为已读取的规则集提供者
+pub fn convert_config(config: &Mapping, controller_port: u16, providers: &HashMap<String, RuleProvider>) -> Conversion {
+    let mut skipped = Vec::new();
+
+    let mut outbounds = vec![
+        json!({ "type": "direct", "tag": "DIRECT" }),
+        json!({ "type": "block", "tag": "REJECT" }),
+    ];
+    let mut known: HashSet<&str> = BUILTIN_OUTBOUNDS.into_iter().collect();
+
+    for proxy in config.get("proxies").and_then(Value::as_sequence).into_iter().flatten() {
+        let Some(proxy) = proxy.as_mapping() else { continue };
+        let name = str_of(proxy, "name").unwrap_or_default();
+        match convert_proxy(proxy) {
+            Some(out) => {
+                known.insert(name);
+                outbounds.push(out);
+            }
+            None => skipped.push(format!("proxy: {name}").into()),
+        }
+    }
+
+    // 策略组可以引用其他策略组，先登记名称再转换
+    let groups: Vec<&Mapping> = config
+        .get("proxy-groups")
+        .and_then(Value::as_sequence)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_mapping)
+        .collect();
+    outbounds.extend(convert_groups(&groups, &mut known, &mut skipped));
+
+    let mut rule_sets = RuleSets {
+        providers,
+        definitions: Vec::new(),
+    };
+    let mut rules = Vec::new();
+    // MATCH 的目标被跳过时拒绝连接，避免本应走代理的流量直连
+    let mut final_outbound = None;
+    for rule in config.get("rules").and_then(Value::as_sequence).into_iter().flatten() {
+        let Some(rule) = rule.as_str() else { continue };
+        if let Some(target) = rule.strip_prefix("MATCH,").map(str::trim) {
+            if known.contains(target) {
+                final_outbound = Some(target);
+            } else {
+                final_outbound = Some("REJECT");
+                skipped.push(format!("rule: {rule}").into());
+            }
+            continue;
+        }
+        match convert_rule(rule, &known, &mut rule_sets) {
+            Some(out) => rules.push(out),
+            None => skipped.push(format!("rule: {rule}").into()),
+        }
+    }
+
+    let listen = if bool_of(config, "allow-lan") {
+        "::"
+    } else {
+        "127.0.0.1"
+    };
+    let mut inbounds = vec![json!({
+        "type": "mixed",
+        "tag": "mixed-in",
+        "listen": listen,
+        "listen_port": u64_of(config, "mixed-port").unwrap_or_else(|| u64::from(instance::offset_port(ports::DEFAULT_MIXED))),
+    })];
+    let tun = config
+        .get("tun")
+        .and_then(Value::as_mapping)
+        .filter(|tun| bool_of(tun, "enable"));
+    if let Some(tun) = tun {
+        inbounds.push(json!({
+            "type": "tun",
+            "tag": "tun-in",
+            "address": ["172.19.0.1/30", "fdfe:dcba:9876::1/126"],
+            "auto_route": bool_of(tun, "auto-route"),
+            "strict_route": bool_of(tun, "strict-route"),
+            "stack": str_of(tun, "stack").unwrap_or("mixed").to_lowercase(),
+        }));
+    }
+
+    let dns = convert_dns(config, &known, &mut skipped);
+    // TUN 模式下劫持 DNS 请求交给 DNS 模块处理，fake-ip 才能生效
+    if dns.is_some() && tun.is_some() {
+        outbounds.push(json!({ "type": "dns", "tag": "dns-out" }));
+        rules.insert(0, json!({ "port": [53], "outbound": "dns-out" }));
+    }
+
+    let level = str_of(config, "log-level").unwrap_or("info");
+    let mut route = json!({ "rules": rules, "auto_detect_interface": true });
+    if !rule_sets.definitions.is_empty() {
+        route["rule_set"] = rule_sets.definitions.into();
+    }
+    if let Some(final_outbound) = final_outbound {
+        route["final"] = final_outbound.into();
+    }
+
+    let mut config = json!({
+        "log": {
+            "disabled": level == "silent",
+            "level": if level == "silent" { "info" } else { level },
+            "timestamp": true,
+        },
+        "inbounds": inbounds,
+        "outbounds": outbounds,
+        "route": route,
+        "experimental": {
+            "clash_api": {
+                "external_controller": format!("127.0.0.1:{controller_port}"),
+                "default_mode": str_of(config, "mode").unwrap_or("rule"),
+            },
+            "cache_file": { "enabled": true, "store_fakeip": dns.as_ref().is_some_and(|(_, fake_ip)| *fake_ip) },
+        },
+    });
+    if let Some((dns, _)) = dns {
+        config["dns"] = dns;
+    }
+
+    Conversion { config, skipped }
+}
+
+/// 读取 `rule-providers` 的内容，`mrs` 格式与尚未下载的提供者无法转换，引用它们的规则会被跳过
+async fn load_rule_providers(config: &Mapping) -> HashMap<String, RuleProvider> {
+    let mut providers = HashMap::new();
+    let Some(entries) = config.get("rule-providers").and_then(Value::as_mapping) else {
+        return providers;
+    };
+    let Ok(home) = dirs::app_home_dir() else {
+        return providers;
+    };
+    for (name, provider) in entries {
+        let (Some(name), Some(provider)) = (name.as_str(), provider.as_mapping()) else {
+            continue;
+        };
+        let format = str_of(provider, "format").unwrap_or("yaml");
+        let payload: Vec<String> = if str_of(provider, "type") == Some("inline") {
+            str_seq(provider, "payload").map(Into::into).collect()
+        } else if let Some(path) = str_of(provider, "path")
+            && format != "mrs"
+            && let Ok(content) = tokio::fs::read_to_string(home.join(path)).await
+        {
+            if format == "text" {
+                content
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                    .map(Into::into)
+                    .collect()
+            } else {
+                serde_yaml_ng::from_str::<Mapping>(&content)
+                    .map(|content| str_seq(&content, "payload").map(Into::into).collect())
+                    .unwrap_or_default()
+            }
+        } else {
+            continue;
+        };
+        providers.insert(
+            name.into(),
+            RuleProvider {
+                behavior: str_of(provider, "behavior").unwrap_or("classical").into(),
+                payload,
+            },
+        );
+    }
+    providers
+}
+
+/// 把 mihomo 格式的配置文件转换后写入应用目录下的 `file_name`
+pub async fn convert_file(source: &PathBuf, file_name: &str) -> Result<PathBuf> {
+    let target = dirs::app_home_dir()?.join(file_name);
+    let mapping = help::read_mapping(source).await?;
+    let providers = load_rule_providers(&mapping).await;
+    let conversion = convert_config(&mapping, singbox_controller_port(), &providers);
+    if !conversion.skipped.is_empty() {
+        logging!(
+            warn,
+            Type::Core,
+            "sing-box conversion skipped {} item(s): {:?}",
+            conversion.skipped.len(),
+            conversion.skipped
+        );
+    }
+    tokio::fs::write(&target, serde_json::to_vec_pretty(&conversion.config)?).await?;
+    Ok(target)
+}
+
+/// 生成运行配置并转换为 sing-box 配置文件
+pub async fn generate_file(typ: ConfigType) -> Result<PathBuf> {
+    let file_name = match typ {
+        ConfigType::Run => SINGBOX_CONFIG,
+        ConfigType::Check => SINGBOX_CHECK_CONFIG,
+    };
+    convert_file(&Config::generate_file(typ).await?, file_name).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Mapping {
+        serde_yaml_ng::from_str(
+            r"
+mixed-port: 7897
+mode: rule
+proxies:
+  - { name: hk, type: ss, server: 1.2.3.4, port: 8388, cipher: aes-128-gcm, password: pw }
+  - { name: jp, type: trojan, server: jp.example.com, port: 443, password: pw, sni: jp.example.com }
+  - { name: legacy, type: ssr, server: 5.6.7.8, port: 1 }
+proxy-groups:
+  - { name: Proxy, type: select, proxies: [hk, jp, legacy, DIRECT] }
+  - { name: Auto, type: url-test, proxies: [hk, jp], interval: 600 }
+rules:
+  - DOMAIN-SUFFIX,google.com,Proxy
+  - GEOIP,CN,DIRECT
+  - DST-PORT,22,DIRECT
+  - MATCH,Proxy
+",
+        )
+        .unwrap_or_default()
+    }
+
+    #[test]
+    fn test_convert_proxies_and_groups() {
+        let conversion = convert_config(&sample(), 9098, &HashMap::new());
+        let outbounds = conversion.config["outbounds"].as_array().cloned().unwrap_or_default();
+        let tags: Vec<&str> = outbounds.iter().filter_map(|o| o["tag"].as_str()).collect();
+        assert_eq!(tags, ["DIRECT", "REJECT", "hk", "jp", "Proxy", "Auto"]);
+
+        let proxy = &outbounds[4];
+        assert_eq!(proxy["type"], "selector");
+        assert_eq!(proxy["outbounds"], json!(["hk", "jp", "DIRECT"]));
+        assert_eq!(outbounds[3]["tls"]["server_name"], "jp.example.com");
+        assert_eq!(outbounds[5]["interval"], "600s");
+        assert!(conversion.skipped.iter().any(|s| s == "proxy: legacy"));
+    }
+
+    #[test]
+    fn test_convert_rules() {
+        let conversion = convert_config(&sample(), 9098, &HashMap::new());
+        let route = &conversion.config["route"];
+        assert_eq!(route["final"], "Proxy");
+        assert_eq!(route["rules"][0]["domain_suffix"], json!(["google.com"]));
+        assert_eq!(route["rules"][1]["rule_set"], json!(["geoip-cn"]));
+        assert_eq!(route["rules"][1]["outbound"], "DIRECT");
+        assert_eq!(route["rules"][2]["port"], json!([22]));
+        assert_eq!(route["rule_set"][0]["tag"], "geoip-cn");
+        assert!(!conversion.skipped.iter().any(|s| s.starts_with("rule:")));
+        assert_eq!(
+            conversion.config["experimental"]["clash_api"]["external_controller"],
+            "127.0.0.1:9098"
+        );
+    }
+
+    #[test]
+    fn test_drop_references_to_skipped_groups() {
+        let config: Mapping = serde_yaml_ng::from_str(
+            r"
+proxies:
+  - { name: hk, type: ss, server: 1.2.3.4, port: 8388, cipher: aes-128-gcm, password: pw }
+proxy-groups:
+  - { name: Chain, type: relay, proxies: [hk] }
+  - { name: Outer, type: select, proxies: [Chain] }
+  - { name: Mixed, type: select, proxies: [Outer, hk] }
+rules:
+  - DOMAIN,example.com,Outer
+  - MATCH,Outer
+",
+        )
+        .unwrap_or_default();
+        let conversion = convert_config(&config, 9098, &HashMap::new());
+        let outbounds = conversion.config["outbounds"].as_array().cloned().unwrap_or_default();
+        let tags: Vec<&str> = outbounds.iter().filter_map(|o| o["tag"].as_str()).collect();
+        assert_eq!(tags, ["DIRECT", "REJECT", "hk", "Mixed"]);
+        assert_eq!(outbounds[3]["outbounds"], json!(["hk"]));
+        assert_eq!(conversion.config["route"]["final"], "REJECT");
+        assert_eq!(conversion.config["route"]["rules"], json!([]));
+        assert!(conversion.skipped.iter().any(|s| s == "group: Chain"));
+        assert!(conversion.skipped.iter().any(|s| s == "group: Outer"));
+    }
+
+    #[test]
+    fn test_convert_rule_providers_and_dns() {
+        let config: Mapping = serde_yaml_ng::from_str(
+            r"
+tun: { enable: true, stack: system }
+dns:
+  enable: true
+  enhanced-mode: fake-ip
+  fake-ip-filter: ['+.lan', 'geosite:cn']
+  default-nameserver: [223.5.5.5]
+  nameserver: ['https://dns.alidns.com/dns-query', system]
+rules:
+  - RULE-SET,ads,REJECT
+  - GEOSITE,google,DIRECT
+  - MATCH,DIRECT
+",
+        )
+        .unwrap_or_default();
+        let providers = HashMap::from([(
+            String::from("ads"),
+            RuleProvider {
+                behavior: "domain".into(),
+                payload: vec!["+.ads.example".into(), "tracker.example".into(), "*.wild".into()],
+            },
+        )]);
+        let conversion = convert_config(&config, 9098, &providers);
+        let route = &conversion.config["route"];
+        assert_eq!(route["rules"][0]["outbound"], "dns-out");
+        assert_eq!(route["rules"][1]["rule_set"], json!(["provider-ads"]));
+        assert_eq!(
+            route["rule_set"][0]["rules"],
+            json!([{ "domain": ["tracker.example"] }, { "domain_suffix": ["ads.example"] }])
+        );
+        assert_eq!(route["rule_set"][1]["tag"], "geosite-google");
+
+        let dns = &conversion.config["dns"];
+        assert_eq!(dns["servers"][0]["address"], "https://dns.alidns.com/dns-query");
+        assert_eq!(dns["servers"][0]["address_resolver"], "bootstrap");
+        assert_eq!(dns["servers"][1], json!({ "tag": "nameserver-1", "address": "local" }));
+        assert_eq!(
+            dns["rules"][0],
+            json!({ "domain_suffix": ["lan"], "server": "nameserver-0" })
+        );
+        assert_eq!(dns["fakeip"]["inet4_range"], "198.18.0.1/16");
+        assert_eq!(dns["strategy"], "ipv4_only");
+        assert!(conversion.skipped.iter().any(|s| s == "fake-ip-filter: geosite:cn"));
+    }
+}
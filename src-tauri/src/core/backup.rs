@@ -1,19 +1,17 @@
-use crate::constants::files::DNS_CONFIG;
-use crate::{config::Config, process::AsyncHandler, utils::dirs};
+use crate::constants::files::{DNS_CONFIG, MANAGED_RULES};
+use crate::{
+    config::{Config, ConfigPersistence},
+    core::secrets,
+    process::AsyncHandler,
+    utils::dirs,
+};
 use anyhow::Error;
 use arc_swap::{ArcSwap, ArcSwapOption};
 use clash_verge_logging::{Type, logging};
 use once_cell::sync::OnceCell;
 use reqwest_dav::list_cmd::{ListEntity, ListFile};
 use smartstring::alias::String;
-use std::{
-    collections::HashMap,
-    env::{consts::OS, temp_dir},
-    io::Write as _,
-    path::PathBuf,
-    sync::Arc,
-    time::Duration,
-};
+use std::{collections::HashMap, env::consts::OS, io::Write as _, path::PathBuf, sync::Arc, time::Duration};
 use tokio::{fs, time::timeout};
 use zip::write::SimpleFileOptions;
 
@@ -25,6 +23,8 @@ const TIMEOUT_DOWNLOAD: u64 = 300; // 下载超时 5 分钟
 const TIMEOUT_LIST: u64 = 3; // 列表超时 30 秒
 const TIMEOUT_DELETE: u64 = 3; // 删除超时 30 秒
 
+const CONNECTION_LOGS_DIR: &str = "logs/connections";
+
 #[derive(Clone)]
 struct WebDavConfig {
     url: String,
@@ -245,9 +245,16 @@ impl WebDavClient {
 }
 
 pub async fn create_backup() -> Result<(String, PathBuf), Error> {
+    create_archive(true).await
+}
+
+/// 打包本地配置；`include_subscriptions` 为 false 时去掉 profiles.yaml 中的订阅地址
+pub async fn create_archive(include_subscriptions: bool) -> Result<(String, PathBuf), Error> {
+    // 先写入尚未落盘的设置，避免备份到旧配置
+    ConfigPersistence::global().flush().await?;
     let now = chrono::Local::now().format("%Y-%m-%d_%H-%M-%S").to_string();
     let zip_file_name: String = format!("{OS}-backup-{now}.zip").into();
-    let zip_path = temp_dir().join(zip_file_name.as_str());
+    let zip_path = dirs::app_temp_dir().join(zip_file_name.as_str());
 
     let value = zip_path.clone();
     let file = AsyncHandler::spawn_blocking(move || std::fs::File::create(&value)).await??;
@@ -270,15 +277,22 @@ pub async fn create_backup() -> Result<(String, PathBuf), Error> {
             }
         }
     }
+    // 文件中的敏感字段可能只是本机凭据库的引用，写入内存中的原值以便在其他设备恢复
     zip.start_file(dirs::CLASH_CONFIG, options)?;
-    zip.write_all(fs::read(dirs::clash_path()?).await?.as_slice())?;
+    let clash_config = Config::clash().await.latest_arc();
+    zip.write_all(serde_yaml_ng::to_string(&clash_config.0)?.as_bytes())?;
 
-    let verge_text = fs::read_to_string(dirs::verge_path()?).await?;
-    let mut verge_config: serde_json::Value = serde_yaml_ng::from_str(&verge_text)?;
+    let mut verge_config = serde_json::to_value(secrets::resolved_verge_mapping().await?)?;
     if let Some(obj) = verge_config.as_object_mut() {
         obj.remove("webdav_username");
         obj.remove("webdav_password");
         obj.remove("webdav_url");
+        obj.remove("backup_password");
+        obj.remove("lan_sync_device_id");
+        obj.remove("lan_sync_peers");
+        obj.remove("lan_sync_identity");
+        obj.remove("control_api_token");
+        obj.remove("telegram_bot_token");
     }
     zip.start_file(dirs::VERGE_CONFIG, options)?;
     zip.write_all(serde_yaml_ng::to_string(&verge_config)?.as_bytes())?;
@@ -289,8 +303,40 @@ pub async fn create_backup() -> Result<(String, PathBuf), Error> {
         zip.write_all(fs::read(&dns_config_path).await?.as_slice())?;
     }
 
+    let managed_rules_path = dirs::app_home_dir()?.join(MANAGED_RULES);
+    if managed_rules_path.exists() {
+        zip.start_file(MANAGED_RULES, options)?;
+        zip.write_all(fs::read(&managed_rules_path).await?.as_slice())?;
+    }
+
+    // 流量统计（连接日志），保持相对 app_home 的路径以便恢复时原样解压
+    if let Ok(mut entries) = fs::read_dir(dirs::app_connection_logs_dir()?).await {
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if path.is_file()
+                && let Some(file_name) = entry.file_name().to_str()
+            {
+                zip.start_file(format!("{CONNECTION_LOGS_DIR}/{file_name}"), options)?;
+                zip.write_all(fs::read(&path).await?.as_slice())?;
+            }
+        }
+    }
+
     zip.start_file(dirs::PROFILE_YAML, options)?;
-    zip.write_all(fs::read(dirs::profiles_path()?).await?.as_slice())?;
+    if include_subscriptions {
+        // 文件中的订阅地址可能只是凭据库引用，写入内存中的原值以便在其他设备恢复
+        let profiles = Config::profiles().await.latest_arc();
+        zip.write_all(serde_yaml_ng::to_string(&*profiles)?.as_bytes())?;
+    } else {
+        let profiles_text = fs::read_to_string(dirs::profiles_path()?).await?;
+        let mut profiles: serde_json::Value = serde_yaml_ng::from_str(&profiles_text)?;
+        if let Some(items) = profiles.get_mut("items").and_then(|items| items.as_array_mut()) {
+            for item in items.iter_mut().filter_map(|item| item.as_object_mut()) {
+                item.remove("url");
+            }
+        }
+        zip.write_all(serde_yaml_ng::to_string(&profiles)?.as_bytes())?;
+    }
     zip.finish()?;
     Ok((zip_file_name, zip_path))
 }
@@ -0,0 +1,250 @@
+//! 系统代理的绕过列表
+//!
+//! 配置中以规则列表的形式编辑，应用时按平台转换：Windows 使用 `;` 分隔且不支持 CIDR，
+//! 网段会展开为通配符；macOS 由 sysproxy 拆分为 `networksetup` 的参数列表；
+//! Linux 写入逗号分隔的 `no_proxy` 格式，不支持 `<local>`。
+
+use anyhow::{Result, bail};
+use serde::Serialize;
+use smartstring::alias::String;
+use std::net::{IpAddr, Ipv4Addr};
+
+/// 表示所有不含点的本地主机名
+pub const LOCAL_RULE: &str = "<local>";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProxyBypass {
+    /// 自定义规则
+    pub rules: Vec<String>,
+    pub use_default: bool,
+    /// 当前平台的默认规则
+    pub defaults: Vec<String>,
+    /// 实际写入系统设置的字符串
+    pub effective: String,
+}
+
+impl ProxyBypass {
+    pub fn new(raw: &str, use_default: bool) -> Self {
+        let format = BypassFormat::current();
+        let rules = parse_bypass(raw);
+        let effective = serialize_bypass(&effective_bypass(&rules, use_default, format), format);
+        Self {
+            rules,
+            use_default,
+            defaults: format.defaults().iter().map(|&r| String::from(r)).collect(),
+            effective,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BypassFormat {
+    Windows,
+    Macos,
+    Linux,
+}
+
+impl BypassFormat {
+    pub const fn current() -> Self {
+        if cfg!(target_os = "windows") {
+            Self::Windows
+        } else if cfg!(target_os = "macos") {
+            Self::Macos
+        } else {
+            Self::Linux
+        }
+    }
+
+    const fn separator(self) -> &'static str {
+        match self {
+            Self::Windows => ";",
+            Self::Macos | Self::Linux => ",",
+        }
+    }
+
+    /// 默认绕过的地址
+    pub const fn defaults(self) -> &'static [&'static str] {
+        match self {
+            Self::Windows => &[
+                "localhost",
+                "127.*",
+                "192.168.*",
+                "10.*",
+                "172.16.*",
+                "172.17.*",
+                "172.18.*",
+                "172.19.*",
+                "172.20.*",
+                "172.21.*",
+                "172.22.*",
+                "172.23.*",
+                "172.24.*",
+                "172.25.*",
+                "172.26.*",
+                "172.27.*",
+                "172.28.*",
+                "172.29.*",
+                "172.30.*",
+                "172.31.*",
+                LOCAL_RULE,
+            ],
+            Self::Macos => &[
+                "127.0.0.1",
+                "192.168.0.0/16",
+                "10.0.0.0/8",
+                "172.16.0.0/12",
+                "localhost",
+                "*.local",
+                "*.crashlytics.com",
+                LOCAL_RULE,
+            ],
+            Self::Linux => &[
+                "localhost",
+                "127.0.0.1",
+                "192.168.0.0/16",
+                "10.0.0.0/8",
+                "172.16.0.0/12",
+                "::1",
+            ],
+        }
+    }
+}
+
+/// 拆分保存的绕过字符串，兼容逗号、分号与换行分隔
+pub fn parse_bypass(raw: &str) -> Vec<String> {
+    let mut rules: Vec<String> = Vec::new();
+    for rule in raw.split([',', ';', '\n']).map(str::trim).filter(|r| !r.is_empty()) {
+        if !rules.iter().any(|r| r == rule) {
+            rules.push(rule.into());
+        }
+    }
+    rules
+}
+
+fn parse_cidr(rule: &str) -> Option<(IpAddr, u8)> {
+    let (addr, prefix) = rule.split_once('/')?;
+    let addr = addr.parse::<IpAddr>().ok()?;
+    let prefix = prefix.parse::<u8>().ok()?;
+    let max = if addr.is_ipv4() { 32 } else { 128 };
+    (prefix <= max).then_some((addr, prefix))
+}
+
+/// 校验单条规则：主机名（可含 `*` 通配符与端口）、IP、CIDR 网段或 `<local>`
+pub fn validate_rule(rule: &str) -> Result<()> {
+    if rule == LOCAL_RULE {
+        return Ok(());
+    }
+    if rule.is_empty() || rule.len() > 253 {
+        bail!("invalid bypass rule length: {rule}");
+    }
+    if rule.contains('/') {
+        if parse_cidr(rule).is_none() {
+            bail!("invalid CIDR in bypass rule: {rule}");
+        }
+        return Ok(());
+    }
+    let valid_chars = rule
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_' | '*' | ':' | '[' | ']'));
+    if !valid_chars {
+        bail!("invalid character in bypass rule: {rule}");
+    }
+    Ok(())
+}
+
+/// 把 IPv4 网段展开为 Windows 支持的通配符，例如 `172.16.0.0/12` 展开为 `172.16.*` 至 `172.31.*`
+fn expand_cidr_wildcards(addr: Ipv4Addr, prefix: u8) -> Vec<String> {
+    let octets = prefix.div_ceil(8);
+    let bits = u32::from(octets) * 8;
+    let base = u32::from(addr) & u32::MAX.checked_shl(32 - u32::from(prefix)).unwrap_or(0);
+    let step = 1u32.checked_shl(32 - bits).unwrap_or(0);
+    let count = 1u32 << (bits - u32::from(prefix));
+    (0..count)
+        .map(|i| {
+            let network = Ipv4Addr::from(base.wrapping_add(i.wrapping_mul(step))).octets();
+            let head = network[..usize::from(octets)]
+                .iter()
+                .map(u8::to_string)
+                .collect::<Vec<_>>()
+                .join(".");
+            let rule = if octets == 4 { head } else { format!("{head}.*") };
+            String::from(rule)
+        })
+        .collect()
+}
+
+/// 按平台格式生成绕过字符串
+pub fn serialize_bypass(rules: &[String], format: BypassFormat) -> String {
+    let mut items: Vec<String> = Vec::new();
+    for rule in rules {
+        match (format, parse_cidr(rule)) {
+            (BypassFormat::Windows, Some((IpAddr::V4(addr), prefix))) if prefix > 0 => {
+                items.extend(expand_cidr_wildcards(addr, prefix));
+            }
+            _ if format == BypassFormat::Linux && rule == LOCAL_RULE => {}
+            _ => items.push(rule.clone()),
+        }
+    }
+    let mut unique: Vec<&str> = Vec::new();
+    for item in &items {
+        if !unique.contains(&item.as_str()) {
+            unique.push(item);
+        }
+    }
+    unique.join(format.separator()).into()
+}
+
+/// 合并默认规则与自定义规则，自定义为空时总是使用默认规则
+pub fn effective_bypass(custom: &[String], use_default: bool, format: BypassFormat) -> Vec<String> {
+    let mut rules: Vec<String> = Vec::new();
+    if custom.is_empty() || use_default {
+        rules.extend(format.defaults().iter().map(|&r| String::from(r)));
+    }
+    rules.extend(custom.iter().cloned());
+    rules
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_validate_bypass() {
+        let rules = parse_bypass("localhost; *.example.com,10.0.0.0/8\nlocalhost");
+        assert_eq!(rules, ["localhost", "*.example.com", "10.0.0.0/8"]);
+        assert!(rules.iter().all(|r| validate_rule(r).is_ok()));
+        assert!(validate_rule(LOCAL_RULE).is_ok());
+        assert!(validate_rule("[::1]:8080").is_ok());
+        assert!(validate_rule("10.0.0.0/33").is_err());
+        assert!(validate_rule("exa mple.com").is_err());
+        assert!(validate_rule("").is_err());
+    }
+
+    #[test]
+    fn test_serialize_bypass() {
+        let rules: Vec<String> = ["localhost", "172.16.0.0/12", "192.168.1.0/24", LOCAL_RULE]
+            .into_iter()
+            .map(Into::into)
+            .collect();
+        let windows = serialize_bypass(&rules, BypassFormat::Windows);
+        assert!(windows.starts_with("localhost;172.16.*;172.17.*;"));
+        assert!(windows.ends_with("172.31.*;192.168.1.*;<local>"));
+        assert_eq!(
+            serialize_bypass(&rules, BypassFormat::Linux),
+            "localhost,172.16.0.0/12,192.168.1.0/24"
+        );
+        assert_eq!(
+            serialize_bypass(&rules, BypassFormat::Macos),
+            "localhost,172.16.0.0/12,192.168.1.0/24,<local>"
+        );
+    }
+
+    #[test]
+    fn test_effective_bypass() {
+        let custom = vec![String::from("*.corp.example")];
+        let linux = BypassFormat::Linux;
+        assert_eq!(effective_bypass(&[], false, linux).len(), linux.defaults().len());
+        assert_eq!(effective_bypass(&custom, false, linux), custom);
+        assert_eq!(effective_bypass(&custom, true, linux).len(), linux.defaults().len() + 1);
+    }
+}
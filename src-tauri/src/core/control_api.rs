@@ -0,0 +1,359 @@
+//! 本地 HTTP 控制接口
+//!
+//! 开启 `enable_control_api` 后在 `127.0.0.1:control_api_port` 上提供 JSON 接口，供脚本、
+//! Raycast/Alfred 扩展或 Stream Deck 插件在不打开界面的情况下控制应用。
+//! 所有请求需携带 `Authorization: Bearer <control_api_token>`。
+//!
+//! - `GET  /api/status` 内核、模式、系统代理、TUN 与当前订阅
+//! - `GET  /api/stats` 实时速率与本次运行的累计流量
+//! - `GET  /api/profiles` 订阅列表
+//! - `PUT  /api/profiles/current` `{"uid": ...}` 切换订阅
+//! - `PUT  /api/system-proxy` / `PUT /api/tun` `{"enabled": bool}`
+//! - `PUT  /api/mode` `{"mode": "rule" | "global" | "direct"}`
+//! - `POST /api/proxies/select` `{"group": ..., "node": ...}`
+
+use crate::{
+    cmd,
+    config::{Config, IVerge},
+    constants::network::ports,
+    core::{CoreManager, audit::AuditSource, handle, traffic::TrafficHub},
+    feat,
+    process::AsyncHandler,
+    singleton,
+    utils::{crypto, instance},
+};
+use anyhow::{Result, bail};
+use clash_verge_logging::{Type, logging};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use smartstring::alias::String;
+use std::{
+    future::Future,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+use warp::{Filter as _, http::StatusCode};
+
+const IDLE_INTERVAL: Duration = Duration::from_secs(30);
+const MAX_BODY_BYTES: u64 = 16 * 1024;
+const MODES: [&str; 3] = ["rule", "global", "direct"];
+
+#[derive(Deserialize)]
+struct SwitchProfile {
+    uid: String,
+}
+
+#[derive(Deserialize)]
+struct Toggle {
+    enabled: bool,
+}
+
+#[derive(Deserialize)]
+struct ChangeMode {
+    mode: String,
+}
+
+#[derive(Deserialize)]
+struct SelectNode {
+    group: String,
+    node: String,
+}
+
+type Reply = warp::http::Response<Vec<u8>>;
+
+fn json_reply(status: StatusCode, body: &Value) -> Reply {
+    warp::http::Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(serde_json::to_vec(body).unwrap_or_default())
+        .unwrap_or_default()
+}
+
+/// 逐字节比较全部内容，避免通过响应时间猜测令牌
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn is_authorized(header: Option<&str>, token: Option<&str>) -> bool {
+    let Some(token) = token.filter(|t| !t.is_empty()) else {
+        return false;
+    };
+    header
+        .and_then(|h| h.strip_prefix("Bearer "))
+        .is_some_and(|given| constant_time_eq(given.trim().as_bytes(), token.as_bytes()))
+}
+
+/// 校验开关与令牌后执行处理函数
+async fn guarded<F, Fut>(auth: Option<std::string::String>, handler: F) -> Result<Reply, warp::Rejection>
+where
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<Value>>,
+{
+    let (enabled, token) = {
+        let verge = Config::verge().await.latest_arc();
+        (
+            verge.enable_control_api.unwrap_or(false),
+            verge.control_api_token.clone(),
+        )
+    };
+    if !enabled {
+        return Ok(json_reply(
+            StatusCode::SERVICE_UNAVAILABLE,
+            &json!({ "error": "control api is disabled" }),
+        ));
+    }
+    if !is_authorized(auth.as_deref(), token.as_deref()) {
+        return Ok(json_reply(
+            StatusCode::UNAUTHORIZED,
+            &json!({ "error": "unauthorized" }),
+        ));
+    }
+    Ok(match AuditSource::Api.scope(handler()).await {
+        Ok(data) => json_reply(StatusCode::OK, &data),
+        Err(err) => {
+            logging!(warn, Type::Network, "Control API request failed: {err}");
+            json_reply(StatusCode::BAD_REQUEST, &json!({ "error": err.to_string() }))
+        }
+    })
+}
+
+async fn status() -> Result<Value> {
+    let verge = Config::verge().await.latest_arc();
+    let profile = Config::profiles().await.latest_arc().current.clone();
+    let mode = Config::clash()
+        .await
+        .latest_arc()
+        .0
+        .get("mode")
+        .and_then(|val| val.as_str())
+        .unwrap_or("rule")
+        .to_owned();
+    Ok(json!({
+        "core": CoreManager::global().get_running_mode().to_string(),
+        "mode": mode,
+        "system_proxy": verge.enable_system_proxy.unwrap_or(false),
+        "tun": verge.enable_tun_mode.unwrap_or(false),
+        "profile": profile,
+    }))
+}
+
+async fn stats() -> Result<Value> {
+    let speed = TrafficHub::global().latest();
+    let memory = feat::CoreMemoryMonitor::global().last();
+    let connections = handle::Handle::mihomo().await.get_connections().await?;
+    Ok(json!({
+        "up": speed.up,
+        "down": speed.down,
+        "upload_total": connections.upload_total,
+        "download_total": connections.download_total,
+        "connections": connections.connections.map(|c| c.len()).unwrap_or_default(),
+        "memory": memory.as_ref().map(|m| m.inuse),
+        "memory_rss": memory.and_then(|m| m.rss),
+    }))
+}
+
+async fn profiles() -> Result<Value> {
+    let profiles = Config::profiles().await.latest_arc();
+    let current = profiles.current.clone();
+    let items: Vec<Value> = profiles
+        .items
+        .as_deref()
+        .unwrap_or_default()
+        .iter()
+        .filter(|item| matches!(item.itype.as_deref(), Some("remote" | "local")))
+        .map(|item| {
+            json!({
+                "uid": item.uid,
+                "name": item.name,
+                "current": item.uid.is_some() && item.uid == current,
+            })
+        })
+        .collect();
+    Ok(Value::from(items))
+}
+
+async fn switch_profile(body: SwitchProfile) -> Result<Value> {
+    feat::ensure_unlocked().await?;
+    if Config::profiles().await.latest_arc().get_item(&body.uid).is_err() {
+        bail!("profile not found: {}", body.uid);
+    }
+    let switched = cmd::patch_profiles_config_by_profile_index(body.uid)
+        .await
+        .map_err(|err| anyhow::anyhow!("{err}"))?;
+    Ok(json!({ "switched": switched }))
+}
+
+async fn set_system_proxy(body: Toggle) -> Result<Value> {
+    feat::ensure_unlocked().await?;
+    let patch = IVerge {
+        enable_system_proxy: Some(body.enabled),
+        ..IVerge::default()
+    };
+    feat::patch_verge(&patch, false).await?;
+    handle::Handle::refresh_verge();
+    Ok(json!({ "enabled": body.enabled }))
+}
+
+async fn set_tun(body: Toggle) -> Result<Value> {
+    feat::ensure_unlocked().await?;
+    let patch = IVerge {
+        enable_tun_mode: Some(body.enabled),
+        ..IVerge::default()
+    };
+    feat::patch_verge(&patch, false).await?;
+    handle::Handle::refresh_verge();
+    Ok(json!({ "enabled": body.enabled }))
+}
+
+async fn change_mode(body: ChangeMode) -> Result<Value> {
+    feat::ensure_unlocked().await?;
+    if !MODES.contains(&body.mode.as_str()) {
+        bail!("unsupported mode: {}", body.mode);
+    }
+    feat::change_clash_mode(body.mode.clone()).await;
+    Ok(json!({ "mode": body.mode }))
+}
+
+async fn select_node(body: SelectNode) -> Result<Value> {
+    let proxies = handle::Handle::mihomo().await.get_proxies().await?;
+    let Some(group) = proxies.proxies.get(body.group.as_str()) else {
+        bail!("proxy group not found: {}", body.group);
+    };
+    if !group
+        .all
+        .as_ref()
+        .is_some_and(|all| all.iter().any(|n| n == body.node.as_str()))
+    {
+        bail!("{} does not contain {}", body.group, body.node);
+    }
+    feat::switch_proxy_node(&body.group, &body.node).await;
+    Ok(json!({ "group": body.group, "node": body.node }))
+}
+
+pub struct ControlApi {
+    runner_started: AtomicBool,
+}
+
+singleton!(ControlApi, CONTROL_API);
+
+impl ControlApi {
+    const fn new() -> Self {
+        Self {
+            runner_started: AtomicBool::new(false),
+        }
+    }
+
+    /// 等待开启后启动服务，重复调用无副作用；关闭后请求返回 503，端口在本次运行内保留
+    pub fn init(&self) {
+        if self.runner_started.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        AsyncHandler::spawn(|| async {
+            loop {
+                if handle::Handle::global().is_exiting() {
+                    return;
+                }
+                if Config::verge().await.latest_arc().enable_control_api.unwrap_or(false) {
+                    break;
+                }
+                tokio::time::sleep(IDLE_INTERVAL).await;
+            }
+            let port = Config::verge()
+                .await
+                .latest_arc()
+                .control_api_port
+                .unwrap_or_else(|| instance::offset_port(ports::CONTROL_API));
+            Self::serve(port).await;
+        });
+    }
+
+    async fn serve(port: u16) {
+        let auth = warp::header::optional::<std::string::String>("authorization");
+        let body = warp::body::content_length_limit(MAX_BODY_BYTES);
+
+        let status_route = warp::path!("api" / "status")
+            .and(warp::get())
+            .and(auth)
+            .and_then(|auth| guarded(auth, status));
+        let stats_route = warp::path!("api" / "stats")
+            .and(warp::get())
+            .and(auth)
+            .and_then(|auth| guarded(auth, stats));
+        let profiles_route = warp::path!("api" / "profiles")
+            .and(warp::get())
+            .and(auth)
+            .and_then(|auth| guarded(auth, profiles));
+        let switch_profile_route = warp::path!("api" / "profiles" / "current")
+            .and(warp::put())
+            .and(auth)
+            .and(body)
+            .and(warp::body::json())
+            .and_then(|auth, req: SwitchProfile| guarded(auth, move || switch_profile(req)));
+        let system_proxy_route = warp::path!("api" / "system-proxy")
+            .and(warp::put())
+            .and(auth)
+            .and(body)
+            .and(warp::body::json())
+            .and_then(|auth, req: Toggle| guarded(auth, move || set_system_proxy(req)));
+        let tun_route = warp::path!("api" / "tun")
+            .and(warp::put())
+            .and(auth)
+            .and(body)
+            .and(warp::body::json())
+            .and_then(|auth, req: Toggle| guarded(auth, move || set_tun(req)));
+        let mode_route = warp::path!("api" / "mode")
+            .and(warp::put())
+            .and(auth)
+            .and(body)
+            .and(warp::body::json())
+            .and_then(|auth, req: ChangeMode| guarded(auth, move || change_mode(req)));
+        let select_route = warp::path!("api" / "proxies" / "select")
+            .and(warp::post())
+            .and(auth)
+            .and(body)
+            .and(warp::body::json())
+            .and_then(|auth, req: SelectNode| guarded(auth, move || select_node(req)));
+
+        let routes = status_route
+            .or(stats_route)
+            .or(profiles_route)
+            .or(switch_profile_route)
+            .or(system_proxy_route)
+            .or(tun_route)
+            .or(mode_route)
+            .or(select_route);
+
+        logging!(info, Type::Network, "Control API listening on 127.0.0.1:{}", port);
+        warp::serve(routes).bind(([127, 0, 0, 1], port)).await.run().await;
+    }
+
+    /// 生成新的访问令牌并保存，旧令牌立即失效
+    pub async fn regenerate_token(&self) -> Result<String> {
+        let mut bytes = [0u8; 24];
+        getrandom::fill(&mut bytes)?;
+        let token: String = crypto::to_hex(&bytes).into();
+        let patch = IVerge {
+            control_api_token: Some(token.clone()),
+            ..IVerge::default()
+        };
+        feat::patch_verge(&patch, false).await?;
+        handle::Handle::refresh_verge();
+        Ok(token)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_authorization() {
+        assert!(is_authorized(Some("Bearer abc"), Some("abc")));
+        assert!(!is_authorized(Some("Bearer abd"), Some("abc")));
+        assert!(!is_authorized(Some("abc"), Some("abc")));
+        assert!(!is_authorized(None, Some("abc")));
+        assert!(!is_authorized(Some("Bearer "), Some("")));
+        assert!(!is_authorized(Some("Bearer abc"), None));
+    }
+}
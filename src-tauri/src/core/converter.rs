@@ -0,0 +1,497 @@
+//! 内置订阅转换
+//!
+//! 将 v2ray 分享链接列表（可整体 base64 编码）、sing-box JSON、Surge 与 Quantumult X 配置中的节点
+//! 转换为 mihomo YAML。只转换节点，生成一个包含全部节点的 `PROXY` 选择组与 `MATCH` 规则，
+//! 原配置中的分组与规则不做转换。
+
+use super::sharelink::{self, base_proxy, insert, ws_opts};
+use anyhow::{Result, anyhow, bail};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as Json;
+use serde_yaml_ng::{Mapping, Value};
+use std::collections::HashMap;
+
+const DEFAULT_GROUP: &str = "PROXY";
+
+/// 订阅内容格式，保存在订阅选项中，`auto` 时按内容自动识别
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SubscriptionFormat {
+    #[default]
+    Auto,
+    Clash,
+    V2ray,
+    SingBox,
+    Surge,
+    QuantumultX,
+}
+
+impl SubscriptionFormat {
+    /// 按内容特征识别格式，无法识别时视为 v2ray 分享链接
+    pub fn detect(data: &str) -> Self {
+        if let Ok(map) = serde_yaml_ng::from_str::<Mapping>(data) {
+            if map.contains_key("outbounds") && !map.contains_key("proxies") {
+                return Self::SingBox;
+            }
+            return Self::Clash;
+        }
+        let has_section = |name: &str| data.lines().any(|line| line.trim().eq_ignore_ascii_case(name));
+        if has_section("[server_local]") {
+            Self::QuantumultX
+        } else if has_section("[Proxy]") {
+            Self::Surge
+        } else {
+            Self::V2ray
+        }
+    }
+}
+
+/// 将订阅内容转换为 mihomo YAML，Clash 格式原样返回以保留注释
+pub fn convert_subscription(data: &str, format: SubscriptionFormat) -> Result<String> {
+    let format = match format {
+        SubscriptionFormat::Auto => SubscriptionFormat::detect(data),
+        other => other,
+    };
+    let proxies = match format {
+        SubscriptionFormat::Auto | SubscriptionFormat::Clash => return Ok(data.to_owned()),
+        SubscriptionFormat::V2ray => sharelink::parse_share_links(data),
+        SubscriptionFormat::SingBox => convert_singbox(data)?,
+        SubscriptionFormat::Surge => section_lines(data, "[Proxy]").filter_map(convert_surge_line).collect(),
+        SubscriptionFormat::QuantumultX => section_lines(data, "[server_local]")
+            .filter_map(convert_quanx_line)
+            .collect(),
+    };
+    if proxies.is_empty() {
+        // 无法解析的 YAML 也会被识别为分享链接列表，此时 YAML 的错误才是真正的原因
+        if format == SubscriptionFormat::V2ray
+            && let Err(err) = serde_yaml_ng::from_str::<Mapping>(data)
+            && data.lines().all(|line| !sharelink::is_share_link(line))
+        {
+            bail!("the profile data is neither valid YAML nor a share link list: {err}");
+        }
+        bail!("no supported proxies found in {format:?} subscription");
+    }
+    build_profile(proxies)
+}
+
+/// 由节点列表生成一份可直接使用的配置
+pub fn build_profile(proxies: Vec<Mapping>) -> Result<String> {
+    let names: Vec<Value> = proxies.iter().filter_map(|p| p.get("name").cloned()).collect();
+
+    let mut group = Mapping::new();
+    insert(&mut group, "name", DEFAULT_GROUP);
+    insert(&mut group, "type", "select");
+    insert(&mut group, "proxies", names);
+
+    let mut config = Mapping::new();
+    insert(
+        &mut config,
+        "proxies",
+        proxies.into_iter().map(Value::Mapping).collect::<Vec<_>>(),
+    );
+    insert(&mut config, "proxy-groups", vec![Value::Mapping(group)]);
+    insert(
+        &mut config,
+        "rules",
+        vec![Value::from(format!("MATCH,{DEFAULT_GROUP}"))],
+    );
+    Ok(serde_yaml_ng::to_string(&config)?)
+}
+
+/// 某个 `[Section]` 下的非空、非注释行
+fn section_lines<'a>(data: &'a str, section: &'a str) -> impl Iterator<Item = &'a str> {
+    data.lines()
+        .map(str::trim)
+        .skip_while(move |line| !line.eq_ignore_ascii_case(section))
+        .skip(1)
+        .take_while(|line| !line.starts_with('['))
+        .filter(|line| !line.is_empty() && !line.starts_with('#') && !line.starts_with(';'))
+}
+
+fn is_true(value: Option<&&str>) -> bool {
+    value.is_some_and(|v| v.eq_ignore_ascii_case("true") || *v == "1")
+}
+
+/// `Name = type, server, port, key=value, ...`
+fn convert_surge_line(line: &str) -> Option<Mapping> {
+    let (name, rest) = line.split_once('=')?;
+    let parts: Vec<&str> = rest.split(',').map(str::trim).collect();
+    let kind = parts.first()?.to_ascii_lowercase();
+    let server = parts.get(1)?;
+    let port: u16 = parts.get(2)?.parse().ok()?;
+    let positional: Vec<&str> = parts.iter().skip(3).filter(|p| !p.contains('=')).copied().collect();
+    let opts: HashMap<&str, &str> = parts
+        .iter()
+        .skip(3)
+        .filter_map(|p| p.split_once('='))
+        .map(|(k, v)| (k.trim(), v.trim()))
+        .collect();
+    let name = name.trim();
+
+    let mut map = match kind.as_str() {
+        "ss" => {
+            let mut map = base_proxy(name, "ss", server, port);
+            insert(&mut map, "cipher", *opts.get("encrypt-method")?);
+            insert(&mut map, "password", *opts.get("password")?);
+            if let Some(obfs) = opts.get("obfs") {
+                let mut plugin_opts = Mapping::new();
+                insert(&mut plugin_opts, "mode", *obfs);
+                if let Some(host) = opts.get("obfs-host") {
+                    insert(&mut plugin_opts, "host", *host);
+                }
+                insert(&mut map, "plugin", "obfs");
+                insert(&mut map, "plugin-opts", plugin_opts);
+            }
+            map
+        }
+        "vmess" => {
+            let mut map = base_proxy(name, "vmess", server, port);
+            insert(&mut map, "uuid", *opts.get("username")?);
+            insert(&mut map, "alterId", 0);
+            insert(&mut map, "cipher", "auto");
+            if is_true(opts.get("tls")) {
+                insert(&mut map, "tls", true);
+                if let Some(sni) = opts.get("sni") {
+                    insert(&mut map, "servername", *sni);
+                }
+            }
+            map
+        }
+        "trojan" => {
+            let mut map = base_proxy(name, "trojan", server, port);
+            insert(&mut map, "password", *opts.get("password")?);
+            if let Some(sni) = opts.get("sni") {
+                insert(&mut map, "sni", *sni);
+            }
+            map
+        }
+        "hysteria2" => {
+            let mut map = base_proxy(name, "hysteria2", server, port);
+            insert(&mut map, "password", *opts.get("password")?);
+            if let Some(sni) = opts.get("sni") {
+                insert(&mut map, "sni", *sni);
+            }
+            map
+        }
+        "http" | "https" | "socks5" | "socks5-tls" => {
+            let proxy_type = if kind.starts_with("socks5") { "socks5" } else { "http" };
+            let mut map = base_proxy(name, proxy_type, server, port);
+            let username = opts.get("username").or_else(|| positional.first());
+            let password = opts.get("password").or_else(|| positional.get(1));
+            if let (Some(username), Some(password)) = (username, password) {
+                insert(&mut map, "username", *username);
+                insert(&mut map, "password", *password);
+            }
+            if kind == "https" || kind == "socks5-tls" {
+                insert(&mut map, "tls", true);
+            }
+            map
+        }
+        _ => return None,
+    };
+
+    if is_true(opts.get("ws")) {
+        let host = opts
+            .get("ws-headers")
+            .and_then(|h| {
+                h.split('|').find_map(|kv| {
+                    kv.split_once(':')
+                        .filter(|(k, _)| k.trim().eq_ignore_ascii_case("host"))
+                })
+            })
+            .map(|(_, v)| v.trim());
+        insert(&mut map, "network", "ws");
+        insert(&mut map, "ws-opts", ws_opts(opts.get("ws-path").copied(), host));
+    }
+    if is_true(opts.get("skip-cert-verify")) {
+        insert(&mut map, "skip-cert-verify", true);
+    }
+    if is_true(opts.get("udp-relay")) {
+        insert(&mut map, "udp", true);
+    }
+    Some(map)
+}
+
+/// `type=server:port, key=value, ..., tag=Name`
+fn convert_quanx_line(line: &str) -> Option<Mapping> {
+    let (kind, rest) = line.split_once('=')?;
+    let mut parts = rest.split(',').map(str::trim);
+    let (server, port) = parts.next()?.rsplit_once(':')?;
+    let port: u16 = port.parse().ok()?;
+    let opts: HashMap<&str, &str> = parts
+        .filter_map(|p| p.split_once('='))
+        .map(|(k, v)| (k.trim(), v.trim()))
+        .collect();
+    let name = opts.get("tag").copied().unwrap_or(server);
+    let obfs = opts.get("obfs").copied();
+    let over_tls = is_true(opts.get("over-tls")) || matches!(obfs, Some("wss" | "over-tls"));
+
+    let mut map = match kind.trim().to_ascii_lowercase().as_str() {
+        "shadowsocks" => {
+            let mut map = base_proxy(name, "ss", server, port);
+            insert(&mut map, "cipher", *opts.get("method")?);
+            insert(&mut map, "password", *opts.get("password")?);
+            if let Some(mode @ ("http" | "tls")) = obfs {
+                let mut plugin_opts = Mapping::new();
+                insert(&mut plugin_opts, "mode", mode);
+                if let Some(host) = opts.get("obfs-host") {
+                    insert(&mut plugin_opts, "host", *host);
+                }
+                insert(&mut map, "plugin", "obfs");
+                insert(&mut map, "plugin-opts", plugin_opts);
+            }
+            map
+        }
+        "vmess" => {
+            let mut map = base_proxy(name, "vmess", server, port);
+            insert(&mut map, "uuid", *opts.get("password")?);
+            insert(&mut map, "alterId", 0);
+            let cipher = opts
+                .get("method")
+                .copied()
+                .filter(|method| matches!(*method, "aes-128-gcm" | "chacha20-poly1305" | "none"))
+                .unwrap_or("auto");
+            insert(&mut map, "cipher", cipher);
+            map
+        }
+        "trojan" => {
+            let mut map = base_proxy(name, "trojan", server, port);
+            insert(&mut map, "password", *opts.get("password")?);
+            map
+        }
+        "http" => {
+            let mut map = base_proxy(name, "http", server, port);
+            if let (Some(username), Some(password)) = (opts.get("username"), opts.get("password")) {
+                insert(&mut map, "username", *username);
+                insert(&mut map, "password", *password);
+            }
+            map
+        }
+        _ => return None,
+    };
+
+    let host = opts.get("obfs-host").or_else(|| opts.get("tls-host")).copied();
+    if over_tls {
+        insert(&mut map, "tls", true);
+        if let Some(host) = host {
+            let key = if map.get("type").and_then(Value::as_str) == Some("trojan") {
+                "sni"
+            } else {
+                "servername"
+            };
+            insert(&mut map, key, host);
+        }
+    }
+    if matches!(obfs, Some("ws" | "wss")) && map.get("plugin").is_none() {
+        insert(&mut map, "network", "ws");
+        insert(&mut map, "ws-opts", ws_opts(opts.get("obfs-uri").copied(), host));
+    }
+    if opts
+        .get("tls-verification")
+        .is_some_and(|v| v.eq_ignore_ascii_case("false"))
+    {
+        insert(&mut map, "skip-cert-verify", true);
+    }
+    if is_true(opts.get("udp-relay")) {
+        insert(&mut map, "udp", true);
+    }
+    Some(map)
+}
+
+fn convert_singbox(data: &str) -> Result<Vec<Mapping>> {
+    let json: Json = serde_json::from_str(data).map_err(|err| anyhow!("invalid sing-box config: {err}"))?;
+    let outbounds = json["outbounds"]
+        .as_array()
+        .ok_or_else(|| anyhow!("sing-box config has no outbounds"))?;
+    Ok(outbounds.iter().filter_map(convert_singbox_outbound).collect())
+}
+
+fn convert_singbox_outbound(outbound: &Json) -> Option<Mapping> {
+    let field = |key: &str| outbound[key].as_str();
+    let name = field("tag")?;
+    let server = field("server")?;
+    let port = u16::try_from(outbound["server_port"].as_u64()?).ok()?;
+
+    let mut map = match field("type")? {
+        "shadowsocks" => {
+            let mut map = base_proxy(name, "ss", server, port);
+            insert(&mut map, "cipher", field("method")?);
+            insert(&mut map, "password", field("password")?);
+            map
+        }
+        "vmess" => {
+            let mut map = base_proxy(name, "vmess", server, port);
+            insert(&mut map, "uuid", field("uuid")?);
+            insert(&mut map, "alterId", outbound["alter_id"].as_u64().unwrap_or(0));
+            insert(&mut map, "cipher", field("security").unwrap_or("auto"));
+            map
+        }
+        "vless" => {
+            let mut map = base_proxy(name, "vless", server, port);
+            insert(&mut map, "uuid", field("uuid")?);
+            if let Some(flow) = field("flow").filter(|f| !f.is_empty()) {
+                insert(&mut map, "flow", flow);
+            }
+            map
+        }
+        "trojan" => {
+            let mut map = base_proxy(name, "trojan", server, port);
+            insert(&mut map, "password", field("password")?);
+            map
+        }
+        "hysteria2" => {
+            let mut map = base_proxy(name, "hysteria2", server, port);
+            insert(&mut map, "password", field("password")?);
+            if let Some(obfs) = outbound.get("obfs").filter(|o| o.is_object()) {
+                insert(&mut map, "obfs", obfs["type"].as_str().unwrap_or("salamander"));
+                insert(&mut map, "obfs-password", obfs["password"].as_str().unwrap_or_default());
+            }
+            map
+        }
+        "socks" => base_proxy(name, "socks5", server, port),
+        "http" => base_proxy(name, "http", server, port),
+        _ => return None,
+    };
+
+    let kind = map.get("type").and_then(Value::as_str).unwrap_or_default().to_owned();
+    let tls = &outbound["tls"];
+    if tls["enabled"].as_bool() == Some(true) {
+        if kind != "trojan" && kind != "hysteria2" {
+            insert(&mut map, "tls", true);
+        }
+        if let Some(sni) = tls["server_name"].as_str() {
+            let key = if matches!(kind.as_str(), "trojan" | "hysteria2") {
+                "sni"
+            } else {
+                "servername"
+            };
+            insert(&mut map, key, sni);
+        }
+        if tls["insecure"].as_bool() == Some(true) {
+            insert(&mut map, "skip-cert-verify", true);
+        }
+        if let Some(alpn) = tls["alpn"].as_array() {
+            let alpn: Vec<Value> = alpn.iter().filter_map(Json::as_str).map(Value::from).collect();
+            insert(&mut map, "alpn", alpn);
+        }
+        if let Some(fp) = tls["utls"]["fingerprint"].as_str() {
+            insert(&mut map, "client-fingerprint", fp);
+        }
+        let reality = &tls["reality"];
+        if reality["enabled"].as_bool() == Some(true) {
+            let mut opts = Mapping::new();
+            insert(
+                &mut opts,
+                "public-key",
+                reality["public_key"].as_str().unwrap_or_default(),
+            );
+            if let Some(short_id) = reality["short_id"].as_str() {
+                insert(&mut opts, "short-id", short_id);
+            }
+            insert(&mut map, "reality-opts", opts);
+        }
+    }
+
+    let transport = &outbound["transport"];
+    match transport["type"].as_str() {
+        Some("ws") => {
+            insert(&mut map, "network", "ws");
+            let host = transport["headers"]["Host"].as_str();
+            insert(&mut map, "ws-opts", ws_opts(transport["path"].as_str(), host));
+        }
+        Some("grpc") => {
+            insert(&mut map, "network", "grpc");
+            let mut opts = Mapping::new();
+            insert(
+                &mut opts,
+                "grpc-service-name",
+                transport["service_name"].as_str().unwrap_or_default(),
+            );
+            insert(&mut map, "grpc-opts", opts);
+        }
+        _ => {}
+    }
+    if kind != "hysteria2" {
+        insert(&mut map, "udp", true);
+    }
+    Some(map)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn proxies(yaml: &str) -> Vec<Mapping> {
+        serde_yaml_ng::from_str::<Mapping>(yaml)
+            .ok()
+            .and_then(|config| config.get("proxies").cloned())
+            .and_then(|proxies| serde_yaml_ng::from_value(proxies).ok())
+            .unwrap_or_default()
+    }
+
+    fn get<'a>(map: &'a Mapping, key: &str) -> Option<&'a str> {
+        map.get(key).and_then(Value::as_str)
+    }
+
+    #[test]
+    fn test_detect_format() {
+        assert_eq!(SubscriptionFormat::detect("proxies: []"), SubscriptionFormat::Clash);
+        assert_eq!(
+            SubscriptionFormat::detect(r#"{"outbounds": []}"#),
+            SubscriptionFormat::SingBox
+        );
+        assert_eq!(
+            SubscriptionFormat::detect("[General]\n[Proxy]\n"),
+            SubscriptionFormat::Surge
+        );
+        assert_eq!(
+            SubscriptionFormat::detect("[server_local]\n"),
+            SubscriptionFormat::QuantumultX
+        );
+        assert_eq!(SubscriptionFormat::detect("dHJvamFuOi8v"), SubscriptionFormat::V2ray);
+        let clash = "# keep comments\nproxies: []\n";
+        assert_eq!(
+            convert_subscription(clash, SubscriptionFormat::Auto).ok().as_deref(),
+            Some(clash)
+        );
+        let broken = convert_subscription("proxies:\n  - name: a\n   type: [ss\n", SubscriptionFormat::Auto);
+        assert!(broken.is_err_and(|err| err.to_string().contains("neither valid YAML")));
+    }
+
+    #[test]
+    fn test_convert_singbox() {
+        let data = r#"{"outbounds": [
+            {"type": "selector", "tag": "select", "outbounds": ["vless-out"]},
+            {"type": "vless", "tag": "vless-out", "server": "example.com", "server_port": 443,
+             "uuid": "uuid-1", "flow": "xtls-rprx-vision",
+             "tls": {"enabled": true, "server_name": "www.apple.com",
+                     "utls": {"enabled": true, "fingerprint": "chrome"},
+                     "reality": {"enabled": true, "public_key": "KEY", "short_id": "ab"}}},
+            {"type": "trojan", "tag": "trojan-out", "server": "t.example.com", "server_port": 443,
+             "password": "pw", "tls": {"enabled": true, "server_name": "t.example.com"},
+             "transport": {"type": "ws", "path": "/ws", "headers": {"Host": "cdn.example.com"}}}
+        ]}"#;
+        let converted = proxies(&convert_subscription(data, SubscriptionFormat::Auto).unwrap_or_default());
+        assert_eq!(converted.len(), 2);
+        assert_eq!(get(&converted[0], "servername"), Some("www.apple.com"));
+        assert!(converted[0].get("reality-opts").is_some());
+        assert_eq!(get(&converted[1], "sni"), Some("t.example.com"));
+        assert_eq!(get(&converted[1], "network"), Some("ws"));
+    }
+
+    #[test]
+    fn test_convert_surge_and_quanx() {
+        let surge = "[General]\nloglevel = notify\n[Proxy]\nDIRECT = direct\nHK = ss, 1.2.3.4, 8388, encrypt-method=aes-128-gcm, password=pass, udp-relay=true\nUS = trojan, t.example.com, 443, password=pw, sni=t.example.com, ws=true, ws-path=/ws\n[Proxy Group]\nPROXY = select, HK, US\n";
+        let converted = proxies(&convert_subscription(surge, SubscriptionFormat::Auto).unwrap_or_default());
+        assert_eq!(converted.len(), 2);
+        assert_eq!(get(&converted[0], "cipher"), Some("aes-128-gcm"));
+        assert_eq!(get(&converted[1], "network"), Some("ws"));
+
+        let quanx = "[server_local]\nshadowsocks=1.2.3.4:8388, method=aes-128-gcm, password=pass, tag=HK\nvmess=v.example.com:443, method=chacha20-poly1305, password=uuid-2, obfs=wss, obfs-host=v.example.com, obfs-uri=/v, tag=JP\n[filter_local]\n";
+        let converted = proxies(&convert_subscription(quanx, SubscriptionFormat::Auto).unwrap_or_default());
+        assert_eq!(converted.len(), 2);
+        assert_eq!(get(&converted[0], "name"), Some("HK"));
+        assert_eq!(get(&converted[1], "servername"), Some("v.example.com"));
+        assert_eq!(converted[1].get("tls"), Some(&Value::Bool(true)));
+    }
+}
@@ -0,0 +1,205 @@
+//! 内核日志
+//!
+//! 订阅内核 `/logs` 接口并缓存最近 [`MAX_LINES`] 条日志。前端通过 `subscribe_core_logs`
+//! 设置过滤条件后，匹配的新日志以 `verge://core-log` 事件推送，不再需要自行维护 WebSocket。
+
+use crate::{core::handle, process::AsyncHandler, singleton};
+use anyhow::Result;
+use clash_verge_logging::{Type, logging};
+use futures::StreamExt as _;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::VecDeque,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+use tauri::Emitter as _;
+
+const MAX_LINES: usize = 1000;
+const RECONNECT_DELAY: Duration = Duration::from_secs(3);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CoreLogLine {
+    #[serde(rename = "type")]
+    pub level: String,
+    pub payload: String,
+    /// 收到日志的时间，毫秒时间戳
+    #[serde(default)]
+    pub time: i64,
+}
+
+/// 过滤条件，未设置的字段不参与过滤
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct CoreLogFilter {
+    /// 最低等级：debug / info / warning / error
+    pub level: Option<String>,
+    /// 不区分大小写的关键字
+    pub keyword: Option<String>,
+    /// 连接相关的地址、端口或进程名，按原文匹配
+    pub connection: Option<String>,
+}
+
+fn level_rank(level: &str) -> u8 {
+    match level {
+        "debug" => 0,
+        "info" => 1,
+        "warning" | "warn" => 2,
+        "error" => 3,
+        _ => 1,
+    }
+}
+
+impl CoreLogFilter {
+    pub fn matches(&self, line: &CoreLogLine) -> bool {
+        if let Some(level) = self.level.as_deref()
+            && level_rank(&line.level) < level_rank(level)
+        {
+            return false;
+        }
+        if let Some(keyword) = self.keyword.as_deref().filter(|k| !k.is_empty())
+            && !line.payload.to_lowercase().contains(&keyword.to_lowercase())
+        {
+            return false;
+        }
+        if let Some(connection) = self.connection.as_deref().filter(|c| !c.is_empty())
+            && !line.payload.contains(connection)
+        {
+            return false;
+        }
+        true
+    }
+}
+
+/// 取出缓冲区中完整的行并解析，不完整的行留在缓冲区
+fn drain_lines(buffer: &mut Vec<u8>) -> Vec<CoreLogLine> {
+    let Some(end) = buffer.iter().rposition(|&b| b == b'\n') else {
+        return Vec::new();
+    };
+    let lines = buffer[..end]
+        .split(|&b| b == b'\n')
+        .filter_map(|line| serde_json::from_slice::<CoreLogLine>(line).ok())
+        .collect();
+    buffer.drain(..=end);
+    lines
+}
+
+pub struct CoreLogHub {
+    lines: Mutex<VecDeque<CoreLogLine>>,
+    filter: Mutex<Option<CoreLogFilter>>,
+    runner_started: AtomicBool,
+}
+
+singleton!(CoreLogHub, CORE_LOG_HUB);
+
+impl CoreLogHub {
+    fn new() -> Self {
+        Self {
+            lines: Mutex::new(VecDeque::with_capacity(MAX_LINES)),
+            filter: Mutex::new(None),
+            runner_started: AtomicBool::new(false),
+        }
+    }
+
+    fn init(&self) {
+        if self.runner_started.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        AsyncHandler::spawn(|| async {
+            let hub = Self::global();
+            loop {
+                if handle::Handle::global().is_exiting() {
+                    break;
+                }
+                if let Err(err) = hub.stream().await {
+                    logging!(debug, Type::Core, "Core log stream interrupted: {err}");
+                }
+                tokio::time::sleep(RECONNECT_DELAY).await;
+            }
+        });
+    }
+
+    /// 设置过滤条件并开始推送，返回缓存中已匹配的日志
+    pub fn subscribe(&self, filter: CoreLogFilter) -> Vec<CoreLogLine> {
+        self.init();
+        let backlog = self
+            .lines
+            .lock()
+            .iter()
+            .filter(|line| filter.matches(line))
+            .cloned()
+            .collect();
+        *self.filter.lock() = Some(filter);
+        backlog
+    }
+
+    /// 停止向前端推送，后台仍继续缓存
+    pub fn unsubscribe(&self) {
+        *self.filter.lock() = None;
+    }
+
+    fn push(&self, mut line: CoreLogLine) {
+        line.time = chrono::Local::now().timestamp_millis();
+        let matched = self.filter.lock().as_ref().is_some_and(|filter| filter.matches(&line));
+        if matched {
+            let _ = handle::Handle::app_handle().emit("verge://core-log", &line);
+        }
+        let mut lines = self.lines.lock();
+        if lines.len() >= MAX_LINES {
+            lines.pop_front();
+        }
+        lines.push_back(line);
+    }
+
+    async fn stream(&self) -> Result<()> {
+        let mut stream = handle::Handle::mihomo_http()
+            .stream("/logs?level=debug")
+            .await?
+            .bytes_stream();
+        let mut buffer = Vec::new();
+        while let Some(chunk) = stream.next().await {
+            buffer.extend_from_slice(&chunk?);
+            for line in drain_lines(&mut buffer) {
+                self.push(line);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(level: &str, payload: &str) -> CoreLogLine {
+        CoreLogLine {
+            level: level.into(),
+            payload: payload.into(),
+            time: 0,
+        }
+    }
+
+    #[test]
+    fn test_drain_lines() {
+        let mut buffer = br#"{"type":"info","payload":"a"}
+{"type":"debug","payload":"b"}
+{"type":"#
+            .to_vec();
+        assert_eq!(drain_lines(&mut buffer), vec![line("info", "a"), line("debug", "b")]);
+        assert_eq!(buffer, br#"{"type":"#);
+    }
+
+    #[test]
+    fn test_filter() {
+        let filter = CoreLogFilter {
+            level: Some("info".into()),
+            keyword: Some("match".into()),
+            connection: Some("1.2.3.4".into()),
+        };
+        assert!(filter.matches(&line("warning", "[TCP] 1.2.3.4:5000 --> example.com:443 MATCH rule")));
+        assert!(!filter.matches(&line("debug", "[TCP] 1.2.3.4:5000 --> example.com:443 match")));
+        assert!(!filter.matches(&line("info", "[TCP] 5.6.7.8:5000 --> example.com:443 match")));
+        assert!(CoreLogFilter::default().matches(&line("debug", "anything")));
+    }
+}
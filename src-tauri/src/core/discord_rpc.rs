@@ -209,7 +209,6 @@ impl DiscordRpcManager {
     }
 
     /// Check if connected to Discord
-    #[allow(dead_code)]
     pub fn is_connected(&self) -> bool {
         *self.connected.lock()
     }
@@ -240,8 +239,21 @@ pub fn connect_discord_rpc() {
     }
 }
 
+/// Wait until the worker has connected to Discord, returns false on timeout
+pub async fn wait_for_connection(timeout: std::time::Duration) -> bool {
+    let deadline = std::time::Instant::now() + timeout;
+    loop {
+        if DISCORD_RPC.lock().as_ref().is_some_and(DiscordRpcManager::is_connected) {
+            return true;
+        }
+        if std::time::Instant::now() >= deadline {
+            return false;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+}
+
 /// Disconnect from Discord RPC
-#[allow(dead_code)]
 pub fn disconnect_discord_rpc() {
     let guard = DISCORD_RPC.lock();
     if let Some(ref manager) = *guard {
@@ -0,0 +1,99 @@
+//! 应用内状态事件总线
+//!
+//! 订阅切换、模式变化、TUN / 系统代理开关、节点切换、内核重启等状态变化在此广播，
+//! 托盘、Discord RPC、回调与插件各自订阅并更新，发布方无需知道有哪些订阅者。
+
+use crate::{process::AsyncHandler, singleton};
+use clash_verge_logging::{Type, logging};
+use smartstring::alias::String;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+const CHANNEL_CAPACITY: usize = 64;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StateEvent {
+    /// 切换到另一个订阅
+    ProfileSwitched {
+        uid: String,
+    },
+    /// 订阅内容已从远程更新
+    ProfileUpdated {
+        uid: String,
+    },
+    /// 代理模式（rule / global / direct）变化
+    ModeChanged {
+        mode: String,
+    },
+    TunToggled {
+        enabled: bool,
+    },
+    SystemProxyToggled {
+        enabled: bool,
+    },
+    NodeSwitched {
+        group: String,
+        node: String,
+    },
+    CoreRestarted,
+}
+
+pub struct EventBus {
+    sender: broadcast::Sender<StateEvent>,
+}
+
+singleton!(EventBus, EVENT_BUS);
+
+impl EventBus {
+    fn new() -> Self {
+        let (sender, _) = broadcast::channel(CHANNEL_CAPACITY);
+        Self { sender }
+    }
+
+    /// 广播事件，没有订阅者时直接丢弃
+    pub fn publish(&self, event: StateEvent) {
+        logging!(debug, Type::System, "State event: {event:?}");
+        let _ = self.sender.send(event);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<StateEvent> {
+        self.sender.subscribe()
+    }
+
+    /// 在后台按顺序处理事件，上一个事件处理完才接收下一个
+    pub fn listen<F, Fut>(&self, name: &'static str, handler: F)
+    where
+        F: Fn(StateEvent) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let mut receiver = self.subscribe();
+        AsyncHandler::spawn(move || async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(event) => handler(event).await,
+                    Err(RecvError::Lagged(skipped)) => {
+                        logging!(warn, Type::System, "{name} skipped {skipped} state events");
+                    }
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_publish_reaches_all_subscribers() {
+        let bus = EventBus::new();
+        let mut first = bus.subscribe();
+        let mut second = bus.subscribe();
+        bus.publish(StateEvent::TunToggled { enabled: true });
+        bus.publish(StateEvent::CoreRestarted);
+
+        assert_eq!(first.try_recv().ok(), Some(StateEvent::TunToggled { enabled: true }));
+        assert_eq!(first.try_recv().ok(), Some(StateEvent::CoreRestarted));
+        assert_eq!(second.try_recv().ok(), Some(StateEvent::TunToggled { enabled: true }));
+    }
+}
@@ -0,0 +1,201 @@
+//! GeoIP / GeoSite / MMDB 数据库更新
+//!
+//! 从 `geo_mirrors` 配置的镜像（为空时使用内置镜像）依次尝试下载 meta-rules-dat 发布的
+//! 数据库文件及其 `.sha256sum` 摘要，校验通过后写入临时文件再重命名替换内核目录中的旧文件，
+//! 最后重启内核使新数据生效。
+
+use crate::{
+    config::Config,
+    core::{CoreManager, manager::RunningMode},
+    utils::{
+        dirs,
+        network::{NetworkManager, ProxyType},
+    },
+};
+use anyhow::{Result, anyhow, bail};
+use clash_verge_logging::{Type, logging};
+use serde::Serialize;
+use sha2::{Digest as _, Sha256};
+use smartstring::alias::String;
+use std::path::Path;
+
+const DEFAULT_MIRRORS: [&str; 2] = [
+    "https://github.com/MetaCubeX/meta-rules-dat/releases/download/latest/",
+    "https://testingcf.jsdelivr.net/gh/MetaCubeX/meta-rules-dat@release/",
+];
+/// (本地文件名, 远程文件名)
+const GEO_FILES: [(&str, &str); 3] = [
+    ("geoip.dat", "geoip.dat"),
+    ("geosite.dat", "geosite.dat"),
+    ("Country.mmdb", "country.mmdb"),
+];
+const REQUEST_TIMEOUT_SECS: u64 = 30;
+const DOWNLOAD_TIMEOUT_SECS: u64 = 300;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GeoUpdateInfo {
+    pub file: String,
+    pub local_hash: Option<String>,
+    pub remote_hash: String,
+    pub has_update: bool,
+    /// 提供摘要的镜像
+    pub mirror: String,
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data).iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// 解析 `sha256sum` 输出，取第一列
+fn parse_checksum(text: &str) -> Option<String> {
+    let hash = text.split_whitespace().next()?;
+    (hash.len() == 64 && hash.bytes().all(|b| b.is_ascii_hexdigit())).then(|| hash.to_ascii_lowercase().into())
+}
+
+fn proxy_type() -> ProxyType {
+    if *CoreManager::global().get_running_mode() == RunningMode::NotRunning {
+        ProxyType::None
+    } else {
+        ProxyType::Localhost
+    }
+}
+
+async fn mirrors() -> Vec<String> {
+    let verge = Config::verge().await.latest_arc();
+    let mirrors: Vec<String> = verge
+        .geo_mirrors
+        .iter()
+        .flatten()
+        .map(|mirror| mirror.trim())
+        .filter(|mirror| mirror.starts_with("https://") || mirror.starts_with("http://"))
+        .map(|mirror| {
+            if mirror.ends_with('/') {
+                mirror.into()
+            } else {
+                format!("{mirror}/").into()
+            }
+        })
+        .collect();
+    if mirrors.is_empty() {
+        DEFAULT_MIRRORS.iter().map(|&mirror| mirror.into()).collect()
+    } else {
+        mirrors
+    }
+}
+
+async fn fetch(url: &str, timeout: u64) -> Result<Vec<u8>> {
+    let client = NetworkManager::new()
+        .create_request(proxy_type(), Some(timeout), None, false)
+        .await?;
+    let bytes = client.get(url).send().await?.error_for_status()?.bytes().await?;
+    Ok(bytes.to_vec())
+}
+
+/// 按镜像顺序获取远程摘要
+async fn remote_checksum(mirrors: &[String], remote: &str) -> Result<(String, String)> {
+    let mut last_error = anyhow!("no mirror available");
+    for mirror in mirrors {
+        match fetch(&format!("{mirror}{remote}.sha256sum"), REQUEST_TIMEOUT_SECS).await {
+            Ok(body) => {
+                if let Some(hash) = parse_checksum(&std::string::String::from_utf8_lossy(&body)) {
+                    return Ok((hash, mirror.clone()));
+                }
+                last_error = anyhow!("invalid checksum from {mirror}");
+            }
+            Err(err) => {
+                logging!(
+                    debug,
+                    Type::Network,
+                    "[Geo] {} 获取 {} 摘要失败: {}",
+                    mirror,
+                    remote,
+                    err
+                );
+                last_error = err;
+            }
+        }
+    }
+    Err(last_error)
+}
+
+async fn local_checksum(path: &Path) -> Option<String> {
+    tokio::fs::read(path).await.ok().map(|data| sha256_hex(&data))
+}
+
+/// 检查各数据库文件是否有更新
+pub async fn check_geo_updates() -> Result<Vec<GeoUpdateInfo>> {
+    let home = dirs::app_home_dir()?;
+    let mirrors = mirrors().await;
+    let mut updates = Vec::new();
+    for (local, remote) in GEO_FILES {
+        let (remote_hash, mirror) = remote_checksum(&mirrors, remote).await?;
+        let local_hash = local_checksum(&home.join(local)).await;
+        updates.push(GeoUpdateInfo {
+            file: local.into(),
+            has_update: local_hash.as_ref() != Some(&remote_hash),
+            local_hash,
+            remote_hash,
+            mirror,
+        });
+    }
+    Ok(updates)
+}
+
+/// 先写临时文件再重命名，保证内核不会读到写了一半的文件
+async fn replace_file(target: &Path, data: &[u8]) -> Result<()> {
+    let staged = target.with_extension("download");
+    tokio::fs::write(&staged, data).await?;
+    if let Err(err) = tokio::fs::rename(&staged, target).await {
+        let _ = tokio::fs::remove_file(&staged).await;
+        return Err(err.into());
+    }
+    Ok(())
+}
+
+/// 下载并替换有更新的数据库文件，返回已更新的文件名
+pub async fn update_geo_databases() -> Result<Vec<String>> {
+    let home = dirs::app_home_dir()?;
+    let mut updated = Vec::new();
+    for info in check_geo_updates().await?.into_iter().filter(|info| info.has_update) {
+        let remote = GEO_FILES
+            .iter()
+            .find(|(local, _)| *local == info.file.as_str())
+            .map(|(_, remote)| *remote)
+            .ok_or_else(|| anyhow!("unknown geo file {}", info.file))?;
+
+        let data = fetch(&format!("{}{remote}", info.mirror), DOWNLOAD_TIMEOUT_SECS).await?;
+        let actual = sha256_hex(&data);
+        if actual != info.remote_hash {
+            bail!(
+                "checksum mismatch for {}: expected {}, got {actual}",
+                info.file,
+                info.remote_hash
+            );
+        }
+        replace_file(&home.join(info.file.as_str()), &data).await?;
+        logging!(info, Type::Core, "[Geo] 已更新 {} ({} bytes)", info.file, data.len());
+        updated.push(info.file);
+    }
+
+    if !updated.is_empty() && *CoreManager::global().get_running_mode() != RunningMode::NotRunning {
+        CoreManager::global().restart_core().await?;
+    }
+    Ok(updated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_checksum() {
+        let hash = "2CF24DBA5FB0A30E26E83B2AC5B9E29E1B161E5C1FA7425E73043362938B9824";
+        assert_eq!(
+            parse_checksum(&format!("{hash}  geoip.dat\n")).as_deref(),
+            Some(hash.to_ascii_lowercase().as_str())
+        );
+        assert!(parse_checksum("not found").is_none());
+        assert!(parse_checksum("").is_none());
+        assert_eq!(sha256_hex(b"hello").as_str(), hash.to_ascii_lowercase());
+    }
+}
@@ -12,7 +12,10 @@ use tauri::{AppHandle, Manager as _, WebviewWindow};
 use tauri_plugin_mihomo::{Mihomo, MihomoExt as _};
 use tokio::sync::RwLockReadGuard;
 
-use super::notification::{ErrorMessage, FrontendEvent, NotificationSystem};
+use super::{
+    mihomo_http::MihomoHttp,
+    notification::{ErrorMessage, FrontendEvent, NotificationSystem},
+};
 
 #[derive(Debug)]
 pub struct Handle {
@@ -62,6 +65,11 @@ impl Handle {
         Self::app_handle().mihomo().read().await
     }
 
+    /// 插件未覆盖的控制接口
+    pub fn mihomo_http() -> &'static MihomoHttp {
+        MihomoHttp::global()
+    }
+
     pub fn get_window() -> Option<WebviewWindow> {
         Self::app_handle().get_webview_window("main")
     }
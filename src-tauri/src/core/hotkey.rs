@@ -1,13 +1,15 @@
+use crate::core::audit::AuditSource;
+use crate::core::notify::{NotificationEvent, notify_event};
 use crate::process::AsyncHandler;
 use crate::singleton;
-use crate::utils::notification::{NotificationEvent, notify_event};
 use crate::{config::Config, core::handle, feat, module::lightweight::entry_lightweight_mode};
 use anyhow::{Result, bail};
 use arc_swap::ArcSwap;
 use clash_verge_logging::{Type, logging};
+use serde::Serialize;
 use smartstring::alias::String;
 use std::{collections::HashMap, fmt, str::FromStr, sync::Arc};
-use tauri_plugin_global_shortcut::{Code, GlobalShortcutExt as _, ShortcutState};
+use tauri_plugin_global_shortcut::{Code, GlobalShortcutExt as _, Shortcut, ShortcutState};
 
 /// Enum representing all available hotkey functions
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -20,6 +22,7 @@ pub enum HotkeyFunction {
     ToggleTunMode,
     EntryLightweightMode,
     ReactivateProfiles,
+    CopyProxyEnv,
     Quit,
     #[cfg(target_os = "macos")]
     Hide,
@@ -36,6 +39,7 @@ impl fmt::Display for HotkeyFunction {
             Self::ToggleTunMode => "toggle_tun_mode",
             Self::EntryLightweightMode => "entry_lightweight_mode",
             Self::ReactivateProfiles => "reactivate_profiles",
+            Self::CopyProxyEnv => "copy_proxy_env",
             Self::Quit => "quit",
             #[cfg(target_os = "macos")]
             Self::Hide => "hide",
@@ -57,6 +61,7 @@ impl FromStr for HotkeyFunction {
             "toggle_tun_mode" => Ok(Self::ToggleTunMode),
             "entry_lightweight_mode" => Ok(Self::EntryLightweightMode),
             "reactivate_profiles" => Ok(Self::ReactivateProfiles),
+            "copy_proxy_env" => Ok(Self::CopyProxyEnv),
             "quit" => Ok(Self::Quit),
             #[cfg(target_os = "macos")]
             "hide" => Ok(Self::Hide),
@@ -94,6 +99,38 @@ impl SystemHotkey {
     }
 }
 
+/// 单条快捷键配置及其注册状态
+#[derive(Debug, Clone, Serialize)]
+pub struct HotkeyBinding {
+    pub function: String,
+    pub key: String,
+    pub registered: bool,
+}
+
+/// 校验快捷键配置，格式为 `function,key`，
+/// 同一功能重复绑定或不同功能绑定到同一组合键均视为冲突
+pub fn check_conflicts(hotkeys: &[String]) -> Result<()> {
+    let mut functions = HashMap::new();
+    let mut shortcuts: HashMap<u32, &str> = HashMap::new();
+
+    for hotkey in hotkeys {
+        let Some((func, key)) = hotkey.split_once(',') else {
+            bail!("invalid hotkey configuration: `{hotkey}`");
+        };
+        let (func, key) = (func.trim(), key.trim());
+        let function = HotkeyFunction::from_str(func)?;
+        let shortcut = Shortcut::from_str(key).map_err(|e| anyhow::anyhow!("invalid hotkey `{key}`: {e}"))?;
+
+        if functions.insert(function, key).is_some() {
+            bail!("hotkey function `{func}` is bound more than once");
+        }
+        if let Some(other) = shortcuts.insert(shortcut.id(), func) {
+            bail!("hotkey `{key}` is used by both `{other}` and `{func}`");
+        }
+    }
+    Ok(())
+}
+
 pub struct Hotkey {
     current: ArcSwap<Vec<String>>,
 }
@@ -116,31 +153,35 @@ impl Hotkey {
             }
             HotkeyFunction::ClashModeRule => {
                 AsyncHandler::spawn(async move || {
-                    feat::change_clash_mode("rule".into()).await;
+                    AuditSource::Hotkey.scope(feat::change_clash_mode("rule".into())).await;
                     notify_event(NotificationEvent::ClashModeChanged { mode: "Rule" }).await;
                 });
             }
             HotkeyFunction::ClashModeGlobal => {
                 AsyncHandler::spawn(async move || {
-                    feat::change_clash_mode("global".into()).await;
+                    AuditSource::Hotkey
+                        .scope(feat::change_clash_mode("global".into()))
+                        .await;
                     notify_event(NotificationEvent::ClashModeChanged { mode: "Global" }).await;
                 });
             }
             HotkeyFunction::ClashModeDirect => {
                 AsyncHandler::spawn(async move || {
-                    feat::change_clash_mode("direct".into()).await;
+                    AuditSource::Hotkey
+                        .scope(feat::change_clash_mode("direct".into()))
+                        .await;
                     notify_event(NotificationEvent::ClashModeChanged { mode: "Direct" }).await;
                 });
             }
             HotkeyFunction::ToggleSystemProxy => {
                 AsyncHandler::spawn(async move || {
-                    feat::toggle_system_proxy().await;
+                    AuditSource::Hotkey.scope(feat::toggle_system_proxy()).await;
                     notify_event(NotificationEvent::SystemProxyToggled).await;
                 });
             }
             HotkeyFunction::ToggleTunMode => {
                 AsyncHandler::spawn(async move || {
-                    feat::toggle_tun_mode(None).await;
+                    AuditSource::Hotkey.scope(feat::toggle_tun_mode(None)).await;
                     notify_event(NotificationEvent::TunModeToggled).await;
                 });
             }
@@ -181,6 +222,11 @@ impl Hotkey {
                     }
                 });
             }
+            HotkeyFunction::CopyProxyEnv => {
+                AsyncHandler::spawn(async move || {
+                    feat::copy_clash_env().await;
+                });
+            }
             HotkeyFunction::Quit => {
                 AsyncHandler::spawn(async move || {
                     notify_event(NotificationEvent::AppQuit).await;
@@ -388,8 +434,18 @@ impl Hotkey {
             let _ = self.unregister(key);
         });
 
+        // 逐个注册，被其他程序占用的组合键不影响其余快捷键，注册状态通过 `bindings` 查询
         for (key, func) in add.iter() {
-            self.register(key, func).await?;
+            if let Err(e) = self.register(key, func).await {
+                logging!(
+                    warn,
+                    Type::Hotkey,
+                    "Failed to register hotkey {} -> {}: {}",
+                    key,
+                    func,
+                    e
+                );
+            }
         }
 
         // Update the current hotkeys after all async operations
@@ -397,6 +453,25 @@ impl Hotkey {
         Ok(())
     }
 
+    /// 当前配置的快捷键及是否已成功注册
+    pub fn bindings(&self) -> Vec<HotkeyBinding> {
+        let app_handle = handle::Handle::app_handle();
+        let manager = app_handle.global_shortcut();
+        self.current
+            .load()
+            .iter()
+            .filter_map(|hotkey| {
+                let (func, key) = hotkey.split_once(',')?;
+                let key = key.trim();
+                Some(HotkeyBinding {
+                    function: func.trim().into(),
+                    key: key.into(),
+                    registered: manager.is_registered(key),
+                })
+            })
+            .collect()
+    }
+
     fn get_map_from_vec(hotkeys: &[String]) -> HashMap<&str, &str> {
         let mut map = HashMap::new();
 
@@ -451,3 +526,28 @@ impl Drop for Hotkey {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_conflicts() {
+        let ok: Vec<String> = vec![
+            "toggle_system_proxy,CmdOrControl+Alt+S".into(),
+            "copy_proxy_env,Alt+E".into(),
+        ];
+        assert!(check_conflicts(&ok).is_ok());
+
+        let same_key: Vec<String> = vec!["clash_mode_rule,Alt+R".into(), "clash_mode_global,alt+r".into()];
+        assert!(check_conflicts(&same_key).is_err());
+
+        let same_func: Vec<String> = vec!["clash_mode_rule,Alt+R".into(), "clash_mode_rule,Alt+G".into()];
+        assert!(check_conflicts(&same_func).is_err());
+
+        let unknown: Vec<String> = vec!["unknown,Alt+R".into()];
+        assert!(check_conflicts(&unknown).is_err());
+        let malformed: Vec<String> = vec!["clash_mode_rule".into()];
+        assert!(check_conflicts(&malformed).is_err());
+    }
+}
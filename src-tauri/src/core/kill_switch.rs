@@ -0,0 +1,420 @@
+//! 内核断开保护（Kill-switch）
+//!
+//! 内核崩溃时安装防火墙规则阻断出站流量，仅放行回环流量与内核自身的出站连接。
+//! Linux 使用 nftables，按内核出站连接的 `routing-mark` 放行；macOS 使用 pf 锚点，
+//! 服务模式下按内核所属的 root 用户放行；Windows 通过 netsh 调整防火墙出站策略
+//! （底层为 Windows Filtering Platform），按内核程序路径放行。
+//!
+//! 能够放行内核时，规则在本次运行期间一直保留，只需提权一次，直到关闭该功能或退出应用。
+//! macOS 侧车模式下内核与其他进程同属当前用户，无法单独放行，只能在内核恢复后移除规则。
+
+use crate::{config::Config, core::handle, process::AsyncHandler, singleton, utils::dirs};
+use anyhow::{Result, bail};
+use clash_verge_logging::{Type, logging, logging_error};
+use serde::Serialize;
+#[cfg(target_os = "linux")]
+use serde_yaml_ng::{Mapping, Value};
+use std::{
+    path::PathBuf,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+use tokio::sync::Mutex;
+
+/// 规则生效期间存在的标记文件，内容为移除规则时需要的平台状态，
+/// 应用异常退出后下次启动据此清理残留规则
+const ENGAGED_MARKER: &str = ".kill_switch";
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_millis(500);
+const HEALTH_CHECK_ATTEMPTS: u32 = 20;
+
+/// 开启断开保护时写入运行配置的默认 `routing-mark`，订阅已设置时沿用订阅的值
+#[cfg(target_os = "linux")]
+const ROUTING_MARK: u32 = 0x2024;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct KillSwitchStatus {
+    pub enabled: bool,
+    pub engaged: bool,
+}
+
+/// 用于放行内核出站连接的标识
+struct CoreIdentity {
+    #[cfg(target_os = "windows")]
+    program: PathBuf,
+    #[cfg(target_os = "linux")]
+    routing_mark: u32,
+    #[cfg(target_os = "macos")]
+    service_mode: bool,
+}
+
+/// 安装规则的结果
+struct Engaged {
+    /// 移除规则时需要的状态，写入标记文件
+    restore: std::string::String,
+    exempts_core: bool,
+}
+
+#[derive(Default)]
+pub struct KillSwitch {
+    engaged: AtomicBool,
+    /// 规则是否放行内核自身，放行时内核恢复后无需移除规则
+    exempts_core: AtomicBool,
+    /// 串行化规则的安装与移除，避免多次弹出提权窗口
+    op_lock: Mutex<()>,
+}
+
+singleton!(KillSwitch, KILL_SWITCH);
+
+fn marker_path() -> Result<PathBuf> {
+    Ok(dirs::app_home_dir()?.join(ENGAGED_MARKER))
+}
+
+/// 开启断开保护时为内核出站连接打上 `routing-mark`
+#[cfg(target_os = "linux")]
+pub fn use_routing_mark(mut config: Mapping, enabled: bool) -> Mapping {
+    if enabled && !config.contains_key("routing-mark") {
+        config.insert("routing-mark".into(), ROUTING_MARK.into());
+    }
+    config
+}
+
+impl CoreIdentity {
+    // 只有 Windows 需要读取内核路径，可能失败
+    #[cfg_attr(not(target_os = "windows"), allow(clippy::unnecessary_wraps))]
+    #[cfg_attr(target_os = "macos", allow(clippy::unused_async))]
+    async fn current() -> Result<Self> {
+        #[cfg(target_os = "windows")]
+        let program = {
+            let verge = Config::verge().await.latest_arc();
+            crate::core::manager::core_binary_path(&verge, &verge.get_valid_clash_core())?
+        };
+        #[cfg(target_os = "linux")]
+        let routing_mark = Config::runtime()
+            .await
+            .latest_arc()
+            .config
+            .as_ref()
+            .and_then(|config| config.get("routing-mark"))
+            .and_then(Value::as_u64)
+            .and_then(|mark| u32::try_from(mark).ok())
+            .unwrap_or(ROUTING_MARK);
+        #[cfg(target_os = "macos")]
+        let service_mode =
+            *crate::core::CoreManager::global().get_running_mode() == crate::core::manager::RunningMode::Service;
+
+        Ok(Self {
+            #[cfg(target_os = "windows")]
+            program,
+            #[cfg(target_os = "linux")]
+            routing_mark,
+            #[cfg(target_os = "macos")]
+            service_mode,
+        })
+    }
+}
+
+impl KillSwitch {
+    fn new() -> Self {
+        Self::default()
+    }
+
+    async fn is_enabled() -> bool {
+        Config::verge().await.latest_arc().enable_kill_switch.unwrap_or(false)
+    }
+
+    pub fn is_engaged(&self) -> bool {
+        self.engaged.load(Ordering::Acquire)
+    }
+
+    fn has_stale_rules() -> bool {
+        marker_path().is_ok_and(|path| path.exists())
+    }
+
+    pub async fn status(&self) -> KillSwitchStatus {
+        KillSwitchStatus {
+            enabled: Self::is_enabled().await,
+            engaged: self.is_engaged(),
+        }
+    }
+
+    /// 安装阻断规则，未开启 `enable_kill_switch` 或本次运行已安装时不做任何操作
+    pub async fn engage(&self) -> Result<()> {
+        if !Self::is_enabled().await {
+            return Ok(());
+        }
+        let _guard = self.op_lock.lock().await;
+        if self.is_engaged() {
+            return Ok(());
+        }
+
+        logging!(warn, Type::Core, "Kill-switch engaged, blocking outbound traffic");
+        let core = CoreIdentity::current().await?;
+        let engaged = AsyncHandler::spawn_blocking(move || platform::engage(&core)).await??;
+        self.exempts_core.store(engaged.exempts_core, Ordering::Release);
+        self.engaged.store(true, Ordering::Release);
+        tokio::fs::write(marker_path()?, engaged.restore).await?;
+        Ok(())
+    }
+
+    /// 移除阻断规则，包括上次运行残留的规则
+    pub async fn release(&self) -> Result<()> {
+        let _guard = self.op_lock.lock().await;
+        if !self.is_engaged() && !Self::has_stale_rules() {
+            return Ok(());
+        }
+
+        logging!(info, Type::Core, "Kill-switch released, restoring outbound traffic");
+        let marker = marker_path()?;
+        let restore = tokio::fs::read_to_string(&marker).await.unwrap_or_default();
+        AsyncHandler::spawn_blocking(move || platform::release(&restore)).await??;
+        self.engaged.store(false, Ordering::Release);
+        self.exempts_core.store(false, Ordering::Release);
+        let _ = tokio::fs::remove_file(marker).await;
+        Ok(())
+    }
+
+    /// 内核启动后调用：清理上次运行残留的规则，以及无法放行内核时本次安装的规则。
+    /// 移除前等待内核控制接口可用
+    pub fn release_when_healthy(&'static self) {
+        let needs_release = if self.is_engaged() {
+            !self.exempts_core.load(Ordering::Acquire)
+        } else {
+            Self::has_stale_rules()
+        };
+        if !needs_release {
+            return;
+        }
+        AsyncHandler::spawn(move || async move {
+            for _ in 0..HEALTH_CHECK_ATTEMPTS {
+                if handle::Handle::mihomo().await.get_version().await.is_ok() {
+                    logging_error!(Type::Core, self.release().await);
+                    return;
+                }
+                tokio::time::sleep(HEALTH_CHECK_INTERVAL).await;
+            }
+            logging!(
+                warn,
+                Type::Core,
+                "Core did not become healthy, kill-switch stays engaged"
+            );
+        });
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use super::{CoreIdentity, Engaged, Result, bail};
+    use std::process::Command;
+
+    const TABLE: &str = "inet clash_verge_killswitch";
+
+    fn run_privileged(script: &str) -> Result<()> {
+        let status = if crate::core::service::linux_running_as_root() {
+            Command::new("sh").arg("-c").arg(script).status()?
+        } else {
+            let elevator = crate::utils::help::linux_elevator();
+            Command::new(&elevator).arg("sh").arg("-c").arg(script).status()?
+        };
+        if !status.success() {
+            bail!("nft exited with status {}", status.code().unwrap_or(-1));
+        }
+        Ok(())
+    }
+
+    pub fn engage(core: &CoreIdentity) -> Result<Engaged> {
+        let mark = core.routing_mark;
+        run_privileged(&format!(
+            "nft delete table {TABLE} 2>/dev/null; \
+             nft add table {TABLE} && \
+             nft add chain {TABLE} output '{{ type filter hook output priority 0; policy drop; }}' && \
+             nft add rule {TABLE} output oifname lo accept && \
+             nft add rule {TABLE} output meta mark {mark} accept"
+        ))?;
+        Ok(Engaged {
+            restore: std::string::String::new(),
+            exempts_core: true,
+        })
+    }
+
+    pub fn release(_restore: &str) -> Result<()> {
+        run_privileged(&format!("nft delete table {TABLE} 2>/dev/null || true"))
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use super::{CoreIdentity, Engaged, Result, bail};
+    use std::process::Command;
+
+    // 系统默认 pf.conf 会加载 com.apple/* 下的锚点
+    const ANCHOR: &str = "com.apple/clash-verge-killswitch";
+
+    fn run_privileged(script: &str) -> Result<std::string::String> {
+        let prompt = "Clash Verge needs administrator privileges to update firewall rules";
+        let command = format!(r#"do shell script "{script}" with administrator privileges with prompt "{prompt}""#);
+        let output = Command::new("osascript").args(["-e", &command]).output()?;
+        if !output.status.success() {
+            bail!("pfctl exited with status {}", output.status.code().unwrap_or(-1));
+        }
+        Ok(std::string::String::from_utf8_lossy(&output.stdout).trim().to_owned())
+    }
+
+    /// `pfctl -E` 返回的引用令牌，释放时用 `pfctl -X` 归还，不影响其他启用 pf 的程序
+    pub fn engage(core: &CoreIdentity) -> Result<Engaged> {
+        // 服务模式下内核以 root 运行，可按用户放行
+        let exempt = if core.service_mode {
+            "'pass out quick user root' "
+        } else {
+            ""
+        };
+        let token = run_privileged(&format!(
+            "printf '%s\\\\n' 'pass out quick on lo0 all' {exempt}'block drop out quick all' \
+             | pfctl -a {ANCHOR} -f - && pfctl -E 2>&1 | sed -n 's/^Token : //p'"
+        ))?;
+        Ok(Engaged {
+            restore: token,
+            exempts_core: core.service_mode,
+        })
+    }
+
+    pub fn release(restore: &str) -> Result<()> {
+        // 令牌来自标记文件，拼接进 root shell 之前只接受数字
+        let token = restore.trim();
+        if !token.is_empty() && token.chars().all(|c| c.is_ascii_digit()) {
+            run_privileged(&format!("pfctl -a {ANCHOR} -F all; pfctl -X {token}"))?;
+        } else {
+            run_privileged(&format!("pfctl -a {ANCHOR} -F all"))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use super::{CoreIdentity, Engaged, Result, bail};
+    use deelevate::{PrivilegeLevel, Token};
+    use runas::Command as RunasCommand;
+    use std::{os::windows::process::CommandExt as _, process::Command};
+    use winreg::{RegKey, enums::HKEY_LOCAL_MACHINE};
+
+    const RULE_NAME: &str = "Clash Verge Kill Switch";
+    const POLICY_KEY: &str = r"SYSTEM\CurrentControlSet\Services\SharedAccess\Parameters\FirewallPolicy";
+    /// 注册表中的配置文件名与 netsh 中的名称
+    const PROFILES: [(&str, &str); 3] = [
+        ("DomainProfile", "domainprofile"),
+        ("StandardProfile", "privateprofile"),
+        ("PublicProfile", "publicprofile"),
+    ];
+
+    /// 单个配置文件的默认策略，true 表示阻止
+    #[derive(Clone, Copy)]
+    struct Policy {
+        block_inbound: bool,
+        block_outbound: bool,
+    }
+
+    impl Policy {
+        /// Windows 的默认策略
+        const DEFAULT: Self = Self {
+            block_inbound: true,
+            block_outbound: false,
+        };
+
+        const fn netsh(self) -> &'static str {
+            match (self.block_inbound, self.block_outbound) {
+                (true, true) => "blockinbound,blockoutbound",
+                (true, false) => "blockinbound,allowoutbound",
+                (false, true) => "allowinbound,blockoutbound",
+                (false, false) => "allowinbound,allowoutbound",
+            }
+        }
+    }
+
+    /// 从注册表读取当前策略，未设置的值按系统默认处理
+    fn current_policies() -> [Policy; 3] {
+        let root = RegKey::predef(HKEY_LOCAL_MACHINE).open_subkey(POLICY_KEY).ok();
+        PROFILES.map(|(key, _)| {
+            let profile = root.as_ref().and_then(|root| root.open_subkey(key).ok());
+            let read = |name: &str| profile.as_ref().and_then(|p| p.get_value::<u32, _>(name).ok());
+            Policy {
+                block_inbound: read("DefaultInboundAction").map_or(Policy::DEFAULT.block_inbound, |v| v != 0),
+                block_outbound: read("DefaultOutboundAction").map_or(Policy::DEFAULT.block_outbound, |v| v != 0),
+            }
+        })
+    }
+
+    /// 标记文件中每个配置文件占两位数字（入站、出站），无法解析时按系统默认处理
+    fn encode(policies: &[Policy; 3]) -> std::string::String {
+        policies
+            .iter()
+            .map(|p| format!("{}{}", u8::from(p.block_inbound), u8::from(p.block_outbound)))
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    fn decode(restore: &str) -> [Policy; 3] {
+        let saved: Vec<&str> = restore.trim().split(',').collect();
+        let mut policies = [Policy::DEFAULT; 3];
+        for (policy, code) in policies.iter_mut().zip(saved) {
+            if let [inbound @ (b'0' | b'1'), outbound @ (b'0' | b'1')] = code.as_bytes() {
+                *policy = Policy {
+                    block_inbound: *inbound == b'1',
+                    block_outbound: *outbound == b'1',
+                };
+            }
+        }
+        policies
+    }
+
+    fn set_policies(policies: impl IntoIterator<Item = Policy>) -> std::string::String {
+        PROFILES
+            .iter()
+            .zip(policies)
+            .map(|((_, name), policy)| format!("netsh advfirewall set {name} firewallpolicy {}", policy.netsh()))
+            .collect::<Vec<_>>()
+            .join(" & ")
+    }
+
+    fn run_privileged(script: &str) -> Result<()> {
+        let token = Token::with_current_process()?;
+        let status = match token.privilege_level()? {
+            PrivilegeLevel::NotPrivileged => RunasCommand::new("cmd").args(&["/C", script]).show(false).status()?,
+            _ => Command::new("cmd")
+                .args(["/C", script])
+                .creation_flags(0x08000000)
+                .status()?,
+        };
+        if !status.success() {
+            bail!("netsh exited with status {}", status.code().unwrap_or(-1));
+        }
+        Ok(())
+    }
+
+    // Windows 防火墙不过滤回环流量，只需放行内核本身；入站策略保持不变
+    pub fn engage(core: &CoreIdentity) -> Result<Engaged> {
+        let program = core.program.display();
+        let saved = current_policies();
+        let blocked = saved.iter().map(|policy| Policy {
+            block_outbound: true,
+            ..*policy
+        });
+        run_privileged(&format!(
+            "netsh advfirewall firewall delete rule name=\"{RULE_NAME}\" >nul 2>&1 & \
+             netsh advfirewall firewall add rule name=\"{RULE_NAME}\" dir=out action=allow program=\"{program}\" enable=yes && \
+             {}",
+            set_policies(blocked)
+        ))?;
+        Ok(Engaged {
+            restore: encode(&saved),
+            exempts_core: true,
+        })
+    }
+
+    /// 恢复安装规则前保存的策略
+    pub fn release(restore: &str) -> Result<()> {
+        run_privileged(&format!(
+            "{} & netsh advfirewall firewall delete rule name=\"{RULE_NAME}\" >nul 2>&1 & exit /b 0",
+            set_policies(decode(restore))
+        ))
+    }
+}
@@ -0,0 +1,1088 @@
+//! 局域网设备间的配置同步
+//!
+//! 开启 `enable_lan_sync` 后，本机通过 mDNS 广播 [`SERVICE_TYPE`] 服务，并在 `lan_sync_port` 上提供 TLS 同步服务。
+//! 每台设备首次使用时生成自签名证书。配对时双方以一次性 PIN 做 SPAKE2 密钥交换，再用 HKDF 派生的确认密钥
+//! 签名双方在 TLS 握手中出示的证书指纹，确认后各自固定对端证书，之后的连接双向校验证书，未配对的设备无法推送或拉取。
+//! 推送与拉取请求带有时间戳与随机数，拉取的响应回带请求的随机数，重放的请求与旧的响应都会被拒绝；
+//! 收到推送后需在本机确认才会覆盖配置。整个过程不经过任何第三方服务器。
+
+use crate::{
+    config::{Config, ILanSyncIdentity, ILanSyncPeer},
+    constants::network::ports,
+    core::handle,
+    feat,
+    process::AsyncHandler,
+    singleton,
+    utils::{crypto, help, i18n, instance},
+};
+use anyhow::{Result, anyhow, bail};
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use chrono::Local;
+use clash_verge_logging::{Type, logging};
+use gethostname::gethostname;
+use mdns_sd::{ServiceDaemon, ServiceEvent, ServiceInfo};
+use parking_lot::Mutex;
+use rustls::{
+    ClientConfig, DigitallySignedStruct, DistinguishedName, ServerConfig, SignatureScheme,
+    client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+    crypto::{CryptoProvider, ring, verify_tls12_signature, verify_tls13_signature},
+    pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer, ServerName, UnixTime},
+    server::danger::{ClientCertVerified, ClientCertVerifier},
+};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use sha2::{Digest as _, Sha256};
+use smartstring::alias::String;
+use spake2::{Ed25519Group, Identity, Password, Spake2};
+use std::{
+    collections::HashMap,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant},
+};
+use tauri_plugin_dialog::{DialogExt as _, MessageDialogButtons, MessageDialogKind};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt as _, AsyncWrite, AsyncWriteExt as _},
+    net::{TcpListener, TcpStream},
+    sync::oneshot,
+};
+use tokio_rustls::{TlsAcceptor, TlsConnector, client::TlsStream};
+
+const SERVICE_TYPE: &str = "_clash-verge-sync._tcp.local.";
+/// 证书中的名称与连接时使用的服务名，对端身份由证书指纹确认
+const SERVER_NAME: &str = "clash-verge-lan-sync";
+const DISCOVERY_WINDOW: Duration = Duration::from_secs(2);
+const IDLE_INTERVAL: Duration = Duration::from_secs(30);
+/// 包含推送后等待对端用户确认的时间
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(120);
+const PAIRING_TTL: Duration = Duration::from_secs(300);
+const MAX_PAIRING_ATTEMPTS: u32 = 5;
+/// 推送与拉取请求中时间戳允许的偏差，窗口内的随机数会被记录以拒绝重放
+const MAX_CLOCK_SKEW_SECS: u64 = 300;
+const NONCE_LENGTH: usize = 16;
+const MAX_MESSAGE_BYTES: usize = 64 * 1024;
+const MAX_ARCHIVE_BYTES: usize = 256 * 1024 * 1024;
+const CLIENT_CONFIRM_INFO: &[u8] = b"clash-verge lan-sync client confirm";
+const SERVER_CONFIRM_INFO: &[u8] = b"clash-verge lan-sync server confirm";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LanPeer {
+    pub id: String,
+    pub name: String,
+    pub address: String,
+    pub port: u16,
+    pub paired: bool,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncDirection {
+    Push,
+    Pull,
+}
+
+/// 推送与拉取携带的时间戳与随机数
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+struct Stamp {
+    time: i64,
+    nonce: [u8; NONCE_LENGTH],
+}
+
+impl Stamp {
+    fn new() -> Result<Self> {
+        Ok(Self {
+            time: Local::now().timestamp(),
+            nonce: random_bytes()?,
+        })
+    }
+
+    /// 响应沿用请求的随机数，请求方据此确认响应属于本次请求
+    fn reply(self) -> Self {
+        Self {
+            time: Local::now().timestamp(),
+            nonce: self.nonce,
+        }
+    }
+
+    fn is_fresh(&self) -> bool {
+        Local::now().timestamp().abs_diff(self.time) <= MAX_CLOCK_SKEW_SECS
+    }
+}
+
+/// 请求方发送的消息，推送请求之后紧跟一帧设置归档
+#[derive(Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Request {
+    /// 配对第一步：发送 SPAKE2 消息
+    PairStart {
+        id: String,
+        name: String,
+        message: String,
+    },
+    /// 配对第二步：证明得到了相同的会话密钥
+    PairConfirm {
+        confirm: String,
+    },
+    Push {
+        id: String,
+        stamp: Stamp,
+    },
+    Pull {
+        id: String,
+        stamp: Stamp,
+    },
+}
+
+/// 服务方的响应，拉取响应之后紧跟一帧设置归档
+#[derive(Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Response {
+    /// 服务方的 SPAKE2 消息与确认值
+    PairChallenge {
+        id: String,
+        name: String,
+        message: String,
+        confirm: String,
+    },
+    Paired,
+    Accepted,
+    Archive {
+        stamp: Stamp,
+    },
+    Error {
+        message: String,
+    },
+}
+
+struct Pairing {
+    pin: String,
+    expires_at: Instant,
+    attempts: u32,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Side {
+    Client,
+    Server,
+}
+
+/// 由 SPAKE2 会话密钥派生的确认密钥，确认值签名双方的证书指纹，
+/// 中间人替换任一方的证书后确认都会失败
+struct PairingKeys {
+    client: [u8; 32],
+    server: [u8; 32],
+    transcript: Vec<u8>,
+}
+
+impl PairingKeys {
+    fn derive(shared: &[u8], client_fingerprint: &str, server_fingerprint: &str) -> Self {
+        let transcript = format!("{client_fingerprint}|{server_fingerprint}").into_bytes();
+        Self {
+            client: crypto::hkdf_sha256(shared, &transcript, CLIENT_CONFIRM_INFO),
+            server: crypto::hkdf_sha256(shared, &transcript, SERVER_CONFIRM_INFO),
+            transcript,
+        }
+    }
+
+    const fn key(&self, side: Side) -> &[u8; 32] {
+        match side {
+            Side::Client => &self.client,
+            Side::Server => &self.server,
+        }
+    }
+
+    fn confirm(&self, side: Side) -> String {
+        crypto::to_hex(&crypto::hmac_sha256(self.key(side), &self.transcript)).into()
+    }
+
+    fn verify(&self, side: Side, confirm: &str) -> bool {
+        from_hex(confirm).is_some_and(|tag| crypto::verify_hmac_sha256(self.key(side), &self.transcript, &tag))
+    }
+}
+
+/// 本机设备标识、名称与同步证书
+struct LocalDevice {
+    id: String,
+    name: String,
+    certificate: CertificateDer<'static>,
+    private_key: PrivatePkcs8KeyDer<'static>,
+}
+
+impl LocalDevice {
+    fn fingerprint(&self) -> String {
+        fingerprint(&self.certificate)
+    }
+
+    fn server_config(&self) -> Result<ServerConfig> {
+        let provider = Arc::new(ring::default_provider());
+        Ok(ServerConfig::builder_with_provider(Arc::clone(&provider))
+            .with_protocol_versions(&[&rustls::version::TLS13])?
+            .with_client_cert_verifier(Arc::new(PeerVerifier { provider, pinned: None }))
+            .with_single_cert(
+                vec![self.certificate.clone()],
+                PrivateKeyDer::Pkcs8(self.private_key.clone_key()),
+            )?)
+    }
+
+    fn client_config(&self, pinned: Option<String>) -> Result<ClientConfig> {
+        let provider = Arc::new(ring::default_provider());
+        Ok(ClientConfig::builder_with_provider(Arc::clone(&provider))
+            .with_protocol_versions(&[&rustls::version::TLS13])?
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(PeerVerifier { provider, pinned }))
+            .with_client_auth_cert(
+                vec![self.certificate.clone()],
+                PrivateKeyDer::Pkcs8(self.private_key.clone_key()),
+            )?)
+    }
+}
+
+/// 校验对端证书
+///
+/// 证书均为自签名，不经过 CA 校验：握手只证明对端持有证书私钥，`pinned` 给出时证书指纹须与之一致。
+/// 配对时与服务方不固定证书，由配对确认值或已配对设备列表判断对端身份。
+#[derive(Debug)]
+struct PeerVerifier {
+    provider: Arc<CryptoProvider>,
+    pinned: Option<String>,
+}
+
+impl PeerVerifier {
+    fn check_pinned(&self, certificate: &CertificateDer<'_>) -> Result<(), rustls::Error> {
+        match &self.pinned {
+            Some(pinned) if fingerprint(certificate) != *pinned => Err(rustls::Error::General(
+                "certificate does not match the paired device".into(),
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl ServerCertVerifier for PeerVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        self.check_pinned(end_entity)?;
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(message, cert, dss, &self.provider.signature_verification_algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(message, cert, dss, &self.provider.signature_verification_algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.provider.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+impl ClientCertVerifier for PeerVerifier {
+    fn root_hint_subjects(&self) -> &[DistinguishedName] {
+        &[]
+    }
+
+    fn verify_client_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _now: UnixTime,
+    ) -> Result<ClientCertVerified, rustls::Error> {
+        self.check_pinned(end_entity)?;
+        Ok(ClientCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(message, cert, dss, &self.provider.signature_verification_algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(message, cert, dss, &self.provider.signature_verification_algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.provider.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+fn fingerprint(certificate: &[u8]) -> String {
+    crypto::to_hex(&Sha256::digest(certificate)).into()
+}
+
+fn peer_fingerprint(certificates: Option<&[CertificateDer<'_>]>) -> Result<String> {
+    certificates
+        .and_then(<[_]>::first)
+        .map(|certificate| fingerprint(certificate))
+        .ok_or_else(|| anyhow!("peer did not present a certificate"))
+}
+
+/// 生成本机的自签名证书
+fn generate_identity() -> Result<ILanSyncIdentity> {
+    let key = rcgen::KeyPair::generate()?;
+    let certificate = rcgen::CertificateParams::new(vec![SERVER_NAME.to_owned()])?.self_signed(&key)?;
+    Ok(ILanSyncIdentity {
+        certificate: STANDARD.encode(certificate.der()).into(),
+        private_key: STANDARD.encode(key.serialize_der()).into(),
+    })
+}
+
+fn from_hex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+fn random_bytes<const N: usize>() -> Result<[u8; N]> {
+    let mut bytes = [0u8; N];
+    getrandom::fill(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// 每帧以 4 字节大端长度开头
+async fn write_frame<S: AsyncWrite + Unpin + Send>(stream: &mut S, data: &[u8]) -> Result<()> {
+    stream.write_u32(u32::try_from(data.len())?).await?;
+    stream.write_all(data).await?;
+    stream.flush().await?;
+    Ok(())
+}
+
+async fn read_frame<S: AsyncRead + Unpin + Send>(stream: &mut S, limit: usize) -> Result<Vec<u8>> {
+    let length = usize::try_from(stream.read_u32().await?)?;
+    if length > limit {
+        bail!("message is too large");
+    }
+    let mut data = vec![0u8; length];
+    stream.read_exact(&mut data).await?;
+    Ok(data)
+}
+
+async fn send<S: AsyncWrite + Unpin + Send, T: Serialize + Sync>(stream: &mut S, message: &T) -> Result<()> {
+    write_frame(stream, &serde_json::to_vec(message)?).await
+}
+
+async fn receive<S: AsyncRead + Unpin + Send, T: DeserializeOwned>(stream: &mut S) -> Result<T> {
+    Ok(serde_json::from_slice(&read_frame(stream, MAX_MESSAGE_BYTES).await?)?)
+}
+
+/// 读取服务方的响应，错误响应转为错误
+async fn receive_response<S: AsyncRead + Unpin + Send>(stream: &mut S) -> Result<Response> {
+    match receive(stream).await? {
+        Response::Error { message } => bail!("{message}"),
+        response => Ok(response),
+    }
+}
+
+/// 弹窗请求用户确认用对端推送的设置覆盖本机，关闭窗口视为拒绝
+async fn confirm_push(name: &str) -> bool {
+    i18n::sync_locale().await;
+    let (tx, rx) = oneshot::channel();
+    handle::Handle::app_handle()
+        .dialog()
+        .message(rust_i18n::t!("lanSync.confirmPush").replace("{name}", name))
+        .title(rust_i18n::t!("lanSync.title").to_string())
+        .kind(MessageDialogKind::Warning)
+        .buttons(MessageDialogButtons::OkCancel)
+        .show(move |confirmed| {
+            let _ = tx.send(confirmed);
+        });
+    rx.await.unwrap_or(false)
+}
+
+pub struct LanSync {
+    peers: Mutex<HashMap<String, LanPeer>>,
+    pairing: Mutex<Option<Pairing>>,
+    /// 时间窗口内已处理请求的随机数与时间戳
+    seen_nonces: Mutex<HashMap<[u8; NONCE_LENGTH], i64>>,
+    mdns: Mutex<Option<ServiceDaemon>>,
+    /// 正在广播的服务全名
+    advertised: Mutex<Option<std::string::String>>,
+    runner_started: AtomicBool,
+}
+
+singleton!(LanSync, LAN_SYNC);
+
+impl LanSync {
+    fn new() -> Self {
+        Self {
+            peers: Mutex::new(HashMap::new()),
+            pairing: Mutex::new(None),
+            seen_nonces: Mutex::new(HashMap::new()),
+            mdns: Mutex::new(None),
+            advertised: Mutex::new(None),
+            runner_started: AtomicBool::new(false),
+        }
+    }
+
+    async fn is_enabled() -> bool {
+        Config::verge().await.latest_arc().enable_lan_sync.unwrap_or(false)
+    }
+
+    async fn ensure_enabled() -> Result<()> {
+        if !Self::is_enabled().await {
+            bail!("LAN sync is disabled");
+        }
+        Ok(())
+    }
+
+    async fn sync_port() -> u16 {
+        Config::verge()
+            .await
+            .latest_arc()
+            .lan_sync_port
+            .unwrap_or_else(|| instance::offset_port(ports::LAN_SYNC))
+    }
+
+    /// 已完成证书配对的设备，旧版本保存的共享密钥条目需重新配对
+    async fn paired_peers() -> Vec<ILanSyncPeer> {
+        Config::verge()
+            .await
+            .latest_arc()
+            .lan_sync_peers
+            .iter()
+            .flatten()
+            .filter(|peer| !peer.fingerprint.is_empty())
+            .cloned()
+            .collect()
+    }
+
+    /// 按 TLS 握手中的证书指纹确认对端是已配对的设备
+    async fn authorize(fingerprint: &str, id: &str) -> Result<ILanSyncPeer> {
+        Self::paired_peers()
+            .await
+            .into_iter()
+            .find(|peer| peer.id == id && peer.fingerprint == fingerprint)
+            .ok_or_else(|| anyhow!("unknown device"))
+    }
+
+    /// 本机设备标识与证书，不存在时生成并保存
+    async fn local_device() -> Result<LocalDevice> {
+        let verge = Config::verge().await.latest_arc();
+        let saved_id = verge.lan_sync_device_id.clone().filter(|id| !id.is_empty());
+        let saved_identity = verge
+            .lan_sync_identity
+            .clone()
+            .filter(|identity| !identity.private_key.is_empty());
+        drop(verge);
+
+        let (id, identity) = match (saved_id, saved_identity) {
+            (Some(id), Some(identity)) => (id, identity),
+            (id, identity) => {
+                let id = id.unwrap_or_else(|| help::get_uid("d"));
+                let identity = match identity {
+                    Some(identity) => identity,
+                    None => generate_identity()?,
+                };
+                Config::verge().await.edit_draft(|d| {
+                    d.lan_sync_device_id = Some(id.clone());
+                    d.lan_sync_identity = Some(identity.clone());
+                });
+                Config::verge().await.apply();
+                Config::verge().await.latest_arc().save_file().await?;
+                (id, identity)
+            }
+        };
+        Ok(LocalDevice {
+            id,
+            name: gethostname().to_string_lossy().as_ref().into(),
+            certificate: CertificateDer::from(STANDARD.decode(identity.certificate.as_str())?),
+            private_key: PrivatePkcs8KeyDer::from(STANDARD.decode(identity.private_key.as_str())?),
+        })
+    }
+
+    async fn save_peer(peer: ILanSyncPeer) -> Result<()> {
+        Config::verge().await.edit_draft(|d| {
+            let peers = d.lan_sync_peers.get_or_insert_with(Vec::new);
+            peers.retain(|p| p.id != peer.id);
+            peers.push(peer);
+        });
+        Config::verge().await.apply();
+        Config::verge().await.latest_arc().save_file().await?;
+        handle::Handle::refresh_verge();
+        Ok(())
+    }
+
+    fn daemon(&self) -> Result<ServiceDaemon> {
+        let mut mdns = self.mdns.lock();
+        let daemon = match mdns.as_ref() {
+            Some(daemon) => daemon.clone(),
+            None => mdns.insert(ServiceDaemon::new()?).clone(),
+        };
+        drop(mdns);
+        Ok(daemon)
+    }
+
+    /// 等待开启后启动同步服务，并按开关广播或撤销 mDNS 服务，重复调用无副作用
+    pub fn init(&self) {
+        if self.runner_started.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        AsyncHandler::spawn(|| async {
+            let mut serving_port = None;
+            loop {
+                if handle::Handle::global().is_exiting() {
+                    let _ = Self::global().advertise(None).await;
+                    return;
+                }
+                let enabled = Self::is_enabled().await;
+                if enabled && serving_port.is_none() {
+                    let port = Self::sync_port().await;
+                    serving_port = Some(port);
+                    AsyncHandler::spawn(move || async move {
+                        if let Err(err) = Self::serve(port).await {
+                            logging!(warn, Type::Network, "LAN sync server stopped: {err}");
+                        }
+                    });
+                }
+                // 关闭后停止广播，服务端口仍由本次运行占用并拒绝请求
+                let port = serving_port.filter(|_| enabled);
+                if let Err(err) = Self::global().advertise(port).await {
+                    logging!(warn, Type::Network, "Failed to advertise LAN sync service: {err}");
+                }
+                tokio::time::sleep(IDLE_INTERVAL).await;
+            }
+        });
+    }
+
+    /// 在给定端口上广播本机，`None` 时撤销广播
+    async fn advertise(&self, port: Option<u16>) -> Result<()> {
+        let Some(port) = port else {
+            let advertised = self.advertised.lock().take();
+            if let Some(fullname) = advertised {
+                self.daemon()?.unregister(&fullname)?;
+            }
+            return Ok(());
+        };
+        if self.advertised.lock().is_some() {
+            return Ok(());
+        }
+        let device = Self::local_device().await?;
+        let host = format!("{}.local.", device.id);
+        let properties = [("id", device.id.as_str()), ("name", device.name.as_str())];
+        let service = ServiceInfo::new(SERVICE_TYPE, &device.id, &host, "", port, &properties[..])?.enable_addr_auto();
+        let fullname = service.get_fullname().to_owned();
+        self.daemon()?.register(service)?;
+        *self.advertised.lock() = Some(fullname);
+        Ok(())
+    }
+
+    async fn serve(port: u16) -> Result<()> {
+        let acceptor = TlsAcceptor::from(Arc::new(Self::local_device().await?.server_config()?));
+        let listener = TcpListener::bind((Ipv4Addr::UNSPECIFIED, port)).await?;
+        logging!(info, Type::Network, "LAN sync listening on port {}", port);
+        loop {
+            let (stream, from) = listener.accept().await?;
+            if handle::Handle::global().is_exiting() {
+                return Ok(());
+            }
+            let acceptor = acceptor.clone();
+            AsyncHandler::spawn(move || async move {
+                match tokio::time::timeout(REQUEST_TIMEOUT, Self::handle_connection(acceptor, stream)).await {
+                    Ok(Ok(())) => {}
+                    Ok(Err(err)) => logging!(warn, Type::Network, "LAN sync request from {} failed: {}", from, err),
+                    Err(_) => logging!(warn, Type::Network, "LAN sync request from {} timed out", from),
+                }
+            });
+        }
+    }
+
+    async fn handle_connection(acceptor: TlsAcceptor, stream: TcpStream) -> Result<()> {
+        let mut stream = acceptor.accept(stream).await?;
+        let fingerprint = peer_fingerprint(stream.get_ref().1.peer_certificates())?;
+        let result = match receive(&mut stream).await? {
+            Request::PairStart { id, name, message } => {
+                Self::accept_pairing(&mut stream, &fingerprint, id, name, &message).await
+            }
+            Request::Push { id, stamp } => Self::accept_push(&mut stream, &fingerprint, &id, &stamp).await,
+            Request::Pull { id, stamp } => Self::accept_pull(&mut stream, &fingerprint, &id, stamp).await,
+            Request::PairConfirm { .. } => Err(anyhow!("no pairing in progress")),
+        };
+        if let Err(err) = &result {
+            // 尽量把失败原因告诉对端，连接已断开时忽略
+            let _ = send(
+                &mut stream,
+                &Response::Error {
+                    message: err.to_string().into(),
+                },
+            )
+            .await;
+        }
+        result
+    }
+
+    /// 生成一次性配对 PIN，在对端输入后完成配对
+    pub async fn start_pairing(&self) -> Result<String> {
+        Self::ensure_enabled().await?;
+        let pin: String = format!("{:06}", u32::from_le_bytes(random_bytes()?) % 1_000_000).into();
+        *self.pairing.lock() = Some(Pairing {
+            pin: pin.clone(),
+            expires_at: Instant::now() + PAIRING_TTL,
+            attempts: 0,
+        });
+        Ok(pin)
+    }
+
+    /// 每次密钥交换消耗一次尝试机会，PIN 过期或尝试次数用尽后作废
+    fn begin_pairing_attempt(&self) -> Result<String> {
+        let mut pairing = self.pairing.lock();
+        let Some(active) = pairing
+            .as_mut()
+            .filter(|p| p.expires_at > Instant::now() && p.attempts < MAX_PAIRING_ATTEMPTS)
+        else {
+            *pairing = None;
+            bail!("no pairing in progress");
+        };
+        active.attempts += 1;
+        let pin = active.pin.clone();
+        drop(pairing);
+        Ok(pin)
+    }
+
+    /// 配对成功或尝试次数用尽后作废 PIN
+    fn end_pairing_attempt(&self, paired: bool) {
+        let mut pairing = self.pairing.lock();
+        if paired || pairing.as_ref().is_some_and(|p| p.attempts >= MAX_PAIRING_ATTEMPTS) {
+            *pairing = None;
+        }
+    }
+
+    async fn accept_pairing<S: AsyncRead + AsyncWrite + Unpin + Send>(
+        stream: &mut S,
+        client_fingerprint: &str,
+        id: String,
+        name: String,
+        message: &str,
+    ) -> Result<()> {
+        Self::ensure_enabled().await?;
+        let client_message = from_hex(message).ok_or_else(|| anyhow!("invalid pairing request"))?;
+        let pin = Self::global().begin_pairing_attempt()?;
+        let device = Self::local_device().await?;
+        let (spake, server_message) = Spake2::<Ed25519Group>::start_b(
+            &Password::new(pin.as_bytes()),
+            &Identity::new(id.as_bytes()),
+            &Identity::new(device.id.as_bytes()),
+        );
+        let keys = PairingKeys::derive(
+            &spake.finish(&client_message)?,
+            client_fingerprint,
+            &device.fingerprint(),
+        );
+        send(
+            stream,
+            &Response::PairChallenge {
+                id: device.id.clone(),
+                name: device.name.clone(),
+                message: crypto::to_hex(&server_message).into(),
+                confirm: keys.confirm(Side::Server),
+            },
+        )
+        .await?;
+
+        let Request::PairConfirm { confirm } = receive(stream).await? else {
+            bail!("unexpected pairing message");
+        };
+        let paired = keys.verify(Side::Client, &confirm);
+        Self::global().end_pairing_attempt(paired);
+        if !paired {
+            bail!("wrong PIN");
+        }
+        logging!(info, Type::Network, "Paired with LAN device {} ({})", name, id);
+        Self::save_peer(ILanSyncPeer {
+            id,
+            name,
+            fingerprint: client_fingerprint.into(),
+        })
+        .await?;
+        send(stream, &Response::Paired).await
+    }
+
+    /// 校验时间戳并记录随机数，拒绝过期或重复的请求
+    fn check_stamp(&self, stamp: &Stamp) -> Result<()> {
+        if !stamp.is_fresh() {
+            bail!("request expired");
+        }
+        let now = Local::now().timestamp();
+        let replayed = {
+            let mut seen = self.seen_nonces.lock();
+            seen.retain(|_, seen_at| now.abs_diff(*seen_at) <= MAX_CLOCK_SKEW_SECS);
+            seen.insert(stamp.nonce, stamp.time).is_some()
+        };
+        if replayed {
+            bail!("request replayed");
+        }
+        Ok(())
+    }
+
+    async fn accept_push<S: AsyncRead + AsyncWrite + Unpin + Send>(
+        stream: &mut S,
+        fingerprint: &str,
+        id: &str,
+        stamp: &Stamp,
+    ) -> Result<()> {
+        Self::ensure_enabled().await?;
+        // 设置锁定时不接受其他设备推送的设置
+        feat::ensure_unlocked().await?;
+        let peer = Self::authorize(fingerprint, id).await?;
+        Self::global().check_stamp(stamp)?;
+        let archive = read_frame(stream, MAX_ARCHIVE_BYTES).await?;
+        if !confirm_push(&peer.name).await {
+            bail!("declined on the receiving device");
+        }
+        logging!(info, Type::Network, "Receiving settings from LAN device {}", peer.name);
+        feat::apply_settings_archive(archive).await?;
+        feat::reload_from_disk().await?;
+        handle::Handle::notice_message("lan_sync::received", peer.name.as_str());
+        send(stream, &Response::Accepted).await
+    }
+
+    async fn accept_pull<S: AsyncRead + AsyncWrite + Unpin + Send>(
+        stream: &mut S,
+        fingerprint: &str,
+        id: &str,
+        stamp: Stamp,
+    ) -> Result<()> {
+        Self::ensure_enabled().await?;
+        let peer = Self::authorize(fingerprint, id).await?;
+        Self::global().check_stamp(&stamp)?;
+        logging!(info, Type::Network, "Sending settings to LAN device {}", peer.name);
+        let archive = feat::settings_archive(true).await?;
+        send(stream, &Response::Archive { stamp: stamp.reply() }).await?;
+        write_frame(stream, &archive).await
+    }
+
+    /// 通过 mDNS 查找局域网内的设备，返回窗口期内解析到的设备
+    pub async fn discover(&self) -> Result<Vec<LanPeer>> {
+        let device = Self::local_device().await?;
+        let paired: Vec<String> = Self::paired_peers().await.into_iter().map(|p| p.id).collect();
+
+        let daemon = self.daemon()?;
+        let events = daemon.browse(SERVICE_TYPE)?;
+        let mut found = HashMap::new();
+        let deadline = tokio::time::Instant::now() + DISCOVERY_WINDOW;
+        while let Ok(Ok(event)) = tokio::time::timeout_at(deadline, events.recv_async()).await {
+            let ServiceEvent::ServiceResolved(service) = event else {
+                continue;
+            };
+            let (Some(id), Some(name)) = (service.get_property_val_str("id"), service.get_property_val_str("name"))
+            else {
+                continue;
+            };
+            let Some(address) = service.get_addresses_v4().into_iter().min() else {
+                continue;
+            };
+            if id == device.id {
+                continue;
+            }
+            let id: String = id.into();
+            let peer = LanPeer {
+                paired: paired.contains(&id),
+                id: id.clone(),
+                name: name.into(),
+                address: address.to_string().into(),
+                port: service.port,
+            };
+            found.insert(id, peer);
+        }
+        if let Err(err) = daemon.stop_browse(SERVICE_TYPE) {
+            logging!(debug, Type::Network, "Failed to stop LAN sync discovery: {err}");
+        }
+
+        *self.peers.lock() = found.clone();
+        let mut peers: Vec<LanPeer> = found.into_values().collect();
+        peers.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(peers)
+    }
+
+    async fn find_peer(&self, id: &str) -> Result<LanPeer> {
+        let known = self.peers.lock().get(id).cloned();
+        if let Some(peer) = known {
+            return Ok(peer);
+        }
+        self.discover()
+            .await?
+            .into_iter()
+            .find(|p| p.id == id)
+            .ok_or_else(|| anyhow!("device not found on the local network: {id}"))
+    }
+
+    /// 连接对端的同步服务，`pinned` 为空时仅用于配对，返回对端的证书指纹
+    async fn connect(
+        peer: &LanPeer,
+        device: &LocalDevice,
+        pinned: Option<String>,
+    ) -> Result<(TlsStream<TcpStream>, String)> {
+        let connector = TlsConnector::from(Arc::new(device.client_config(pinned)?));
+        let address: IpAddr = peer.address.parse()?;
+        let stream = TcpStream::connect(SocketAddr::new(address, peer.port)).await?;
+        let stream = connector.connect(ServerName::try_from(SERVER_NAME)?, stream).await?;
+        let fingerprint = peer_fingerprint(stream.get_ref().1.peer_certificates())?;
+        Ok((stream, fingerprint))
+    }
+
+    /// 使用对端显示的 PIN 完成配对
+    pub async fn pair(&self, id: &str, pin: &str) -> Result<LanPeer> {
+        let mut peer = self.find_peer(id).await?;
+        let device = Self::local_device().await?;
+        let (server_id, name, server_fingerprint) = tokio::time::timeout(REQUEST_TIMEOUT, async {
+            let (mut stream, server_fingerprint) = Self::connect(&peer, &device, None).await?;
+            let (spake, client_message) = Spake2::<Ed25519Group>::start_a(
+                &Password::new(pin.as_bytes()),
+                &Identity::new(device.id.as_bytes()),
+                &Identity::new(peer.id.as_bytes()),
+            );
+            send(
+                &mut stream,
+                &Request::PairStart {
+                    id: device.id.clone(),
+                    name: device.name.clone(),
+                    message: crypto::to_hex(&client_message).into(),
+                },
+            )
+            .await?;
+            let response = receive_response(&mut stream)
+                .await
+                .map_err(|err| anyhow!("pairing rejected: {err}"))?;
+            let Response::PairChallenge {
+                id: server_id,
+                name,
+                message,
+                confirm,
+            } = response
+            else {
+                bail!("unexpected pairing response");
+            };
+            if server_id != peer.id {
+                bail!("unexpected device answered the pairing request");
+            }
+            let server_message = from_hex(&message).ok_or_else(|| anyhow!("invalid pairing response"))?;
+            let keys = PairingKeys::derive(
+                &spake.finish(&server_message)?,
+                &device.fingerprint(),
+                &server_fingerprint,
+            );
+            if !keys.verify(Side::Server, &confirm) {
+                bail!("wrong PIN");
+            }
+
+            send(
+                &mut stream,
+                &Request::PairConfirm {
+                    confirm: keys.confirm(Side::Client),
+                },
+            )
+            .await?;
+            let response = receive_response(&mut stream)
+                .await
+                .map_err(|err| anyhow!("pairing rejected: {err}"))?;
+            if response != Response::Paired {
+                bail!("unexpected pairing response");
+            }
+            Ok((server_id, name, server_fingerprint))
+        })
+        .await??;
+
+        Self::save_peer(ILanSyncPeer {
+            id: server_id,
+            name,
+            fingerprint: server_fingerprint,
+        })
+        .await?;
+        peer.paired = true;
+        self.peers.lock().insert(peer.id.clone(), peer.clone());
+        logging!(
+            info,
+            Type::Network,
+            "Paired with LAN device {} ({})",
+            peer.name,
+            peer.id
+        );
+        Ok(peer)
+    }
+
+    /// 向已配对设备推送本机配置，或从其拉取配置覆盖本机
+    pub async fn sync(&self, id: &str, direction: SyncDirection) -> Result<()> {
+        if matches!(direction, SyncDirection::Pull) {
+            feat::ensure_unlocked().await?;
+        }
+        let paired = Self::paired_peers()
+            .await
+            .into_iter()
+            .find(|p| p.id == id)
+            .ok_or_else(|| anyhow!("device is not paired: {id}"))?;
+        let peer = self.find_peer(id).await?;
+        let device = Self::local_device().await?;
+
+        let pulled = tokio::time::timeout(REQUEST_TIMEOUT, async {
+            let (mut stream, _) = Self::connect(&peer, &device, Some(paired.fingerprint)).await?;
+            let stamp = Stamp::new()?;
+            match direction {
+                SyncDirection::Push => {
+                    let archive = feat::settings_archive(true).await?;
+                    send(
+                        &mut stream,
+                        &Request::Push {
+                            id: device.id.clone(),
+                            stamp,
+                        },
+                    )
+                    .await?;
+                    write_frame(&mut stream, &archive).await?;
+                    let response = receive_response(&mut stream)
+                        .await
+                        .map_err(|err| anyhow!("push rejected: {err}"))?;
+                    if response != Response::Accepted {
+                        bail!("unexpected push response");
+                    }
+                    Ok(None)
+                }
+                SyncDirection::Pull => {
+                    send(
+                        &mut stream,
+                        &Request::Pull {
+                            id: device.id.clone(),
+                            stamp,
+                        },
+                    )
+                    .await?;
+                    let response = receive_response(&mut stream)
+                        .await
+                        .map_err(|err| anyhow!("pull rejected: {err}"))?;
+                    let Response::Archive { stamp: reply } = response else {
+                        bail!("unexpected pull response");
+                    };
+                    // 响应须回带本次请求的随机数，旧的响应无法被重放
+                    if reply.nonce != stamp.nonce || !reply.is_fresh() {
+                        bail!("stale pull response");
+                    }
+                    Ok(Some(read_frame(&mut stream, MAX_ARCHIVE_BYTES).await?))
+                }
+            }
+        })
+        .await??;
+
+        if let Some(archive) = pulled {
+            feat::apply_settings_archive(archive).await?;
+            feat::reload_from_disk().await?;
+        }
+        logging!(
+            info,
+            Type::Network,
+            "LAN sync {:?} with {} finished",
+            direction,
+            peer.name
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_message_roundtrip() {
+        let request = Request::Pull {
+            id: "d1".into(),
+            stamp: Stamp::new().unwrap_or_default(),
+        };
+        let encoded = serde_json::to_vec(&request).unwrap_or_default();
+        assert_eq!(serde_json::from_slice::<Request>(&encoded).ok(), Some(request));
+        assert_eq!(
+            serde_json::from_str::<Response>(r#"{"type":"error","message":"unknown device"}"#).ok(),
+            Some(Response::Error {
+                message: "unknown device".into()
+            })
+        );
+    }
+
+    #[test]
+    fn test_from_hex() {
+        assert_eq!(from_hex("00ff10").unwrap_or_default(), vec![0x00, 0xff, 0x10]);
+        assert!(from_hex("abc").is_none());
+        assert!(from_hex("zz").is_none());
+    }
+
+    #[test]
+    fn test_pairing_keys_bind_pin_and_certificates() {
+        let exchange = |client_pin: &str, server_pin: &str| {
+            let (client, client_message) = Spake2::<Ed25519Group>::start_a(
+                &Password::new(client_pin.as_bytes()),
+                &Identity::new(b"client"),
+                &Identity::new(b"server"),
+            );
+            let (server, server_message) = Spake2::<Ed25519Group>::start_b(
+                &Password::new(server_pin.as_bytes()),
+                &Identity::new(b"client"),
+                &Identity::new(b"server"),
+            );
+            (
+                client.finish(&server_message).unwrap_or_default(),
+                server.finish(&client_message).unwrap_or_default(),
+            )
+        };
+
+        let (client_key, server_key) = exchange("123456", "123456");
+        let client = PairingKeys::derive(&client_key, "c", "s");
+        let server = PairingKeys::derive(&server_key, "c", "s");
+        assert!(client.verify(Side::Server, &server.confirm(Side::Server)));
+        assert!(server.verify(Side::Client, &client.confirm(Side::Client)));
+        assert!(!server.verify(Side::Client, &client.confirm(Side::Server)));
+
+        // 中间人替换了服务方证书
+        let relayed = PairingKeys::derive(&client_key, "c", "mitm");
+        assert!(!relayed.verify(Side::Server, &server.confirm(Side::Server)));
+
+        let (client_key, server_key) = exchange("123456", "654321");
+        let client = PairingKeys::derive(&client_key, "c", "s");
+        let server = PairingKeys::derive(&server_key, "c", "s");
+        assert!(!client.verify(Side::Server, &server.confirm(Side::Server)));
+    }
+
+    #[test]
+    fn test_reject_replayed_request() {
+        let sync = LanSync::new();
+        let stamp = Stamp::new().unwrap_or_default();
+        assert!(sync.check_stamp(&stamp).is_ok());
+        assert!(sync.check_stamp(&stamp).is_err());
+        assert_eq!(stamp.reply().nonce, stamp.nonce);
+
+        let expired = Stamp {
+            time: Local::now().timestamp() - 3600,
+            nonce: [0u8; NONCE_LENGTH],
+        };
+        assert!(sync.check_stamp(&expired).is_err());
+    }
+}
@@ -0,0 +1,355 @@
+#![cfg(target_os = "linux")]
+//! 按桌面环境设置 Linux 系统代理
+//!
+//! GNOME 系桌面通过 `gsettings` 修改 `org.gnome.system.proxy`，KDE 通过 `kwriteconfig` 修改 `kioslaverc`
+//! 并通知 KIO 重新读取；其他桌面写入 `~/.config/environment.d`，重新登录后对所有程序生效。
+//! 默认按 `XDG_CURRENT_DESKTOP` 自动选择，也可以在设置中指定。首次修改前把原值保存到 [`BACKUP_FILE`]，
+//! 关闭系统代理或退出时恢复，应用异常退出后也能在下次关闭时还原。
+
+use super::proxy_scope::ProxyScopeMode;
+use crate::utils::dirs;
+use anyhow::{Result, bail};
+use serde::{Deserialize, Serialize};
+use smartstring::alias::String;
+use std::{path::PathBuf, process::Command};
+
+const BACKUP_FILE: &str = "linux_proxy_backup.json";
+const ENVIRONMENT_FILE: &str = "90-clash-verge-proxy.conf";
+const KDE_GROUP: &str = "Proxy Settings";
+
+/// 需要备份与恢复的 GNOME 设置
+const GNOME_KEYS: [(&str, &str); 9] = [
+    ("org.gnome.system.proxy", "mode"),
+    ("org.gnome.system.proxy", "autoconfig-url"),
+    ("org.gnome.system.proxy", "ignore-hosts"),
+    ("org.gnome.system.proxy.http", "host"),
+    ("org.gnome.system.proxy.http", "port"),
+    ("org.gnome.system.proxy.https", "host"),
+    ("org.gnome.system.proxy.https", "port"),
+    ("org.gnome.system.proxy.socks", "host"),
+    ("org.gnome.system.proxy.socks", "port"),
+];
+
+const KDE_KEYS: [&str; 6] = [
+    "ProxyType",
+    "httpProxy",
+    "httpsProxy",
+    "socksProxy",
+    "NoProxyFor",
+    "Proxy Config Script",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DesktopBackend {
+    Gnome,
+    Kde,
+    /// 写入 environment.d，适用于没有图形代理设置的桌面
+    Environment,
+}
+
+impl DesktopBackend {
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Gnome => "gnome",
+            Self::Kde => "kde",
+            Self::Environment => "environment",
+        }
+    }
+
+    /// `auto` 或未设置时按当前桌面检测
+    pub fn resolve(setting: Option<&str>) -> Self {
+        match setting {
+            Some("gnome") => Self::Gnome,
+            Some("kde") => Self::Kde,
+            Some("environment") => Self::Environment,
+            _ => detect(std::env::var("XDG_CURRENT_DESKTOP").ok().as_deref()),
+        }
+    }
+}
+
+/// 修改前的设置，`None` 表示原本未设置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct DesktopBackup {
+    backend: DesktopBackend,
+    values: Vec<(String, Option<String>)>,
+}
+
+fn find_in_path(program: &str) -> bool {
+    std::env::var_os("PATH").is_some_and(|paths| std::env::split_paths(&paths).any(|dir| dir.join(program).is_file()))
+}
+
+/// 桌面名可能是 `ubuntu:GNOME` 这样的列表
+fn detect(desktop: Option<&str>) -> DesktopBackend {
+    let desktop = desktop.unwrap_or_default().to_ascii_lowercase();
+    let is_any = |names: &[&str]| desktop.split(':').any(|part| names.contains(&part));
+    if is_any(&["kde"]) && kde_tool("kwriteconfig").is_some() {
+        DesktopBackend::Kde
+    } else if is_any(&["gnome", "unity", "cinnamon", "budgie", "pantheon", "mate", "x-cinnamon"])
+        && find_in_path("gsettings")
+    {
+        DesktopBackend::Gnome
+    } else {
+        DesktopBackend::Environment
+    }
+}
+
+/// Plasma 6 使用 `kwriteconfig6`，之前的版本为 `kwriteconfig5`
+fn kde_tool(base: &str) -> Option<std::string::String> {
+    ["6", "5"]
+        .into_iter()
+        .map(|version| format!("{base}{version}"))
+        .find(|tool| find_in_path(tool))
+}
+
+fn run(program: &str, args: &[&str]) -> Result<std::string::String> {
+    let output = Command::new(program).args(args).output()?;
+    if !output.status.success() {
+        bail!(
+            "{program} {} failed: {}",
+            args.first().copied().unwrap_or_default(),
+            std::string::String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(std::string::String::from_utf8_lossy(&output.stdout).trim().to_owned())
+}
+
+fn backup_path() -> Result<PathBuf> {
+    Ok(dirs::app_home_dir()?.join(BACKUP_FILE))
+}
+
+fn environment_path() -> Result<PathBuf> {
+    let config = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .ok_or_else(|| anyhow::anyhow!("failed to locate the config directory"))?;
+    Ok(config.join("environment.d").join(ENVIRONMENT_FILE))
+}
+
+fn gnome_key(schema: &str, key: &str) -> String {
+    format!("{schema} {key}").into()
+}
+
+fn read_backup(backend: DesktopBackend) -> Result<DesktopBackup> {
+    let values = match backend {
+        DesktopBackend::Gnome => GNOME_KEYS
+            .iter()
+            .map(|(schema, key)| {
+                let value = run("gsettings", &["get", schema, key]).ok().map(Into::into);
+                (gnome_key(schema, key), value)
+            })
+            .collect(),
+        DesktopBackend::Kde => {
+            let Some(tool) = kde_tool("kreadconfig") else {
+                bail!("kreadconfig is not available");
+            };
+            KDE_KEYS
+                .iter()
+                .map(|key| {
+                    let value = run(&tool, &["--file", "kioslaverc", "--group", KDE_GROUP, "--key", key])
+                        .ok()
+                        .filter(|value| !value.is_empty())
+                        .map(Into::into);
+                    (String::from(*key), value)
+                })
+                .collect()
+        }
+        // environment.d 使用单独的文件，恢复时删除即可
+        DesktopBackend::Environment => Vec::new(),
+    };
+    Ok(DesktopBackup { backend, values })
+}
+
+/// GNOME 的 `ignore-hosts` 为字符串数组
+fn gnome_hosts(bypass: &str) -> std::string::String {
+    let hosts: Vec<std::string::String> = bypass
+        .split(',')
+        .map(str::trim)
+        .filter(|host| !host.is_empty())
+        .map(|host| format!("'{}'", host.replace('\'', "")))
+        .collect();
+    format!("[{}]", hosts.join(", "))
+}
+
+fn apply_gnome(mode: &ProxyScopeMode) -> Result<()> {
+    let set = |schema: &str, key: &str, value: &str| run("gsettings", &["set", schema, key, value]).map(drop);
+    match mode {
+        ProxyScopeMode::Off => set("org.gnome.system.proxy", "mode", "'none'"),
+        ProxyScopeMode::Manual { host, port, bypass } => {
+            let port = port.to_string();
+            for schema in [
+                "org.gnome.system.proxy.http",
+                "org.gnome.system.proxy.https",
+                "org.gnome.system.proxy.socks",
+            ] {
+                set(schema, "host", host)?;
+                set(schema, "port", &port)?;
+            }
+            set("org.gnome.system.proxy", "ignore-hosts", &gnome_hosts(bypass))?;
+            set("org.gnome.system.proxy", "mode", "'manual'")
+        }
+        ProxyScopeMode::Pac { url } => {
+            set("org.gnome.system.proxy", "autoconfig-url", url)?;
+            set("org.gnome.system.proxy", "mode", "'auto'")
+        }
+    }
+}
+
+fn kde_write(key: &str, value: Option<&str>) -> Result<()> {
+    let Some(tool) = kde_tool("kwriteconfig") else {
+        bail!("kwriteconfig is not available");
+    };
+    let mut args = vec!["--file", "kioslaverc", "--group", KDE_GROUP, "--key", key];
+    match value {
+        Some(value) => args.push(value),
+        None => args.push("--delete"),
+    }
+    run(&tool, &args).map(drop)
+}
+
+/// 通知 KIO 重新读取代理设置，失败时新打开的程序仍会读取到新设置
+fn kde_notify() {
+    let _ = run(
+        "dbus-send",
+        &[
+            "--type=signal",
+            "/KIO/Scheduler",
+            "org.kde.KIO.Scheduler.reparseSlaveConfiguration",
+            "string:",
+        ],
+    );
+}
+
+fn apply_kde(mode: &ProxyScopeMode) -> Result<()> {
+    match mode {
+        ProxyScopeMode::Off => kde_write("ProxyType", Some("0"))?,
+        ProxyScopeMode::Manual { host, port, bypass } => {
+            kde_write("httpProxy", Some(&format!("http://{host} {port}")))?;
+            kde_write("httpsProxy", Some(&format!("http://{host} {port}")))?;
+            kde_write("socksProxy", Some(&format!("socks://{host} {port}")))?;
+            kde_write("NoProxyFor", Some(bypass))?;
+            kde_write("ProxyType", Some("1"))?;
+        }
+        ProxyScopeMode::Pac { url } => {
+            kde_write("Proxy Config Script", Some(url))?;
+            kde_write("ProxyType", Some("2"))?;
+        }
+    }
+    kde_notify();
+    Ok(())
+}
+
+fn environment_content(host: &str, port: u16, bypass: &str) -> std::string::String {
+    let proxy = format!("http://{host}:{port}");
+    let socks = format!("socks5://{host}:{port}");
+    let lines: Vec<std::string::String> = [
+        ("http_proxy", proxy.as_str()),
+        ("https_proxy", proxy.as_str()),
+        ("all_proxy", socks.as_str()),
+        ("no_proxy", bypass),
+    ]
+    .into_iter()
+    .flat_map(|(key, value)| {
+        [
+            format!("{key}={value}"),
+            format!("{}={value}", key.to_ascii_uppercase()),
+        ]
+    })
+    .collect();
+    format!(
+        "# Generated by Clash Verge, removed when the system proxy is disabled\n{}\n",
+        lines.join("\n")
+    )
+}
+
+fn apply_environment(mode: &ProxyScopeMode) -> Result<()> {
+    let path = environment_path()?;
+    match mode {
+        ProxyScopeMode::Manual { host, port, bypass } => {
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            std::fs::write(&path, environment_content(host, *port, bypass))?;
+        }
+        // 环境变量不支持 PAC
+        ProxyScopeMode::Off | ProxyScopeMode::Pac { .. } => {
+            if path.exists() {
+                std::fs::remove_file(&path)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 使用 `backend` 应用代理，之前用其他方式设置过时先恢复
+pub fn apply(backend: DesktopBackend, mode: &ProxyScopeMode) -> Result<()> {
+    if matches!(mode, ProxyScopeMode::Off) {
+        return restore();
+    }
+    let path = backup_path()?;
+    let previous = std::fs::read_to_string(&path)
+        .ok()
+        .and_then(|content| serde_json::from_str::<DesktopBackup>(&content).ok());
+    if previous.is_some_and(|backup| backup.backend != backend) {
+        restore()?;
+    }
+    if !path.exists() {
+        std::fs::write(&path, serde_json::to_string(&read_backup(backend)?)?)?;
+    }
+
+    match backend {
+        DesktopBackend::Gnome => apply_gnome(mode),
+        DesktopBackend::Kde => apply_kde(mode),
+        DesktopBackend::Environment => apply_environment(mode),
+    }
+}
+
+/// 恢复修改前的设置，没有备份时不做任何事
+pub fn restore() -> Result<()> {
+    let path = backup_path()?;
+    if !path.exists() {
+        return Ok(());
+    }
+    let backup: DesktopBackup = serde_json::from_str(&std::fs::read_to_string(&path)?)?;
+    match backup.backend {
+        DesktopBackend::Gnome => {
+            for (key, value) in &backup.values {
+                let (Some((schema, key)), Some(value)) = (key.split_once(' '), value) else {
+                    continue;
+                };
+                run("gsettings", &["set", schema, key, value.as_str()])?;
+            }
+        }
+        DesktopBackend::Kde => {
+            for (key, value) in &backup.values {
+                kde_write(key, value.as_deref())?;
+            }
+            kde_notify();
+        }
+        DesktopBackend::Environment => apply_environment(&ProxyScopeMode::Off)?,
+    }
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gnome_hosts() {
+        assert_eq!(
+            gnome_hosts("localhost, 127.0.0.1/8,,*.local"),
+            "['localhost', '127.0.0.1/8', '*.local']"
+        );
+        assert_eq!(gnome_hosts(""), "[]");
+    }
+
+    #[test]
+    fn test_environment_content() {
+        let content = environment_content("127.0.0.1", 7897, "localhost");
+        assert!(content.contains("http_proxy=http://127.0.0.1:7897\n"));
+        assert!(content.contains("ALL_PROXY=socks5://127.0.0.1:7897\n"));
+        assert!(content.contains("NO_PROXY=localhost\n"));
+    }
+}
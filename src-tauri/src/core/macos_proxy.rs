@@ -0,0 +1,435 @@
+#![cfg(target_os = "macos")]
+//! 通过 SystemConfiguration 框架设置 macOS 系统代理
+//!
+//! 取代逐个服务调用 `networksetup`：在一次 `SCPreferences` 事务中读取当前网络位置的全部服务，
+//! 修改各服务的 `Proxies` 协议配置（手动代理、PAC 与例外列表）后一次提交，未知的键保持不变。
+//! 另外通过 `SCDynamicStore` 订阅全局代理状态的变化，其他程序改写代理时立即得到通知。
+
+use super::proxy_scope::{ProxyScopeMode, is_selected};
+use anyhow::{Result, bail};
+use clash_verge_logging::{Type, logging};
+use once_cell::sync::OnceCell;
+use smartstring::alias::String;
+use std::{
+    ffi::{c_char, c_int, c_void},
+    ptr,
+};
+
+type CFTypeRef = *const c_void;
+type CFStringRef = *const c_void;
+type CFArrayRef = *const c_void;
+type CFDictionaryRef = *const c_void;
+type CFMutableDictionaryRef = *mut c_void;
+type CFRunLoopSourceRef = *const c_void;
+type CFRunLoopRef = *const c_void;
+type CFIndex = isize;
+type Boolean = u8;
+type SCPreferencesRef = *const c_void;
+type SCNetworkSetRef = *const c_void;
+type SCNetworkServiceRef = *const c_void;
+type SCNetworkProtocolRef = *const c_void;
+type SCDynamicStoreRef = *const c_void;
+type AuthorizationRef = *const c_void;
+type DynamicStoreCallBack = extern "C" fn(SCDynamicStoreRef, CFArrayRef, *mut c_void);
+
+const CF_STRING_ENCODING_UTF8: u32 = 0x0800_0100;
+const CF_NUMBER_SINT32_TYPE: CFIndex = 3;
+const AUTHORIZATION_FLAG_DEFAULTS: u32 = 0;
+
+/// 全局代理状态在动态存储中的键
+const PROXIES_STATE_KEY: &str = "State:/Network/Global/Proxies";
+const PROXIES_PROTOCOL: &str = "Proxies";
+
+#[repr(C)]
+struct CallBacks {
+    _private: [u8; 0],
+}
+
+#[link(name = "CoreFoundation", kind = "framework")]
+unsafe extern "C" {
+    static kCFTypeArrayCallBacks: CallBacks;
+    static kCFTypeDictionaryKeyCallBacks: CallBacks;
+    static kCFTypeDictionaryValueCallBacks: CallBacks;
+    static kCFRunLoopDefaultMode: CFStringRef;
+
+    fn CFRelease(cf: CFTypeRef);
+    fn CFStringCreateWithBytes(
+        alloc: CFTypeRef,
+        bytes: *const u8,
+        len: CFIndex,
+        encoding: u32,
+        external: Boolean,
+    ) -> CFStringRef;
+    fn CFStringGetLength(string: CFStringRef) -> CFIndex;
+    fn CFStringGetMaximumSizeForEncoding(len: CFIndex, encoding: u32) -> CFIndex;
+    fn CFStringGetCString(string: CFStringRef, buffer: *mut c_char, size: CFIndex, encoding: u32) -> Boolean;
+    fn CFArrayCreate(
+        alloc: CFTypeRef,
+        values: *const CFTypeRef,
+        count: CFIndex,
+        callbacks: *const CallBacks,
+    ) -> CFArrayRef;
+    fn CFArrayGetCount(array: CFArrayRef) -> CFIndex;
+    fn CFArrayGetValueAtIndex(array: CFArrayRef, index: CFIndex) -> CFTypeRef;
+    fn CFDictionaryCreateMutable(
+        alloc: CFTypeRef,
+        capacity: CFIndex,
+        key_callbacks: *const CallBacks,
+        value_callbacks: *const CallBacks,
+    ) -> CFMutableDictionaryRef;
+    fn CFDictionaryCreateMutableCopy(
+        alloc: CFTypeRef,
+        capacity: CFIndex,
+        dict: CFDictionaryRef,
+    ) -> CFMutableDictionaryRef;
+    fn CFDictionarySetValue(dict: CFMutableDictionaryRef, key: CFTypeRef, value: CFTypeRef);
+    fn CFDictionaryRemoveValue(dict: CFMutableDictionaryRef, key: CFTypeRef);
+    fn CFNumberCreate(alloc: CFTypeRef, number_type: CFIndex, value: *const c_void) -> CFTypeRef;
+    fn CFRunLoopGetCurrent() -> CFRunLoopRef;
+    fn CFRunLoopAddSource(run_loop: CFRunLoopRef, source: CFRunLoopSourceRef, mode: CFStringRef);
+    fn CFRunLoopRun();
+}
+
+#[link(name = "SystemConfiguration", kind = "framework")]
+unsafe extern "C" {
+    fn SCError() -> c_int;
+    fn SCPreferencesCreateWithAuthorization(
+        alloc: CFTypeRef,
+        name: CFStringRef,
+        prefs_id: CFStringRef,
+        authorization: AuthorizationRef,
+    ) -> SCPreferencesRef;
+    fn SCPreferencesLock(prefs: SCPreferencesRef, wait: Boolean) -> Boolean;
+    fn SCPreferencesUnlock(prefs: SCPreferencesRef) -> Boolean;
+    fn SCPreferencesCommitChanges(prefs: SCPreferencesRef) -> Boolean;
+    fn SCPreferencesApplyChanges(prefs: SCPreferencesRef) -> Boolean;
+    fn SCNetworkSetCopyCurrent(prefs: SCPreferencesRef) -> SCNetworkSetRef;
+    fn SCNetworkSetCopyServices(set: SCNetworkSetRef) -> CFArrayRef;
+    fn SCNetworkServiceGetName(service: SCNetworkServiceRef) -> CFStringRef;
+    fn SCNetworkServiceCopyProtocol(service: SCNetworkServiceRef, protocol_type: CFStringRef) -> SCNetworkProtocolRef;
+    fn SCNetworkProtocolGetConfiguration(protocol: SCNetworkProtocolRef) -> CFDictionaryRef;
+    fn SCNetworkProtocolSetConfiguration(protocol: SCNetworkProtocolRef, config: CFDictionaryRef) -> Boolean;
+    fn SCDynamicStoreCreate(
+        alloc: CFTypeRef,
+        name: CFStringRef,
+        callout: Option<DynamicStoreCallBack>,
+        context: *mut c_void,
+    ) -> SCDynamicStoreRef;
+    fn SCDynamicStoreSetNotificationKeys(store: SCDynamicStoreRef, keys: CFArrayRef, patterns: CFArrayRef) -> Boolean;
+    fn SCDynamicStoreCreateRunLoopSource(
+        alloc: CFTypeRef,
+        store: SCDynamicStoreRef,
+        order: CFIndex,
+    ) -> CFRunLoopSourceRef;
+}
+
+#[link(name = "Security", kind = "framework")]
+unsafe extern "C" {
+    fn AuthorizationCreate(
+        rights: *const c_void,
+        environment: *const c_void,
+        flags: u32,
+        authorization: *mut AuthorizationRef,
+    ) -> i32;
+    fn AuthorizationFree(authorization: AuthorizationRef, flags: u32) -> i32;
+}
+
+/// 持有 Create / Copy 得到的对象，离开作用域时释放
+struct Owned(CFTypeRef);
+
+impl Owned {
+    fn new(value: CFTypeRef, what: &str) -> Result<Self> {
+        if value.is_null() {
+            bail!("{what} failed: SCError {}", unsafe { SCError() });
+        }
+        Ok(Self(value))
+    }
+}
+
+impl Drop for Owned {
+    fn drop(&mut self) {
+        // SAFETY: 只保存非空且由本进程持有所有权的对象
+        unsafe { CFRelease(self.0) }
+    }
+}
+
+fn cf_string(value: &str) -> Result<Owned> {
+    let len = CFIndex::try_from(value.len())?;
+    // SAFETY: 字节在调用期间有效，CoreFoundation 会复制内容
+    let string = unsafe { CFStringCreateWithBytes(ptr::null(), value.as_ptr(), len, CF_STRING_ENCODING_UTF8, 0) };
+    Owned::new(string, "CFStringCreateWithBytes")
+}
+
+fn cf_number(value: i32) -> Result<Owned> {
+    // SAFETY: value 在调用期间有效
+    let number = unsafe { CFNumberCreate(ptr::null(), CF_NUMBER_SINT32_TYPE, (&raw const value).cast()) };
+    Owned::new(number, "CFNumberCreate")
+}
+
+fn cf_array(values: &[&Owned]) -> Result<Owned> {
+    let values: Vec<CFTypeRef> = values.iter().map(|value| value.0).collect();
+    // SAFETY: 数组元素均为有效的 CF 对象，回调会为数组持有引用
+    let array = unsafe {
+        CFArrayCreate(
+            ptr::null(),
+            values.as_ptr(),
+            CFIndex::try_from(values.len())?,
+            &raw const kCFTypeArrayCallBacks,
+        )
+    };
+    Owned::new(array, "CFArrayCreate")
+}
+
+fn to_string(string: CFStringRef) -> Option<String> {
+    if string.is_null() {
+        return None;
+    }
+    // SAFETY: string 为有效的 CFString，缓冲区大小按最大编码长度分配
+    unsafe {
+        let size = CFStringGetMaximumSizeForEncoding(CFStringGetLength(string), CF_STRING_ENCODING_UTF8) + 1;
+        let mut buffer = vec![0u8; usize::try_from(size).ok()?];
+        if CFStringGetCString(string, buffer.as_mut_ptr().cast(), size, CF_STRING_ENCODING_UTF8) == 0 {
+            return None;
+        }
+        let end = buffer.iter().position(|&b| b == 0).unwrap_or(buffer.len());
+        Some(std::string::String::from_utf8_lossy(&buffer[..end]).into())
+    }
+}
+
+/// 一次修改事务，持有系统网络配置的锁，提交或放弃时释放
+struct Preferences {
+    prefs: Owned,
+    authorization: AuthorizationRef,
+    locked: bool,
+}
+
+impl Preferences {
+    fn open() -> Result<Self> {
+        let mut authorization: AuthorizationRef = ptr::null();
+        // SAFETY: 输出参数指向有效的局部变量
+        let status = unsafe {
+            AuthorizationCreate(
+                ptr::null(),
+                ptr::null(),
+                AUTHORIZATION_FLAG_DEFAULTS,
+                &raw mut authorization,
+            )
+        };
+        if status != 0 {
+            bail!("AuthorizationCreate failed: {status}");
+        }
+        let name = cf_string("Clash Verge")?;
+        // SAFETY: 参数均为有效对象，prefs_id 为空表示系统网络配置
+        let prefs = unsafe { SCPreferencesCreateWithAuthorization(ptr::null(), name.0, ptr::null(), authorization) };
+        let prefs = Owned::new(prefs, "SCPreferencesCreateWithAuthorization").inspect_err(|_| {
+            // SAFETY: authorization 由上面创建
+            unsafe { AuthorizationFree(authorization, AUTHORIZATION_FLAG_DEFAULTS) };
+        })?;
+        Ok(Self {
+            prefs,
+            authorization,
+            locked: false,
+        })
+    }
+
+    fn lock(&mut self) -> Result<()> {
+        // SAFETY: prefs 有效
+        if unsafe { SCPreferencesLock(self.prefs.0, 1) } == 0 {
+            bail!("SCPreferencesLock failed: SCError {}", unsafe { SCError() });
+        }
+        self.locked = true;
+        Ok(())
+    }
+
+    /// 当前网络位置下的全部服务
+    fn services(&self) -> Result<(Owned, Vec<SCNetworkServiceRef>)> {
+        // SAFETY: prefs 有效，返回的对象由 Owned 释放
+        let set = Owned::new(
+            unsafe { SCNetworkSetCopyCurrent(self.prefs.0) },
+            "SCNetworkSetCopyCurrent",
+        )?;
+        let services = Owned::new(unsafe { SCNetworkSetCopyServices(set.0) }, "SCNetworkSetCopyServices")?;
+        // SAFETY: services 为有效数组，元素在数组释放前有效
+        let count = unsafe { CFArrayGetCount(services.0) };
+        let items = (0..count)
+            .map(|index| unsafe { CFArrayGetValueAtIndex(services.0, index) })
+            .collect();
+        Ok((services, items))
+    }
+
+    fn commit(mut self) -> Result<()> {
+        // SAFETY: prefs 有效且已加锁
+        unsafe {
+            if SCPreferencesCommitChanges(self.prefs.0) == 0 {
+                bail!("SCPreferencesCommitChanges failed: SCError {}", SCError());
+            }
+            if SCPreferencesApplyChanges(self.prefs.0) == 0 {
+                bail!("SCPreferencesApplyChanges failed: SCError {}", SCError());
+            }
+            SCPreferencesUnlock(self.prefs.0);
+        }
+        self.locked = false;
+        Ok(())
+    }
+}
+
+impl Drop for Preferences {
+    fn drop(&mut self) {
+        // SAFETY: prefs 与 authorization 在 open 中创建
+        unsafe {
+            if self.locked {
+                SCPreferencesUnlock(self.prefs.0);
+            }
+            AuthorizationFree(self.authorization, AUTHORIZATION_FLAG_DEFAULTS);
+        }
+    }
+}
+
+pub fn list_services() -> Result<Vec<String>> {
+    let prefs = Preferences::open()?;
+    let (_services, items) = prefs.services()?;
+    Ok(items
+        .into_iter()
+        .filter_map(|service| to_string(unsafe { SCNetworkServiceGetName(service) }))
+        .collect())
+}
+
+/// 在服务原有的代理配置上按模式修改，只改动本应用管理的键
+fn build_config(existing: CFDictionaryRef, mode: &ProxyScopeMode) -> Result<Owned> {
+    // SAFETY: existing 为空时创建新字典，否则复制一份可变字典
+    let dict = unsafe {
+        if existing.is_null() {
+            CFDictionaryCreateMutable(
+                ptr::null(),
+                0,
+                &raw const kCFTypeDictionaryKeyCallBacks,
+                &raw const kCFTypeDictionaryValueCallBacks,
+            )
+        } else {
+            CFDictionaryCreateMutableCopy(ptr::null(), 0, existing)
+        }
+    };
+    let dict = Owned::new(dict.cast_const(), "CFDictionaryCreateMutable")?;
+    let set = |key: &str, value: &Owned| -> Result<()> {
+        let key = cf_string(key)?;
+        // SAFETY: 字典会为键值持有引用
+        unsafe { CFDictionarySetValue(dict.0.cast_mut(), key.0, value.0) };
+        Ok(())
+    };
+    let remove = |key: &str| -> Result<()> {
+        let key = cf_string(key)?;
+        // SAFETY: 键不存在时调用无影响
+        unsafe { CFDictionaryRemoveValue(dict.0.cast_mut(), key.0) };
+        Ok(())
+    };
+
+    let (manual, pac) = match mode {
+        ProxyScopeMode::Off => (None, None),
+        ProxyScopeMode::Manual { host, port, bypass } => (Some((host, *port, bypass)), None),
+        ProxyScopeMode::Pac { url } => (None, Some(url)),
+    };
+    let on = cf_number(1)?;
+    let off = cf_number(0)?;
+
+    for prefix in ["HTTP", "HTTPS", "SOCKS"] {
+        match manual {
+            Some((host, port, _)) => {
+                set(&format!("{prefix}Enable"), &on)?;
+                set(&format!("{prefix}Proxy"), &cf_string(host)?)?;
+                set(&format!("{prefix}Port"), &cf_number(i32::from(port))?)?;
+            }
+            None => set(&format!("{prefix}Enable"), &off)?,
+        }
+    }
+    if let Some((_, _, bypass)) = manual {
+        let domains: Vec<Owned> = bypass
+            .split(',')
+            .map(str::trim)
+            .filter(|domain| !domain.is_empty())
+            .map(cf_string)
+            .collect::<Result<_>>()?;
+        set("ExceptionsList", &cf_array(&domains.iter().collect::<Vec<_>>())?)?;
+    }
+    match pac {
+        Some(url) => {
+            set("ProxyAutoConfigEnable", &on)?;
+            set("ProxyAutoConfigURLString", &cf_string(url)?)?;
+        }
+        None => {
+            set("ProxyAutoConfigEnable", &off)?;
+            remove("ProxyAutoConfigURLString")?;
+        }
+    }
+    Ok(dict)
+}
+
+/// 选中的服务（为空表示全部）应用 `mode`，其余服务关闭代理，一次提交
+pub fn set_proxies(selected: &[String], mode: &ProxyScopeMode) -> Result<()> {
+    let mut prefs = Preferences::open()?;
+    prefs.lock()?;
+    let (_services, items) = prefs.services()?;
+    let protocol_type = cf_string(PROXIES_PROTOCOL)?;
+    for service in items {
+        let Some(name) = to_string(unsafe { SCNetworkServiceGetName(service) }) else {
+            continue;
+        };
+        let service_mode = if is_selected(selected, &name) {
+            mode
+        } else {
+            &ProxyScopeMode::Off
+        };
+        // SAFETY: service 在 _services 释放前有效
+        let protocol = unsafe { SCNetworkServiceCopyProtocol(service, protocol_type.0) };
+        let Ok(protocol) = Owned::new(protocol, "SCNetworkServiceCopyProtocol") else {
+            continue;
+        };
+        let config = build_config(unsafe { SCNetworkProtocolGetConfiguration(protocol.0) }, service_mode)?;
+        // SAFETY: protocol 与 config 均有效
+        if unsafe { SCNetworkProtocolSetConfiguration(protocol.0, config.0) } == 0 {
+            bail!("failed to set proxies for {name}: SCError {}", unsafe { SCError() });
+        }
+    }
+    prefs.commit()
+}
+
+static ON_CHANGE: OnceCell<fn()> = OnceCell::new();
+
+extern "C" fn proxies_changed(_store: SCDynamicStoreRef, _keys: CFArrayRef, _info: *mut c_void) {
+    if let Some(on_change) = ON_CHANGE.get() {
+        on_change();
+    }
+}
+
+/// 在后台线程订阅全局代理设置的变化，只会启动一次
+pub fn watch_changes(on_change: fn()) {
+    if ON_CHANGE.set(on_change).is_err() {
+        return;
+    }
+    std::thread::spawn(|| {
+        let run = || -> Result<()> {
+            let name = cf_string("Clash Verge Proxy Watcher")?;
+            // SAFETY: 回调为静态函数，不需要上下文
+            let store = Owned::new(
+                unsafe { SCDynamicStoreCreate(ptr::null(), name.0, Some(proxies_changed), ptr::null_mut()) },
+                "SCDynamicStoreCreate",
+            )?;
+            let keys = cf_array(&[&cf_string(PROXIES_STATE_KEY)?])?;
+            // SAFETY: store 与 keys 均有效，运行循环在本线程中一直运行
+            unsafe {
+                if SCDynamicStoreSetNotificationKeys(store.0, keys.0, ptr::null()) == 0 {
+                    bail!("SCDynamicStoreSetNotificationKeys failed: SCError {}", SCError());
+                }
+                let source = Owned::new(
+                    SCDynamicStoreCreateRunLoopSource(ptr::null(), store.0, 0),
+                    "SCDynamicStoreCreateRunLoopSource",
+                )?;
+                CFRunLoopAddSource(CFRunLoopGetCurrent(), source.0, kCFRunLoopDefaultMode);
+                CFRunLoopRun();
+            }
+            Ok(())
+        };
+        if let Err(err) = run() {
+            logging!(warn, Type::Core, "Failed to watch proxy changes: {err}");
+        }
+    });
+}
@@ -1,8 +1,16 @@
-use super::CoreManager;
+use super::{
+    CoreManager,
+    reload::{self, ReloadPlan},
+};
 use crate::{
     config::{Config, ConfigType},
-    constants::timing,
-    core::{handle, validate::CoreConfigValidator},
+    constants::{files::CHECK_CONFIG, timing},
+    core::{
+        backend::CoreBackend,
+        handle,
+        selection::SelectionMemory,
+        validate::{CoreConfigValidator, parse_diagnostics},
+    },
     utils::{dirs, help},
 };
 use anyhow::{Result, anyhow};
@@ -10,6 +18,7 @@ use clash_verge_logging::{Type, logging};
 use clash_verge_types::runtime::IRuntime;
 use smartstring::alias::String;
 use std::{collections::HashSet, path::PathBuf, time::Instant};
+use tauri::Emitter as _;
 use tauri_plugin_mihomo::Error as MihomoError;
 
 impl CoreManager {
@@ -72,6 +81,9 @@ impl CoreManager {
             }
             Ok((false, error_msg)) => {
                 Config::runtime().await.discard();
+                let check_file = dirs::app_home_dir()?.join(CHECK_CONFIG);
+                let diagnostics = parse_diagnostics(&check_file.to_string_lossy(), &error_msg);
+                let _ = handle::Handle::app_handle().emit("config-validation-failed", &diagnostics);
                 Ok((false, error_msg))
             }
             Err(e) => {
@@ -82,8 +94,41 @@ impl CoreManager {
     }
 
     async fn apply_config(&self, path: PathBuf) -> Result<()> {
-        let path = dirs::path_to_str(&path)?;
-        match self.reload_config(path).await {
+        if !CoreBackend::of(&Config::verge().await.latest_arc()).supports_hot_reload() {
+            Config::runtime().await.apply();
+            logging!(
+                info,
+                Type::Core,
+                "Backend cannot hot reload, restarting core to apply config"
+            );
+            return self.restart_core().await;
+        }
+
+        let plan = {
+            let runtime = Config::runtime().await;
+            let previous = runtime.data_arc().config.clone();
+            let next = runtime.latest_arc().config.clone().unwrap_or_default();
+            reload::plan(previous.as_ref(), &next)
+        };
+        logging!(info, Type::Core, "Applying config: {}", plan.describe());
+
+        let result = match plan {
+            ReloadPlan::Unchanged => Ok(()),
+            ReloadPlan::Patch(patch) => handle::Handle::mihomo().await.patch_base_config(&patch).await,
+            ReloadPlan::Reload(_) => {
+                let result = self.reload_config(dirs::path_to_str(&path)?).await;
+                // 重载后内核会重置代理组的选择
+                if result.is_ok() {
+                    SelectionMemory::global().restore_when_ready();
+                }
+                result
+            }
+            ReloadPlan::Restart(_) => {
+                Config::runtime().await.apply();
+                return self.restart_core().await;
+            }
+        };
+        match result {
             Ok(_) => {
                 Config::runtime().await.apply();
                 logging!(info, Type::Core, "Configuration applied");
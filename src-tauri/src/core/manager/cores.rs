@@ -0,0 +1,293 @@
+//! 多内核管理：内置 mihomo 稳定版 / Alpha 版，以及用户注册的自定义二进制
+
+use super::{CoreManager, updater::stage_binary};
+use crate::{
+    config::{Config, ICustomCore, IVerge},
+    core::{backend::CoreBackend, handle},
+    process::AsyncHandler,
+    utils::dirs,
+};
+use anyhow::{Context as _, Result, bail};
+use serde::Serialize;
+use smartstring::alias::String;
+use std::{env::current_exe, path::PathBuf};
+use tauri_plugin_shell::{ShellExt as _, process::Command};
+
+const BIN_EXT: &str = if cfg!(windows) { ".exe" } else { "" };
+const MAX_CORE_NAME_LEN: usize = 32;
+/// 升级后的内置内核放在 cores 目录下，安装目录可能只读，macOS 上改动 .app 还会破坏签名。
+/// 按应用版本分目录，应用更新后重新使用随包附带的内核
+const UPGRADED_DIR: &str = "builtin";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CoreInfo {
+    pub name: String,
+    pub path: String,
+    pub custom: bool,
+    pub version: Option<String>,
+    pub active: bool,
+    pub backend: CoreBackend,
+}
+
+fn is_valid_core_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= MAX_CORE_NAME_LEN
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+/// 从版本输出中提取版本号，如 `Mihomo Meta v1.19.0 linux amd64 ...` 或 `sing-box version 1.11.0`
+pub fn parse_core_version(output: &str) -> Option<String> {
+    let line = output.lines().find(|line| !line.trim().is_empty())?;
+    if let Some(version) = line.trim().strip_prefix("sing-box version ") {
+        return Some(version.trim().into());
+    }
+    let version = line
+        .split_whitespace()
+        .find(|token| {
+            token.starts_with("alpha-")
+                || token
+                    .strip_prefix('v')
+                    .is_some_and(|rest| rest.starts_with(|c: char| c.is_ascii_digit()))
+        })
+        .unwrap_or_else(|| line.trim());
+    Some(version.into())
+}
+
+/// 依次尝试 mihomo 的 `-v` 与 sing-box 的 `version`，返回版本号及后端类型
+pub(super) fn detect_core(path: &PathBuf) -> Option<(String, CoreBackend)> {
+    ["-v", "version"].into_iter().find_map(|arg| {
+        let output = std::process::Command::new(path).arg(arg).output().ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        let stdout = std::string::String::from_utf8_lossy(&output.stdout);
+        let backend = if stdout.trim_start().starts_with("sing-box") {
+            CoreBackend::SingBox
+        } else {
+            CoreBackend::Mihomo
+        };
+        parse_core_version(&stdout).map(|version| (version, backend))
+    })
+}
+
+/// 在线升级写入的内置内核路径
+pub(super) fn upgraded_core_path(core: &str) -> Result<PathBuf> {
+    Ok(dirs::app_cores_dir()?
+        .join(UPGRADED_DIR)
+        .join(env!("CARGO_PKG_VERSION"))
+        .join(format!("{core}{BIN_EXT}")))
+}
+
+/// 已升级时返回升级后的内置内核
+fn upgraded_core(core: &str) -> Result<Option<PathBuf>> {
+    let path = upgraded_core_path(core)?;
+    Ok(path.is_file().then_some(path))
+}
+
+/// 内核可执行文件路径，内置内核优先使用升级后的副本，否则与主程序位于同一目录
+pub fn core_binary_path(verge: &IVerge, core: &str) -> Result<PathBuf> {
+    if let Some(custom) = verge.get_custom_core(core) {
+        return Ok(PathBuf::from(custom.path.as_str()));
+    }
+    if !IVerge::VALID_CLASH_CORES.contains(&core) {
+        bail!("unknown core: {core}");
+    }
+    if let Some(path) = upgraded_core(core)? {
+        return Ok(path);
+    }
+    Ok(current_exe()?.with_file_name(format!("{core}{BIN_EXT}")))
+}
+
+/// 构造启动内核的命令，内置内核走 sidecar，自定义内核与升级后的内置内核直接执行
+pub fn core_command(verge: &IVerge, core: &str) -> Result<Command> {
+    let shell = handle::Handle::app_handle().shell();
+    if let Some(custom) = verge.get_custom_core(core) {
+        return Ok(shell.command(custom.path.as_str()));
+    }
+    match upgraded_core(core)? {
+        Some(path) => Ok(shell.command(dirs::path_to_str(&path)?)),
+        None => Ok(shell.sidecar(core)?),
+    }
+}
+
+impl CoreManager {
+    /// 列出所有可用内核及其版本
+    pub async fn list_cores(&self) -> Result<Vec<CoreInfo>> {
+        let verge = Config::verge().await.latest_arc();
+        let active = verge.get_valid_clash_core();
+
+        let mut entries = Vec::new();
+        for core in IVerge::VALID_CLASH_CORES {
+            entries.push((
+                (*core).into(),
+                core_binary_path(&verge, core)?,
+                false,
+                CoreBackend::Mihomo,
+            ));
+        }
+        for custom in verge.custom_cores.iter().flatten() {
+            entries.push((
+                custom.name.clone(),
+                PathBuf::from(custom.path.as_str()),
+                true,
+                custom.backend,
+            ));
+        }
+
+        AsyncHandler::spawn_blocking(move || {
+            entries
+                .into_iter()
+                .map(|(name, path, custom, backend)| CoreInfo {
+                    active: name == active,
+                    version: detect_core(&path).map(|(version, _)| version),
+                    path: path.to_string_lossy().into(),
+                    name,
+                    custom,
+                    backend,
+                })
+                .collect()
+        })
+        .await
+        .map_err(Into::into)
+    }
+
+    /// 注册自定义内核，二进制会被复制到 cores 目录，之后由 [`Self::upgrade_custom_core`] 替换升级
+    pub async fn register_core(&self, name: &str, source: &str) -> Result<CoreInfo> {
+        if !is_valid_core_name(name) || name == UPGRADED_DIR || IVerge::VALID_CLASH_CORES.contains(&name) {
+            bail!("invalid core name: {name}");
+        }
+        let source = PathBuf::from(source);
+        if !source.is_file() {
+            bail!("core binary not found: {}", source.display());
+        }
+
+        let dir = dirs::app_cores_dir()?;
+        tokio::fs::create_dir_all(&dir).await?;
+        let target = dir.join(format!("{name}{BIN_EXT}"));
+        tokio::fs::copy(&source, &target).await?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt as _;
+            tokio::fs::set_permissions(&target, std::fs::Permissions::from_mode(0o755)).await?;
+        }
+
+        let probe = target.clone();
+        let Some((version, backend)) = AsyncHandler::spawn_blocking(move || detect_core(&probe)).await? else {
+            let _ = tokio::fs::remove_file(&target).await;
+            bail!("{} does not look like a mihomo or sing-box core", source.display());
+        };
+
+        let path: String = target.to_string_lossy().into();
+        let entry = ICustomCore {
+            name: name.into(),
+            path: path.clone(),
+            backend,
+        };
+        Config::verge().await.edit_draft(|d| {
+            let cores = d.custom_cores.get_or_insert_with(Vec::new);
+            cores.retain(|c| c.name != name);
+            cores.push(entry);
+        });
+        Config::verge().await.apply();
+        Config::verge().await.latest_arc().save_file().await?;
+
+        let active = Config::verge().await.latest_arc().get_valid_clash_core() == name;
+        Ok(CoreInfo {
+            name: name.into(),
+            path,
+            custom: true,
+            version: Some(version),
+            active,
+            backend,
+        })
+    }
+
+    /// 用新的二进制升级自定义内核，内核类型必须不变；正在使用时会重启内核，启动失败则回滚
+    pub async fn upgrade_custom_core(&self, name: &str, source: &str) -> Result<CoreInfo> {
+        let verge = Config::verge().await.latest_arc();
+        let Some(custom) = verge.get_custom_core(name) else {
+            bail!("custom core not found: {name}");
+        };
+        let target = PathBuf::from(custom.path.as_str());
+        let current_backend = custom.backend;
+        let active = verge.get_valid_clash_core() == name;
+        drop(verge);
+
+        let binary = tokio::fs::read(source)
+            .await
+            .with_context(|| format!("failed to read {source}"))?;
+        let (staged, version, backend) = stage_binary(&target, binary).await?;
+        if backend != current_backend {
+            let _ = tokio::fs::remove_file(&staged).await;
+            bail!("{source} is a {backend:?} core, register it as a new core instead");
+        }
+        self.install_binary(&target, &staged, active).await?;
+
+        Ok(CoreInfo {
+            name: name.into(),
+            path: target.to_string_lossy().into(),
+            custom: true,
+            version: Some(version),
+            active,
+            backend,
+        })
+    }
+
+    /// 移除自定义内核，正在使用的内核不可移除
+    pub async fn unregister_core(&self, name: &str) -> Result<()> {
+        let verge = Config::verge().await.latest_arc();
+        if verge.get_valid_clash_core() == name {
+            bail!("cannot remove the active core: {name}");
+        }
+        let Some(custom) = verge.get_custom_core(name) else {
+            bail!("custom core not found: {name}");
+        };
+        let path = PathBuf::from(custom.path.as_str());
+
+        Config::verge().await.edit_draft(|d| {
+            if let Some(cores) = d.custom_cores.as_mut() {
+                cores.retain(|c| c.name != name);
+            }
+        });
+        Config::verge().await.apply();
+        Config::verge().await.latest_arc().save_file().await?;
+
+        // 只删除由我们管理的副本
+        if path.starts_with(dirs::app_cores_dir()?) {
+            let _ = tokio::fs::remove_file(path).await;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_core_version() {
+        assert_eq!(
+            parse_core_version("Mihomo Meta v1.19.2 linux amd64 with go1.24.0\nUse tags: with_gvisor"),
+            Some("v1.19.2".into())
+        );
+        assert_eq!(
+            parse_core_version("Mihomo Meta alpha-7b4b1d2 darwin arm64"),
+            Some("alpha-7b4b1d2".into())
+        );
+        assert_eq!(
+            parse_core_version("sing-box version 1.11.4\n\nEnvironment: go1.23.6 linux/amd64"),
+            Some("1.11.4".into())
+        );
+        assert_eq!(parse_core_version("custom build\n"), Some("custom build".into()));
+        assert_eq!(parse_core_version(""), None);
+    }
+
+    #[test]
+    fn test_core_name_validation() {
+        assert!(is_valid_core_name("mihomo-smart"));
+        assert!(!is_valid_core_name("../mihomo"));
+        assert!(!is_valid_core_name(""));
+    }
+}
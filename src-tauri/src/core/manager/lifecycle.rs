@@ -1,10 +1,14 @@
 use super::{CoreManager, RunningMode};
-use crate::cmd::StringifyErr as _;
-use crate::config::{Config, IVerge};
+use crate::config::Config;
+use crate::core::audit::AuditLog;
+use crate::core::backend::CoreBackend;
+use crate::core::event_bus::{EventBus, StateEvent};
 use crate::core::handle::Handle;
+use crate::core::kill_switch::KillSwitch;
 use crate::core::manager::CLASH_LOGGER;
+use crate::core::selection::SelectionMemory;
 use crate::core::service::{SERVICE_MANAGER, ServiceStatus};
-use anyhow::Result;
+use anyhow::{Result, bail};
 use clash_verge_logging::{Type, logging};
 use scopeguard::defer;
 use smartstring::alias::String;
@@ -18,9 +22,12 @@ impl CoreManager {
         }
 
         match *self.get_running_mode() {
-            RunningMode::Service => self.start_core_by_service().await,
-            RunningMode::NotRunning | RunningMode::Sidecar => self.start_core_by_sidecar().await,
+            RunningMode::Service => self.start_core_by_service().await?,
+            RunningMode::NotRunning | RunningMode::Sidecar => self.start_core_by_sidecar().await?,
         }
+        KillSwitch::global().release_when_healthy();
+        SelectionMemory::global().restore_when_ready();
+        Ok(())
     }
 
     pub async fn stop_core(&self) -> Result<()> {
@@ -41,14 +48,20 @@ impl CoreManager {
 
     pub async fn restart_core(&self) -> Result<()> {
         logging!(info, Type::Core, "Restarting core");
+        crate::utils::crash::record_action("restart_core");
         self.stop_core().await?;
-        self.start_core().await
+        self.start_core().await?;
+        AuditLog::record("core.restart", None, None, None);
+        EventBus::global().publish(StateEvent::CoreRestarted);
+        Ok(())
     }
 
-    pub async fn change_core(&self, clash_core: &String) -> Result<(), String> {
-        if !IVerge::VALID_CLASH_CORES.contains(&clash_core.as_str()) {
-            return Err(format!("Invalid clash core: {}", clash_core).into());
+    pub async fn change_core(&self, clash_core: &String) -> Result<()> {
+        let previous = Config::verge().await.latest_arc();
+        if !previous.is_valid_clash_core(clash_core) {
+            bail!("Invalid clash core: {clash_core}");
         }
+        let previous_backend = CoreBackend::of(&previous);
 
         Config::verge().await.edit_draft(|d| {
             d.clash_core = Some(clash_core.to_owned());
@@ -56,9 +69,14 @@ impl CoreManager {
         Config::verge().await.apply();
 
         let verge_data = Config::verge().await.latest_arc();
-        verge_data.save_file().await.map_err(|e| e.to_string())?;
+        verge_data.save_file().await?;
 
-        self.update_config().await.stringify_err()?;
+        // 跨后端切换时无法热重载，直接以新内核重启
+        if CoreBackend::of(&verge_data) != previous_backend {
+            Config::generate().await?;
+            return self.restart_core().await;
+        }
+        self.update_config().await?;
         Ok(())
     }
 
@@ -67,8 +85,9 @@ impl CoreManager {
         self.wait_for_service_if_needed().await;
 
         let value = SERVICE_MANAGER.lock().await.current();
+        let backend = CoreBackend::of(&Config::verge().await.latest_arc());
         let mode = match value {
-            ServiceStatus::Ready => RunningMode::Service,
+            ServiceStatus::Ready if backend.supports_service() => RunningMode::Service,
             _ => RunningMode::Sidecar,
         };
 
@@ -1,6 +1,11 @@
 mod config;
+mod cores;
 mod lifecycle;
+mod priority;
+mod reload;
 mod state;
+mod updater;
+mod watchdog;
 
 use anyhow::Result;
 use arc_swap::{ArcSwap, ArcSwapOption};
@@ -11,6 +16,12 @@ use tauri_plugin_shell::process::CommandChild;
 
 use crate::singleton;
 
+pub use self::{
+    cores::{CoreInfo, core_binary_path, core_command},
+    updater::CoreUpdateInfo,
+    watchdog::{CoreCrashInfo, CoreWatchdog},
+};
+
 pub(crate) static CLASH_LOGGER: Lazy<Arc<AsyncLogger>> = Lazy::new(|| Arc::new(AsyncLogger::new()));
 
 #[derive(Debug, serde::Serialize, PartialEq, Eq)]
@@ -77,6 +88,16 @@ impl CoreManager {
             .and_then(|arc| Arc::try_unwrap(arc).ok())
     }
 
+    /// 子进程仍登记在案，说明退出并非由 `stop_core` 发起
+    pub fn is_sidecar_attached(&self) -> bool {
+        self.state.load().child_sidecar.load().is_some()
+    }
+
+    /// sidecar 模式下内核进程的 PID
+    pub fn sidecar_pid(&self) -> Option<u32> {
+        self.state.load().child_sidecar.load().as_ref().map(|child| child.pid())
+    }
+
     pub fn get_last_update(&self) -> Option<Arc<Instant>> {
         self.last_update.load_full()
     }
@@ -0,0 +1,258 @@
+//! 内核进程优先级与 CPU 亲和性
+//!
+//! sidecar 启动后按 `core_priority` 与 `core_cpu_affinity` 调整内核进程，修改设置时对运行中的内核立即生效。
+//! Windows 使用 `SetPriorityClass` / `SetProcessAffinityMask`；Linux 对进程的每个线程执行 `renice`，
+//! 并用 `taskset -a` 绑定 CPU；macOS 只支持调整优先级。外部命令在阻塞线程中执行。
+//! Linux 与 macOS 上降低 nice 值需要 root 或 CAP_SYS_NICE：提高优先级失败时把错误返回给设置界面，
+//! 从 `low` 恢复到 `normal` 等无法直接撤销的调整改为重启内核。服务模式下内核由服务进程启动，无法调整，
+//! 修改这两项设置时返回错误。
+
+use super::{CoreManager, RunningMode};
+use crate::{
+    config::{Config, IVerge},
+    process::AsyncHandler,
+};
+use anyhow::{Result, bail};
+use clash_verge_logging::{Type, logging};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CorePriority {
+    Low,
+    BelowNormal,
+    #[default]
+    Normal,
+    AboveNormal,
+    High,
+}
+
+impl CorePriority {
+    /// 未设置或无法识别时为 `normal`
+    pub fn parse(setting: Option<&str>) -> Self {
+        match setting {
+            Some("low") => Self::Low,
+            Some("below_normal") => Self::BelowNormal,
+            Some("above_normal") => Self::AboveNormal,
+            Some("high") => Self::High,
+            _ => Self::Normal,
+        }
+    }
+
+    /// 对应的 nice 值，提高优先级（负值）需要 root 权限
+    #[cfg(unix)]
+    const fn nice(self) -> i32 {
+        match self {
+            Self::Low => 10,
+            Self::BelowNormal => 5,
+            Self::Normal => 0,
+            Self::AboveNormal => -5,
+            Self::High => -10,
+        }
+    }
+}
+
+/// 去掉超出本机 CPU 数量与重复的核心编号，结果为空表示不限制
+fn valid_cores(cores: &[u32], available: usize) -> Vec<u32> {
+    let mut valid: Vec<u32> = cores
+        .iter()
+        .copied()
+        .filter(|&core| usize::try_from(core).is_ok_and(|core| core < available))
+        .collect();
+    valid.sort_unstable();
+    valid.dedup();
+    valid
+}
+
+#[cfg(any(target_os = "windows", test))]
+fn affinity_mask(cores: &[u32]) -> usize {
+    cores
+        .iter()
+        .filter_map(|&core| 1usize.checked_shl(core))
+        .fold(0, |mask, bit| mask | bit)
+}
+
+#[cfg(any(target_os = "linux", test))]
+fn cpu_list(cores: &[u32]) -> std::string::String {
+    cores.iter().map(u32::to_string).collect::<Vec<_>>().join(",")
+}
+
+#[cfg(unix)]
+fn run(program: &str, args: &[&str]) -> Result<()> {
+    let output = std::process::Command::new(program).args(args).output()?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "{program} failed: {}",
+            std::string::String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+/// 非特权进程只能提高 nice 值
+#[cfg(unix)]
+fn nice_error(err: anyhow::Error, priority: CorePriority) -> anyhow::Error {
+    if priority.nice() < 0 {
+        err.context("raising the core priority requires root or CAP_SYS_NICE")
+    } else {
+        err
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn apply_to_process(pid: u32, priority: CorePriority, cores: &[u32]) -> Result<()> {
+    // setpriority 只作用于单个线程，Go 运行时已创建的线程需要逐个调整
+    let nice = priority.nice().to_string();
+    let tasks: Vec<std::string::String> = std::fs::read_dir(format!("/proc/{pid}/task"))?
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .collect();
+    for tid in &tasks {
+        run("renice", &["-n", &nice, "-p", tid]).map_err(|err| nice_error(err, priority))?;
+    }
+    if !cores.is_empty() {
+        run("taskset", &["-a", "-p", "-c", &cpu_list(cores), &pid.to_string()])?;
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+fn apply_to_process(pid: u32, priority: CorePriority, cores: &[u32]) -> Result<()> {
+    run("renice", &["-n", &priority.nice().to_string(), "-p", &pid.to_string()])
+        .map_err(|err| nice_error(err, priority))?;
+    if !cores.is_empty() {
+        logging!(info, Type::Core, "CPU affinity is not supported on macOS, ignored");
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "windows")]
+fn apply_to_process(pid: u32, priority: CorePriority, cores: &[u32]) -> Result<()> {
+    use winapi::um::{
+        handleapi::CloseHandle,
+        processthreadsapi::{OpenProcess, SetPriorityClass},
+        winbase::{
+            ABOVE_NORMAL_PRIORITY_CLASS, BELOW_NORMAL_PRIORITY_CLASS, HIGH_PRIORITY_CLASS, IDLE_PRIORITY_CLASS,
+            NORMAL_PRIORITY_CLASS, SetProcessAffinityMask,
+        },
+        winnt::{PROCESS_QUERY_INFORMATION, PROCESS_SET_INFORMATION},
+    };
+
+    let class = match priority {
+        CorePriority::Low => IDLE_PRIORITY_CLASS,
+        CorePriority::BelowNormal => BELOW_NORMAL_PRIORITY_CLASS,
+        CorePriority::Normal => NORMAL_PRIORITY_CLASS,
+        CorePriority::AboveNormal => ABOVE_NORMAL_PRIORITY_CLASS,
+        CorePriority::High => HIGH_PRIORITY_CLASS,
+    };
+    // SAFETY: 只请求设置信息所需的权限，句柄在下方关闭
+    let process = unsafe { OpenProcess(PROCESS_SET_INFORMATION | PROCESS_QUERY_INFORMATION, 0, pid) };
+    if process.is_null() {
+        bail!("OpenProcess failed: {}", std::io::Error::last_os_error());
+    }
+    // SAFETY: process 为有效句柄
+    let result = unsafe {
+        let mut error = None;
+        if SetPriorityClass(process, class) == 0 {
+            error = Some(format!("SetPriorityClass failed: {}", std::io::Error::last_os_error()));
+        }
+        if error.is_none() && !cores.is_empty() && SetProcessAffinityMask(process, affinity_mask(cores)) == 0 {
+            error = Some(format!(
+                "SetProcessAffinityMask failed: {}",
+                std::io::Error::last_os_error()
+            ));
+        }
+        CloseHandle(process);
+        error
+    };
+    if let Some(error) = result {
+        bail!(error);
+    }
+    Ok(())
+}
+
+fn log_applied(priority: CorePriority, cores: &[u32]) {
+    logging!(
+        info,
+        Type::Core,
+        "Core process tuned: priority {:?}, cpus {:?}",
+        priority,
+        cores
+    );
+}
+
+/// 启动内核后按设置调整，默认优先级且未限制 CPU 时不做任何操作
+pub fn apply_process_tuning(pid: u32, verge: &IVerge) {
+    let priority = CorePriority::parse(verge.core_priority.as_deref());
+    let available = std::thread::available_parallelism().map_or(1, usize::from);
+    let cores = valid_cores(verge.core_cpu_affinity.as_deref().unwrap_or_default(), available);
+    if priority == CorePriority::Normal && cores.is_empty() {
+        return;
+    }
+    AsyncHandler::spawn_blocking(move || match apply_to_process(pid, priority, &cores) {
+        Ok(()) => log_applied(priority, &cores),
+        Err(err) => logging!(warn, Type::Core, "Failed to tune core process: {err:#}"),
+    });
+}
+
+impl CoreManager {
+    /// 设置变化后对运行中的 sidecar 内核重新应用，恢复默认时同样需要执行以撤销之前的调整
+    pub async fn apply_process_tuning(&self) -> Result<()> {
+        let verge = Config::verge().await.latest_arc();
+        let priority = CorePriority::parse(verge.core_priority.as_deref());
+        let available = std::thread::available_parallelism().map_or(1, usize::from);
+        let mut cores = valid_cores(verge.core_cpu_affinity.as_deref().unwrap_or_default(), available);
+        drop(verge);
+        if *self.get_running_mode() == RunningMode::Service {
+            if priority != CorePriority::Normal || !cores.is_empty() {
+                bail!("core priority and CPU affinity are not supported in service mode");
+            }
+            return Ok(());
+        }
+        let Some(pid) = self.sidecar_pid() else {
+            return Ok(());
+        };
+        if cores.is_empty() {
+            cores = (0..u32::try_from(available).unwrap_or(u32::MAX)).collect();
+        }
+
+        let applied = cores.clone();
+        let result = AsyncHandler::spawn_blocking(move || apply_to_process(pid, priority, &applied)).await?;
+        match result {
+            Ok(()) => {
+                log_applied(priority, &cores);
+                Ok(())
+            }
+            // 新启动的内核继承应用的默认优先级，重启后再按设置调整
+            #[cfg(unix)]
+            Err(err) if priority.nice() >= 0 => {
+                logging!(
+                    info,
+                    Type::Core,
+                    "Cannot lower the core nice value ({err:#}), restarting the core instead"
+                );
+                self.restart_core().await
+            }
+            Err(err) => Err(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_priority() {
+        assert_eq!(CorePriority::parse(Some("low")), CorePriority::Low);
+        assert_eq!(CorePriority::parse(Some("above_normal")), CorePriority::AboveNormal);
+        assert_eq!(CorePriority::parse(Some("realtime")), CorePriority::Normal);
+        assert_eq!(CorePriority::parse(None), CorePriority::Normal);
+    }
+
+    #[test]
+    fn test_cpu_selection() {
+        let cores = valid_cores(&[3, 0, 3, 8, 1], 4);
+        assert_eq!(cores, [0, 1, 3]);
+        assert_eq!(affinity_mask(&cores), 0b1011);
+        assert_eq!(cpu_list(&cores), "0,1,3");
+        assert!(valid_cores(&[4, 5], 4).is_empty());
+    }
+}
@@ -0,0 +1,145 @@
+//! 差量应用配置
+//!
+//! 比较内核正在使用的运行配置与新生成的配置，按变化范围选择代价最小的方式：
+//! 只改了 `PATCH /configs` 支持的基础选项时直接修补；规则、代理组、代理集合等变化走
+//! `PUT /configs` 热重载，已建立的连接不会断开；只有控制接口等结构性选项变化才重启内核。
+//! 代理组的选择不写入运行配置，切换节点不会触发任何重载。
+
+use serde_yaml_ng::{Mapping, Value};
+use std::collections::BTreeSet;
+
+/// `PATCH /configs` 可直接生效的选项
+const PATCHABLE_KEYS: [&str; 15] = [
+    "port",
+    "socks-port",
+    "redir-port",
+    "tproxy-port",
+    "mixed-port",
+    "allow-lan",
+    "bind-address",
+    "lan-allowed-ips",
+    "lan-disallowed-ips",
+    "skip-auth-prefixes",
+    "mode",
+    "log-level",
+    "ipv6",
+    "tcp-concurrent",
+    "interface-name",
+];
+
+/// 热重载无法可靠生效、需要重启内核的选项
+const STRUCTURAL_KEYS: [&str; 8] = [
+    "external-controller",
+    "external-controller-tls",
+    "external-controller-unix",
+    "external-controller-pipe",
+    "external-controller-cors",
+    "external-ui",
+    "secret",
+    "tls",
+];
+
+#[derive(Debug, PartialEq)]
+pub enum ReloadPlan {
+    /// 配置未变化
+    Unchanged,
+    /// 仅基础选项变化，内容为需要修补的字段
+    Patch(serde_json::Value),
+    /// 热重载，保留现有连接
+    Reload(Vec<String>),
+    /// 结构性变化，需要重启内核
+    Restart(Vec<String>),
+}
+
+fn changed_keys(previous: &Mapping, next: &Mapping) -> BTreeSet<String> {
+    previous
+        .keys()
+        .chain(next.keys())
+        .filter(|key| previous.get(*key) != next.get(*key))
+        .filter_map(|key| key.as_str().map(ToOwned::to_owned))
+        .collect()
+}
+
+/// 根据新旧运行配置确定应用方式，没有旧配置时重载
+pub fn plan(previous: Option<&Mapping>, next: &Mapping) -> ReloadPlan {
+    let Some(previous) = previous else {
+        return ReloadPlan::Reload(Vec::new());
+    };
+    let changed = changed_keys(previous, next);
+    if changed.is_empty() {
+        return ReloadPlan::Unchanged;
+    }
+
+    let structural: Vec<String> = changed
+        .iter()
+        .filter(|key| STRUCTURAL_KEYS.contains(&key.as_str()))
+        .cloned()
+        .collect();
+    if !structural.is_empty() {
+        return ReloadPlan::Restart(structural);
+    }
+
+    // 删除的选项无法通过 PATCH 恢复默认值
+    let patchable = changed
+        .iter()
+        .all(|key| PATCHABLE_KEYS.contains(&key.as_str()) && next.contains_key(key.as_str()));
+    if patchable {
+        let patch: serde_json::Map<_, _> = changed
+            .iter()
+            .filter_map(|key| {
+                let value = serde_json::to_value(next.get(key.as_str())?).ok()?;
+                Some((key.clone(), value))
+            })
+            .collect();
+        if patch.len() == changed.len() {
+            return ReloadPlan::Patch(serde_json::Value::Object(patch));
+        }
+    }
+    ReloadPlan::Reload(changed.into_iter().collect())
+}
+
+impl ReloadPlan {
+    pub fn describe(&self) -> String {
+        match self {
+            Self::Unchanged => "unchanged".to_owned(),
+            Self::Patch(patch) => format!("patch {patch}"),
+            Self::Reload(keys) => format!("reload ({})", keys.join(", ")),
+            Self::Restart(keys) => format!("restart ({})", keys.join(", ")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mapping(yaml: &str) -> Mapping {
+        serde_yaml_ng::from_str(yaml).unwrap_or_default()
+    }
+
+    #[test]
+    fn test_plan() {
+        let base = mapping("mode: rule\nmixed-port: 7897\nrules:\n  - MATCH,DIRECT\n");
+        assert_eq!(plan(Some(&base), &base), ReloadPlan::Unchanged);
+        assert_eq!(plan(None, &base), ReloadPlan::Reload(Vec::new()));
+
+        let next = mapping("mode: global\nmixed-port: 7897\nrules:\n  - MATCH,DIRECT\n");
+        assert_eq!(
+            plan(Some(&base), &next),
+            ReloadPlan::Patch(serde_json::json!({ "mode": "global" }))
+        );
+
+        let next = mapping("mode: rule\nmixed-port: 7897\nrules:\n  - MATCH,PROXY\n");
+        assert_eq!(plan(Some(&base), &next), ReloadPlan::Reload(vec!["rules".to_owned()]));
+
+        // 删除基础选项需要重载
+        let next = mapping("mode: rule\nrules:\n  - MATCH,DIRECT\n");
+        assert_eq!(
+            plan(Some(&base), &next),
+            ReloadPlan::Reload(vec!["mixed-port".to_owned()])
+        );
+
+        let next = mapping("mode: global\nmixed-port: 7897\nsecret: abc\nrules:\n  - MATCH,DIRECT\n");
+        assert_eq!(plan(Some(&base), &next), ReloadPlan::Restart(vec!["secret".to_owned()]));
+    }
+}
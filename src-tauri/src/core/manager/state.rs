@@ -1,18 +1,22 @@
-use super::{CoreManager, RunningMode};
+use super::{CoreManager, CoreWatchdog, RunningMode, core_command, priority};
 use crate::{
     AsyncHandler,
-    config::{Config, IClashTemp},
-    core::{handle, manager::CLASH_LOGGER, service},
+    config::{Config, ConfigType, IClashTemp},
+    core::{
+        backend::{ControllerShim, CoreBackend, singbox, singbox_controller_port},
+        kill_switch::KillSwitch,
+        manager::CLASH_LOGGER,
+        service,
+    },
     logging,
     utils::{dirs, init::sidecar_writer},
 };
 use anyhow::Result;
-use clash_verge_logging::{SharedWriter, Type, write_sidecar_log};
+use clash_verge_logging::{SharedWriter, Type, logging_error, write_sidecar_log};
 use compact_str::CompactString;
 use flexi_logger::DeferredNow;
 use log::Level;
 use scopeguard::defer;
-use tauri_plugin_shell::ShellExt as _;
 
 impl CoreManager {
     pub async fn get_clash_logs(&self) -> Result<Vec<CompactString>> {
@@ -26,33 +30,53 @@ impl CoreManager {
     pub(super) async fn start_core_by_sidecar(&self) -> Result<()> {
         logging!(info, Type::Core, "Starting core in sidecar mode");
 
-        let config_file = Config::generate_file(crate::config::ConfigType::Run).await?;
-        let app_handle = handle::Handle::app_handle();
-        let clash_core = Config::verge().await.latest_arc().get_valid_clash_core();
+        let verge = Config::verge().await.latest_arc();
+        let clash_core = verge.get_valid_clash_core();
+        let backend = CoreBackend::of(&verge);
         let config_dir = dirs::app_home_dir()?;
+        let command = core_command(&verge, &clash_core)?;
 
-        let (mut rx, child) = app_handle
-            .shell()
-            .sidecar(clash_core.as_str())?
-            .args([
-                "-d",
-                dirs::path_to_str(&config_dir)?,
-                "-f",
-                dirs::path_to_str(&config_file)?,
-                if cfg!(windows) {
-                    "-ext-ctl-pipe"
-                } else {
-                    "-ext-ctl-unix"
-                },
-                &IClashTemp::guard_external_controller_ipc(),
-            ])
-            .spawn()?;
+        let (mut rx, child) = match backend {
+            CoreBackend::Mihomo => {
+                let config_file = Config::generate_file(ConfigType::Run).await?;
+                command
+                    .args([
+                        "-d",
+                        dirs::path_to_str(&config_dir)?,
+                        "-f",
+                        dirs::path_to_str(&config_file)?,
+                        if cfg!(windows) {
+                            "-ext-ctl-pipe"
+                        } else {
+                            "-ext-ctl-unix"
+                        },
+                        &IClashTemp::guard_external_controller_ipc(),
+                    ])
+                    .spawn()?
+            }
+            CoreBackend::SingBox => {
+                let config_file = singbox::generate_file(ConfigType::Run).await?;
+                command
+                    .args([
+                        "run",
+                        "-D",
+                        dirs::path_to_str(&config_dir)?,
+                        "-c",
+                        dirs::path_to_str(&config_file)?,
+                    ])
+                    .spawn()?
+            }
+        };
 
         let pid = child.pid();
         logging!(trace, Type::Core, "Sidecar started with PID: {}", pid);
+        priority::apply_process_tuning(pid, &verge);
 
         self.set_running_child_sidecar(child);
         self.set_running_mode(RunningMode::Sidecar);
+        if backend == CoreBackend::SingBox {
+            ControllerShim::global().start(singbox_controller_port())?;
+        }
 
         let shared_writer: SharedWriter = std::sync::Arc::new(tokio::sync::Mutex::new(sidecar_writer().await?));
 
@@ -76,6 +100,12 @@ impl CoreManager {
                             CompactString::from("Process terminated")
                         };
                         write_sidecar_log(shared_writer.lock().await, &mut now, Level::Info, &message);
+                        if Self::global().is_sidecar_attached() {
+                            logging!(warn, Type::Core, "Sidecar exited unexpectedly: {}", message);
+                            let logs = CLASH_LOGGER.get_logs().await;
+                            logging_error!(Type::Core, KillSwitch::global().engage().await);
+                            CoreWatchdog::global().on_crash(message.as_str().into(), logs);
+                        }
                         CLASH_LOGGER.clear_logs().await;
                         break;
                     }
@@ -92,6 +122,7 @@ impl CoreManager {
         defer! {
             self.set_running_mode(RunningMode::NotRunning);
         }
+        ControllerShim::global().stop();
         if let Some(child) = self.take_child_sidecar() {
             let pid = child.pid();
             let result = child.kill();
@@ -107,7 +138,7 @@ impl CoreManager {
 
     pub(super) async fn start_core_by_service(&self) -> Result<()> {
         logging!(info, Type::Core, "Starting core in service mode");
-        let config_file = Config::generate_file(crate::config::ConfigType::Run).await?;
+        let config_file = Config::generate_file(ConfigType::Run).await?;
         service::run_core_by_service(&config_file).await?;
         self.set_running_mode(RunningMode::Service);
         Ok(())
@@ -0,0 +1,442 @@
+//! 内置 mihomo 内核的在线升级
+//!
+//! 从 MetaCubeX/mihomo 的 GitHub Releases 获取与当前平台匹配的构建，按上游发布流程上传的
+//! `checksums.txt` 校验，GitHub 为附件计算的摘要也必须一致。解压后安装到数据目录的 cores 下，
+//! 不改动安装目录，通过重命名原子替换内核文件，再重启内核；重启失败时回滚到旧版本。
+//! 升级进度通过 `core-upgrade-progress` 事件推送。
+//! 自定义内核没有下载来源，由用户提供新的二进制，沿用同样的暂存、替换与回滚流程。
+//!
+//! mihomo 的发布不附带签名，校验文件与附件同属一个发布，能发现下载损坏或被替换，
+//! 但无法察觉发布本身被篡改。
+
+use super::{
+    CoreManager, RunningMode, core_binary_path,
+    cores::{detect_core, upgraded_core_path},
+};
+use crate::{
+    config::{Config, IVerge},
+    core::{backend::CoreBackend, handle},
+    process::AsyncHandler,
+    utils::network::{NetworkManager, ProxyType},
+};
+use anyhow::{Context as _, Result, anyhow, bail};
+use clash_verge_logging::{Type, logging};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest as _, Sha256};
+use smartstring::alias::String;
+use std::{
+    io::Read as _,
+    path::{Path, PathBuf},
+};
+use tauri::Emitter as _;
+
+const RELEASE_API: &str = "https://api.github.com/repos/MetaCubeX/mihomo/releases";
+const ALPHA_TAG: &str = "Prerelease-Alpha";
+/// 上游随构建一起发布的 `sha256sum` 格式校验文件
+const CHECKSUM_ASSET: &str = "checksums.txt";
+const REQUEST_TIMEOUT_SECS: u64 = 30;
+const DOWNLOAD_TIMEOUT_SECS: u64 = 600;
+
+#[derive(Debug, Deserialize)]
+struct Release {
+    tag_name: String,
+    html_url: String,
+    assets: Vec<Asset>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Asset {
+    name: String,
+    browser_download_url: String,
+    size: u64,
+    /// 形如 `sha256:<hex>`
+    digest: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CoreUpdateInfo {
+    pub core: String,
+    pub current_version: Option<String>,
+    pub latest_version: String,
+    pub has_update: bool,
+    pub release_url: String,
+    pub asset_name: String,
+    pub asset_size: u64,
+}
+
+/// `core-upgrade-progress` 事件的负载
+#[derive(Debug, Clone, Serialize)]
+pub struct CoreUpgradeProgress<'a> {
+    pub stage: &'a str,
+    pub downloaded: u64,
+    pub total: u64,
+}
+
+fn emit_progress(stage: &str, downloaded: u64, total: u64) {
+    let _ = handle::Handle::app_handle().emit(
+        "core-upgrade-progress",
+        CoreUpgradeProgress {
+            stage,
+            downloaded,
+            total,
+        },
+    );
+}
+
+/// mihomo 发布文件中使用的系统与架构名称
+const fn platform() -> Option<(&'static str, &'static str)> {
+    let os = if cfg!(target_os = "windows") {
+        "windows"
+    } else if cfg!(target_os = "macos") {
+        "darwin"
+    } else if cfg!(target_os = "linux") {
+        "linux"
+    } else {
+        return None;
+    };
+    let arch = if cfg!(target_arch = "x86_64") {
+        "amd64"
+    } else if cfg!(target_arch = "aarch64") {
+        "arm64"
+    } else if cfg!(target_arch = "x86") {
+        "386"
+    } else if cfg!(target_arch = "arm") {
+        "armv7"
+    } else {
+        return None;
+    };
+    Some((os, arch))
+}
+
+/// 匹配 `mihomo-{os}-{arch}-{version}.{gz|zip}` 并返回版本号，
+/// 排除 `compatible`、`v1`/`v2`/`v3` 等变体
+fn asset_version<'a>(name: &'a str, os: &str, arch: &str) -> Option<&'a str> {
+    let rest = name.strip_prefix(&format!("mihomo-{os}-{arch}-"))?;
+    let version = rest.strip_suffix(".gz").or_else(|| rest.strip_suffix(".zip"))?;
+    let is_release = version
+        .strip_prefix('v')
+        .is_some_and(|v| v.starts_with(|c: char| c.is_ascii_digit()) && !v.contains('-'));
+    let is_alpha = version
+        .strip_prefix("alpha-")
+        .is_some_and(|hash| !hash.is_empty() && !hash.contains('-'));
+    (is_release || is_alpha).then_some(version)
+}
+
+/// 从校验文件中找出附件的 SHA-256，兼容 `<hash>  <name>` 与二进制模式的 `<hash> *<name>`
+fn checksum_for<'a>(checksums: &'a str, name: &str) -> Option<&'a str> {
+    checksums.lines().find_map(|line| {
+        let (hash, file) = line.trim().split_once(char::is_whitespace)?;
+        (file.trim_start().trim_start_matches('*') == name).then_some(hash)
+    })
+}
+
+fn verify_sha256(data: &[u8], expected: &str) -> Result<()> {
+    let actual: std::string::String = Sha256::digest(data).iter().map(|b| format!("{b:02x}")).collect();
+    if !actual.eq_ignore_ascii_case(expected) {
+        bail!("checksum mismatch: expected {expected}, got {actual}");
+    }
+    Ok(())
+}
+
+/// 校验 GitHub API 返回的 `sha256:<hex>` 摘要
+fn verify_digest(data: &[u8], digest: &str) -> Result<()> {
+    let expected = digest
+        .strip_prefix("sha256:")
+        .ok_or_else(|| anyhow!("unsupported digest: {digest}"))?;
+    verify_sha256(data, expected)
+}
+
+fn extract_binary(name: &str, data: &[u8]) -> Result<Vec<u8>> {
+    let mut binary = Vec::new();
+    if name.ends_with(".zip") {
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(data))?;
+        let index = (0..archive.len())
+            .find(|&i| {
+                archive
+                    .by_index(i)
+                    .is_ok_and(|f| f.is_file() && f.name().starts_with("mihomo"))
+            })
+            .ok_or_else(|| anyhow!("no mihomo binary in {name}"))?;
+        archive.by_index(index)?.read_to_end(&mut binary)?;
+    } else {
+        flate2::read::GzDecoder::new(data).read_to_end(&mut binary)?;
+    }
+    Ok(binary)
+}
+
+async fn fetch_release(core: &str) -> Result<Release> {
+    let url = if core == "verge-mihomo-alpha" {
+        format!("{RELEASE_API}/tags/{ALPHA_TAG}")
+    } else {
+        format!("{RELEASE_API}/latest")
+    };
+    let client = NetworkManager::new()
+        .create_request(proxy_type(), Some(REQUEST_TIMEOUT_SECS), None, false)
+        .await?;
+    let release = client
+        .get(url)
+        .header("Accept", "application/vnd.github+json")
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    Ok(release)
+}
+
+/// 内核运行时经由自身代理访问 GitHub
+fn proxy_type() -> ProxyType {
+    if *CoreManager::global().get_running_mode() == RunningMode::NotRunning {
+        ProxyType::None
+    } else {
+        ProxyType::Localhost
+    }
+}
+
+fn ensure_builtin(verge: &IVerge) -> Result<String> {
+    let core = verge.get_valid_clash_core();
+    if verge.get_custom_core(&core).is_some() {
+        bail!("custom core {core} is upgraded by replacing its binary");
+    }
+    Ok(core)
+}
+
+/// 用重命名替换文件，返回旧文件的备份路径；首次安装时没有备份
+fn swap_binary(target: &Path, staged: &Path) -> Result<Option<PathBuf>> {
+    if !target.exists() {
+        std::fs::rename(staged, target).context("failed to install the new core")?;
+        return Ok(None);
+    }
+    let backup = target.with_extension("old");
+    let _ = std::fs::remove_file(&backup);
+    std::fs::rename(target, &backup).context("failed to move the current core aside")?;
+    if let Err(err) = std::fs::rename(staged, target) {
+        let _ = std::fs::rename(&backup, target);
+        return Err(err).context("failed to install the new core");
+    }
+    Ok(Some(backup))
+}
+
+/// 删除其他应用版本留下的升级内核
+async fn prune_upgraded_cores(current_dir: &Path) {
+    let Some(root) = current_dir.parent() else {
+        return;
+    };
+    let Ok(mut entries) = tokio::fs::read_dir(root).await else {
+        return;
+    };
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let path = entry.path();
+        if path != current_dir && path.is_dir() {
+            let _ = tokio::fs::remove_dir_all(&path).await;
+        }
+    }
+}
+
+/// 把新内核写到目标旁边并确认可以运行，保证随后的 rename 不跨分区；返回暂存路径与识别出的版本、后端
+pub(super) async fn stage_binary(target: &Path, binary: Vec<u8>) -> Result<(PathBuf, String, CoreBackend)> {
+    let staged = target.with_extension("new");
+    tokio::fs::write(&staged, binary).await?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt as _;
+        tokio::fs::set_permissions(&staged, std::fs::Permissions::from_mode(0o755)).await?;
+    }
+
+    let probe = staged.clone();
+    let Some((version, backend)) = AsyncHandler::spawn_blocking(move || detect_core(&probe)).await? else {
+        let _ = tokio::fs::remove_file(&staged).await;
+        bail!("new core failed to run");
+    };
+    Ok((staged, version, backend))
+}
+
+impl CoreManager {
+    /// 用暂存的新内核替换 `target`。`restart` 时先停止内核，替换后重新启动，启动失败则回滚到旧内核
+    pub(super) async fn install_binary(&self, target: &Path, staged: &Path, restart: bool) -> Result<()> {
+        if restart {
+            self.stop_core().await?;
+        }
+        let backup = match swap_binary(target, staged) {
+            Ok(backup) => backup,
+            Err(err) => {
+                let _ = tokio::fs::remove_file(staged).await;
+                if restart {
+                    self.start_core().await?;
+                }
+                return Err(err);
+            }
+        };
+
+        if restart && let Err(err) = self.start_core().await {
+            logging!(error, Type::Core, "New core failed to start, rolling back: {err:#}");
+            let _ = self.stop_core().await;
+            match &backup {
+                Some(backup) => std::fs::rename(backup, target)?,
+                None => std::fs::remove_file(target)?,
+            }
+            self.start_core().await?;
+            return Err(err.context("new core failed to start, rolled back"));
+        }
+        if let Some(backup) = backup {
+            let _ = tokio::fs::remove_file(&backup).await;
+        }
+        Ok(())
+    }
+
+    /// 查询当前内核是否有新版本
+    pub async fn check_core_update(&self) -> Result<CoreUpdateInfo> {
+        let verge = Config::verge().await.latest_arc();
+        let core = ensure_builtin(&verge)?;
+        let (os, arch) = platform().ok_or_else(|| anyhow!("unsupported platform"))?;
+
+        let release = fetch_release(&core).await?;
+        let (asset, latest_version) = release
+            .assets
+            .iter()
+            .find_map(|asset| asset_version(&asset.name, os, arch).map(|v| (asset, v)))
+            .ok_or_else(|| anyhow!("no release asset for {os}-{arch} in {}", release.tag_name))?;
+
+        let path = core_binary_path(&verge, &core)?;
+        let current_version = AsyncHandler::spawn_blocking(move || detect_core(&path))
+            .await?
+            .map(|(version, _)| version);
+
+        Ok(CoreUpdateInfo {
+            has_update: current_version.as_deref() != Some(latest_version),
+            latest_version: latest_version.into(),
+            current_version,
+            release_url: release.html_url.clone(),
+            asset_name: asset.name.clone(),
+            asset_size: asset.size,
+            core,
+        })
+    }
+
+    /// 下载、校验并替换当前内核，完成后重启，返回新版本号
+    pub async fn upgrade_core(&self) -> Result<String> {
+        emit_progress("checking", 0, 0);
+        let info = self.check_core_update().await?;
+        if !info.has_update {
+            emit_progress("done", 0, 0);
+            return Ok(info.latest_version);
+        }
+
+        let release = fetch_release(&info.core).await?;
+        let checksum_url = release
+            .assets
+            .iter()
+            .find(|asset| asset.name == CHECKSUM_ASSET)
+            .map(|asset| asset.browser_download_url.clone())
+            .ok_or_else(|| anyhow!("release {} publishes no {CHECKSUM_ASSET}", release.tag_name))?;
+        let asset = release
+            .assets
+            .into_iter()
+            .find(|asset| asset.name == info.asset_name)
+            .ok_or_else(|| anyhow!("release asset disappeared: {}", info.asset_name))?;
+
+        logging!(
+            info,
+            Type::Core,
+            "Downloading {} ({} -> {})",
+            asset.name,
+            info.current_version.as_deref().unwrap_or("unknown"),
+            info.latest_version
+        );
+        let client = NetworkManager::new()
+            .create_request(proxy_type(), Some(DOWNLOAD_TIMEOUT_SECS), None, false)
+            .await?;
+        let checksums = client
+            .get(checksum_url.as_str())
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+        let expected: String = checksum_for(&checksums, &asset.name)
+            .ok_or_else(|| anyhow!("{CHECKSUM_ASSET} has no entry for {}", asset.name))?
+            .into();
+
+        let mut response = client
+            .get(asset.browser_download_url.as_str())
+            .send()
+            .await?
+            .error_for_status()?;
+        let total = response.content_length().unwrap_or(asset.size);
+        let mut data = Vec::with_capacity(usize::try_from(total).unwrap_or_default());
+        while let Some(chunk) = response.chunk().await? {
+            data.extend_from_slice(&chunk);
+            emit_progress("downloading", data.len() as u64, total);
+        }
+
+        emit_progress("verifying", total, total);
+        let name = asset.name.clone();
+        let digest = asset.digest;
+        let binary = AsyncHandler::spawn_blocking(move || {
+            verify_sha256(&data, &expected)?;
+            if let Some(digest) = digest {
+                verify_digest(&data, &digest)?;
+            }
+            extract_binary(&name, &data)
+        })
+        .await??;
+
+        let target = upgraded_core_path(&info.core)?;
+        let target_dir = target
+            .parent()
+            .ok_or_else(|| anyhow!("invalid core path: {}", target.display()))?
+            .to_path_buf();
+        tokio::fs::create_dir_all(&target_dir).await?;
+        let (staged, ..) = stage_binary(&target, binary).await?;
+
+        emit_progress("installing", total, total);
+        self.install_binary(&target, &staged, true).await?;
+        prune_upgraded_cores(&target_dir).await;
+
+        logging!(info, Type::Core, "Core upgraded to {}", info.latest_version);
+        emit_progress("done", total, total);
+        Ok(info.latest_version)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_asset_version() {
+        let pick = |name| asset_version(name, "linux", "amd64");
+        assert_eq!(pick("mihomo-linux-amd64-v1.19.2.gz"), Some("v1.19.2"));
+        assert_eq!(pick("mihomo-linux-amd64-alpha-7b4b1d2.gz"), Some("alpha-7b4b1d2"));
+        assert_eq!(pick("mihomo-linux-amd64-v1-v1.19.2.gz"), None);
+        assert_eq!(pick("mihomo-linux-amd64-compatible-v1.19.2.gz"), None);
+        assert_eq!(pick("mihomo-linux-amd64-v1.19.2.deb"), None);
+        assert_eq!(
+            asset_version("mihomo-windows-arm64-v1.19.2.zip", "windows", "arm64"),
+            Some("v1.19.2")
+        );
+    }
+
+    #[test]
+    fn test_checksum_for() {
+        let checksums = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824  mihomo-linux-amd64-v1.19.2.gz\n\
+                         486ea46224d1bb4fb680f34f7c9ad96a8f24ec88be73ea8e5a6c65260e9cb8a7 *mihomo-windows-amd64-v1.19.2.zip\n";
+        assert_eq!(
+            checksum_for(checksums, "mihomo-linux-amd64-v1.19.2.gz"),
+            Some("2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824")
+        );
+        assert_eq!(
+            checksum_for(checksums, "mihomo-windows-amd64-v1.19.2.zip"),
+            Some("486ea46224d1bb4fb680f34f7c9ad96a8f24ec88be73ea8e5a6c65260e9cb8a7")
+        );
+        assert_eq!(checksum_for(checksums, "mihomo-linux-amd64-v1.19.2"), None);
+    }
+
+    #[test]
+    fn test_verify_digest() {
+        let digest = "sha256:2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+        assert!(verify_digest(b"hello", digest).is_ok());
+        assert!(verify_digest(b"hello!", digest).is_err());
+        assert!(verify_digest(b"hello", "md5:abc").is_err());
+    }
+}
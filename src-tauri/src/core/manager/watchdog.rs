@@ -0,0 +1,249 @@
+//! 内核看门狗
+//!
+//! 检测内核进程意外退出或控制接口持续无响应，按指数退避自动重启，
+//! 保留最近的崩溃日志，并通过 `core-crashed` / `core-recovered` 事件通知前端。
+
+use super::{CoreManager, RunningMode};
+use crate::{
+    config::Config,
+    core::{
+        handle,
+        notify::{NotificationEvent, notify_event},
+        tray,
+    },
+    process::AsyncHandler,
+    singleton,
+    utils::dirs,
+};
+use anyhow::Result;
+use chrono::Local;
+use clash_verge_logging::{Type, logging, logging_error};
+use compact_str::CompactString;
+use serde::Serialize;
+use smartstring::alias::String;
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+use tauri::Emitter as _;
+
+const PROBE_INTERVAL: Duration = Duration::from_secs(10);
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+/// 连续探测失败达到该次数视为无响应
+const MAX_PROBE_FAILURES: u32 = 3;
+const INITIAL_BACKOFF: Duration = Duration::from_secs(2);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+const MAX_RESTART_ATTEMPTS: u32 = 8;
+const RECOVERY_CHECK_ATTEMPTS: u32 = 10;
+const MAX_CRASH_LOGS: usize = 10;
+
+/// `core-crashed` / `core-recovered` 事件的负载
+#[derive(Debug, Clone, Serialize)]
+pub struct CoreCrashInfo {
+    pub time: i64,
+    pub reason: String,
+    pub log_file: Option<String>,
+    pub restart_attempts: u32,
+}
+
+#[derive(Default)]
+pub struct CoreWatchdog {
+    started: AtomicBool,
+    recovering: AtomicBool,
+}
+
+singleton!(CoreWatchdog, CORE_WATCHDOG);
+
+const fn backoff(attempt: u32) -> Duration {
+    let secs = INITIAL_BACKOFF.as_secs().saturating_mul(1 << attempt);
+    if secs > MAX_BACKOFF.as_secs() {
+        MAX_BACKOFF
+    } else {
+        Duration::from_secs(secs)
+    }
+}
+
+async fn is_core_responsive() -> bool {
+    let mihomo = handle::Handle::mihomo().await;
+    matches!(
+        tokio::time::timeout(PROBE_TIMEOUT, mihomo.get_version()).await,
+        Ok(Ok(_))
+    )
+}
+
+/// 写入崩溃日志并清理超出保留数量的旧文件
+async fn save_crash_log(reason: &str, logs: &[CompactString]) -> Result<String> {
+    let dir = dirs::core_crash_log_dir()?;
+    let path = dir.join(format!("core-crash-{}.log", Local::now().format("%Y%m%d-%H%M%S")));
+
+    let mut content = format!("reason: {reason}\n\n");
+    for line in logs {
+        content.push_str(line);
+        if !line.ends_with('\n') {
+            content.push('\n');
+        }
+    }
+    tokio::fs::write(&path, content).await?;
+
+    let mut files = Vec::new();
+    let mut entries = tokio::fs::read_dir(&dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        files.push(entry.path());
+    }
+    files.sort();
+    let excess = files.len().saturating_sub(MAX_CRASH_LOGS);
+    for old in files.into_iter().take(excess) {
+        let _ = tokio::fs::remove_file(old).await;
+    }
+
+    Ok(path.to_string_lossy().into())
+}
+
+impl CoreWatchdog {
+    fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_recovering(&self) -> bool {
+        self.recovering.load(Ordering::Acquire)
+    }
+
+    /// 启动控制接口探测，重复调用无副作用
+    pub fn init(&'static self) {
+        if self.started.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        AsyncHandler::spawn(move || async move {
+            let mut failures = 0;
+            loop {
+                tokio::time::sleep(PROBE_INTERVAL).await;
+                if handle::Handle::global().is_exiting() {
+                    break;
+                }
+                if self.is_recovering() || *CoreManager::global().get_running_mode() == RunningMode::NotRunning {
+                    failures = 0;
+                    continue;
+                }
+
+                if is_core_responsive().await {
+                    failures = 0;
+                    continue;
+                }
+                failures += 1;
+                logging!(
+                    warn,
+                    Type::Core,
+                    "Core health probe failed ({}/{})",
+                    failures,
+                    MAX_PROBE_FAILURES
+                );
+                if failures >= MAX_PROBE_FAILURES {
+                    failures = 0;
+                    let logs = CoreManager::global().get_clash_logs().await.unwrap_or_default();
+                    self.on_crash("core API is unresponsive".into(), logs);
+                }
+            }
+        });
+    }
+
+    /// 立即探测一次控制接口，无响应时按崩溃处理，返回内核是否正常
+    pub async fn probe_now(&'static self) -> bool {
+        if self.is_recovering() || *CoreManager::global().get_running_mode() == RunningMode::NotRunning {
+            return true;
+        }
+        if is_core_responsive().await {
+            return true;
+        }
+        let logs = CoreManager::global().get_clash_logs().await.unwrap_or_default();
+        self.on_crash("core API is unresponsive".into(), logs);
+        false
+    }
+
+    /// 内核异常退出或无响应时调用，`logs` 为崩溃前的内核输出
+    pub fn on_crash(&'static self, reason: String, logs: Vec<CompactString>) {
+        if handle::Handle::global().is_exiting() || self.recovering.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        logging!(error, Type::Core, "Core crashed: {}", reason);
+
+        AsyncHandler::spawn(move || async move {
+            let log_file = save_crash_log(&reason, &logs)
+                .await
+                .inspect_err(|e| logging!(warn, Type::Core, "Failed to save crash log: {e}"))
+                .ok();
+            let mut info = CoreCrashInfo {
+                time: Local::now().timestamp(),
+                reason,
+                log_file,
+                restart_attempts: 0,
+            };
+            refresh_tray_icon().await;
+            let _ = handle::Handle::app_handle().emit("core-crashed", &info);
+            notify_event(NotificationEvent::CoreCrashed { reason: &info.reason }).await;
+
+            let recovered = self.recover(&mut info).await;
+            self.recovering.store(false, Ordering::Release);
+            refresh_tray_icon().await;
+
+            if recovered {
+                logging!(
+                    info,
+                    Type::Core,
+                    "Core recovered after {} attempt(s)",
+                    info.restart_attempts
+                );
+                let _ = handle::Handle::app_handle().emit("core-recovered", &info);
+                notify_event(NotificationEvent::CoreRecovered).await;
+            } else {
+                logging!(
+                    error,
+                    Type::Core,
+                    "Core recovery gave up after {} attempts",
+                    info.restart_attempts
+                );
+                handle::Handle::notice_message("core_recovery_failed", info.reason.as_str());
+            }
+        });
+    }
+
+    async fn recover(&self, info: &mut CoreCrashInfo) -> bool {
+        for attempt in 0..MAX_RESTART_ATTEMPTS {
+            tokio::time::sleep(backoff(attempt)).await;
+            if handle::Handle::global().is_exiting() {
+                return false;
+            }
+            info.restart_attempts = attempt + 1;
+
+            logging_error!(Type::Core, CoreManager::global().restart_core().await);
+            for _ in 0..RECOVERY_CHECK_ATTEMPTS {
+                if is_core_responsive().await {
+                    return true;
+                }
+                tokio::time::sleep(Duration::from_millis(500)).await;
+            }
+        }
+        false
+    }
+}
+
+/// 托盘图标随内核状态切换
+async fn refresh_tray_icon() {
+    logging_error!(
+        Type::Tray,
+        tray::Tray::global()
+            .update_icon(&Config::verge().await.latest_arc())
+            .await
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_is_capped() {
+        assert_eq!(backoff(0), INITIAL_BACKOFF);
+        assert_eq!(backoff(1), INITIAL_BACKOFF * 2);
+        assert_eq!(backoff(MAX_RESTART_ATTEMPTS), MAX_BACKOFF);
+    }
+}
@@ -0,0 +1,226 @@
+//! mihomo 控制接口的 HTTP 客户端
+//!
+//! 插件未覆盖的接口（`/traffic`、`/logs` 等流式接口）统一经由这里访问：所有调用方共用一个连接池，
+//! 控制地址与密钥缓存到配置变化或连接、鉴权失败为止，错误按 [`MihomoHttpError`] 区分，
+//! 连接阶段的失败按 [`RetryPolicy`] 指数退避重试。
+
+use crate::{config::Config, singleton};
+use arc_swap::ArcSwapOption;
+use reqwest::{Client, Method, Response, StatusCode};
+use smartstring::alias::String;
+use std::{fmt, sync::Arc, time::Duration};
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MihomoHttpError {
+    /// 无法连接控制接口，内核未运行或地址已变化
+    Connect(String),
+    /// 超过请求超时时间
+    Timeout,
+    /// 密钥错误
+    Unauthorized,
+    /// 其他非成功状态码
+    Status(u16),
+    /// 响应无法解析
+    Decode(String),
+}
+
+impl fmt::Display for MihomoHttpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Connect(err) => write!(f, "failed to connect to mihomo: {err}"),
+            Self::Timeout => write!(f, "mihomo request timed out"),
+            Self::Unauthorized => write!(f, "mihomo rejected the controller secret"),
+            Self::Status(status) => write!(f, "mihomo responded with status {status}"),
+            Self::Decode(err) => write!(f, "failed to decode mihomo response: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for MihomoHttpError {}
+
+impl From<reqwest::Error> for MihomoHttpError {
+    fn from(err: reqwest::Error) -> Self {
+        if err.is_timeout() {
+            Self::Timeout
+        } else if err.is_decode() || err.is_body() {
+            Self::Decode(err.to_string().into())
+        } else if let Some(status) = err.status() {
+            Self::from_status(status)
+        } else {
+            Self::Connect(err.to_string().into())
+        }
+    }
+}
+
+impl MihomoHttpError {
+    fn from_status(status: StatusCode) -> Self {
+        if status == StatusCode::UNAUTHORIZED {
+            Self::Unauthorized
+        } else {
+            Self::Status(status.as_u16())
+        }
+    }
+
+    /// 重试可能成功的错误，鉴权失败时会重新读取密钥
+    pub const fn is_transient(&self) -> bool {
+        match self {
+            Self::Connect(_) | Self::Timeout | Self::Unauthorized => true,
+            Self::Status(status) => *status >= 500,
+            Self::Decode(_) => false,
+        }
+    }
+}
+
+/// 失败重试策略，`attempts` 包含首次请求
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    pub attempts: u32,
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            attempts: 3,
+            initial_delay: Duration::from_millis(200),
+            max_delay: Duration::from_secs(2),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// 第 `attempt` 次失败（从 0 开始）后的等待时间
+    fn delay(&self, attempt: u32) -> Duration {
+        self.initial_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RequestOptions {
+    /// 整个请求（含读取响应体）的超时，流式接口为空
+    pub timeout: Option<Duration>,
+    pub retry: RetryPolicy,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Endpoint {
+    server: String,
+    secret: Option<String>,
+}
+
+impl Endpoint {
+    fn url(&self, path: &str) -> std::string::String {
+        format!("http://{}/{}", self.server, path.trim_start_matches('/'))
+    }
+}
+
+pub struct MihomoHttp {
+    client: Client,
+    endpoint: ArcSwapOption<Endpoint>,
+}
+
+singleton!(MihomoHttp, MIHOMO_HTTP);
+
+impl MihomoHttp {
+    fn new() -> Self {
+        Self {
+            // 控制接口在本机，不经过系统代理
+            client: Client::builder()
+                .connect_timeout(CONNECT_TIMEOUT)
+                .no_proxy()
+                .build()
+                .unwrap_or_default(),
+            endpoint: ArcSwapOption::empty(),
+        }
+    }
+
+    async fn endpoint(&self) -> Arc<Endpoint> {
+        if let Some(endpoint) = self.endpoint.load_full() {
+            return endpoint;
+        }
+        let info = Config::clash().await.data_arc().get_client_info();
+        let endpoint = Arc::new(Endpoint {
+            server: info.server,
+            secret: info.secret.filter(|secret| !secret.is_empty()),
+        });
+        self.endpoint.store(Some(Arc::clone(&endpoint)));
+        endpoint
+    }
+
+    /// 控制地址或密钥可能已变化，下次请求时重新读取
+    pub fn invalidate(&self) {
+        self.endpoint.store(None);
+    }
+
+    async fn send(&self, method: Method, path: &str, timeout: Option<Duration>) -> Result<Response, MihomoHttpError> {
+        let endpoint = self.endpoint().await;
+        let mut request = self.client.request(method, endpoint.url(path));
+        if let Some(timeout) = timeout {
+            request = request.timeout(timeout);
+        }
+        if let Some(secret) = &endpoint.secret {
+            request = request.bearer_auth(secret);
+        }
+        let result = match request.send().await {
+            Ok(response) if response.status().is_success() => Ok(response),
+            Ok(response) => Err(MihomoHttpError::from_status(response.status())),
+            Err(err) => Err(MihomoHttpError::from(err)),
+        };
+        if matches!(result, Err(MihomoHttpError::Connect(_) | MihomoHttpError::Unauthorized)) {
+            self.invalidate();
+        }
+        result
+    }
+
+    /// 发送请求，失败时按策略重试
+    pub async fn request(
+        &self,
+        method: Method,
+        path: &str,
+        options: RequestOptions,
+    ) -> Result<Response, MihomoHttpError> {
+        let mut attempt = 0;
+        loop {
+            match self.send(method.clone(), path, options.timeout).await {
+                Err(err) if err.is_transient() && attempt + 1 < options.retry.attempts => {
+                    tokio::time::sleep(options.retry.delay(attempt)).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// 打开流式接口，不设整体超时，断线重连由调用方处理
+    pub async fn stream(&self, path: &str) -> Result<Response, MihomoHttpError> {
+        self.request(Method::GET, path, RequestOptions::default()).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_policy() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.delay(0), Duration::from_millis(200));
+        assert_eq!(policy.delay(1), Duration::from_millis(400));
+        assert_eq!(policy.delay(10), policy.max_delay);
+
+        assert!(MihomoHttpError::Unauthorized.is_transient());
+        assert!(MihomoHttpError::Status(502).is_transient());
+        assert!(!MihomoHttpError::Status(404).is_transient());
+
+        let endpoint = Endpoint {
+            server: "127.0.0.1:9097".into(),
+            secret: None,
+        };
+        assert_eq!(endpoint.url("/traffic"), "http://127.0.0.1:9097/traffic");
+    }
+}
@@ -1,15 +1,44 @@
+pub mod app_updater;
+pub mod audit;
+pub mod backend;
 pub mod backup;
+pub mod bypass;
+pub mod control_api;
+pub mod converter;
+pub mod core_log;
 pub mod discord_rpc;
+pub mod event_bus;
+pub mod geodata;
 pub mod handle;
 pub mod hotkey;
+pub mod kill_switch;
+pub mod lan_sync;
+pub mod linux_proxy;
 pub mod logger;
+pub mod macos_proxy;
 pub mod manager;
+pub mod mihomo_http;
+pub mod network_monitor;
 mod notification;
+pub mod notify;
+pub mod plugin;
+pub mod proxy_scope;
+pub mod resume;
+pub mod scheduler;
+pub mod secrets;
+pub mod selection;
 pub mod service;
+pub mod sharelink;
+pub mod stats;
 pub mod sysopt;
+pub mod sysproxy_guard;
+pub mod telegram;
 pub mod timer;
+pub mod traffic;
 pub mod tray;
 pub mod validate;
+pub mod webhook;
+pub mod win_proxy_sync;
 pub mod win_uwp;
 
 pub use self::{manager::CoreManager, timer::Timer};
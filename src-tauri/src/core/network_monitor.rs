@@ -0,0 +1,393 @@
+//! 网络环境监测
+//!
+//! 定期检测当前所用网卡、Wi-Fi 名称（SSID）、是否连接了其他 VPN，网络变化时
+//! 检测是否存在认证页面（captive portal），发送 `network-changed` 事件，
+//! 并按 verge 配置中 `network_rules` 的顺序执行第一条匹配规则的动作，
+//! 例如连接办公室 Wi-Fi 时关闭系统代理、连接公共 Wi-Fi 时开启 TUN。
+
+use super::scheduler::run_action;
+use crate::{
+    config::{Config, INetworkRule},
+    core::{audit::AuditSource, handle, selection::SelectionMemory},
+    process::AsyncHandler,
+    singleton,
+    utils::network::{NetworkManager, ProxyType},
+};
+use clash_verge_logging::{Type, logging, logging_error};
+use network_interface::{Addr, NetworkInterface, NetworkInterfaceConfig as _};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use smartstring::alias::String;
+use std::{
+    net::{IpAddr, Ipv4Addr, UdpSocket},
+    process::Command,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+use tauri::Emitter as _;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(15);
+const NETWORK_CHANGED_EVENT: &str = "network-changed";
+const CAPTIVE_CHECK_URL: &str = "http://connectivitycheck.gstatic.com/generate_204";
+const CAPTIVE_CHECK_TIMEOUT_SECS: u64 = 5;
+/// macOS 14.4 起未授权定位服务的应用读取到的 SSID
+const REDACTED_SSID: &str = "<redacted>";
+/// 其他 VPN 常用的网卡名前缀
+const VPN_PREFIXES: [&str; 8] = ["tun", "utun", "tap", "wg", "ppp", "ipsec", "zt", "tailscale"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NetworkKind {
+    Wifi,
+    Wired,
+    Offline,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct NetworkState {
+    pub kind: NetworkKind,
+    /// 出站网卡
+    pub interface: Option<String>,
+    pub address: Option<IpAddr>,
+    pub ssid: Option<String>,
+    /// 已连接 Wi-Fi 但系统隐藏了 SSID，按 SSID 匹配的规则无法生效
+    pub ssid_redacted: bool,
+    /// 是否连接了其他 VPN
+    pub vpn: bool,
+    pub captive_portal: bool,
+}
+
+impl NetworkState {
+    /// 用于判断网络是否变化，不含认证页面检测结果
+    fn same_network(&self, other: &Self) -> bool {
+        self.kind == other.kind && self.interface == other.interface && self.ssid == other.ssid && self.vpn == other.vpn
+    }
+}
+
+/// 内核 TUN 使用的 198.18.0.0/15 网段
+const fn is_core_tun(ip: Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+    a == 198 && (b & 0xfe) == 18
+}
+
+fn is_vpn_interface(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    VPN_PREFIXES.iter().any(|prefix| name.starts_with(prefix))
+}
+
+/// 出站地址，不实际发送数据
+fn outbound_address() -> Option<Ipv4Addr> {
+    let socket = UdpSocket::bind("0.0.0.0:0").ok()?;
+    socket.connect("223.5.5.5:53").ok()?;
+    match socket.local_addr().ok()?.ip() {
+        IpAddr::V4(ip) if !ip.is_unspecified() => Some(ip),
+        _ => None,
+    }
+}
+
+/// 选出物理出站网卡；开启 TUN 时出站地址属于内核网卡，改为选第一个普通网卡
+fn primary_interface(interfaces: &[NetworkInterface]) -> Option<(String, Ipv4Addr)> {
+    let candidates = interfaces.iter().flat_map(|iface| {
+        iface.addr.iter().filter_map(move |addr| match addr {
+            Addr::V4(v4) if !v4.ip.is_loopback() && !v4.ip.is_link_local() && !is_core_tun(v4.ip) => {
+                Some((iface.name.as_str(), v4.ip))
+            }
+            _ => None,
+        })
+    });
+    let outbound = outbound_address().filter(|ip| !is_core_tun(*ip));
+    let mut fallback = None;
+    for (name, ip) in candidates {
+        if Some(ip) == outbound {
+            return Some((name.into(), ip));
+        }
+        if fallback.is_none() && !is_vpn_interface(name) {
+            fallback = Some((name.into(), ip));
+        }
+    }
+    fallback
+}
+
+fn run(program: &str, args: &[&str]) -> Option<std::string::String> {
+    let mut command = Command::new(program);
+    command.args(args);
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::process::CommandExt as _;
+        command.creation_flags(0x08000000);
+    }
+    let output = command.output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| std::string::String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// 解析 `nmcli -t -f active,ssid dev wifi list` 的输出
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_nmcli_ssid(output: &str) -> Option<String> {
+    output
+        .lines()
+        .find_map(|line| line.strip_prefix("yes:"))
+        .map(|ssid| ssid.replace("\\:", ":"))
+        .filter(|ssid| !ssid.is_empty())
+        .map(Into::into)
+}
+
+/// 解析 `key : value` 形式的输出，用于 `netsh wlan show interfaces` 与 `ipconfig getsummary`
+#[cfg_attr(target_os = "linux", allow(dead_code))]
+fn parse_ssid_field(output: &str) -> Option<String> {
+    output.lines().find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        let value = value.trim();
+        (key.trim() == "SSID" && !value.is_empty()).then(|| value.into())
+    })
+}
+
+#[cfg(target_os = "linux")]
+fn current_ssid(_interface: &str) -> Option<String> {
+    // 不加 `--rescan no` 时每次查询都会触发 Wi-Fi 扫描
+    run(
+        "nmcli",
+        &["-t", "-f", "active,ssid", "dev", "wifi", "list", "--rescan", "no"],
+    )
+    .and_then(|output| parse_nmcli_ssid(&output))
+    .or_else(|| {
+        run("iwgetid", &["-r"])
+            .map(|ssid| ssid.trim().into())
+            .filter(|ssid: &String| !ssid.is_empty())
+    })
+}
+
+#[cfg(target_os = "macos")]
+fn current_ssid(interface: &str) -> Option<String> {
+    run("ipconfig", &["getsummary", interface]).and_then(|output| parse_ssid_field(&output))
+}
+
+#[cfg(target_os = "windows")]
+fn current_ssid(_interface: &str) -> Option<String> {
+    run("netsh", &["wlan", "show", "interfaces"]).and_then(|output| parse_ssid_field(&output))
+}
+
+/// 检测当前网络环境，不含认证页面检测
+fn detect_network() -> NetworkState {
+    let interfaces = NetworkInterface::show().unwrap_or_default();
+    let vpn = interfaces.iter().any(|iface| {
+        is_vpn_interface(&iface.name)
+            && iface
+                .addr
+                .iter()
+                .any(|addr| matches!(addr, Addr::V4(v4) if !is_core_tun(v4.ip) && !v4.ip.is_loopback()))
+    });
+    let Some((interface, address)) = primary_interface(&interfaces) else {
+        return NetworkState {
+            kind: NetworkKind::Offline,
+            interface: None,
+            address: None,
+            ssid: None,
+            ssid_redacted: false,
+            vpn,
+            captive_portal: false,
+        };
+    };
+    let ssid = current_ssid(&interface);
+    let ssid_redacted = ssid.as_deref() == Some(REDACTED_SSID);
+    NetworkState {
+        kind: if ssid.is_some() {
+            NetworkKind::Wifi
+        } else {
+            NetworkKind::Wired
+        },
+        interface: Some(interface),
+        address: Some(IpAddr::V4(address)),
+        ssid: ssid.filter(|_| !ssid_redacted),
+        ssid_redacted,
+        vpn,
+        captive_portal: false,
+    }
+}
+
+/// 直连访问检测地址，返回非 204 时认为存在认证页面
+async fn detect_captive_portal() -> bool {
+    NetworkManager::new()
+        .get_with_interrupt(
+            CAPTIVE_CHECK_URL,
+            ProxyType::None,
+            Some(CAPTIVE_CHECK_TIMEOUT_SECS),
+            None,
+            false,
+            None,
+        )
+        .await
+        .is_ok_and(|response| response.status().as_u16() != 204)
+}
+
+/// 规则中设置的条件均满足时匹配，没有任何条件的规则不匹配
+fn rule_matches(rule: &INetworkRule, state: &NetworkState) -> bool {
+    if !rule.enabled.unwrap_or(true) {
+        return false;
+    }
+    let conditions = [
+        rule.ssid.as_ref().map(|ssid| state.ssid.as_ref() == Some(ssid)),
+        rule.interface
+            .as_ref()
+            .map(|name| state.interface.as_ref() == Some(name)),
+        rule.kind.map(|kind| state.kind == kind),
+        rule.vpn.map(|vpn| state.vpn == vpn),
+        rule.captive_portal.map(|captive| state.captive_portal == captive),
+    ];
+    conditions.iter().any(Option::is_some) && conditions.iter().flatten().all(|&matched| matched)
+}
+
+pub struct NetworkMonitor {
+    current: Mutex<Option<NetworkState>>,
+    runner_started: AtomicBool,
+}
+
+singleton!(NetworkMonitor, NETWORK_MONITOR);
+
+impl NetworkMonitor {
+    fn new() -> Self {
+        Self {
+            current: Mutex::new(None),
+            runner_started: AtomicBool::new(false),
+        }
+    }
+
+    pub fn init(&self) {
+        if self.runner_started.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        AsyncHandler::spawn(|| async {
+            loop {
+                Self::global().check().await;
+                tokio::time::sleep(POLL_INTERVAL).await;
+            }
+        });
+    }
+
+    pub fn current(&self) -> Option<NetworkState> {
+        self.current.lock().clone()
+    }
+
+    /// 检测网络环境，变化时发送事件并执行匹配的规则
+    pub async fn check(&self) -> Option<NetworkState> {
+        let mut state = AsyncHandler::spawn_blocking(detect_network).await.ok()?;
+        let previous = self.current();
+        if previous.as_ref().is_some_and(|previous| previous.same_network(&state)) {
+            return previous;
+        }
+
+        if state.kind != NetworkKind::Offline {
+            state.captive_portal = detect_captive_portal().await;
+        }
+        *self.current.lock() = Some(state.clone());
+        logging!(
+            info,
+            Type::Network,
+            "Network changed: {:?} {} ssid={} vpn={} captive={}",
+            state.kind,
+            state.interface.as_deref().unwrap_or("-"),
+            state.ssid.as_deref().unwrap_or("-"),
+            state.vpn,
+            state.captive_portal
+        );
+        let _ = handle::Handle::app_handle().emit(NETWORK_CHANGED_EVENT, &state);
+
+        // 启动时的首次检测同样应用规则，保证与当前网络一致
+        Self::apply_rules(&state).await;
+        if previous.is_some()
+            && Config::verge()
+                .await
+                .latest_arc()
+                .selection_per_network
+                .unwrap_or(false)
+        {
+            logging_error!(Type::Network, SelectionMemory::global().restore().await);
+        }
+        Some(state)
+    }
+
+    async fn apply_rules(state: &NetworkState) {
+        let verge = Config::verge().await.latest_arc();
+        let mut rules = verge.network_rules.iter().flatten();
+        if state.ssid_redacted && rules.clone().any(|rule| rule.ssid.is_some()) {
+            logging!(
+                warn,
+                Type::Network,
+                "Wi-Fi name is hidden by the system, rules matching an SSID will not apply until Location Services access is granted"
+            );
+        }
+        let rule = rules.find(|rule| rule_matches(rule, state)).cloned();
+        drop(verge);
+        let Some(rule) = rule else {
+            return;
+        };
+        logging!(
+            info,
+            Type::Network,
+            "Applying network rule {}",
+            rule.name.as_deref().unwrap_or_default()
+        );
+        for action in &rule.actions {
+            if let Err(err) = AuditSource::System.scope(run_action(action)).await {
+                logging!(error, Type::Network, "Network rule action {:?} failed: {err}", action);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(kind: NetworkKind, ssid: Option<&str>) -> NetworkState {
+        NetworkState {
+            kind,
+            interface: Some("wlan0".into()),
+            address: None,
+            ssid: ssid.map(Into::into),
+            ssid_redacted: false,
+            vpn: false,
+            captive_portal: false,
+        }
+    }
+
+    #[test]
+    fn test_parse_ssid() {
+        assert_eq!(
+            parse_nmcli_ssid("no:Neighbor\nyes:Office\\:5G\n"),
+            Some("Office:5G".into())
+        );
+        assert_eq!(parse_nmcli_ssid("no:Neighbor\n"), None);
+        let netsh = "    Name                   : Wi-Fi\n    SSID                   : Cafe Free\n    BSSID                  : aa:bb:cc:dd:ee:ff\n";
+        assert_eq!(parse_ssid_field(netsh), Some("Cafe Free".into()));
+        assert_eq!(parse_ssid_field("  BSSID : aa:bb\n"), None);
+        assert_eq!(
+            parse_ssid_field("  SSID : <redacted>\n").as_deref(),
+            Some(REDACTED_SSID)
+        );
+    }
+
+    #[test]
+    fn test_rule_matches() {
+        let office = INetworkRule {
+            ssid: Some("Office".into()),
+            ..INetworkRule::default()
+        };
+        let any_wifi = INetworkRule {
+            kind: Some(NetworkKind::Wifi),
+            ..INetworkRule::default()
+        };
+        let office_state = state(NetworkKind::Wifi, Some("Office"));
+        let cafe_state = state(NetworkKind::Wifi, Some("Cafe"));
+        assert!(rule_matches(&office, &office_state));
+        assert!(!rule_matches(&office, &cafe_state));
+        assert!(rule_matches(&any_wifi, &cafe_state));
+        assert!(!rule_matches(&any_wifi, &state(NetworkKind::Wired, None)));
+        assert!(!rule_matches(&INetworkRule::default(), &office_state));
+        assert!(is_core_tun(Ipv4Addr::new(198, 19, 0, 1)));
+        assert!(!is_core_tun(Ipv4Addr::new(198, 20, 0, 1)));
+    }
+}
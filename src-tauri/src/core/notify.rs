@@ -0,0 +1,237 @@
+//! 系统通知
+//!
+//! 所有原生通知都经由 [`notify_event`] 发送。归属于 [`NotifyCategory`] 的事件可在
+//! `notification_prefs` 中单独关闭，快捷键操作反馈等未分类事件始终发送。
+
+use crate::{
+    config::{Config, INotificationPrefs},
+    core::{handle, telegram::TelegramBot},
+    utils::i18n,
+};
+use tauri_plugin_notification::NotificationExt as _;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotifyCategory {
+    CoreCrash,
+    ProfileUpdateFailed,
+    SubscriptionExpiring,
+    DataCap,
+    ExitIpChanged,
+}
+
+impl NotifyCategory {
+    pub fn is_enabled(self, prefs: Option<&INotificationPrefs>) -> bool {
+        let Some(prefs) = prefs else {
+            return true;
+        };
+        let flag = match self {
+            Self::CoreCrash => prefs.core_crash,
+            Self::ProfileUpdateFailed => prefs.profile_update_failed,
+            Self::SubscriptionExpiring => prefs.subscription_expiring,
+            Self::DataCap => prefs.data_cap,
+            Self::ExitIpChanged => prefs.exit_ip_change,
+        };
+        flag.unwrap_or(true)
+    }
+}
+
+pub enum NotificationEvent<'a> {
+    DashboardToggled,
+    ClashModeChanged {
+        mode: &'a str,
+    },
+    SystemProxyToggled,
+    TunModeToggled,
+    LightweightModeEntered,
+    ProfilesReactivated,
+    AppQuit,
+    #[cfg(target_os = "macos")]
+    AppHidden,
+    PluginMessage {
+        title: &'a str,
+        body: &'a str,
+    },
+    CoreCrashed {
+        reason: &'a str,
+    },
+    CoreRecovered,
+    SubscriptionQuotaLow {
+        name: &'a str,
+        percent: &'a str,
+    },
+    SubscriptionExpiring {
+        name: &'a str,
+        days: &'a str,
+    },
+    ProfileReverted {
+        name: &'a str,
+        reason: &'a str,
+    },
+    ProfileUpdateFailed {
+        name: &'a str,
+        reason: &'a str,
+    },
+    ExitIpChanged {
+        ip: &'a str,
+        country: &'a str,
+    },
+    Test,
+}
+
+impl NotificationEvent<'_> {
+    pub const fn category(&self) -> Option<NotifyCategory> {
+        match self {
+            Self::CoreCrashed { .. } | Self::CoreRecovered => Some(NotifyCategory::CoreCrash),
+            Self::ProfileReverted { .. } | Self::ProfileUpdateFailed { .. } => {
+                Some(NotifyCategory::ProfileUpdateFailed)
+            }
+            Self::SubscriptionExpiring { .. } => Some(NotifyCategory::SubscriptionExpiring),
+            Self::SubscriptionQuotaLow { .. } => Some(NotifyCategory::DataCap),
+            Self::ExitIpChanged { .. } => Some(NotifyCategory::ExitIpChanged),
+            _ => None,
+        }
+    }
+}
+
+fn notify(title: &str, body: &str) {
+    let app_handle = handle::Handle::app_handle();
+    app_handle.notification().builder().title(title).body(body).show().ok();
+}
+
+/// 归类的事件同时转发到 Telegram
+fn notify_and_forward(title: &str, body: &str) {
+    notify(title, body);
+    TelegramBot::global().push(format!("{title}\n{body}"));
+}
+
+pub async fn notify_event<'a>(event: NotificationEvent<'a>) {
+    if let Some(category) = event.category() {
+        let prefs = Config::verge().await.latest_arc().notification_prefs.clone();
+        if !category.is_enabled(prefs.as_ref()) {
+            return;
+        }
+    }
+    i18n::sync_locale().await;
+
+    match event {
+        NotificationEvent::DashboardToggled => {
+            let title = rust_i18n::t!("notifications.dashboardToggled.title").to_string();
+            let body = rust_i18n::t!("notifications.dashboardToggled.body").to_string();
+            notify(&title, &body);
+        }
+        NotificationEvent::ClashModeChanged { mode } => {
+            let title = rust_i18n::t!("notifications.clashModeChanged.title").to_string();
+            let body = rust_i18n::t!("notifications.clashModeChanged.body").replace("{mode}", mode);
+            notify(&title, &body);
+        }
+        NotificationEvent::SystemProxyToggled => {
+            let title = rust_i18n::t!("notifications.systemProxyToggled.title").to_string();
+            let body = rust_i18n::t!("notifications.systemProxyToggled.body").to_string();
+            notify(&title, &body);
+        }
+        NotificationEvent::TunModeToggled => {
+            let title = rust_i18n::t!("notifications.tunModeToggled.title").to_string();
+            let body = rust_i18n::t!("notifications.tunModeToggled.body").to_string();
+            notify(&title, &body);
+        }
+        NotificationEvent::LightweightModeEntered => {
+            let title = rust_i18n::t!("notifications.lightweightModeEntered.title").to_string();
+            let body = rust_i18n::t!("notifications.lightweightModeEntered.body").to_string();
+            notify(&title, &body);
+        }
+        NotificationEvent::ProfilesReactivated => {
+            let title = rust_i18n::t!("notifications.profilesReactivated.title").to_string();
+            let body = rust_i18n::t!("notifications.profilesReactivated.body").to_string();
+            notify(&title, &body);
+        }
+        NotificationEvent::AppQuit => {
+            let title = rust_i18n::t!("notifications.appQuit.title").to_string();
+            let body = rust_i18n::t!("notifications.appQuit.body").to_string();
+            notify(&title, &body);
+        }
+        #[cfg(target_os = "macos")]
+        NotificationEvent::AppHidden => {
+            let title = rust_i18n::t!("notifications.appHidden.title").to_string();
+            let body = rust_i18n::t!("notifications.appHidden.body").to_string();
+            notify(&title, &body);
+        }
+        NotificationEvent::PluginMessage { title, body } => {
+            notify(title, body);
+        }
+        NotificationEvent::CoreCrashed { reason } => {
+            let title = rust_i18n::t!("notifications.coreCrashed.title").to_string();
+            let body = rust_i18n::t!("notifications.coreCrashed.body").replace("{reason}", reason);
+            notify_and_forward(&title, &body);
+        }
+        NotificationEvent::CoreRecovered => {
+            let title = rust_i18n::t!("notifications.coreRecovered.title").to_string();
+            let body = rust_i18n::t!("notifications.coreRecovered.body").to_string();
+            notify_and_forward(&title, &body);
+        }
+        NotificationEvent::SubscriptionQuotaLow { name, percent } => {
+            let title = rust_i18n::t!("notifications.subscriptionQuotaLow.title").to_string();
+            let body = rust_i18n::t!("notifications.subscriptionQuotaLow.body")
+                .replace("{name}", name)
+                .replace("{percent}", percent);
+            notify_and_forward(&title, &body);
+        }
+        NotificationEvent::SubscriptionExpiring { name, days } => {
+            let title = rust_i18n::t!("notifications.subscriptionExpiring.title").to_string();
+            let body = rust_i18n::t!("notifications.subscriptionExpiring.body")
+                .replace("{name}", name)
+                .replace("{days}", days);
+            notify_and_forward(&title, &body);
+        }
+        NotificationEvent::ProfileReverted { name, reason } => {
+            let title = rust_i18n::t!("notifications.profileReverted.title").to_string();
+            let body = rust_i18n::t!("notifications.profileReverted.body")
+                .replace("{name}", name)
+                .replace("{reason}", reason);
+            notify_and_forward(&title, &body);
+        }
+        NotificationEvent::ProfileUpdateFailed { name, reason } => {
+            let title = rust_i18n::t!("notifications.profileUpdateFailed.title").to_string();
+            let body = rust_i18n::t!("notifications.profileUpdateFailed.body")
+                .replace("{name}", name)
+                .replace("{reason}", reason);
+            notify_and_forward(&title, &body);
+        }
+        NotificationEvent::ExitIpChanged { ip, country } => {
+            let title = rust_i18n::t!("notifications.exitIpChanged.title").to_string();
+            let body = rust_i18n::t!("notifications.exitIpChanged.body")
+                .replace("{ip}", ip)
+                .replace("{country}", country);
+            notify_and_forward(&title, &body);
+        }
+        NotificationEvent::Test => {
+            let title = rust_i18n::t!("notifications.test.title").to_string();
+            let body = rust_i18n::t!("notifications.test.body").to_string();
+            notify(&title, &body);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_category_enabled() {
+        assert!(NotifyCategory::DataCap.is_enabled(None));
+        let prefs = INotificationPrefs {
+            data_cap: Some(false),
+            ..INotificationPrefs::default()
+        };
+        assert!(!NotifyCategory::DataCap.is_enabled(Some(&prefs)));
+        assert!(NotifyCategory::CoreCrash.is_enabled(Some(&prefs)));
+        assert_eq!(
+            NotificationEvent::SubscriptionQuotaLow {
+                name: "a",
+                percent: "90"
+            }
+            .category(),
+            Some(NotifyCategory::DataCap)
+        );
+        assert_eq!(NotificationEvent::Test.category(), None);
+    }
+}
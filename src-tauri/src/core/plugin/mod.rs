@@ -0,0 +1,522 @@
+mod runtime;
+mod watchdog;
+
+use crate::{
+    core::{
+        event_bus::{EventBus, StateEvent},
+        handle,
+        notify::{NotificationEvent, notify_event},
+    },
+    process::AsyncHandler,
+    sandbox::LimitExceeded,
+    utils::{dirs::app_plugins_dir, help},
+};
+use anyhow::{Context as _, Result, bail};
+use clash_verge_logging::{Type, logging};
+use once_cell::sync::OnceCell;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use smartstring::alias::String;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, Instant},
+};
+use tauri::Emitter as _;
+use tokio::sync::broadcast;
+
+pub use runtime::{HostCall, serve_event};
+use watchdog::RunOutcome;
+pub use watchdog::{PluginHealth, PluginHealthStatus};
+
+const MANIFEST_FILE: &str = "manifest.yaml";
+const STORAGE_FILE: &str = "storage.json";
+const REGISTRY_FILE: &str = "plugins.yaml";
+const EVENT_CHANNEL_CAPACITY: usize = 64;
+const TRAFFIC_POLL_INTERVAL: Duration = Duration::from_secs(5);
+const MAX_SCRIPT_SIZE: u64 = 512 * 1024;
+
+/// 插件可申请的宿主能力
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PluginPermission {
+    Notify,
+    TemplateVars,
+    Storage,
+}
+
+/// 插件可订阅的事件类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PluginEventKind {
+    Traffic,
+    NodeChanged,
+    ProfileUpdated,
+}
+
+/// 分发给插件的事件
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PluginEvent {
+    Traffic { upload_total: u64, download_total: u64 },
+    NodeChanged { group: String, node: String },
+    ProfileUpdated { uid: String },
+}
+
+impl PluginEvent {
+    pub const fn kind(&self) -> PluginEventKind {
+        match self {
+            Self::Traffic { .. } => PluginEventKind::Traffic,
+            Self::NodeChanged { .. } => PluginEventKind::NodeChanged,
+            Self::ProfileUpdated { .. } => PluginEventKind::ProfileUpdated,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PluginRuntime {
+    Js,
+    Wasm,
+}
+
+/// 插件目录中的 manifest.yaml
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginManifest {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub version: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    pub runtime: PluginRuntime,
+    pub entry: String,
+    #[serde(default)]
+    pub permissions: Vec<PluginPermission>,
+    #[serde(default)]
+    pub events: Vec<PluginEventKind>,
+}
+
+impl PluginManifest {
+    fn validate(&self) -> Result<()> {
+        if self.id.is_empty()
+            || self.id.len() > 64
+            || !self
+                .id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            bail!("invalid plugin id \"{}\"", self.id);
+        }
+        if self.runtime != PluginRuntime::Js {
+            bail!("plugin runtime {:?} is not supported yet", self.runtime);
+        }
+        if self.entry.contains("..") || Path::new(self.entry.as_str()).is_absolute() {
+            bail!("invalid plugin entry \"{}\"", self.entry);
+        }
+        Ok(())
+    }
+}
+
+/// 持久化的插件状态（plugins.yaml）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct PluginRecord {
+    id: String,
+    enabled: bool,
+    /// 尚未经过用户确认权限
+    pending: bool,
+    granted: Vec<PluginPermission>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PluginInfo {
+    #[serde(flatten)]
+    pub manifest: PluginManifest,
+    pub enabled: bool,
+    pub pending: bool,
+    pub granted: Vec<PluginPermission>,
+}
+
+#[derive(Debug, Clone)]
+struct LoadedPlugin {
+    manifest: PluginManifest,
+    record: PluginRecord,
+    dir: PathBuf,
+}
+
+impl LoadedPlugin {
+    fn info(&self) -> PluginInfo {
+        PluginInfo {
+            manifest: self.manifest.clone(),
+            enabled: self.record.enabled,
+            pending: self.record.pending,
+            granted: self.record.granted.clone(),
+        }
+    }
+
+    fn is_allowed(&self, permission: PluginPermission) -> bool {
+        self.record.granted.contains(&permission)
+    }
+
+    fn is_subscribed(&self, kind: PluginEventKind) -> bool {
+        self.record.enabled && !self.record.pending && self.manifest.events.contains(&kind)
+    }
+}
+
+pub struct PluginManager {
+    plugins: RwLock<Vec<LoadedPlugin>>,
+    template_vars: RwLock<HashMap<String, String>>,
+    health: RwLock<HashMap<String, PluginHealth>>,
+    events: broadcast::Sender<PluginEvent>,
+    runner_started: AtomicBool,
+}
+
+impl PluginManager {
+    pub fn global() -> &'static Self {
+        static INSTANCE: OnceCell<PluginManager> = OnceCell::new();
+        INSTANCE.get_or_init(|| {
+            let (tx, _rx) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+            Self {
+                plugins: RwLock::new(Vec::new()),
+                template_vars: RwLock::new(HashMap::new()),
+                health: RwLock::new(HashMap::new()),
+                events: tx,
+                runner_started: AtomicBool::new(false),
+            }
+        })
+    }
+
+    pub async fn init(&self) -> Result<()> {
+        let dir = app_plugins_dir()?;
+        tokio::fs::create_dir_all(&dir).await?;
+
+        let records: Vec<PluginRecord> = help::read_yaml(&dir.join(REGISTRY_FILE)).await.unwrap_or_default();
+        let mut plugins = Vec::with_capacity(records.len());
+        for record in records {
+            let plugin_dir = dir.join(record.id.as_str());
+            match Self::load_manifest(&plugin_dir).await {
+                Ok(manifest) => plugins.push(LoadedPlugin {
+                    manifest,
+                    record,
+                    dir: plugin_dir,
+                }),
+                Err(err) => logging!(warn, Type::System, "Skip plugin {}: {}", record.id, err),
+            }
+        }
+        logging!(info, Type::System, "Loaded {} plugin(s)", plugins.len());
+        *self.plugins.write() = plugins;
+
+        self.maybe_start_runner();
+        Ok(())
+    }
+
+    /// 向订阅的插件分发事件
+    pub fn emit(&self, event: PluginEvent) {
+        let kind = event.kind();
+        if self.plugins.read().iter().any(|p| self.is_active(p, kind)) {
+            let _ = self.events.send(event);
+        }
+    }
+
+    /// 节点切换与订阅更新事件转发给插件
+    pub fn listen_state_events(&'static self) {
+        EventBus::global().listen("plugins", move |event| async move {
+            match event {
+                StateEvent::NodeSwitched { group, node } => self.emit(PluginEvent::NodeChanged { group, node }),
+                StateEvent::ProfileUpdated { uid } => self.emit(PluginEvent::ProfileUpdated { uid }),
+                _ => {}
+            }
+        });
+    }
+
+    /// 插件通过 `verge.setVar` 写入的模板变量，键名为 `<plugin id>.<key>`
+    pub fn template_vars(&self) -> HashMap<String, String> {
+        self.template_vars.read().clone()
+    }
+
+    pub fn list(&self) -> Vec<PluginInfo> {
+        self.plugins.read().iter().map(LoadedPlugin::info).collect()
+    }
+
+    /// 已安装插件的运行状况，从未运行过的插件也会返回默认状态
+    pub fn health(&self) -> Vec<PluginHealth> {
+        let health = self.health.read();
+        self.plugins
+            .read()
+            .iter()
+            .map(|p| {
+                health
+                    .get(&p.manifest.id)
+                    .cloned()
+                    .unwrap_or_else(|| PluginHealth::new(&p.manifest.id))
+            })
+            .collect()
+    }
+
+    fn is_suspended(&self, id: &str) -> bool {
+        self.health.read().get(id).is_some_and(PluginHealth::is_suspended)
+    }
+
+    fn is_active(&self, plugin: &LoadedPlugin, kind: PluginEventKind) -> bool {
+        plugin.is_subscribed(kind) && !self.is_suspended(&plugin.manifest.id)
+    }
+
+    /// 从本地目录安装插件，安装后处于待授权状态
+    pub async fn install(&self, source: &Path) -> Result<PluginInfo> {
+        let manifest = Self::load_manifest(source).await?;
+        let entry = source.join(manifest.entry.as_str());
+        let metadata = tokio::fs::metadata(&entry)
+            .await
+            .with_context(|| format!("plugin entry not found \"{}\"", entry.display()))?;
+        if metadata.len() > MAX_SCRIPT_SIZE {
+            bail!("plugin entry exceeds maximum allowed size");
+        }
+
+        let target = app_plugins_dir()?.join(manifest.id.as_str());
+        if let Some(parent) = target.join(manifest.entry.as_str()).parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::copy(source.join(MANIFEST_FILE), target.join(MANIFEST_FILE)).await?;
+        tokio::fs::copy(&entry, target.join(manifest.entry.as_str())).await?;
+
+        let plugin = LoadedPlugin {
+            record: PluginRecord {
+                id: manifest.id.clone(),
+                enabled: false,
+                pending: !manifest.permissions.is_empty(),
+                granted: Vec::new(),
+            },
+            manifest,
+            dir: target,
+        };
+        let info = plugin.info();
+        {
+            let mut plugins = self.plugins.write();
+            plugins.retain(|p| p.manifest.id != info.manifest.id);
+            plugins.push(plugin);
+        }
+        self.save_registry().await?;
+
+        logging!(info, Type::System, "Installed plugin {}", info.manifest.id);
+        if info.pending {
+            let _ = handle::Handle::app_handle().emit("verge://plugin-permission-request", &info);
+        }
+        Ok(info)
+    }
+
+    pub async fn uninstall(&self, id: &str) -> Result<()> {
+        let removed = {
+            let mut plugins = self.plugins.write();
+            let index = plugins.iter().position(|p| p.manifest.id == id);
+            index.map(|i| plugins.remove(i))
+        };
+        let Some(plugin) = removed else {
+            bail!("plugin \"{id}\" not found");
+        };
+        self.template_vars
+            .write()
+            .retain(|key, _| !key.starts_with(&format!("{id}.")));
+        self.health.write().remove(id);
+        self.save_registry().await?;
+        tokio::fs::remove_dir_all(&plugin.dir).await.ok();
+        Ok(())
+    }
+
+    /// 用户对权限提示的答复，只能授予 manifest 中申请过的权限
+    pub async fn grant_permissions(&self, id: &str, permissions: Vec<PluginPermission>) -> Result<()> {
+        {
+            let mut plugins = self.plugins.write();
+            let Some(plugin) = plugins.iter_mut().find(|p| p.manifest.id == id) else {
+                bail!("plugin \"{id}\" not found");
+            };
+            plugin.record.granted = plugin
+                .manifest
+                .permissions
+                .iter()
+                .copied()
+                .filter(|p| permissions.contains(p))
+                .collect();
+            plugin.record.pending = false;
+            plugin.record.enabled = true;
+        }
+        self.save_registry().await
+    }
+
+    pub async fn set_enabled(&self, id: &str, enabled: bool) -> Result<()> {
+        {
+            let mut plugins = self.plugins.write();
+            let Some(plugin) = plugins.iter_mut().find(|p| p.manifest.id == id) else {
+                bail!("plugin \"{id}\" not found");
+            };
+            if enabled && plugin.record.pending {
+                bail!("plugin \"{id}\" is waiting for permission approval");
+            }
+            plugin.record.enabled = enabled;
+        }
+        if enabled {
+            let mut health = self.health.write();
+            if let Some(health) = health.get_mut(id) {
+                health.resume();
+            }
+        }
+        self.save_registry().await
+    }
+
+    async fn load_manifest(dir: &Path) -> Result<PluginManifest> {
+        let manifest: PluginManifest = help::read_yaml(&dir.join(MANIFEST_FILE)).await?;
+        manifest.validate()?;
+        Ok(manifest)
+    }
+
+    async fn save_registry(&self) -> Result<()> {
+        let records: Vec<PluginRecord> = self.plugins.read().iter().map(|p| p.record.clone()).collect();
+        let path = app_plugins_dir()?.join(REGISTRY_FILE);
+        help::save_yaml(&path, &records, Some("# Clash Verge Plugins")).await
+    }
+
+    fn maybe_start_runner(&self) {
+        if self.runner_started.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        let mut rx = self.events.subscribe();
+        AsyncHandler::spawn(move || async move {
+            let manager = Self::global();
+            let mut ticker = tokio::time::interval(TRAFFIC_POLL_INTERVAL);
+            loop {
+                tokio::select! {
+                    event = rx.recv() => match event {
+                        Ok(event) => manager.dispatch(event).await,
+                        Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                            logging!(warn, Type::System, "Plugin event queue lagged, {} event(s) dropped", skipped);
+                        }
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    },
+                    _ = ticker.tick() => manager.poll_traffic().await,
+                }
+            }
+        });
+    }
+
+    async fn poll_traffic(&self) {
+        let subscribed = self
+            .plugins
+            .read()
+            .iter()
+            .any(|p| self.is_active(p, PluginEventKind::Traffic));
+        if !subscribed {
+            return;
+        }
+        if let Ok(connections) = handle::Handle::mihomo().await.get_connections().await {
+            self.emit(PluginEvent::Traffic {
+                upload_total: connections.upload_total,
+                download_total: connections.download_total,
+            });
+        }
+    }
+
+    async fn dispatch(&self, event: PluginEvent) {
+        let kind = event.kind();
+        let targets: Vec<LoadedPlugin> = self
+            .plugins
+            .read()
+            .iter()
+            .filter(|p| self.is_active(p, kind))
+            .cloned()
+            .collect();
+        let Ok(payload) = serde_json::to_string(&event) else {
+            return;
+        };
+
+        for plugin in targets {
+            let started = Instant::now();
+            let outcome = match self.run_plugin(&plugin, &payload).await {
+                Ok(()) => RunOutcome::Ok(started.elapsed()),
+                Err(err) => match err.downcast_ref::<LimitExceeded>() {
+                    Some(exceeded) => RunOutcome::Killed(*exceeded),
+                    None => {
+                        logging!(warn, Type::System, "Plugin {} failed: {}", plugin.manifest.id, err);
+                        RunOutcome::Failed(started.elapsed(), err.to_string().into())
+                    }
+                },
+            };
+            self.record_outcome(&plugin, outcome).await;
+        }
+    }
+
+    /// 记录执行结果，必要时挂起插件并通知用户
+    async fn record_outcome(&self, plugin: &LoadedPlugin, outcome: RunOutcome) {
+        let id = &plugin.manifest.id;
+        let suspended_reason = {
+            let mut health = self.health.write();
+            let entry = health.entry(id.clone()).or_insert_with(|| PluginHealth::new(id));
+            entry
+                .record(outcome)
+                .then(|| entry.suspended_reason.clone().unwrap_or_default())
+        };
+        let Some(reason) = suspended_reason else {
+            return;
+        };
+
+        logging!(warn, Type::System, "Plugin {} suspended: {}", id, reason);
+        handle::Handle::notice_message("plugin_suspended", format!("{} - {reason}", plugin.manifest.name));
+        let title = format!("{} suspended", plugin.manifest.name);
+        notify_event(NotificationEvent::PluginMessage {
+            title: &title,
+            body: &reason,
+        })
+        .await;
+    }
+
+    async fn run_plugin(&self, plugin: &LoadedPlugin, payload: &str) -> Result<()> {
+        let script = tokio::fs::read_to_string(plugin.dir.join(plugin.manifest.entry.as_str())).await?;
+        let storage_path = plugin.dir.join(STORAGE_FILE);
+        let storage = if plugin.is_allowed(PluginPermission::Storage) {
+            tokio::fs::read_to_string(&storage_path)
+                .await
+                .ok()
+                .filter(|s| serde_json::from_str::<serde_json::Map<_, _>>(s).is_ok())
+                .unwrap_or_else(|| "{}".into())
+        } else {
+            "{}".into()
+        };
+
+        let task = runtime::EventTask {
+            script,
+            event: payload.to_owned(),
+            storage,
+        };
+        let output = AsyncHandler::spawn_blocking(move || runtime::run_event_isolated(&task)).await??;
+
+        for call in output.calls {
+            self.apply_host_call(plugin, call).await;
+        }
+        if plugin.is_allowed(PluginPermission::Storage)
+            && let Some(storage) = output.storage
+        {
+            tokio::fs::write(&storage_path, storage.as_bytes()).await?;
+        }
+        Ok(())
+    }
+
+    async fn apply_host_call(&self, plugin: &LoadedPlugin, call: HostCall) {
+        let id = &plugin.manifest.id;
+        match call {
+            HostCall::Notify { title, body } if plugin.is_allowed(PluginPermission::Notify) => {
+                let title = format!("{}: {}", plugin.manifest.name, title);
+                notify_event(NotificationEvent::PluginMessage {
+                    title: &title,
+                    body: &body,
+                })
+                .await;
+            }
+            HostCall::SetVar { key, value } if plugin.is_allowed(PluginPermission::TemplateVars) => {
+                self.template_vars.write().insert(format!("{id}.{key}").into(), value);
+            }
+            call => {
+                logging!(warn, Type::System, "Plugin {} denied host call {:?}", id, call);
+            }
+        }
+    }
+}
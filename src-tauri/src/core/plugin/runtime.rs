@@ -0,0 +1,220 @@
+use super::watchdog::{HARD_TIME_BUDGET, MEMORY_BUDGET};
+use crate::sandbox::{self, Limits};
+use anyhow::{Result, bail};
+use boa_engine::{Context, JsString, JsValue, Source, native_function::NativeFunction};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use smartstring::alias::String;
+use std::sync::Arc;
+
+const MAX_HOST_CALLS: usize = 64;
+const MAX_ARG_SIZE: usize = 4 * 1024;
+pub const MAX_STORAGE_SIZE: usize = 64 * 1024;
+const LOOP_ITERATION_LIMIT: u64 = 1_000_000;
+const RECURSION_LIMIT: usize = 256;
+const STACK_SIZE_LIMIT: usize = 64 * 1024;
+
+/// 插件在一次事件处理中请求的宿主调用
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum HostCall {
+    Notify { title: String, body: String },
+    SetVar { key: String, value: String },
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct PluginOutput {
+    pub calls: Vec<HostCall>,
+    /// 插件执行后的 storage 内容（JSON）
+    pub storage: Option<String>,
+}
+
+fn js_error(msg: &str) -> boa_engine::JsError {
+    boa_engine::JsError::from_opaque(JsString::from(msg).into())
+}
+
+fn arg_to_string(args: &[JsValue], index: usize, context: &mut Context) -> boa_engine::JsResult<String> {
+    let value = args.get(index).cloned().unwrap_or_default();
+    let value = value
+        .to_string(context)?
+        .to_std_string()
+        .map_err(|_| js_error("Failed to convert argument to string"))?;
+    if value.len() > MAX_ARG_SIZE {
+        return Err(js_error("Host call argument too large"));
+    }
+    Ok(value.into())
+}
+
+/// 在独立的 JS 上下文中执行插件的 `onEvent`
+///
+/// 插件只能访问 `verge` 对象：`notify`、`setVar` 以及可读写的 `storage`，
+/// 宿主调用只被记录下来，是否执行由调用方根据授权决定。
+pub fn run_event(script: &str, event: &str, storage: &str) -> Result<PluginOutput> {
+    if storage.len() > MAX_STORAGE_SIZE {
+        bail!("plugin storage exceeds maximum allowed size");
+    }
+
+    let mut context = Context::default();
+    // 限制循环次数、递归深度与栈大小，防止插件占满 CPU 或内存
+    let limits = context.runtime_limits_mut();
+    limits.set_loop_iteration_limit(LOOP_ITERATION_LIMIT);
+    limits.set_recursion_limit(RECURSION_LIMIT);
+    limits.set_stack_size_limit(STACK_SIZE_LIMIT);
+
+    let calls = Arc::new(Mutex::new(Vec::<HostCall>::new()));
+    let calls_clone = Arc::clone(&calls);
+
+    let _ = context.register_global_builtin_callable("__verge_host__".into(), 3, unsafe {
+        NativeFunction::from_closure(move |_: &JsValue, args: &[JsValue], context: &mut Context| {
+            let op = arg_to_string(args, 0, context)?;
+            let first = arg_to_string(args, 1, context)?;
+            let second = arg_to_string(args, 2, context)?;
+
+            let call = match op.as_str() {
+                "notify" => HostCall::Notify {
+                    title: first,
+                    body: second,
+                },
+                "set_var" => HostCall::SetVar {
+                    key: first,
+                    value: second,
+                },
+                _ => return Err(js_error("Unknown host call")),
+            };
+
+            let mut calls = calls_clone.lock();
+            if calls.len() >= MAX_HOST_CALLS {
+                return Err(js_error("Maximum number of host calls exceeded"));
+            }
+            calls.push(call);
+            Ok(JsValue::undefined())
+        })
+    });
+
+    let code = format!(
+        r#"var verge = Object.freeze({{
+        notify(title, body){{__verge_host__("notify", String(title), String(body ?? ""))}},
+        setVar(key, value){{__verge_host__("set_var", String(key), String(value ?? ""))}},
+        storage: {storage},
+      }});
+      try{{
+        {script};
+        if (typeof onEvent === "function") {{ onEvent({event}); }}
+        JSON.stringify(verge.storage)
+      }} catch(err) {{
+        `__error_flag__ ${{err.toString()}}`
+      }}"#
+    );
+
+    let result = context
+        .eval(Source::from_bytes(code.as_str()))
+        .map_err(|e| anyhow::anyhow!("Failed to evaluate plugin: {}", e))?;
+    let result = result
+        .to_string(&mut context)
+        .map_err(|e| anyhow::anyhow!("Failed to convert plugin result to string: {}", e))?
+        .to_std_string()
+        .map_err(|_| anyhow::anyhow!("Failed to convert JS string to std string"))?;
+
+    if let Some(err) = result.strip_prefix("__error_flag__ ") {
+        bail!("{err}");
+    }
+
+    let storage = (result.len() <= MAX_STORAGE_SIZE).then(|| result.into());
+    let calls = calls.lock().to_vec();
+    Ok(PluginOutput { calls, storage })
+}
+
+/// 交给隔离进程执行的一次事件处理
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EventTask {
+    pub script: std::string::String,
+    pub event: std::string::String,
+    pub storage: std::string::String,
+}
+
+/// 在隔离进程中执行插件，超出时间或内存预算时进程被结束并返回 [`sandbox::LimitExceeded`]
+pub fn run_event_isolated(task: &EventTask) -> Result<PluginOutput> {
+    let limits = Limits {
+        timeout: HARD_TIME_BUDGET,
+        memory: MEMORY_BUDGET,
+    };
+    let output = sandbox::run(sandbox::PLUGIN_TASK, serde_json::to_string(task)?, limits)?;
+    Ok(serde_json::from_str(&output)?)
+}
+
+/// 隔离进程中的入口：读取 [`EventTask`]，输出 JSON 格式的 [`PluginOutput`]
+pub fn serve_event(input: &str) -> Result<std::string::String> {
+    let task: EventTask = serde_json::from_str(input)?;
+    let output = run_event(&task.script, &task.event, &task.storage)?;
+    Ok(serde_json::to_string(&output)?)
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_event_records_host_calls() {
+        let script = r#"
+            function onEvent(e) {
+                verge.notify("node", e.node);
+                verge.setVar("last", e.node);
+                verge.storage.count = (verge.storage.count || 0) + 1;
+            }
+        "#;
+        let output = run_event(script, r#"{"type":"node_changed","group":"G","node":"HK-01"}"#, "{}")
+            .expect("plugin should run");
+
+        assert_eq!(
+            output.calls,
+            vec![
+                HostCall::Notify {
+                    title: "node".into(),
+                    body: "HK-01".into()
+                },
+                HostCall::SetVar {
+                    key: "last".into(),
+                    value: "HK-01".into()
+                },
+            ]
+        );
+        assert_eq!(output.storage.as_deref(), Some(r#"{"count":1}"#));
+    }
+
+    #[test]
+    fn test_run_event_reports_script_errors() {
+        let result = run_event("function onEvent() { throw new Error('boom'); }", "{}", "{}");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_run_event_stops_infinite_loop() {
+        assert!(run_event("function onEvent() { while (true) {} }", "{}", "{}").is_err());
+    }
+
+    #[test]
+    fn test_run_event_limits_host_calls() {
+        let script = "function onEvent() { for (let i = 0; i < 1000; i++) verge.setVar('k', i); }";
+        assert!(run_event(script, "{}", "{}").is_err());
+    }
+
+    #[test]
+    fn test_serve_event_round_trip() {
+        let task = EventTask {
+            script: "function onEvent(e) { verge.notify('t', e.node); }".into(),
+            event: r#"{"node":"HK-01"}"#.into(),
+            storage: "{}".into(),
+        };
+        let output =
+            serve_event(&serde_json::to_string(&task).expect("task should serialize")).expect("plugin should run");
+        let output: PluginOutput = serde_json::from_str(&output).expect("output should parse");
+        assert_eq!(
+            output.calls,
+            vec![HostCall::Notify {
+                title: "t".into(),
+                body: "HK-01".into()
+            }]
+        );
+    }
+}
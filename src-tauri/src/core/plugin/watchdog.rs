@@ -0,0 +1,174 @@
+use crate::sandbox::LimitExceeded;
+use serde::Serialize;
+use smartstring::alias::String;
+use std::time::Duration;
+
+/// 单次事件处理的软性 CPU 时间预算，超出后记为一次违规
+pub const SOFT_TIME_BUDGET: Duration = Duration::from_millis(250);
+/// 硬性时间上限，超出后结束执行插件的进程并立即挂起插件
+pub const HARD_TIME_BUDGET: Duration = Duration::from_secs(2);
+/// 执行插件的进程的常驻内存上限，超出后同样结束进程并挂起插件
+pub const MEMORY_BUDGET: u64 = 64 * 1024 * 1024;
+/// 连续失败或超预算达到该次数后挂起插件
+const MAX_CONSECUTIVE_VIOLATIONS: u32 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PluginHealthStatus {
+    Healthy,
+    Suspended,
+}
+
+/// 插件运行状况，供 `get_plugin_health` 返回
+#[derive(Debug, Clone, Serialize)]
+pub struct PluginHealth {
+    pub id: String,
+    pub status: PluginHealthStatus,
+    pub runs: u64,
+    pub failures: u64,
+    pub budget_exceeded: u64,
+    pub consecutive_violations: u32,
+    pub last_duration_ms: u64,
+    pub max_duration_ms: u64,
+    pub last_error: Option<String>,
+    pub suspended_reason: Option<String>,
+}
+
+/// 单次执行的结果
+pub enum RunOutcome {
+    Ok(Duration),
+    Failed(Duration, String),
+    /// 超出硬性时间或内存上限，进程已被结束
+    Killed(LimitExceeded),
+}
+
+impl PluginHealth {
+    pub fn new(id: &str) -> Self {
+        Self {
+            id: id.into(),
+            status: PluginHealthStatus::Healthy,
+            runs: 0,
+            failures: 0,
+            budget_exceeded: 0,
+            consecutive_violations: 0,
+            last_duration_ms: 0,
+            max_duration_ms: 0,
+            last_error: None,
+            suspended_reason: None,
+        }
+    }
+
+    pub fn is_suspended(&self) -> bool {
+        self.status == PluginHealthStatus::Suspended
+    }
+
+    fn record_duration(&mut self, duration: Duration) {
+        let ms = u64::try_from(duration.as_millis()).unwrap_or(u64::MAX);
+        self.last_duration_ms = ms;
+        self.max_duration_ms = self.max_duration_ms.max(ms);
+    }
+
+    fn suspend(&mut self, reason: String) {
+        self.status = PluginHealthStatus::Suspended;
+        self.suspended_reason = Some(reason);
+    }
+
+    /// 记录一次执行结果，返回本次是否导致插件被挂起
+    pub fn record(&mut self, outcome: RunOutcome) -> bool {
+        if self.is_suspended() {
+            return false;
+        }
+        self.runs += 1;
+
+        match outcome {
+            RunOutcome::Ok(duration) => {
+                self.record_duration(duration);
+                if duration > SOFT_TIME_BUDGET {
+                    self.budget_exceeded += 1;
+                    self.consecutive_violations += 1;
+                } else {
+                    self.consecutive_violations = 0;
+                }
+            }
+            RunOutcome::Failed(duration, err) => {
+                self.record_duration(duration);
+                self.failures += 1;
+                self.consecutive_violations += 1;
+                self.last_error = Some(err);
+            }
+            RunOutcome::Killed(exceeded) => {
+                if let LimitExceeded::Time(timeout) = exceeded {
+                    self.record_duration(timeout);
+                }
+                self.budget_exceeded += 1;
+                self.suspend(exceeded.to_string().into());
+                return true;
+            }
+        }
+
+        if self.consecutive_violations >= MAX_CONSECUTIVE_VIOLATIONS {
+            self.suspend(
+                format!(
+                    "{} consecutive failures or budget violations",
+                    self.consecutive_violations
+                )
+                .into(),
+            );
+            return true;
+        }
+        false
+    }
+
+    /// 用户重新启用插件时清除挂起状态
+    pub fn resume(&mut self) {
+        self.status = PluginHealthStatus::Healthy;
+        self.consecutive_violations = 0;
+        self.suspended_reason = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_suspend_after_consecutive_violations() {
+        let mut health = PluginHealth::new("demo");
+        let slow = SOFT_TIME_BUDGET + Duration::from_millis(1);
+
+        assert!(!health.record(RunOutcome::Ok(slow)));
+        assert!(!health.record(RunOutcome::Failed(Duration::ZERO, "boom".into())));
+        assert!(health.record(RunOutcome::Ok(slow)));
+        assert!(health.is_suspended());
+        assert_eq!(health.budget_exceeded, 2);
+        assert_eq!(health.failures, 1);
+
+        health.resume();
+        assert!(!health.is_suspended());
+    }
+
+    #[test]
+    fn test_successful_run_resets_violations() {
+        let mut health = PluginHealth::new("demo");
+        health.record(RunOutcome::Failed(Duration::ZERO, "boom".into()));
+        health.record(RunOutcome::Failed(Duration::ZERO, "boom".into()));
+        health.record(RunOutcome::Ok(Duration::from_millis(1)));
+        assert_eq!(health.consecutive_violations, 0);
+        assert!(!health.record(RunOutcome::Failed(Duration::ZERO, "boom".into())));
+    }
+
+    #[test]
+    fn test_limit_exceeded_suspends_immediately() {
+        let mut health = PluginHealth::new("demo");
+        assert!(health.record(RunOutcome::Killed(LimitExceeded::Time(HARD_TIME_BUDGET))));
+        assert!(health.is_suspended());
+        assert!(!health.record(RunOutcome::Ok(Duration::ZERO)));
+
+        let mut health = PluginHealth::new("demo");
+        assert!(health.record(RunOutcome::Killed(LimitExceeded::Memory(MEMORY_BUDGET))));
+        assert_eq!(
+            health.suspended_reason.as_deref(),
+            Some("exceeded memory limit of 64 MB")
+        );
+    }
+}
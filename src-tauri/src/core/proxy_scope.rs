@@ -0,0 +1,214 @@
+//! 按网络服务 / 连接应用系统代理
+//!
+//! macOS 上系统代理全部由这里通过 SystemConfiguration 设置，未配置 `sysproxy_interfaces` 时应用到全部服务，
+//! 否则未选中的服务会被关闭代理。Windows 上 sysproxy 只设置默认的局域网连接，以 `LAN` 表示，
+//! 拨号 / VPN 连接通过 WinINet 的按连接设置单独应用。
+//! Linux 的代理设置是全局的，不支持按接口区分，由 `linux_proxy` 按桌面环境设置。
+
+use anyhow::Result;
+use smartstring::alias::String;
+
+/// Windows 默认局域网连接（以太网与 Wi-Fi 共用）的名称
+#[cfg(target_os = "windows")]
+pub const LAN_CONNECTION: &str = "LAN";
+
+/// 系统代理的应用方式
+#[derive(Debug, Clone)]
+pub enum ProxyScopeMode {
+    Off,
+    Manual { host: String, port: u16, bypass: String },
+    Pac { url: String },
+}
+
+/// 选中列表为空表示应用到全部
+#[cfg_attr(target_os = "linux", allow(dead_code))]
+pub(super) fn is_selected(selected: &[String], name: &str) -> bool {
+    selected.is_empty() || selected.iter().any(|s| s == name)
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use super::ProxyScopeMode;
+    use crate::core::macos_proxy;
+    use anyhow::Result;
+    use smartstring::alias::String;
+
+    pub fn list_interfaces() -> Result<Vec<String>> {
+        macos_proxy::list_services()
+    }
+
+    /// 系统代理由这里统一设置：选中的服务应用代理，其余服务关闭
+    pub fn apply(selected: &[String], mode: &ProxyScopeMode) -> Result<()> {
+        macos_proxy::set_proxies(selected, mode)
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use super::{LAN_CONNECTION, ProxyScopeMode, is_selected};
+    use anyhow::{Result, bail};
+    use smartstring::alias::String;
+    use std::{mem::size_of, ptr};
+    use winapi::um::wininet::{
+        INTERNET_OPTION_PER_CONNECTION_OPTION, INTERNET_OPTION_REFRESH, INTERNET_OPTION_SETTINGS_CHANGED,
+        INTERNET_PER_CONN_AUTOCONFIG_URL, INTERNET_PER_CONN_FLAGS, INTERNET_PER_CONN_OPTION_LISTW,
+        INTERNET_PER_CONN_OPTIONW, INTERNET_PER_CONN_PROXY_BYPASS, INTERNET_PER_CONN_PROXY_SERVER, InternetSetOptionW,
+        PROXY_TYPE_AUTO_PROXY_URL, PROXY_TYPE_DIRECT, PROXY_TYPE_PROXY,
+    };
+    use winreg::{RegKey, enums::HKEY_CURRENT_USER};
+
+    const CONNECTIONS_KEY: &str = r"Software\Microsoft\Windows\CurrentVersion\Internet Settings\Connections";
+
+    fn wide(s: &str) -> Vec<u16> {
+        s.encode_utf16().chain(std::iter::once(0)).collect()
+    }
+
+    /// 拨号与 VPN 连接在注册表中各有一项按连接保存的设置
+    fn ras_connections() -> Vec<String> {
+        RegKey::predef(HKEY_CURRENT_USER)
+            .open_subkey(CONNECTIONS_KEY)
+            .map(|key| {
+                key.enum_values()
+                    .filter_map(|value| value.ok().map(|(name, _)| name))
+                    .filter(|name| !matches!(name.as_str(), "DefaultConnectionSettings" | "SavedLegacySettings"))
+                    .map(Into::into)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn list_interfaces() -> Result<Vec<String>> {
+        let mut interfaces = vec![String::from(LAN_CONNECTION)];
+        interfaces.extend(ras_connections());
+        Ok(interfaces)
+    }
+
+    /// 通过 WinINet 设置某个连接的代理，`connection` 为空表示局域网设置
+    fn set_connection_proxy(connection: Option<&str>, mode: &ProxyScopeMode) -> Result<()> {
+        let connection = connection.map(wide);
+        let (flags, server, bypass, pac) = match mode {
+            ProxyScopeMode::Off => (PROXY_TYPE_DIRECT, None, None, None),
+            ProxyScopeMode::Manual { host, port, bypass } => (
+                PROXY_TYPE_DIRECT | PROXY_TYPE_PROXY,
+                Some(wide(&format!("{host}:{port}"))),
+                Some(wide(bypass)),
+                None,
+            ),
+            ProxyScopeMode::Pac { url } => (
+                PROXY_TYPE_DIRECT | PROXY_TYPE_AUTO_PROXY_URL,
+                None,
+                None,
+                Some(wide(url)),
+            ),
+        };
+
+        let mut options: Vec<INTERNET_PER_CONN_OPTIONW> = Vec::new();
+        let mut push = |option, build: &dyn Fn(&mut INTERNET_PER_CONN_OPTIONW)| {
+            // SAFETY: INTERNET_PER_CONN_OPTIONW 是纯数据结构，全零是合法值
+            let mut item: INTERNET_PER_CONN_OPTIONW = unsafe { std::mem::zeroed() };
+            item.dwOption = option;
+            build(&mut item);
+            options.push(item);
+        };
+        push(INTERNET_PER_CONN_FLAGS, &|item| unsafe {
+            *item.Value.dwValue_mut() = flags
+        });
+        if let Some(server) = &server {
+            push(INTERNET_PER_CONN_PROXY_SERVER, &|item| unsafe {
+                *item.Value.pszValue_mut() = server.as_ptr().cast_mut()
+            });
+        }
+        if let Some(bypass) = &bypass {
+            push(INTERNET_PER_CONN_PROXY_BYPASS, &|item| unsafe {
+                *item.Value.pszValue_mut() = bypass.as_ptr().cast_mut()
+            });
+        }
+        if let Some(pac) = &pac {
+            push(INTERNET_PER_CONN_AUTOCONFIG_URL, &|item| unsafe {
+                *item.Value.pszValue_mut() = pac.as_ptr().cast_mut()
+            });
+        }
+
+        let mut list = INTERNET_PER_CONN_OPTION_LISTW {
+            dwSize: size_of::<INTERNET_PER_CONN_OPTION_LISTW>() as u32,
+            pszConnection: connection.as_ref().map_or(ptr::null_mut(), |c| c.as_ptr().cast_mut()),
+            dwOptionCount: options.len() as u32,
+            dwOptionError: 0,
+            pOptions: options.as_mut_ptr(),
+        };
+        // SAFETY: list 与其引用的字符串在调用期间保持有效
+        let ok = unsafe {
+            InternetSetOptionW(
+                ptr::null_mut(),
+                INTERNET_OPTION_PER_CONNECTION_OPTION,
+                (&raw mut list).cast(),
+                size_of::<INTERNET_PER_CONN_OPTION_LISTW>() as u32,
+            )
+        };
+        if ok == 0 {
+            bail!("InternetSetOptionW failed: {}", std::io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    /// sysproxy 只设置局域网连接，这里补充拨号 / VPN 连接并按选择关闭局域网代理
+    pub fn apply(selected: &[String], mode: &ProxyScopeMode) -> Result<()> {
+        if !is_selected(selected, LAN_CONNECTION) && !matches!(mode, ProxyScopeMode::Off) {
+            set_connection_proxy(None, &ProxyScopeMode::Off)?;
+        }
+        for connection in ras_connections() {
+            let connection_mode = if is_selected(selected, &connection) {
+                mode
+            } else {
+                &ProxyScopeMode::Off
+            };
+            set_connection_proxy(Some(&connection), connection_mode)?;
+        }
+        // SAFETY: 通知 WinINet 重新读取设置，不需要参数
+        unsafe {
+            InternetSetOptionW(ptr::null_mut(), INTERNET_OPTION_SETTINGS_CHANGED, ptr::null_mut(), 0);
+            InternetSetOptionW(ptr::null_mut(), INTERNET_OPTION_REFRESH, ptr::null_mut(), 0);
+        }
+        Ok(())
+    }
+}
+
+/// 可选择的网络服务 / 连接，Linux 上为空
+#[cfg_attr(target_os = "linux", allow(clippy::unnecessary_wraps))]
+pub fn list_proxy_interfaces() -> Result<Vec<String>> {
+    #[cfg(not(target_os = "linux"))]
+    {
+        platform::list_interfaces()
+    }
+    #[cfg(target_os = "linux")]
+    {
+        Ok(Vec::new())
+    }
+}
+
+/// 在 sysproxy 应用全局设置之后，按选择调整各网络服务 / 连接，未配置选择时应用到全部。
+/// Windows 上即使选择为空也要设置拨号 / VPN 连接，否则之前按连接设置的代理在关闭或清空选择后会残留
+#[cfg_attr(target_os = "linux", allow(clippy::unnecessary_wraps))]
+pub fn apply_proxy_scope(selected: &[String], mode: &ProxyScopeMode) -> Result<()> {
+    #[cfg(not(target_os = "linux"))]
+    {
+        platform::apply(selected, mode)
+    }
+    #[cfg(target_os = "linux")]
+    {
+        let _ = (selected, mode);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_selected() {
+        assert!(is_selected(&[], "Wi-Fi"));
+        assert!(is_selected(&[String::from("Wi-Fi")], "Wi-Fi"));
+        assert!(!is_selected(&[String::from("Wi-Fi")], "USB 10/100/1000 LAN"));
+    }
+}
@@ -0,0 +1,104 @@
+//! 睡眠唤醒后的恢复
+//!
+//! 后台心跳比较两次心跳间的系统时间，实际流逝时间远超心跳间隔时认为系统刚从睡眠中唤醒，
+//! 随后检查内核是否正常、重新应用系统代理、重启 Discord 流量监听，并刷新网络状态与订阅更新。
+
+use crate::{
+    cmd::discord::restart_discord_rpc,
+    core::{Timer, handle, manager::CoreWatchdog, network_monitor::NetworkMonitor, sysopt::Sysopt},
+    process::AsyncHandler,
+    singleton,
+};
+use clash_verge_logging::{Type, logging, logging_error};
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, SystemTime},
+};
+use tauri::Emitter as _;
+
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(10);
+/// 比心跳间隔多出这么久时视为经历了睡眠
+const SLEEP_THRESHOLD: Duration = Duration::from_secs(30);
+/// 唤醒后等待网卡重新获取地址
+const SETTLE_DELAY: Duration = Duration::from_secs(3);
+const SYSTEM_RESUMED_EVENT: &str = "system-resumed";
+
+/// 根据一次心跳的实际耗时判断睡眠时长
+fn detect_sleep(elapsed: Duration) -> Option<Duration> {
+    elapsed
+        .checked_sub(HEARTBEAT_INTERVAL)
+        .filter(|slept| *slept >= SLEEP_THRESHOLD)
+}
+
+pub struct ResumeWatcher {
+    runner_started: AtomicBool,
+    recovering: AtomicBool,
+}
+
+singleton!(ResumeWatcher, RESUME_WATCHER);
+
+impl ResumeWatcher {
+    fn new() -> Self {
+        Self {
+            runner_started: AtomicBool::new(false),
+            recovering: AtomicBool::new(false),
+        }
+    }
+
+    pub fn init(&self) {
+        if self.runner_started.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        AsyncHandler::spawn(|| async {
+            loop {
+                let before = SystemTime::now();
+                tokio::time::sleep(HEARTBEAT_INTERVAL).await;
+                if handle::Handle::global().is_exiting() {
+                    break;
+                }
+                // 系统时间被往回调整时 elapsed 返回错误，不视为睡眠
+                if let Some(slept) = before.elapsed().ok().and_then(detect_sleep) {
+                    Self::global().on_resume(slept).await;
+                }
+            }
+        });
+    }
+
+    async fn on_resume(&self, slept: Duration) {
+        if self.recovering.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        logging!(
+            info,
+            Type::System,
+            "System resumed after ~{}s of sleep",
+            slept.as_secs()
+        );
+        tokio::time::sleep(SETTLE_DELAY).await;
+
+        if !CoreWatchdog::global().probe_now().await {
+            logging!(warn, Type::Core, "Core is unresponsive after resume, restarting");
+        }
+        logging_error!(Type::System, Sysopt::global().update_sysproxy().await);
+        restart_discord_rpc().await;
+        NetworkMonitor::global().check().await;
+        Timer::global().on_network_reconnected().await;
+
+        let _ = handle::Handle::app_handle().emit(SYSTEM_RESUMED_EVENT, slept.as_secs());
+        self.recovering.store(false, Ordering::Release);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_sleep() {
+        assert_eq!(detect_sleep(Duration::from_secs(10)), None);
+        assert_eq!(detect_sleep(Duration::from_secs(35)), None);
+        assert_eq!(detect_sleep(Duration::from_secs(3610)), Some(Duration::from_secs(3600)));
+        assert_eq!(detect_sleep(Duration::from_secs(1)), None);
+    }
+}
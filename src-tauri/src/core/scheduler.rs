@@ -0,0 +1,507 @@
+//! 定时任务
+//!
+//! 按五段式 cron 表达式（分 时 日 月 周）定时切换代理模式、系统代理或 TUN 模式，
+//! 例如 `0 2 * * *` 切换到直连、`0 8 * * *` 切换回规则模式，
+//! `0 0 * * 6,0` 在周末关闭系统代理。任务保存在 verge 配置的 `schedules` 中，
+//! 后台任务每分钟检查一次。最近的检查时间与执行结果保存在 [`STATE_FILE`] 中，
+//! 启动时补执行应用关闭期间错过的任务。
+
+use crate::{
+    config::{Config, ISchedule, IVerge, ScheduleAction},
+    core::{audit::AuditSource, handle},
+    feat,
+    process::AsyncHandler,
+    singleton,
+    utils::{dirs, help},
+};
+use anyhow::{Result, anyhow, bail};
+use chrono::{Datelike as _, Duration as ChronoDuration, Local, NaiveDateTime, TimeZone as _, Timelike as _};
+use clash_verge_logging::{Type, logging};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use smartstring::alias::String;
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+const CLASH_MODES: [&str; 3] = ["rule", "global", "direct"];
+/// 计算下次执行时间时最多向后查找的天数
+const MAX_LOOKAHEAD_DAYS: i64 = 366 * 4;
+/// 补执行错过的任务时最多向前追溯的天数
+const MAX_CATCH_UP_DAYS: i64 = 7;
+const STATE_FILE: &str = "scheduler_state.json";
+
+/// 解析后的 cron 表达式，每个字段为允许取值的位图
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronExpr {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    /// 日与周均有限制时，满足其一即可（与 cron 一致）
+    any_day: bool,
+    any_weekday: bool,
+}
+
+fn parse_field(spec: &str, min: u32, max: u32) -> Result<u64> {
+    let mut bits = 0u64;
+    for item in spec.split(',') {
+        let (range, step) = match item.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().ok().filter(|&s| s > 0)),
+            None => (item, Some(1)),
+        };
+        let step = step.ok_or_else(|| anyhow!("invalid step in {item}"))?;
+        let (start, end) = match range {
+            "*" => (min, max),
+            range => match range.split_once('-') {
+                Some((start, end)) => (start.parse()?, end.parse()?),
+                // `5/15` 表示从 5 开始每 15 一次
+                None if item.contains('/') => (range.parse()?, max),
+                None => {
+                    let value = range.parse()?;
+                    (value, value)
+                }
+            },
+        };
+        if start < min || end > max || start > end {
+            bail!("{item} is out of range {min}-{max}");
+        }
+        for value in (start..=end).step_by(step as usize) {
+            bits |= 1 << value;
+        }
+    }
+    Ok(bits)
+}
+
+impl CronExpr {
+    pub fn parse(expr: &str) -> Result<Self> {
+        let fields: Vec<&str> = expr.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields.as_slice() else {
+            bail!("cron expression needs 5 fields: {expr}");
+        };
+        let mut weekdays = parse_field(weekday, 0, 7)?;
+        // 0 与 7 都表示周日
+        if weekdays & (1 << 7) != 0 {
+            weekdays = (weekdays | 1) & !(1 << 7);
+        }
+        Ok(Self {
+            minutes: parse_field(minute, 0, 59)?,
+            hours: parse_field(hour, 0, 23)?,
+            days: parse_field(day, 1, 31)?,
+            months: parse_field(month, 1, 12)?,
+            weekdays,
+            any_day: *day == "*",
+            any_weekday: *weekday == "*",
+        })
+    }
+
+    fn matches_day(&self, time: &NaiveDateTime) -> bool {
+        let day = self.days & (1 << time.day()) != 0;
+        let weekday = self.weekdays & (1 << time.weekday().num_days_from_sunday()) != 0;
+        let day_matches = match (self.any_day, self.any_weekday) {
+            (true, true) => true,
+            (true, false) => weekday,
+            (false, true) => day,
+            (false, false) => day || weekday,
+        };
+        day_matches && self.months & (1 << time.month()) != 0
+    }
+
+    pub fn matches(&self, time: &NaiveDateTime) -> bool {
+        self.matches_day(time) && self.hours & (1 << time.hour()) != 0 && self.minutes & (1 << time.minute()) != 0
+    }
+
+    /// `after` 之后第一个匹配的时刻
+    pub fn next_after(&self, after: NaiveDateTime) -> Option<NaiveDateTime> {
+        let limit = after + ChronoDuration::days(MAX_LOOKAHEAD_DAYS);
+        let mut time = after.with_second(0)?.with_nanosecond(0)? + ChronoDuration::minutes(1);
+        while time < limit {
+            if !self.matches_day(&time) {
+                time = time.date().succ_opt()?.and_hms_opt(0, 0, 0)?;
+            } else if self.hours & (1 << time.hour()) == 0 {
+                time = time.with_minute(0)? + ChronoDuration::hours(1);
+            } else if self.minutes & (1 << time.minute()) == 0 {
+                time += ChronoDuration::minutes(1);
+            } else {
+                return Some(time);
+            }
+        }
+        None
+    }
+
+    /// `(after, until]` 之间最后一个匹配的时刻
+    pub fn last_between(&self, after: NaiveDateTime, until: NaiveDateTime) -> Option<NaiveDateTime> {
+        let mut last = None;
+        let mut time = after;
+        while let Some(next) = self.next_after(time).filter(|next| *next <= until) {
+            last = Some(next);
+            time = next;
+        }
+        last
+    }
+}
+
+fn validate_schedule(schedule: &ISchedule) -> Result<()> {
+    CronExpr::parse(&schedule.cron)?;
+    if let ScheduleAction::ClashMode(mode) = &schedule.action
+        && !CLASH_MODES.contains(&mode.as_str())
+    {
+        bail!("invalid clash mode: {mode}");
+    }
+    if let ScheduleAction::SelectRegion { country, .. } = &schedule.action
+        && feat::normalize_country(country).is_none()
+    {
+        bail!("invalid country code: {country}");
+    }
+    Ok(())
+}
+
+/// 最近一次执行的结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduleRun {
+    /// 执行时间（秒）
+    pub time: i64,
+    pub ok: bool,
+    pub error: Option<String>,
+}
+
+/// `list_schedules` 返回的单个任务
+#[derive(Debug, Clone, Serialize)]
+pub struct ScheduleInfo {
+    #[serde(flatten)]
+    pub schedule: ISchedule,
+    /// 下次执行时间（秒），表达式无效或已停用时为空
+    pub next_run: Option<i64>,
+    pub last_run: Option<ScheduleRun>,
+}
+
+/// 保存在 [`STATE_FILE`] 中的运行状态
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SchedulerState {
+    /// 最近一次检查的时刻（秒）
+    last_tick: Option<i64>,
+    last_runs: HashMap<String, ScheduleRun>,
+}
+
+fn state_path() -> Result<PathBuf> {
+    Ok(dirs::app_home_dir()?.join(STATE_FILE))
+}
+
+fn read_state() -> Result<SchedulerState> {
+    let path = state_path()?;
+    if !path.exists() {
+        return Ok(SchedulerState::default());
+    }
+    Ok(serde_json::from_str(&std::fs::read_to_string(&path)?)?)
+}
+
+fn write_state(state: &SchedulerState) -> Result<()> {
+    std::fs::write(state_path()?, serde_json::to_string(state)?)?;
+    Ok(())
+}
+
+fn to_timestamp(time: &NaiveDateTime) -> Option<i64> {
+    Local.from_local_datetime(time).earliest().map(|time| time.timestamp())
+}
+
+fn from_timestamp(timestamp: i64) -> Option<NaiveDateTime> {
+    Local
+        .timestamp_opt(timestamp, 0)
+        .single()
+        .map(|time| time.naive_local())
+}
+
+/// 当前所在的整分钟
+fn current_minute() -> Option<NaiveDateTime> {
+    Local::now().naive_local().with_second(0)?.with_nanosecond(0)
+}
+
+pub struct Scheduler {
+    last_runs: Mutex<HashMap<String, ScheduleRun>>,
+    runner_started: AtomicBool,
+}
+
+singleton!(Scheduler, SCHEDULER);
+
+impl Scheduler {
+    fn new() -> Self {
+        Self {
+            last_runs: Mutex::new(HashMap::new()),
+            runner_started: AtomicBool::new(false),
+        }
+    }
+
+    pub fn init(&self) {
+        if self.runner_started.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        AsyncHandler::spawn(|| async {
+            let scheduler = Self::global();
+            let state = read_state().unwrap_or_else(|err| {
+                logging!(warn, Type::Timer, "[定时任务] 读取运行状态失败: {}", err);
+                SchedulerState::default()
+            });
+            scheduler.last_runs.lock().extend(state.last_runs);
+            let mut last_tick = current_minute();
+            if let (Some(since), Some(now)) = (state.last_tick.and_then(from_timestamp), last_tick) {
+                scheduler.catch_up(since, now).await;
+            }
+            scheduler.save_state(last_tick);
+
+            loop {
+                // 对齐到下一分钟
+                let now = Local::now();
+                let wait = 60 - u64::from(now.second()).min(59);
+                tokio::time::sleep(Duration::from_secs(wait)).await;
+
+                let Some(tick) = current_minute() else {
+                    continue;
+                };
+                if last_tick == Some(tick) {
+                    continue;
+                }
+                last_tick = Some(tick);
+                scheduler.run_due(&tick).await;
+                scheduler.save_state(last_tick);
+            }
+        });
+    }
+
+    async fn schedules() -> Vec<ISchedule> {
+        Config::verge().await.latest_arc().schedules.clone().unwrap_or_default()
+    }
+
+    fn save_state(&self, last_tick: Option<NaiveDateTime>) {
+        let state = SchedulerState {
+            last_tick: last_tick.as_ref().and_then(to_timestamp),
+            last_runs: self.last_runs.lock().clone(),
+        };
+        if let Err(err) = write_state(&state) {
+            logging!(warn, Type::Timer, "[定时任务] 保存运行状态失败: {}", err);
+        }
+    }
+
+    /// 补执行 `(since, now]` 之间错过的任务。每个任务只执行最近错过的一次，
+    /// 按错过的时间先后执行，使最终状态与应用一直运行时一致
+    async fn catch_up(&self, since: NaiveDateTime, now: NaiveDateTime) {
+        let since = since.max(now - ChronoDuration::days(MAX_CATCH_UP_DAYS));
+        let mut missed: Vec<(NaiveDateTime, ISchedule)> = Self::schedules()
+            .await
+            .into_iter()
+            .filter(|schedule| schedule.enabled.unwrap_or(true))
+            .filter_map(|schedule| {
+                let at = CronExpr::parse(&schedule.cron).ok()?.last_between(since, now)?;
+                Some((at, schedule))
+            })
+            .collect();
+        missed.sort_by_key(|(at, _)| *at);
+        for (at, schedule) in missed {
+            logging!(
+                info,
+                Type::Timer,
+                "[定时任务] 补执行 {} 在 {} 错过的任务",
+                schedule.id,
+                at
+            );
+            self.execute(&schedule).await;
+        }
+    }
+
+    async fn run_due(&self, tick: &NaiveDateTime) {
+        for schedule in Self::schedules().await {
+            if !schedule.enabled.unwrap_or(true) {
+                continue;
+            }
+            match CronExpr::parse(&schedule.cron) {
+                Ok(cron) if cron.matches(tick) => self.execute(&schedule).await,
+                Ok(_) => {}
+                Err(err) => logging!(warn, Type::Timer, "[定时任务] {} 的表达式无效: {}", schedule.id, err),
+            }
+        }
+    }
+
+    async fn execute(&self, schedule: &ISchedule) {
+        logging!(
+            info,
+            Type::Timer,
+            "[定时任务] 执行 {} ({}): {:?}",
+            schedule.name.as_deref().unwrap_or_default(),
+            schedule.id,
+            schedule.action
+        );
+        let result = AuditSource::Scheduler.scope(run_action(&schedule.action)).await;
+        if let Err(err) = &result {
+            logging!(error, Type::Timer, "[定时任务] {} 执行失败: {}", schedule.id, err);
+        }
+        self.last_runs.lock().insert(
+            schedule.id.clone(),
+            ScheduleRun {
+                time: Local::now().timestamp(),
+                ok: result.is_ok(),
+                error: result.err().map(|err| err.to_string().into()),
+            },
+        );
+    }
+
+    pub async fn list(&self) -> Vec<ScheduleInfo> {
+        let now = Local::now().naive_local();
+        let last_runs = self.last_runs.lock().clone();
+        Self::schedules()
+            .await
+            .into_iter()
+            .map(|schedule| {
+                let next_run = CronExpr::parse(&schedule.cron)
+                    .ok()
+                    .filter(|_| schedule.enabled.unwrap_or(true))
+                    .and_then(|cron| cron.next_after(now))
+                    .and_then(|next| Local.from_local_datetime(&next).earliest())
+                    .map(|next| next.timestamp());
+                ScheduleInfo {
+                    last_run: last_runs.get(&schedule.id).cloned(),
+                    schedule,
+                    next_run,
+                }
+            })
+            .collect()
+    }
+
+    async fn save(edit: impl FnOnce(&mut Vec<ISchedule>)) -> Result<()> {
+        Config::verge()
+            .await
+            .edit_draft(|d| edit(d.schedules.get_or_insert_with(Vec::new)));
+        Config::verge().await.apply();
+        Config::verge().await.latest_arc().save_file().await?;
+        handle::Handle::refresh_verge();
+        Ok(())
+    }
+
+    /// 新建或更新任务，`id` 为空时新建
+    pub async fn upsert(&self, mut schedule: ISchedule) -> Result<Vec<ScheduleInfo>> {
+        validate_schedule(&schedule)?;
+        if schedule.id.is_empty() {
+            schedule.id = help::get_uid("s");
+        }
+        logging!(info, Type::Timer, "[定时任务] 保存 {} ({})", schedule.id, schedule.cron);
+        Self::save(|schedules| match schedules.iter_mut().find(|s| s.id == schedule.id) {
+            Some(existing) => *existing = schedule,
+            None => schedules.push(schedule),
+        })
+        .await?;
+        Ok(self.list().await)
+    }
+
+    pub async fn delete(&self, id: &str) -> Result<Vec<ScheduleInfo>> {
+        if !Self::schedules().await.iter().any(|s| s.id == id) {
+            bail!("schedule not found: {id}");
+        }
+        Self::save(|schedules| schedules.retain(|s| s.id != id)).await?;
+        self.last_runs.lock().remove(id);
+        logging!(info, Type::Timer, "[定时任务] 删除 {}", id);
+        Ok(self.list().await)
+    }
+}
+
+pub(super) async fn run_action(action: &ScheduleAction) -> Result<()> {
+    let (system_proxy, tun_mode) = {
+        let verge = Config::verge().await.latest_arc();
+        (
+            verge.enable_system_proxy.unwrap_or(false),
+            verge.enable_tun_mode.unwrap_or(false),
+        )
+    };
+    let patch = match action {
+        ScheduleAction::ClashMode(mode) => {
+            if !CLASH_MODES.contains(&mode.as_str()) {
+                bail!("invalid clash mode: {mode}");
+            }
+            return feat::set_clash_mode(mode.clone()).await;
+        }
+        ScheduleAction::SelectRegion { group, country } => {
+            feat::select_node_by_region(group, country).await?;
+            return Ok(());
+        }
+        ScheduleAction::SystemProxy(enable) if system_proxy != *enable => IVerge {
+            enable_system_proxy: Some(*enable),
+            ..IVerge::default()
+        },
+        ScheduleAction::TunMode(enable) if tun_mode != *enable => IVerge {
+            enable_tun_mode: Some(*enable),
+            ..IVerge::default()
+        },
+        // 已是目标状态
+        ScheduleAction::SystemProxy(_) | ScheduleAction::TunMode(_) => return Ok(()),
+    };
+    feat::patch_verge(&patch, false).await?;
+    handle::Handle::refresh_verge();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn at(y: i32, m: u32, d: u32, h: u32, min: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(y, m, d)
+            .and_then(|date| date.and_hms_opt(h, min, 0))
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn test_parse_cron() {
+        assert!(CronExpr::parse("0 2 * * *").is_ok());
+        assert!(CronExpr::parse("*/15 8-18 * * 1-5").is_ok());
+        assert!(CronExpr::parse("0 2 * *").is_err());
+        assert!(CronExpr::parse("60 2 * * *").is_err());
+        assert!(CronExpr::parse("0 2 * * */0").is_err());
+        assert_eq!(CronExpr::parse("0 0 * * 7").ok(), CronExpr::parse("0 0 * * 0").ok());
+    }
+
+    #[test]
+    fn test_cron_matches() {
+        let weekend = CronExpr::parse("0 0 * * 6,0").ok();
+        // 2026-10-17 是周六
+        assert!(weekend.as_ref().is_some_and(|c| c.matches(&at(2026, 10, 17, 0, 0))));
+        assert!(!weekend.as_ref().is_some_and(|c| c.matches(&at(2026, 10, 16, 0, 0))));
+
+        let step = CronExpr::parse("5/20 * * * *").ok();
+        assert!(step.as_ref().is_some_and(|c| c.matches(&at(2026, 1, 1, 3, 45))));
+        assert!(!step.as_ref().is_some_and(|c| c.matches(&at(2026, 1, 1, 3, 40))));
+    }
+
+    #[test]
+    fn test_cron_next_after() {
+        let nightly = CronExpr::parse("0 2 * * *").ok();
+        assert_eq!(
+            nightly.as_ref().and_then(|c| c.next_after(at(2026, 10, 16, 2, 0))),
+            Some(at(2026, 10, 17, 2, 0))
+        );
+        let monthly = CronExpr::parse("30 8 1 * *").ok();
+        assert_eq!(
+            monthly.as_ref().and_then(|c| c.next_after(at(2026, 12, 15, 9, 0))),
+            Some(at(2027, 1, 1, 8, 30))
+        );
+        let never = CronExpr::parse("0 0 31 2 *").ok();
+        assert_eq!(never.and_then(|c| c.next_after(at(2026, 1, 1, 0, 0))), None);
+    }
+
+    #[test]
+    fn test_cron_last_between() {
+        let nightly = CronExpr::parse("0 2 * * *").ok();
+        let last = |after, until| nightly.as_ref().and_then(|c| c.last_between(after, until));
+        assert_eq!(
+            last(at(2026, 10, 14, 23, 0), at(2026, 10, 16, 9, 0)),
+            Some(at(2026, 10, 16, 2, 0))
+        );
+        assert_eq!(
+            last(at(2026, 10, 16, 1, 0), at(2026, 10, 16, 2, 0)),
+            Some(at(2026, 10, 16, 2, 0))
+        );
+        assert_eq!(last(at(2026, 10, 16, 2, 0), at(2026, 10, 16, 9, 0)), None);
+    }
+}
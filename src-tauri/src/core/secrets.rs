@@ -0,0 +1,539 @@
+//! 敏感配置存入系统凭据库
+//!
+//! macOS 使用钥匙串（`security`），Linux 使用 Secret Service（`secret-tool`），Windows 使用凭据管理器。
+//! 保存配置文件时，外部控制器密钥、订阅地址、WebDAV 与 Telegram 凭据等写入凭据库，
+//! 文件中只保留 `keychain:<名称>` 引用，读取时再替换回原值，内存中始终是原值。
+//! 凭据库不可用或处于便携模式时沿用文件内加密存储。旧版本的配置在启动时由 [`migrate`] 迁移。
+//! 引用无法在其他设备上解析，备份与导出时改为写入内存中的原值。
+
+use crate::{
+    config::{Config, IProfiles, IVerge, profiles_save_file_safe, with_encryption},
+    process::AsyncHandler,
+    utils::{dirs, instance},
+};
+use anyhow::Result;
+use clash_verge_logging::{Type, logging, logging_error};
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde_yaml_ng::{Mapping, Value};
+use smartstring::alias::String;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+const SERVICE: &str = "io.github.clash-verge-rev.clash-verge-rev";
+const REF_PREFIX: &str = "keychain:";
+const CLASH_SECRET: &str = "clash_secret";
+const PROFILE_URL_PREFIX: &str = "profile_url:";
+
+/// 本次运行中已写入或读出的值，避免每次保存都访问凭据库
+static KNOWN: Lazy<Mutex<HashMap<std::string::String, String>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+/// 读取失败的条目，保存时写回引用，避免凭据库暂时不可用时丢失
+static UNRESOLVED: Lazy<Mutex<HashSet<std::string::String>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+/// 读取配置时发现了仍以原值保存的敏感字段
+static NEEDS_MIGRATION: AtomicBool = AtomicBool::new(false);
+
+fn service() -> std::string::String {
+    instance::suffixed(SERVICE, '.')
+}
+
+fn is_enabled() -> bool {
+    !dirs::is_portable() && platform::is_available()
+}
+
+fn reference(account: &str) -> String {
+    format!("{REF_PREFIX}{account}").into()
+}
+
+fn lookup(account: &str) -> Option<String> {
+    if let Some(value) = KNOWN.lock().get(account) {
+        return Some(value.clone());
+    }
+    match platform::get(&service(), account) {
+        Ok(Some(value)) => {
+            KNOWN.lock().insert(account.to_owned(), value.clone());
+            Some(value)
+        }
+        Ok(None) => {
+            logging!(warn, Type::Config, "Secret {account} not found in the system keychain");
+            None
+        }
+        Err(err) => {
+            logging!(
+                warn,
+                Type::Config,
+                "Failed to read {account} from the system keychain: {err}"
+            );
+            UNRESOLVED.lock().insert(account.to_owned());
+            None
+        }
+    }
+}
+
+/// 写入凭据库并返回引用，失败时返回 `None`，调用方保留原值
+fn store(account: &str, value: &str) -> Option<String> {
+    if !is_enabled() {
+        return None;
+    }
+    if KNOWN.lock().get(account).is_some_and(|known| known == value) {
+        return Some(reference(account));
+    }
+    match platform::set(&service(), account, value) {
+        Ok(()) => {
+            KNOWN.lock().insert(account.to_owned(), value.into());
+            UNRESOLVED.lock().remove(account);
+            Some(reference(account))
+        }
+        Err(err) => {
+            logging!(
+                warn,
+                Type::Config,
+                "Failed to store {account} in the system keychain: {err}"
+            );
+            None
+        }
+    }
+}
+
+fn remove(account: &str) {
+    if KNOWN.lock().remove(account).is_some() {
+        logging_error!(Type::Config, platform::delete(&service(), account));
+    }
+}
+
+/// 引用替换为原值；仍为原值的非空字段标记为待迁移
+fn resolve_value(value: &str) -> Option<String> {
+    match value.strip_prefix(REF_PREFIX) {
+        Some(account) => lookup(account),
+        None => {
+            if !value.is_empty() && is_enabled() {
+                NEEDS_MIGRATION.store(true, Ordering::Relaxed);
+            }
+            Some(value.into())
+        }
+    }
+}
+
+fn resolve_field(field: &mut Option<String>) {
+    if let Some(value) = field.take() {
+        *field = resolve_value(&value);
+    }
+}
+
+fn externalize_field(account: &str, field: &mut Option<String>) {
+    match field.as_deref() {
+        Some(value) if !value.is_empty() && !value.starts_with(REF_PREFIX) => {
+            if let Some(reference) = store(account, value) {
+                *field = Some(reference);
+            }
+        }
+        Some(_) => {}
+        None if UNRESOLVED.lock().contains(account) => *field = Some(reference(account)),
+        None => remove(account),
+    }
+}
+
+fn verge_fields(verge: &mut IVerge) -> [(&'static str, &mut Option<String>); 6] {
+    [
+        ("webdav_url", &mut verge.webdav_url),
+        ("webdav_username", &mut verge.webdav_username),
+        ("webdav_password", &mut verge.webdav_password),
+        ("telegram_bot_token", &mut verge.telegram_bot_token),
+        ("backup_password", &mut verge.backup_password),
+        ("control_api_token", &mut verge.control_api_token),
+    ]
+}
+
+fn profile_account(uid: &str) -> std::string::String {
+    format!("{PROFILE_URL_PREFIX}{uid}")
+}
+
+/// 读取 verge.yaml 后调用
+pub async fn resolve_verge(verge: IVerge) -> Result<IVerge> {
+    AsyncHandler::spawn_blocking(move || {
+        let mut verge = verge;
+        for (_, field) in verge_fields(&mut verge) {
+            resolve_field(field);
+        }
+        verge
+    })
+    .await
+    .map_err(Into::into)
+}
+
+/// 返回写入 verge.yaml 的副本
+pub async fn externalize_verge(verge: &IVerge) -> Result<IVerge> {
+    let mut verge = verge.clone();
+    AsyncHandler::spawn_blocking(move || {
+        for (account, field) in verge_fields(&mut verge) {
+            externalize_field(account, field);
+        }
+        verge
+    })
+    .await
+    .map_err(Into::into)
+}
+
+/// 读取 config.yaml 后调用
+pub async fn resolve_clash(map: Mapping) -> Result<Mapping> {
+    AsyncHandler::spawn_blocking(move || {
+        let mut map = map;
+        if let Some(Value::String(secret)) = map.get("secret")
+            && let Some(value) = resolve_value(secret)
+        {
+            map.insert("secret".into(), value.as_str().into());
+        }
+        map
+    })
+    .await
+    .map_err(Into::into)
+}
+
+/// 返回写入 config.yaml 的副本
+pub async fn externalize_clash(map: &Mapping) -> Result<Mapping> {
+    let mut map = map.clone();
+    AsyncHandler::spawn_blocking(move || {
+        let mut secret = map.get("secret").and_then(Value::as_str).map(String::from);
+        externalize_field(CLASH_SECRET, &mut secret);
+        if let Some(secret) = secret {
+            map.insert("secret".into(), secret.as_str().into());
+        }
+        map
+    })
+    .await
+    .map_err(Into::into)
+}
+
+/// 读取 profiles.yaml 后调用
+pub async fn resolve_profiles(profiles: IProfiles) -> Result<IProfiles> {
+    AsyncHandler::spawn_blocking(move || {
+        let mut profiles = profiles;
+        for item in profiles.items.iter_mut().flatten() {
+            resolve_field(&mut item.url);
+        }
+        profiles
+    })
+    .await
+    .map_err(Into::into)
+}
+
+/// 返回写入 profiles.yaml 的副本，并清理已删除订阅的条目
+pub async fn externalize_profiles(profiles: &IProfiles) -> Result<IProfiles> {
+    let mut profiles = profiles.clone();
+    AsyncHandler::spawn_blocking(move || {
+        let mut accounts = Vec::new();
+        for item in profiles.items.iter_mut().flatten() {
+            if let Some(uid) = item.uid.as_deref() {
+                let account = profile_account(uid);
+                externalize_field(&account, &mut item.url);
+                accounts.push(account);
+            }
+        }
+        let stale: Vec<_> = KNOWN
+            .lock()
+            .keys()
+            .filter(|account| account.starts_with(PROFILE_URL_PREFIX) && !accounts.contains(account))
+            .cloned()
+            .collect();
+        stale.iter().for_each(|account| remove(account));
+        profiles
+    })
+    .await
+    .map_err(Into::into)
+}
+
+/// 内存中的 verge 设置按 verge.yaml 的格式序列化，敏感字段为原值而非引用，供备份与导出使用
+pub async fn resolved_verge_mapping() -> Result<Mapping> {
+    let verge = Config::verge().await.latest_arc();
+    let text = with_encryption(|| async { serde_yaml_ng::to_string(&*verge) }).await?;
+    Ok(serde_yaml_ng::from_str(&text)?)
+}
+
+/// 启动时将仍以原值保存在配置文件中的敏感字段迁移到凭据库
+pub async fn migrate() {
+    if !NEEDS_MIGRATION.swap(false, Ordering::Relaxed) {
+        return;
+    }
+    logging!(info, Type::Config, "Moving sensitive settings into the system keychain");
+    logging_error!(Type::Config, Config::verge().await.latest_arc().save_file().await);
+    logging_error!(Type::Config, Config::clash().await.latest_arc().save_config().await);
+    logging_error!(Type::Config, profiles_save_file_safe().await);
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use anyhow::{Result, bail};
+    use smartstring::alias::String;
+    use std::{
+        io::Write as _,
+        process::{Command, Stdio},
+    };
+
+    pub const fn is_available() -> bool {
+        true
+    }
+
+    /// `security` 未找到条目时的退出码
+    const NOT_FOUND: i32 = 44;
+
+    fn quote(value: &str) -> std::string::String {
+        format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+    }
+
+    /// 通过交互模式从标准输入传入命令，避免密码出现在进程参数中
+    fn run_interactive(command: &str) -> Result<()> {
+        let mut child = Command::new("security")
+            .arg("-i")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        if let Some(mut stdin) = child.stdin.take() {
+            writeln!(stdin, "{command}")?;
+        }
+        let output = child.wait_with_output()?;
+        if !output.status.success() || !output.stderr.is_empty() {
+            bail!(
+                "security: {}",
+                std::string::String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(())
+    }
+
+    pub fn get(service: &str, account: &str) -> Result<Option<String>> {
+        let output = Command::new("security")
+            .args(["find-generic-password", "-s", service, "-a", account, "-w"])
+            .output()?;
+        match output.status.code() {
+            Some(0) => Ok(Some(
+                std::string::String::from_utf8_lossy(&output.stdout)
+                    .trim_end_matches('\n')
+                    .into(),
+            )),
+            Some(NOT_FOUND) => Ok(None),
+            _ => bail!(
+                "security: {}",
+                std::string::String::from_utf8_lossy(&output.stderr).trim()
+            ),
+        }
+    }
+
+    pub fn set(service: &str, account: &str, value: &str) -> Result<()> {
+        run_interactive(&format!(
+            "add-generic-password -U -s {} -a {} -w {}",
+            quote(service),
+            quote(account),
+            quote(value)
+        ))
+    }
+
+    pub fn delete(service: &str, account: &str) -> Result<()> {
+        let status = Command::new("security")
+            .args(["delete-generic-password", "-s", service, "-a", account])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()?;
+        if !status.success() && status.code() != Some(NOT_FOUND) {
+            bail!("failed to delete {account} from the keychain");
+        }
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use anyhow::{Result, bail};
+    use clash_verge_logging::{Type, logging};
+    use once_cell::sync::Lazy;
+    use smartstring::alias::String;
+    use std::{
+        io::Write as _,
+        process::{Command, Stdio},
+    };
+
+    /// 需要安装 `secret-tool` 且会话中有 Secret Service 提供者，探测一次后缓存结果
+    pub fn is_available() -> bool {
+        static AVAILABLE: Lazy<bool> = Lazy::new(|| {
+            let available = get("clash-verge-probe", "probe").is_ok();
+            if !available {
+                logging!(
+                    info,
+                    Type::Config,
+                    "Secret Service is not available, sensitive settings stay in the config files"
+                );
+            }
+            available
+        });
+        *AVAILABLE
+    }
+
+    pub fn get(service: &str, account: &str) -> Result<Option<String>> {
+        let output = Command::new("secret-tool")
+            .args(["lookup", "service", service, "account", account])
+            .output()?;
+        // secret-tool 未找到条目时退出码为 1 且没有错误输出
+        if output.status.success() {
+            Ok(Some(std::string::String::from_utf8_lossy(&output.stdout).into()))
+        } else if output.stderr.is_empty() {
+            Ok(None)
+        } else {
+            bail!(
+                "secret-tool: {}",
+                std::string::String::from_utf8_lossy(&output.stderr).trim()
+            )
+        }
+    }
+
+    /// 密码从标准输入传入，避免出现在进程参数中
+    pub fn set(service: &str, account: &str, value: &str) -> Result<()> {
+        let mut child = Command::new("secret-tool")
+            .args(["store", "--label", &format!("Clash Verge {account}")])
+            .args(["service", service, "account", account])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(value.as_bytes())?;
+        }
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            bail!(
+                "secret-tool: {}",
+                std::string::String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(())
+    }
+
+    pub fn delete(service: &str, account: &str) -> Result<()> {
+        let status = Command::new("secret-tool")
+            .args(["clear", "service", service, "account", account])
+            .stderr(Stdio::null())
+            .status()?;
+        if !status.success() {
+            bail!("failed to delete {account} from the secret service");
+        }
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use anyhow::{Result, bail};
+    use smartstring::alias::String;
+    use std::{os::windows::ffi::OsStrExt as _, ptr};
+    use winapi::{
+        shared::winerror::ERROR_NOT_FOUND,
+        um::{
+            errhandlingapi::GetLastError,
+            wincred::{
+                CRED_PERSIST_LOCAL_MACHINE, CRED_TYPE_GENERIC, CREDENTIALW, CredDeleteW, CredFree, CredReadW,
+                CredWriteW, PCREDENTIALW,
+            },
+        },
+    };
+
+    pub const fn is_available() -> bool {
+        true
+    }
+
+    fn target(service: &str, account: &str) -> Vec<u16> {
+        std::ffi::OsStr::new(&format!("{service}/{account}"))
+            .encode_wide()
+            .chain(std::iter::once(0))
+            .collect()
+    }
+
+    pub fn get(service: &str, account: &str) -> Result<Option<String>> {
+        let target = target(service, account);
+        let mut credential: PCREDENTIALW = ptr::null_mut();
+        // SAFETY: target 以 0 结尾，成功时 credential 由系统分配并在下方释放
+        let ok = unsafe { CredReadW(target.as_ptr(), CRED_TYPE_GENERIC, 0, &raw mut credential) };
+        if ok == 0 {
+            // SAFETY: 紧接失败的调用读取错误码
+            if unsafe { GetLastError() } == ERROR_NOT_FOUND {
+                return Ok(None);
+            }
+            bail!("CredReadW failed: {}", std::io::Error::last_os_error());
+        }
+        // SAFETY: credential 非空，CredentialBlob 指向 CredentialBlobSize 字节
+        let value = unsafe {
+            let blob =
+                std::slice::from_raw_parts((*credential).CredentialBlob, (*credential).CredentialBlobSize as usize);
+            let value = std::string::String::from_utf8_lossy(blob).into();
+            CredFree(credential.cast());
+            value
+        };
+        Ok(Some(value))
+    }
+
+    pub fn set(service: &str, account: &str, value: &str) -> Result<()> {
+        let mut target = target(service, account);
+        let mut blob = value.as_bytes().to_vec();
+        // SAFETY: CREDENTIALW 是纯数据结构，全零是合法值
+        let mut credential: CREDENTIALW = unsafe { std::mem::zeroed() };
+        credential.Type = CRED_TYPE_GENERIC;
+        credential.TargetName = target.as_mut_ptr();
+        credential.CredentialBlobSize = u32::try_from(blob.len())?;
+        credential.CredentialBlob = blob.as_mut_ptr();
+        credential.Persist = CRED_PERSIST_LOCAL_MACHINE;
+        // SAFETY: credential 引用的缓冲区在调用期间保持有效
+        if unsafe { CredWriteW(&raw mut credential, 0) } == 0 {
+            bail!("CredWriteW failed: {}", std::io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    pub fn delete(service: &str, account: &str) -> Result<()> {
+        let target = target(service, account);
+        // SAFETY: target 以 0 结尾
+        if unsafe { CredDeleteW(target.as_ptr(), CRED_TYPE_GENERIC, 0) } == 0 {
+            // SAFETY: 紧接失败的调用读取错误码
+            if unsafe { GetLastError() } != ERROR_NOT_FOUND {
+                bail!("CredDeleteW failed: {}", std::io::Error::last_os_error());
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux", target_os = "windows")))]
+mod platform {
+    use anyhow::{Result, bail};
+    use smartstring::alias::String;
+
+    pub const fn is_available() -> bool {
+        false
+    }
+
+    pub fn get(_service: &str, _account: &str) -> Result<Option<String>> {
+        bail!("system keychain is not supported on this platform")
+    }
+
+    pub fn set(_service: &str, _account: &str, _value: &str) -> Result<()> {
+        bail!("system keychain is not supported on this platform")
+    }
+
+    pub fn delete(_service: &str, _account: &str) -> Result<()> {
+        bail!("system keychain is not supported on this platform")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_externalize_keeps_references() {
+        let mut field = Some(reference("webdav_password"));
+        externalize_field("webdav_password", &mut field);
+        assert_eq!(field.as_deref(), Some("keychain:webdav_password"));
+
+        let mut empty = Some(String::new());
+        externalize_field("webdav_password", &mut empty);
+        assert_eq!(empty.as_deref(), Some(""));
+    }
+}
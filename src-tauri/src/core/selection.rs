@@ -0,0 +1,211 @@
+//! 节点选择记忆
+//!
+//! 按订阅 uid 记录每个代理组最后选择的节点，开启 `selection_per_network` 后再按 Wi-Fi 名称分开记录，
+//! 保存在 [`SELECTION_FILE`]。订阅更新或内核重启后内核会回到代理组的第一个节点，此时按记录恢复；
+//! 记录的节点已不在代理组中时保持内核的选择。
+
+use crate::{
+    config::Config,
+    core::{audit::AuditLog, handle, network_monitor::NetworkMonitor, tray},
+    process::AsyncHandler,
+    singleton,
+    utils::{dirs, help},
+};
+use anyhow::Result;
+use clash_verge_logging::{Type, logging, logging_error};
+use smartstring::alias::String;
+use std::{collections::BTreeMap, time::Duration};
+use tauri::Emitter as _;
+use tokio::sync::Mutex;
+
+const SELECTION_FILE: &str = "selections.yaml";
+const READY_CHECK_ATTEMPTS: u32 = 20;
+const READY_CHECK_INTERVAL: Duration = Duration::from_millis(500);
+
+/// 记录键 -> 代理组 -> 节点
+type Store = BTreeMap<String, BTreeMap<String, String>>;
+
+/// 代理组名、当前节点、可选节点
+type GroupState = (String, Option<String>, Vec<String>);
+
+fn store_key(uid: &str, ssid: Option<&str>) -> String {
+    match ssid {
+        Some(ssid) => format!("{uid}@{ssid}").into(),
+        None => uid.into(),
+    }
+}
+
+/// 需要切换的代理组与节点
+fn pending_selections(saved: &BTreeMap<String, String>, groups: &[GroupState]) -> Vec<(String, String)> {
+    groups
+        .iter()
+        .filter_map(|(group, now, all)| {
+            let node = saved.get(group)?;
+            (now.as_ref() != Some(node) && all.contains(node)).then(|| (group.clone(), node.clone()))
+        })
+        .collect()
+}
+
+pub struct SelectionMemory {
+    store: Mutex<Option<Store>>,
+}
+
+singleton!(SelectionMemory, SELECTION_MEMORY);
+
+impl SelectionMemory {
+    fn new() -> Self {
+        Self {
+            store: Mutex::new(None),
+        }
+    }
+
+    /// 当前订阅的记录键，按网络记录时附带 Wi-Fi 名称
+    async fn current_keys() -> Option<(String, Option<String>)> {
+        let uid = Config::profiles().await.latest_arc().current.clone()?;
+        let per_network = Config::verge()
+            .await
+            .latest_arc()
+            .selection_per_network
+            .unwrap_or(false);
+        let network_key = per_network
+            .then(|| NetworkMonitor::global().current())
+            .flatten()
+            .and_then(|state| state.ssid)
+            .map(|ssid| store_key(&uid, Some(&ssid)));
+        Some((store_key(&uid, None), network_key))
+    }
+
+    async fn load() -> Store {
+        match dirs::app_home_dir() {
+            Ok(dir) => help::read_yaml(&dir.join(SELECTION_FILE)).await.unwrap_or_default(),
+            Err(_) => Store::new(),
+        }
+    }
+
+    async fn save(store: &Store) -> Result<()> {
+        let path = dirs::app_home_dir()?.join(SELECTION_FILE);
+        help::save_yaml(&path, store, Some("# Clash Verge Proxy Selections")).await
+    }
+
+    /// 记录一次节点切换，同时写入审计日志
+    pub async fn record(&self, group: &str, node: &str) {
+        let Some((profile_key, network_key)) = Self::current_keys().await else {
+            return;
+        };
+        let mut store = self.store.lock().await;
+        if store.is_none() {
+            *store = Some(Self::load().await);
+        }
+        let Some(entries) = store.as_mut() else {
+            return;
+        };
+        let mut previous = None;
+        for key in std::iter::once(profile_key).chain(network_key) {
+            let old = entries.entry(key).or_default().insert(group.into(), node.into());
+            previous = previous.or(old);
+        }
+        logging_error!(Type::Config, Self::save(entries).await);
+        drop(store);
+        AuditLog::record(
+            "node.switch",
+            Some(group),
+            previous.map(|old| old.as_str().into()),
+            Some(node.into()),
+        );
+    }
+
+    /// 按记录恢复当前订阅的节点选择，按网络记录时优先使用当前网络的记录
+    pub async fn restore(&self) -> Result<()> {
+        let Some((profile_key, network_key)) = Self::current_keys().await else {
+            return Ok(());
+        };
+        let saved = {
+            let mut store = self.store.lock().await;
+            if store.is_none() {
+                *store = Some(Self::load().await);
+            }
+            let entries = store.as_ref();
+            let mut saved = entries
+                .and_then(|entries| entries.get(&profile_key))
+                .cloned()
+                .unwrap_or_default();
+            if let Some(network) = network_key.and_then(|key| entries.and_then(|entries| entries.get(&key))) {
+                saved.extend(network.iter().map(|(group, node)| (group.clone(), node.clone())));
+            }
+            saved
+        };
+        if saved.is_empty() {
+            return Ok(());
+        }
+
+        let mihomo = handle::Handle::mihomo().await;
+        let proxies = mihomo.get_proxies().await?;
+        let groups: Vec<GroupState> = proxies
+            .proxies
+            .iter()
+            .filter_map(|(name, proxy)| {
+                let all = proxy.all.as_ref()?;
+                Some((
+                    name.as_str().into(),
+                    proxy.now.as_deref().map(Into::into),
+                    all.iter().map(|node| node.as_str().into()).collect(),
+                ))
+            })
+            .collect();
+        let pending = pending_selections(&saved, &groups);
+        if pending.is_empty() {
+            return Ok(());
+        }
+        for (group, node) in &pending {
+            if let Err(err) = mihomo.select_node_for_group(group, node).await {
+                logging!(warn, Type::Core, "Failed to restore {group} -> {node}: {err}");
+            }
+        }
+        drop(mihomo);
+        logging!(info, Type::Core, "Restored {} proxy selections", pending.len());
+        let _ = handle::Handle::app_handle().emit("verge://refresh-proxy-config", ());
+        logging_error!(Type::Tray, tray::Tray::global().update_menu().await);
+        Ok(())
+    }
+
+    /// 等待内核接口可用后恢复节点选择
+    pub fn restore_when_ready(&'static self) {
+        AsyncHandler::spawn(move || async move {
+            for _ in 0..READY_CHECK_ATTEMPTS {
+                if handle::Handle::mihomo().await.get_version().await.is_ok() {
+                    logging_error!(Type::Core, self.restore().await);
+                    return;
+                }
+                tokio::time::sleep(READY_CHECK_INTERVAL).await;
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pending_selections() {
+        assert_eq!(store_key("abc", Some("Home")), "abc@Home");
+        assert_eq!(store_key("abc", None), "abc");
+
+        let saved: BTreeMap<String, String> = [("Proxy".into(), "SG-01".into()), ("Auto".into(), "Gone".into())]
+            .into_iter()
+            .collect();
+        let groups: Vec<GroupState> = vec![
+            (
+                "Proxy".into(),
+                Some("HK-01".into()),
+                vec!["HK-01".into(), "SG-01".into()],
+            ),
+            ("Auto".into(), Some("HK-01".into()), vec!["HK-01".into()]),
+            ("Other".into(), None, vec!["SG-01".into()]),
+        ];
+        assert_eq!(
+            pending_selections(&saved, &groups),
+            vec![(String::from("Proxy"), String::from("SG-01"))]
+        );
+    }
+}
@@ -1,6 +1,11 @@
 use crate::{
     config::{Config, IClashTemp},
-    core::tray::Tray,
+    core::{
+        CoreManager, handle,
+        manager::{RunningMode, core_binary_path},
+        tray::Tray,
+    },
+    process::AsyncHandler,
     utils::{dirs, init::service_writer_config},
 };
 use anyhow::{Context as _, Result, bail};
@@ -8,14 +13,21 @@ use clash_verge_logging::{Type, logging, logging_error};
 use clash_verge_service_ipc::CoreConfig;
 use compact_str::CompactString;
 use once_cell::sync::Lazy;
+use serde::Serialize;
 use std::{
-    env::current_exe,
     path::{Path, PathBuf},
     process::Command as StdCommand,
+    sync::atomic::{AtomicBool, Ordering},
     time::Duration,
 };
+use tauri::Emitter as _;
 use tokio::sync::Mutex;
 
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+const SERVICE_HEALTH_EVENT: &str = "service-health";
+
+static HEALTH_CHECK_STARTED: AtomicBool = AtomicBool::new(false);
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ServiceStatus {
     Ready,
@@ -241,7 +253,7 @@ async fn reinstall_service() -> Result<()> {
 }
 
 #[cfg(target_os = "linux")]
-fn linux_running_as_root() -> bool {
+pub(crate) fn linux_running_as_root() -> bool {
     use crate::core::handle;
     use tauri_plugin_clash_verge_sysinfo::is_current_app_handle_admin;
     let app_handle = handle::Handle::app_handle();
@@ -388,12 +400,8 @@ pub async fn check_service_needs_reinstall() -> Result<bool> {
 pub(super) async fn start_with_existing_service(config_file: &PathBuf) -> Result<()> {
     logging!(info, Type::Service, "尝试使用现有服务启动核心");
 
-    let verge_config = Config::verge().await;
-    let clash_core = verge_config.latest_arc().get_valid_clash_core();
-    drop(verge_config);
-
-    let bin_ext = if cfg!(windows) { ".exe" } else { "" };
-    let bin_path = current_exe()?.with_file_name(format!("{clash_core}{bin_ext}"));
+    let verge = Config::verge().await.latest_arc();
+    let bin_path = core_binary_path(&verge, &verge.get_valid_clash_core())?;
 
     let payload = clash_verge_service_ipc::ClashConfig {
         core_config: CoreConfig {
@@ -581,4 +589,79 @@ impl ServiceManager {
     }
 }
 
+/// 服务的安装与运行状态，供设置页展示
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct ServiceInfo {
+    /// 服务的 IPC 端点存在
+    pub installed: bool,
+    /// 能够通过 IPC 连接服务
+    pub available: bool,
+    pub version: Option<String>,
+    pub expected_version: String,
+    pub needs_reinstall: bool,
+    /// 内核当前由服务托管运行
+    pub in_use: bool,
+    pub error: Option<String>,
+}
+
+/// 查询服务状态，不会触发安装或提权
+pub async fn service_info() -> ServiceInfo {
+    let installed = is_service_ipc_path_exists();
+    let in_use = *CoreManager::global().get_running_mode() == RunningMode::Service;
+    let (version, error) = if installed {
+        match check_service_version().await {
+            Ok(version) => (Some(version), None),
+            Err(err) => (None, Some(err.to_string())),
+        }
+    } else {
+        (None, None)
+    };
+    let expected_version = clash_verge_service_ipc::VERSION.to_string();
+    ServiceInfo {
+        installed,
+        available: version.is_some(),
+        needs_reinstall: version.as_ref().is_some_and(|v| *v != expected_version),
+        version,
+        expected_version,
+        in_use,
+        error,
+    }
+}
+
+/// 定期检查已安装服务的连通性，状态变化时发送 `service-health` 事件
+///
+/// 服务在后台常驻，TUN 的开关与路由设置都经由服务完成，只在安装、卸载时需要提权。
+/// 服务掉线时内核会由看门狗重新拉起，这里只负责通知前端。
+pub fn init_health_check() {
+    if HEALTH_CHECK_STARTED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    AsyncHandler::spawn(|| async {
+        let mut last_healthy: Option<bool> = None;
+        loop {
+            tokio::time::sleep(HEALTH_CHECK_INTERVAL).await;
+            if handle::Handle::global().is_exiting() {
+                break;
+            }
+            if !is_service_ipc_path_exists() && last_healthy.is_none() {
+                continue;
+            }
+            let healthy = clash_verge_service_ipc::connect().await.is_ok();
+            if last_healthy == Some(healthy) {
+                continue;
+            }
+            if last_healthy.is_some() {
+                if healthy {
+                    logging!(info, Type::Service, "Service is reachable again");
+                } else {
+                    logging!(warn, Type::Service, "Service health check failed");
+                }
+                let info = service_info().await;
+                let _ = handle::Handle::app_handle().emit(SERVICE_HEALTH_EVENT, &info);
+            }
+            last_healthy = Some(healthy);
+        }
+    });
+}
+
 pub static SERVICE_MANAGER: Lazy<Mutex<ServiceManager>> = Lazy::new(|| Mutex::new(ServiceManager::default()));
@@ -0,0 +1,564 @@
+//! 节点分享链接与 mihomo proxy 映射的互相转换
+//!
+//! 支持 `ss://`、`vmess://`、`vless://`、`trojan://` 与 `hysteria2://`（`hy2://`），
+//! 整段 base64 编码的订阅内容会先解码再逐行解析；导出时统一使用 `hysteria2://`。
+
+use anyhow::{Context as _, Result, anyhow, bail};
+use base64::{
+    Engine as _,
+    engine::general_purpose::{STANDARD, STANDARD_NO_PAD, URL_SAFE, URL_SAFE_NO_PAD},
+};
+use percent_encoding::percent_decode_str;
+use serde_json::Value as Json;
+use serde_yaml_ng::{Mapping, Value};
+use std::collections::HashMap;
+use tauri::Url;
+
+type Query = HashMap<std::string::String, std::string::String>;
+
+pub const SUPPORTED_SCHEMES: [&str; 6] = ["ss", "vmess", "vless", "trojan", "hysteria2", "hy2"];
+
+/// 尝试各种 base64 变体解码，忽略空白
+pub fn decode_base64(input: &str) -> Option<std::string::String> {
+    let compact: std::string::String = input.chars().filter(|c| !c.is_whitespace()).collect();
+    [STANDARD, STANDARD_NO_PAD, URL_SAFE, URL_SAFE_NO_PAD]
+        .iter()
+        .find_map(|engine| engine.decode(&compact).ok())
+        .and_then(|bytes| std::string::String::from_utf8(bytes).ok())
+}
+
+/// 文本中是否包含可识别的分享链接
+pub fn is_share_link(line: &str) -> bool {
+    line.trim()
+        .split_once("://")
+        .is_some_and(|(scheme, _)| SUPPORTED_SCHEMES.contains(&scheme.to_ascii_lowercase().as_str()))
+}
+
+/// 解析多行分享链接，无法识别的行被忽略；整段为 base64 时先解码
+pub fn parse_share_links(text: &str) -> Vec<Mapping> {
+    let text = if text.lines().any(is_share_link) {
+        text.to_owned()
+    } else {
+        match decode_base64(text) {
+            Some(decoded) => decoded,
+            None => return Vec::new(),
+        }
+    };
+
+    text.lines()
+        .filter(|line| is_share_link(line))
+        .filter_map(|line| parse_share_link(line.trim()).ok())
+        .collect()
+}
+
+/// 解析单条分享链接
+pub fn parse_share_link(link: &str) -> Result<Mapping> {
+    let (scheme, rest) = link.split_once("://").ok_or_else(|| anyhow!("not a share link"))?;
+    match scheme.to_ascii_lowercase().as_str() {
+        "ss" => parse_ss(rest),
+        "vmess" => parse_vmess(rest),
+        "vless" => parse_vless(&Url::parse(link)?),
+        "trojan" => parse_trojan(&Url::parse(link)?),
+        "hysteria2" | "hy2" => parse_hysteria2(&Url::parse(link)?),
+        other => bail!("unsupported scheme: {other}"),
+    }
+}
+
+fn decode(s: &str) -> std::string::String {
+    percent_decode_str(s).decode_utf8_lossy().into_owned()
+}
+
+pub(super) fn insert(map: &mut Mapping, key: &str, value: impl Into<Value>) {
+    map.insert(key.into(), value.into());
+}
+
+pub(super) fn base_proxy(name: &str, kind: &str, server: &str, port: u16) -> Mapping {
+    let mut map = Mapping::new();
+    insert(&mut map, "name", name);
+    insert(&mut map, "type", kind);
+    insert(&mut map, "server", server);
+    insert(&mut map, "port", u64::from(port));
+    map
+}
+
+/// WebSocket 传输选项，路径缺省为 `/`
+pub(super) fn ws_opts(path: Option<&str>, host: Option<&str>) -> Mapping {
+    let mut opts = Mapping::new();
+    insert(&mut opts, "path", path.unwrap_or("/"));
+    if let Some(host) = host {
+        let mut headers = Mapping::new();
+        insert(&mut headers, "Host", host);
+        insert(&mut opts, "headers", headers);
+    }
+    opts
+}
+
+fn url_parts(url: &Url) -> Result<(std::string::String, std::string::String, u16, Query)> {
+    let server = url
+        .host_str()
+        .ok_or_else(|| anyhow!("missing server"))?
+        .trim_matches(['[', ']'])
+        .to_owned();
+    let port = url.port().ok_or_else(|| anyhow!("missing port"))?;
+    let name = url.fragment().map(decode).unwrap_or_else(|| format!("{server}:{port}"));
+    let query = url
+        .query_pairs()
+        .map(|(k, v)| (k.into_owned(), v.into_owned()))
+        .collect();
+    Ok((name, server, port, query))
+}
+
+/// `type=ws|grpc|h2` 等传输参数，vless 与 trojan 共用
+fn apply_transport(map: &mut Mapping, query: &Query) {
+    let network = query.get("type").map_or("tcp", std::string::String::as_str);
+    match network {
+        "ws" => {
+            insert(map, "network", "ws");
+            insert(
+                map,
+                "ws-opts",
+                ws_opts(
+                    query.get("path").map(std::string::String::as_str),
+                    query.get("host").map(std::string::String::as_str),
+                ),
+            );
+        }
+        "grpc" => {
+            insert(map, "network", "grpc");
+            let mut opts = Mapping::new();
+            insert(
+                &mut opts,
+                "grpc-service-name",
+                query.get("serviceName").map_or("", |s| s.as_str()),
+            );
+            insert(map, "grpc-opts", opts);
+        }
+        "h2" | "http" => insert(map, "network", network),
+        _ => {}
+    }
+}
+
+fn apply_tls(map: &mut Mapping, query: &Query, sni_key: &str) {
+    if let Some(sni) = query.get("sni").or_else(|| query.get("peer")) {
+        insert(map, sni_key, sni.as_str());
+    }
+    if let Some(alpn) = query.get("alpn") {
+        let alpn: Vec<Value> = alpn.split(',').map(Value::from).collect();
+        insert(map, "alpn", alpn);
+    }
+    if let Some(fp) = query.get("fp") {
+        insert(map, "client-fingerprint", fp.as_str());
+    }
+    if query
+        .get("allowInsecure")
+        .or_else(|| query.get("insecure"))
+        .is_some_and(|v| v == "1" || v == "true")
+    {
+        insert(map, "skip-cert-verify", true);
+    }
+}
+
+/// SIP002：`ss://base64(method:password)@host:port#name`，
+/// 以及旧格式 `ss://base64(method:password@host:port)#name`
+fn parse_ss(rest: &str) -> Result<Mapping> {
+    let (body, name) = match rest.split_once('#') {
+        Some((body, name)) => (body, Some(decode(name))),
+        None => (rest, None),
+    };
+    let body = body.split('?').next().unwrap_or(body).trim_end_matches('/');
+
+    let (userinfo, host) = match body.rsplit_once('@') {
+        Some((userinfo, host)) => {
+            let userinfo = decode(userinfo);
+            (decode_base64(&userinfo).unwrap_or(userinfo), host.to_owned())
+        }
+        None => {
+            let decoded = decode_base64(body).ok_or_else(|| anyhow!("invalid ss link"))?;
+            let (userinfo, host) = decoded.rsplit_once('@').ok_or_else(|| anyhow!("invalid ss link"))?;
+            (userinfo.to_owned(), host.to_owned())
+        }
+    };
+    let (cipher, password) = userinfo.split_once(':').ok_or_else(|| anyhow!("invalid ss userinfo"))?;
+    let (server, port) = host.rsplit_once(':').ok_or_else(|| anyhow!("missing port"))?;
+    let port: u16 = port.parse().context("invalid port")?;
+    let server = server.trim_matches(['[', ']']);
+
+    let name = name.unwrap_or_else(|| format!("{server}:{port}"));
+    let mut map = base_proxy(&name, "ss", server, port);
+    insert(&mut map, "cipher", cipher);
+    insert(&mut map, "password", password);
+    insert(&mut map, "udp", true);
+    Ok(map)
+}
+
+/// v2rayN 格式：`vmess://base64(json)`
+fn parse_vmess(rest: &str) -> Result<Mapping> {
+    let decoded = decode_base64(rest).ok_or_else(|| anyhow!("invalid vmess link"))?;
+    let json: Json = serde_json::from_str(&decoded).context("invalid vmess json")?;
+    let field = |key: &str| match &json[key] {
+        Json::String(s) => Some(s.clone()),
+        Json::Number(n) => Some(n.to_string()),
+        _ => None,
+    };
+
+    let server = field("add").ok_or_else(|| anyhow!("missing server"))?;
+    let port: u16 = field("port").ok_or_else(|| anyhow!("missing port"))?.parse()?;
+    let name = field("ps").unwrap_or_else(|| format!("{server}:{port}"));
+    let mut map = base_proxy(&name, "vmess", &server, port);
+    insert(&mut map, "uuid", field("id").ok_or_else(|| anyhow!("missing uuid"))?);
+    insert(
+        &mut map,
+        "alterId",
+        field("aid").and_then(|a| a.parse::<u64>().ok()).unwrap_or(0),
+    );
+    insert(
+        &mut map,
+        "cipher",
+        field("scy").filter(|s| !s.is_empty()).unwrap_or_else(|| "auto".into()),
+    );
+    insert(&mut map, "udp", true);
+
+    if field("tls").is_some_and(|t| t == "tls") {
+        insert(&mut map, "tls", true);
+        if let Some(sni) = field("sni").filter(|s| !s.is_empty()) {
+            insert(&mut map, "servername", sni);
+        }
+    }
+
+    let mut query = HashMap::new();
+    if let Some(net) = field("net") {
+        query.insert("type".to_owned(), net);
+    }
+    if let Some(host) = field("host").filter(|h| !h.is_empty()) {
+        query.insert("host".to_owned(), host);
+    }
+    if let Some(path) = field("path").filter(|p| !p.is_empty()) {
+        // grpc 的 serviceName 也放在 path 字段
+        query.insert("serviceName".to_owned(), path.clone());
+        query.insert("path".to_owned(), path);
+    }
+    apply_transport(&mut map, &query);
+    Ok(map)
+}
+
+fn parse_vless(url: &Url) -> Result<Mapping> {
+    let (name, server, port, query) = url_parts(url)?;
+    let mut map = base_proxy(&name, "vless", &server, port);
+    insert(&mut map, "uuid", decode(url.username()));
+    insert(&mut map, "udp", true);
+    if let Some(flow) = query.get("flow").filter(|f| !f.is_empty()) {
+        insert(&mut map, "flow", flow.as_str());
+    }
+
+    match query.get("security").map(std::string::String::as_str) {
+        Some("tls") => {
+            insert(&mut map, "tls", true);
+            apply_tls(&mut map, &query, "servername");
+        }
+        Some("reality") => {
+            insert(&mut map, "tls", true);
+            apply_tls(&mut map, &query, "servername");
+            let mut reality = Mapping::new();
+            insert(&mut reality, "public-key", query.get("pbk").map_or("", |s| s.as_str()));
+            if let Some(sid) = query.get("sid") {
+                insert(&mut reality, "short-id", sid.as_str());
+            }
+            insert(&mut map, "reality-opts", reality);
+        }
+        _ => {}
+    }
+    apply_transport(&mut map, &query);
+    Ok(map)
+}
+
+fn parse_trojan(url: &Url) -> Result<Mapping> {
+    let (name, server, port, query) = url_parts(url)?;
+    let mut map = base_proxy(&name, "trojan", &server, port);
+    insert(&mut map, "password", decode(url.username()));
+    insert(&mut map, "udp", true);
+    apply_tls(&mut map, &query, "sni");
+    apply_transport(&mut map, &query);
+    Ok(map)
+}
+
+fn parse_hysteria2(url: &Url) -> Result<Mapping> {
+    let (name, server, port, query) = url_parts(url)?;
+    let mut map = base_proxy(&name, "hysteria2", &server, port);
+    let password = match url.password() {
+        Some(password) => format!("{}:{}", decode(url.username()), decode(password)),
+        None => decode(url.username()),
+    };
+    insert(&mut map, "password", password);
+    apply_tls(&mut map, &query, "sni");
+    if let Some(obfs) = query.get("obfs") {
+        insert(&mut map, "obfs", obfs.as_str());
+        if let Some(obfs_password) = query.get("obfs-password") {
+            insert(&mut map, "obfs-password", obfs_password.as_str());
+        }
+    }
+    Ok(map)
+}
+
+fn str_field<'a>(map: &'a Mapping, key: &str) -> Option<&'a str> {
+    map.get(key).and_then(Value::as_str)
+}
+
+/// 字符串或数字字段统一转为字符串
+fn text_field(map: &Mapping, key: &str) -> Option<std::string::String> {
+    match map.get(key)? {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+fn required<'a>(map: &'a Mapping, key: &str) -> Result<&'a str> {
+    str_field(map, key).ok_or_else(|| anyhow!("missing field: {key}"))
+}
+
+fn endpoint(map: &Mapping) -> Result<(&str, u16)> {
+    let server = required(map, "server")?;
+    let port = text_field(map, "port")
+        .and_then(|p| p.parse().ok())
+        .ok_or_else(|| anyhow!("missing field: port"))?;
+    Ok((server, port))
+}
+
+/// `apply_transport` 的逆过程
+fn transport_query(map: &Mapping, query: &mut Vec<(&'static str, std::string::String)>) {
+    let Some(network) = str_field(map, "network") else {
+        return;
+    };
+    query.push(("type", network.to_owned()));
+    match network {
+        "ws" => {
+            let opts = map.get("ws-opts").and_then(Value::as_mapping);
+            if let Some(path) = opts.and_then(|o| str_field(o, "path")) {
+                query.push(("path", path.to_owned()));
+            }
+            let host = opts
+                .and_then(|o| o.get("headers"))
+                .and_then(Value::as_mapping)
+                .and_then(|h| str_field(h, "Host"));
+            if let Some(host) = host {
+                query.push(("host", host.to_owned()));
+            }
+        }
+        "grpc" => {
+            let service = map
+                .get("grpc-opts")
+                .and_then(Value::as_mapping)
+                .and_then(|o| str_field(o, "grpc-service-name"));
+            if let Some(service) = service {
+                query.push(("serviceName", service.to_owned()));
+            }
+        }
+        _ => {}
+    }
+}
+
+/// `apply_tls` 的逆过程
+fn tls_query(map: &Mapping, sni_key: &str, query: &mut Vec<(&'static str, std::string::String)>) {
+    if let Some(sni) = str_field(map, sni_key) {
+        query.push(("sni", sni.to_owned()));
+    }
+    if let Some(alpn) = map.get("alpn").and_then(Value::as_sequence) {
+        let alpn: Vec<&str> = alpn.iter().filter_map(Value::as_str).collect();
+        query.push(("alpn", alpn.join(",")));
+    }
+    if let Some(fp) = str_field(map, "client-fingerprint") {
+        query.push(("fp", fp.to_owned()));
+    }
+    if map.get("skip-cert-verify").and_then(Value::as_bool) == Some(true) {
+        query.push(("insecure", "1".to_owned()));
+    }
+}
+
+fn build_url(
+    scheme: &str,
+    user: &str,
+    map: &Mapping,
+    query: &[(&'static str, std::string::String)],
+) -> Result<std::string::String> {
+    let (server, port) = endpoint(map)?;
+    let host = if server.contains(':') {
+        format!("[{server}]")
+    } else {
+        server.to_owned()
+    };
+    let mut url = Url::parse(&format!("{scheme}://{host}:{port}"))?;
+    url.set_username(user).map_err(|()| anyhow!("invalid user info"))?;
+    if !query.is_empty() {
+        url.query_pairs_mut().extend_pairs(query);
+    }
+    url.set_fragment(str_field(map, "name"));
+    Ok(url.into())
+}
+
+fn export_ss(map: &Mapping) -> Result<std::string::String> {
+    let userinfo = URL_SAFE_NO_PAD.encode(format!("{}:{}", required(map, "cipher")?, required(map, "password")?));
+    build_url("ss", &userinfo, map, &[])
+}
+
+fn export_vmess(map: &Mapping) -> Result<std::string::String> {
+    let (server, port) = endpoint(map)?;
+    let network = str_field(map, "network").unwrap_or("tcp");
+    let (host, path) = match network {
+        "ws" => {
+            let opts = map.get("ws-opts").and_then(Value::as_mapping);
+            let host = opts
+                .and_then(|o| o.get("headers"))
+                .and_then(Value::as_mapping)
+                .and_then(|h| str_field(h, "Host"));
+            (host, opts.and_then(|o| str_field(o, "path")))
+        }
+        "grpc" => {
+            let opts = map.get("grpc-opts").and_then(Value::as_mapping);
+            (None, opts.and_then(|o| str_field(o, "grpc-service-name")))
+        }
+        _ => (None, None),
+    };
+    let tls = map.get("tls").and_then(Value::as_bool) == Some(true);
+    let json = serde_json::json!({
+        "v": "2",
+        "ps": str_field(map, "name").unwrap_or_default(),
+        "add": server,
+        "port": port.to_string(),
+        "id": required(map, "uuid")?,
+        "aid": text_field(map, "alterId").unwrap_or_else(|| "0".into()),
+        "scy": str_field(map, "cipher").unwrap_or("auto"),
+        "net": network,
+        "type": "none",
+        "host": host.unwrap_or_default(),
+        "path": path.unwrap_or_default(),
+        "tls": if tls { "tls" } else { "" },
+        "sni": str_field(map, "servername").unwrap_or_default(),
+    });
+    Ok(format!("vmess://{}", STANDARD.encode(json.to_string())))
+}
+
+fn export_vless(map: &Mapping) -> Result<std::string::String> {
+    let mut query = Vec::new();
+    if let Some(flow) = str_field(map, "flow") {
+        query.push(("flow", flow.to_owned()));
+    }
+    let reality = map.get("reality-opts").and_then(Value::as_mapping);
+    if let Some(reality) = reality {
+        query.push(("security", "reality".to_owned()));
+        if let Some(pbk) = str_field(reality, "public-key") {
+            query.push(("pbk", pbk.to_owned()));
+        }
+        if let Some(sid) = str_field(reality, "short-id") {
+            query.push(("sid", sid.to_owned()));
+        }
+    } else if map.get("tls").and_then(Value::as_bool) == Some(true) {
+        query.push(("security", "tls".to_owned()));
+    }
+    tls_query(map, "servername", &mut query);
+    transport_query(map, &mut query);
+    build_url("vless", required(map, "uuid")?, map, &query)
+}
+
+fn export_trojan(map: &Mapping) -> Result<std::string::String> {
+    let mut query = Vec::new();
+    tls_query(map, "sni", &mut query);
+    transport_query(map, &mut query);
+    build_url("trojan", required(map, "password")?, map, &query)
+}
+
+fn export_hysteria2(map: &Mapping) -> Result<std::string::String> {
+    let mut query = Vec::new();
+    tls_query(map, "sni", &mut query);
+    if let Some(obfs) = str_field(map, "obfs") {
+        query.push(("obfs", obfs.to_owned()));
+        if let Some(obfs_password) = str_field(map, "obfs-password") {
+            query.push(("obfs-password", obfs_password.to_owned()));
+        }
+    }
+    build_url("hysteria2", required(map, "password")?, map, &query)
+}
+
+/// 将 mihomo proxy 映射转换为分享链接
+pub fn to_share_link(proxy: &Mapping) -> Result<std::string::String> {
+    match required(proxy, "type")? {
+        "ss" => export_ss(proxy),
+        "vmess" => export_vmess(proxy),
+        "vless" => export_vless(proxy),
+        "trojan" => export_trojan(proxy),
+        "hysteria2" => export_hysteria2(proxy),
+        other => bail!("proxy type {other} has no share link format"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get<'a>(map: &'a Mapping, key: &str) -> Option<&'a Value> {
+        map.get(key)
+    }
+
+    #[test]
+    fn test_parse_ss() {
+        let map = parse_share_link("ss://YWVzLTEyOC1nY206cGFzcw@1.2.3.4:8388#HK%201").unwrap_or_default();
+        assert_eq!(get(&map, "name"), Some(&Value::from("HK 1")));
+        assert_eq!(get(&map, "cipher"), Some(&Value::from("aes-128-gcm")));
+        assert_eq!(get(&map, "password"), Some(&Value::from("pass")));
+        assert_eq!(get(&map, "port"), Some(&Value::from(8388)));
+
+        let legacy = parse_share_link("ss://YWVzLTEyOC1nY206cGFzc0AxLjIuMy40OjgzODg#old").unwrap_or_default();
+        assert_eq!(get(&legacy, "server"), Some(&Value::from("1.2.3.4")));
+    }
+
+    #[test]
+    fn test_parse_vless_reality() {
+        let map = parse_share_link(
+            "vless://uuid-1@example.com:443?security=reality&sni=www.apple.com&pbk=KEY&sid=ab&fp=chrome&type=grpc&serviceName=svc&flow=xtls-rprx-vision#JP",
+        )
+        .unwrap_or_default();
+        assert_eq!(get(&map, "uuid"), Some(&Value::from("uuid-1")));
+        assert_eq!(get(&map, "servername"), Some(&Value::from("www.apple.com")));
+        assert_eq!(get(&map, "network"), Some(&Value::from("grpc")));
+        assert!(get(&map, "reality-opts").is_some());
+    }
+
+    #[test]
+    fn test_parse_base64_subscription() {
+        let links = "trojan://pw@t.example.com:443?sni=t.example.com#T\nhy2://auth@h.example.com:8443?insecure=1#H\ninvalid line";
+        let encoded = STANDARD.encode(links);
+        let proxies = parse_share_links(&encoded);
+        assert_eq!(proxies.len(), 2);
+        assert_eq!(get(&proxies[1], "type"), Some(&Value::from("hysteria2")));
+        assert_eq!(get(&proxies[1], "skip-cert-verify"), Some(&Value::Bool(true)));
+    }
+
+    #[test]
+    fn test_share_link_round_trip() {
+        let links = [
+            "ss://YWVzLTEyOC1nY206cGFzcw@1.2.3.4:8388#HK%201",
+            "vless://uuid-1@example.com:443?security=reality&sni=www.apple.com&pbk=KEY&sid=ab&fp=chrome&type=grpc&serviceName=svc&flow=xtls-rprx-vision#JP",
+            "trojan://pw@t.example.com:443?sni=t.example.com&type=ws&path=%2Fws&host=cdn.example.com#T",
+            "hy2://auth@h.example.com:8443?insecure=1&obfs=salamander&obfs-password=x#H",
+        ];
+        for link in links {
+            let proxy = parse_share_link(link).unwrap_or_default();
+            let exported = to_share_link(&proxy).unwrap_or_default();
+            assert_eq!(parse_share_link(&exported).unwrap_or_default(), proxy, "{link}");
+        }
+
+        let vmess = serde_json::json!({
+            "ps": "US", "add": "v.example.com", "port": 443, "id": "uuid-2", "aid": "0",
+            "net": "ws", "host": "cdn.example.com", "path": "/v", "tls": "tls", "sni": "v.example.com",
+        });
+        let proxy = parse_share_link(&format!("vmess://{}", STANDARD.encode(vmess.to_string()))).unwrap_or_default();
+        let exported = to_share_link(&proxy).unwrap_or_default();
+        assert_eq!(parse_share_link(&exported).unwrap_or_default(), proxy);
+    }
+
+    #[test]
+    fn test_unsupported_export() {
+        let proxy = base_proxy("d", "direct", "", 0);
+        assert!(to_share_link(&proxy).is_err());
+    }
+}
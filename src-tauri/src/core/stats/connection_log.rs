@@ -0,0 +1,256 @@
+use super::ConnectionSnapshot;
+use crate::utils::dirs::app_connection_logs_dir;
+use anyhow::Result;
+use chrono::{Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use smartstring::alias::String;
+use std::path::PathBuf;
+use tokio::io::AsyncWriteExt as _;
+
+const LOG_FILE_PREFIX: &str = "connections-";
+const LOG_FILE_EXT: &str = "jsonl";
+const LOG_KEEP_DAYS: i64 = 7;
+pub const MAX_QUERY_LIMIT: usize = 5000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConnectionEventKind {
+    Open,
+    Close,
+    /// 出站节点的延迟采样，连接信息取自触发采样的连接
+    Latency,
+}
+
+/// 连接日志中的一条记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConnectionEvent {
+    pub time: i64,
+    pub kind: ConnectionEventKind,
+    #[serde(flatten)]
+    pub connection: ConnectionSnapshot,
+    /// 出站节点的延迟采样，记录在 latency 事件上，同一节点每隔一段时间采样一次
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rtt_ms: Option<u64>,
+}
+
+/// `query_connection_log` 的过滤条件，字段均为可选
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ConnectionLogFilter {
+    /// 匹配 host / 目标地址 / 进程名（不区分大小写）
+    pub keyword: Option<String>,
+    /// 代理链中包含的节点或策略组
+    pub node: Option<String>,
+    pub rule: Option<String>,
+    pub kind: Option<ConnectionEventKind>,
+    pub since: Option<i64>,
+    pub until: Option<i64>,
+}
+
+impl ConnectionLogFilter {
+    pub fn matches(&self, event: &ConnectionEvent) -> bool {
+        let conn = &event.connection;
+        if self.kind.is_some_and(|kind| kind != event.kind)
+            || self.since.is_some_and(|since| event.time < since)
+            || self.until.is_some_and(|until| event.time > until)
+        {
+            return false;
+        }
+        if let Some(rule) = self.rule.as_deref()
+            && !conn.rule.eq_ignore_ascii_case(rule)
+        {
+            return false;
+        }
+        if let Some(node) = self.node.as_deref()
+            && !conn.chains.iter().any(|c| c == node)
+        {
+            return false;
+        }
+        if let Some(keyword) = self.keyword.as_deref() {
+            let keyword = keyword.to_lowercase();
+            let hit = [Some(&conn.host), Some(&conn.destination), conn.process.as_ref()]
+                .into_iter()
+                .flatten()
+                .any(|field| field.to_lowercase().contains(&keyword));
+            if !hit {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// 按天滚动的连接日志（JSON Lines）
+pub struct ConnectionLog;
+
+impl ConnectionLog {
+    fn file_for(date: NaiveDate) -> Result<PathBuf> {
+        Ok(app_connection_logs_dir()?.join(format!("{LOG_FILE_PREFIX}{}.{LOG_FILE_EXT}", date.format("%Y%m%d"))))
+    }
+
+    fn parse_file_date(path: &std::path::Path) -> Option<NaiveDate> {
+        let stem = path.file_stem()?.to_str()?;
+        let date = stem.strip_prefix(LOG_FILE_PREFIX)?;
+        NaiveDate::parse_from_str(date, "%Y%m%d").ok()
+    }
+
+    /// 日志文件按日期从新到旧排列
+    async fn list_files() -> Result<Vec<(NaiveDate, PathBuf)>> {
+        let dir = app_connection_logs_dir()?;
+        let mut files = Vec::new();
+        if !dir.exists() {
+            return Ok(files);
+        }
+        let mut entries = tokio::fs::read_dir(&dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if let Some(date) = Self::parse_file_date(&path) {
+                files.push((date, path));
+            }
+        }
+        files.sort_by(|a, b| b.0.cmp(&a.0));
+        Ok(files)
+    }
+
+    pub async fn append(events: &[ConnectionEvent]) -> Result<()> {
+        if events.is_empty() {
+            return Ok(());
+        }
+
+        let dir = app_connection_logs_dir()?;
+        tokio::fs::create_dir_all(&dir).await?;
+        let path = Self::file_for(Local::now().date_naive())?;
+        let is_new_file = !path.exists();
+
+        let mut buf = std::string::String::new();
+        for event in events {
+            buf.push_str(&serde_json::to_string(event)?);
+            buf.push('\n');
+        }
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .await?;
+        file.write_all(buf.as_bytes()).await?;
+
+        if is_new_file {
+            Self::cleanup().await?;
+        }
+        Ok(())
+    }
+
+    /// 删除超出保留天数的日志文件
+    async fn cleanup() -> Result<()> {
+        let oldest = Local::now().date_naive() - chrono::Duration::days(LOG_KEEP_DAYS);
+        for (date, path) in Self::list_files().await? {
+            if date < oldest {
+                tokio::fs::remove_file(path).await.ok();
+            }
+        }
+        Ok(())
+    }
+
+    /// 从新到旧遍历命中过滤条件的记录，回调返回 `false` 时停止
+    pub async fn scan<F>(filter: &ConnectionLogFilter, mut visit: F) -> Result<()>
+    where
+        F: FnMut(ConnectionEvent) -> bool + Send,
+    {
+        for (_, path) in Self::list_files().await? {
+            let content = tokio::fs::read_to_string(&path).await?;
+            for line in content.lines().rev() {
+                let Ok(event) = serde_json::from_str::<ConnectionEvent>(line) else {
+                    continue;
+                };
+                if filter.since.is_some_and(|since| event.time < since) {
+                    // 文件内按时间追加，更早的记录都不会命中
+                    return Ok(());
+                }
+                if filter.matches(&event) && !visit(event) {
+                    return Ok(());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 从新到旧查询日志，最多返回 `limit` 条
+    pub async fn query(filter: &ConnectionLogFilter, limit: usize) -> Result<Vec<ConnectionEvent>> {
+        let limit = limit.clamp(1, MAX_QUERY_LIMIT);
+        let mut result = Vec::new();
+        Self::scan(filter, |event| {
+            result.push(event);
+            result.len() < limit
+        })
+        .await?;
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(kind: ConnectionEventKind, host: &str, chains: &[&str], time: i64) -> ConnectionEvent {
+        ConnectionEvent {
+            time,
+            kind,
+            connection: ConnectionSnapshot {
+                id: "1".into(),
+                host: host.into(),
+                destination: "1.1.1.1:443".into(),
+                network: "tcp".into(),
+                process: Some("curl".into()),
+                rule: "DomainSuffix".into(),
+                rule_payload: "example.com".into(),
+                chains: chains.iter().map(|c| (*c).into()).collect(),
+                upload: 10,
+                download: 20,
+            },
+            rtt_ms: None,
+        }
+    }
+
+    #[test]
+    fn test_filter_by_keyword_and_node() {
+        let ev = event(ConnectionEventKind::Open, "www.Example.com", &["HK-01", "Proxy"], 100);
+
+        let filter = ConnectionLogFilter {
+            keyword: Some("example".into()),
+            node: Some("HK-01".into()),
+            ..Default::default()
+        };
+        assert!(filter.matches(&ev));
+
+        let filter = ConnectionLogFilter {
+            node: Some("JP-01".into()),
+            ..Default::default()
+        };
+        assert!(!filter.matches(&ev));
+    }
+
+    #[test]
+    fn test_filter_by_kind_and_time() {
+        let ev = event(ConnectionEventKind::Close, "example.com", &["DIRECT"], 100);
+
+        let filter = ConnectionLogFilter {
+            kind: Some(ConnectionEventKind::Close),
+            since: Some(50),
+            until: Some(150),
+            ..Default::default()
+        };
+        assert!(filter.matches(&ev));
+
+        let filter = ConnectionLogFilter {
+            kind: Some(ConnectionEventKind::Open),
+            ..Default::default()
+        };
+        assert!(!filter.matches(&ev));
+
+        let filter = ConnectionLogFilter {
+            since: Some(101),
+            ..Default::default()
+        };
+        assert!(!filter.matches(&ev));
+    }
+}
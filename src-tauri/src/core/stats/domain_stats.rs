@@ -0,0 +1,170 @@
+use super::{ConnectionEvent, ConnectionEventKind, ConnectionLog, ConnectionLogFilter};
+use anyhow::Result;
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use smartstring::alias::String;
+use std::collections::HashMap;
+
+pub(super) const BUILTIN_OUTBOUNDS: [&str; 3] = ["DIRECT", "REJECT", "REJECT-DROP"];
+
+/// 统计时间范围
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+pub enum StatsRange {
+    #[serde(rename = "1h")]
+    Hour,
+    #[default]
+    #[serde(rename = "24h")]
+    Day,
+    #[serde(rename = "7d")]
+    Week,
+}
+
+impl StatsRange {
+    pub(super) const fn seconds(self) -> i64 {
+        match self {
+            Self::Hour => 3600,
+            Self::Day => 24 * 3600,
+            Self::Week => 7 * 24 * 3600,
+        }
+    }
+}
+
+/// 单个域名（或无域名时的目标地址）的聚合结果
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DomainStat {
+    pub host: String,
+    pub connections: u64,
+    pub upload: u64,
+    pub download: u64,
+    /// 经过代理节点（非 DIRECT/REJECT）的流量
+    pub proxied_bytes: u64,
+    /// 命中的规则及次数，按次数降序
+    pub rules: Vec<(String, u64)>,
+    /// 使用过的出口节点及次数，按次数降序
+    pub outbounds: Vec<(String, u64)>,
+}
+
+#[derive(Default)]
+struct DomainAccumulator {
+    stat: DomainStat,
+    rules: HashMap<String, u64>,
+    outbounds: HashMap<String, u64>,
+}
+
+fn sorted_counts(map: HashMap<String, u64>) -> Vec<(String, u64)> {
+    let mut counts: Vec<(String, u64)> = map.into_iter().collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    counts
+}
+
+/// 按目标聚合已关闭的连接，按代理流量与总流量降序取前 `top_n` 个
+pub fn aggregate<I: IntoIterator<Item = ConnectionEvent>>(events: I, top_n: usize) -> Vec<DomainStat> {
+    let mut domains: HashMap<String, DomainAccumulator> = HashMap::new();
+
+    for event in events {
+        if event.kind != ConnectionEventKind::Close {
+            continue;
+        }
+        let conn = event.connection;
+        let acc = domains.entry(conn.target().into()).or_default();
+        let bytes = conn.upload + conn.download;
+
+        acc.stat.connections += 1;
+        acc.stat.upload += conn.upload;
+        acc.stat.download += conn.download;
+
+        // mihomo 的 chains 从出口节点开始排列
+        let outbound = conn.chains.first().cloned().unwrap_or_else(|| "DIRECT".into());
+        if !BUILTIN_OUTBOUNDS.contains(&outbound.as_str()) {
+            acc.stat.proxied_bytes += bytes;
+        }
+        *acc.outbounds.entry(outbound).or_default() += 1;
+
+        let rule = if conn.rule_payload.is_empty() {
+            conn.rule
+        } else {
+            format!("{},{}", conn.rule, conn.rule_payload).into()
+        };
+        *acc.rules.entry(rule).or_default() += 1;
+    }
+
+    let mut stats: Vec<DomainStat> = domains
+        .into_iter()
+        .map(|(host, acc)| DomainStat {
+            host,
+            rules: sorted_counts(acc.rules),
+            outbounds: sorted_counts(acc.outbounds),
+            ..acc.stat
+        })
+        .collect();
+    stats.sort_by(|a, b| {
+        b.proxied_bytes
+            .cmp(&a.proxied_bytes)
+            .then_with(|| (b.upload + b.download).cmp(&(a.upload + a.download)))
+            .then_with(|| a.host.cmp(&b.host))
+    });
+    stats.truncate(top_n);
+    stats
+}
+
+/// 从连接日志读取指定时间范围内的连接并聚合
+pub async fn query_domain_stats(top_n: usize, range: StatsRange) -> Result<Vec<DomainStat>> {
+    let filter = ConnectionLogFilter {
+        kind: Some(ConnectionEventKind::Close),
+        since: Some(Local::now().timestamp() - range.seconds()),
+        ..Default::default()
+    };
+    let mut events = Vec::new();
+    ConnectionLog::scan(&filter, |event| {
+        events.push(event);
+        true
+    })
+    .await?;
+    Ok(aggregate(events, top_n))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::stats::ConnectionSnapshot;
+
+    fn closed(host: &str, chains: &[&str], rule: &str, bytes: u64) -> ConnectionEvent {
+        ConnectionEvent {
+            time: 0,
+            kind: ConnectionEventKind::Close,
+            connection: ConnectionSnapshot {
+                host: host.into(),
+                rule: rule.into(),
+                chains: chains.iter().map(|c| (*c).into()).collect(),
+                download: bytes,
+                ..Default::default()
+            },
+            rtt_ms: None,
+        }
+    }
+
+    #[test]
+    fn test_aggregate_orders_by_proxied_traffic() {
+        let events = vec![
+            closed("a.com", &["DIRECT"], "GeoSite", 1000),
+            closed("b.com", &["HK-01", "Proxy"], "Match", 100),
+            closed("b.com", &["JP-01", "Proxy"], "Match", 50),
+            closed("c.com", &["HK-01", "Proxy"], "Match", 10),
+        ];
+
+        let stats = aggregate(events, 2);
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats[0].host, "b.com");
+        assert_eq!(stats[0].connections, 2);
+        assert_eq!(stats[0].proxied_bytes, 150);
+        assert_eq!(stats[0].rules, vec![(String::from("Match"), 2)]);
+        assert_eq!(stats[1].host, "c.com");
+    }
+
+    #[test]
+    fn test_aggregate_ignores_open_events() {
+        let mut event = closed("a.com", &["DIRECT"], "Match", 10);
+        event.kind = ConnectionEventKind::Open;
+        assert!(aggregate(vec![event], 10).is_empty());
+    }
+}
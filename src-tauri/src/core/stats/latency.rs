@@ -0,0 +1,257 @@
+//! 节点延迟热力图
+//!
+//! 采集连接时把新连接的出站节点排队（同一节点每 [`SAMPLE_INTERVAL_SECS`] 最多一次），由单独的任务测延迟，
+//! 结果以 latency 记录写入连接日志的 `rtt_ms`（旧版本记录在 open 事件上）。查询时按节点与时间段聚合，前端据此按一天中的时段绘制热力图，
+//! 用来发现运营商在固定时段限速的情况。
+
+use super::{
+    ConnectionEvent, ConnectionEventKind, ConnectionLog, ConnectionLogFilter, StatsRange,
+    domain_stats::BUILTIN_OUTBOUNDS,
+};
+use crate::{config::Config, core::handle, process::AsyncHandler};
+use anyhow::Result;
+use chrono::Local;
+use clash_verge_logging::{Type, logging_error};
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::Serialize;
+use smartstring::alias::String;
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::Duration,
+};
+
+const DEFAULT_TEST_URL: &str = "https://www.gstatic.com/generate_204";
+const DEFAULT_TEST_TIMEOUT_MS: u32 = 5000;
+const SAMPLE_INTERVAL_SECS: i64 = 300;
+/// 延迟测试的节拍，与连接采集分开，避免测试超时拖慢连接日志
+const SAMPLE_TICK: Duration = Duration::from_secs(10);
+/// 每个节拍最多测试的节点数，避免连接突增时集中发起大量测试
+const MAX_SAMPLES_PER_TICK: usize = 8;
+
+/// 节点上次采样的时间
+static LAST_SAMPLED: Lazy<Mutex<HashMap<String, i64>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+/// 等待测试的采样，沿用触发采样的连接信息
+static PENDING: Lazy<Mutex<Vec<(String, ConnectionEvent)>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+/// 单个节点在一个时间段内的延迟统计，`rtt_ms` 为 0 的采样计为超时
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct LatencyBucket {
+    pub node: String,
+    /// 时间段起点（Unix 秒）
+    pub start: i64,
+    pub samples: u64,
+    pub timeouts: u64,
+    pub avg_ms: Option<u64>,
+    pub min_ms: Option<u64>,
+    pub max_ms: Option<u64>,
+    pub p90_ms: Option<u64>,
+}
+
+/// 时间段长度：1 小时范围按 5 分钟，其余按小时
+const fn bucket_seconds(range: StatsRange) -> i64 {
+    match range {
+        StatsRange::Hour => 300,
+        StatsRange::Day | StatsRange::Week => 3600,
+    }
+}
+
+/// 出站节点为代理链第一项，内置出站不测
+fn outbound(event: &ConnectionEvent) -> Option<&String> {
+    event
+        .connection
+        .chains
+        .first()
+        .filter(|node| !BUILTIN_OUTBOUNDS.contains(&node.as_str()))
+}
+
+/// 记录需要测延迟的新连接，实际测试由 [`spawn_sampler`] 按自己的节奏进行，不阻塞连接采集
+pub(super) fn queue_outbounds(events: &[ConnectionEvent], now: i64) {
+    let mut pending = PENDING.lock();
+    let mut last_sampled = LAST_SAMPLED.lock();
+    for event in events {
+        if pending.len() >= MAX_SAMPLES_PER_TICK {
+            break;
+        }
+        if event.kind != ConnectionEventKind::Open {
+            continue;
+        }
+        let Some(node) = outbound(event) else {
+            continue;
+        };
+        if last_sampled
+            .get(node)
+            .is_some_and(|&at| now - at < SAMPLE_INTERVAL_SECS)
+        {
+            continue;
+        }
+        last_sampled.insert(node.clone(), now);
+        pending.push((
+            node.clone(),
+            ConnectionEvent {
+                kind: ConnectionEventKind::Latency,
+                ..event.clone()
+            },
+        ));
+    }
+}
+
+/// 定期测试排队的节点并把结果写入连接日志
+pub(super) fn spawn_sampler() {
+    AsyncHandler::spawn(|| async {
+        let mut ticker = tokio::time::interval(SAMPLE_TICK);
+        loop {
+            ticker.tick().await;
+            logging_error!(Type::Network, sample_pending().await);
+        }
+    });
+}
+
+async fn sample_pending() -> Result<()> {
+    let due = std::mem::take(&mut *PENDING.lock());
+    if due.is_empty() {
+        return Ok(());
+    }
+
+    let (test_url, timeout) = {
+        let verge = Config::verge().await.latest_arc();
+        (
+            verge
+                .default_latency_test
+                .clone()
+                .unwrap_or_else(|| DEFAULT_TEST_URL.into()),
+            verge
+                .default_latency_timeout
+                .and_then(|t| u32::try_from(t).ok())
+                .filter(|&t| t > 0)
+                .unwrap_or(DEFAULT_TEST_TIMEOUT_MS),
+        )
+    };
+    let mihomo = handle::Handle::mihomo().await;
+    let delays = futures::future::join_all(
+        due.iter()
+            .map(|(node, _)| mihomo.delay_proxy_by_name(node, &test_url, timeout)),
+    )
+    .await;
+    drop(mihomo);
+
+    let now = Local::now().timestamp();
+    let samples: Vec<ConnectionEvent> = due
+        .into_iter()
+        .zip(delays)
+        // 内核不可用时不记录，避免误计为超时
+        .filter_map(|((_, event), result)| {
+            let result = result.ok()?;
+            Some(ConnectionEvent {
+                time: now,
+                rtt_ms: Some(u64::from(result.delay)),
+                ..event
+            })
+        })
+        .collect();
+    ConnectionLog::append(&samples).await
+}
+
+fn percentile(sorted: &[u64], percent: usize) -> Option<u64> {
+    let index = (sorted.len() * percent).div_ceil(100).checked_sub(1)?;
+    sorted.get(index).copied()
+}
+
+/// 按节点与时间段聚合延迟采样，结果按节点、时间排序
+fn aggregate(events: Vec<ConnectionEvent>, bucket: i64) -> Vec<LatencyBucket> {
+    let mut groups: BTreeMap<(String, i64), Vec<u64>> = BTreeMap::new();
+    for event in events {
+        let (Some(rtt), Some(node)) = (event.rtt_ms, outbound(&event)) else {
+            continue;
+        };
+        let start = event.time - event.time.rem_euclid(bucket);
+        groups.entry((node.clone(), start)).or_default().push(rtt);
+    }
+
+    groups
+        .into_iter()
+        .map(|((node, start), samples)| {
+            let mut rtts: Vec<u64> = samples.iter().copied().filter(|&rtt| rtt > 0).collect();
+            rtts.sort_unstable();
+            let count = u64::try_from(rtts.len()).unwrap_or_default();
+            LatencyBucket {
+                node,
+                start,
+                samples: u64::try_from(samples.len()).unwrap_or_default(),
+                timeouts: u64::try_from(samples.len() - rtts.len()).unwrap_or_default(),
+                avg_ms: rtts.iter().sum::<u64>().checked_div(count),
+                min_ms: rtts.first().copied(),
+                max_ms: rtts.last().copied(),
+                p90_ms: percentile(&rtts, 90),
+            }
+        })
+        .collect()
+}
+
+/// 从连接日志读取指定时间范围内的延迟采样并聚合，`node` 为空时返回所有节点
+pub async fn query_latency_heatmap(node: Option<&str>, range: StatsRange) -> Result<Vec<LatencyBucket>> {
+    // 旧版本的采样记录在 open 事件上，这里不按类型过滤，只看是否带有 `rtt_ms`
+    let filter = ConnectionLogFilter {
+        node: node.map(Into::into),
+        since: Some(Local::now().timestamp() - range.seconds()),
+        ..Default::default()
+    };
+    let mut events = Vec::new();
+    ConnectionLog::scan(&filter, |event| {
+        if event.rtt_ms.is_some() {
+            events.push(event);
+        }
+        true
+    })
+    .await?;
+    // 过滤条件匹配整条代理链，这里只保留以该节点为出站的采样
+    events.retain(|event| node.is_none_or(|node| outbound(event).is_some_and(|out| out == node)));
+    Ok(aggregate(events, bucket_seconds(range)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::stats::ConnectionSnapshot;
+
+    fn sample(node: &str, time: i64, rtt_ms: Option<u64>) -> ConnectionEvent {
+        ConnectionEvent {
+            time,
+            kind: ConnectionEventKind::Open,
+            connection: ConnectionSnapshot {
+                chains: vec![node.into(), "Proxy".into()],
+                ..Default::default()
+            },
+            rtt_ms,
+        }
+    }
+
+    #[test]
+    fn test_aggregate_buckets_by_node_and_hour() {
+        let events = vec![
+            sample("HK-01", 10, Some(100)),
+            sample("HK-01", 20, Some(300)),
+            sample("HK-01", 30, Some(0)),
+            sample("HK-01", 3700, Some(50)),
+            sample("JP-01", 40, None),
+            sample("DIRECT", 50, Some(5)),
+        ];
+
+        let buckets = aggregate(events, 3600);
+        assert_eq!(buckets.len(), 2);
+        assert_eq!(
+            buckets[0],
+            LatencyBucket {
+                node: "HK-01".into(),
+                start: 0,
+                samples: 3,
+                timeouts: 1,
+                avg_ms: Some(200),
+                min_ms: Some(100),
+                max_ms: Some(300),
+                p90_ms: Some(300),
+            }
+        );
+        assert_eq!((buckets[1].start, buckets[1].avg_ms), (3600, Some(50)));
+    }
+}
@@ -0,0 +1,230 @@
+mod connection_log;
+mod domain_stats;
+mod latency;
+mod speedtest;
+
+use crate::{config::Config, core::handle, process::AsyncHandler};
+use chrono::Local;
+use clash_verge_logging::{Type, logging, logging_error};
+use once_cell::sync::OnceCell;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use smartstring::alias::String;
+use std::{
+    collections::HashMap,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+pub use connection_log::{ConnectionEvent, ConnectionEventKind, ConnectionLog, ConnectionLogFilter};
+pub use domain_stats::{DomainStat, StatsRange, query_domain_stats};
+pub use latency::{LatencyBucket, query_latency_heatmap};
+pub use speedtest::{SpeedtestHistory, SpeedtestRecord};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// 从内核 `/connections` 中提取的单个连接信息
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConnectionSnapshot {
+    pub id: String,
+    pub host: String,
+    pub destination: String,
+    pub network: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub process: Option<String>,
+    pub rule: String,
+    pub rule_payload: String,
+    pub chains: Vec<String>,
+    pub upload: u64,
+    pub download: u64,
+}
+
+impl ConnectionSnapshot {
+    /// 按 mihomo API 的字段名解析，不依赖具体的 Rust 模型
+    pub fn from_value(value: &Value) -> Option<Self> {
+        let str_of = |v: &Value, key: &str| -> String { v.get(key).and_then(Value::as_str).unwrap_or_default().into() };
+
+        let id = str_of(value, "id");
+        if id.is_empty() {
+            return None;
+        }
+        let metadata = value.get("metadata").cloned().unwrap_or_default();
+        let destination_ip = str_of(&metadata, "destinationIP");
+        let destination_port = str_of(&metadata, "destinationPort");
+        let process = Some(str_of(&metadata, "process")).filter(|p| !p.is_empty());
+
+        Some(Self {
+            id,
+            host: str_of(&metadata, "host"),
+            destination: if destination_port.is_empty() {
+                destination_ip
+            } else {
+                format!("{destination_ip}:{destination_port}").into()
+            },
+            network: str_of(&metadata, "network"),
+            process,
+            rule: str_of(value, "rule"),
+            rule_payload: str_of(value, "rulePayload"),
+            chains: value
+                .get("chains")
+                .and_then(Value::as_array)
+                .map(|chains| chains.iter().filter_map(Value::as_str).map(Into::into).collect())
+                .unwrap_or_default(),
+            upload: value.get("upload").and_then(Value::as_u64).unwrap_or(0),
+            download: value.get("download").and_then(Value::as_u64).unwrap_or(0),
+        })
+    }
+
+    /// host 为空时（纯 IP 连接）使用目标地址
+    pub fn target(&self) -> &str {
+        if self.host.is_empty() {
+            &self.destination
+        } else {
+            &self.host
+        }
+    }
+}
+
+/// 定期采样内核连接列表，生成连接开启/关闭事件
+pub struct StatsCollector {
+    tracked: Mutex<HashMap<String, ConnectionSnapshot>>,
+    runner_started: AtomicBool,
+}
+
+impl StatsCollector {
+    pub fn global() -> &'static Self {
+        static INSTANCE: OnceCell<StatsCollector> = OnceCell::new();
+        INSTANCE.get_or_init(|| Self {
+            tracked: Mutex::new(HashMap::new()),
+            runner_started: AtomicBool::new(false),
+        })
+    }
+
+    pub fn init(&self) {
+        if self.runner_started.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        AsyncHandler::spawn(|| async {
+            let collector = Self::global();
+            let mut ticker = tokio::time::interval(POLL_INTERVAL);
+            loop {
+                ticker.tick().await;
+                collector.collect().await;
+            }
+        });
+        latency::spawn_sampler();
+    }
+
+    async fn collect(&self) {
+        let log_enabled = Config::verge()
+            .await
+            .latest_arc()
+            .enable_connection_log
+            .unwrap_or(false);
+        if !log_enabled {
+            self.tracked.lock().clear();
+            return;
+        }
+
+        let connections = match handle::Handle::mihomo().await.get_connections().await {
+            Ok(connections) => connections,
+            Err(err) => {
+                logging!(debug, Type::Network, "Failed to sample connections: {err}");
+                return;
+            }
+        };
+        let current: Vec<ConnectionSnapshot> = connections
+            .connections
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|conn| serde_json::to_value(conn).ok())
+            .filter_map(|value| ConnectionSnapshot::from_value(&value))
+            .collect();
+
+        let now = Local::now().timestamp();
+        let events = self.diff(current, now);
+        latency::queue_outbounds(&events, now);
+        logging_error!(Type::Network, ConnectionLog::append(&events).await);
+    }
+
+    /// 与上一次采样比较，新出现的连接记为 open，消失的连接以最后一次采样的流量记为 close
+    fn diff(&self, current: Vec<ConnectionSnapshot>, now: i64) -> Vec<ConnectionEvent> {
+        let next: HashMap<String, ConnectionSnapshot> = current.into_iter().map(|c| (c.id.clone(), c)).collect();
+        let previous = std::mem::take(&mut *self.tracked.lock());
+        let event = |kind, connection| ConnectionEvent {
+            time: now,
+            kind,
+            connection,
+            rtt_ms: None,
+        };
+
+        let mut events: Vec<ConnectionEvent> = next
+            .values()
+            .filter(|conn| !previous.contains_key(&conn.id))
+            .map(|conn| event(ConnectionEventKind::Open, conn.clone()))
+            .collect();
+        events.extend(
+            previous
+                .into_values()
+                .filter(|conn| !next.contains_key(&conn.id))
+                .map(|conn| event(ConnectionEventKind::Close, conn)),
+        );
+
+        *self.tracked.lock() = next;
+        events
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_from_mihomo_json() {
+        let value = serde_json::json!({
+            "id": "abc",
+            "metadata": {
+                "network": "tcp",
+                "host": "",
+                "destinationIP": "1.1.1.1",
+                "destinationPort": "443",
+                "process": "curl"
+            },
+            "upload": 12,
+            "download": 34,
+            "chains": ["HK-01", "Proxy"],
+            "rule": "GeoIP",
+            "rulePayload": "CN"
+        });
+
+        let snapshot = ConnectionSnapshot::from_value(&value).expect("valid connection");
+        assert_eq!(snapshot.target(), "1.1.1.1:443");
+        assert_eq!(snapshot.process.as_deref(), Some("curl"));
+        assert_eq!(snapshot.chains, vec![String::from("HK-01"), String::from("Proxy")]);
+        assert_eq!((snapshot.upload, snapshot.download), (12, 34));
+    }
+
+    #[test]
+    fn test_diff_emits_open_and_close() {
+        let collector = StatsCollector {
+            tracked: Mutex::new(HashMap::new()),
+            runner_started: AtomicBool::new(false),
+        };
+        let conn = |id: &str| ConnectionSnapshot {
+            id: id.into(),
+            ..Default::default()
+        };
+
+        let events = collector.diff(vec![conn("a"), conn("b")], 1);
+        assert_eq!(events.len(), 2);
+        assert!(events.iter().all(|e| e.kind == ConnectionEventKind::Open));
+
+        let events = collector.diff(vec![conn("b")], 2);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].kind, ConnectionEventKind::Close);
+        assert_eq!(events[0].connection.id, "a");
+    }
+}
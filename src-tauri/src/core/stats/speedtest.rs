@@ -0,0 +1,97 @@
+use crate::utils::dirs;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use smartstring::alias::String;
+use std::path::PathBuf;
+
+const HISTORY_FILE: &str = "speedtest.jsonl";
+/// 最多保留的测速记录数，超出时丢弃最早的记录
+const MAX_RECORDS: usize = 1000;
+
+/// 一次测速的结果，速度单位为 bit/s
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpeedtestRecord {
+    pub time: i64,
+    /// 测速时流量经过的节点
+    pub node: String,
+    /// 测速服务器名称
+    pub server: String,
+    pub latency_ms: Option<u64>,
+    pub download_bps: Option<u64>,
+    pub upload_bps: Option<u64>,
+    /// 部分测试失败时的原因
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// 测速历史（JSON Lines），按节点对比历次结果
+pub struct SpeedtestHistory;
+
+impl SpeedtestHistory {
+    fn path() -> Result<PathBuf> {
+        Ok(dirs::app_home_dir()?.join("stats").join(HISTORY_FILE))
+    }
+
+    async fn load() -> Result<Vec<SpeedtestRecord>> {
+        let path = Self::path()?;
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = tokio::fs::read_to_string(&path).await?;
+        Ok(content
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect())
+    }
+
+    pub async fn append(record: &SpeedtestRecord) -> Result<()> {
+        let mut records = Self::load().await?;
+        records.push(record.clone());
+        let skip = records.len().saturating_sub(MAX_RECORDS);
+
+        let mut buf = std::string::String::new();
+        for record in &records[skip..] {
+            buf.push_str(&serde_json::to_string(record)?);
+            buf.push('\n');
+        }
+        let path = Self::path()?;
+        if let Some(dir) = path.parent() {
+            tokio::fs::create_dir_all(dir).await?;
+        }
+        tokio::fs::write(&path, buf).await?;
+        Ok(())
+    }
+
+    /// 按时间从新到旧返回，`node` 为空时返回全部节点的记录
+    pub async fn query(node: Option<&str>, limit: usize) -> Result<Vec<SpeedtestRecord>> {
+        Ok(filter_records(Self::load().await?, node, limit))
+    }
+}
+
+fn filter_records(records: Vec<SpeedtestRecord>, node: Option<&str>, limit: usize) -> Vec<SpeedtestRecord> {
+    records
+        .into_iter()
+        .rev()
+        .filter(|record| node.is_none_or(|node| record.node == node))
+        .take(limit)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_records() {
+        let record = |time, node: &str| SpeedtestRecord {
+            time,
+            node: node.into(),
+            ..Default::default()
+        };
+        let records = vec![record(1, "HK"), record(2, "JP"), record(3, "HK")];
+
+        let hk = filter_records(records.clone(), Some("HK"), 10);
+        assert_eq!(hk.iter().map(|r| r.time).collect::<Vec<_>>(), vec![3, 1]);
+        assert_eq!(filter_records(records, None, 2).len(), 2);
+    }
+}
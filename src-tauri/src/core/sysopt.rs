@@ -2,7 +2,12 @@
 use crate::utils::autostart as startup_shortcut;
 use crate::{
     config::{Config, IVerge},
-    core::handle::Handle,
+    core::{
+        bypass::{BypassFormat, effective_bypass, parse_bypass, serialize_bypass},
+        handle::Handle,
+        proxy_scope::{ProxyScopeMode, apply_proxy_scope},
+        sysproxy_guard::{GuardTarget, ProxyGuard},
+    },
     singleton,
 };
 use anyhow::Result;
@@ -17,14 +22,14 @@ use std::{
     },
     time::Duration,
 };
-use sysproxy::{Autoproxy, GuardMonitor, GuardType, Sysproxy};
+use sysproxy::{Autoproxy, Sysproxy};
 use tauri_plugin_autostart::ManagerExt as _;
 
 pub struct Sysopt {
     update_sysproxy: AtomicBool,
     reset_sysproxy: AtomicBool,
     inner_proxy: Arc<RwLock<(Sysproxy, Autoproxy)>>,
-    guard: Arc<RwLock<GuardMonitor>>,
+    guard: Arc<RwLock<ProxyGuard>>,
 }
 
 impl Default for Sysopt {
@@ -33,38 +38,94 @@ impl Default for Sysopt {
             update_sysproxy: AtomicBool::new(false),
             reset_sysproxy: AtomicBool::new(false),
             inner_proxy: Arc::new(RwLock::new((Sysproxy::default(), Autoproxy::default()))),
-            guard: Arc::new(RwLock::new(GuardMonitor::new(GuardType::None, Duration::from_secs(30)))),
+            guard: Arc::new(RwLock::new(ProxyGuard::new(GuardTarget::None, Duration::from_secs(30)))),
         }
     }
 }
 
+async fn get_bypass() -> String {
+    let (use_default, custom) = {
+        let verge = Config::verge().await.latest_arc();
+        (
+            verge.use_default_bypass.unwrap_or(true),
+            verge.system_proxy_bypass.clone().unwrap_or_default(),
+        )
+    };
+    let format = BypassFormat::current();
+    let rules = effective_bypass(&parse_bypass(&custom), use_default, format);
+    serialize_bypass(&rules, format)
+}
+
+/// 按设置同步或恢复 WinHTTP 代理，失败时只记录日志，不影响系统代理本身。
+/// 可能弹出 UAC 提示，在后台线程执行，避免持有代理设置的锁等待用户操作
+#[cfg(target_os = "windows")]
+fn sync_legacy_proxy(enabled: bool, mode: &ProxyScopeMode) {
+    use crate::core::win_proxy_sync;
+
+    let mode = mode.clone();
+    crate::process::AsyncHandler::spawn_blocking(move || {
+        let result = if enabled {
+            win_proxy_sync::apply(&mode)
+        } else {
+            win_proxy_sync::restore()
+        };
+        logging_error!(Type::Core, result);
+    });
+}
+
+#[cfg(not(target_os = "windows"))]
+const fn sync_legacy_proxy(_enabled: bool, _mode: &ProxyScopeMode) {}
+
+/// 退出时同步恢复 WinHTTP 代理，确保在进程结束前完成
 #[cfg(target_os = "windows")]
-static DEFAULT_BYPASS: &str = "localhost;127.*;192.168.*;10.*;172.16.*;172.17.*;172.18.*;172.19.*;172.20.*;172.21.*;172.22.*;172.23.*;172.24.*;172.25.*;172.26.*;172.27.*;172.28.*;172.29.*;172.30.*;172.31.*;<local>";
+fn restore_legacy_proxy() {
+    logging_error!(Type::Core, crate::core::win_proxy_sync::restore());
+}
+
+#[cfg(not(target_os = "windows"))]
+const fn restore_legacy_proxy() {}
+
+/// Linux 上按桌面环境设置系统代理，关闭时恢复修改前的设置
 #[cfg(target_os = "linux")]
-static DEFAULT_BYPASS: &str = "localhost,127.0.0.1,192.168.0.0/16,10.0.0.0/8,172.16.0.0/12,::1";
-#[cfg(target_os = "macos")]
-static DEFAULT_BYPASS: &str =
-    "127.0.0.1,192.168.0.0/16,10.0.0.0/8,172.16.0.0/12,localhost,*.local,*.crashlytics.com,<local>";
+fn apply_desktop_proxy(backend: Option<&str>, mode: &ProxyScopeMode) -> Result<()> {
+    use crate::core::linux_proxy::{self, DesktopBackend};
 
-async fn get_bypass() -> String {
-    let use_default = Config::verge().await.latest_arc().use_default_bypass.unwrap_or(true);
-    let res = {
-        let verge = Config::verge().await;
-        let verge = verge.latest_arc();
-        verge.system_proxy_bypass.clone()
-    };
-    let custom_bypass = match res {
-        Some(bypass) => bypass,
-        None => "".into(),
-    };
+    linux_proxy::apply(DesktopBackend::resolve(backend), mode)
+}
+
+#[cfg(not(target_os = "linux"))]
+#[allow(clippy::unnecessary_wraps)]
+const fn apply_desktop_proxy(_backend: Option<&str>, _mode: &ProxyScopeMode) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn uses_environment_file(backend: Option<&str>) -> bool {
+    use crate::core::linux_proxy::DesktopBackend;
 
-    if custom_bypass.is_empty() {
-        DEFAULT_BYPASS.into()
-    } else if use_default {
-        format!("{DEFAULT_BYPASS},{custom_bypass}").into()
+    DesktopBackend::resolve(backend) == DesktopBackend::Environment
+}
+
+#[cfg(not(target_os = "linux"))]
+const fn uses_environment_file(_backend: Option<&str>) -> bool {
+    false
+}
+
+/// 先关闭不使用的一方，避免手动代理与 PAC 同时生效；
+/// macOS 上系统代理由 [`apply_proxy_scope`] 通过 SystemConfiguration 设置，
+/// Linux 上由 [`apply_desktop_proxy`] 按桌面环境设置，这里都跳过
+fn set_sysproxy(sys: &Sysproxy, auto: &Autoproxy) -> Result<()> {
+    if cfg!(any(target_os = "macos", target_os = "linux")) {
+        return Ok(());
+    }
+    if sys.enable {
+        auto.set_auto_proxy()?;
+        sys.set_system_proxy()?;
     } else {
-        custom_bypass
+        sys.set_system_proxy()?;
+        auto.set_auto_proxy()?;
     }
+    Ok(())
 }
 
 singleton!(Sysopt, SYSOPT);
@@ -74,7 +135,7 @@ impl Sysopt {
         Self::default()
     }
 
-    fn access_guard(&self) -> Arc<RwLock<GuardMonitor>> {
+    fn access_guard(&self) -> Arc<RwLock<ProxyGuard>> {
         Arc::clone(&self.guard)
     }
 
@@ -107,6 +168,9 @@ impl Sysopt {
             let guard = self.access_guard();
             guard.write().start();
         }
+        // 系统代理被改写时立即检查，不必等到下一轮
+        #[cfg(target_os = "macos")]
+        crate::core::macos_proxy::watch_changes(|| Self::global().access_guard().read().wake());
     }
 
     /// init the sysproxy
@@ -129,13 +193,7 @@ impl Sysopt {
         }
 
         let verge = Config::verge().await.latest_arc();
-        let port = {
-            let verge_port = verge.verge_mixed_port;
-            match verge_port {
-                Some(port) => port,
-                None => Config::clash().await.latest_arc().get_mixed_port(),
-            }
-        };
+        let port = Config::mixed_port().await;
         let pac_port = IVerge::get_singleton_port();
 
         let (sys_enable, pac_enable, proxy_host, proxy_guard) = {
@@ -146,6 +204,12 @@ impl Sysopt {
                 verge.enable_proxy_guard.unwrap_or_default(),
             )
         };
+        let interfaces = verge.sysproxy_interfaces.clone().unwrap_or_default();
+        let sync_winhttp = verge.enable_winhttp_proxy_sync.unwrap_or_default();
+        let desktop_backend = verge.linux_proxy_backend.clone();
+        // 守护会把代理重新应用到全部网络服务，限定范围时不启用；
+        // environment.d 只在登录时读取，也无需守护
+        let proxy_guard = proxy_guard && interfaces.is_empty() && !uses_environment_file(desktop_backend.as_deref());
 
         // 先 await, 避免持有锁导致的 Send 问题
         let bypass = get_bypass().await;
@@ -159,24 +223,31 @@ impl Sysopt {
         auto.enable = false;
         auto.url = format!("http://{proxy_host}:{pac_port}/commands/pac");
 
-        self.access_guard().write().set_guard_type(GuardType::None);
+        self.access_guard().write().set_guard_type(GuardTarget::None);
 
         if !sys_enable && !pac_enable {
             // disable proxy
-            sys.set_system_proxy()?;
-            auto.set_auto_proxy()?;
+            set_sysproxy(sys, auto)?;
+            apply_proxy_scope(&interfaces, &ProxyScopeMode::Off)?;
+            apply_desktop_proxy(desktop_backend.as_deref(), &ProxyScopeMode::Off)?;
+            sync_legacy_proxy(false, &ProxyScopeMode::Off);
             return Ok(());
         }
 
         if pac_enable {
             sys.enable = false;
             auto.enable = true;
-            sys.set_system_proxy()?;
-            auto.set_auto_proxy()?;
+            set_sysproxy(sys, auto)?;
+            let mode = ProxyScopeMode::Pac {
+                url: auto.url.clone().into(),
+            };
+            apply_proxy_scope(&interfaces, &mode)?;
+            apply_desktop_proxy(desktop_backend.as_deref(), &mode)?;
+            sync_legacy_proxy(sync_winhttp, &mode);
             if proxy_guard {
                 self.access_guard()
                     .write()
-                    .set_guard_type(GuardType::Autoproxy(auto.clone()));
+                    .set_guard_type(GuardTarget::Autoproxy(auto.clone()));
             }
             return Ok(());
         }
@@ -184,12 +255,19 @@ impl Sysopt {
         if sys_enable {
             auto.enable = false;
             sys.enable = true;
-            auto.set_auto_proxy()?;
-            sys.set_system_proxy()?;
+            set_sysproxy(sys, auto)?;
+            let mode = ProxyScopeMode::Manual {
+                host: sys.host.clone().into(),
+                port: sys.port,
+                bypass: sys.bypass.clone().into(),
+            };
+            apply_proxy_scope(&interfaces, &mode)?;
+            apply_desktop_proxy(desktop_backend.as_deref(), &mode)?;
+            sync_legacy_proxy(sync_winhttp, &mode);
             if proxy_guard {
                 self.access_guard()
                     .write()
-                    .set_guard_type(GuardType::Sysproxy(sys.clone()));
+                    .set_guard_type(GuardTarget::Sysproxy(sys.clone()));
             }
             return Ok(());
         }
@@ -198,7 +276,6 @@ impl Sysopt {
     }
 
     /// reset the sysproxy
-    #[allow(clippy::unused_async)]
     pub async fn reset_sysproxy(&self) -> Result<()> {
         if self
             .reset_sysproxy
@@ -211,15 +288,27 @@ impl Sysopt {
             self.reset_sysproxy.store(false, Ordering::SeqCst);
         }
 
+        let (interfaces, desktop_backend) = {
+            let verge = Config::verge().await.latest_arc();
+            (
+                verge.sysproxy_interfaces.clone().unwrap_or_default(),
+                verge.linux_proxy_backend.clone(),
+            )
+        };
+
         // close proxy guard
-        self.access_guard().write().set_guard_type(GuardType::None);
+        self.access_guard().write().set_guard_type(GuardTarget::None);
 
         // 直接关闭所有代理
-        let (sys, auto) = &mut *self.inner_proxy.write();
-        sys.enable = false;
-        sys.set_system_proxy()?;
-        auto.enable = false;
-        auto.set_auto_proxy()?;
+        {
+            let (sys, auto) = &mut *self.inner_proxy.write();
+            sys.enable = false;
+            auto.enable = false;
+            set_sysproxy(sys, auto)?;
+        }
+        apply_proxy_scope(&interfaces, &ProxyScopeMode::Off)?;
+        apply_desktop_proxy(desktop_backend.as_deref(), &ProxyScopeMode::Off)?;
+        restore_legacy_proxy();
 
         Ok(())
     }
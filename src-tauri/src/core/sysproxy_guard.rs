@@ -0,0 +1,278 @@
+//! 系统代理守护
+//!
+//! 定期读取系统代理设置并与应用写入的设置比较。被其他程序（企业管理工具、其他 VPN 客户端等）
+//! 改写时重新应用，并发送 `sysproxy-conflict` 事件，说明被改动的内容。
+
+use crate::{core::handle, process::AsyncHandler};
+use clash_verge_logging::{Type, logging};
+use parking_lot::RwLock;
+use serde::Serialize;
+use smartstring::alias::String;
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+use sysproxy::{Autoproxy, Sysproxy};
+use tauri::{Emitter as _, async_runtime::JoinHandle};
+use tokio::sync::Notify;
+
+const CONFLICT_EVENT: &str = "sysproxy-conflict";
+
+/// 守护的目标设置
+#[derive(Debug, Clone)]
+pub enum GuardTarget {
+    None,
+    Sysproxy(Sysproxy),
+    Autoproxy(Autoproxy),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ConflictKind {
+    /// 代理被关闭
+    Disabled,
+    /// 代理服务器地址或端口被修改
+    Server,
+    /// PAC 地址被修改
+    PacUrl,
+    /// 手动代理与 PAC 被互相切换
+    Mode,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SysproxyConflict {
+    pub kind: ConflictKind,
+    pub expected: String,
+    pub found: String,
+    /// 是否已恢复为应用写入的设置
+    pub reapplied: bool,
+    pub time: i64,
+}
+
+impl SysproxyConflict {
+    fn new(kind: ConflictKind, expected: impl Into<String>, found: impl Into<String>) -> Self {
+        Self {
+            kind,
+            expected: expected.into(),
+            found: found.into(),
+            reapplied: false,
+            time: chrono::Local::now().timestamp(),
+        }
+    }
+}
+
+fn server_of(proxy: &Sysproxy) -> String {
+    format!("{}:{}", proxy.host, proxy.port).into()
+}
+
+/// 比较手动代理设置，`current_pac` 为当前的 PAC 设置
+fn sysproxy_conflict(expected: &Sysproxy, current: &Sysproxy, current_pac: &Autoproxy) -> Option<SysproxyConflict> {
+    let expected_server = server_of(expected);
+    if current_pac.enable {
+        return Some(SysproxyConflict::new(
+            ConflictKind::Mode,
+            expected_server,
+            current_pac.url.as_str(),
+        ));
+    }
+    if !current.enable {
+        return Some(SysproxyConflict::new(
+            ConflictKind::Disabled,
+            expected_server,
+            "disabled",
+        ));
+    }
+    let found = server_of(current);
+    (found != expected_server).then(|| SysproxyConflict::new(ConflictKind::Server, expected_server, found))
+}
+
+fn autoproxy_conflict(expected: &Autoproxy, current: &Autoproxy, current_sys: &Sysproxy) -> Option<SysproxyConflict> {
+    if !current.enable {
+        let (kind, found) = if current_sys.enable {
+            (ConflictKind::Mode, server_of(current_sys))
+        } else {
+            (ConflictKind::Disabled, "disabled".into())
+        };
+        return Some(SysproxyConflict::new(kind, expected.url.as_str(), found));
+    }
+    (current.url != expected.url)
+        .then(|| SysproxyConflict::new(ConflictKind::PacUrl, expected.url.as_str(), current.url.as_str()))
+}
+
+/// 读取当前系统设置，与目标不一致时重新应用
+fn check_and_restore(target: &GuardTarget) -> Option<SysproxyConflict> {
+    if matches!(target, GuardTarget::None) {
+        return None;
+    }
+    let (current_sys, current_auto) = match (Sysproxy::get_system_proxy(), Autoproxy::get_auto_proxy()) {
+        (Ok(sys), Ok(auto)) => (sys, auto),
+        (Err(err), _) | (_, Err(err)) => {
+            logging!(warn, Type::Core, "Failed to read system proxy settings: {err}");
+            return None;
+        }
+    };
+
+    let (mut conflict, result) = match target {
+        GuardTarget::None => return None,
+        GuardTarget::Sysproxy(expected) => {
+            let conflict = sysproxy_conflict(expected, &current_sys, &current_auto)?;
+            let disable_pac = Autoproxy {
+                enable: false,
+                url: current_auto.url,
+            };
+            (
+                conflict,
+                disable_pac.set_auto_proxy().and_then(|()| expected.set_system_proxy()),
+            )
+        }
+        GuardTarget::Autoproxy(expected) => {
+            let conflict = autoproxy_conflict(expected, &current_auto, &current_sys)?;
+            let disable_sys = Sysproxy {
+                enable: false,
+                ..current_sys
+            };
+            (
+                conflict,
+                disable_sys.set_system_proxy().and_then(|()| expected.set_auto_proxy()),
+            )
+        }
+    };
+    if let Err(err) = &result {
+        logging!(error, Type::Core, "Failed to re-apply system proxy: {err}");
+    }
+    conflict.reapplied = result.is_ok();
+    Some(conflict)
+}
+
+/// 定期检查系统代理的后台任务
+pub struct ProxyGuard {
+    target: Arc<RwLock<GuardTarget>>,
+    interval_secs: Arc<AtomicU64>,
+    /// 收到系统代理变化的通知时提前检查
+    wake: Arc<Notify>,
+    task: Option<JoinHandle<()>>,
+}
+
+impl ProxyGuard {
+    pub fn new(target: GuardTarget, interval: Duration) -> Self {
+        Self {
+            target: Arc::new(RwLock::new(target)),
+            interval_secs: Arc::new(AtomicU64::new(interval.as_secs())),
+            wake: Arc::new(Notify::new()),
+            task: None,
+        }
+    }
+
+    pub fn set_guard_type(&self, target: GuardTarget) {
+        *self.target.write() = target;
+    }
+
+    /// 下一轮检查起生效
+    pub fn set_interval(&self, interval: Duration) {
+        self.interval_secs.store(interval.as_secs(), Ordering::Relaxed);
+    }
+
+    /// 立即进行一次检查，守护未运行时无效果
+    #[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+    pub fn wake(&self) {
+        self.wake.notify_one();
+    }
+
+    pub fn start(&mut self) {
+        if self.task.as_ref().is_some_and(|task| !task.inner().is_finished()) {
+            return;
+        }
+        let target = Arc::clone(&self.target);
+        let interval_secs = Arc::clone(&self.interval_secs);
+        let wake = Arc::clone(&self.wake);
+        self.task = Some(AsyncHandler::spawn(move || async move {
+            loop {
+                let secs = interval_secs.load(Ordering::Relaxed).max(1);
+                tokio::select! {
+                    () = tokio::time::sleep(Duration::from_secs(secs)) => {}
+                    () = wake.notified() => {}
+                }
+
+                let current = target.read().clone();
+                let Ok(Some(conflict)) = AsyncHandler::spawn_blocking(move || check_and_restore(&current)).await else {
+                    continue;
+                };
+                logging!(
+                    warn,
+                    Type::Core,
+                    "System proxy was changed by another program ({:?}: expected {}, found {}), re-applied: {}",
+                    conflict.kind,
+                    conflict.expected,
+                    conflict.found,
+                    conflict.reapplied
+                );
+                let _ = handle::Handle::app_handle().emit(CONFLICT_EVENT, &conflict);
+            }
+        }));
+    }
+
+    pub fn stop(&mut self) {
+        if let Some(task) = self.task.take() {
+            task.abort();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manual(enable: bool, port: u16) -> Sysproxy {
+        Sysproxy {
+            enable,
+            host: "127.0.0.1".into(),
+            port,
+            ..Sysproxy::default()
+        }
+    }
+
+    fn pac(enable: bool, url: &str) -> Autoproxy {
+        Autoproxy {
+            enable,
+            url: url.into(),
+        }
+    }
+
+    #[test]
+    fn test_detect_sysproxy_conflict() {
+        let expected = manual(true, 7897);
+        let no_pac = pac(false, "");
+        assert!(sysproxy_conflict(&expected, &manual(true, 7897), &no_pac).is_none());
+        assert_eq!(
+            sysproxy_conflict(&expected, &manual(true, 8080), &no_pac).map(|c| (c.kind, c.found)),
+            Some((ConflictKind::Server, "127.0.0.1:8080".into()))
+        );
+        assert_eq!(
+            sysproxy_conflict(&expected, &manual(false, 7897), &no_pac).map(|c| c.kind),
+            Some(ConflictKind::Disabled)
+        );
+        assert_eq!(
+            sysproxy_conflict(&expected, &manual(true, 7897), &pac(true, "http://corp/proxy.pac")).map(|c| c.kind),
+            Some(ConflictKind::Mode)
+        );
+    }
+
+    #[test]
+    fn test_detect_autoproxy_conflict() {
+        let url = "http://127.0.0.1:33331/commands/pac";
+        let expected = pac(true, url);
+        let no_sys = manual(false, 0);
+        assert!(autoproxy_conflict(&expected, &pac(true, url), &no_sys).is_none());
+        assert_eq!(
+            autoproxy_conflict(&expected, &pac(true, "http://corp/proxy.pac"), &no_sys).map(|c| c.kind),
+            Some(ConflictKind::PacUrl)
+        );
+        assert_eq!(
+            autoproxy_conflict(&expected, &pac(false, url), &manual(true, 8080)).map(|c| (c.kind, c.found)),
+            Some((ConflictKind::Mode, "127.0.0.1:8080".into()))
+        );
+    }
+}
@@ -0,0 +1,283 @@
+//! Telegram 机器人
+//!
+//! 开启 `enable_telegram_notify` 后，归类的系统通知（内核崩溃、流量与到期提醒、出口 IP 变化等）
+//! 会同时发送到 `telegram_chat_id`。开启 `enable_telegram_commands` 后通过长轮询接收命令，
+//! 只响应来自该会话的消息：
+//!
+//! - `/status` 当前模式、系统代理、TUN、实时速率与主代理组节点
+//! - `/switch <节点>` 切换主代理组（全局模式下为 GLOBAL，否则为第一个包含该节点的代理组）
+//!
+//! `telegram_bot_token` 由 [`crate::core::secrets`] 存入凭据库，备份时不写入，恢复后保留本机的令牌。
+
+use crate::{
+    config::Config,
+    core::{CoreManager, audit::AuditSource, handle, traffic::TrafficHub, tray::speed_rate},
+    feat,
+    process::AsyncHandler,
+    singleton,
+};
+use anyhow::{Result, bail};
+use clash_verge_logging::{Type, logging};
+use serde::{Deserialize, de::DeserializeOwned};
+use serde_json::json;
+use smartstring::alias::String;
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+const API_BASE: &str = "https://api.telegram.org";
+const POLL_TIMEOUT_SECS: u64 = 25;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(POLL_TIMEOUT_SECS + 10);
+const IDLE_INTERVAL: Duration = Duration::from_secs(30);
+const ERROR_DELAY: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum BotCommand {
+    Status,
+    Switch(String),
+    Help,
+}
+
+/// 解析命令文本，兼容群聊中的 `/status@bot_name` 形式
+fn parse_command(text: &str) -> Option<BotCommand> {
+    let text = text.trim();
+    let (command, arg) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+    let command = command.strip_prefix('/')?;
+    let command = command.split_once('@').map_or(command, |(c, _)| c);
+    let arg = arg.trim();
+    match command {
+        "status" => Some(BotCommand::Status),
+        "switch" if !arg.is_empty() => Some(BotCommand::Switch(arg.into())),
+        "start" | "help" | "switch" => Some(BotCommand::Help),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiResponse<T> {
+    ok: bool,
+    result: Option<T>,
+    description: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Update {
+    update_id: i64,
+    message: Option<Message>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Message {
+    chat: Chat,
+    text: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Chat {
+    id: i64,
+}
+
+struct BotConfig {
+    token: String,
+    chat_id: String,
+}
+
+pub struct TelegramBot {
+    client: reqwest::Client,
+    runner_started: AtomicBool,
+}
+
+singleton!(TelegramBot, TELEGRAM_BOT);
+
+impl TelegramBot {
+    fn new() -> Self {
+        Self {
+            client: reqwest::Client::builder()
+                .timeout(REQUEST_TIMEOUT)
+                .build()
+                .unwrap_or_default(),
+            runner_started: AtomicBool::new(false),
+        }
+    }
+
+    async fn config(commands: bool) -> Option<BotConfig> {
+        let verge = Config::verge().await.latest_arc();
+        let enabled = if commands {
+            verge.enable_telegram_commands
+        } else {
+            verge.enable_telegram_notify
+        };
+        if !enabled.unwrap_or(false) {
+            return None;
+        }
+        let token = verge.telegram_bot_token.clone().filter(|t| !t.is_empty())?;
+        let chat_id = verge.telegram_chat_id.clone().filter(|c| !c.is_empty())?;
+        Some(BotConfig { token, chat_id })
+    }
+
+    /// 推送一条通知，未开启或未配置时忽略
+    pub fn push(&self, text: std::string::String) {
+        AsyncHandler::spawn(move || async move {
+            let Some(config) = Self::config(false).await else {
+                return;
+            };
+            if let Err(err) = Self::global().send_message(&config, &text).await {
+                logging!(warn, Type::Network, "Failed to push Telegram notification: {err}");
+            }
+        });
+    }
+
+    /// 错误信息中去掉请求地址，避免 token 写入日志
+    async fn call<T: DeserializeOwned>(request: reqwest::RequestBuilder) -> Result<Option<T>> {
+        let response: ApiResponse<T> = request
+            .send()
+            .await
+            .map_err(reqwest::Error::without_url)?
+            .json()
+            .await
+            .map_err(reqwest::Error::without_url)?;
+        if !response.ok {
+            bail!("{}", response.description.unwrap_or_default());
+        }
+        Ok(response.result)
+    }
+
+    async fn send_message(&self, config: &BotConfig, text: &str) -> Result<()> {
+        let request = self
+            .client
+            .post(format!("{API_BASE}/bot{}/sendMessage", config.token))
+            .json(&json!({ "chat_id": config.chat_id.as_str(), "text": text }));
+        Self::call::<serde_json::Value>(request).await?;
+        Ok(())
+    }
+
+    async fn get_updates(&self, config: &BotConfig, offset: Option<i64>) -> Result<Vec<Update>> {
+        let mut query = vec![("timeout", POLL_TIMEOUT_SECS.to_string())];
+        if let Some(offset) = offset {
+            query.push(("offset", offset.to_string()));
+        }
+        let request = self
+            .client
+            .get(format!("{API_BASE}/bot{}/getUpdates", config.token))
+            .query(&query);
+        Ok(Self::call(request).await?.unwrap_or_default())
+    }
+
+    /// 启动命令轮询，重复调用无副作用
+    pub fn init(&self) {
+        if self.runner_started.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        AsyncHandler::spawn(|| async {
+            let bot = Self::global();
+            let mut offset: Option<i64> = None;
+            loop {
+                if handle::Handle::global().is_exiting() {
+                    break;
+                }
+                let Some(config) = Self::config(true).await else {
+                    offset = None;
+                    tokio::time::sleep(IDLE_INTERVAL).await;
+                    continue;
+                };
+                let updates = match bot.get_updates(&config, offset).await {
+                    Ok(updates) => updates,
+                    Err(err) => {
+                        logging!(debug, Type::Network, "Telegram polling failed: {err}");
+                        tokio::time::sleep(ERROR_DELAY).await;
+                        continue;
+                    }
+                };
+                let next = updates.iter().map(|u| u.update_id + 1).max();
+                // 首次轮询只确认积压的消息，避免执行离线期间的旧命令
+                if offset.is_some() {
+                    for update in updates {
+                        bot.handle_update(&config, update).await;
+                    }
+                }
+                offset = next.or(offset).or(Some(0));
+            }
+        });
+    }
+
+    async fn handle_update(&self, config: &BotConfig, update: Update) {
+        let Some(message) = update.message else {
+            return;
+        };
+        if message.chat.id.to_string() != config.chat_id.as_str() {
+            return;
+        }
+        let Some(command) = message.text.as_deref().and_then(parse_command) else {
+            return;
+        };
+        logging!(info, Type::Network, "Telegram command: {:?}", command);
+        let reply = match command {
+            BotCommand::Status => status_text().await,
+            BotCommand::Switch(node) => match AuditSource::Telegram.scope(switch_node(&node)).await {
+                Ok(group) => format!("{group} -> {node}"),
+                Err(err) => format!("Switch failed: {err}"),
+            },
+            BotCommand::Help => "/status - show current status\n/switch <node> - switch the main proxy group".into(),
+        };
+        if let Err(err) = self.send_message(config, &reply).await {
+            logging!(warn, Type::Network, "Failed to reply Telegram command: {err}");
+        }
+    }
+}
+
+fn on_off(value: Option<bool>) -> &'static str {
+    if value.unwrap_or(false) { "on" } else { "off" }
+}
+
+async fn status_text() -> std::string::String {
+    let mode = feat::clash_mode().await;
+    let (system_proxy, tun) = {
+        let verge = Config::verge().await.latest_arc();
+        (verge.enable_system_proxy, verge.enable_tun_mode)
+    };
+    let mut lines = vec![
+        format!("Core: {}", CoreManager::global().get_running_mode()),
+        format!("Mode: {mode}"),
+        format!("System proxy: {}", on_off(system_proxy)),
+        format!("TUN: {}", on_off(tun)),
+        format!("Speed: {}", speed_rate::speed_text(TrafficHub::global().latest())),
+    ];
+    if let Ok(proxies) = handle::Handle::mihomo().await.get_proxies().await
+        && let Some(group) = feat::main_group(&proxies, &mode, None)
+        && let Some(now) = proxies.proxies.get(group).and_then(|g| g.now.as_deref())
+    {
+        lines.push(format!("{group}: {now}"));
+    }
+    lines.join("\n")
+}
+
+async fn switch_node(node: &str) -> Result<String> {
+    let mode = feat::clash_mode().await;
+    let proxies = handle::Handle::mihomo().await.get_proxies().await?;
+    let Some(group) = feat::main_group(&proxies, &mode, Some(node)) else {
+        bail!("no proxy group contains {node}");
+    };
+    let group: String = group.into();
+    feat::switch_proxy_node(&group, node).await;
+    Ok(group)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_command() {
+        assert_eq!(parse_command("/status"), Some(BotCommand::Status));
+        assert_eq!(parse_command("/status@verge_bot"), Some(BotCommand::Status));
+        assert_eq!(
+            parse_command("/switch  HK 01 "),
+            Some(BotCommand::Switch("HK 01".into()))
+        );
+        assert_eq!(parse_command("/switch"), Some(BotCommand::Help));
+        assert_eq!(parse_command("hello"), None);
+        assert_eq!(parse_command("/unknown"), None);
+    }
+}
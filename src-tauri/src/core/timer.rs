@@ -1,8 +1,9 @@
-use crate::{config::Config, feat, singleton, utils::resolve::is_resolve_done};
-use anyhow::{Context as _, Result};
+use crate::{config::Config, feat, process::AsyncHandler, singleton, utils::resolve::is_resolve_done};
+use anyhow::{Context as _, Result, anyhow};
 use clash_verge_logging::{Type, logging, logging_error};
 use delay_timer::prelude::{DelayTimer, DelayTimerBuilder, TaskBuilder};
 use parking_lot::RwLock;
+use serde::Serialize;
 use smartstring::alias::String;
 use std::{
     collections::HashMap,
@@ -17,6 +18,31 @@ use tokio::time::{sleep, timeout};
 
 type TaskID = u64;
 
+/// 订阅更新失败后的重试间隔，超过更新周期的重试会被跳过
+const RETRY_DELAYS_SECS: [u64; 3] = [60, 180, 600];
+/// 随机延迟上限，避免多个订阅同时请求
+const MAX_JITTER_SECS: u64 = 60;
+const NETWORK_POLL_INTERVAL: Duration = Duration::from_secs(15);
+
+/// 最近一次订阅更新的结果，成功后清空
+#[derive(Debug, Clone, Default)]
+struct UpdateState {
+    failures: u32,
+    last_error: Option<String>,
+}
+
+/// `get_profile_schedule` 返回的单个订阅计划
+#[derive(Debug, Clone, Serialize)]
+pub struct ProfileSchedule {
+    pub uid: String,
+    pub name: Option<String>,
+    pub interval_minutes: u64,
+    pub last_updated: Option<i64>,
+    pub next_update: Option<i64>,
+    pub failures: u32,
+    pub last_error: Option<String>,
+}
+
 #[derive(Debug, Clone)]
 pub struct TimerTask {
     pub task_id: TaskID,
@@ -37,6 +63,12 @@ pub struct Timer {
 
     /// Flag to mark if timer is initialized - atomic for better performance
     pub initialized: AtomicBool,
+
+    /// last update result per profile uid
+    update_states: RwLock<HashMap<String, UpdateState>>,
+
+    /// whether the network reconnect watcher is running
+    network_watch_started: AtomicBool,
 }
 
 // Use singleton macro
@@ -49,6 +81,8 @@ impl Timer {
             timer_map: Arc::new(RwLock::new(HashMap::new())),
             timer_count: AtomicU64::new(1),
             initialized: AtomicBool::new(false),
+            update_states: RwLock::new(HashMap::new()),
+            network_watch_started: AtomicBool::new(false),
         }
     }
 
@@ -89,57 +123,132 @@ impl Timer {
             }
         }
 
-        let cur_timestamp = chrono::Local::now().timestamp();
+        let profiles_to_update = Self::overdue_profiles().await;
+        self.advance_tasks(profiles_to_update);
+        self.start_network_watch();
 
-        // Collect profiles that need immediate update
-        let profiles_to_update = if let Some(items) = Config::profiles().await.latest_arc().get_items() {
-            items
-                .iter()
-                .filter_map(|item| {
-                    let allow_auto_update = item.option.as_ref()?.allow_auto_update.unwrap_or_default();
-                    if !allow_auto_update {
-                        return None;
-                    }
+        logging!(info, Type::Timer, "Timer initialization completed");
+        Ok(())
+    }
 
-                    let interval = item.option.as_ref()?.update_interval? as i64;
-                    let updated = item.updated? as i64;
-                    let uid = item.uid.as_ref()?;
+    /// 已到更新时间或上次更新失败的订阅
+    async fn overdue_profiles() -> Vec<String> {
+        let cur_timestamp = chrono::Local::now().timestamp();
+        let failed: Vec<String> = Self::global().update_states.read().keys().cloned().collect();
 
-                    if interval > 0 && cur_timestamp - updated >= interval * 60 {
-                        logging!(info, Type::Timer, "需要立即更新的配置: uid={}", uid);
-                        Some(uid.clone())
-                    } else {
-                        None
-                    }
-                })
-                .collect::<Vec<String>>()
-        } else {
-            Vec::new()
+        let Some(items) = Config::profiles().await.latest_arc().get_items().cloned() else {
+            return Vec::new();
         };
+        items
+            .iter()
+            .filter_map(|item| {
+                let allow_auto_update = item.option.as_ref()?.allow_auto_update.unwrap_or_default();
+                if !allow_auto_update {
+                    return None;
+                }
 
-        // Advance tasks outside of locks to minimize lock contention
-        if !profiles_to_update.is_empty() {
-            logging!(
-                info,
-                Type::Timer,
-                "需要立即更新的配置数量: {}",
-                profiles_to_update.len()
-            );
-            let timer_map = self.timer_map.read();
-            let delay_timer = self.delay_timer.write();
+                let interval = item.option.as_ref()?.update_interval? as i64;
+                let updated = item.updated? as i64;
+                let uid = item.uid.as_ref()?;
 
-            for uid in profiles_to_update {
-                if let Some(task) = timer_map.get(&uid) {
-                    logging!(info, Type::Timer, "立即执行任务: uid={}", uid);
-                    if let Err(e) = delay_timer.advance_task(task.task_id) {
-                        logging!(warn, Type::Timer, "Failed to advance task {}: {}", uid, e);
-                    }
+                if interval > 0 && (cur_timestamp - updated >= interval * 60 || failed.contains(uid)) {
+                    logging!(info, Type::Timer, "需要立即更新的配置: uid={}", uid);
+                    Some(uid.clone())
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// 立即执行指定订阅的定时任务
+    fn advance_tasks(&self, uids: Vec<String>) {
+        if uids.is_empty() {
+            return;
+        }
+        logging!(info, Type::Timer, "需要立即更新的配置数量: {}", uids.len());
+        let timer_map = self.timer_map.read();
+        let delay_timer = self.delay_timer.write();
+
+        for uid in uids {
+            if let Some(task) = timer_map.get(&uid) {
+                logging!(info, Type::Timer, "立即执行任务: uid={}", uid);
+                if let Err(e) = delay_timer.advance_task(task.task_id) {
+                    logging!(warn, Type::Timer, "Failed to advance task {}: {}", uid, e);
                 }
             }
         }
+    }
 
-        logging!(info, Type::Timer, "Timer initialization completed");
-        Ok(())
+    /// 网络恢复时补上错过或失败的订阅更新
+    pub async fn on_network_reconnected(&self) {
+        logging!(info, Type::Timer, "Network reconnected, checking overdue subscriptions");
+        self.advance_tasks(Self::overdue_profiles().await);
+    }
+
+    /// 轮询网卡状态，从无可用网络恢复时触发 [`Self::on_network_reconnected`]
+    fn start_network_watch(&self) {
+        if self.network_watch_started.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        AsyncHandler::spawn(|| async {
+            let mut online = true;
+            loop {
+                sleep(NETWORK_POLL_INTERVAL).await;
+                let now_online = AsyncHandler::spawn_blocking(has_network).await.unwrap_or(true);
+                if now_online && !online {
+                    Self::global().on_network_reconnected().await;
+                }
+                online = now_online;
+            }
+        });
+    }
+
+    fn record_result(&self, uid: &String, result: &Result<()>) {
+        let mut states = self.update_states.write();
+        match result {
+            Ok(()) => {
+                states.remove(uid);
+            }
+            Err(err) => {
+                let state = states.entry(uid.clone()).or_default();
+                state.failures += 1;
+                state.last_error = Some(err.to_string().into());
+            }
+        }
+    }
+
+    /// 所有启用自动更新的订阅及其上次/下次更新时间
+    pub async fn get_schedule(&self) -> Vec<ProfileSchedule> {
+        let intervals: HashMap<String, u64> = self
+            .timer_map
+            .read()
+            .iter()
+            .map(|(uid, task)| (uid.clone(), task.interval_minutes))
+            .collect();
+        let Some(items) = Config::profiles().await.latest_arc().get_items().cloned() else {
+            return Vec::new();
+        };
+        let states = self.update_states.read();
+
+        items
+            .iter()
+            .filter_map(|item| {
+                let uid = item.uid.as_ref()?;
+                let interval_minutes = *intervals.get(uid)?;
+                let last_updated = item.updated.map(|t| t as i64).filter(|&t| t > 0);
+                let state = states.get(uid).cloned().unwrap_or_default();
+                Some(ProfileSchedule {
+                    uid: uid.clone(),
+                    name: item.name.clone(),
+                    interval_minutes,
+                    last_updated,
+                    next_update: last_updated.map(|t| t + interval_minutes as i64 * 60),
+                    failures: state.failures,
+                    last_error: state.last_error,
+                })
+            })
+            .collect()
     }
 
     /// Refresh timer tasks with better error handling
@@ -342,7 +451,8 @@ impl Timer {
                 let uid = uid.clone();
                 Box::pin(async move {
                     Self::wait_until_resolve_done(Duration::from_millis(5000)).await;
-                    Self::async_task(&uid).await;
+                    sleep(jitter(minutes)).await;
+                    Self::run_with_retry(&uid, minutes).await;
                 }) as Pin<Box<dyn std::future::Future<Output = ()> + Send>>
             })
             .context("failed to create timer task")?;
@@ -419,12 +529,40 @@ impl Timer {
         }
     }
 
+    /// 执行一次订阅更新，失败时按 [`RETRY_DELAYS_SECS`] 重试
+    async fn run_with_retry(uid: &String, interval_minutes: u64) {
+        let delays = std::iter::once(0).chain(RETRY_DELAYS_SECS);
+        for (attempt, delay) in delays.enumerate() {
+            if delay >= interval_minutes * 60 {
+                break;
+            }
+            sleep(Duration::from_secs(delay)).await;
+
+            let result = Self::async_task(uid).await;
+            Self::global().record_result(uid, &result);
+            match result {
+                Ok(()) => return,
+                Err(e) if attempt < RETRY_DELAYS_SECS.len() => {
+                    logging!(
+                        warn,
+                        Type::Timer,
+                        "订阅更新失败，稍后重试: uid={}, attempt={}, error={}",
+                        uid,
+                        attempt + 1,
+                        e
+                    );
+                }
+                Err(e) => logging!(warn, Type::Timer, "订阅更新多次失败: uid={}, error={}", uid, e),
+            }
+        }
+    }
+
     /// Async task with better error handling and logging
-    async fn async_task(uid: &String) {
+    async fn async_task(uid: &String) -> Result<()> {
         let task_start = std::time::Instant::now();
         logging!(info, Type::Timer, "Running timer task for profile: {}", uid);
 
-        match tokio::time::timeout(std::time::Duration::from_secs(40), async {
+        let result = match tokio::time::timeout(std::time::Duration::from_secs(40), async {
             Self::emit_update_event(uid, true);
 
             let is_current = Config::profiles().await.latest_arc().current.as_ref() == Some(uid);
@@ -444,18 +582,22 @@ impl Timer {
                         uid,
                         duration
                     );
+                    Ok(())
                 }
                 Err(e) => {
                     logging_error!(Type::Timer, "Failed to update profile uid {}: {}", uid, e);
+                    Err(e)
                 }
             },
             Err(_) => {
                 logging_error!(Type::Timer, "Timer task timed out for uid: {}", uid);
+                Err(anyhow!("timer task timed out"))
             }
-        }
+        };
 
         // Emit completed event
         Self::emit_update_event(uid, false);
+        result
     }
 
     async fn wait_until_resolve_done(max_wait: Duration) {
@@ -469,6 +611,25 @@ impl Timer {
     }
 }
 
+/// 随机延迟，不超过更新周期的十分之一
+fn jitter(interval_minutes: u64) -> Duration {
+    let max = MAX_JITTER_SECS.min(interval_minutes * 6);
+    let random = getrandom::u64().unwrap_or_default();
+    Duration::from_secs(random % (max + 1))
+}
+
+/// 是否存在可用的非回环 IPv4 地址
+fn has_network() -> bool {
+    use network_interface::{Addr, NetworkInterface, NetworkInterfaceConfig as _};
+
+    NetworkInterface::show().is_ok_and(|interfaces| {
+        interfaces.iter().flat_map(|i| &i.addr).any(|addr| match addr {
+            Addr::V4(v4) => !v4.ip.is_loopback() && !v4.ip.is_link_local(),
+            Addr::V6(_) => false,
+        })
+    })
+}
+
 #[derive(Debug)]
 enum DiffFlag {
     Del(TaskID),
@@ -0,0 +1,210 @@
+//! 实时流量
+//!
+//! 订阅内核 `/traffic` 接口，保存最近一次的上传/下载速率供托盘等使用。
+//! 内核每秒推送一次，超过 [`STALL_TIMEOUT`] 没有数据（例如睡眠唤醒后连接已失效）时重新连接。
+//! 网络分块与数据边界无关，一个数据块可能包含半条或多条数据，由 [`JsonFrameDecoder`] 增量解析。
+
+use crate::{core::handle, process::AsyncHandler, singleton};
+use anyhow::Result;
+use clash_verge_logging::{Type, logging};
+use futures::StreamExt as _;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+const STALL_TIMEOUT: Duration = Duration::from_secs(10);
+const RECONNECT_DELAY: Duration = Duration::from_secs(3);
+/// 未完成数据的最大长度，超过说明数据流已损坏，丢弃缓冲区
+const MAX_PENDING_BYTES: usize = 64 * 1024;
+
+/// 每秒字节数
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrafficSnapshot {
+    pub up: u64,
+    pub down: u64,
+}
+
+/// 增量解析以空白（通常为换行）分隔的 JSON 数据流
+///
+/// 直接在缓冲区上反序列化，只保留末尾不完整的数据；无法解析的数据跳到下一行继续。
+#[derive(Debug, Default)]
+pub struct JsonFrameDecoder {
+    buffer: Vec<u8>,
+}
+
+impl JsonFrameDecoder {
+    /// 追加一个数据块，返回其中所有完整的数据
+    pub fn feed<T: DeserializeOwned>(&mut self, chunk: &[u8]) -> Vec<T> {
+        self.buffer.extend_from_slice(chunk);
+        let mut frames = Vec::new();
+        let mut consumed = 0;
+        while consumed < self.buffer.len() {
+            let rest = &self.buffer[consumed..];
+            let mut iter = serde_json::Deserializer::from_slice(rest).into_iter::<T>();
+            match iter.next() {
+                Some(Ok(frame)) => {
+                    frames.push(frame);
+                    consumed += iter.byte_offset();
+                }
+                // 数据不完整，等待下一个数据块
+                Some(Err(err)) if err.is_eof() => break,
+                Some(Err(_)) => match rest.iter().position(|&b| b == b'\n') {
+                    Some(end) => consumed += end + 1,
+                    None => consumed = self.buffer.len(),
+                },
+                // 只剩空白
+                None => consumed = self.buffer.len(),
+            }
+        }
+        self.buffer.drain(..consumed);
+        if self.buffer.len() > MAX_PENDING_BYTES {
+            logging!(
+                debug,
+                Type::Core,
+                "Dropping {} bytes of unterminated stream data",
+                self.buffer.len()
+            );
+            self.buffer.clear();
+        }
+        frames
+    }
+}
+
+pub struct TrafficHub {
+    latest: Mutex<TrafficSnapshot>,
+    /// 数据流正在推送数据，重连期间为 false
+    healthy: AtomicBool,
+    runner_started: AtomicBool,
+}
+
+singleton!(TrafficHub, TRAFFIC_HUB);
+
+impl TrafficHub {
+    fn new() -> Self {
+        Self {
+            latest: Mutex::new(TrafficSnapshot::default()),
+            healthy: AtomicBool::new(false),
+            runner_started: AtomicBool::new(false),
+        }
+    }
+
+    /// 开始订阅，重复调用无副作用
+    pub fn init(&self) {
+        if self.runner_started.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        AsyncHandler::spawn(|| async {
+            let hub = Self::global();
+            loop {
+                if handle::Handle::global().is_exiting() {
+                    break;
+                }
+                if let Err(err) = hub.stream().await {
+                    logging!(debug, Type::Core, "Traffic stream interrupted: {err}");
+                }
+                hub.healthy.store(false, Ordering::Relaxed);
+                *hub.latest.lock() = TrafficSnapshot::default();
+                tokio::time::sleep(RECONNECT_DELAY).await;
+            }
+        });
+    }
+
+    pub fn latest(&self) -> TrafficSnapshot {
+        *self.latest.lock()
+    }
+
+    /// 最近的速率是否为实时数据，数据流重连期间为 false
+    pub fn is_healthy(&self) -> bool {
+        self.healthy.load(Ordering::Relaxed)
+    }
+
+    async fn stream(&self) -> Result<()> {
+        let mut stream = handle::Handle::mihomo_http().stream("/traffic").await?.bytes_stream();
+        let mut decoder = JsonFrameDecoder::default();
+        while let Some(chunk) = tokio::time::timeout(STALL_TIMEOUT, stream.next()).await? {
+            if let Some(snapshot) = decoder.feed::<TrafficSnapshot>(&chunk?).pop() {
+                *self.latest.lock() = snapshot;
+                self.healthy.store(true, Ordering::Relaxed);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frames(count: u64) -> (Vec<u8>, Vec<TrafficSnapshot>) {
+        let snapshots: Vec<TrafficSnapshot> = (0..count)
+            .map(|i| TrafficSnapshot {
+                up: i * 1_000_003,
+                down: u64::MAX - i,
+            })
+            .collect();
+        let mut data = Vec::new();
+        for snapshot in &snapshots {
+            data.extend_from_slice(&serde_json::to_vec(snapshot).unwrap_or_default());
+            data.push(b'\n');
+        }
+        (data, snapshots)
+    }
+
+    #[test]
+    fn test_decoder_partial_and_multiple_frames() {
+        let mut decoder = JsonFrameDecoder::default();
+        let decoded: Vec<TrafficSnapshot> = decoder.feed(br#"{"up":1,"down":2}{"up":3,"down":4} {"up":5,"#);
+        assert_eq!(
+            decoded,
+            [TrafficSnapshot { up: 1, down: 2 }, TrafficSnapshot { up: 3, down: 4 }]
+        );
+        let decoded: Vec<TrafficSnapshot> = decoder.feed(b"\"down\":6}\n");
+        assert_eq!(decoded, [TrafficSnapshot { up: 5, down: 6 }]);
+        assert!(decoder.buffer.is_empty());
+    }
+
+    #[test]
+    fn test_decoder_skips_malformed_lines() {
+        let mut decoder = JsonFrameDecoder::default();
+        let decoded: Vec<TrafficSnapshot> = decoder.feed(b"garbage\n{\"up\":\"x\"}\n{\"up\":7,\"down\":8}\n");
+        assert_eq!(decoded, [TrafficSnapshot { up: 7, down: 8 }]);
+    }
+
+    #[test]
+    fn test_decoder_every_split_point() {
+        let (data, expected) = frames(4);
+        for split in 0..=data.len() {
+            let (head, tail) = data.split_at(split);
+            let mut decoder = JsonFrameDecoder::default();
+            let mut decoded: Vec<TrafficSnapshot> = decoder.feed(head);
+            decoded.extend(decoder.feed::<TrafficSnapshot>(tail));
+            assert_eq!(decoded, expected, "split at {split}");
+        }
+    }
+
+    #[test]
+    fn test_decoder_random_chunks() {
+        let (data, expected) = frames(50);
+        // 固定种子的线性同余生成器，结果可复现
+        let mut seed: u64 = 0x2545_F491_4F6C_DD1D;
+        for _ in 0..200 {
+            let mut decoder = JsonFrameDecoder::default();
+            let mut decoded = Vec::new();
+            let mut rest = data.as_slice();
+            while !rest.is_empty() {
+                seed = seed
+                    .wrapping_mul(6_364_136_223_846_793_005)
+                    .wrapping_add(1_442_695_040_888_963_407);
+                let len = usize::try_from(seed >> 58).unwrap_or(1).clamp(1, rest.len());
+                let (chunk, tail) = rest.split_at(len);
+                decoded.extend(decoder.feed::<TrafficSnapshot>(chunk));
+                rest = tail;
+            }
+            assert_eq!(decoded, expected);
+        }
+    }
+}
@@ -0,0 +1,167 @@
+//! 托盘图标主题
+//!
+//! 主题为 `tray-themes` 目录下的子目录，按状态存放 `common`、`sysproxy`、`tun`、`direct`、
+//! `core-down` 图标（png 或 ico），缺少的状态回退到 `common`，仍缺失时使用内置图标。
+
+use crate::{
+    config::{Config, IVerge},
+    core::{
+        CoreManager,
+        manager::{CoreWatchdog, RunningMode},
+    },
+    feat,
+    utils::dirs,
+};
+use anyhow::{Result, bail};
+use smartstring::alias::String;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+const ICON_EXTENSIONS: [&str; 2] = ["png", "ico"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrayIconState {
+    Common,
+    SystemProxy,
+    Tun,
+    Direct,
+    CoreDown,
+}
+
+impl TrayIconState {
+    pub const fn file_stem(self) -> &'static str {
+        match self {
+            Self::Common => "common",
+            Self::SystemProxy => "sysproxy",
+            Self::Tun => "tun",
+            Self::Direct => "direct",
+            Self::CoreDown => "core-down",
+        }
+    }
+
+    /// 内核异常优先，其次直连模式，再按 TUN、系统代理区分
+    pub fn resolve(core_running: bool, mode: &str, sysproxy: bool, tun: bool) -> Self {
+        if !core_running {
+            Self::CoreDown
+        } else if mode == "direct" {
+            Self::Direct
+        } else if tun {
+            Self::Tun
+        } else if sysproxy {
+            Self::SystemProxy
+        } else {
+            Self::Common
+        }
+    }
+
+    pub async fn current(verge: &IVerge) -> Self {
+        let core_running = *CoreManager::global().get_running_mode() != RunningMode::NotRunning
+            && !CoreWatchdog::global().is_recovering();
+        let mode = Config::clash()
+            .await
+            .latest_arc()
+            .0
+            .get("mode")
+            .and_then(|val| val.as_str())
+            .unwrap_or("rule")
+            .to_owned();
+        Self::resolve(
+            core_running,
+            &mode,
+            verge.enable_system_proxy.unwrap_or(false),
+            verge.enable_tun_mode.unwrap_or(false),
+        )
+    }
+}
+
+fn theme_dir(name: &str) -> Result<PathBuf> {
+    if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+        bail!("invalid tray icon theme name: {name}");
+    }
+    Ok(dirs::app_tray_themes_dir()?.join(name))
+}
+
+async fn read_icon(dir: &Path, state: TrayIconState) -> Option<Vec<u8>> {
+    for ext in ICON_EXTENSIONS {
+        let path = dir.join(format!("{}.{ext}", state.file_stem()));
+        if let Ok(data) = fs::read(&path).await {
+            return Some(data);
+        }
+    }
+    None
+}
+
+/// 读取当前主题下对应状态的图标，未设置主题或主题内没有可用图标时返回 `None`
+pub async fn load_theme_icon(verge: &IVerge, state: TrayIconState) -> Option<Vec<u8>> {
+    let name = verge.tray_icon_theme.as_ref().filter(|name| !name.is_empty())?;
+    let dir = theme_dir(name).ok()?;
+    match read_icon(&dir, state).await {
+        Some(data) => Some(data),
+        None if state != TrayIconState::Common => read_icon(&dir, TrayIconState::Common).await,
+        None => None,
+    }
+}
+
+/// 列出已安装的图标主题
+pub async fn list_tray_icon_themes() -> Result<Vec<String>> {
+    let dir = dirs::app_tray_themes_dir()?;
+    let mut themes = Vec::new();
+    let Ok(mut entries) = fs::read_dir(&dir).await else {
+        return Ok(themes);
+    };
+    while let Some(entry) = entries.next_entry().await? {
+        if entry.file_type().await?.is_dir()
+            && let Some(name) = entry.file_name().to_str()
+            && !name.starts_with('.')
+        {
+            themes.push(name.into());
+        }
+    }
+    themes.sort();
+    Ok(themes)
+}
+
+/// 切换图标主题，空名称恢复内置图标
+pub async fn set_tray_icon_theme(name: String) -> Result<()> {
+    if !name.is_empty() && !fs::try_exists(theme_dir(&name)?).await.unwrap_or(false) {
+        bail!("tray icon theme not found: {name}");
+    }
+    let patch = IVerge {
+        tray_icon_theme: Some(name),
+        ..IVerge::default()
+    };
+    feat::patch_verge(&patch, false).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_state() {
+        assert_eq!(
+            TrayIconState::resolve(false, "rule", true, true),
+            TrayIconState::CoreDown
+        );
+        assert_eq!(
+            TrayIconState::resolve(true, "direct", true, false),
+            TrayIconState::Direct
+        );
+        assert_eq!(TrayIconState::resolve(true, "rule", true, true), TrayIconState::Tun);
+        assert_eq!(
+            TrayIconState::resolve(true, "global", true, false),
+            TrayIconState::SystemProxy
+        );
+        assert_eq!(
+            TrayIconState::resolve(true, "rule", false, false),
+            TrayIconState::Common
+        );
+    }
+
+    #[test]
+    fn test_theme_name_validation() {
+        assert!(theme_dir("../icons").is_err());
+        assert!(theme_dir("a\\b").is_err());
+        assert!(theme_dir("").is_err());
+    }
+}
@@ -39,6 +39,7 @@ define_menu! {
     outbound_modes => OUTBOUND_MODES, "tray_outbound_modes", "tray.outboundModes",
     profiles => PROFILES, "tray_profiles", "tray.profiles",
     proxies => PROXIES, "tray_proxies", "tray.proxies",
+    favorites => FAVORITES, "tray_favorites", "tray.favorites",
     system_proxy => SYSTEM_PROXY, "tray_system_proxy", "tray.systemProxy",
     tun_mode => TUN_MODE, "tray_tun_mode", "tray.tunMode",
     close_all_connections => CLOSE_ALL_CONNECTIONS, "tray_close_all_connections", "tray.closeAllConnections",
@@ -56,3 +57,8 @@ define_menu! {
     more => MORE, "tray_more", "tray.more",
     exit => EXIT, "tray_exit", "tray.exit",
 }
+
+impl MenuIds {
+    /// 非默认实例在菜单顶部显示实例名，不可点击
+    pub const INSTANCE: &'static str = "tray_instance";
+}
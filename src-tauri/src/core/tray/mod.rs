@@ -3,9 +3,11 @@ use tauri::tray::TrayIconBuilder;
 use tauri_plugin_clash_verge_sysinfo::is_current_app_handle_admin;
 use tauri_plugin_mihomo::models::Proxies;
 use tokio::fs;
-#[cfg(target_os = "macos")]
+pub mod icon_theme;
 pub mod speed_rate;
 use crate::config::{IProfilePreview, IVerge};
+use crate::core::audit::AuditSource;
+use crate::core::event_bus::{EventBus, StateEvent};
 use crate::core::service;
 use crate::module::lightweight;
 use crate::process::AsyncHandler;
@@ -16,8 +18,9 @@ use crate::{
     config::Config,
     feat, logging,
     module::lightweight::is_in_lightweight_mode,
-    utils::{dirs::find_target_icons, i18n},
+    utils::{dirs::find_target_icons, i18n, instance},
 };
+use icon_theme::TrayIconState;
 
 use super::handle;
 use anyhow::Result;
@@ -47,6 +50,10 @@ struct TrayState {}
 // 托盘点击防抖机制
 static TRAY_CLICK_DEBOUNCE: OnceCell<Mutex<Instant>> = OnceCell::new();
 const TRAY_CLICK_DEBOUNCE_MS: u64 = 300;
+/// 快速切换菜单项的 id 前缀，后接节点名
+const QUICK_PROXY_PREFIX: &str = "quick_proxy_";
+/// 收藏菜单项的 id 前缀，后接 [`Tray::favorite_targets`] 中的序号
+const FAVORITE_PREFIX: &str = "favorite_";
 
 fn get_tray_click_debounce() -> &'static Mutex<Instant> {
     TRAY_CLICK_DEBOUNCE.get_or_init(|| Mutex::new(Instant::now() - Duration::from_secs(1)))
@@ -74,15 +81,46 @@ fn should_handle_tray_click() -> bool {
 pub struct Tray {
     last_menu_update: Mutex<Option<Instant>>,
     menu_updating: AtomicBool,
+    /// 不含网速的托盘提示，网速刷新时在其后追加
+    tooltip: Mutex<String>,
+    speed_started: AtomicBool,
+    /// 快速切换菜单对应的代理组
+    quick_group: Mutex<Option<String>>,
+    /// 收藏菜单项对应的代理组与节点
+    favorite_targets: Mutex<Vec<(String, String)>>,
 }
 
 #[cfg(not(target_os = "macos"))]
 pub struct Tray {
     last_menu_update: Mutex<Option<Instant>>,
     menu_updating: AtomicBool,
+    /// 不含网速的托盘提示，网速刷新时在其后追加
+    tooltip: Mutex<String>,
+    speed_started: AtomicBool,
+    /// 快速切换菜单对应的代理组
+    quick_group: Mutex<Option<String>>,
+    /// 收藏菜单项对应的代理组与节点
+    favorite_targets: Mutex<Vec<(String, String)>>,
 }
 
 impl TrayState {
+    /// 按当前状态选择图标，优先使用图标主题
+    async fn get_state_tray_icon(verge: &IVerge) -> (bool, Vec<u8>) {
+        let state = TrayIconState::current(verge).await;
+        if let Some(icon_data) = icon_theme::load_theme_icon(verge, state).await {
+            return (true, icon_data);
+        }
+        // 内置图标只区分系统代理与 TUN
+        match (
+            verge.enable_system_proxy.unwrap_or(false),
+            verge.enable_tun_mode.unwrap_or(false),
+        ) {
+            (_, true) => Self::get_tun_tray_icon(verge).await,
+            (true, false) => Self::get_sysproxy_tray_icon(verge).await,
+            (false, false) => Self::get_common_tray_icon(verge).await,
+        }
+    }
+
     async fn get_common_tray_icon(verge: &IVerge) -> (bool, Vec<u8>) {
         let is_common_tray_icon = verge.common_tray_icon.unwrap_or(false);
         if is_common_tray_icon
@@ -166,6 +204,10 @@ impl Default for Tray {
         Self {
             last_menu_update: Mutex::new(None),
             menu_updating: AtomicBool::new(false),
+            tooltip: Mutex::new(String::new()),
+            speed_started: AtomicBool::new(false),
+            quick_group: Mutex::new(None),
+            favorite_targets: Mutex::new(Vec::new()),
         }
     }
 }
@@ -188,6 +230,7 @@ impl Tray {
         match self.create_tray_from_handle(app_handle).await {
             Ok(_) => {
                 logging!(info, Type::Tray, "System tray created successfully");
+                Self::global().start_speed_display();
             }
             Err(e) => {
                 // Don't return error, let application continue running without tray
@@ -331,15 +374,7 @@ impl Tray {
             }
         };
 
-        let system_mode = verge.enable_system_proxy.as_ref().unwrap_or(&false);
-        let tun_mode = verge.enable_tun_mode.as_ref().unwrap_or(&false);
-
-        let (_is_custom_icon, icon_bytes) = match (*system_mode, *tun_mode) {
-            (true, true) => TrayState::get_tun_tray_icon(verge).await,
-            (true, false) => TrayState::get_sysproxy_tray_icon(verge).await,
-            (false, true) => TrayState::get_tun_tray_icon(verge).await,
-            (false, false) => TrayState::get_common_tray_icon(verge).await,
-        };
+        let (_is_custom_icon, icon_bytes) = TrayState::get_state_tray_icon(verge).await;
 
         let colorful = verge.tray_icon.clone().unwrap_or_else(|| "monochrome".into());
         let is_colorful = colorful == "colorful";
@@ -366,15 +401,7 @@ impl Tray {
             }
         };
 
-        let system_mode = verge.enable_system_proxy.as_ref().unwrap_or(&false);
-        let tun_mode = verge.enable_tun_mode.as_ref().unwrap_or(&false);
-
-        let (_is_custom_icon, icon_bytes) = match (*system_mode, *tun_mode) {
-            (true, true) => TrayState::get_tun_tray_icon(verge).await,
-            (true, false) => TrayState::get_sysproxy_tray_icon(verge).await,
-            (false, true) => TrayState::get_tun_tray_icon(verge).await,
-            (false, false) => TrayState::get_common_tray_icon(verge).await,
-        };
+        let (_is_custom_icon, icon_bytes) = TrayState::get_state_tray_icon(verge).await;
 
         let _ = tray.set_icon(Some(tauri::image::Image::from_bytes(&icon_bytes)?));
         Ok(())
@@ -427,8 +454,11 @@ impl Tray {
             |(main, rest)| format!("{main}+{}", rest.split('.').next().unwrap_or("")),
         );
 
+        let app_name = app_title();
+
         let tooltip = format!(
-            "Clash Verge {}\n{}: {}\n{}: {}\n{}: {}",
+            "{} {}\n{}: {}\n{}: {}\n{}: {}",
+            app_name,
             reassembled_version,
             sys_proxy_text,
             switch_map[system_proxy],
@@ -438,6 +468,7 @@ impl Tray {
             current_profile_name
         );
 
+        *self.tooltip.lock() = tooltip.as_str().into();
         if let Some(tray) = app_handle.tray_by_id("main") {
             let _ = tray.set_tooltip(Some(&tooltip));
         } else {
@@ -459,6 +490,20 @@ impl Tray {
         Ok(())
     }
 
+    /// 按状态事件更新托盘
+    pub fn listen_state_events(&'static self) {
+        EventBus::global().listen("tray", move |event| async move {
+            let result = match event {
+                StateEvent::ModeChanged { .. } | StateEvent::ProfileSwitched { .. } => self.update_part().await,
+                StateEvent::NodeSwitched { .. } => self.update_menu().await,
+                _ => Ok(()),
+            };
+            if let Err(err) = result {
+                logging!(warn, Type::Tray, "Failed to update tray for state event: {err}");
+            }
+        });
+    }
+
     async fn create_tray_from_handle(&self, app_handle: &AppHandle) -> Result<()> {
         if handle::Handle::global().is_exiting() {
             logging!(debug, Type::Tray, "应用正在退出，跳过托盘创建");
@@ -474,7 +519,7 @@ impl Tray {
         let icon = tauri::image::Image::from_bytes(&icon_bytes)?;
 
         #[cfg(target_os = "linux")]
-        let builder = TrayIconBuilder::with_id("main").icon(icon).icon_as_template(false);
+        let mut builder = TrayIconBuilder::with_id("main").icon(icon).icon_as_template(false);
 
         #[cfg(any(target_os = "macos", target_os = "windows"))]
         let show_menu_on_left_click = {
@@ -498,6 +543,12 @@ impl Tray {
             }
         }
 
+        // 非默认实例在图标旁显示实例名（macOS 菜单栏与 Linux AppIndicator 支持，Windows 上见菜单顶部与提示）
+        #[cfg(not(target_os = "windows"))]
+        if let Some(name) = instance::name() {
+            builder = builder.title(name);
+        }
+
         let tray = builder.build(app_handle)?;
 
         tray.on_tray_icon_event(|_app_handle, event| {
@@ -512,23 +563,25 @@ impl Tray {
                     logging!(info, Type::Tray, "click tray icon too fast, ignore");
                     return;
                 }
-                AsyncHandler::spawn(|| async move {
-                    let tray_event = { Config::verge().await.latest_arc().tray_event.clone() };
-                    let tray_event: String = tray_event.unwrap_or_else(|| "main_window".into());
-                    logging!(debug, Type::Tray, "tray event: {tray_event:?}");
-
-                    match tray_event.as_str() {
-                        "system_proxy" => feat::toggle_system_proxy().await,
-                        "tun_mode" => feat::toggle_tun_mode(None).await,
-                        "main_window" => {
-                            if !lightweight::exit_lightweight_mode().await {
-                                WindowManager::show_main_window().await;
-                            };
-                        }
-                        _ => {
-                            logging!(warn, Type::Tray, "invalid tray event: {}", tray_event);
-                        }
-                    };
+                AsyncHandler::spawn(|| {
+                    AuditSource::Tray.scope(async move {
+                        let tray_event = { Config::verge().await.latest_arc().tray_event.clone() };
+                        let tray_event: String = tray_event.unwrap_or_else(|| "main_window".into());
+                        logging!(debug, Type::Tray, "tray event: {tray_event:?}");
+
+                        match tray_event.as_str() {
+                            "system_proxy" => feat::toggle_system_proxy().await,
+                            "tun_mode" => feat::toggle_tun_mode(None).await,
+                            "main_window" => {
+                                if !lightweight::exit_lightweight_mode().await {
+                                    WindowManager::show_main_window().await;
+                                };
+                            }
+                            _ => {
+                                logging!(warn, Type::Tray, "invalid tray event: {}", tray_event);
+                            }
+                        };
+                    })
                 });
             }
         });
@@ -676,6 +729,106 @@ fn create_subcreate_proxy_menu_item(
     proxy_submenus
 }
 
+/// 主代理组（全局模式下为 GLOBAL，否则为配置中的第一个代理组）的节点快速切换菜单
+fn create_quick_switch_menu(
+    app_handle: &AppHandle,
+    proxy_mode: &str,
+    group_order: Option<&[String]>,
+    proxy_nodes_data: Option<&Proxies>,
+) -> Option<Submenu<Wry>> {
+    let proxies = &proxy_nodes_data?.proxies;
+    let group_name = if proxy_mode == "global" {
+        "GLOBAL"
+    } else {
+        group_order?.iter().map(String::as_str).find(|name| {
+            proxies
+                .get(*name)
+                .is_some_and(|group| !group.hidden.unwrap_or_default() && group.all.is_some())
+        })?
+    };
+    let group = proxies.get(group_name)?;
+    let now_proxy = group.now.as_deref().unwrap_or_default();
+
+    let items: Vec<CheckMenuItem<Wry>> = group
+        .all
+        .as_ref()?
+        .iter()
+        .filter_map(|proxy_str| {
+            CheckMenuItem::with_id(
+                app_handle,
+                format!("{QUICK_PROXY_PREFIX}{proxy_str}"),
+                proxy_str,
+                true,
+                *proxy_str == now_proxy,
+                None::<&str>,
+            )
+            .ok()
+        })
+        .collect();
+    if items.is_empty() {
+        return None;
+    }
+    let item_refs: Vec<&dyn IsMenuItem<Wry>> = items.iter().map(|item| item as &dyn IsMenuItem<Wry>).collect();
+
+    *Tray::global().quick_group.lock() = Some(group_name.into());
+    Submenu::with_id_and_items(
+        app_handle,
+        "tray_quick_switch",
+        format!("{group_name}: {now_proxy}"),
+        true,
+        &item_refs,
+    )
+    .map_err(|e| logging!(warn, Type::Tray, "Failed to create quick switch menu: {}", e))
+    .ok()
+}
+
+/// 收藏的节点可直接切换，收藏的代理组展开为其节点列表
+fn create_favorites_menu(
+    app_handle: &AppHandle,
+    favorites: &[feat::Favorite],
+    proxies: &Proxies,
+    title: &Arc<str>,
+) -> Option<Submenu<Wry>> {
+    let mut targets: Vec<(String, String)> = Vec::new();
+    let mut check_item = |group: &str, node: &str, label: &str, checked: bool| {
+        let id = format!("{FAVORITE_PREFIX}{}", targets.len());
+        targets.push((group.into(), node.into()));
+        CheckMenuItem::with_id(app_handle, id, label, true, checked, None::<&str>).ok()
+    };
+
+    let mut items: Vec<Box<dyn IsMenuItem<Wry>>> = Vec::new();
+    for favorite in favorites.iter().filter(|favorite| favorite.available) {
+        if favorite.is_group {
+            let now = favorite.now.as_deref().unwrap_or_default();
+            let nodes: Vec<CheckMenuItem<Wry>> = proxies
+                .proxies
+                .get(favorite.name.as_str())
+                .and_then(|group| group.all.as_ref())
+                .into_iter()
+                .flatten()
+                .filter_map(|node| check_item(&favorite.name, node, node, *node == now))
+                .collect();
+            let node_refs: Vec<&dyn IsMenuItem<Wry>> = nodes.iter().map(|item| item as &dyn IsMenuItem<Wry>).collect();
+            if let Ok(submenu) = Submenu::with_items(app_handle, format!("{}: {now}", favorite.name), true, &node_refs)
+            {
+                items.push(Box::new(submenu));
+            }
+        } else if let Some(group) = favorite.group.as_deref()
+            && let Some(item) = check_item(group, &favorite.name, &favorite.name, favorite.selected)
+        {
+            items.push(Box::new(item));
+        }
+    }
+    if items.is_empty() {
+        return None;
+    }
+    *Tray::global().favorite_targets.lock() = targets;
+    let item_refs: Vec<&dyn IsMenuItem<Wry>> = items.iter().map(|item| item.as_ref()).collect();
+    Submenu::with_id_and_items(app_handle, MenuIds::FAVORITES, title, true, &item_refs)
+        .map_err(|e| logging!(warn, Type::Tray, "Failed to create favorites menu: {}", e))
+        .ok()
+}
+
 fn create_proxy_menu_item(
     app_handle: &AppHandle,
     show_proxy_groups_inline: bool,
@@ -772,20 +925,46 @@ async fn create_tray_menu(
     let verge_settings = Config::verge().await.latest_arc();
     let show_proxy_groups_inline = verge_settings.tray_inline_proxy_groups.unwrap_or(true);
 
+    // 代理组收在子菜单中时，在根菜单提供主代理组的快速切换
+    let quick_switch = if show_proxy_groups_inline {
+        None
+    } else {
+        create_quick_switch_menu(
+            app_handle,
+            current_proxy_mode,
+            runtime_proxy_groups_order.as_deref(),
+            proxy_nodes_data.as_ref(),
+        )
+    };
+
+    let texts = MenuTexts::new();
+
+    let favorites = verge_settings.favorites.as_deref().unwrap_or_default();
+    let favorites_menu = proxy_nodes_data.as_ref().and_then(|proxies| {
+        create_favorites_menu(
+            app_handle,
+            &feat::resolve_favorites(favorites, proxies, current_proxy_mode),
+            proxies,
+            &texts.favorites,
+        )
+    });
+
     let version = env!("CARGO_PKG_VERSION");
 
     let hotkeys = create_hotkeys(&verge_settings.hotkeys);
 
     let profile_menu_items: Vec<CheckMenuItem<Wry>> = create_profile_menu_item(app_handle, profiles_preview)?;
 
-    // Pre-fetch all localized strings
-    let texts = MenuTexts::new();
     // Convert to references only when needed
     let profile_menu_items_refs: Vec<&dyn IsMenuItem<Wry>> = profile_menu_items
         .iter()
         .map(|item| item as &dyn IsMenuItem<Wry>)
         .collect();
 
+    let instance_label = instance::name()
+        .map(|name| MenuItem::with_id(app_handle, MenuIds::INSTANCE, app_title(), false, None::<&str>))
+        .transpose()?;
+
     let open_window = &MenuItem::with_id(
         app_handle,
         MenuIds::DASHBOARD,
@@ -889,7 +1068,13 @@ async fn create_tray_menu(
         hotkeys.get("entry_lightweight_mode").map(|s| s.as_str()),
     )?;
 
-    let copy_env = &MenuItem::with_id(app_handle, MenuIds::COPY_ENV, &texts.copy_env, true, None::<&str>)?;
+    let copy_env = &MenuItem::with_id(
+        app_handle,
+        MenuIds::COPY_ENV,
+        &texts.copy_env,
+        true,
+        hotkeys.get("copy_proxy_env").map(|s| s.as_str()),
+    )?;
 
     let open_app_dir = &MenuItem::with_id(app_handle, MenuIds::CONF_DIR, &texts.conf_dir, true, None::<&str>)?;
 
@@ -946,15 +1131,28 @@ async fn create_tray_menu(
     let separator = &PredefinedMenuItem::separator(app_handle)?;
 
     // 动态构建菜单项
-    let mut menu_items: Vec<&dyn IsMenuItem<Wry>> = vec![open_window, outbound_modes, separator, profiles];
+    let mut menu_items: Vec<&dyn IsMenuItem<Wry>> = Vec::new();
+    if let Some(ref instance_label) = instance_label {
+        menu_items.extend_from_slice(&[instance_label as &dyn IsMenuItem<Wry>, separator]);
+    }
+    menu_items.extend_from_slice(&[open_window as &dyn IsMenuItem<Wry>, outbound_modes, separator, profiles]);
+
+    if let Some(ref favorites_menu) = favorites_menu {
+        menu_items.push(favorites_menu);
+    }
 
     // 如果有代理节点，添加代理节点菜单
     if show_proxy_groups_inline {
         if !inline_proxy_items.is_empty() {
             menu_items.extend(inline_proxy_items.iter().map(|item| item.as_ref()));
         }
-    } else if let Some(ref proxies_menu) = proxies_menu {
-        menu_items.push(proxies_menu);
+    } else {
+        if let Some(ref quick_switch) = quick_switch {
+            menu_items.push(quick_switch);
+        }
+        if let Some(ref proxies_menu) = proxies_menu {
+            menu_items.push(proxies_menu);
+        }
     }
 
     menu_items.extend_from_slice(&[
@@ -973,90 +1171,113 @@ async fn create_tray_menu(
     Ok(menu)
 }
 
+/// 非默认实例标注实例名，便于区分多个托盘图标
+fn app_title() -> std::string::String {
+    instance::name().map_or_else(|| "Clash Verge".into(), |name| format!("Clash Verge [{name}]"))
+}
+
 fn on_menu_event(_: &AppHandle, event: MenuEvent) {
-    AsyncHandler::spawn(|| async move {
-        match event.id.as_ref() {
-            mode @ (MenuIds::RULE_MODE | MenuIds::GLOBAL_MODE | MenuIds::DIRECT_MODE) => {
-                // Removing the the "tray_" prefix and "_mode" suffix
-                let mode = &mode[5..mode.len() - 5];
-                logging!(info, Type::ProxyMode, "Switch Proxy Mode To: {}", mode);
-                feat::change_clash_mode(mode.into()).await;
-            }
-            MenuIds::DASHBOARD => {
-                logging!(info, Type::Tray, "托盘菜单点击: 打开窗口");
+    AsyncHandler::spawn(|| AuditSource::Tray.scope(handle_menu_event(event)));
+}
 
-                if !should_handle_tray_click() {
-                    return;
-                }
-                if !lightweight::exit_lightweight_mode().await {
-                    WindowManager::show_main_window().await;
-                };
-            }
-            MenuIds::SYSTEM_PROXY => {
-                feat::toggle_system_proxy().await;
-            }
-            MenuIds::TUN_MODE => {
-                feat::toggle_tun_mode(None).await;
-            }
-            MenuIds::CLOSE_ALL_CONNECTIONS => {
-                if let Err(err) = handle::Handle::mihomo().await.close_all_connections().await {
-                    logging!(error, Type::Tray, "Failed to close all connections from tray: {err}");
-                }
-            }
-            MenuIds::COPY_ENV => feat::copy_clash_env().await,
-            MenuIds::CONF_DIR => {
-                println!("Open directory submenu clicked");
-                let _ = cmd::open_app_dir().await;
-            }
-            MenuIds::CORE_DIR => {
-                let _ = cmd::open_core_dir().await;
-            }
-            MenuIds::LOGS_DIR => {
-                let _ = cmd::open_logs_dir().await;
-            }
-            MenuIds::APP_LOG => {
-                let _ = cmd::open_app_log().await;
-            }
-            MenuIds::CORE_LOG => {
-                let _ = cmd::open_core_log().await;
+async fn handle_menu_event(event: MenuEvent) {
+    match event.id.as_ref() {
+        mode @ (MenuIds::RULE_MODE | MenuIds::GLOBAL_MODE | MenuIds::DIRECT_MODE) => {
+            // Removing the the "tray_" prefix and "_mode" suffix
+            let mode = &mode[5..mode.len() - 5];
+            logging!(info, Type::ProxyMode, "Switch Proxy Mode To: {}", mode);
+            feat::change_clash_mode(mode.into()).await;
+        }
+        MenuIds::DASHBOARD => {
+            logging!(info, Type::Tray, "托盘菜单点击: 打开窗口");
+
+            if !should_handle_tray_click() {
+                return;
             }
-            MenuIds::RESTART_CLASH => feat::restart_clash_core().await,
-            MenuIds::RESTART_APP => feat::restart_app().await,
-            MenuIds::LIGHTWEIGHT_MODE => {
-                if !should_handle_tray_click() {
-                    return;
-                }
-                if !is_in_lightweight_mode() {
-                    lightweight::entry_lightweight_mode().await;
-                } else {
-                    lightweight::exit_lightweight_mode().await;
-                }
+            if !lightweight::exit_lightweight_mode().await {
+                WindowManager::show_main_window().await;
+            };
+        }
+        MenuIds::SYSTEM_PROXY => {
+            feat::toggle_system_proxy().await;
+        }
+        MenuIds::TUN_MODE => {
+            feat::toggle_tun_mode(None).await;
+        }
+        MenuIds::CLOSE_ALL_CONNECTIONS => {
+            if let Err(err) = handle::Handle::mihomo().await.close_all_connections().await {
+                logging!(error, Type::Tray, "Failed to close all connections from tray: {err}");
             }
-            MenuIds::EXIT => {
-                feat::quit().await;
+        }
+        MenuIds::COPY_ENV => feat::copy_clash_env().await,
+        MenuIds::CONF_DIR => {
+            println!("Open directory submenu clicked");
+            let _ = cmd::open_app_dir().await;
+        }
+        MenuIds::CORE_DIR => {
+            let _ = cmd::open_core_dir().await;
+        }
+        MenuIds::LOGS_DIR => {
+            let _ = cmd::open_logs_dir().await;
+        }
+        MenuIds::APP_LOG => {
+            let _ = cmd::open_app_log().await;
+        }
+        MenuIds::CORE_LOG => {
+            let _ = cmd::open_core_log().await;
+        }
+        MenuIds::RESTART_CLASH => feat::restart_clash_core().await,
+        MenuIds::RESTART_APP => feat::restart_app().await,
+        MenuIds::LIGHTWEIGHT_MODE => {
+            if !should_handle_tray_click() {
+                return;
             }
-            id if id.starts_with("profiles_") => {
-                let profile_index = &id["profiles_".len()..];
-                feat::toggle_proxy_profile(profile_index.into()).await;
+            if !is_in_lightweight_mode() {
+                lightweight::entry_lightweight_mode().await;
+            } else {
+                lightweight::exit_lightweight_mode().await;
             }
-            id if id.starts_with("proxy_") => {
-                // proxy_{group_name}_{proxy_name}
-                let rest = match id.strip_prefix("proxy_") {
-                    Some(r) => r,
-                    None => return,
-                };
-                let (group_name, proxy_name) = match rest.split_once('_') {
-                    Some((g, p)) => (g, p),
-                    None => return,
-                };
-                feat::switch_proxy_node(group_name, proxy_name).await;
+        }
+        MenuIds::EXIT => {
+            feat::quit().await;
+        }
+        id if id.starts_with("profiles_") => {
+            let profile_index = &id["profiles_".len()..];
+            feat::toggle_proxy_profile(profile_index.into()).await;
+        }
+        id if id.starts_with(FAVORITE_PREFIX) => {
+            let target = id[FAVORITE_PREFIX.len()..]
+                .parse::<usize>()
+                .ok()
+                .and_then(|index| Tray::global().favorite_targets.lock().get(index).cloned());
+            if let Some((group_name, proxy_name)) = target {
+                feat::switch_proxy_node(&group_name, &proxy_name).await;
             }
-            _ => {
-                logging!(debug, Type::Tray, "Unhandled tray menu event: {:?}", event.id);
+        }
+        id if id.starts_with(QUICK_PROXY_PREFIX) => {
+            let proxy_name = &id[QUICK_PROXY_PREFIX.len()..];
+            let group_name = Tray::global().quick_group.lock().clone();
+            if let Some(group_name) = group_name {
+                feat::switch_proxy_node(&group_name, proxy_name).await;
             }
         }
+        id if id.starts_with("proxy_") => {
+            // proxy_{group_name}_{proxy_name}
+            let rest = match id.strip_prefix("proxy_") {
+                Some(r) => r,
+                None => return,
+            };
+            let (group_name, proxy_name) = match rest.split_once('_') {
+                Some((g, p)) => (g, p),
+                None => return,
+            };
+            feat::switch_proxy_node(group_name, proxy_name).await;
+        }
+        _ => {
+            logging!(debug, Type::Tray, "Unhandled tray menu event: {:?}", event.id);
+        }
+    }
 
-        // We dont expected to refresh tray state here
-        // as the inner handle function (SHOULD) already takes care of it
-    });
+    // We dont expected to refresh tray state here
+    // as the inner handle function (SHOULD) already takes care of it
 }
@@ -1 +1,106 @@
+//! 托盘实时网速
+//!
+//! 开启 `enable_tray_speed` 后每秒读取 [`TrafficHub`] 的速率，显示在托盘标题
+//! （macOS 菜单栏、Linux 指示器标签）中，并追加到托盘提示末尾。
+//! 开启 `enable_tray_memory` 后同样在托盘提示末尾追加最近一次采样的内核内存占用。
 
+use super::Tray;
+use crate::{
+    config::Config,
+    core::{
+        handle,
+        traffic::{TrafficHub, TrafficSnapshot},
+    },
+    feat::{CoreMemoryMonitor, memory_text},
+    process::AsyncHandler,
+};
+use smartstring::alias::String;
+use std::{sync::atomic::Ordering, time::Duration};
+
+const REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 紧凑的速率文本，例如 `512B`、`1.2M`、`120M`
+pub fn format_rate(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["K", "M", "G", "T"];
+    if bytes < 1024 {
+        return format!("{bytes}B").into();
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = UNITS[0];
+    for &next in &UNITS[1..] {
+        if value < 1024.0 {
+            break;
+        }
+        value /= 1024.0;
+        unit = next;
+    }
+    if value >= 100.0 {
+        format!("{value:.0}{unit}").into()
+    } else {
+        format!("{value:.1}{unit}").into()
+    }
+}
+
+pub fn speed_text(traffic: TrafficSnapshot) -> String {
+    format!("↑{} ↓{}", format_rate(traffic.up), format_rate(traffic.down)).into()
+}
+
+impl Tray {
+    /// 启动网速刷新任务，重复调用无副作用
+    pub fn start_speed_display(&'static self) {
+        if self.speed_started.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        AsyncHandler::spawn(move || async move {
+            let mut shown = false;
+            loop {
+                tokio::time::sleep(REFRESH_INTERVAL).await;
+                if handle::Handle::global().is_exiting() {
+                    break;
+                }
+                let (speed_enabled, memory_enabled) = {
+                    let verge = Config::verge().await.latest_arc();
+                    (
+                        verge.enable_tray_speed.unwrap_or(false),
+                        verge.enable_tray_memory.unwrap_or(false),
+                    )
+                };
+                let enabled = speed_enabled || memory_enabled;
+                if !enabled && !shown {
+                    continue;
+                }
+                let Some(tray) = handle::Handle::app_handle().tray_by_id("main") else {
+                    continue;
+                };
+                let mut tooltip = self.tooltip.lock().clone();
+                if speed_enabled {
+                    TrafficHub::global().init();
+                    let text = speed_text(TrafficHub::global().latest());
+                    let _ = tray.set_title(Some(text.as_str()));
+                    tooltip = format!("{tooltip}\n{text}").into();
+                } else if shown {
+                    let _ = tray.set_title(None::<&str>);
+                }
+                if memory_enabled && let Some(memory) = CoreMemoryMonitor::global().last() {
+                    tooltip = format!("{tooltip}\n{}", memory_text(&memory)).into();
+                }
+                let _ = tray.set_tooltip(Some(tooltip.as_str()));
+                shown = enabled;
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_rate() {
+        assert_eq!(format_rate(0), "0B");
+        assert_eq!(format_rate(1023), "1023B");
+        assert_eq!(format_rate(1536), "1.5K");
+        assert_eq!(format_rate(150 * 1024 * 1024), "150M");
+        assert_eq!(format_rate(3 * 1024 * 1024 * 1024), "3.0G");
+    }
+}
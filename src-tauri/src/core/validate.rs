@@ -1,20 +1,124 @@
 use anyhow::Result;
+use regex::Regex;
 use scopeguard::defer;
+use serde::Serialize;
 use smartstring::alias::String;
-use std::sync::atomic::{AtomicBool, Ordering};
-use tauri_plugin_shell::ShellExt as _;
+use std::{
+    path::Path,
+    sync::{
+        OnceLock,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+use tauri_plugin_shell::process::Output;
 use tokio::fs;
 
-use crate::config::{Config, ConfigType};
-use crate::core::handle;
+use crate::config::{Config, ConfigType, IProfiles};
+use crate::constants::files;
+use crate::core::{
+    backend::{CoreBackend, singbox},
+    handle,
+    manager::core_command,
+};
+use crate::enhance;
 use crate::singleton;
-use crate::utils::dirs;
+use crate::utils::{dirs, help};
 use clash_verge_logging::{Type, logging};
 
 pub struct CoreConfigValidator {
     is_processing: AtomicBool,
 }
 
+/// 单条配置错误，`line` / `column` 从 1 开始
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ConfigDiagnostic {
+    pub file: String,
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigValidation {
+    pub valid: bool,
+    pub diagnostics: Vec<ConfigDiagnostic>,
+}
+
+impl ConfigValidation {
+    pub const fn valid() -> Self {
+        Self {
+            valid: true,
+            diagnostics: Vec::new(),
+        }
+    }
+
+    pub const fn invalid(diagnostics: Vec<ConfigDiagnostic>) -> Self {
+        Self {
+            valid: false,
+            diagnostics,
+        }
+    }
+}
+
+/// 检查 YAML 语法，出错时返回带行列号的诊断
+pub fn diagnose_yaml(file: &str, content: &str) -> Option<ConfigDiagnostic> {
+    let err = serde_yaml_ng::from_str::<serde_yaml_ng::Value>(content).err()?;
+    let location = err.location();
+    Some(ConfigDiagnostic {
+        file: file.into(),
+        line: location.as_ref().and_then(|l| u32::try_from(l.line()).ok()),
+        column: location.as_ref().and_then(|l| u32::try_from(l.column()).ok()),
+        message: format!("YAML syntax error: {err}").into(),
+    })
+}
+
+/// 从内核检查模式的输出中提取错误，兼容 mihomo 的 `level=error msg="..."`、
+/// sing-box 的 `FATAL[0000] ...` 以及 YAML 解析器的 `line N` 位置信息
+pub fn parse_diagnostics(file: &str, output: &str) -> Vec<ConfigDiagnostic> {
+    static MSG_REGEX: OnceLock<Option<Regex>> = OnceLock::new();
+    static LOCATION_REGEX: OnceLock<Option<Regex>> = OnceLock::new();
+    let msg_regex = MSG_REGEX.get_or_init(|| Regex::new(r#"msg="((?:[^"\\]|\\.)*)""#).ok());
+    let location_regex = LOCATION_REGEX.get_or_init(|| Regex::new(r"line (\d+)(?:,? column (\d+))?").ok());
+
+    let mut diagnostics: Vec<ConfigDiagnostic> = Vec::new();
+    for line in output.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        let lower = line.to_ascii_lowercase();
+        let is_error = lower.contains("level=error")
+            || lower.contains("level=fatal")
+            || lower.starts_with("fata")
+            || lower.starts_with("error")
+            || lower.contains("parse config error")
+            || lower.contains("yaml:");
+        if !is_error {
+            continue;
+        }
+
+        let message = msg_regex
+            .as_ref()
+            .and_then(|re| re.captures(line))
+            .and_then(|caps| caps.get(1))
+            .map_or(line, |m| m.as_str())
+            .replace("\\\"", "\"");
+        let location = location_regex.as_ref().and_then(|re| re.captures(&message));
+        let number = |i: usize| {
+            location
+                .as_ref()
+                .and_then(|caps| caps.get(i))
+                .and_then(|m| m.as_str().parse().ok())
+        };
+        let diagnostic = ConfigDiagnostic {
+            file: file.into(),
+            line: number(1),
+            column: number(2),
+            message: message.as_str().into(),
+        };
+        if !diagnostics.contains(&diagnostic) {
+            diagnostics.push(diagnostic);
+        }
+    }
+    diagnostics
+}
+
 impl CoreConfigValidator {
     pub const fn new() -> Self {
         Self {
@@ -223,6 +327,110 @@ impl CoreConfigValidator {
         Self::validate_config_internal(config_path).await
     }
 
+    /// 以检查模式运行当前内核
+    async fn run_core_check(config_path: &str) -> Result<Output> {
+        let verge = Config::verge().await.latest_arc();
+        let clash_core = verge.get_valid_clash_core();
+        logging!(info, Type::Validate, "使用内核: {}", clash_core);
+
+        let app_dir = dirs::app_home_dir()?;
+        let app_dir_str = dirs::path_to_str(&app_dir)?;
+        logging!(info, Type::Validate, "验证目录: {}", app_dir_str);
+
+        // 使用子进程运行clash验证配置
+        let command = core_command(&verge, &clash_core)?;
+        let command = match CoreBackend::of(&verge) {
+            CoreBackend::Mihomo => command.args(["-t", "-d", app_dir_str, "-f", config_path]),
+            CoreBackend::SingBox => {
+                let check_file = singbox::convert_file(&config_path.into(), singbox::SINGBOX_CHECK_CONFIG).await?;
+                command.args(["check", "-D", app_dir_str, "-c", dirs::path_to_str(&check_file)?])
+            }
+        };
+        Ok(command.output().await?)
+    }
+
+    /// 检查进程退出状态和错误输出
+    fn has_check_error(output: &Output) -> bool {
+        let error_keywords = ["FATA", "fatal", "Parse config error", "level=fatal"];
+        !output.status.success() || contains_any_keyword(&output.stderr, &error_keywords)
+    }
+
+    /// 验证配置文件并返回结构化的错误列表，YAML 语法错误不再交给内核
+    pub async fn diagnose_config_file(config_path: &str) -> Result<ConfigValidation> {
+        Self::diagnose_config_as(config_path, config_path).await
+    }
+
+    /// 验证 `config_path`，诊断中的文件名报告为 `file`。检查用的临时文件与运行配置内容一致，
+    /// 行号可以直接对应到运行配置
+    async fn diagnose_config_as(config_path: &str, file: &str) -> Result<ConfigValidation> {
+        let content = fs::read_to_string(config_path).await?;
+        if let Some(diagnostic) = diagnose_yaml(file, &content) {
+            return Ok(ConfigValidation::invalid(vec![diagnostic]));
+        }
+
+        let output = Self::run_core_check(config_path).await?;
+        if !Self::has_check_error(&output) {
+            return Ok(ConfigValidation::valid());
+        }
+
+        let text = format!(
+            "{}\n{}",
+            std::string::String::from_utf8_lossy(&output.stdout),
+            std::string::String::from_utf8_lossy(&output.stderr)
+        );
+        let mut diagnostics = parse_diagnostics(file, &text);
+        if diagnostics.is_empty() {
+            diagnostics.push(ConfigDiagnostic {
+                file: file.into(),
+                line: None,
+                column: None,
+                message: match output.status.code() {
+                    Some(code) => format!("core check exited with code {code}").into(),
+                    None => "core check was terminated".into(),
+                },
+            });
+        }
+        Ok(ConfigValidation::invalid(diagnostics))
+    }
+
+    /// 按给定的订阅列表重新执行增强流程并验证结果，
+    /// 只写入检查用的临时文件，不影响运行配置
+    pub async fn diagnose_enhanced_config(profiles: &IProfiles) -> Result<ConfigValidation> {
+        let (config, _, logs) = enhance::enhance_with_profiles(profiles, None).await;
+        let diagnostics: Vec<ConfigDiagnostic> = logs
+            .into_iter()
+            .flat_map(|(uid, logs)| {
+                logs.into_iter()
+                    .filter(|(level, _)| level == "exception")
+                    .map(move |(_, message)| ConfigDiagnostic {
+                        file: uid.clone(),
+                        line: None,
+                        column: None,
+                        message,
+                    })
+            })
+            .collect();
+        if !diagnostics.is_empty() {
+            return Ok(ConfigValidation::invalid(diagnostics));
+        }
+
+        let config_path = dirs::app_home_dir()?.join(files::CHECK_CONFIG);
+        help::save_yaml(&config_path, &config, Some("# Generated by Clash Verge")).await?;
+        Self::diagnose_runtime_check(&config_path).await
+    }
+
+    /// 生成当前的运行配置并做完整验证，不会修改正在使用的配置
+    pub async fn diagnose_runtime_config() -> Result<ConfigValidation> {
+        let config_path = Config::generate_file(ConfigType::Check).await?;
+        Self::diagnose_runtime_check(&config_path).await
+    }
+
+    /// 验证检查用的临时文件，诊断指向它将要替换的运行配置
+    async fn diagnose_runtime_check(check_path: &Path) -> Result<ConfigValidation> {
+        let runtime_path = dirs::app_home_dir()?.join(files::RUNTIME_CONFIG);
+        Self::diagnose_config_as(dirs::path_to_str(check_path)?, dirs::path_to_str(&runtime_path)?).await
+    }
+
     /// 内部验证配置文件的实现
     async fn validate_config_internal(config_path: &str) -> Result<(bool, String)> {
         // 检查程序是否正在退出，如果是则跳过验证
@@ -233,29 +441,11 @@ impl CoreConfigValidator {
 
         logging!(info, Type::Validate, "开始验证配置文件: {}", config_path);
 
-        let clash_core = Config::verge().await.latest_arc().get_valid_clash_core();
-        logging!(info, Type::Validate, "使用内核: {}", clash_core);
-
-        let app_handle = handle::Handle::app_handle();
-        let app_dir = dirs::app_home_dir()?;
-        let app_dir_str = dirs::path_to_str(&app_dir)?;
-        logging!(info, Type::Validate, "验证目录: {}", app_dir_str);
-
-        // 使用子进程运行clash验证配置
-        let command =
-            app_handle
-                .shell()
-                .sidecar(clash_core.as_str())?
-                .args(["-t", "-d", app_dir_str, "-f", config_path]);
-        let output = command.output().await?;
-
+        let output = Self::run_core_check(config_path).await?;
         let status = &output.status;
         let stderr = &output.stderr;
         let stdout = &output.stdout;
-
-        // 检查进程退出状态和错误输出
-        let error_keywords = ["FATA", "fatal", "Parse config error", "level=fatal"];
-        let has_error = !status.success() || contains_any_keyword(stderr, &error_keywords);
+        let has_error = Self::has_check_error(&output);
 
         logging!(info, Type::Validate, "-------- 验证结果 --------");
 
@@ -327,3 +517,38 @@ fn contains_any_keyword<'a>(buf: &'a [u8], keywords: &'a [&str]) -> bool {
 }
 
 singleton!(CoreConfigValidator, CORECONFIGVALIDATOR);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_mihomo_diagnostics() {
+        let output = r#"time="2025-01-01T00:00:00Z" level=info msg="Start initial configuration in progress"
+time="2025-01-01T00:00:00Z" level=error msg="yaml: line 12: did not find expected key"
+time="2025-01-01T00:00:00Z" level=fatal msg="Parse config error: proxy group[0]: 'Proxy' not found"
+configuration file config.yaml test failed"#;
+        let diagnostics = parse_diagnostics("config.yaml", output);
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].line, Some(12));
+        assert_eq!(diagnostics[0].message, "yaml: line 12: did not find expected key");
+        assert_eq!(diagnostics[1].line, None);
+        assert!(diagnostics[1].message.starts_with("Parse config error"));
+    }
+
+    #[test]
+    fn test_parse_singbox_diagnostics() {
+        let output = "FATAL[0000] decode config at sing-box.json: outbounds[2].server_port: json: cannot unmarshal";
+        let diagnostics = parse_diagnostics("sing-box.json", output);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].file, "sing-box.json");
+    }
+
+    #[test]
+    fn test_diagnose_yaml() {
+        assert!(diagnose_yaml("a.yaml", "proxies: []\nrules: []").is_none());
+        let diagnostic = diagnose_yaml("a.yaml", "proxies:\n  - name: a\n   type: ss");
+        assert_eq!(diagnostic.as_ref().and_then(|d| d.line), Some(3));
+        assert!(diagnostic.is_some_and(|d| d.message.starts_with("YAML syntax error")));
+    }
+}
@@ -0,0 +1,334 @@
+//! 事件回调
+//!
+//! 订阅更新、节点切换、内核重启、流量超限时向用户配置的地址发送 JSON。
+//! 配置了 `secret` 的回调在 `X-Verge-Signature` 头中附带 `sha256=<hex>` 形式的
+//! HMAC-SHA256 签名；网络错误、429 与 5xx 响应按指数退避重试。
+
+use crate::{
+    config::{Config, IWebhook, WebhookEventKind},
+    core::{
+        event_bus::{EventBus, StateEvent},
+        handle,
+    },
+    process::AsyncHandler,
+    singleton,
+    utils::{crypto, help},
+};
+use anyhow::{Result, bail};
+use chrono::Local;
+use clash_verge_logging::{Type, logging};
+use parking_lot::Mutex;
+use serde::Serialize;
+use smartstring::alias::String;
+use std::{
+    collections::HashSet,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+const MAX_ATTEMPTS: u32 = 3;
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(2);
+const TRAFFIC_POLL_INTERVAL: Duration = Duration::from_secs(60);
+const SIGNATURE_HEADER: &str = "X-Verge-Signature";
+const EVENT_HEADER: &str = "X-Verge-Event";
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", content = "data", rename_all = "snake_case")]
+pub enum WebhookEvent {
+    ProfileUpdated {
+        uid: String,
+    },
+    NodeSwitched {
+        group: String,
+        node: String,
+    },
+    CoreRestarted,
+    TrafficThreshold {
+        upload_total: u64,
+        download_total: u64,
+        threshold: u64,
+    },
+}
+
+impl WebhookEvent {
+    pub const fn kind(&self) -> WebhookEventKind {
+        match self {
+            Self::ProfileUpdated { .. } => WebhookEventKind::ProfileUpdated,
+            Self::NodeSwitched { .. } => WebhookEventKind::NodeSwitched,
+            Self::CoreRestarted => WebhookEventKind::CoreRestarted,
+            Self::TrafficThreshold { .. } => WebhookEventKind::TrafficThreshold,
+        }
+    }
+
+    const fn name(&self) -> &'static str {
+        match self {
+            Self::ProfileUpdated { .. } => "profile_updated",
+            Self::NodeSwitched { .. } => "node_switched",
+            Self::CoreRestarted => "core_restarted",
+            Self::TrafficThreshold { .. } => "traffic_threshold",
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct WebhookPayload<'a> {
+    #[serde(flatten)]
+    event: &'a WebhookEvent,
+    timestamp: i64,
+}
+
+fn subscribes(webhook: &IWebhook, kind: WebhookEventKind) -> bool {
+    webhook.enabled.unwrap_or(true) && (webhook.events.is_empty() || webhook.events.contains(&kind))
+}
+
+const fn should_retry(status: reqwest::StatusCode) -> bool {
+    status.as_u16() == 429 || status.is_server_error()
+}
+
+pub struct WebhookManager {
+    client: reqwest::Client,
+    /// 本次运行已触发过流量提醒的回调
+    traffic_fired: Mutex<HashSet<String>>,
+    runner_started: AtomicBool,
+}
+
+singleton!(WebhookManager, WEBHOOK_MANAGER);
+
+impl WebhookManager {
+    fn new() -> Self {
+        Self {
+            client: reqwest::Client::builder()
+                .timeout(REQUEST_TIMEOUT)
+                .build()
+                .unwrap_or_default(),
+            traffic_fired: Mutex::new(HashSet::new()),
+            runner_started: AtomicBool::new(false),
+        }
+    }
+
+    /// 启动流量阈值检查，重复调用无副作用
+    pub fn init(&self) {
+        if self.runner_started.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        AsyncHandler::spawn(|| async {
+            let manager = Self::global();
+            loop {
+                tokio::time::sleep(TRAFFIC_POLL_INTERVAL).await;
+                if handle::Handle::global().is_exiting() {
+                    break;
+                }
+                manager.check_traffic().await;
+            }
+        });
+    }
+
+    /// 在后台向订阅了该事件的回调发送通知
+    pub fn dispatch(&self, event: WebhookEvent) {
+        AsyncHandler::spawn(move || async move {
+            let kind = event.kind();
+            let targets: Vec<IWebhook> = Self::webhooks()
+                .await
+                .into_iter()
+                .filter(|w| subscribes(w, kind))
+                .collect();
+            for webhook in targets {
+                Self::global().send(&webhook, &event).await;
+            }
+        });
+    }
+
+    /// 订阅更新、节点切换与内核重启事件转发给回调
+    pub fn listen_state_events(&'static self) {
+        EventBus::global().listen("webhooks", move |event| async move {
+            let event = match event {
+                StateEvent::ProfileUpdated { uid } => WebhookEvent::ProfileUpdated { uid },
+                StateEvent::NodeSwitched { group, node } => WebhookEvent::NodeSwitched { group, node },
+                StateEvent::CoreRestarted => WebhookEvent::CoreRestarted,
+                _ => return,
+            };
+            self.dispatch(event);
+        });
+    }
+
+    async fn webhooks() -> Vec<IWebhook> {
+        Config::verge().await.latest_arc().webhooks.clone().unwrap_or_default()
+    }
+
+    async fn check_traffic(&self) {
+        let targets: Vec<IWebhook> = Self::webhooks()
+            .await
+            .into_iter()
+            .filter(|w| w.traffic_threshold.is_some() && subscribes(w, WebhookEventKind::TrafficThreshold))
+            .collect();
+        if targets.is_empty() {
+            return;
+        }
+        let Ok(connections) = handle::Handle::mihomo().await.get_connections().await else {
+            return;
+        };
+        let total = connections.upload_total.saturating_add(connections.download_total);
+
+        for webhook in targets {
+            let threshold = webhook.traffic_threshold.unwrap_or_default();
+            if total < threshold {
+                // 内核重启后计数归零，允许再次触发
+                self.traffic_fired.lock().remove(&webhook.id);
+                continue;
+            }
+            if !self.traffic_fired.lock().insert(webhook.id.clone()) {
+                continue;
+            }
+            let event = WebhookEvent::TrafficThreshold {
+                upload_total: connections.upload_total,
+                download_total: connections.download_total,
+                threshold,
+            };
+            self.send(&webhook, &event).await;
+        }
+    }
+
+    async fn send(&self, webhook: &IWebhook, event: &WebhookEvent) {
+        let payload = WebhookPayload {
+            event,
+            timestamp: Local::now().timestamp(),
+        };
+        let Ok(body) = serde_json::to_vec(&payload) else {
+            return;
+        };
+
+        for attempt in 0..MAX_ATTEMPTS {
+            if attempt > 0 {
+                tokio::time::sleep(RETRY_BASE_DELAY * 2u32.pow(attempt - 1)).await;
+            }
+            let mut request = self
+                .client
+                .post(webhook.url.as_str())
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .header(EVENT_HEADER, event.name());
+            if let Some(secret) = webhook.secret.as_ref().filter(|s| !s.is_empty()) {
+                request = request.header(
+                    SIGNATURE_HEADER,
+                    format!(
+                        "sha256={}",
+                        crypto::to_hex(&crypto::hmac_sha256(secret.as_bytes(), &body))
+                    ),
+                );
+            }
+
+            match request.body(body.clone()).send().await {
+                Ok(response) if response.status().is_success() => return,
+                Ok(response) if !should_retry(response.status()) => {
+                    logging!(
+                        warn,
+                        Type::Network,
+                        "Webhook {} rejected {}: {}",
+                        webhook.id,
+                        event.name(),
+                        response.status()
+                    );
+                    return;
+                }
+                Ok(response) => logging!(
+                    debug,
+                    Type::Network,
+                    "Webhook {} attempt {} failed: {}",
+                    webhook.id,
+                    attempt + 1,
+                    response.status()
+                ),
+                Err(err) => logging!(
+                    debug,
+                    Type::Network,
+                    "Webhook {} attempt {} failed: {}",
+                    webhook.id,
+                    attempt + 1,
+                    err
+                ),
+            }
+        }
+        logging!(
+            warn,
+            Type::Network,
+            "Webhook {} gave up on {} after {} attempts",
+            webhook.id,
+            event.name(),
+            MAX_ATTEMPTS
+        );
+    }
+
+    async fn save(edit: impl FnOnce(&mut Vec<IWebhook>)) -> Result<()> {
+        Config::verge()
+            .await
+            .edit_draft(|d| edit(d.webhooks.get_or_insert_with(Vec::new)));
+        Config::verge().await.apply();
+        Config::verge().await.latest_arc().save_file().await?;
+        handle::Handle::refresh_verge();
+        Ok(())
+    }
+
+    pub async fn list(&self) -> Vec<IWebhook> {
+        Self::webhooks().await
+    }
+
+    /// 新增或更新回调，`id` 为空时新增
+    pub async fn add(&self, mut webhook: IWebhook) -> Result<Vec<IWebhook>> {
+        let url = webhook.url.trim();
+        if !(url.starts_with("http://") || url.starts_with("https://")) {
+            bail!("webhook url must start with http:// or https://");
+        }
+        webhook.url = url.into();
+        if webhook.id.is_empty() {
+            webhook.id = help::get_uid("w");
+        }
+        logging!(info, Type::Network, "Saving webhook {} -> {}", webhook.id, webhook.url);
+        Self::save(|webhooks| match webhooks.iter_mut().find(|w| w.id == webhook.id) {
+            Some(existing) => *existing = webhook,
+            None => webhooks.push(webhook),
+        })
+        .await?;
+        Ok(self.list().await)
+    }
+
+    pub async fn delete(&self, id: &str) -> Result<Vec<IWebhook>> {
+        if !Self::webhooks().await.iter().any(|w| w.id == id) {
+            bail!("webhook not found: {id}");
+        }
+        Self::save(|webhooks| webhooks.retain(|w| w.id != id)).await?;
+        self.traffic_fired.lock().remove(id);
+        Ok(self.list().await)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_payload_shape() {
+        let event = WebhookEvent::NodeSwitched {
+            group: "Proxy".into(),
+            node: "HK".into(),
+        };
+        let payload = serde_json::to_value(WebhookPayload {
+            event: &event,
+            timestamp: 1,
+        })
+        .unwrap_or_default();
+        assert_eq!(payload["event"], "node_switched");
+        assert_eq!(payload["data"]["node"], "HK");
+        assert_eq!(payload["timestamp"], 1);
+    }
+
+    #[test]
+    fn test_subscribes() {
+        let mut webhook = IWebhook::default();
+        assert!(subscribes(&webhook, WebhookEventKind::CoreRestarted));
+        webhook.events = vec![WebhookEventKind::ProfileUpdated];
+        assert!(!subscribes(&webhook, WebhookEventKind::CoreRestarted));
+        webhook.enabled = Some(false);
+        assert!(!subscribes(&webhook, WebhookEventKind::ProfileUpdated));
+    }
+}
@@ -0,0 +1,123 @@
+#![cfg(target_os = "windows")]
+//! 同步 WinHTTP 代理设置
+//!
+//! sysproxy 通过 WinINet 设置当前用户的代理，以服务运行或使用 WinHTTP 的程序不会读取这些设置。
+//! 开启 `enable_winhttp_proxy_sync` 后，启用系统代理时一并设置 WinHTTP 代理；首次修改前把原值保存到
+//! [`STATE_FILE`]，关闭系统代理、切换到 PAC 或关闭该选项时恢复，应用异常退出后也能在下次关闭时还原。
+//! 当前用户的注册表代理值由 sysproxy 管理，这里不再重复写入。
+//! 修改 WinHTTP 需要管理员权限，未提权时会弹出 UAC 提示；已写入的代理记录在状态文件中，重启后不会重复提权。
+
+use super::proxy_scope::ProxyScopeMode;
+use crate::utils::dirs;
+use anyhow::{Result, bail};
+use deelevate::{PrivilegeLevel, Token};
+use parking_lot::Mutex;
+use runas::Command as RunasCommand;
+use serde::{Deserialize, Serialize};
+use smartstring::alias::String;
+use std::{os::windows::process::CommandExt as _, path::PathBuf, process::Command};
+use winreg::{RegKey, enums::HKEY_LOCAL_MACHINE};
+
+const STATE_FILE: &str = "winhttp_backup.json";
+const WINHTTP_KEY: &str = r"SOFTWARE\Microsoft\Windows\CurrentVersion\Internet Settings\Connections";
+const WINHTTP_VALUE: &str = "WinHttpSettings";
+
+/// 同一时间只进行一次同步，避免设置与恢复交错
+static SYNC_LOCK: Mutex<()> = Mutex::new(());
+
+/// 修改前的 WinHTTP 设置与最近一次写入的代理
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct SyncState {
+    /// `None` 表示原本没有设置
+    winhttp: Option<Vec<u8>>,
+    /// 最近一次写入的 `代理|例外`，相同时不再重复提权
+    applied: Option<String>,
+}
+
+fn state_path() -> Result<PathBuf> {
+    Ok(dirs::app_home_dir()?.join(STATE_FILE))
+}
+
+fn read_state() -> Result<Option<SyncState>> {
+    let path = state_path()?;
+    if !path.exists() {
+        return Ok(None);
+    }
+    Ok(Some(serde_json::from_str(&std::fs::read_to_string(&path)?)?))
+}
+
+fn write_state(state: &SyncState) -> Result<()> {
+    std::fs::write(state_path()?, serde_json::to_string(state)?)?;
+    Ok(())
+}
+
+fn current_winhttp() -> Option<Vec<u8>> {
+    RegKey::predef(HKEY_LOCAL_MACHINE)
+        .open_subkey(WINHTTP_KEY)
+        .and_then(|key| key.get_raw_value(WINHTTP_VALUE))
+        .ok()
+        .map(|value| value.bytes.to_vec())
+}
+
+fn run_privileged(script: &str) -> Result<()> {
+    let token = Token::with_current_process()?;
+    let status = match token.privilege_level()? {
+        PrivilegeLevel::NotPrivileged => RunasCommand::new("cmd").args(&["/C", script]).show(false).status()?,
+        _ => Command::new("cmd")
+            .args(["/C", script])
+            .creation_flags(0x08000000)
+            .status()?,
+    };
+    if !status.success() {
+        bail!("command exited with status {}", status.code().unwrap_or(-1));
+    }
+    Ok(())
+}
+
+/// 按系统代理的设置同步 WinHTTP；PAC 模式下 WinHTTP 无法使用 PAC，恢复为修改前的设置
+pub fn apply(mode: &ProxyScopeMode) -> Result<()> {
+    let ProxyScopeMode::Manual { host, port, bypass } = mode else {
+        return restore();
+    };
+    let _sync = SYNC_LOCK.lock();
+    let target: String = format!("{host}:{port}|{bypass}").into();
+    let mut state = match read_state()? {
+        Some(state) => state,
+        // 首次修改前保存原值，此时 WinHTTP 尚未被本应用修改
+        None => SyncState {
+            winhttp: current_winhttp(),
+            applied: None,
+        },
+    };
+    if state.applied.as_ref() == Some(&target) {
+        return Ok(());
+    }
+    // 先保存备份，提权失败或应用退出后仍能恢复
+    write_state(&state)?;
+    run_privileged(&format!(
+        "netsh winhttp set proxy proxy-server=\"{host}:{port}\" bypass-list=\"{bypass}\""
+    ))?;
+    state.applied = Some(target);
+    write_state(&state)
+}
+
+/// 恢复修改前的 WinHTTP 设置，没有修改过时不做任何事
+pub fn restore() -> Result<()> {
+    let _sync = SYNC_LOCK.lock();
+    let Some(state) = read_state()? else {
+        return Ok(());
+    };
+    if state.applied.is_some() {
+        match &state.winhttp {
+            Some(bytes) => {
+                let hex: std::string::String = bytes.iter().map(|b| format!("{b:02x}")).collect();
+                run_privileged(&format!(
+                    "reg add \"HKLM\\{WINHTTP_KEY}\" /v {WINHTTP_VALUE} /t REG_BINARY /d {hex} /f"
+                ))?;
+            }
+            None => run_privileged("netsh winhttp reset proxy")?,
+        }
+    }
+    std::fs::remove_file(state_path()?)?;
+    Ok(())
+}
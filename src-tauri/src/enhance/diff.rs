@@ -0,0 +1,214 @@
+//! 运行配置的结构化差异
+//!
+//! `proxies`、`proxy-groups` 等按 `name` 对齐，`rules` 等普通列表按条目对齐，
+//! 其余映射逐键递归比较，路径以 `.` 连接，如 `proxy-groups.Proxy.proxies`。
+
+use serde::Serialize;
+use serde_yaml_ng::{Mapping, Value};
+use smartstring::alias::String;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Modified,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigChange {
+    pub path: String,
+    pub kind: ChangeKind,
+    pub old: Option<Value>,
+    pub new: Option<Value>,
+}
+
+fn join(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.into()
+    } else {
+        format!("{path}.{key}").into()
+    }
+}
+
+fn key_name(key: &Value) -> String {
+    match key {
+        Value::String(s) => s.as_str().into(),
+        other => serde_yaml_ng::to_string(other).unwrap_or_default().trim().into(),
+    }
+}
+
+fn item_name(item: &Value) -> Option<&str> {
+    item.as_mapping()?.get("name")?.as_str()
+}
+
+fn push(changes: &mut Vec<ConfigChange>, path: String, old: Option<&Value>, new: Option<&Value>) {
+    let kind = match (old, new) {
+        (None, Some(_)) => ChangeKind::Added,
+        (Some(_), None) => ChangeKind::Removed,
+        _ => ChangeKind::Modified,
+    };
+    changes.push(ConfigChange {
+        path,
+        kind,
+        old: old.cloned(),
+        new: new.cloned(),
+    });
+}
+
+fn diff_mapping(path: &str, old: &Mapping, new: &Mapping, changes: &mut Vec<ConfigChange>) {
+    for (key, old_value) in old {
+        let child = join(path, &key_name(key));
+        match new.get(key) {
+            Some(new_value) => diff_value(&child, old_value, new_value, changes),
+            None => push(changes, child, Some(old_value), None),
+        }
+    }
+    for (key, new_value) in new {
+        if !old.contains_key(key) {
+            push(changes, join(path, &key_name(key)), None, Some(new_value));
+        }
+    }
+}
+
+/// 按名称索引，存在不带 `name` 的元素时为 `None`
+fn by_name(seq: &[Value]) -> Option<HashMap<&str, &Value>> {
+    seq.iter().map(|item| Some((item_name(item)?, item))).collect()
+}
+
+fn counts(seq: &[Value]) -> HashMap<&Value, usize> {
+    let mut counts: HashMap<&Value, usize> = HashMap::with_capacity(seq.len());
+    for item in seq {
+        *counts.entry(item).or_default() += 1;
+    }
+    counts
+}
+
+/// 每个元素都带 `name` 时按名称对齐，否则按条目内容对齐；均先建立索引，避免逐项查找
+fn diff_sequence(path: &str, old: &[Value], new: &[Value], changes: &mut Vec<ConfigChange>) {
+    if !old.is_empty()
+        && !new.is_empty()
+        && let (Some(old_names), Some(new_names)) = (by_name(old), by_name(new))
+    {
+        for old_item in old {
+            let name = item_name(old_item).unwrap_or_default();
+            let child = join(path, name);
+            match new_names.get(name) {
+                Some(new_item) => diff_value(&child, old_item, new_item, changes),
+                None => push(changes, child, Some(old_item), None),
+            }
+        }
+        for new_item in new {
+            let name = item_name(new_item).unwrap_or_default();
+            if !old_names.contains_key(name) {
+                push(changes, join(path, name), None, Some(new_item));
+            }
+        }
+        return;
+    }
+
+    // 按出现次数比较，重复的条目同样计入
+    let path: String = path.into();
+    let before = changes.len();
+    let mut remaining = counts(new);
+    for item in old {
+        match remaining.get_mut(item) {
+            Some(count) if *count > 0 => *count -= 1,
+            _ => push(changes, path.clone(), Some(item), None),
+        }
+    }
+    let mut remaining = counts(old);
+    for item in new {
+        match remaining.get_mut(item) {
+            Some(count) if *count > 0 => *count -= 1,
+            _ => push(changes, path.clone(), None, Some(item)),
+        }
+    }
+    // 条目相同但顺序变化（如规则重排）同样会影响匹配结果
+    if changes.len() == before && old != new {
+        changes.push(ConfigChange {
+            path,
+            kind: ChangeKind::Modified,
+            old: Some(Value::Sequence(old.to_vec())),
+            new: Some(Value::Sequence(new.to_vec())),
+        });
+    }
+}
+
+fn diff_value(path: &str, old: &Value, new: &Value, changes: &mut Vec<ConfigChange>) {
+    if old == new {
+        return;
+    }
+    match (old, new) {
+        (Value::Mapping(old), Value::Mapping(new)) => diff_mapping(path, old, new, changes),
+        (Value::Sequence(old), Value::Sequence(new)) => diff_sequence(path, old, new, changes),
+        _ => push(changes, path.into(), Some(old), Some(new)),
+    }
+}
+
+/// 比较两份运行配置，返回从 `old` 到 `new` 的全部变更
+pub fn diff_config(old: &Mapping, new: &Mapping) -> Vec<ConfigChange> {
+    let mut changes = Vec::new();
+    diff_mapping("", old, new, &mut changes);
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(yaml: &str) -> Mapping {
+        serde_yaml_ng::from_str(yaml).unwrap_or_default()
+    }
+
+    #[test]
+    fn test_diff_named_items_and_rules() {
+        let old = parse(
+            r#"
+mode: rule
+proxies:
+  - { name: hk, type: ss, port: 1 }
+  - { name: jp, type: ss, port: 2 }
+rules: ["DOMAIN,a.com,DIRECT", "MATCH,hk"]
+"#,
+        );
+        let new = parse(
+            r#"
+mode: global
+proxies:
+  - { name: hk, type: ss, port: 3 }
+  - { name: us, type: ss, port: 4 }
+rules: ["DOMAIN,a.com,DIRECT", "DOMAIN,b.com,hk", "MATCH,hk"]
+"#,
+        );
+        let changes = diff_config(&old, &new);
+        let summary: Vec<(&str, ChangeKind)> = changes.iter().map(|c| (c.path.as_str(), c.kind)).collect();
+        assert_eq!(
+            summary,
+            [
+                ("mode", ChangeKind::Modified),
+                ("proxies.hk.port", ChangeKind::Modified),
+                ("proxies.jp", ChangeKind::Removed),
+                ("proxies.us", ChangeKind::Added),
+                ("rules", ChangeKind::Added),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diff_reordered_rules() {
+        let old = parse(r#"rules: ["DOMAIN,a.com,DIRECT", "MATCH,hk"]"#);
+        let new = parse(r#"rules: ["MATCH,hk", "DOMAIN,a.com,DIRECT"]"#);
+        let changes = diff_config(&old, &new);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].kind, ChangeKind::Modified);
+        assert!(diff_config(&old, &old).is_empty());
+
+        let old = parse(r#"rules: ["MATCH,hk", "MATCH,hk"]"#);
+        let new = parse(r#"rules: ["MATCH,hk"]"#);
+        let changes = diff_config(&old, &new);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].kind, ChangeKind::Removed);
+    }
+}
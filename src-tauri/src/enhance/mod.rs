@@ -1,21 +1,32 @@
 mod chain;
+pub mod diff;
 pub mod field;
 mod merge;
+mod nodes;
+pub mod rules;
+mod runtime;
 mod script;
 pub mod seq;
 mod tun;
 
+pub use self::script::serve_script;
 use self::{
     chain::{AsyncChainItemFrom as _, ChainItem, ChainType},
     field::{use_keys, use_lowercase, use_sort},
     merge::use_merge,
+    nodes::use_node_filter,
+    rules::{ManagedRules, use_managed_rules},
+    runtime::use_profile_runtime,
     script::use_script,
     seq::{SeqMap, use_seq},
     tun::use_tun,
 };
 use crate::utils::dirs;
 use crate::{config::Config, utils::tmpl};
-use crate::{config::IVerge, constants};
+use crate::{
+    config::{IClashTemp, IProfiles, IVerge, PrfNodeFilter, PrfRuntime},
+    constants,
+};
 use clash_verge_logging::{Type, logging};
 use serde_yaml_ng::{Mapping, Value};
 use smartstring::alias::String;
@@ -37,6 +48,8 @@ struct ConfigValues {
     redir_enabled: bool,
     #[cfg(target_os = "linux")]
     tproxy_enabled: bool,
+    #[cfg(target_os = "linux")]
+    kill_switch_enabled: bool,
 }
 
 #[derive(Debug)]
@@ -49,6 +62,12 @@ struct ProfileItems {
     groups_item: ChainItem,
     global_merge: ChainItem,
     global_script: ChainItem,
+    /// 订阅设置了显式扩展链时，按顺序启用的各层
+    layers: Option<Vec<ChainItem>>,
+    /// 订阅覆盖的运行参数
+    runtime: Option<PrfRuntime>,
+    /// 订阅的节点去重、过滤与重命名
+    nodes: Option<PrfNodeFilter>,
     profile_name: String,
 }
 
@@ -85,6 +104,9 @@ impl Default for ProfileItems {
                 uid: "Script".into(),
                 data: ChainType::Script(tmpl::ITEM_SCRIPT.into()),
             },
+            layers: None,
+            runtime: None,
+            nodes: None,
         }
     }
 }
@@ -123,6 +145,9 @@ async fn get_config_values() -> ConfigValues {
     #[cfg(target_os = "linux")]
     let tproxy_enabled = verge_arc.verge_tproxy_enabled.unwrap_or(false);
 
+    #[cfg(target_os = "linux")]
+    let kill_switch_enabled = verge_arc.enable_kill_switch.unwrap_or(false);
+
     drop(verge_arc);
     drop(verge);
 
@@ -138,31 +163,23 @@ async fn get_config_values() -> ConfigValues {
         redir_enabled,
         #[cfg(target_os = "linux")]
         tproxy_enabled,
+        #[cfg(target_os = "linux")]
+        kill_switch_enabled,
     }
 }
 
 #[allow(clippy::cognitive_complexity)]
-async fn collect_profile_items() -> ProfileItems {
-    let profiles = Config::profiles().await;
-    let profiles_arc = profiles.latest_arc();
-    drop(profiles);
-
-    let current = profiles_arc.current_mapping().await.unwrap_or_default();
-
-    let current_profile_uid = match profiles_arc.get_current() {
+async fn collect_profile_items(profiles: &IProfiles, profile_uid: Option<&String>) -> ProfileItems {
+    let current_profile_uid = match profile_uid.or_else(|| profiles.get_current()) {
         Some(uid) => uid,
-        None => {
-            drop(profiles_arc);
-            return ProfileItems::default();
-        }
+        None => return ProfileItems::default(),
     };
 
-    let current_item = match profiles_arc.get_item(current_profile_uid) {
+    let current = profiles.profile_mapping(current_profile_uid).await.unwrap_or_default();
+
+    let current_item = match profiles.get_item(current_profile_uid) {
         Ok(item) => item,
-        Err(_) => {
-            drop(profiles_arc);
-            return ProfileItems::default();
-        }
+        Err(_) => return ProfileItems::default(),
     };
 
     let merge_uid: Cow<'_, str> = if let Some(s) = current_item.current_merge() {
@@ -191,14 +208,33 @@ async fn collect_profile_items() -> ProfileItems {
         Cow::Owned("Groups".into())
     };
 
-    let name = profiles_arc
+    let layers = match current_item.option.as_ref().and_then(|o| o.chain.clone()) {
+        Some(chain) => {
+            let mut layers = Vec::new();
+            for layer in chain.iter().filter(|layer| layer.enabled) {
+                let item = profiles.get_item(&layer.uid).ok().cloned();
+                if let Some(item) = item
+                    && let Some(chain_item) = <Option<ChainItem>>::from_async(&item).await
+                {
+                    layers.push(chain_item);
+                }
+            }
+            Some(layers)
+        }
+        None => None,
+    };
+
+    let runtime = current_item.option.as_ref().and_then(|o| o.runtime.clone());
+    let nodes = current_item.option.as_ref().and_then(|o| o.nodes.clone());
+
+    let name = profiles
         .get_item(current_profile_uid)
         .ok()
         .and_then(|item| item.name.clone())
         .unwrap_or_default();
 
     let merge_item = {
-        let item = profiles_arc.get_item(&merge_uid).ok().cloned();
+        let item = profiles.get_item(&merge_uid).ok().cloned();
         if let Some(item) = item {
             <Option<ChainItem>>::from_async(&item).await
         } else {
@@ -211,7 +247,7 @@ async fn collect_profile_items() -> ProfileItems {
     });
 
     let script_item = {
-        let item = profiles_arc.get_item(&script_uid).ok().cloned();
+        let item = profiles.get_item(&script_uid).ok().cloned();
         if let Some(item) = item {
             <Option<ChainItem>>::from_async(&item).await
         } else {
@@ -224,7 +260,7 @@ async fn collect_profile_items() -> ProfileItems {
     });
 
     let rules_item = {
-        let item = profiles_arc.get_item(&rules_uid).ok().cloned();
+        let item = profiles.get_item(&rules_uid).ok().cloned();
         if let Some(item) = item {
             <Option<ChainItem>>::from_async(&item).await
         } else {
@@ -237,7 +273,7 @@ async fn collect_profile_items() -> ProfileItems {
     });
 
     let proxies_item = {
-        let item = profiles_arc.get_item(&proxies_uid).ok().cloned();
+        let item = profiles.get_item(&proxies_uid).ok().cloned();
         if let Some(item) = item {
             <Option<ChainItem>>::from_async(&item).await
         } else {
@@ -250,7 +286,7 @@ async fn collect_profile_items() -> ProfileItems {
     });
 
     let groups_item = {
-        let item = profiles_arc.get_item(&groups_uid).ok().cloned();
+        let item = profiles.get_item(&groups_uid).ok().cloned();
         if let Some(item) = item {
             <Option<ChainItem>>::from_async(&item).await
         } else {
@@ -263,7 +299,7 @@ async fn collect_profile_items() -> ProfileItems {
     });
 
     let global_merge = {
-        let item = profiles_arc.get_item("Merge").ok().cloned();
+        let item = profiles.get_item("Merge").ok().cloned();
         if let Some(item) = item {
             <Option<ChainItem>>::from_async(&item).await
         } else {
@@ -276,7 +312,7 @@ async fn collect_profile_items() -> ProfileItems {
     });
 
     let global_script = {
-        let item = profiles_arc.get_item("Script").ok().cloned();
+        let item = profiles.get_item("Script").ok().cloned();
         if let Some(item) = item {
             <Option<ChainItem>>::from_async(&item).await
         } else {
@@ -288,8 +324,6 @@ async fn collect_profile_items() -> ProfileItems {
         data: ChainType::Script(tmpl::ITEM_SCRIPT.into()),
     });
 
-    drop(profiles_arc);
-
     ProfileItems {
         config: current,
         merge_item,
@@ -299,6 +333,9 @@ async fn collect_profile_items() -> ProfileItems {
         groups_item,
         global_merge,
         global_script,
+        layers,
+        runtime,
+        nodes,
         profile_name: name,
     }
 }
@@ -333,48 +370,51 @@ fn process_global_items(
     (config, exists_keys, result_map)
 }
 
-#[allow(clippy::too_many_arguments)]
+/// 按类型将单个扩展项叠加到配置上
+fn use_chain_item(
+    config: Mapping,
+    item: ChainItem,
+    exists_keys: &mut Vec<String>,
+    result_map: &mut HashMap<String, ResultLog>,
+    profile_name: &String,
+) -> Mapping {
+    match item.data {
+        ChainType::Rules(rules) => use_seq(rules, config, "rules"),
+        ChainType::Proxies(proxies) => use_seq(proxies, config, "proxies"),
+        ChainType::Groups(groups) => use_seq(groups, config, "proxy-groups"),
+        ChainType::Merge(merge) => {
+            exists_keys.extend(use_keys(&merge));
+            use_merge(&merge, config)
+        }
+        ChainType::Script(script) => {
+            let mut logs = vec![];
+            let config = match use_script(script, &config, profile_name) {
+                Ok((res_config, res_logs)) => {
+                    exists_keys.extend(use_keys(&res_config));
+                    logs.extend(res_logs);
+                    res_config
+                }
+                Err(err) => {
+                    logs.push(("exception".into(), err.to_string().into()));
+                    config
+                }
+            };
+            result_map.insert(item.uid, logs);
+            config
+        }
+    }
+}
+
 fn process_profile_items(
     mut config: Mapping,
     mut exists_keys: Vec<String>,
     mut result_map: HashMap<String, ResultLog>,
-    rules_item: ChainItem,
-    proxies_item: ChainItem,
-    groups_item: ChainItem,
-    merge_item: ChainItem,
-    script_item: ChainItem,
+    items: Vec<ChainItem>,
     profile_name: &String,
 ) -> (Mapping, Vec<String>, HashMap<String, ResultLog>) {
-    if let ChainType::Rules(rules) = rules_item.data {
-        config = use_seq(rules, config.to_owned(), "rules");
-    }
-
-    if let ChainType::Proxies(proxies) = proxies_item.data {
-        config = use_seq(proxies, config.to_owned(), "proxies");
-    }
-
-    if let ChainType::Groups(groups) = groups_item.data {
-        config = use_seq(groups, config.to_owned(), "proxy-groups");
+    for item in items {
+        config = use_chain_item(config, item, &mut exists_keys, &mut result_map, profile_name);
     }
-
-    if let ChainType::Merge(merge) = merge_item.data {
-        exists_keys.extend(use_keys(&merge));
-        config = use_merge(&merge, config.to_owned());
-    }
-
-    if let ChainType::Script(script) = script_item.data {
-        let mut logs = vec![];
-        match use_script(script, &config, profile_name) {
-            Ok((res_config, res_logs)) => {
-                exists_keys.extend(use_keys(&res_config));
-                config = res_config;
-                logs.extend(res_logs);
-            }
-            Err(err) => logs.push(("exception".into(), err.to_string().into())),
-        }
-        result_map.insert(script_item.uid, logs);
-    }
-
     (config, exists_keys, result_map)
 }
 
@@ -386,6 +426,11 @@ async fn merge_default_config(
     #[cfg(not(target_os = "windows"))] redir_enabled: bool,
     #[cfg(target_os = "linux")] tproxy_enabled: bool,
 ) -> Mapping {
+    let restrict_cors = Config::verge()
+        .await
+        .latest_arc()
+        .restrict_controller_cors
+        .unwrap_or(false);
     for (key, value) in clash_config.into_iter() {
         if key.as_str() == Some("tun") {
             let mut tun = config.get_mut("tun").map_or_else(Mapping::new, |val| {
@@ -432,6 +477,10 @@ async fn merge_default_config(
                     continue;
                 }
             }
+            if key.as_str() == Some("external-controller-cors") && restrict_cors {
+                config.insert(key, IClashTemp::app_only_cors().into());
+                continue;
+            }
             // 处理 external-controller 键的开关逻辑
             if key.as_str() == Some("external-controller") {
                 let enable_external_controller = Config::verge()
@@ -592,6 +641,20 @@ async fn apply_dns_settings(mut config: Mapping, enable_dns_settings: bool) -> M
 /// Enhance mode
 /// 返回最终订阅、该订阅包含的键、和script执行的结果
 pub async fn enhance() -> (Mapping, HashSet<String>, HashMap<String, ResultLog>) {
+    enhance_profile(None).await
+}
+
+/// 以指定订阅代替当前订阅生成最终配置，用于切换前的试运行与预览
+pub async fn enhance_profile(profile_uid: Option<&String>) -> (Mapping, HashSet<String>, HashMap<String, ResultLog>) {
+    let profiles = Config::profiles().await.latest_arc();
+    enhance_with_profiles(&profiles, profile_uid).await
+}
+
+/// 按给定的订阅列表生成最终配置，不读取共享的订阅草稿
+pub async fn enhance_with_profiles(
+    profiles: &IProfiles,
+    profile_uid: Option<&String>,
+) -> (Mapping, HashSet<String>, HashMap<String, ResultLog>) {
     // gather config values
     let cfg_vals = get_config_values().await;
     let ConfigValues {
@@ -606,10 +669,12 @@ pub async fn enhance() -> (Mapping, HashSet<String>, HashMap<String, ResultLog>)
         redir_enabled,
         #[cfg(target_os = "linux")]
         tproxy_enabled,
+        #[cfg(target_os = "linux")]
+        kill_switch_enabled,
     } = cfg_vals;
 
     // collect profile items
-    let profile = collect_profile_items().await;
+    let profile = collect_profile_items(profiles, profile_uid).await;
     let config = profile.config;
     let merge_item = profile.merge_item;
     let script_item = profile.script_item;
@@ -619,22 +684,31 @@ pub async fn enhance() -> (Mapping, HashSet<String>, HashMap<String, ResultLog>)
     let global_merge = profile.global_merge;
     let global_script = profile.global_script;
     let profile_name = profile.profile_name;
+    let profile_runtime = profile.runtime;
+    let profile_nodes = profile.nodes;
+    let profile_items = profile
+        .layers
+        .unwrap_or_else(|| vec![rules_item, proxies_item, groups_item, merge_item, script_item]);
 
     // process globals
     let (config, exists_keys, result_map) = process_global_items(config, global_merge, global_script, &profile_name);
 
     // process profile-specific items
-    let (config, exists_keys, result_map) = process_profile_items(
-        config,
-        exists_keys,
-        result_map,
-        rules_item,
-        proxies_item,
-        groups_item,
-        merge_item,
-        script_item,
-        &profile_name,
-    );
+    let (config, exists_keys, result_map) =
+        process_profile_items(config, exists_keys, result_map, profile_items, &profile_name);
+
+    // managed rules take precedence over profile rules
+    let config = match ManagedRules::load().await {
+        Ok(managed) => use_managed_rules(config, &managed),
+        Err(err) => {
+            logging!(error, Type::Config, "failed to load managed rules: {err}");
+            config
+        }
+    };
+
+    // node dedupe / filter / rename, after all profile layers have added their proxies
+    // and after managed rules, so rules naming the original nodes follow the renames
+    let config = use_node_filter(config, profile_nodes.as_ref());
 
     // merge default clash config
     let config = merge_default_config(
@@ -649,12 +723,19 @@ pub async fn enhance() -> (Mapping, HashSet<String>, HashMap<String, ResultLog>)
     )
     .await;
 
+    // profile-scoped runtime options override the global ones
+    let config = use_profile_runtime(config, profile_runtime.as_ref());
+
     // builtin scripts
     let mut config = apply_builtin_scripts(config, clash_core, enable_builtin);
 
     config = cleanup_proxy_groups(config);
 
     config = use_tun(config, enable_tun);
+    #[cfg(target_os = "linux")]
+    {
+        config = crate::core::kill_switch::use_routing_mark(config, kill_switch_enabled);
+    }
     config = use_sort(config);
 
     // dns settings
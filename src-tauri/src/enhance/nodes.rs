@@ -0,0 +1,336 @@
+//! 订阅节点的去重、过滤与重命名
+//!
+//! 只处理配置中直接列出的 `proxies`。代理集合（`proxy-providers`）的节点由内核在运行时拉取，
+//! 生成配置时无法得知，因此只向其下发名称过滤，不参与去重与重命名。
+//! 被过滤的节点不会被静默替换为 `DIRECT`：成员全部被过滤的代理组与指向已移除节点的规则均改为 `REJECT`。
+
+use crate::{
+    config::PrfNodeFilter,
+    feat::{node_country, normalize_country},
+};
+use clash_verge_logging::{Type, logging};
+use regex::Regex;
+use serde_yaml_ng::{Mapping, Value};
+use smartstring::alias::String;
+use std::collections::{HashMap, HashSet};
+
+/// 判断重复节点时比较的凭据字段，取第一个存在的
+const CREDENTIAL_KEYS: [&str; 5] = ["uuid", "password", "auth-str", "private-key", "username"];
+
+/// 名称过滤，关键字按正则处理，不区分大小写
+fn name_pattern(pattern: Option<&str>) -> Option<Regex> {
+    let pattern = pattern?.trim();
+    if pattern.is_empty() {
+        return None;
+    }
+    Regex::new(&format!("(?i){pattern}"))
+        .inspect_err(|err| logging!(warn, Type::Config, "invalid node filter \"{pattern}\": {err}"))
+        .ok()
+}
+
+fn field(proxy: &Mapping, key: &str) -> Option<std::string::String> {
+    match proxy.get(key)? {
+        Value::String(value) => Some(value.clone()),
+        Value::Number(value) => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+/// 类型、服务器、端口与凭据相同即视为同一节点
+fn dedupe_key(proxy: &Mapping) -> Option<std::string::String> {
+    let server = field(proxy, "server")?;
+    let credential = CREDENTIAL_KEYS
+        .iter()
+        .find_map(|key| field(proxy, key))
+        .unwrap_or_default();
+    Some(format!(
+        "{}|{}|{}|{credential}",
+        field(proxy, "type").unwrap_or_default(),
+        server.to_lowercase(),
+        field(proxy, "port").unwrap_or_default(),
+    ))
+}
+
+fn is_flag_char(c: char) -> bool {
+    ('\u{1F1E6}'..='\u{1F1FF}').contains(&c)
+}
+
+/// 国家代码对应的旗帜 emoji
+fn flag(code: &str) -> std::string::String {
+    code.chars()
+        .filter_map(|c| {
+            let offset = u32::from(c.to_ascii_uppercase()).checked_sub(u32::from('A'))?;
+            char::from_u32(0x1F1E6 + offset)
+        })
+        .collect()
+}
+
+/// 模板包含 `{flag}` 时去掉名称中原有的旗帜，避免重复
+fn render_name(template: &str, name: &str, region: Option<&str>, index: usize) -> String {
+    let base = if template.contains("{flag}") {
+        name.chars()
+            .filter(|c| !is_flag_char(*c))
+            .collect::<std::string::String>()
+    } else {
+        name.to_owned()
+    };
+    template
+        .replace("{flag}", &region.map(flag).unwrap_or_default())
+        .replace("{region}", region.unwrap_or_default())
+        .replace("{index}", &index.to_string())
+        .replace("{name}", base.trim())
+        .trim()
+        .into()
+}
+
+/// 重名时追加序号
+fn unique_name(name: String, used: &HashSet<String>) -> String {
+    if !used.contains(&name) {
+        return name;
+    }
+    (2..)
+        .map(|n| String::from(format!("{name} {n}")))
+        .find(|candidate| !used.contains(candidate))
+        .unwrap_or(name)
+}
+
+/// 按映射更新代理组成员：重命名的替换为新名称，合并的指向保留的节点，移除的删除
+fn update_groups(config: &mut Mapping, renamed: &HashMap<String, Option<String>>) {
+    let Some(Value::Sequence(groups)) = config.get_mut("proxy-groups") else {
+        return;
+    };
+    for group in groups.iter_mut().filter_map(Value::as_mapping_mut) {
+        let has_provider = group.contains_key("use") || group.contains_key("include-all");
+        let Some(Value::Sequence(members)) = group.get_mut("proxies") else {
+            continue;
+        };
+        let mut seen = HashSet::new();
+        let mut next = Vec::with_capacity(members.len());
+        for member in std::mem::take(members) {
+            let member = match member.as_str().and_then(|name| renamed.get(name)) {
+                Some(Some(name)) => Value::from(name.as_str()),
+                Some(None) => continue,
+                None => member,
+            };
+            if member.as_str().is_none_or(|name| seen.insert(name.to_owned())) {
+                next.push(member);
+            }
+        }
+        // 成员全部被过滤时内核会拒绝该组，改为拒绝连接，避免本应走代理的流量直连
+        if next.is_empty() && !has_provider {
+            next.push("REJECT".into());
+        }
+        *members = next;
+    }
+}
+
+/// 规则中引用节点名称的同样更新，引用已移除节点的规则（包括 `MATCH`）改为 `REJECT`，
+/// 删除规则会让流量落到后续规则，同样可能直连
+fn update_rules(config: &mut Mapping, renamed: &HashMap<String, Option<String>>) {
+    let Some(Value::Sequence(rules)) = config.get_mut("rules") else {
+        return;
+    };
+    for rule in rules.iter_mut() {
+        let Some(text) = rule.as_str() else {
+            continue;
+        };
+        let mut parts: Vec<&str> = text.split(',').collect();
+        // 目标位于末尾，`no-resolve` 等选项之前
+        let Some(target) = parts
+            .iter_mut()
+            .rev()
+            .find(|part| !matches!(part.trim(), "no-resolve" | "src"))
+        else {
+            continue;
+        };
+        match renamed.get(target.trim()) {
+            Some(Some(name)) => *target = name.as_str(),
+            Some(None) => {
+                logging!(info, Type::Config, "rule targeting a filtered node now rejects: {text}");
+                *target = "REJECT";
+            }
+            None => continue,
+        }
+        *rule = parts.join(",").into();
+    }
+}
+
+/// 代理集合的节点由内核拉取，只下发名称过滤；已设置过滤的集合保持不变
+fn filter_providers(config: &mut Mapping, filter: &PrfNodeFilter) {
+    let Some(Value::Mapping(providers)) = config.get_mut("proxy-providers") else {
+        return;
+    };
+    for provider in providers.values_mut().filter_map(Value::as_mapping_mut) {
+        for (key, pattern) in [("filter", &filter.include), ("exclude-filter", &filter.exclude)] {
+            if let Some(pattern) = pattern.as_deref().filter(|p| !p.trim().is_empty())
+                && !provider.contains_key(key)
+            {
+                provider.insert(key.into(), format!("(?i){}", pattern.trim()).into());
+            }
+        }
+    }
+}
+
+/// 对订阅中的节点去重、过滤并重命名，并同步更新代理组与规则中的引用
+pub fn use_node_filter(mut config: Mapping, filter: Option<&PrfNodeFilter>) -> Mapping {
+    let Some(filter) = filter else {
+        return config;
+    };
+    let include = name_pattern(filter.include.as_deref());
+    let exclude = name_pattern(filter.exclude.as_deref());
+    let regions: HashSet<String> = filter
+        .regions
+        .iter()
+        .flatten()
+        .filter_map(|code| normalize_country(code))
+        .collect();
+    let dedupe = filter.dedupe.unwrap_or_default();
+
+    // 原名称 -> 新名称，`None` 表示已移除
+    let mut renamed: HashMap<String, Option<String>> = HashMap::new();
+    if let Some(Value::Sequence(proxies)) = config.get_mut("proxies") {
+        let mut first_by_key: HashMap<std::string::String, String> = HashMap::new();
+        let mut used = HashSet::new();
+        let mut kept = Vec::with_capacity(proxies.len());
+        for mut proxy in std::mem::take(proxies) {
+            let Some(map) = proxy.as_mapping_mut() else {
+                kept.push(proxy);
+                continue;
+            };
+            let Some(name) = map.get("name").and_then(Value::as_str).map(ToOwned::to_owned) else {
+                kept.push(proxy);
+                continue;
+            };
+            let region = node_country(&name);
+            let matched = include.as_ref().is_none_or(|re| re.is_match(&name))
+                && !exclude.as_ref().is_some_and(|re| re.is_match(&name))
+                && (regions.is_empty() || region.as_ref().is_some_and(|code| regions.contains(code)));
+            if !matched {
+                renamed.insert(name.into(), None);
+                continue;
+            }
+            let key = dedupe.then(|| dedupe_key(map)).flatten();
+            if let Some(first) = key.as_ref().and_then(|key| first_by_key.get(key)) {
+                renamed.insert(name.into(), Some(first.clone()));
+                continue;
+            }
+
+            let new_name = match filter.rename.as_deref() {
+                Some(template) => unique_name(render_name(template, &name, region.as_deref(), kept.len() + 1), &used),
+                None => name.as_str().into(),
+            };
+            if new_name != name {
+                map.insert("name".into(), new_name.as_str().into());
+                renamed.insert(name.into(), Some(new_name.clone()));
+            }
+            if let Some(key) = key {
+                first_by_key.insert(key, new_name.clone());
+            }
+            used.insert(new_name);
+            kept.push(proxy);
+        }
+        *proxies = kept;
+    }
+
+    if !renamed.is_empty() {
+        update_groups(&mut config, &renamed);
+        update_rules(&mut config, &renamed);
+    }
+    filter_providers(&mut config, filter);
+    config
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(config: &Mapping, key: &str) -> Vec<std::string::String> {
+        match key {
+            "proxies" => config
+                .get("proxies")
+                .and_then(Value::as_sequence)
+                .into_iter()
+                .flatten()
+                .filter_map(|p| p.get("name").and_then(Value::as_str))
+                .map(ToOwned::to_owned)
+                .collect(),
+            _ => config
+                .get("proxy-groups")
+                .and_then(Value::as_sequence)
+                .and_then(|groups| groups.first())
+                .and_then(|group| group.get("proxies"))
+                .and_then(Value::as_sequence)
+                .into_iter()
+                .flatten()
+                .filter_map(Value::as_str)
+                .map(ToOwned::to_owned)
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_dedupe_filter_and_rename() {
+        let config: Mapping = serde_yaml_ng::from_str(
+            r"
+proxies:
+  - {name: HK 01, type: ss, server: a.com, port: 443, password: p}
+  - {name: HK 01 copy, type: ss, server: A.com, port: 443, password: p}
+  - {name: JP 01, type: ss, server: b.com, port: 443, password: p}
+  - {name: Expire 2030-01-01, type: ss, server: c.com, port: 443, password: p}
+proxy-groups:
+  - {name: Proxy, type: select, proxies: [HK 01 copy, HK 01, JP 01, Expire 2030-01-01]}
+rules:
+  - DOMAIN,example.com,HK 01
+  - DOMAIN,expire.com,Expire 2030-01-01
+  - MATCH,Expire 2030-01-01
+",
+        )
+        .unwrap_or_default();
+        let filter = PrfNodeFilter {
+            dedupe: Some(true),
+            exclude: Some("expire".into()),
+            rename: Some("{flag} {name}".into()),
+            ..PrfNodeFilter::default()
+        };
+
+        let config = use_node_filter(config, Some(&filter));
+        assert_eq!(names(&config, "proxies"), ["🇭🇰 HK 01", "🇯🇵 JP 01"]);
+        assert_eq!(names(&config, "proxy-groups"), ["🇭🇰 HK 01", "🇯🇵 JP 01"]);
+        let rules = config
+            .get("rules")
+            .and_then(Value::as_sequence)
+            .cloned()
+            .unwrap_or_default();
+        assert_eq!(
+            rules,
+            [
+                Value::from("DOMAIN,example.com,🇭🇰 HK 01"),
+                Value::from("DOMAIN,expire.com,REJECT"),
+                Value::from("MATCH,REJECT"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_region_filter() {
+        let config: Mapping = serde_yaml_ng::from_str(
+            r"
+proxies:
+  - {name: 香港 01, type: ss, server: a.com, port: 1}
+  - {name: US 01, type: ss, server: b.com, port: 1}
+  - {name: Info, type: ss, server: c.com, port: 1}
+proxy-groups:
+  - {name: Proxy, type: select, proxies: [Info]}
+",
+        )
+        .unwrap_or_default();
+        let filter = PrfNodeFilter {
+            regions: Some(vec!["hk".into()]),
+            ..PrfNodeFilter::default()
+        };
+
+        let config = use_node_filter(config, Some(&filter));
+        assert_eq!(names(&config, "proxies"), ["香港 01"]);
+        assert_eq!(names(&config, "proxy-groups"), ["REJECT"]);
+    }
+}
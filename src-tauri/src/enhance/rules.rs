@@ -0,0 +1,336 @@
+//! 由应用管理的规则覆盖层
+//!
+//! 规则保存在 `managed_rules.yaml` 中，生成运行配置时插入到订阅规则之前，
+//! 因此总是优先匹配，且切换订阅后依然有效。通过 URL 添加的 rule-providers
+//! 也保存在这里，与订阅中的同名 provider 冲突时以这里为准。
+//! 代理链同样保存在这里，生成运行配置时展开为带 `dialer-proxy` 的节点；
+//! 按应用分流的设置转换为 `PROCESS-NAME` / `PROCESS-PATH` 规则，排在手动规则之后。
+//! 目标代理组或节点在当前订阅中不存在的规则会被跳过，否则内核会拒绝整个配置。
+
+use crate::{
+    constants,
+    utils::{dirs, help},
+};
+use anyhow::{Result, bail};
+use clash_verge_logging::{Type, logging};
+use serde::{Deserialize, Serialize};
+use serde_yaml_ng::{Mapping, Sequence, Value};
+use smartstring::alias::String;
+use std::collections::HashSet;
+
+/// 内核内置的策略，任何配置中都可作为规则目标
+const BUILTIN_TARGETS: [&str; 6] = ["DIRECT", "REJECT", "REJECT-DROP", "PASS", "COMPATIBLE", "GLOBAL"];
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ManagedRules {
+    #[serde(default)]
+    pub rules: Vec<String>,
+    #[serde(default, skip_serializing_if = "Mapping::is_empty")]
+    pub providers: Mapping,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub chains: Vec<ProxyChain>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub apps: Vec<AppRoute>,
+}
+
+/// 按应用分流，`process` 为进程名或可执行文件的完整路径
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AppRoute {
+    pub process: String,
+    /// 代理组、节点或 `DIRECT` / `REJECT`
+    pub target: String,
+}
+
+impl AppRoute {
+    /// 含路径分隔符时按完整路径匹配
+    pub fn to_rule(&self) -> Result<String> {
+        let kind = if self.process.contains(['/', '\\']) {
+            "PROCESS-PATH"
+        } else {
+            "PROCESS-NAME"
+        };
+        if self.process.contains(',') {
+            bail!("process cannot contain ',': {}", self.process);
+        }
+        normalize_rule(&format!("{kind},{},{}", self.process, self.target))
+    }
+}
+
+/// 代理链，流量依次经过 `nodes` 中的节点，最后一个为出口
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProxyChain {
+    pub name: String,
+    pub nodes: Vec<String>,
+}
+
+impl ManagedRules {
+    pub async fn load() -> Result<Self> {
+        let path = dirs::app_home_dir()?.join(constants::files::MANAGED_RULES);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        help::read_yaml(&path).await
+    }
+
+    pub async fn save(&self) -> Result<()> {
+        let path = dirs::app_home_dir()?.join(constants::files::MANAGED_RULES);
+        help::save_yaml(&path, self, Some("# Managed rules by Clash Verge")).await
+    }
+}
+
+/// 规范化一条规则，如 `domain-suffix, example.com ,PROXY` -> `DOMAIN-SUFFIX,example.com,PROXY`
+pub fn normalize_rule(rule: &str) -> Result<String> {
+    let parts: Vec<&str> = rule.split(',').map(str::trim).collect();
+    let kind = parts[0].to_ascii_uppercase();
+    if kind.is_empty() || !kind.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-') {
+        bail!("invalid rule type: {rule}");
+    }
+    let min_parts = if kind == "MATCH" { 2 } else { 3 };
+    if parts.len() < min_parts || parts.iter().any(|part| part.is_empty()) {
+        bail!("invalid rule: {rule}");
+    }
+
+    let mut normalized = String::from(kind);
+    for part in &parts[1..] {
+        normalized.push(',');
+        normalized.push_str(part);
+    }
+    Ok(normalized)
+}
+
+/// 规则的目标，位于末尾的 `no-resolve` 等选项之前
+fn rule_target(rule: &str) -> Option<&str> {
+    rule.rsplit(',')
+        .map(str::trim)
+        .find(|part| !matches!(*part, "no-resolve" | "src"))
+}
+
+/// 配置中可作为规则目标的名称：节点、代理组与内置策略
+fn rule_targets(config: &Mapping) -> HashSet<&str> {
+    ["proxies", "proxy-groups"]
+        .iter()
+        .filter_map(|key| config.get(*key).and_then(Value::as_sequence))
+        .flatten()
+        .filter_map(|item| item.get("name").and_then(Value::as_str))
+        .chain(BUILTIN_TARGETS)
+        .collect()
+}
+
+fn find_proxy<'a>(proxies: &'a Sequence, name: &str) -> Option<&'a Mapping> {
+    proxies
+        .iter()
+        .filter_map(Value::as_mapping)
+        .find(|proxy| proxy.get("name").and_then(Value::as_str) == Some(name))
+}
+
+/// 展开代理链：入口节点保持不变，之后每一跳复制对应节点并通过 `dialer-proxy` 指向上一跳，
+/// 最后一跳使用代理链的名称
+fn build_chain(proxies: &Sequence, chain: &ProxyChain) -> Option<Vec<Value>> {
+    let (entry, rest) = chain.nodes.split_first()?;
+    find_proxy(proxies, entry)?;
+    let mut previous = entry.clone();
+    let mut hops = Vec::new();
+    for (index, node) in rest.iter().enumerate() {
+        let mut proxy = find_proxy(proxies, node)?.clone();
+        let name: String = if index + 1 == rest.len() {
+            chain.name.clone()
+        } else {
+            format!("{}/{node}", chain.name).into()
+        };
+        proxy.insert("name".into(), name.as_str().into());
+        proxy.insert("dialer-proxy".into(), previous.as_str().into());
+        hops.push(Value::Mapping(proxy));
+        previous = name;
+    }
+    (!hops.is_empty()).then_some(hops)
+}
+
+/// 将代理链加入节点列表，并添加到包含其出口节点的 select 组中
+fn use_proxy_chains(mut config: Mapping, chains: &[ProxyChain]) -> Mapping {
+    let mut proxies = config
+        .get("proxies")
+        .and_then(Value::as_sequence)
+        .cloned()
+        .unwrap_or_default();
+    let mut applied = Vec::new();
+    for chain in chains {
+        match build_chain(&proxies, chain) {
+            Some(hops) => {
+                proxies.extend(hops);
+                applied.push(chain);
+            }
+            None => logging!(warn, Type::Config, "[代理链] {} 中的节点不存在，已跳过", chain.name),
+        }
+    }
+    config.insert("proxies".into(), Value::Sequence(proxies));
+
+    if let Some(Value::Sequence(groups)) = config.get_mut("proxy-groups") {
+        for group in groups.iter_mut().filter_map(Value::as_mapping_mut) {
+            if group.get("type").and_then(Value::as_str) != Some("select") {
+                continue;
+            }
+            let Some(Value::Sequence(members)) = group.get_mut("proxies") else {
+                continue;
+            };
+            for chain in &applied {
+                let exit = chain.nodes.last().map(String::as_str);
+                if members.iter().any(|m| m.as_str() == exit)
+                    && !members.iter().any(|m| m.as_str() == Some(chain.name.as_str()))
+                {
+                    members.push(chain.name.as_str().into());
+                }
+            }
+        }
+    }
+    config
+}
+
+/// 将管理的规则插入到配置规则的最前面，并合并管理的 rule-providers 与代理链
+pub fn use_managed_rules(mut config: Mapping, managed: &ManagedRules) -> Mapping {
+    if !managed.chains.is_empty() {
+        config = use_proxy_chains(config, &managed.chains);
+    }
+    if !managed.providers.is_empty() {
+        let mut providers = config
+            .get("rule-providers")
+            .and_then(Value::as_mapping)
+            .cloned()
+            .unwrap_or_default();
+        for (name, provider) in &managed.providers {
+            providers.insert(name.clone(), provider.clone());
+        }
+        config.insert("rule-providers".into(), Value::Mapping(providers));
+    }
+
+    let app_rules: Vec<String> = managed.apps.iter().filter_map(|app| app.to_rule().ok()).collect();
+    if managed.rules.is_empty() && app_rules.is_empty() {
+        return config;
+    }
+    let targets = rule_targets(&config);
+    let mut merged: Sequence = managed
+        .rules
+        .iter()
+        .chain(&app_rules)
+        .filter(|rule| {
+            let found = rule_target(rule).is_some_and(|target| targets.contains(target));
+            if !found {
+                logging!(
+                    warn,
+                    Type::Config,
+                    "[规则管理] 规则的目标在当前订阅中不存在，已跳过: {rule}"
+                );
+            }
+            found
+        })
+        .map(|rule| Value::from(rule.as_str()))
+        .collect();
+    if let Some(Value::Sequence(origin)) = config.get("rules") {
+        merged.extend(origin.iter().cloned());
+    }
+    config.insert("rules".into(), Value::Sequence(merged));
+    config
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_rule() {
+        assert_eq!(
+            normalize_rule("domain-suffix, example.com ,PROXY").ok().as_deref(),
+            Some("DOMAIN-SUFFIX,example.com,PROXY")
+        );
+        assert_eq!(
+            normalize_rule("IP-CIDR,10.0.0.0/8,DIRECT,no-resolve").ok().as_deref(),
+            Some("IP-CIDR,10.0.0.0/8,DIRECT,no-resolve")
+        );
+        assert_eq!(normalize_rule("match,DIRECT").ok().as_deref(), Some("MATCH,DIRECT"));
+        assert!(normalize_rule("DOMAIN,example.com").is_err());
+        assert!(normalize_rule("").is_err());
+        assert!(normalize_rule("DOMAIN,,PROXY").is_err());
+    }
+
+    #[test]
+    fn test_use_managed_rules() {
+        let config: Mapping =
+            serde_yaml_ng::from_str("proxy-groups:\n  - {name: PROXY, type: select}\nrules:\n  - MATCH,DIRECT")
+                .unwrap_or_default();
+        let managed = ManagedRules {
+            rules: vec![
+                "DOMAIN,example.com,PROXY".into(),
+                "DOMAIN,missing.com,Streaming".into(),
+                "IP-CIDR,10.0.0.0/8,DIRECT,no-resolve".into(),
+            ],
+            ..ManagedRules::default()
+        };
+        let config = use_managed_rules(config, &managed);
+        let rules = config
+            .get("rules")
+            .and_then(Value::as_sequence)
+            .cloned()
+            .unwrap_or_default();
+        assert_eq!(rules.len(), 3);
+        assert_eq!(rules[0].as_str(), Some("DOMAIN,example.com,PROXY"));
+        assert_eq!(rules[1].as_str(), Some("IP-CIDR,10.0.0.0/8,DIRECT,no-resolve"));
+    }
+
+    #[test]
+    fn test_app_route_rule() {
+        let route = |process: &str| AppRoute {
+            process: process.into(),
+            target: "DIRECT".into(),
+        };
+        assert_eq!(
+            route("Telegram").to_rule().unwrap_or_default(),
+            "PROCESS-NAME,Telegram,DIRECT"
+        );
+        assert_eq!(
+            route("C:\\Program Files\\Steam\\steam.exe")
+                .to_rule()
+                .unwrap_or_default(),
+            "PROCESS-PATH,C:\\Program Files\\Steam\\steam.exe,DIRECT"
+        );
+        assert!(route("a,b").to_rule().is_err());
+        assert!(route("").to_rule().is_err());
+    }
+
+    #[test]
+    fn test_use_proxy_chains() {
+        let config: Mapping = serde_yaml_ng::from_str(
+            "proxies:\n  - {name: hk, type: ss}\n  - {name: us, type: trojan}\n  - {name: jp, type: vmess}\n\
+             proxy-groups:\n  - {name: PROXY, type: select, proxies: [hk, us]}\n  - {name: AUTO, type: url-test, proxies: [us]}",
+        )
+        .unwrap_or_default();
+        let chains = [
+            ProxyChain {
+                name: "hk-jp-us".into(),
+                nodes: vec!["hk".into(), "jp".into(), "us".into()],
+            },
+            ProxyChain {
+                name: "broken".into(),
+                nodes: vec!["hk".into(), "missing".into()],
+            },
+        ];
+        let config = use_proxy_chains(config, &chains);
+        let proxies = config
+            .get("proxies")
+            .and_then(Value::as_sequence)
+            .cloned()
+            .unwrap_or_default();
+        assert_eq!(proxies.len(), 5);
+        let dialer_of = |name: &str| {
+            find_proxy(&proxies, name)
+                .and_then(|p| p.get("dialer-proxy"))
+                .and_then(Value::as_str)
+                .map(ToOwned::to_owned)
+        };
+        assert_eq!(dialer_of("hk-jp-us/jp").as_deref(), Some("hk"));
+        assert_eq!(dialer_of("hk-jp-us").as_deref(), Some("hk-jp-us/jp"));
+        assert!(find_proxy(&proxies, "broken").is_none());
+
+        let groups = serde_yaml_ng::to_string(config.get("proxy-groups").unwrap_or(&Value::Null)).unwrap_or_default();
+        assert!(groups.contains("- hk-jp-us"));
+        assert_eq!(groups.matches("hk-jp-us").count(), 1);
+    }
+}
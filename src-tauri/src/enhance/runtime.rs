@@ -0,0 +1,55 @@
+use crate::config::PrfRuntime;
+use serde_yaml_ng::{Mapping, Value};
+
+/// 用当前订阅的运行参数覆盖全局 Clash 设置，需在合并全局设置之后调用
+pub fn use_profile_runtime(mut config: Mapping, runtime: Option<&PrfRuntime>) -> Mapping {
+    let Some(runtime) = runtime else {
+        return config;
+    };
+    if let Some(port) = runtime.mixed_port {
+        config.insert("mixed-port".into(), port.into());
+    }
+    if let Some(level) = &runtime.log_level {
+        config.insert("log-level".into(), level.as_str().into());
+    }
+    if let Some(ipv6) = runtime.ipv6 {
+        config.insert("ipv6".into(), ipv6.into());
+    }
+    if let Some(stack) = &runtime.tun_stack {
+        let mut tun = config
+            .get("tun")
+            .and_then(Value::as_mapping)
+            .cloned()
+            .unwrap_or_default();
+        tun.insert("stack".into(), stack.as_str().into());
+        config.insert("tun".into(), tun.into());
+    }
+    config
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_runtime_overrides_global() {
+        let mut config = Mapping::new();
+        config.insert("mixed-port".into(), 7897.into());
+        config.insert("log-level".into(), "info".into());
+        let mut tun = Mapping::new();
+        tun.insert("enable".into(), true.into());
+        config.insert("tun".into(), tun.into());
+
+        let runtime = PrfRuntime {
+            mixed_port: Some(7890),
+            tun_stack: Some("system".into()),
+            ..Default::default()
+        };
+        let config = use_profile_runtime(config, Some(&runtime));
+        assert_eq!(config.get("mixed-port").and_then(Value::as_u64), Some(7890));
+        assert_eq!(config.get("log-level").and_then(Value::as_str), Some("info"));
+        let tun = config.get("tun").and_then(Value::as_mapping);
+        assert_eq!(tun.and_then(|t| t.get("stack")).and_then(Value::as_str), Some("system"));
+        assert_eq!(tun.and_then(|t| t.get("enable")).and_then(Value::as_bool), Some(true));
+    }
+}
@@ -1,19 +1,81 @@
 use super::use_lowercase;
+use crate::sandbox::{self, LimitExceeded, Limits};
 use anyhow::{Error, Result};
 use boa_engine::{Context, JsString, JsValue, Source, native_function::NativeFunction};
 use clash_verge_logging::{Type, logging_error};
 use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
 use serde_yaml_ng::Mapping;
 use smartstring::alias::String;
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 const MAX_OUTPUTS: usize = 1000;
 const MAX_OUTPUT_SIZE: usize = 1024 * 1024; // 1MB
 const MAX_JSON_SIZE: usize = 10 * 1024 * 1024; // 10MB
+const MAX_SCRIPT_SIZE: usize = 1024 * 1024; // 1MB
+
+// 虚拟机限制：单个循环的迭代次数、调用深度与栈大小
+const MAX_LOOP_ITERATIONS: u64 = 1_000_000;
+const MAX_RECURSION_DEPTH: usize = 256;
+const MAX_STACK_SIZE: usize = 64 * 1024;
+/// 执行脚本的进程的时间与常驻内存上限，超出后进程被结束，避免阻塞配置激活
+const SCRIPT_TIMEOUT: Duration = Duration::from_secs(5);
+const MAX_MEMORY: u64 = 256 * 1024 * 1024;
+
+type ScriptResult = Result<(Mapping, Vec<(String, String)>)>;
+
+#[derive(Serialize, Deserialize)]
+struct ScriptTask {
+    script: String,
+    config: Mapping,
+    name: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ScriptOutput {
+    config: Mapping,
+    logs: Vec<(String, String)>,
+}
+
+/// 在隔离进程中执行脚本，超时或超出内存上限时返回错误
+pub fn use_script(script: String, config: &Mapping, name: &String) -> ScriptResult {
+    if script.len() > MAX_SCRIPT_SIZE {
+        anyhow::bail!("Script size exceeds maximum allowed size");
+    }
 
-// TODO 使用引用改进上下相关处理，避免不必要 Clone
-pub fn use_script(script: String, config: &Mapping, name: &String) -> Result<(Mapping, Vec<(String, String)>)> {
+    let task = ScriptTask {
+        script,
+        config: config.clone(),
+        name: name.clone(),
+    };
+    let limits = Limits {
+        timeout: SCRIPT_TIMEOUT,
+        memory: MAX_MEMORY,
+    };
+    let output =
+        sandbox::run(sandbox::SCRIPT_TASK, serde_yaml_ng::to_string(&task)?, limits).map_err(|err| match err
+            .downcast_ref::<LimitExceeded>(
+        ) {
+            Some(exceeded) => anyhow::anyhow!("Script {exceeded}"),
+            None => err,
+        })?;
+    let output: ScriptOutput = serde_yaml_ng::from_str(&output)?;
+    Ok((output.config, output.logs))
+}
+
+/// 隔离进程中的入口：读取 [`ScriptTask`]，输出 [`ScriptOutput`]
+pub fn serve_script(input: &str) -> Result<std::string::String> {
+    let task: ScriptTask = serde_yaml_ng::from_str(input)?;
+    let (config, logs) = run_script(&task.script, &task.config, &task.name)?;
+    Ok(serde_yaml_ng::to_string(&ScriptOutput { config, logs })?)
+}
+
+fn run_script(script: &str, config: &Mapping, name: &str) -> ScriptResult {
     let mut context = Context::default();
+    let limits = context.runtime_limits_mut();
+    limits.set_loop_iteration_limit(MAX_LOOP_ITERATIONS);
+    limits.set_recursion_limit(MAX_RECURSION_DEPTH);
+    limits.set_stack_size_limit(MAX_STACK_SIZE);
 
     let outputs = Arc::new(Mutex::new(vec![]));
     let total_size = Arc::new(Mutex::new(0usize));
@@ -68,7 +130,13 @@ pub fn use_script(script: String, config: &Mapping, name: &String) -> Result<(Ma
         debug(data){__verge_log__("debug",JSON.stringify(data, null, 2))},
         warn(data){__verge_log__("warn",JSON.stringify(data, null, 2))},
         table(data){__verge_log__("table",JSON.stringify(data, null, 2))},
-      });"#,
+      });
+      // 移除所有从字符串构造代码的入口，包括通过各类函数原型的 constructor 取得的构造函数
+      delete globalThis.eval;
+      for (const fn of [function () {}, function* () {}, async function () {}, async function* () {}]) {
+        Object.defineProperty(Object.getPrototypeOf(fn), "constructor", { value: undefined });
+      }
+      delete globalThis.Function;"#,
     ));
 
     let config = use_lowercase(config);
@@ -92,35 +160,37 @@ pub fn use_script(script: String, config: &Mapping, name: &String) -> Result<(Ma
       }}"
     );
 
-    if let Ok(result) = context.eval(Source::from_bytes(code.as_str())) {
-        if !result.is_string() {
-            anyhow::bail!("main function should return object");
-        }
-        let result = result
-            .to_string(&mut context)
-            .map_err(|e| anyhow::anyhow!("Failed to convert JS result to string: {}", e))?;
-        let result = result
-            .to_std_string()
-            .map_err(|_| anyhow::anyhow!("Failed to convert JS string to std string"))?;
-
-        if result.len() > MAX_JSON_SIZE {
-            anyhow::bail!("Script result exceeds maximum allowed size");
-        }
-
-        let res: Result<Mapping, Error> = parse_json_safely(&result);
+    match context.eval(Source::from_bytes(code.as_str())) {
+        Ok(result) => {
+            if !result.is_string() {
+                anyhow::bail!("main function should return object");
+            }
+            let result = result
+                .to_string(&mut context)
+                .map_err(|e| anyhow::anyhow!("Failed to convert JS result to string: {}", e))?;
+            let result = result
+                .to_std_string()
+                .map_err(|_| anyhow::anyhow!("Failed to convert JS string to std string"))?;
+
+            if result.len() > MAX_JSON_SIZE {
+                anyhow::bail!("Script result exceeds maximum allowed size");
+            }
 
-        match res {
-            Ok(config) => Ok((use_lowercase(&config), outputs.lock().to_vec())),
-            Err(err) => {
-                outputs
-                    .lock()
-                    .push(("exception".into(), "Script execution failed".into()));
-                logging_error!(Type::Config, "Script execution error: {}. Script name: {}", err, name);
-                Ok((config, outputs.lock().to_vec()))
+            let res: Result<Mapping, Error> = parse_json_safely(&result);
+
+            match res {
+                Ok(config) => Ok((use_lowercase(&config), outputs.lock().to_vec())),
+                Err(err) => {
+                    outputs
+                        .lock()
+                        .push(("exception".into(), "Script execution failed".into()));
+                    logging_error!(Type::Config, "Script execution error: {}. Script name: {}", err, name);
+                    Ok((config, outputs.lock().to_vec()))
+                }
             }
         }
-    } else {
-        anyhow::bail!("main function should return object");
+        // 超出运行限制的错误无法被脚本捕获，会在这里返回
+        Err(err) => anyhow::bail!("Script execution failed: {err}"),
     }
 }
 
@@ -247,3 +317,30 @@ fn test_memory_limits() {
     // 应该失败或被限制
     assert!(result.is_ok()); // 会被限制但不会 panic
 }
+
+#[test]
+fn test_runaway_script() {
+    let config = Mapping::new();
+    let endless = "function main(config) { while (true) {} }";
+    assert!(use_script(endless.into(), &config, &String::from("")).is_err());
+
+    let recursive = "function f() { return f(); } function main(config) { return f(); }";
+    assert!(use_script(recursive.into(), &config, &String::from("")).is_err());
+
+    let sandboxed = "function main(config) { config.eval = typeof eval; return config; }";
+    let result = use_script(sandboxed.into(), &config, &String::from(""));
+    assert!(result.is_ok_and(|(config, _)| config.get("eval").and_then(|v| v.as_str()) == Some("undefined")));
+
+    let constructors = "function main(config) {
+        config.function = typeof Function;
+        config.indirect = typeof (function () {}).constructor;
+        config.async = typeof (async function () {}).constructor;
+        return config;
+    }";
+    let result = use_script(constructors.into(), &config, &String::from(""));
+    assert!(result.is_ok_and(|(config, _)| {
+        ["function", "indirect", "async"]
+            .iter()
+            .all(|key| config.get(*key).and_then(|v| v.as_str()) == Some("undefined"))
+    }));
+}
@@ -0,0 +1,203 @@
+//! 数据目录迁移
+//!
+//! 复制前先写入未保存的配置并停止内核，避免复制到内核正在写入的缓存与日志。
+//! 完整复制到新目录后逐个文件核对 SHA-256，全部一致后才在默认目录写入迁移记录
+//! [`dirs::RELOCATION_FILE`]；任一步失败都会删除已复制的内容并重新启动内核，原目录保持不变。
+//! 旧数据在下次启动时清理。
+
+use crate::{
+    config::ConfigPersistence,
+    core::CoreManager,
+    process::AsyncHandler,
+    utils::{dirs, instance},
+};
+use anyhow::{Context as _, Result, bail};
+use clash_verge_logging::{Type, logging};
+use sha2::{Digest as _, Sha256};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// 记录待清理的旧目录
+const CLEANUP_FILE: &str = "relocation-cleanup";
+
+/// 递归复制目录，返回每个文件的相对路径
+fn copy_dir(from: &Path, to: &Path, skip: &[&str]) -> Result<Vec<PathBuf>> {
+    let mut copied = Vec::new();
+    let mut stack = vec![PathBuf::new()];
+    while let Some(relative) = stack.pop() {
+        fs::create_dir_all(to.join(&relative))?;
+        for entry in fs::read_dir(from.join(&relative))? {
+            let entry = entry?;
+            let name = entry.file_name();
+            if relative.as_os_str().is_empty() && skip.iter().any(|s| name == *s) {
+                continue;
+            }
+            let path = relative.join(&name);
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                stack.push(path);
+            } else if file_type.is_file() {
+                fs::copy(entry.path(), to.join(&path)).with_context(|| format!("failed to copy {}", path.display()))?;
+                copied.push(path);
+            }
+        }
+    }
+    Ok(copied)
+}
+
+fn file_digest(path: &Path) -> Result<Vec<u8>> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut fs::File::open(path)?, &mut hasher)?;
+    Ok(hasher.finalize().to_vec())
+}
+
+fn verify_copy(source: &Path, target: &Path, copied: &[PathBuf]) -> Result<()> {
+    for path in copied {
+        if file_digest(&source.join(path))? != file_digest(&target.join(path))? {
+            bail!("checksum mismatch after copying {}", path.display());
+        }
+    }
+    Ok(())
+}
+
+fn is_empty_dir(path: &Path) -> bool {
+    fs::read_dir(path).is_ok_and(|mut entries| entries.next().is_none())
+}
+
+fn migrate_blocking(current: &Path, target: &Path, default_home: &Path) -> Result<()> {
+    let existed = target.exists();
+    if existed && !is_empty_dir(target) {
+        bail!("target directory is not empty: {}", target.display());
+    }
+    if target.starts_with(current) || current.starts_with(target) {
+        bail!("target directory must not overlap the current data directory");
+    }
+
+    let skip = [dirs::RELOCATION_FILE, CLEANUP_FILE];
+    let result = copy_dir(current, target, &skip).and_then(|copied| verify_copy(current, target, &copied));
+    if let Err(err) = result {
+        // 回滚：删除已复制的内容，保留用户事先创建的空目录
+        let _ = fs::remove_dir_all(target);
+        if existed {
+            let _ = fs::create_dir_all(target);
+        }
+        return Err(err);
+    }
+
+    fs::create_dir_all(default_home)?;
+    fs::write(default_home.join(CLEANUP_FILE), current.to_string_lossy().as_bytes())?;
+    fs::write(
+        default_home.join(dirs::RELOCATION_FILE),
+        target.to_string_lossy().as_bytes(),
+    )?;
+    Ok(())
+}
+
+/// 将数据目录迁移到 `new_path`，需重启后生效
+pub async fn migrate_app_home(new_path: &Path) -> Result<()> {
+    if dirs::is_portable() || instance::data_dir().is_some() {
+        bail!("the data directory is fixed by portable mode or --data-dir");
+    }
+    if !new_path.is_absolute() {
+        bail!("target directory must be an absolute path");
+    }
+    let current = dirs::app_home_dir()?;
+    let default_home = dirs::default_app_home_dir()?;
+    let target = new_path.to_path_buf();
+    logging!(
+        info,
+        Type::File,
+        "Migrating app data from {} to {}",
+        current.display(),
+        target.display()
+    );
+    ConfigPersistence::global().flush().await?;
+    CoreManager::global().stop_core().await?;
+    let result = async {
+        AsyncHandler::spawn_blocking(move || migrate_blocking(&current, &target, &default_home)).await??;
+        <Result<()>>::Ok(())
+    }
+    .await;
+    if result.is_err() {
+        // 迁移失败时继续使用原目录
+        CoreManager::global().start_core().await?;
+    }
+    result
+}
+
+/// 启动时删除上一次迁移留下的旧数据，需在读取配置之前调用
+pub fn cleanup_previous_app_home() {
+    if dirs::is_portable() || instance::data_dir().is_some() {
+        return;
+    }
+    let (Ok(default_home), Ok(current)) = (dirs::default_app_home_dir(), dirs::app_home_dir()) else {
+        return;
+    };
+    let marker = default_home.join(CLEANUP_FILE);
+    let Ok(previous) = fs::read_to_string(&marker) else {
+        return;
+    };
+    let previous = PathBuf::from(previous.trim());
+    if previous == current {
+        return;
+    }
+
+    let result = if previous == default_home {
+        // 默认目录中保留迁移记录
+        fs::read_dir(&default_home).map(|entries| {
+            for entry in entries.flatten() {
+                if entry.file_name() == dirs::RELOCATION_FILE {
+                    continue;
+                }
+                let path = entry.path();
+                let _ = if path.is_dir() {
+                    fs::remove_dir_all(&path)
+                } else {
+                    fs::remove_file(&path)
+                };
+            }
+        })
+    } else {
+        fs::remove_dir_all(&previous)
+    };
+    match result {
+        Ok(()) => logging!(info, Type::File, "Removed old app data in {}", previous.display()),
+        Err(err) => logging!(warn, Type::File, "Failed to remove old app data: {err}"),
+    }
+    let _ = fs::remove_file(marker);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrate_and_rollback() {
+        let root = std::env::temp_dir().join(format!("clash-verge-home-test-{}", std::process::id()));
+        let current = root.join("current");
+        let default_home = root.join("default");
+        let _ = fs::create_dir_all(current.join("profiles"));
+        let _ = fs::write(current.join("verge.yaml"), "a: 1");
+        let _ = fs::write(current.join("profiles/p.yaml"), "b: 2");
+
+        let target = root.join("target");
+        assert!(migrate_blocking(&current, &target, &default_home).is_ok());
+        assert_eq!(
+            fs::read_to_string(target.join("profiles/p.yaml")).ok().as_deref(),
+            Some("b: 2")
+        );
+        assert_eq!(
+            fs::read_to_string(default_home.join(dirs::RELOCATION_FILE)).ok(),
+            Some(target.to_string_lossy().into_owned())
+        );
+
+        // 目标非空或与当前目录重叠时拒绝
+        assert!(migrate_blocking(&current, &target, &default_home).is_err());
+        assert!(migrate_blocking(&current, &current.join("nested"), &default_home).is_err());
+        assert!(current.join("verge.yaml").exists());
+
+        let _ = fs::remove_dir_all(root);
+    }
+}
@@ -0,0 +1,221 @@
+//! 按应用分流
+//!
+//! 列出已安装的应用：macOS 扫描 `.app` 包，Windows 读取注册表中的卸载信息，
+//! Linux 解析 `.desktop` 文件。分流设置保存到管理的覆盖层。
+
+use super::rules::{RULES_LOCK, apply_managed};
+use crate::{
+    enhance::rules::{AppRoute, ManagedRules},
+    process::AsyncHandler,
+};
+use anyhow::Result;
+use clash_verge_logging::{Type, logging};
+use serde::Serialize;
+use smartstring::alias::String;
+#[cfg(not(target_os = "windows"))]
+use std::path::Path;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct InstalledApp {
+    pub name: String,
+    /// 用于 `PROCESS-NAME` 规则的进程名
+    pub process: String,
+    /// 可执行文件路径，用于 `PROCESS-PATH` 规则
+    pub path: Option<String>,
+}
+
+/// 从 `.desktop` 文件中读取名称与可执行文件，忽略隐藏项与非应用项
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_desktop_entry(content: &str) -> Option<InstalledApp> {
+    let mut name = None;
+    let mut exec = None;
+    let mut in_entry = false;
+    for line in content.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_entry = line == "[Desktop Entry]";
+            continue;
+        }
+        if !in_entry {
+            continue;
+        }
+        match line.split_once('=') {
+            Some(("Name", value)) if name.is_none() => name = Some(value),
+            Some(("Exec", value)) => exec = Some(value),
+            Some(("NoDisplay" | "Hidden", "true")) => return None,
+            Some(("Type", value)) if value != "Application" => return None,
+            _ => {}
+        }
+    }
+    // Exec 可能带有 env 前缀与 %U 等参数
+    let program = exec?
+        .split_whitespace()
+        .find(|part| !part.starts_with("env") && !part.contains('='))?;
+    let process = program.rsplit('/').next().filter(|p| !p.is_empty())?;
+    Some(InstalledApp {
+        name: name?.into(),
+        process: process.into(),
+        path: program.starts_with('/').then(|| program.into()),
+    })
+}
+
+/// 从 XML 格式的 Info.plist 中读取 `CFBundleExecutable`
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+fn plist_executable(content: &str) -> Option<&str> {
+    let rest = &content[content.find("<key>CFBundleExecutable</key>")?..];
+    let start = rest.find("<string>")? + "<string>".len();
+    let end = rest.find("</string>")?;
+    (start < end).then(|| rest[start..end].trim())
+}
+
+#[cfg(target_os = "linux")]
+fn scan_apps() -> Vec<InstalledApp> {
+    let mut dirs = vec![
+        std::path::PathBuf::from("/usr/share/applications"),
+        std::path::PathBuf::from("/usr/local/share/applications"),
+        std::path::PathBuf::from("/var/lib/flatpak/exports/share/applications"),
+    ];
+    if let Some(home) = std::env::var_os("HOME") {
+        dirs.push(Path::new(&home).join(".local/share/applications"));
+    }
+    dirs.iter()
+        .filter_map(|dir| std::fs::read_dir(dir).ok())
+        .flatten()
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "desktop"))
+        .filter_map(|path| std::fs::read_to_string(path).ok())
+        .filter_map(|content| parse_desktop_entry(&content))
+        .collect()
+}
+
+#[cfg(target_os = "macos")]
+fn scan_apps() -> Vec<InstalledApp> {
+    let mut dirs = vec![
+        std::path::PathBuf::from("/Applications"),
+        std::path::PathBuf::from("/System/Applications"),
+    ];
+    if let Some(home) = std::env::var_os("HOME") {
+        dirs.push(Path::new(&home).join("Applications"));
+    }
+    dirs.iter()
+        .filter_map(|dir| std::fs::read_dir(dir).ok())
+        .flatten()
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "app"))
+        .filter_map(|bundle| {
+            let name = bundle.file_stem()?.to_string_lossy().into_owned();
+            let plist = std::fs::read_to_string(bundle.join("Contents/Info.plist")).unwrap_or_default();
+            let executable = plist_executable(&plist).unwrap_or(&name).to_owned();
+            let path = bundle.join("Contents/MacOS").join(&executable);
+            Some(InstalledApp {
+                name: name.into(),
+                process: executable.into(),
+                path: path.exists().then(|| path.to_string_lossy().into_owned().into()),
+            })
+        })
+        .collect()
+}
+
+#[cfg(target_os = "windows")]
+fn scan_apps() -> Vec<InstalledApp> {
+    use winreg::{
+        RegKey,
+        enums::{HKEY_CURRENT_USER, HKEY_LOCAL_MACHINE},
+    };
+
+    const UNINSTALL_KEYS: [&str; 2] = [
+        r"SOFTWARE\Microsoft\Windows\CurrentVersion\Uninstall",
+        r"SOFTWARE\WOW6432Node\Microsoft\Windows\CurrentVersion\Uninstall",
+    ];
+
+    let mut apps = Vec::new();
+    for root in [HKEY_LOCAL_MACHINE, HKEY_CURRENT_USER] {
+        for key in UNINSTALL_KEYS {
+            let Ok(uninstall) = RegKey::predef(root).open_subkey(key) else {
+                continue;
+            };
+            for sub in uninstall.enum_keys().filter_map(std::result::Result::ok) {
+                let Ok(app) = uninstall.open_subkey(&sub) else {
+                    continue;
+                };
+                let Ok(name) = app.get_value::<std::string::String, _>("DisplayName") else {
+                    continue;
+                };
+                // DisplayIcon 通常是 `C:\path\app.exe,0`
+                let Some(exe) = app
+                    .get_value::<std::string::String, _>("DisplayIcon")
+                    .ok()
+                    .map(|icon| icon.split(',').next().unwrap_or_default().trim_matches('"').to_owned())
+                    .filter(|icon| icon.to_ascii_lowercase().ends_with(".exe"))
+                else {
+                    continue;
+                };
+                let process = exe.rsplit('\\').next().unwrap_or(&exe).to_owned();
+                apps.push(InstalledApp {
+                    name: name.into(),
+                    process: process.into(),
+                    path: Some(exe.into()),
+                });
+            }
+        }
+    }
+    apps
+}
+
+/// 列出已安装的应用，按名称排序并去重
+pub async fn list_installed_apps() -> Result<Vec<InstalledApp>> {
+    let mut apps = AsyncHandler::spawn_blocking(scan_apps).await?;
+    apps.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
+    apps.dedup_by(|a, b| a.name == b.name && a.process == b.process);
+    Ok(apps)
+}
+
+/// 获取按应用分流的设置
+pub async fn get_app_routing() -> Result<Vec<AppRoute>> {
+    Ok(ManagedRules::load().await?.apps)
+}
+
+/// 替换按应用分流的设置
+pub async fn set_app_routing(routes: Vec<AppRoute>) -> Result<Vec<AppRoute>> {
+    for route in &routes {
+        route.to_rule()?;
+    }
+    let _guard = RULES_LOCK.lock().await;
+    let previous = ManagedRules::load().await?;
+    let mut managed = previous.clone();
+    managed.apps = routes;
+    logging!(info, Type::Config, "[应用分流] 更新 {} 条应用规则", managed.apps.len());
+    Ok(apply_managed(&previous, managed).await?.apps)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_desktop_entry() {
+        let entry = "[Desktop Entry]\nType=Application\nName=Firefox\nName[de]=Firefox\n\
+                     Exec=env MOZ_ENABLE_WAYLAND=1 /usr/lib/firefox/firefox %u\n\n\
+                     [Desktop Action new-window]\nExec=/usr/lib/firefox/firefox --new-window\n";
+        let app = parse_desktop_entry(entry);
+        assert_eq!(
+            app.map(|a| (a.name, a.process, a.path)),
+            Some((
+                "Firefox".into(),
+                "firefox".into(),
+                Some("/usr/lib/firefox/firefox".into())
+            ))
+        );
+        assert!(parse_desktop_entry("[Desktop Entry]\nName=Hidden\nExec=foo\nNoDisplay=true").is_none());
+        assert_eq!(
+            parse_desktop_entry("[Desktop Entry]\nName=Term\nExec=kitty").map(|a| (a.process, a.path)),
+            Some(("kitty".into(), None))
+        );
+    }
+
+    #[test]
+    fn test_plist_executable() {
+        let plist = "<dict>\n\t<key>CFBundleExecutable</key>\n\t<string>Telegram</string>\n</dict>";
+        assert_eq!(plist_executable(plist), Some("Telegram"));
+        assert_eq!(plist_executable("<dict></dict>"), None);
+    }
+}
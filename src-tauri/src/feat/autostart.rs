@@ -0,0 +1,106 @@
+//! 开机自启
+//!
+//! 统一管理"登录时启动"、"启动时最小化到托盘"与"延迟启动"三项设置。开机自启的启动项带有
+//! `--autostart` 参数，据此与手动启动区分，只有开机自启后的首次启动才等待 `auto_launch_delay` 秒再启动内核（托盘不等待），
+//! 避免网络尚未就绪时订阅更新与系统代理设置失败。旧版本创建的启动项（Windows 快捷方式或注册表、
+//! macOS LaunchAgent、Linux desktop 文件）不带该参数，首次启动时重新创建一次。
+
+use super::patch_verge;
+use crate::{
+    config::{Config, IVerge},
+    core::{handle::Handle, sysopt::Sysopt},
+    utils::{autostart::is_autostarted, dirs},
+};
+use anyhow::{Result, bail};
+use clash_verge_logging::{Type, logging};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tauri_plugin_autostart::ManagerExt as _;
+
+/// 存在时说明启动项已按新格式重新创建
+const MIGRATED_FILE: &str = ".autostart-migrated";
+const MAX_DELAY_SECS: u64 = 600;
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AutostartConfig {
+    /// 登录时启动
+    pub enabled: bool,
+    /// 启动时不显示主窗口，只显示托盘
+    pub silent: bool,
+    /// 开机自启后延迟启动内核的秒数
+    pub delay_secs: u64,
+}
+
+pub async fn get_autostart_config() -> AutostartConfig {
+    let verge = Config::verge().await.latest_arc();
+    AutostartConfig {
+        // 以系统中实际的启动项为准，用户可能在系统设置中关闭
+        enabled: Sysopt::global()
+            .get_launch_status()
+            .unwrap_or_else(|_| verge.enable_auto_launch.unwrap_or_default()),
+        silent: verge.enable_silent_start.unwrap_or_default(),
+        delay_secs: verge.auto_launch_delay.unwrap_or_default(),
+    }
+}
+
+pub async fn set_autostart_config(config: AutostartConfig) -> Result<()> {
+    if config.delay_secs > MAX_DELAY_SECS {
+        bail!("the start delay must be at most {MAX_DELAY_SECS} seconds");
+    }
+    let patch = IVerge {
+        enable_auto_launch: Some(config.enabled),
+        enable_silent_start: Some(config.silent),
+        auto_launch_delay: Some(config.delay_secs),
+        ..IVerge::default()
+    };
+    patch_verge(&patch, false).await
+}
+
+/// 开机自启时按设置等待，手动启动时直接返回
+pub async fn wait_autostart_delay() {
+    if !is_autostarted() {
+        return;
+    }
+    let delay = Config::verge()
+        .await
+        .latest_arc()
+        .auto_launch_delay
+        .unwrap_or_default()
+        .min(MAX_DELAY_SECS);
+    if delay > 0 {
+        logging!(
+            info,
+            Type::Setup,
+            "Started at login, waiting {delay}s before starting the core"
+        );
+        tokio::time::sleep(Duration::from_secs(delay)).await;
+    }
+}
+
+/// 删除旧的启动项并按当前设置重新创建，只执行一次
+pub async fn migrate_autostart_entries() -> Result<()> {
+    let marker = dirs::app_home_dir()?.join(MIGRATED_FILE);
+    if marker.exists() {
+        return Ok(());
+    }
+    if Config::verge()
+        .await
+        .latest_arc()
+        .enable_auto_launch
+        .unwrap_or_default()
+    {
+        #[cfg(target_os = "windows")]
+        crate::utils::autostart::remove_shortcut().await?;
+        // Windows 上快捷方式创建失败时会回退到注册表启动项，一并清除；不存在时忽略错误
+        let _ = Handle::app_handle().autolaunch().disable();
+        Sysopt::global().update_launch().await?;
+        logging!(
+            info,
+            Type::Setup,
+            "Recreated the autostart entry with the autostart flag"
+        );
+    }
+    tokio::fs::write(&marker, "").await?;
+    Ok(())
+}
@@ -1,13 +1,14 @@
 use crate::{
-    config::{Config, IVerge},
+    config::{Config, ILanSyncIdentity, ILanSyncPeer, IVerge},
     core::backup,
     process::AsyncHandler,
     utils::{
+        crypto,
         dirs::{PathBufExec as _, app_home_dir, local_backup_dir, verge_path},
         help,
     },
 };
-use anyhow::{Result, anyhow};
+use anyhow::{Result, anyhow, bail};
 use chrono::Utc;
 use clash_verge_logging::{Type, logging};
 use reqwest_dav::list_cmd::ListFile;
@@ -24,18 +25,63 @@ pub struct LocalBackupFile {
     pub content_length: u64,
 }
 
-/// Load restored verge.yaml from disk, merge back WebDAV creds, save, and sync memory.
-async fn finalize_restored_verge_config(
+#[derive(Debug, Serialize)]
+pub struct RemoteBackupFile {
+    pub id: String,
+    pub last_modified: String,
+    pub content_length: u64,
+    pub encrypted: bool,
+}
+
+/// 加密备份在原文件名后追加的后缀
+const ENCRYPTED_SUFFIX: &str = ".enc";
+
+/// Credentials kept on this device across a restore; they are never written into backups.
+struct PreservedSecrets {
     webdav_url: Option<String>,
     webdav_username: Option<String>,
     webdav_password: Option<String>,
-) -> Result<()> {
+    backup_password: Option<String>,
+    lan_sync_device_id: Option<String>,
+    lan_sync_peers: Option<Vec<ILanSyncPeer>>,
+    lan_sync_identity: Option<ILanSyncIdentity>,
+    telegram_bot_token: Option<String>,
+}
+
+impl PreservedSecrets {
+    async fn current() -> Self {
+        let verge = Config::verge().await;
+        let verge = verge.latest_arc();
+        Self {
+            webdav_url: verge.webdav_url.clone(),
+            webdav_username: verge.webdav_username.clone(),
+            webdav_password: verge.webdav_password.clone(),
+            backup_password: verge.backup_password.clone(),
+            lan_sync_device_id: verge.lan_sync_device_id.clone(),
+            lan_sync_peers: verge.lan_sync_peers.clone(),
+            lan_sync_identity: verge.lan_sync_identity.clone(),
+            telegram_bot_token: verge.telegram_bot_token.clone(),
+        }
+    }
+
+    fn password(&self) -> Option<String> {
+        self.backup_password.clone().filter(|p| !p.is_empty())
+    }
+}
+
+/// Load restored verge.yaml from disk, merge back WebDAV creds, save, and sync memory.
+async fn finalize_restored_verge_config(secrets: PreservedSecrets) -> Result<()> {
     // Do NOT silently fallback to defaults; a broken/missing verge.yaml means restore failed.
     // Propagate the error so the UI/user can react accordingly.
-    let mut restored = help::read_yaml::<IVerge>(&verge_path()?).await?;
-    restored.webdav_url = webdav_url;
-    restored.webdav_username = webdav_username;
-    restored.webdav_password = webdav_password;
+    let mut restored = crate::core::secrets::resolve_verge(help::read_yaml::<IVerge>(&verge_path()?).await?).await?;
+    restored.webdav_url = secrets.webdav_url;
+    restored.webdav_username = secrets.webdav_username;
+    restored.webdav_password = secrets.webdav_password;
+    restored.backup_password = secrets.backup_password;
+    restored.lan_sync_device_id = secrets.lan_sync_device_id;
+    restored.lan_sync_peers = secrets.lan_sync_peers;
+    restored.lan_sync_identity = secrets.lan_sync_identity;
+    restored.telegram_bot_token = secrets.telegram_bot_token;
     restored.save_file().await?;
 
     let verge_draft = Config::verge().await;
@@ -54,26 +100,47 @@ async fn finalize_restored_verge_config(
 
 /// Create a backup and upload to WebDAV
 pub async fn create_backup_and_upload_webdav() -> Result<()> {
+    backup_now().await.map(|_| ())
+}
+
+/// Create a backup, encrypt it when a backup password is set, and upload it to WebDAV.
+/// Returns the id of the uploaded backup.
+pub async fn backup_now() -> Result<String> {
     let (file_name, temp_file_path) = backup::create_backup().await.map_err(|err| {
         logging!(error, Type::Backup, "Failed to create backup: {err:#?}");
         err
     })?;
 
-    if let Err(err) = backup::WebDavClient::global()
-        .upload(temp_file_path.clone(), file_name)
-        .await
-    {
+    let (file_name, upload_path) = match PreservedSecrets::current().await.password() {
+        Some(password) => {
+            let sealed_name: String = format!("{file_name}{ENCRYPTED_SUFFIX}").into();
+            let sealed_path = temp_file_path.with_file_name(sealed_name.as_str());
+            let data = fs::read(&temp_file_path).await?;
+            let _ = temp_file_path.remove_if_exists().await;
+            let sealed = AsyncHandler::spawn_blocking(move || crypto::seal(&data, &password)).await??;
+            fs::write(&sealed_path, sealed).await?;
+            (sealed_name, sealed_path)
+        }
+        None => (file_name, temp_file_path),
+    };
+
+    let result = backup::WebDavClient::global()
+        .upload(upload_path.clone(), file_name.clone())
+        .await;
+
+    if let Err(err) = upload_path.remove_if_exists().await {
+        logging!(warn, Type::Backup, "Failed to remove temp file: {err:#?}");
+    }
+
+    if let Err(err) = result {
         logging!(error, Type::Backup, "Failed to upload to WebDAV: {err:#?}");
         // 上传失败时重置客户端缓存
         backup::WebDavClient::global().reset();
         return Err(err);
     }
 
-    if let Err(err) = temp_file_path.remove_if_exists().await {
-        logging!(warn, Type::Backup, "Failed to remove temp file: {err:#?}");
-    }
-
-    Ok(())
+    logging!(info, Type::Backup, "WebDAV backup uploaded: {}", file_name);
+    Ok(file_name)
 }
 
 /// List WebDAV backups
@@ -84,6 +151,25 @@ pub async fn list_wevdav_backup() -> Result<Vec<ListFile>> {
     })
 }
 
+/// List WebDAV backups, newest first
+pub async fn list_backups() -> Result<Vec<RemoteBackupFile>> {
+    let mut backups: Vec<RemoteBackupFile> = list_wevdav_backup()
+        .await?
+        .into_iter()
+        .filter_map(|file| {
+            let id = file.href.trim_end_matches('/').rsplit('/').next()?.to_owned();
+            Some(RemoteBackupFile {
+                encrypted: id.ends_with(ENCRYPTED_SUFFIX),
+                id: id.into(),
+                last_modified: file.last_modified.to_rfc3339().into(),
+                content_length: u64::try_from(file.content_length).unwrap_or_default(),
+            })
+        })
+        .collect();
+    backups.sort_by(|a, b| b.last_modified.cmp(&a.last_modified));
+    Ok(backups)
+}
+
 /// Delete WebDAV backup
 pub async fn delete_webdav_backup(filename: String) -> Result<()> {
     backup::WebDavClient::global().delete(filename).await.map_err(|err| {
@@ -94,29 +180,45 @@ pub async fn delete_webdav_backup(filename: String) -> Result<()> {
 
 /// Restore WebDAV backup
 pub async fn restore_webdav_backup(filename: String) -> Result<()> {
-    let verge = Config::verge().await;
-    let verge_data = verge.latest_arc();
-    let webdav_url = verge_data.webdav_url.clone();
-    let webdav_username = verge_data.webdav_username.clone();
-    let webdav_password = verge_data.webdav_password.clone();
+    restore_backup(filename).await
+}
+
+/// Download a WebDAV backup, decrypt it if needed, and restore it into the app home.
+pub async fn restore_backup(id: String) -> Result<()> {
+    if id.is_empty() || id.contains(['/', '\\']) || id.starts_with('.') {
+        bail!("invalid backup id: {id}");
+    }
+    let secrets = PreservedSecrets::current().await;
 
     let backup_storage_path = app_home_dir()
         .map_err(|e| anyhow::anyhow!("Failed to get app home dir: {e}"))?
-        .join(filename.as_str());
+        .join(id.as_str());
     backup::WebDavClient::global()
-        .download(filename, backup_storage_path.clone())
+        .download(id, backup_storage_path.clone())
         .await
         .map_err(|err| {
             logging!(error, Type::Backup, "Failed to download WebDAV backup file: {err:#?}");
             err
         })?;
 
-    // extract zip file
-    let value = backup_storage_path.clone();
-    let file = AsyncHandler::spawn_blocking(move || std::fs::File::open(&value)).await??;
-    let mut zip = zip::ZipArchive::new(file)?;
-    zip.extract(app_home_dir()?)?;
-    let res = finalize_restored_verge_config(webdav_url, webdav_username, webdav_password).await;
+    let res = async {
+        let data = fs::read(&backup_storage_path).await?;
+        if crypto::is_sealed(&data) {
+            let Some(password) = secrets.password() else {
+                bail!("backup is encrypted, set the backup password first");
+            };
+            let plain = AsyncHandler::spawn_blocking(move || crypto::open(&data, &password)).await??;
+            fs::write(&backup_storage_path, plain).await?;
+        }
+
+        // extract zip file
+        let value = backup_storage_path.clone();
+        let file = AsyncHandler::spawn_blocking(move || std::fs::File::open(&value)).await??;
+        let mut zip = zip::ZipArchive::new(file)?;
+        zip.extract(app_home_dir()?)?;
+        finalize_restored_verge_config(secrets).await
+    }
+    .await;
     // Finally remove the temp file (attempt cleanup even if finalize fails)
     let _ = backup_storage_path.remove_if_exists().await;
     res
@@ -239,20 +341,12 @@ pub async fn restore_local_backup(filename: String) -> Result<()> {
         return Err(anyhow!("Backup file not found: {}", filename));
     }
 
-    let (webdav_url, webdav_username, webdav_password) = {
-        let verge = Config::verge().await;
-        let verge = verge.latest_arc();
-        (
-            verge.webdav_url.clone(),
-            verge.webdav_username.clone(),
-            verge.webdav_password.clone(),
-        )
-    };
+    let secrets = PreservedSecrets::current().await;
 
     let file = AsyncHandler::spawn_blocking(move || std::fs::File::open(&target_path)).await??;
     let mut zip = zip::ZipArchive::new(file)?;
     zip.extract(app_home_dir()?)?;
-    finalize_restored_verge_config(webdav_url, webdav_username, webdav_password).await?;
+    finalize_restored_verge_config(secrets).await?;
     Ok(())
 }
 
@@ -275,3 +369,59 @@ pub async fn export_local_backup(filename: String, destination: String) -> Resul
         .map_err(|err| anyhow!("Failed to export backup file: {err:#?}"))?;
     Ok(())
 }
+
+/// Export all local configuration into a password-encrypted bundle at `path`
+pub async fn export_settings(path: String, password: String, include_subscriptions: bool) -> Result<()> {
+    if password.is_empty() {
+        bail!("password is required to export settings");
+    }
+    let data = settings_archive(include_subscriptions).await?;
+    let sealed = AsyncHandler::spawn_blocking(move || crypto::seal(&data, &password)).await??;
+
+    let dest_path = PathBuf::from(path.as_str());
+    if let Some(parent) = dest_path.parent() {
+        fs::create_dir_all(parent).await?;
+    }
+    fs::write(&dest_path, sealed)
+        .await
+        .map_err(|err| anyhow!("Failed to write settings bundle: {err:#?}"))?;
+    logging!(info, Type::Backup, "Settings exported to {}", dest_path.display());
+    Ok(())
+}
+
+/// Import a bundle created by [`export_settings`]; local WebDAV creds and backup password are kept
+pub async fn import_settings(path: String, password: String) -> Result<()> {
+    let data = fs::read(path.as_str())
+        .await
+        .map_err(|err| anyhow!("Failed to read settings bundle: {err:#?}"))?;
+    if !crypto::is_sealed(&data) {
+        bail!("not a settings bundle: {path}");
+    }
+    let plain = AsyncHandler::spawn_blocking(move || crypto::open(&data, &password)).await??;
+    apply_settings_archive(plain).await?;
+    logging!(info, Type::Backup, "Settings imported from {}", path);
+    Ok(())
+}
+
+/// Archive all local configuration in memory
+pub async fn settings_archive(include_subscriptions: bool) -> Result<Vec<u8>> {
+    let (_, temp_file_path) = backup::create_archive(include_subscriptions).await.map_err(|err| {
+        logging!(error, Type::Backup, "Failed to create settings archive: {err:#?}");
+        err
+    })?;
+    let data = fs::read(&temp_file_path).await;
+    let _ = temp_file_path.remove_if_exists().await;
+    Ok(data?)
+}
+
+/// Extract a settings archive into the app home, keeping device-local credentials
+pub async fn apply_settings_archive(data: Vec<u8>) -> Result<()> {
+    let secrets = PreservedSecrets::current().await;
+    AsyncHandler::spawn_blocking(move || {
+        let mut zip = zip::ZipArchive::new(std::io::Cursor::new(data))?;
+        zip.extract(app_home_dir()?)?;
+        Ok::<(), anyhow::Error>(())
+    })
+    .await??;
+    finalize_restored_verge_config(secrets).await
+}
@@ -0,0 +1,88 @@
+//! 代理链
+//!
+//! 用已有节点组成代理链并保存到管理的覆盖层，生成运行配置时展开为 `dialer-proxy` 节点，
+//! 无需手动编辑 YAML 即可组合入口与出口节点。
+
+use super::rules::{RULES_LOCK, apply_managed};
+use crate::{
+    config::Config,
+    enhance::rules::{ManagedRules, ProxyChain},
+};
+use anyhow::{Result, bail};
+use clash_verge_logging::{Type, logging};
+use serde_yaml_ng::Value;
+use smartstring::alias::String;
+
+/// 运行配置中的节点与代理组名称
+async fn runtime_names() -> (Vec<String>, Vec<String>) {
+    let runtime = Config::runtime().await.latest_arc();
+    let names_of = |key: &str| -> Vec<String> {
+        runtime
+            .config
+            .as_ref()
+            .and_then(|config| config.get(key))
+            .and_then(Value::as_sequence)
+            .map(|items| {
+                items
+                    .iter()
+                    .filter_map(|item| item.get("name").and_then(Value::as_str))
+                    .map(Into::into)
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+    (names_of("proxies"), names_of("proxy-groups"))
+}
+
+/// 列出已创建的代理链
+pub async fn list_proxy_chains() -> Result<Vec<ProxyChain>> {
+    Ok(ManagedRules::load().await?.chains)
+}
+
+/// 创建代理链，`nodes` 按流量经过的顺序排列，同名代理链会被替换
+pub async fn create_proxy_chain(name: &str, nodes: Vec<String>) -> Result<Vec<ProxyChain>> {
+    let name = name.trim();
+    if name.is_empty() || name.contains('/') {
+        bail!("invalid proxy chain name: {name}");
+    }
+    if nodes.len() < 2 {
+        bail!("a proxy chain needs at least two nodes");
+    }
+    if nodes.iter().any(|node| node == name) {
+        bail!("a proxy chain cannot contain itself");
+    }
+
+    let _guard = RULES_LOCK.lock().await;
+    let previous = ManagedRules::load().await?;
+    let (proxies, groups) = runtime_names().await;
+    let is_chain = |n: &str| previous.chains.iter().any(|chain| chain.name == n);
+    if !is_chain(name) && (proxies.iter().any(|p| p == name) || groups.iter().any(|g| g == name)) {
+        bail!("name {name} is already used by a proxy or group");
+    }
+    if let Some(missing) = nodes.iter().find(|node| !proxies.contains(node)) {
+        bail!("node {missing} not found; only nodes defined in the profile can be chained");
+    }
+
+    let mut managed = previous.clone();
+    managed.chains.retain(|chain| chain.name != name);
+    let route: Vec<&str> = nodes.iter().map(String::as_str).collect();
+    logging!(info, Type::Config, "[代理链] 创建 {}: {}", name, route.join(" -> "));
+    managed.chains.push(ProxyChain {
+        name: name.into(),
+        nodes,
+    });
+    Ok(apply_managed(&previous, managed).await?.chains)
+}
+
+/// 删除代理链
+pub async fn delete_proxy_chain(name: &str) -> Result<Vec<ProxyChain>> {
+    let _guard = RULES_LOCK.lock().await;
+    let previous = ManagedRules::load().await?;
+    let mut managed = previous.clone();
+    managed.chains.retain(|chain| chain.name != name);
+    if managed.chains.len() == previous.chains.len() {
+        bail!("proxy chain {name} not found");
+    }
+    logging!(info, Type::Config, "[代理链] 删除 {}", name);
+    Ok(apply_managed(&previous, managed).await?.chains)
+}
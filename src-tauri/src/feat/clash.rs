@@ -1,9 +1,14 @@
 use crate::{
-    config::Config,
-    core::{CoreManager, handle, tray},
+    config::{Config, ConfigPersistence},
+    core::{
+        CoreManager,
+        audit::AuditLog,
+        event_bus::{EventBus, StateEvent},
+        handle,
+    },
     feat::clean_async,
     process::AsyncHandler,
-    utils::{self, resolve::reset_resolve_done},
+    utils::{self, crypto, resolve::reset_resolve_done},
 };
 use clash_verge_logging::{Type, logging, logging_error};
 use serde_yaml_ng::{Mapping, Value};
@@ -23,6 +28,20 @@ pub async fn restart_clash_core() {
     }
 }
 
+/// 生成新的外部控制器密钥并重启内核，旧密钥立即失效
+///
+/// 内置的流量、日志订阅在内核重启后按新配置重连，前端收到刷新事件后重新获取
+pub async fn rotate_core_secret() -> anyhow::Result<String> {
+    let mut bytes = [0u8; 32];
+    getrandom::fill(&mut bytes)?;
+    let secret: String = crypto::to_hex(&bytes).into();
+    let mut patch = Mapping::new();
+    patch.insert("secret".into(), secret.as_str().into());
+    super::patch_clash(&patch).await?;
+    logging!(info, Type::Core, "External controller secret rotated");
+    Ok(secret)
+}
+
 /// Restart the application
 pub async fn restart_app() {
     logging!(debug, Type::System, "启动重启应用流程");
@@ -30,6 +49,8 @@ pub async fn restart_app() {
     handle::Handle::global().set_is_exiting();
 
     utils::server::shutdown_embedded_server();
+    // 写入防抖中尚未落盘的修改
+    logging_error!(Type::Config, ConfigPersistence::global().flush().await);
     Config::apply_all_and_save_file().await;
 
     logging!(info, Type::System, "开始异步清理资源");
@@ -68,6 +89,13 @@ fn after_change_clash_mode() {
 
 /// Change Clash mode (rule/global/direct/script)
 pub async fn change_clash_mode(mode: String) {
+    if let Err(err) = set_clash_mode(mode).await {
+        logging!(error, Type::Core, "{err}");
+    }
+}
+
+/// 切换代理模式，内核拒绝时返回错误
+pub async fn set_clash_mode(mode: String) -> anyhow::Result<()> {
     let mut mapping = Mapping::new();
     mapping.insert(Value::from("mode"), Value::from(mode.as_str()));
     // Convert YAML mapping to JSON Value
@@ -75,34 +103,35 @@ pub async fn change_clash_mode(mode: String) {
         "mode": mode
     });
     logging!(debug, Type::Core, "change clash mode to {mode}");
-    match handle::Handle::mihomo().await.patch_base_config(&json_value).await {
-        Ok(_) => {
-            // 更新订阅
-            Config::clash().await.edit_draft(|d| d.patch_config(&mapping));
-
-            // 分离数据获取和异步调用
-            let clash_data = Config::clash().await.data_arc();
-            if clash_data.save_config().await.is_ok() {
-                handle::Handle::refresh_clash();
-                logging_error!(Type::Tray, tray::Tray::global().update_menu().await);
-                logging_error!(
-                    Type::Tray,
-                    tray::Tray::global()
-                        .update_icon(&Config::verge().await.data_arc())
-                        .await
-                );
-            }
-
-            let is_auto_close_connection = Config::verge().await.data_arc().auto_close_connection.unwrap_or(false);
-            if is_auto_close_connection {
-                after_change_clash_mode();
-            }
+    let old_mode = Config::clash().await.latest_arc().0.get("mode").cloned();
+    handle::Handle::mihomo()
+        .await
+        .patch_base_config(&json_value)
+        .await
+        .map_err(|err| anyhow::anyhow!("{err}"))?;
+    // 更新订阅
+    Config::clash().await.edit_draft(|d| d.patch_config(&mapping));
+
+    // 分离数据获取和异步调用
+    let clash_data = Config::clash().await.data_arc();
+    if clash_data.save_config().await.is_ok() {
+        handle::Handle::refresh_clash();
+    }
 
-            // Update Discord activity when clash mode changes
-            crate::cmd::discord::update_discord_activity().await;
-        }
-        Err(err) => logging!(error, Type::Core, "{err}"),
+    let is_auto_close_connection = Config::verge().await.data_arc().auto_close_connection.unwrap_or(false);
+    if is_auto_close_connection {
+        after_change_clash_mode();
     }
+
+    AuditLog::record(
+        "mode",
+        None,
+        old_mode.and_then(|old| serde_json::to_value(old).ok()),
+        Some(mode.as_str().into()),
+    );
+    // 托盘与 Discord 状态随事件更新
+    EventBus::global().publish(StateEvent::ModeChanged { mode });
+    Ok(())
 }
 
 /// Test connection delay to a URL
@@ -124,7 +153,7 @@ pub async fn test_delay(url: String) -> anyhow::Result<u32> {
     let start = Instant::now();
 
     let response = NetworkManager::new()
-        .get_with_interrupt(&url, proxy_type, Some(10), user_agent, false)
+        .get_with_interrupt(&url, proxy_type, Some(10), user_agent, false, None)
         .await;
 
     match response {
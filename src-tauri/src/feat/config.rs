@@ -1,23 +1,35 @@
 use crate::{
-    cmd::discord::update_discord_activity,
-    config::{Config, IVerge},
-    core::{CoreManager, handle, hotkey, sysopt, tray},
+    config::{Config, ConfigPersistence, ConfigTarget, IVerge},
+    core::{
+        CoreManager,
+        audit::AuditLog,
+        event_bus::{EventBus, StateEvent},
+        handle, hotkey,
+        kill_switch::KillSwitch,
+        sysopt, tray,
+    },
     module::{auto_backup::AutoBackupManager, lightweight},
+    utils::{crash, logger},
 };
 use anyhow::Result;
 use clash_verge_draft::SharedBox;
-use clash_verge_logging::{Type, logging, logging_error};
+use clash_verge_logging::{Type, logging_error};
 use serde_yaml_ng::Mapping;
 
 /// Patch Clash configuration
 pub async fn patch_clash(patch: &Mapping) -> Result<()> {
+    crash::record_action("patch_clash");
+    let old = Config::clash().await.latest_arc();
     Config::clash().await.edit_draft(|d| d.patch_config(patch));
 
-    let res = {
+    // 在异步块内传播错误，失败时撤销本次修改
+    let res = async {
         // 激活订阅
         if patch.get("secret").is_some() || patch.get("external-controller").is_some() {
+            handle::Handle::mihomo_http().invalidate();
             Config::generate().await?;
             CoreManager::global().restart_core().await?;
+            handle::Handle::refresh_clash();
         } else {
             if patch.get("mode").is_some() {
                 logging_error!(Type::Tray, tray::Tray::global().update_menu().await);
@@ -29,17 +41,17 @@ pub async fn patch_clash(patch: &Mapping) -> Result<()> {
                 );
             }
             Config::runtime().await.edit_draft(|d| d.patch_config(patch));
-            CoreManager::global().update_config().await?;
+            // 连续修改合并为一次重载
+            ConfigPersistence::global().request_reload();
         }
-        handle::Handle::refresh_clash();
         <Result<()>>::Ok(())
-    };
+    }
+    .await;
     match res {
         Ok(()) => {
             Config::clash().await.apply();
-            // 分离数据获取和异步调用
-            let clash_data = Config::clash().await.data_arc();
-            clash_data.save_config().await?;
+            ConfigPersistence::global().mark_dirty(ConfigTarget::Clash);
+            AuditLog::record_patch("clash", &old.0, patch);
             Ok(())
         }
         Err(err) => {
@@ -64,7 +76,9 @@ enum UpdateFlags {
     SystrayTooltip = 1 << 8,
     SystrayClickBehavior = 1 << 9,
     LighteWeight = 1 << 10,
-    DiscordRpc = 1 << 11,
+    KillSwitch = 1 << 12,
+    LogLevel = 1 << 13,
+    CoreProcess = 1 << 14,
 }
 
 fn determine_update_flags(patch: &IVerge) -> i32 {
@@ -113,7 +127,6 @@ fn determine_update_flags(patch: &IVerge) -> i32 {
         update_flags |= UpdateFlags::SystrayMenu as i32;
         update_flags |= UpdateFlags::SystrayTooltip as i32;
         update_flags |= UpdateFlags::SystrayIcon as i32;
-        update_flags |= UpdateFlags::DiscordRpc as i32;
     }
     if enable_global_hotkey.is_some() || home_cards.is_some() {
         update_flags |= UpdateFlags::VergeConfig as i32;
@@ -143,14 +156,17 @@ fn determine_update_flags(patch: &IVerge) -> i32 {
         update_flags |= UpdateFlags::SystrayMenu as i32;
         update_flags |= UpdateFlags::SystrayTooltip as i32;
         update_flags |= UpdateFlags::SystrayIcon as i32;
-        update_flags |= UpdateFlags::DiscordRpc as i32;
     }
 
     if proxy_bypass.is_some()
+        || patch.use_default_bypass.is_some()
         || pac_content.is_some()
         || pac.is_some()
         || enable_proxy_guard.is_some()
         || proxy_guard_duration.is_some()
+        || patch.sysproxy_interfaces.is_some()
+        || patch.enable_winhttp_proxy_sync.is_some()
+        || patch.linux_proxy_backend.is_some()
     {
         update_flags |= UpdateFlags::SysProxy as i32;
     }
@@ -163,6 +179,7 @@ fn determine_update_flags(patch: &IVerge) -> i32 {
         || tun_tray_icon.is_some()
         || tray_icon.is_some()
         || enable_tray_speed.is_some()
+        || patch.tray_icon_theme.is_some()
     // || enable_tray_icon.is_some()
     {
         update_flags |= UpdateFlags::SystrayIcon as i32;
@@ -181,7 +198,7 @@ fn determine_update_flags(patch: &IVerge) -> i32 {
         update_flags |= UpdateFlags::LighteWeight as i32;
     }
 
-    if enable_external_controller.is_some() {
+    if enable_external_controller.is_some() || patch.restrict_controller_cors.is_some() {
         update_flags |= UpdateFlags::RestartCore as i32;
     }
 
@@ -189,6 +206,22 @@ fn determine_update_flags(patch: &IVerge) -> i32 {
         update_flags |= UpdateFlags::SystrayMenu as i32;
     }
 
+    if patch.favorites.is_some() {
+        update_flags |= UpdateFlags::SystrayMenu as i32;
+    }
+
+    if patch.enable_kill_switch.is_some() {
+        update_flags |= UpdateFlags::KillSwitch as i32;
+    }
+
+    if patch.app_log_level.is_some() {
+        update_flags |= UpdateFlags::LogLevel as i32;
+    }
+
+    if patch.core_priority.is_some() || patch.core_cpu_affinity.is_some() {
+        update_flags |= UpdateFlags::CoreProcess as i32;
+    }
+
     update_flags
 }
 
@@ -242,13 +275,32 @@ async fn process_terminated_flags(update_flags: i32, patch: &IVerge) -> Result<(
             lightweight::disable_auto_light_weight_mode();
         }
     }
-    if (update_flags & (UpdateFlags::DiscordRpc as i32)) != 0 {
-        update_discord_activity().await;
+    if (update_flags & (UpdateFlags::KillSwitch as i32)) != 0 {
+        if patch.enable_kill_switch.unwrap_or(false) {
+            // Linux 依赖运行配置中的 routing-mark 放行内核
+            #[cfg(target_os = "linux")]
+            CoreManager::global().update_config().await?;
+        } else {
+            KillSwitch::global().release().await?;
+        }
+    }
+    if (update_flags & (UpdateFlags::LogLevel as i32)) != 0
+        && let Some(level) = &patch.app_log_level
+    {
+        logger::set_level(level)?;
+    }
+    if (update_flags & (UpdateFlags::CoreProcess as i32)) != 0 {
+        CoreManager::global().apply_process_tuning().await?;
     }
     Ok(())
 }
 
 pub async fn patch_verge(patch: &IVerge, not_save_file: bool) -> Result<()> {
+    crash::record_action("patch_verge");
+    let old = Config::verge().await.latest_arc();
+    if patch.enable_tun_mode == Some(true) && !old.enable_tun_mode.unwrap_or(false) {
+        super::ensure_tun_ready().await?;
+    }
     Config::verge().await.edit_draft(|d| d.patch_config(patch));
 
     let update_flags = determine_update_flags(patch);
@@ -262,12 +314,16 @@ pub async fn patch_verge(patch: &IVerge, not_save_file: bool) -> Result<()> {
         return Err(err);
     }
     Config::verge().await.apply();
+    AuditLog::record_patch("verge", &*old, patch);
+    if let Some(enabled) = patch.enable_tun_mode {
+        EventBus::global().publish(StateEvent::TunToggled { enabled });
+    }
+    if let Some(enabled) = patch.enable_system_proxy {
+        EventBus::global().publish(StateEvent::SystemProxyToggled { enabled });
+    }
     logging_error!(Type::Backup, AutoBackupManager::global().refresh_settings().await);
     if !not_save_file {
-        // 分离数据获取和异步调用
-        let verge_data = Config::verge().await.data_arc();
-        logging!(info, Type::Setup, "Saving Verge configuration to file...");
-        verge_data.save_file().await?;
+        ConfigPersistence::global().mark_dirty(ConfigTarget::Verge);
     }
     Ok(())
 }
@@ -0,0 +1,225 @@
+//! 内核内存占用
+//!
+//! 从内核 `/memory` 接口读取 Go 运行时统计的占用，以 sidecar 模式运行时同时读取进程的 RSS。
+//! 开启 `enable_tray_memory` 或设置 `core_memory_warn_mb` 后定期采样，结果供托盘提示与控制接口使用；
+//! 超过阈值时发送 `core-memory-warning` 事件，回落到阈值以下后才会再次发送。
+
+use crate::{
+    config::Config,
+    core::{CoreManager, handle, traffic::JsonFrameDecoder},
+    process::AsyncHandler,
+    singleton,
+};
+use anyhow::{Result, anyhow};
+use chrono::Local;
+use clash_verge_logging::{Type, logging};
+use futures::StreamExt as _;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+use tauri::Emitter as _;
+
+const CORE_MEMORY_WARNING_EVENT: &str = "core-memory-warning";
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+/// 未开启采样时检查配置的间隔
+const IDLE_INTERVAL: Duration = Duration::from_secs(60);
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+/// 内核推送的第一条数据可能为 0，最多多读几条
+const MAX_FRAMES: usize = 3;
+
+#[derive(Debug, Deserialize)]
+struct MemoryFrame {
+    inuse: u64,
+    #[serde(default)]
+    oslimit: u64,
+}
+
+/// 内存占用（字节）
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct CoreMemory {
+    /// 内核报告的使用量
+    pub inuse: u64,
+    /// 内核可用内存上限，0 表示未限制
+    pub oslimit: u64,
+    /// 进程常驻内存，服务模式下无法读取
+    pub rss: Option<u64>,
+    pub checked_at: i64,
+}
+
+impl CoreMemory {
+    /// 判断是否超过阈值时优先使用进程 RSS
+    pub fn usage(&self) -> u64 {
+        self.rss.unwrap_or(self.inuse)
+    }
+}
+
+/// 读取内核推送的第一条非零数据
+async fn read_inuse() -> Result<MemoryFrame> {
+    let mut stream = handle::Handle::mihomo_http().stream("/memory").await?.bytes_stream();
+    let mut decoder = JsonFrameDecoder::default();
+    let mut seen = 0;
+    let mut latest = None;
+    while seen < MAX_FRAMES {
+        let Some(chunk) = tokio::time::timeout(READ_TIMEOUT, stream.next()).await? else {
+            break;
+        };
+        for frame in decoder.feed::<MemoryFrame>(&chunk?) {
+            seen += 1;
+            if frame.inuse > 0 {
+                return Ok(frame);
+            }
+            latest = Some(frame);
+        }
+    }
+    latest.ok_or_else(|| anyhow!("core returned no memory data"))
+}
+
+/// `/proc/<pid>/status` 中的 `VmRSS`，单位 kB
+#[cfg(target_os = "linux")]
+pub fn process_rss(pid: u32) -> Option<u64> {
+    let status = std::fs::read_to_string(format!("/proc/{pid}/status")).ok()?;
+    let kb = status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))?
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse::<u64>()
+        .ok()?;
+    Some(kb * 1024)
+}
+
+#[cfg(target_os = "macos")]
+pub fn process_rss(pid: u32) -> Option<u64> {
+    let output = std::process::Command::new("ps")
+        .args(["-o", "rss=", "-p", &pid.to_string()])
+        .output()
+        .ok()?;
+    let kb = std::string::String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .parse::<u64>()
+        .ok()?;
+    Some(kb * 1024)
+}
+
+/// `tasklist` 的内存列形如 `"12,345 K"`
+#[cfg(target_os = "windows")]
+pub fn process_rss(pid: u32) -> Option<u64> {
+    use std::os::windows::process::CommandExt as _;
+
+    let output = std::process::Command::new("tasklist")
+        .args(["/FI", &format!("PID eq {pid}"), "/FO", "CSV", "/NH"])
+        .creation_flags(0x08000000) // CREATE_NO_WINDOW
+        .output()
+        .ok()?;
+    let text = std::string::String::from_utf8_lossy(&output.stdout);
+    let column = text.trim().rsplit("\",\"").next()?;
+    let kb: std::string::String = column.chars().filter(char::is_ascii_digit).collect();
+    Some(kb.parse::<u64>().ok()? * 1024)
+}
+
+/// 托盘提示中的内存文本
+pub fn memory_text(memory: &CoreMemory) -> std::string::String {
+    format!("Core: {:.1} MB", memory.usage() as f64 / (1024.0 * 1024.0))
+}
+
+pub struct CoreMemoryMonitor {
+    last: Mutex<Option<CoreMemory>>,
+    warned: AtomicBool,
+    runner_started: AtomicBool,
+}
+
+singleton!(CoreMemoryMonitor, CORE_MEMORY_MONITOR);
+
+impl CoreMemoryMonitor {
+    const fn new() -> Self {
+        Self {
+            last: Mutex::new(None),
+            warned: AtomicBool::new(false),
+            runner_started: AtomicBool::new(false),
+        }
+    }
+
+    /// 启动后台采样，重复调用无副作用
+    pub fn init(&self) {
+        if self.runner_started.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        AsyncHandler::spawn(|| async {
+            let monitor = Self::global();
+            loop {
+                let enabled = {
+                    let verge = Config::verge().await.latest_arc();
+                    verge.enable_tray_memory.unwrap_or(false) || verge.core_memory_warn_mb.is_some_and(|mb| mb > 0)
+                };
+                if !enabled {
+                    *monitor.last.lock() = None;
+                    tokio::time::sleep(IDLE_INTERVAL).await;
+                    continue;
+                }
+                if let Err(err) = monitor.check().await {
+                    logging!(debug, Type::Core, "Core memory check failed: {err}");
+                }
+                tokio::time::sleep(POLL_INTERVAL).await;
+            }
+        });
+    }
+
+    /// 最近一次的采样结果
+    pub fn last(&self) -> Option<CoreMemory> {
+        self.last.lock().clone()
+    }
+
+    /// 立即采样，超过阈值时发送事件
+    pub async fn check(&self) -> Result<CoreMemory> {
+        let frame = read_inuse().await?;
+        let memory = CoreMemory {
+            inuse: frame.inuse,
+            oslimit: frame.oslimit,
+            rss: CoreManager::global().sidecar_pid().and_then(process_rss),
+            checked_at: Local::now().timestamp(),
+        };
+        *self.last.lock() = Some(memory.clone());
+
+        let threshold = Config::verge()
+            .await
+            .latest_arc()
+            .core_memory_warn_mb
+            .filter(|&mb| mb > 0)
+            .map(|mb| mb.saturating_mul(1024 * 1024));
+        let exceeded = threshold.is_some_and(|limit| memory.usage() > limit);
+        if exceeded && !self.warned.swap(true, Ordering::SeqCst) {
+            logging!(warn, Type::Core, "Core memory usage is high: {}", memory_text(&memory));
+            let _ = handle::Handle::app_handle().emit(CORE_MEMORY_WARNING_EVENT, &memory);
+        } else if !exceeded {
+            self.warned.store(false, Ordering::SeqCst);
+        }
+        Ok(memory)
+    }
+}
+
+/// 立即读取内核内存占用
+pub async fn get_core_memory() -> Result<CoreMemory> {
+    CoreMemoryMonitor::global().check().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_usage() {
+        let mut memory = CoreMemory {
+            inuse: 64 * 1024 * 1024,
+            ..CoreMemory::default()
+        };
+        assert_eq!(memory_text(&memory), "Core: 64.0 MB");
+        memory.rss = Some(96 * 1024 * 1024 + 512 * 1024);
+        assert_eq!(memory.usage(), 96 * 1024 * 1024 + 512 * 1024);
+        assert_eq!(memory_text(&memory), "Core: 96.5 MB");
+    }
+}
@@ -0,0 +1,230 @@
+//! 问题反馈用的诊断包
+//!
+//! 打包脱敏后的运行配置与应用设置、版本与系统信息、端口占用、系统代理状态以及最新的应用和内核日志。
+//! 订阅地址、服务器地址、密码、令牌等字段统一替换为 [`REDACTED`]，日志中的链接、令牌与认证头同样会被替换。
+
+use super::{check_port_conflicts, configured_ports};
+use crate::{
+    config::Config,
+    core::{CoreManager, handle},
+    process::AsyncHandler,
+    utils::dirs,
+};
+use anyhow::Result;
+use parking_lot::RwLock;
+use regex::Regex;
+use serde_json::json;
+use serde_yaml_ng::Value;
+use std::{io::Write as _, path::PathBuf, sync::OnceLock};
+use sysproxy::{Autoproxy, Sysproxy};
+use tauri::Manager as _;
+use tauri_plugin_clash_verge_sysinfo::Platform;
+use zip::write::SimpleFileOptions;
+
+const REDACTED: &str = "<redacted>";
+const SENSITIVE_KEYS: [&str; 20] = [
+    "server",
+    "servername",
+    "sni",
+    "url",
+    "uuid",
+    "username",
+    "ip",
+    "ipv6",
+    "psk",
+    "auth",
+    "auth-str",
+    "authentication",
+    "authorization",
+    "cookie",
+    "private-key",
+    "public-key",
+    "pre-shared-key",
+    "short-id",
+    "lan_sync_peers",
+    "lan_sync_identity",
+];
+const SENSITIVE_PARTS: [&str; 4] = ["password", "secret", "token", "webdav"];
+
+fn is_sensitive_key(key: &str) -> bool {
+    let key = key.to_lowercase();
+    SENSITIVE_KEYS.contains(&key.as_str()) || SENSITIVE_PARTS.iter().any(|part| key.contains(part))
+}
+
+/// 递归替换敏感字段的值
+fn redact(value: &mut Value) {
+    match value {
+        Value::Mapping(map) => {
+            for (key, val) in map.iter_mut() {
+                if key.as_str().is_some_and(is_sensitive_key) && !val.is_null() {
+                    *val = Value::from(REDACTED);
+                } else {
+                    redact(val);
+                }
+            }
+        }
+        Value::Sequence(seq) => seq.iter_mut().for_each(redact),
+        Value::Tagged(tagged) => redact(&mut tagged.value),
+        _ => {}
+    }
+}
+
+/// 替换日志中的链接（订阅地址、深度链接等）、认证头以及 `key=value` 形式的令牌
+fn scrub_log(content: &str) -> std::string::String {
+    static URL_REGEX: OnceLock<Option<Regex>> = OnceLock::new();
+    static AUTH_REGEX: OnceLock<Option<Regex>> = OnceLock::new();
+    static PAIR_REGEX: OnceLock<Option<Regex>> = OnceLock::new();
+
+    let mut content = std::string::String::from(content);
+    if let Some(re) = URL_REGEX.get_or_init(|| Regex::new(r#"(?i)\b([a-z][a-z0-9+.-]*)://[^\s"'<>]+"#).ok()) {
+        content = re.replace_all(&content, format!("${{1}}://{REDACTED}")).into_owned();
+    }
+    if let Some(re) = AUTH_REGEX.get_or_init(|| Regex::new(r"(?i)\b(bearer|basic)\s+[a-z0-9._~+/=-]+").ok()) {
+        content = re.replace_all(&content, format!("${{1}} {REDACTED}")).into_owned();
+    }
+    if let Some(re) = PAIR_REGEX.get_or_init(|| {
+        Regex::new(r#"(?i)\b([a-z_-]*(?:password|secret|token|authentication|cookie|uuid)[a-z_-]*)(["']?\s*[:=]\s*["']?)[^\s"',;&]+"#).ok()
+    }) {
+        content = re.replace_all(&content, format!("${{1}}${{2}}{REDACTED}")).into_owned();
+    }
+    content
+}
+
+fn redacted_yaml(mut value: Value) -> Result<String> {
+    redact(&mut value);
+    Ok(serde_yaml_ng::to_string(&value)?)
+}
+
+async fn system_report() -> serde_json::Value {
+    let platform = handle::Handle::app_handle()
+        .state::<RwLock<Platform>>()
+        .read()
+        .to_string();
+    let core_version = handle::Handle::mihomo()
+        .await
+        .get_version()
+        .await
+        .map(|v| v.version)
+        .unwrap_or_else(|err| format!("unavailable: {err}"));
+    let sysproxy = Sysproxy::get_system_proxy()
+        .map(|p| json!({ "enable": p.enable, "server": format!("{}:{}", p.host, p.port), "bypass": p.bypass }))
+        .unwrap_or_else(|err| json!({ "error": err.to_string() }));
+    let autoproxy = Autoproxy::get_auto_proxy()
+        .map(|p| json!({ "enable": p.enable, "url": p.url }))
+        .unwrap_or_else(|err| json!({ "error": err.to_string() }));
+    let ports = configured_ports().await;
+    let conflicts = check_port_conflicts(&ports).await;
+
+    json!({
+        "generated_at": chrono::Local::now().to_rfc3339(),
+        "app_version": env!("CARGO_PKG_VERSION"),
+        "core_version": core_version,
+        "running_mode": CoreManager::global().get_running_mode().to_string(),
+        "platform": platform,
+        "system_proxy": sysproxy,
+        "auto_proxy": autoproxy,
+        "ports": ports,
+        "port_conflicts": conflicts,
+    })
+}
+
+/// 生成诊断包并返回 zip 文件路径
+pub async fn generate_diagnostics_bundle() -> Result<PathBuf> {
+    let runtime = Config::runtime()
+        .await
+        .latest_arc()
+        .config
+        .clone()
+        .map(Value::Mapping)
+        .unwrap_or_default();
+    let verge = serde_yaml_ng::to_value(&*Config::verge().await.latest_arc())?;
+
+    let mut entries: Vec<(&'static str, Vec<u8>)> = vec![
+        ("system.json", serde_json::to_vec_pretty(&system_report().await)?),
+        ("runtime.yaml", redacted_yaml(runtime)?.into_bytes()),
+        (dirs::VERGE_CONFIG, redacted_yaml(verge)?.into_bytes()),
+    ];
+    for (name, path) in [
+        ("logs/app_latest.log", dirs::app_latest_log()?),
+        ("logs/core_latest.log", dirs::clash_latest_log()?),
+    ] {
+        if let Ok(content) = tokio::fs::read(&path).await {
+            entries.push((
+                name,
+                scrub_log(&std::string::String::from_utf8_lossy(&content)).into_bytes(),
+            ));
+        }
+    }
+
+    let now = chrono::Local::now().format("%Y-%m-%d_%H-%M-%S");
+    let zip_path = dirs::app_temp_dir().join(format!("clash-verge-diagnostics-{now}.zip"));
+    let target = zip_path.clone();
+    AsyncHandler::spawn_blocking(move || -> Result<()> {
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(&target)?);
+        let options = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+        for (name, content) in entries {
+            zip.start_file(name, options)?;
+            zip.write_all(&content)?;
+        }
+        zip.finish()?;
+        Ok(())
+    })
+    .await??;
+    Ok(zip_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact() {
+        let mut value: Value = serde_yaml_ng::from_str(
+            r"
+mixed-port: 7897
+secret: abc
+proxies:
+  - name: HK
+    server: 1.2.3.4
+    password: p
+    port: 443
+proxy-providers:
+  sub:
+    url: https://example.com/sub?token=1
+webdav_password: w
+headers:
+  Authorization: Bearer abc
+authentication:
+  - user:pass
+",
+        )
+        .unwrap_or_default();
+        redact(&mut value);
+        assert_eq!(value["mixed-port"], Value::from(7897));
+        assert_eq!(value["secret"], Value::from(REDACTED));
+        assert_eq!(value["proxies"][0]["name"], Value::from("HK"));
+        assert_eq!(value["proxies"][0]["server"], Value::from(REDACTED));
+        assert_eq!(value["proxies"][0]["password"], Value::from(REDACTED));
+        assert_eq!(value["proxies"][0]["port"], Value::from(443));
+        assert_eq!(value["proxy-providers"]["sub"]["url"], Value::from(REDACTED));
+        assert_eq!(value["webdav_password"], Value::from(REDACTED));
+        assert_eq!(value["headers"]["Authorization"], Value::from(REDACTED));
+        assert_eq!(value["authentication"], Value::from(REDACTED));
+    }
+
+    #[test]
+    fn test_scrub_log() {
+        let log = "INFO import clash://install-config?url=https%3A%2F%2Fa.com%2Fs&name=x\n\
+                   WARN fetch https://example.com/sub?token=abc failed\n\
+                   DEBUG header Authorization: Bearer eyJhbGciOi.abc\n\
+                   DEBUG secret=s3cr3t port=7897";
+        let scrubbed = scrub_log(log);
+        assert!(scrubbed.contains(&format!("clash://{REDACTED}")));
+        assert!(scrubbed.contains(&format!("https://{REDACTED} failed")));
+        assert!(scrubbed.contains(&format!("Bearer {REDACTED}")));
+        assert!(scrubbed.contains(&format!("secret={REDACTED} port=7897")));
+        for leaked in ["a.com", "example.com", "abc", "eyJhbGciOi", "s3cr3t"] {
+            assert!(!scrubbed.contains(leaked), "{leaked} leaked: {scrubbed}");
+        }
+    }
+}
@@ -0,0 +1,557 @@
+//! DNS 查询工具、fake-ip 映射查询与 DNS 覆写
+//!
+//! 通过内核查询 DNS 用于排查分流问题；DNS 覆写保存在 `dns_config.yaml` 中，
+//! 开启 DNS 设置后由增强流程合并到运行配置，这里提供预设切换与 nameserver-policy 编辑。
+
+use crate::{
+    config::{Config, IVerge},
+    constants,
+    core::{CoreManager, handle},
+    process::AsyncHandler,
+    utils::{bbolt, dirs},
+};
+use anyhow::{Result, bail};
+use clash_verge_logging::{Type, logging};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use serde_yaml_ng::Mapping;
+use smartstring::alias::String;
+use std::net::{IpAddr, Ipv4Addr};
+use tokio::fs;
+
+const DEFAULT_FAKE_IP_RANGE: &str = "198.18.0.1/16";
+/// 内核在缓存文件中保存 fake-ip 映射的 bucket，键为 IP 时值为域名，反之亦然
+const FAKE_IP_BUCKET: &[u8] = b"fakeip";
+/// 与设置界面 DNS 覆写的默认值一致
+const DEFAULT_NAMESERVER: [&str; 3] = [
+    "8.8.8.8",
+    "https://doh.pub/dns-query",
+    "https://dns.alidns.com/dns-query",
+];
+const DEFAULT_BOOTSTRAP: [&str; 5] = ["system", "223.6.6.6", "8.8.8.8", "2400:3200::1", "2001:4860:4860::8888"];
+const DEFAULT_FAKE_IP_FILTER: [&str; 9] = [
+    "*.lan",
+    "*.local",
+    "*.arpa",
+    "time.*.com",
+    "ntp.*.com",
+    "+.market.xiaomi.com",
+    "localhost.ptlogin2.qq.com",
+    "*.msftncsi.com",
+    "www.msftconnecttest.com",
+];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DnsAnswer {
+    pub name: String,
+    pub record_type: u16,
+    pub ttl: u32,
+    pub data: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DnsQueryResult {
+    pub domain: String,
+    pub record_type: String,
+    /// DNS 响应码，0 为 NOERROR
+    pub status: i64,
+    pub answers: Vec<DnsAnswer>,
+    /// 按运行配置推断出的上游，命中 nameserver-policy 时为对应策略
+    pub upstream: Vec<String>,
+    pub policy: Option<String>,
+    pub fake_ip: bool,
+}
+
+fn strings_of(value: &serde_yaml_ng::Value) -> Vec<String> {
+    match value {
+        serde_yaml_ng::Value::String(s) => s.split(',').map(|s| s.trim().into()).collect(),
+        serde_yaml_ng::Value::Sequence(seq) => seq.iter().filter_map(|v| v.as_str()).map(Into::into).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// nameserver-policy 的域名匹配，`geosite:` / `rule-set:` 等需要内核数据的条目不参与推断
+fn policy_matches(pattern: &str, domain: &str) -> bool {
+    pattern.split(',').map(str::trim).any(|pattern| {
+        if let Some(suffix) = pattern.strip_prefix("+.") {
+            domain == suffix || domain.ends_with(&format!(".{suffix}"))
+        } else if let Some(suffix) = pattern.strip_prefix("*.") {
+            domain
+                .strip_suffix(suffix)
+                .is_some_and(|sub| sub.ends_with('.') && !sub[..sub.len() - 1].contains('.'))
+        } else if let Some(suffix) = pattern.strip_prefix('.') {
+            domain.ends_with(&format!(".{suffix}"))
+        } else {
+            !pattern.contains(':') && pattern.eq_ignore_ascii_case(domain)
+        }
+    })
+}
+
+/// 推断查询会使用的上游与命中的策略
+fn select_upstream(dns: &Mapping, domain: &str) -> (Vec<String>, Option<String>) {
+    if let Some(policies) = dns.get("nameserver-policy").and_then(|v| v.as_mapping()) {
+        for (pattern, servers) in policies {
+            if let Some(pattern) = pattern.as_str()
+                && policy_matches(pattern, domain)
+            {
+                return (strings_of(servers), Some(pattern.into()));
+            }
+        }
+    }
+    (dns.get("nameserver").map(strings_of).unwrap_or_default(), None)
+}
+
+fn parse_ipv4_cidr(cidr: &str) -> Option<(u32, u32)> {
+    let (addr, prefix) = cidr.split_once('/')?;
+    let addr: Ipv4Addr = addr.parse().ok()?;
+    let prefix: u32 = prefix.parse().ok().filter(|&p| p <= 32)?;
+    let mask = u32::MAX.checked_shl(32 - prefix).unwrap_or(0);
+    Some((u32::from(addr) & mask, mask))
+}
+
+/// 判断地址是否落在 fake-ip 地址池内
+pub fn in_fake_ip_range(range: &str, ip: &IpAddr) -> bool {
+    let IpAddr::V4(ip) = ip else {
+        return false;
+    };
+    parse_ipv4_cidr(range).is_some_and(|(network, mask)| u32::from(*ip) & mask == network)
+}
+
+/// 运行配置中的 dns 段
+pub(super) async fn runtime_dns() -> Mapping {
+    Config::runtime()
+        .await
+        .latest_arc()
+        .config
+        .as_ref()
+        .and_then(|config| config.get("dns"))
+        .and_then(|dns| dns.as_mapping())
+        .cloned()
+        .unwrap_or_default()
+}
+
+pub(super) fn fake_ip_range(dns: &Mapping) -> String {
+    dns.get("fake-ip-range")
+        .and_then(|v| v.as_str())
+        .unwrap_or(DEFAULT_FAKE_IP_RANGE)
+        .into()
+}
+
+fn parse_answers(response: &Value) -> Vec<DnsAnswer> {
+    response
+        .get("Answer")
+        .and_then(Value::as_array)
+        .map(|answers| {
+            answers
+                .iter()
+                .map(|answer| DnsAnswer {
+                    name: answer.get("name").and_then(Value::as_str).unwrap_or_default().into(),
+                    record_type: answer
+                        .get("type")
+                        .and_then(Value::as_u64)
+                        .and_then(|t| u16::try_from(t).ok())
+                        .unwrap_or(0),
+                    ttl: answer
+                        .get("TTL")
+                        .and_then(Value::as_u64)
+                        .and_then(|t| u32::try_from(t).ok())
+                        .unwrap_or(0),
+                    data: answer.get("data").and_then(Value::as_str).unwrap_or_default().into(),
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// 通过内核的 `/dns/query` 解析域名
+pub async fn resolve_via_core(domain: &str, record_type: Option<&str>) -> Result<DnsQueryResult> {
+    let domain = domain.trim().trim_end_matches('.').to_ascii_lowercase();
+    if domain.is_empty() || domain.contains(char::is_whitespace) {
+        bail!("invalid domain: {domain}");
+    }
+    let record_type = record_type.unwrap_or("A").to_ascii_uppercase();
+
+    let response = handle::Handle::mihomo().await.dns_query(&domain, &record_type).await?;
+    let response = serde_json::to_value(&response)?;
+    let answers = parse_answers(&response);
+
+    let dns = runtime_dns().await;
+    let (upstream, policy) = select_upstream(&dns, &domain);
+    let is_fake_ip_mode = dns.get("enhanced-mode").and_then(|v| v.as_str()) == Some("fake-ip");
+    let range = fake_ip_range(&dns);
+    let fake_ip = is_fake_ip_mode
+        && answers
+            .iter()
+            .filter_map(|answer| answer.data.parse::<IpAddr>().ok())
+            .any(|ip| in_fake_ip_range(&range, &ip));
+
+    Ok(DnsQueryResult {
+        status: response.get("Status").and_then(Value::as_i64).unwrap_or(-1),
+        domain: domain.into(),
+        record_type: record_type.into(),
+        answers,
+        upstream,
+        policy,
+        fake_ip,
+    })
+}
+
+/// 清空内核的 DNS 缓存与 fake-ip 映射
+pub async fn flush_dns_cache() -> Result<()> {
+    let mihomo = handle::Handle::mihomo().await;
+    mihomo.flush_dns().await?;
+    mihomo.flush_fakeip().await?;
+    drop(mihomo);
+    logging!(info, Type::Network, "DNS cache flushed");
+    Ok(())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FakeIpMapping {
+    pub ip: String,
+    pub domain: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FakeIpStatus {
+    pub enabled: bool,
+    /// 是否开启了 `profile.store-fake-ip`，未开启时映射只在内核内存中，无法读取
+    pub persisted: bool,
+    pub range: String,
+    pub pool_size: u64,
+    pub mappings: Vec<FakeIpMapping>,
+    /// 已分配的地址占地址池的百分比
+    pub usage_percent: f64,
+}
+
+/// 地址池可分配的地址数（去掉网络地址与广播地址）
+fn fake_ip_pool_size(range: &str) -> u64 {
+    parse_ipv4_cidr(range).map_or(0, |(_, mask)| (u64::from(!mask) + 1).saturating_sub(2))
+}
+
+/// 从缓存文件的 fake-ip bucket 中提取 IP -> 域名的映射，按 IP 排序
+fn collect_fake_ip_mappings(entries: &[(Vec<u8>, Vec<u8>)], range: &str) -> Vec<FakeIpMapping> {
+    let mut mappings: Vec<(Ipv4Addr, FakeIpMapping)> = entries
+        .iter()
+        .filter_map(|(key, value)| {
+            let ip = Ipv4Addr::from(<[u8; 4]>::try_from(key.as_slice()).ok()?);
+            let domain = std::str::from_utf8(value).ok().filter(|d| !d.is_empty())?;
+            in_fake_ip_range(range, &IpAddr::V4(ip)).then(|| {
+                (
+                    ip,
+                    FakeIpMapping {
+                        ip: ip.to_string().into(),
+                        domain: domain.into(),
+                    },
+                )
+            })
+        })
+        .collect();
+    mappings.sort_by_key(|(ip, _)| *ip);
+    mappings.dedup_by_key(|(ip, _)| *ip);
+    mappings.into_iter().map(|(_, mapping)| mapping).collect()
+}
+
+async fn read_fake_ip_cache() -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+    let path = dirs::app_home_dir()?.join(constants::files::CORE_CACHE);
+    if !fs::try_exists(&path).await.unwrap_or(false) {
+        return Ok(Vec::new());
+    }
+    let buf = fs::read(&path).await?;
+    AsyncHandler::spawn_blocking(move || bbolt::read_bucket(&buf, FAKE_IP_BUCKET)).await?
+}
+
+/// 查询 fake-ip 映射与地址池使用情况
+///
+/// 内核没有列出映射的接口，且 fake-ip 连接的目标地址已被还原为域名，无法从连接中得到映射。
+/// 开启 `profile.store-fake-ip` 后内核会把映射写入缓存文件，这里只读该文件，不会触发新的分配。
+/// `filter` 按 IP 或域名子串过滤。
+pub async fn get_fakeip_mappings(filter: Option<&str>) -> Result<FakeIpStatus> {
+    let dns = runtime_dns().await;
+    let range = fake_ip_range(&dns);
+    let enabled = dns.get("enhanced-mode").and_then(|v| v.as_str()) == Some("fake-ip");
+    let persisted = Config::runtime()
+        .await
+        .latest_arc()
+        .config
+        .as_ref()
+        .and_then(|config| config.get("profile"))
+        .and_then(|profile| profile.get("store-fake-ip"))
+        .and_then(serde_yaml_ng::Value::as_bool)
+        .unwrap_or(false);
+
+    let mut mappings = if enabled && persisted {
+        collect_fake_ip_mappings(&read_fake_ip_cache().await?, &range)
+    } else {
+        Vec::new()
+    };
+    let pool_size = fake_ip_pool_size(&range);
+    let usage_percent = if pool_size == 0 {
+        0.0
+    } else {
+        mappings.len() as f64 * 100.0 / pool_size as f64
+    };
+
+    if let Some(filter) = filter.map(str::trim).filter(|f| !f.is_empty()) {
+        let filter = filter.to_ascii_lowercase();
+        mappings.retain(|m| m.ip.contains(filter.as_str()) || m.domain.to_ascii_lowercase().contains(&filter));
+    }
+
+    Ok(FakeIpStatus {
+        enabled,
+        persisted,
+        range,
+        pool_size,
+        mappings,
+        usage_percent,
+    })
+}
+
+/// DNS 覆写预设：增强模式或加密上游
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DnsPreset {
+    FakeIp,
+    RedirHost,
+    CloudflareDoh,
+    GoogleDoh,
+    Quad9Dot,
+    AlidnsDoh,
+    DnspodDoh,
+}
+
+impl DnsPreset {
+    /// 加密上游及用于解析其域名的 IP 上游
+    const fn upstream(self) -> Option<(&'static [&'static str], &'static [&'static str])> {
+        match self {
+            Self::FakeIp | Self::RedirHost => None,
+            Self::CloudflareDoh => Some((
+                &["https://1.1.1.1/dns-query", "https://1.0.0.1/dns-query"],
+                &["1.1.1.1"],
+            )),
+            Self::GoogleDoh => Some((&["https://dns.google/dns-query"], &["8.8.8.8"])),
+            Self::Quad9Dot => Some((&["tls://dns.quad9.net"], &["9.9.9.9"])),
+            Self::AlidnsDoh => Some((&["https://dns.alidns.com/dns-query"], &["223.5.5.5"])),
+            Self::DnspodDoh => Some((&["https://doh.pub/dns-query"], &["119.29.29.29"])),
+        }
+    }
+}
+
+fn string_seq(items: &[&str]) -> serde_yaml_ng::Value {
+    serde_yaml_ng::Value::Sequence(items.iter().map(|&item| item.into()).collect())
+}
+
+fn insert_missing(dns: &mut Mapping, key: &str, value: serde_yaml_ng::Value) {
+    if !dns.contains_key(key) {
+        dns.insert(key.into(), value);
+    }
+}
+
+/// 把预设合并到现有 DNS 设置，增强模式预设只修改 enhanced-mode 并补齐缺少的上游，上游预设替换 nameserver
+fn use_dns_preset(mut dns: Mapping, preset: DnsPreset) -> Mapping {
+    match preset {
+        DnsPreset::FakeIp | DnsPreset::RedirHost => {
+            let mode = if preset == DnsPreset::FakeIp {
+                "fake-ip"
+            } else {
+                "redir-host"
+            };
+            dns.insert("enhanced-mode".into(), mode.into());
+            insert_missing(&mut dns, "nameserver", string_seq(&DEFAULT_NAMESERVER));
+            insert_missing(&mut dns, "default-nameserver", string_seq(&DEFAULT_BOOTSTRAP));
+            if preset == DnsPreset::FakeIp {
+                insert_missing(&mut dns, "fake-ip-range", DEFAULT_FAKE_IP_RANGE.into());
+                insert_missing(&mut dns, "fake-ip-filter", string_seq(&DEFAULT_FAKE_IP_FILTER));
+            }
+        }
+        _ => {
+            if let Some((nameserver, bootstrap)) = preset.upstream() {
+                dns.insert("nameserver".into(), string_seq(nameserver));
+                dns.insert("default-nameserver".into(), string_seq(bootstrap));
+            }
+        }
+    }
+    dns.insert("enable".into(), true.into());
+    dns
+}
+
+/// 读取 `dns_config.yaml` 中的 dns 段，兼容没有 `dns` 键的旧格式；
+/// 尚未保存过覆写时以运行配置中的 dns 段为基础，避免覆写只剩下预设修改的几项
+async fn load_dns_override() -> Result<(Mapping, Mapping)> {
+    let path = dirs::app_home_dir()?.join(constants::files::DNS_CONFIG);
+    if !fs::try_exists(&path).await.unwrap_or(false) {
+        return Ok((Mapping::new(), runtime_dns().await));
+    }
+    let file: Mapping = serde_yaml_ng::from_str(&fs::read_to_string(&path).await?)?;
+    match file.get("dns").and_then(|v| v.as_mapping()).cloned() {
+        Some(dns) => Ok((file, dns)),
+        None => Ok((Mapping::new(), file)),
+    }
+}
+
+async fn set_dns_settings_enabled(enabled: bool) -> Result<()> {
+    super::patch_verge(
+        &IVerge {
+            enable_dns_settings: Some(enabled),
+            ..IVerge::default()
+        },
+        false,
+    )
+    .await?;
+    handle::Handle::refresh_verge();
+    Ok(())
+}
+
+/// 保存 DNS 覆写并重新生成运行配置，内核拒绝时恢复原内容，并关闭因此开启的 DNS 设置
+async fn apply_dns_override(mut file: Mapping, dns: Mapping) -> Result<()> {
+    let path = dirs::app_home_dir()?.join(constants::files::DNS_CONFIG);
+    let previous = fs::read(&path).await.ok();
+    file.insert("dns".into(), dns.into());
+    fs::write(&path, serde_yaml_ng::to_string(&file)?).await?;
+
+    let enabled_here = !Config::verge().await.latest_arc().enable_dns_settings.unwrap_or(false);
+    if enabled_here {
+        set_dns_settings_enabled(true).await?;
+    }
+
+    let result = CoreManager::global().update_config().await;
+    if let Ok((true, _)) = result {
+        handle::Handle::refresh_clash();
+        return Ok(());
+    }
+
+    match previous {
+        Some(previous) => fs::write(&path, previous).await?,
+        None => fs::remove_file(&path).await?,
+    }
+    if enabled_here {
+        set_dns_settings_enabled(false).await?;
+    }
+    let (_, msg) = result?;
+    bail!("DNS override was rejected by the core: {msg}");
+}
+
+/// 切换 DNS 预设并热重载
+pub async fn apply_dns_preset(preset: DnsPreset) -> Result<Mapping> {
+    let (file, dns) = load_dns_override().await?;
+    let dns = use_dns_preset(dns, preset);
+    apply_dns_override(file, dns.clone()).await?;
+    logging!(info, Type::Network, "DNS preset {:?} applied", preset);
+    Ok(dns)
+}
+
+/// 获取 DNS 覆写中的 nameserver-policy
+pub async fn get_nameserver_policy() -> Result<Mapping> {
+    Ok(load_dns_override()
+        .await?
+        .1
+        .get("nameserver-policy")
+        .and_then(|v| v.as_mapping())
+        .cloned()
+        .unwrap_or_default())
+}
+
+/// 设置或删除（`servers` 为空）一条 nameserver-policy
+pub async fn set_nameserver_policy(pattern: &str, servers: &[String]) -> Result<Mapping> {
+    let pattern = pattern.trim();
+    if pattern.is_empty() {
+        bail!("nameserver-policy pattern is empty");
+    }
+    let (file, mut dns) = load_dns_override().await?;
+    let mut policy = dns
+        .get("nameserver-policy")
+        .and_then(|v| v.as_mapping())
+        .cloned()
+        .unwrap_or_default();
+    if servers.is_empty() {
+        policy.remove(pattern);
+    } else {
+        let servers = servers.iter().map(|s| serde_yaml_ng::Value::from(s.as_str())).collect();
+        policy.insert(pattern.into(), serde_yaml_ng::Value::Sequence(servers));
+    }
+    if policy.is_empty() {
+        dns.remove("nameserver-policy");
+    } else {
+        dns.insert("nameserver-policy".into(), policy.clone().into());
+    }
+    apply_dns_override(file, dns).await?;
+    Ok(policy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select_upstream() {
+        let dns: Mapping = serde_yaml_ng::from_str(
+            "nameserver: [https://1.1.1.1/dns-query]\nnameserver-policy:\n  'geosite:cn': [223.5.5.5]\n  '+.example.com': [tls://8.8.8.8]\n  '*.test.org,local.lan': 10.0.0.1",
+        )
+        .unwrap_or_default();
+        let (upstream, policy) = select_upstream(&dns, "a.example.com");
+        assert_eq!(upstream, vec![String::from("tls://8.8.8.8")]);
+        assert_eq!(policy.as_deref(), Some("+.example.com"));
+        assert_eq!(
+            select_upstream(&dns, "www.test.org").1.as_deref(),
+            Some("*.test.org,local.lan")
+        );
+        assert_eq!(select_upstream(&dns, "a.b.test.org").1, None);
+        assert_eq!(select_upstream(&dns, "local.lan").0, vec![String::from("10.0.0.1")]);
+        assert_eq!(
+            select_upstream(&dns, "other.net").0,
+            vec![String::from("https://1.1.1.1/dns-query")]
+        );
+    }
+
+    #[test]
+    fn test_fake_ip_range() {
+        let ip = |s: &str| s.parse::<IpAddr>().unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+        assert!(in_fake_ip_range("198.18.0.1/16", &ip("198.18.3.4")));
+        assert!(!in_fake_ip_range("198.18.0.1/16", &ip("198.19.0.1")));
+        assert!(!in_fake_ip_range("198.18.0.1/16", &ip("::1")));
+        assert!(in_fake_ip_range("0.0.0.0/0", &ip("8.8.8.8")));
+    }
+
+    #[test]
+    fn test_use_dns_preset() {
+        let dns: Mapping =
+            serde_yaml_ng::from_str("enhanced-mode: redir-host\nnameserver: [8.8.8.8]").unwrap_or_default();
+        let dns = use_dns_preset(dns, DnsPreset::FakeIp);
+        assert_eq!(dns.get("enhanced-mode").and_then(|v| v.as_str()), Some("fake-ip"));
+        assert_eq!(
+            dns.get("fake-ip-range").and_then(|v| v.as_str()),
+            Some(DEFAULT_FAKE_IP_RANGE)
+        );
+
+        assert_eq!(select_upstream(&dns, "example.com").0, vec![String::from("8.8.8.8")]);
+        assert!(dns.contains_key("fake-ip-filter"));
+
+        let dns = use_dns_preset(dns, DnsPreset::Quad9Dot);
+        assert_eq!(dns.get("enhanced-mode").and_then(|v| v.as_str()), Some("fake-ip"));
+        let (upstream, _) = select_upstream(&dns, "example.com");
+        assert_eq!(upstream, vec![String::from("tls://dns.quad9.net")]);
+
+        let dns = use_dns_preset(Mapping::new(), DnsPreset::RedirHost);
+        assert_eq!(select_upstream(&dns, "example.com").0.len(), DEFAULT_NAMESERVER.len());
+        assert!(dns.contains_key("default-nameserver"));
+        assert!(!dns.contains_key("fake-ip-range"));
+    }
+
+    #[test]
+    fn test_fake_ip_mappings() {
+        assert_eq!(fake_ip_pool_size("198.18.0.1/16"), 65534);
+        assert_eq!(fake_ip_pool_size("invalid"), 0);
+
+        let entries = vec![
+            (vec![198, 18, 0, 9], b"b.com".to_vec()),
+            (vec![198, 18, 0, 3], b"a.com".to_vec()),
+            (b"a.com".to_vec(), vec![198, 18, 0, 3]),
+            (vec![1, 2, 3, 4], b"c.com".to_vec()),
+            (vec![198, 18, 0, 5], Vec::new()),
+        ];
+        let mappings = collect_fake_ip_mappings(&entries, "198.18.0.1/16");
+        assert_eq!(mappings.len(), 2);
+        assert_eq!(mappings[0].domain, "a.com");
+        assert_eq!(mappings[1].ip, "198.18.0.9");
+    }
+}
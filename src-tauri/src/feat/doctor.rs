@@ -0,0 +1,168 @@
+//! 网络诊断
+//!
+//! 按顺序检查：内核 API → 经内核的 DNS 解析 → 直连 → 经代理访问探测地址 → 出口 IP。
+//! 前置阶段失败时，依赖它的阶段标记为跳过。
+
+use super::{CheckStatus, ExitIpInfo, lookup_exit_ip, resolve_via_core};
+use crate::{
+    config::Config,
+    core::handle,
+    utils::network::{NetworkManager, ProxyType},
+};
+use clash_verge_logging::{Type, logging};
+use serde::Serialize;
+use smartstring::alias::String;
+use std::time::{Duration, Instant};
+
+const CANARY_URL: &str = "https://www.gstatic.com/generate_204";
+const CANARY_DOMAIN: &str = "www.gstatic.com";
+const STAGE_TIMEOUT: Duration = Duration::from_secs(8);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DoctorStage {
+    pub id: &'static str,
+    pub status: CheckStatus,
+    pub detail: String,
+    pub elapsed_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DoctorReport {
+    pub stages: Vec<DoctorStage>,
+    pub exit_ip: Option<ExitIpInfo>,
+    /// 第一个失败的阶段
+    pub failed_stage: Option<&'static str>,
+}
+
+struct StageTimer {
+    id: &'static str,
+    start: Instant,
+}
+
+impl StageTimer {
+    fn start(id: &'static str) -> Self {
+        Self {
+            id,
+            start: Instant::now(),
+        }
+    }
+
+    fn finish(self, status: CheckStatus, detail: impl Into<String>) -> DoctorStage {
+        DoctorStage {
+            id: self.id,
+            status,
+            detail: detail.into(),
+            elapsed_ms: u64::try_from(self.start.elapsed().as_millis()).unwrap_or(u64::MAX),
+        }
+    }
+}
+
+fn skipped(id: &'static str, reason: &str) -> DoctorStage {
+    StageTimer::start(id).finish(CheckStatus::Skip, format!("skipped: {reason}"))
+}
+
+async fn check_core_api() -> DoctorStage {
+    let timer = StageTimer::start("core-api");
+    let mihomo = handle::Handle::mihomo().await;
+    let result = tokio::time::timeout(STAGE_TIMEOUT, mihomo.get_version()).await;
+    drop(mihomo);
+    match result {
+        Ok(Ok(_)) => timer.finish(CheckStatus::Pass, "core API is reachable"),
+        Ok(Err(err)) => timer.finish(CheckStatus::Fail, format!("core API error: {err}")),
+        Err(_) => timer.finish(CheckStatus::Fail, "core API timed out"),
+    }
+}
+
+async fn check_dns() -> DoctorStage {
+    let timer = StageTimer::start("dns");
+    match resolve_via_core(CANARY_DOMAIN, Some("A")).await {
+        Ok(result) if !result.answers.is_empty() => {
+            let answers: Vec<&str> = result.answers.iter().map(|a| a.data.as_str()).collect();
+            timer.finish(CheckStatus::Pass, format!("{CANARY_DOMAIN} -> {}", answers.join(", ")))
+        }
+        Ok(result) => timer.finish(
+            CheckStatus::Fail,
+            format!("no answer for {CANARY_DOMAIN} (rcode {})", result.status),
+        ),
+        Err(err) => timer.finish(CheckStatus::Fail, format!("DNS query failed: {err}")),
+    }
+}
+
+async fn check_http(id: &'static str, proxy: ProxyType) -> DoctorStage {
+    let timer = StageTimer::start(id);
+    let response = NetworkManager::new()
+        .get_with_interrupt(CANARY_URL, proxy, Some(STAGE_TIMEOUT.as_secs()), None, false, None)
+        .await;
+    match response {
+        Ok(response) if response.status().is_success() => {
+            timer.finish(CheckStatus::Pass, format!("HTTP {}", response.status().as_u16()))
+        }
+        Ok(response) => timer.finish(
+            CheckStatus::Warn,
+            format!("unexpected HTTP {}", response.status().as_u16()),
+        ),
+        Err(err) => timer.finish(CheckStatus::Fail, err.to_string()),
+    }
+}
+
+/// 运行全部诊断阶段
+pub async fn run_network_doctor() -> DoctorReport {
+    let tun_mode = Config::verge().await.latest_arc().enable_tun_mode.unwrap_or_default();
+    let mut stages = Vec::new();
+    let mut exit_ip = None;
+
+    let core = check_core_api().await;
+    let core_ok = core.status == CheckStatus::Pass;
+    stages.push(core);
+
+    stages.push(if core_ok {
+        check_dns().await
+    } else {
+        skipped("dns", "core API unreachable")
+    });
+
+    let mut direct = check_http("direct", ProxyType::None).await;
+    if tun_mode {
+        direct.detail = format!("{} (TUN mode: traffic may still pass through the core)", direct.detail).into();
+    }
+    stages.push(direct);
+
+    if core_ok {
+        let proxied = check_http("proxied", ProxyType::Localhost).await;
+        let proxied_ok = proxied.status != CheckStatus::Fail;
+        stages.push(proxied);
+
+        let timer = StageTimer::start("exit-ip");
+        stages.push(if proxied_ok {
+            match lookup_exit_ip(ProxyType::Localhost).await {
+                Ok(info) => {
+                    let detail = format!("{} ({}, {})", info.ip, info.country, info.organization);
+                    exit_ip = Some(info);
+                    timer.finish(CheckStatus::Pass, detail)
+                }
+                Err(err) => timer.finish(CheckStatus::Fail, err.to_string()),
+            }
+        } else {
+            skipped("exit-ip", "proxied connection failed")
+        });
+    } else {
+        stages.push(skipped("proxied", "core API unreachable"));
+        stages.push(skipped("exit-ip", "core API unreachable"));
+    }
+
+    let failed_stage = stages
+        .iter()
+        .find(|stage| stage.status == CheckStatus::Fail)
+        .map(|stage| stage.id);
+    logging!(
+        info,
+        Type::Network,
+        "Network doctor finished, first failure: {}",
+        failed_stage.unwrap_or("none")
+    );
+    DoctorReport {
+        stages,
+        exit_ip,
+        failed_stage,
+    }
+}
@@ -0,0 +1,294 @@
+//! 出口 IP 与地理位置查询
+//!
+//! 依次尝试与前端相同的几个公共查询服务，任一成功即返回。
+//! 开启 `enable_exit_ip_monitor` 后定期经代理查询，出口 IP 或国家变化时发送
+//! `exit-ip-changed` 事件，便于发现节点失效后静默回落到直连的情况。
+
+use crate::{
+    config::Config,
+    core::{
+        handle,
+        notify::{NotificationEvent, notify_event},
+    },
+    process::AsyncHandler,
+    utils::network::{NetworkManager, ProxyType},
+};
+use anyhow::{Result, anyhow};
+use chrono::Local;
+use clash_verge_logging::{Type, logging};
+use once_cell::sync::OnceCell;
+use parking_lot::Mutex;
+use serde::Serialize;
+use serde_json::Value;
+use smartstring::alias::String;
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+use tauri::Emitter as _;
+
+const LOOKUP_TIMEOUT_SECS: u64 = 8;
+const EXIT_IP_CHANGED_EVENT: &str = "exit-ip-changed";
+const DEFAULT_MONITOR_INTERVAL_MINUTES: u64 = 10;
+/// 未开启监控时检查配置的间隔
+const IDLE_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy)]
+enum IpService {
+    IpSb,
+    IpapiCo,
+    IpwhoIs,
+}
+
+impl IpService {
+    const ALL: [Self; 3] = [Self::IpSb, Self::IpapiCo, Self::IpwhoIs];
+
+    const fn url(self) -> &'static str {
+        match self {
+            Self::IpSb => "https://api.ip.sb/geoip",
+            Self::IpapiCo => "https://ipapi.co/json",
+            Self::IpwhoIs => "https://ipwho.is/",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ExitIpInfo {
+    pub ip: String,
+    pub country_code: String,
+    pub country: String,
+    pub region: String,
+    pub city: String,
+    pub organization: String,
+    pub asn: u64,
+}
+
+fn str_at(data: &Value, pointer: &str) -> String {
+    data.pointer(pointer).and_then(Value::as_str).unwrap_or_default().into()
+}
+
+fn parse_ip_info(service: IpService, data: &Value) -> Option<ExitIpInfo> {
+    let info = match service {
+        IpService::IpSb => ExitIpInfo {
+            ip: str_at(data, "/ip"),
+            country_code: str_at(data, "/country_code"),
+            country: str_at(data, "/country"),
+            region: str_at(data, "/region"),
+            city: str_at(data, "/city"),
+            organization: str_at(data, "/organization"),
+            asn: data.get("asn").and_then(Value::as_u64).unwrap_or_default(),
+        },
+        IpService::IpapiCo => ExitIpInfo {
+            ip: str_at(data, "/ip"),
+            country_code: str_at(data, "/country_code"),
+            country: str_at(data, "/country_name"),
+            region: str_at(data, "/region"),
+            city: str_at(data, "/city"),
+            organization: str_at(data, "/org"),
+            asn: data
+                .get("asn")
+                .and_then(Value::as_str)
+                .and_then(|asn| asn.trim_start_matches("AS").parse().ok())
+                .unwrap_or_default(),
+        },
+        IpService::IpwhoIs => ExitIpInfo {
+            ip: str_at(data, "/ip"),
+            country_code: str_at(data, "/country_code"),
+            country: str_at(data, "/country"),
+            region: str_at(data, "/region"),
+            city: str_at(data, "/city"),
+            organization: str_at(data, "/connection/org"),
+            asn: data
+                .pointer("/connection/asn")
+                .and_then(Value::as_u64)
+                .unwrap_or_default(),
+        },
+    };
+    (!info.ip.is_empty()).then_some(info)
+}
+
+/// 查询出口 IP，`proxy` 决定请求是否经过本机代理
+pub async fn lookup_exit_ip(proxy: ProxyType) -> Result<ExitIpInfo> {
+    let manager = NetworkManager::new();
+    let mut last_error = anyhow!("no IP lookup service available");
+    for service in IpService::ALL {
+        let response = match manager
+            .get_with_interrupt(service.url(), proxy, Some(LOOKUP_TIMEOUT_SECS), None, false, None)
+            .await
+        {
+            Ok(response) => response,
+            Err(err) => {
+                logging!(debug, Type::Network, "IP lookup via {} failed: {err}", service.url());
+                last_error = err;
+                continue;
+            }
+        };
+        let parsed = response
+            .text_with_charset()
+            .ok()
+            .and_then(|body| serde_json::from_str::<Value>(body).ok())
+            .and_then(|data| parse_ip_info(service, &data));
+        match parsed {
+            Some(info) => return Ok(info),
+            None => last_error = anyhow!("unexpected response from {}", service.url()),
+        }
+    }
+    Err(last_error)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExitIpSnapshot {
+    pub info: ExitIpInfo,
+    /// 查询时间（秒）
+    pub checked_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExitIpChange {
+    pub previous: ExitIpInfo,
+    pub current: ExitIpInfo,
+    pub country_changed: bool,
+}
+
+/// 出口 IP 或国家是否变化，首次查询不算变化
+fn detect_change(previous: Option<&ExitIpInfo>, current: &ExitIpInfo) -> Option<ExitIpChange> {
+    let previous = previous?;
+    let country_changed = previous.country_code != current.country_code;
+    (previous.ip != current.ip || country_changed).then(|| ExitIpChange {
+        previous: previous.clone(),
+        current: current.clone(),
+        country_changed,
+    })
+}
+
+/// 定期检查出口 IP 的后台任务
+pub struct ExitIpMonitor {
+    last: Mutex<Option<ExitIpSnapshot>>,
+    runner_started: AtomicBool,
+}
+
+impl ExitIpMonitor {
+    pub fn global() -> &'static Self {
+        static INSTANCE: OnceCell<ExitIpMonitor> = OnceCell::new();
+        INSTANCE.get_or_init(|| Self {
+            last: Mutex::new(None),
+            runner_started: AtomicBool::new(false),
+        })
+    }
+
+    pub fn init(&self) {
+        if self.runner_started.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        AsyncHandler::spawn(|| async {
+            let monitor = Self::global();
+            loop {
+                let (enabled, minutes) = {
+                    let verge = Config::verge().await.latest_arc();
+                    (
+                        verge.enable_exit_ip_monitor.unwrap_or(false),
+                        verge
+                            .exit_ip_monitor_interval
+                            .filter(|&m| m > 0)
+                            .unwrap_or(DEFAULT_MONITOR_INTERVAL_MINUTES),
+                    )
+                };
+                if !enabled {
+                    tokio::time::sleep(IDLE_INTERVAL).await;
+                    continue;
+                }
+                if let Err(err) = monitor.check().await {
+                    logging!(debug, Type::Network, "Exit IP check failed: {err}");
+                }
+                tokio::time::sleep(Duration::from_secs(minutes.saturating_mul(60))).await;
+            }
+        });
+    }
+
+    /// 最近一次的查询结果
+    pub fn last(&self) -> Option<ExitIpSnapshot> {
+        self.last.lock().clone()
+    }
+
+    /// 立即查询出口 IP，变化时发送事件
+    pub async fn check(&self) -> Result<ExitIpSnapshot> {
+        let info = lookup_exit_ip(ProxyType::Localhost).await?;
+        let snapshot = ExitIpSnapshot {
+            info,
+            checked_at: Local::now().timestamp(),
+        };
+        let previous = self.last.lock().replace(snapshot.clone());
+        if let Some(change) = detect_change(previous.as_ref().map(|p| &p.info), &snapshot.info) {
+            logging!(
+                warn,
+                Type::Network,
+                "Exit IP changed: {} ({}) -> {} ({})",
+                change.previous.ip,
+                change.previous.country_code,
+                change.current.ip,
+                change.current.country_code
+            );
+            let _ = handle::Handle::app_handle().emit(EXIT_IP_CHANGED_EVENT, &change);
+            notify_event(NotificationEvent::ExitIpChanged {
+                ip: &change.current.ip,
+                country: &change.current.country,
+            })
+            .await;
+        }
+        Ok(snapshot)
+    }
+}
+
+/// 出口 IP 信息，`refresh` 为真或尚未查询过时立即查询
+pub async fn get_exit_ip_info(refresh: bool) -> Result<ExitIpSnapshot> {
+    let monitor = ExitIpMonitor::global();
+    match monitor.last() {
+        Some(snapshot) if !refresh => Ok(snapshot),
+        _ => monitor.check().await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ip_info() {
+        let ipapi = serde_json::json!({
+            "ip": "203.0.113.7",
+            "country_code": "JP",
+            "country_name": "Japan",
+            "city": "Tokyo",
+            "org": "Example Net",
+            "asn": "AS64500"
+        });
+        let info = parse_ip_info(IpService::IpapiCo, &ipapi).unwrap_or_default();
+        assert_eq!(info.ip, "203.0.113.7");
+        assert_eq!(info.country, "Japan");
+        assert_eq!(info.asn, 64500);
+
+        let ipwho = serde_json::json!({"ip": "198.51.100.1", "connection": {"asn": 64501, "org": "Org"}});
+        let info = parse_ip_info(IpService::IpwhoIs, &ipwho).unwrap_or_default();
+        assert_eq!((info.asn, info.organization.as_str()), (64501, "Org"));
+
+        assert!(parse_ip_info(IpService::IpSb, &serde_json::json!({"error": "rate limited"})).is_none());
+    }
+
+    #[test]
+    fn test_detect_exit_ip_change() {
+        let proxied = ExitIpInfo {
+            ip: "203.0.113.7".into(),
+            country_code: "JP".into(),
+            ..ExitIpInfo::default()
+        };
+        let direct = ExitIpInfo {
+            ip: "198.51.100.1".into(),
+            country_code: "CN".into(),
+            ..ExitIpInfo::default()
+        };
+        assert!(detect_change(None, &proxied).is_none());
+        assert!(detect_change(Some(&proxied), &proxied).is_none());
+        assert!(detect_change(Some(&proxied), &direct).is_some_and(|c| c.country_changed));
+    }
+}
@@ -0,0 +1,357 @@
+//! 端口暴露检查
+//!
+//! 未开启局域网共享时，代理端口应只监听回环地址；外部控制器无论何时都只应监听回环地址。
+//! 实际监听地址通过 `ss`（Linux）、`lsof`（macOS）或 `netstat`（Windows）获取，
+//! 同时列出放行这些端口或内核程序的入站防火墙规则。修复只改写内核配置，防火墙规则需用户自行处理。
+//! Windows 上 `netstat` 的状态列与 `netsh` 的输出随系统语言翻译，因此只按列位置与数值解析，
+//! 防火墙规则通过 PowerShell 的 NetSecurity 模块读取。
+
+use super::{PortRole, configured_ports, ports::run};
+use crate::{
+    config::Config,
+    core::{CoreManager, handle},
+    process::AsyncHandler,
+};
+use anyhow::Result;
+use clash_verge_logging::{Type, logging};
+use serde::Serialize;
+use serde_yaml_ng::{Mapping, Value};
+use smartstring::alias::String;
+use std::net::IpAddr;
+
+/// 内核与应用的进程名前缀
+const OWN_PROGRAMS: [&str; 2] = ["verge-mihomo", "clash-verge"];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExposureFinding {
+    pub role: PortRole,
+    pub port: u16,
+    /// 监听或配置的地址
+    pub address: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct FirewallStatus {
+    /// 无法查询时为 `None`
+    pub enabled: Option<bool>,
+    /// 放行内核或代理端口的入站规则
+    pub inbound_rules: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExposureReport {
+    pub allow_lan: bool,
+    pub findings: Vec<ExposureFinding>,
+    pub firewall: FirewallStatus,
+    /// 本次检查前已修复的项目
+    pub fixed: Vec<ExposureFinding>,
+}
+
+/// 地址不是回环地址即视为暴露，`*`、`0.0.0.0`、`::` 和空主机表示所有网卡
+fn is_exposed(address: &str) -> bool {
+    let host = address.rsplit_once(':').map_or(address, |(host, _)| host);
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    match host {
+        "localhost" => false,
+        "" | "*" => true,
+        host => !host.parse::<IpAddr>().is_ok_and(|ip| ip.is_loopback()),
+    }
+}
+
+/// `ss -Hltn` 输出的第四列为本地地址
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_ss_addresses(output: &str) -> Vec<&str> {
+    output
+        .lines()
+        .filter_map(|line| line.split_whitespace().nth(3))
+        .collect()
+}
+
+/// `lsof -Fn` 输出中 `n` 开头的行为本地地址
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+fn parse_lsof_addresses(output: &str) -> Vec<&str> {
+    output.lines().filter_map(|line| line.strip_prefix('n')).collect()
+}
+
+/// `netstat -ano` 输出中监听指定端口的本地地址，监听行的远端端口为 0
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+fn parse_netstat_addresses(output: &str, port: u16) -> Vec<&str> {
+    let suffix = format!(":{port}");
+    output
+        .lines()
+        .filter_map(|line| match line.split_whitespace().collect::<Vec<_>>().as_slice() {
+            [proto, local, remote, _, _]
+                if proto.eq_ignore_ascii_case("TCP") && local.ends_with(&suffix) && remote.ends_with(":0") =>
+            {
+                Some(*local)
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(target_os = "linux")]
+fn listen_addresses(port: u16) -> Vec<String> {
+    run("ss", &["-Hltn", &format!("sport = :{port}")])
+        .map(|output| parse_ss_addresses(&output).into_iter().map(Into::into).collect())
+        .unwrap_or_default()
+}
+
+#[cfg(target_os = "macos")]
+fn listen_addresses(port: u16) -> Vec<String> {
+    run("lsof", &["-nP", &format!("-iTCP:{port}"), "-sTCP:LISTEN", "-Fn"])
+        .map(|output| parse_lsof_addresses(&output).into_iter().map(Into::into).collect())
+        .unwrap_or_default()
+}
+
+#[cfg(target_os = "windows")]
+fn listen_addresses(port: u16) -> Vec<String> {
+    run("netstat", &["-ano"])
+        .map(|output| {
+            parse_netstat_addresses(&output, port)
+                .into_iter()
+                .map(Into::into)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn matches_ports(value: &str, ports: &[u16]) -> bool {
+    value
+        .split(|c: char| !c.is_ascii_digit())
+        .filter_map(|part| part.parse::<u16>().ok())
+        .any(|port| ports.contains(&port))
+}
+
+/// 首行输出防火墙是否启用，之后每行为一条已启用的入站放行规则：`名称\t本地端口\t程序`
+#[cfg(target_os = "windows")]
+const FIREWALL_SCRIPT: &str = "[Console]::OutputEncoding = [Text.Encoding]::UTF8; \
+    [bool](Get-NetFirewallProfile -PolicyStore ActiveStore | Where-Object { $_.Enabled -eq 'True' }); \
+    $ports = @{}; Get-NetFirewallPortFilter | ForEach-Object { $ports[$_.InstanceID] = $_.LocalPort -join ',' }; \
+    $apps = @{}; Get-NetFirewallApplicationFilter | ForEach-Object { $apps[$_.InstanceID] = $_.Program }; \
+    Get-NetFirewallRule -Direction Inbound -Enabled True -Action Allow | ForEach-Object { \
+        \"$($_.DisplayName)`t$($ports[$_.InstanceID])`t$($apps[$_.InstanceID])\" }";
+
+/// 解析 `FIREWALL_SCRIPT` 的输出，返回是否启用与放行内核或代理端口的规则
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+fn parse_windows_firewall(output: &str, ports: &[u16]) -> FirewallStatus {
+    let mut lines = output.lines();
+    let enabled = match lines.next().map(str::trim) {
+        Some("True") => Some(true),
+        Some("False") => Some(false),
+        _ => return FirewallStatus::default(),
+    };
+    let inbound_rules = lines
+        .filter_map(|line| {
+            let mut fields = line.split('\t');
+            let name = fields.next()?.trim();
+            let local_port = fields.next().unwrap_or_default();
+            let program = fields.next().unwrap_or_default().to_lowercase();
+            let own_program = OWN_PROGRAMS.iter().any(|own| program.contains(own));
+            (!name.is_empty() && (own_program || matches_ports(local_port, ports))).then(|| name.into())
+        })
+        .collect();
+    FirewallStatus { enabled, inbound_rules }
+}
+
+/// 解析 `ufw status` 的输出，返回是否启用与放行代理端口的规则
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_ufw_status(output: &str, ports: &[u16]) -> Option<(bool, Vec<String>)> {
+    let state = output.lines().find_map(|line| line.strip_prefix("Status:"))?.trim();
+    let rules = output
+        .lines()
+        .filter(|line| line.contains("ALLOW"))
+        .filter(|line| {
+            line.split_whitespace()
+                .next()
+                .is_some_and(|to| matches_ports(to, ports))
+        })
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" ").into())
+        .collect();
+    Some((state == "active", rules))
+}
+
+#[cfg(target_os = "linux")]
+fn firewall_status(ports: &[u16]) -> FirewallStatus {
+    if let Some((enabled, inbound_rules)) = run("ufw", &["status"]).and_then(|out| parse_ufw_status(&out, ports)) {
+        return FirewallStatus {
+            enabled: Some(enabled),
+            inbound_rules,
+        };
+    }
+    // firewalld
+    let Some(state) = run("firewall-cmd", &["--state"]) else {
+        return FirewallStatus::default();
+    };
+    let inbound_rules = run("firewall-cmd", &["--list-ports"])
+        .unwrap_or_default()
+        .split_whitespace()
+        .filter(|rule| matches_ports(rule, ports))
+        .map(Into::into)
+        .collect();
+    FirewallStatus {
+        enabled: Some(state.trim() == "running"),
+        inbound_rules,
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn firewall_status(_ports: &[u16]) -> FirewallStatus {
+    // 应用防火墙按程序而非端口放行，这里只报告是否启用
+    let enabled = run("/usr/libexec/ApplicationFirewall/socketfilterfw", &["--getglobalstate"])
+        .map(|out| out.contains("enabled"));
+    FirewallStatus {
+        enabled,
+        inbound_rules: Vec::new(),
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn firewall_status(ports: &[u16]) -> FirewallStatus {
+    run(
+        "powershell",
+        &["-NoProfile", "-NonInteractive", "-Command", FIREWALL_SCRIPT],
+    )
+    .map(|out| parse_windows_firewall(&out, ports))
+    .unwrap_or_default()
+}
+
+async fn collect_findings() -> (bool, Vec<ExposureFinding>, Vec<u16>) {
+    let runtime = Config::runtime().await.latest_arc().config.clone().unwrap_or_default();
+    let allow_lan = runtime.get("allow-lan").and_then(Value::as_bool).unwrap_or_default();
+    let ports = configured_ports().await;
+    let port_numbers: Vec<u16> = ports.iter().map(|usage| usage.port).collect();
+
+    let mut findings = Vec::new();
+    if let Some(controller) = runtime
+        .get("external-controller")
+        .and_then(Value::as_str)
+        .filter(|addr| !addr.is_empty() && is_exposed(addr))
+    {
+        let port = controller
+            .rsplit_once(':')
+            .and_then(|(_, port)| port.parse().ok())
+            .unwrap_or_default();
+        findings.push(ExposureFinding {
+            role: PortRole::Controller,
+            port,
+            address: controller.into(),
+        });
+    }
+    for usage in ports {
+        if usage.role == PortRole::Controller || allow_lan {
+            continue;
+        }
+        let port = usage.port;
+        let addresses = AsyncHandler::spawn_blocking(move || listen_addresses(port))
+            .await
+            .unwrap_or_default();
+        if let Some(address) = addresses.into_iter().find(|addr| is_exposed(addr)) {
+            findings.push(ExposureFinding {
+                role: usage.role,
+                port,
+                address,
+            });
+        }
+    }
+    (allow_lan, findings, port_numbers)
+}
+
+/// 将暴露的监听改回回环地址并重启内核
+async fn fix_findings(findings: &[ExposureFinding]) -> Result<()> {
+    let mut patch = Mapping::new();
+    for finding in findings {
+        if finding.role == PortRole::Controller {
+            patch.insert(
+                "external-controller".into(),
+                format!("127.0.0.1:{}", finding.port).into(),
+            );
+        } else {
+            patch.insert("allow-lan".into(), false.into());
+            patch.insert("bind-address".into(), "127.0.0.1".into());
+        }
+    }
+    Config::clash().await.edit_draft(|d| d.patch_config(&patch));
+    Config::clash().await.apply();
+    Config::clash().await.data_arc().save_config().await?;
+    Config::generate().await?;
+    CoreManager::global().restart_core().await?;
+    handle::Handle::refresh_clash();
+    Ok(())
+}
+
+/// 检查监听地址与防火墙规则，`fix` 为真时先修复再重新检查
+pub async fn audit_exposure(fix: bool) -> Result<ExposureReport> {
+    let (mut allow_lan, mut findings, mut ports) = collect_findings().await;
+    let mut fixed = Vec::new();
+    if fix && !findings.is_empty() {
+        fix_findings(&findings).await?;
+        for finding in &findings {
+            logging!(
+                warn,
+                Type::Network,
+                "Restricted exposed {:?} listener {} to loopback",
+                finding.role,
+                finding.address
+            );
+        }
+        fixed = findings;
+        (allow_lan, findings, ports) = collect_findings().await;
+    }
+    let firewall = AsyncHandler::spawn_blocking(move || firewall_status(&ports))
+        .await
+        .unwrap_or_default();
+    Ok(ExposureReport {
+        allow_lan,
+        findings,
+        firewall,
+        fixed,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_exposed() {
+        assert!(is_exposed("0.0.0.0:7897"));
+        assert!(is_exposed("*:7897"));
+        assert!(is_exposed("[::]:7897"));
+        assert!(is_exposed(":9097"));
+        assert!(is_exposed("192.168.1.2:9097"));
+        assert!(!is_exposed("127.0.0.1:7897"));
+        assert!(!is_exposed("[::1]:7897"));
+        assert!(!is_exposed("localhost:9097"));
+    }
+
+    #[test]
+    fn test_parse_listeners() {
+        let ss = "LISTEN 0 4096 127.0.0.1:7897 0.0.0.0:*\nLISTEN 0 4096 *:7897 *:*\n";
+        assert_eq!(parse_ss_addresses(ss), vec!["127.0.0.1:7897", "*:7897"]);
+        assert_eq!(parse_lsof_addresses("p42\nn*:7897\n"), vec!["*:7897"]);
+        // 状态列已翻译（德语），仅靠远端端口为 0 识别监听
+        let netstat = "  TCP    0.0.0.0:7897    0.0.0.0:0    ABHÖREN    42\n  TCP    0.0.0.0:17897    0.0.0.0:0    ABHÖREN    43\n  TCP    [::]:7897    [::]:0    ABHÖREN    42\n  TCP    127.0.0.1:7897    127.0.0.1:50000    HERGESTELLT    42\n";
+        assert_eq!(
+            parse_netstat_addresses(netstat, 7897),
+            vec!["0.0.0.0:7897", "[::]:7897"]
+        );
+    }
+
+    #[test]
+    fn test_parse_firewall_rules() {
+        let windows = "True\r\nverge-mihomo\tAny\tC:\\app\\verge-mihomo.exe\r\nweb\t80\tAny\r\nProxy\t7897\tAny\r\n";
+        let firewall = parse_windows_firewall(windows, &[7897]);
+        assert_eq!(firewall.enabled, Some(true));
+        assert_eq!(
+            firewall.inbound_rules,
+            vec![String::from("verge-mihomo"), String::from("Proxy")]
+        );
+        assert_eq!(parse_windows_firewall("", &[7897]).enabled, None);
+
+        let ufw = "Status: active\n\nTo                         Action      From\n--                         ------      ----\n7897/tcp                   ALLOW       Anywhere\n22/tcp                     ALLOW       Anywhere\n";
+        let (enabled, rules) = parse_ufw_status(ufw, &[7897]).unwrap_or_default();
+        assert!(enabled);
+        assert_eq!(rules, vec![String::from("7897/tcp ALLOW Anywhere")]);
+    }
+}
@@ -0,0 +1,123 @@
+//! 收藏的代理组与节点
+//!
+//! 名称按收藏顺序保存在 verge 配置的 `favorites` 中。托盘根菜单列出收藏项，
+//! Discord 状态模板可通过 `{favorite_node}` 显示正在使用的收藏节点。
+
+use crate::{
+    config::{Config, IVerge},
+    core::handle,
+};
+use anyhow::Result;
+use serde::Serialize;
+use smartstring::alias::String;
+use tauri_plugin_mihomo::models::Proxies;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Favorite {
+    pub name: String,
+    /// 收藏的是代理组
+    pub is_group: bool,
+    /// 节点所在的主代理组，切换节点时在此组中选择
+    pub group: Option<String>,
+    /// 代理组（或节点所在代理组）当前选中的节点
+    pub now: Option<String>,
+    /// 节点正在被所在代理组使用
+    pub selected: bool,
+    /// 当前配置中存在该名称
+    pub available: bool,
+}
+
+fn toggled(list: &[String], name: &str) -> (Vec<String>, bool) {
+    if list.iter().any(|n| n == name) {
+        (list.iter().filter(|n| *n != name).cloned().collect(), false)
+    } else {
+        let mut list = list.to_vec();
+        list.push(name.into());
+        (list, true)
+    }
+}
+
+/// 结合内核中的代理信息解析收藏项
+pub fn resolve_favorites(names: &[String], proxies: &Proxies, mode: &str) -> Vec<Favorite> {
+    names
+        .iter()
+        .map(|name| {
+            let entry = proxies.proxies.get(name.as_str());
+            let is_group = entry.is_some_and(|proxy| proxy.all.is_some());
+            let group: Option<String> = if is_group {
+                None
+            } else {
+                super::main_group(proxies, mode, Some(name.as_str())).map(Into::into)
+            };
+            let now: Option<String> = if is_group {
+                entry.and_then(|proxy| proxy.now.as_deref()).map(Into::into)
+            } else {
+                group
+                    .as_deref()
+                    .and_then(|group| proxies.proxies.get(group))
+                    .and_then(|group| group.now.as_deref())
+                    .map(Into::into)
+            };
+            Favorite {
+                name: name.clone(),
+                is_group,
+                selected: !is_group && now.as_deref() == Some(name.as_str()),
+                group,
+                now,
+                available: entry.is_some(),
+            }
+        })
+        .collect()
+}
+
+/// 收藏列表及其在内核中的当前状态
+pub async fn get_favorites() -> Result<Vec<Favorite>> {
+    let names = Config::verge().await.latest_arc().favorites.clone().unwrap_or_default();
+    if names.is_empty() {
+        return Ok(Vec::new());
+    }
+    let proxies = handle::Handle::mihomo().await.get_proxies().await?;
+    Ok(resolve_favorites(&names, &proxies, &super::clash_mode().await))
+}
+
+/// 收藏或取消收藏，返回操作后是否处于收藏状态
+pub async fn toggle_favorite(name: &str) -> Result<bool> {
+    let current = Config::verge().await.latest_arc().favorites.clone().unwrap_or_default();
+    let (favorites, pinned) = toggled(&current, name);
+    super::patch_verge(
+        &IVerge {
+            favorites: Some(favorites),
+            ..IVerge::default()
+        },
+        false,
+    )
+    .await?;
+    Ok(pinned)
+}
+
+/// 正在使用的收藏节点，没有时为空
+pub async fn favorite_node(proxies: &Proxies) -> String {
+    let names = Config::verge().await.latest_arc().favorites.clone().unwrap_or_default();
+    let mode = super::clash_mode().await;
+    resolve_favorites(&names, proxies, &mode)
+        .into_iter()
+        .find(|favorite| favorite.selected)
+        .map(|favorite| favorite.name)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_toggled() {
+        let list: Vec<String> = vec!["HK-01".into(), "Proxy".into()];
+        let (list, pinned) = toggled(&list, "SG-01");
+        assert!(pinned);
+        assert_eq!(list.last().map(String::as_str), Some("SG-01"));
+        let (list, pinned) = toggled(&list, "HK-01");
+        assert!(!pinned);
+        assert_eq!(list, vec![String::from("Proxy"), String::from("SG-01")]);
+    }
+}
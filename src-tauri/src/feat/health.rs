@@ -0,0 +1,204 @@
+//! 订阅更新后的健康检查
+//!
+//! 新内容无法解析或不含任何节点时，恢复到更新前保存的历史版本并发送通知；
+//! 节点数量骤减只记录警告。开启 `enable_profile_health_delay_test` 后，
+//! 当前订阅重新加载后还会抽样测试部分节点的延迟。
+
+use crate::{
+    config::{Config, versions},
+    core::{
+        handle,
+        notify::{NotificationEvent, notify_event},
+    },
+    utils::dirs,
+};
+use anyhow::{Result, anyhow};
+use clash_verge_logging::{Type, logging};
+use serde::Serialize;
+use serde_yaml_ng::{Mapping, Value};
+use smartstring::alias::String;
+use tauri::Emitter as _;
+use tokio::fs;
+
+/// 抽样测试延迟的节点数
+const DELAY_SAMPLE_SIZE: usize = 5;
+const DEFAULT_TEST_URL: &str = "https://www.gstatic.com/generate_204";
+const DEFAULT_TEST_TIMEOUT_MS: u32 = 5000;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NodeDelay {
+    pub name: String,
+    /// 超时或失败时为 `None`
+    pub delay: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProfileHealth {
+    pub uid: String,
+    pub valid: bool,
+    pub reason: Option<String>,
+    pub proxies: usize,
+    pub providers: usize,
+    /// 更新前版本的节点数，没有历史版本时为 `None`
+    pub previous_proxies: Option<usize>,
+    pub reverted: bool,
+    pub delays: Vec<NodeDelay>,
+}
+
+/// 配置中的节点名称与 proxy-providers 数量，无法解析时返回原因
+fn inspect(content: &str) -> Result<(Vec<String>, usize), String> {
+    let config =
+        serde_yaml_ng::from_str::<Mapping>(content).map_err(|err| String::from(format!("invalid yaml: {err}")))?;
+    let names = config
+        .get("proxies")
+        .and_then(Value::as_sequence)
+        .map(|proxies| {
+            proxies
+                .iter()
+                .filter_map(|p| p.get("name").and_then(Value::as_str).map(String::from))
+                .collect()
+        })
+        .unwrap_or_default();
+    let providers = config
+        .get("proxy-providers")
+        .and_then(Value::as_mapping)
+        .map_or(0, Mapping::len);
+    Ok((names, providers))
+}
+
+/// 检查刚更新的订阅，不可用时回滚到上一个版本
+pub async fn check_profile_health(uid: &String) -> Result<ProfileHealth> {
+    let (file, name) = {
+        let profiles = Config::profiles().await.latest_arc();
+        let item = profiles.get_item(uid)?;
+        let file = item.file.clone().ok_or_else(|| anyhow!("profile {uid} has no file"))?;
+        (file, item.name.clone().unwrap_or_else(|| uid.clone()))
+    };
+    let path = dirs::app_profiles_dir()?.join(file.as_str());
+    let content = fs::read_to_string(&path).await.unwrap_or_default();
+
+    let previous = match versions::list_profile_versions(uid).await?.first() {
+        Some(latest) => Some(versions::read_profile_version(uid, &latest.version).await?),
+        None => None,
+    };
+    let previous_proxies = previous
+        .as_deref()
+        .and_then(|content| inspect(content).ok())
+        .map(|(names, _)| names.len());
+
+    let (names, providers, reason) = match inspect(&content) {
+        Ok((names, providers)) if names.is_empty() && providers == 0 => {
+            (names, providers, Some(String::from("no proxies")))
+        }
+        Ok((names, providers)) => (names, providers, None),
+        Err(reason) => (Vec::new(), 0, Some(reason)),
+    };
+
+    let mut health = ProfileHealth {
+        uid: uid.clone(),
+        valid: reason.is_none(),
+        reason,
+        proxies: names.len(),
+        providers,
+        previous_proxies,
+        reverted: false,
+        delays: Vec::new(),
+    };
+
+    if let (Some(reason), Some(previous)) = (health.reason.as_deref(), previous) {
+        fs::write(&path, previous.as_bytes()).await?;
+        health.reverted = true;
+        logging!(
+            warn,
+            Type::Config,
+            "[订阅检查] {} 不可用（{}），已恢复上一个版本",
+            uid,
+            reason
+        );
+        notify_event(NotificationEvent::ProfileReverted { name: &name, reason }).await;
+    } else if let Some(before) = previous_proxies
+        && health.proxies * 2 < before
+    {
+        logging!(
+            warn,
+            Type::Config,
+            "[订阅检查] {} 节点数量从 {} 减少到 {}",
+            uid,
+            before,
+            health.proxies
+        );
+    }
+
+    let _ = handle::Handle::app_handle().emit("profile-health", &health);
+    Ok(health)
+}
+
+/// 通过内核抽样测试订阅中部分节点的延迟，需在订阅加载到内核后调用
+pub async fn sample_profile_delays(uid: &String) -> Result<Vec<NodeDelay>> {
+    let (test_url, timeout) = {
+        let verge = Config::verge().await.latest_arc();
+        (
+            verge
+                .default_latency_test
+                .clone()
+                .unwrap_or_else(|| DEFAULT_TEST_URL.into()),
+            verge
+                .default_latency_timeout
+                .and_then(|t| u32::try_from(t).ok())
+                .filter(|&t| t > 0)
+                .unwrap_or(DEFAULT_TEST_TIMEOUT_MS),
+        )
+    };
+    let file = {
+        let profiles = Config::profiles().await.latest_arc();
+        profiles
+            .get_item(uid)?
+            .file
+            .clone()
+            .ok_or_else(|| anyhow!("profile {uid} has no file"))?
+    };
+    let content = fs::read_to_string(dirs::app_profiles_dir()?.join(file.as_str())).await?;
+    let (names, _) = inspect(&content).map_err(|reason| anyhow!("{reason}"))?;
+
+    // 均匀抽样，避免只测到同一地区的节点
+    let step = (names.len() / DELAY_SAMPLE_SIZE).max(1);
+    let mihomo = handle::Handle::mihomo().await;
+    let mut delays = Vec::new();
+    for name in names.into_iter().step_by(step).take(DELAY_SAMPLE_SIZE) {
+        let delay = mihomo
+            .delay_proxy_by_name(&name, &test_url, timeout)
+            .await
+            .ok()
+            .map(|result| result.delay)
+            .filter(|&delay| delay > 0);
+        delays.push(NodeDelay { name, delay });
+    }
+    drop(mihomo);
+
+    let reachable = delays.iter().filter(|d| d.delay.is_some()).count();
+    logging!(
+        info,
+        Type::Config,
+        "[订阅检查] {} 抽样延迟测试: {}/{} 可用",
+        uid,
+        reachable,
+        delays.len()
+    );
+    Ok(delays)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inspect_profile() {
+        let (names, providers) =
+            inspect("proxies:\n  - {name: a, type: ss}\n  - {name: b, type: ss}\nproxy-providers:\n  p: {type: http}")
+                .unwrap_or_default();
+        assert_eq!(names.iter().map(String::as_str).collect::<Vec<_>>(), ["a", "b"]);
+        assert_eq!(providers, 1);
+        assert!(inspect("proxies: [").is_err());
+        assert_eq!(inspect("rules: []").map(|(names, _)| names.len()), Ok(0));
+    }
+}
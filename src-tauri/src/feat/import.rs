@@ -0,0 +1,278 @@
+//! 从剪贴板、二维码或拖入的文件导入配置
+//!
+//! 内容按以下顺序识别：Clash YAML 配置、订阅链接（含 `clash://install-config`）、
+//! 节点分享链接（可整体 base64 编码）、sing-box / Surge / Quantumult X 配置。
+//! 后两者会转换后生成一个本地配置。
+
+use crate::{
+    config::{
+        PrfItem,
+        profiles::{profiles_append_item_safe, profiles_save_file_safe},
+    },
+    core::{
+        converter::{self, SubscriptionFormat},
+        handle, sharelink,
+    },
+    module::auto_backup::{AutoBackupManager, AutoBackupTrigger},
+    process::AsyncHandler,
+    utils::{i18n, resolve::scheme::install_config_url},
+};
+use anyhow::{Result, anyhow, bail};
+use clash_verge_logging::{Type, logging};
+use serde::Serialize;
+use serde_yaml_ng::Mapping;
+use smartstring::alias::String;
+use std::path::Path;
+use tauri::Url;
+use tauri_plugin_clipboard_manager::ClipboardExt as _;
+use tauri_plugin_dialog::{DialogExt as _, MessageDialogButtons, MessageDialogKind};
+use tokio::sync::oneshot;
+
+/// 拖入文件的大小上限
+const MAX_IMPORT_FILE_SIZE: u64 = 10 * 1024 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ImportKind {
+    Subscription,
+    ShareLinks,
+    Yaml,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportResult {
+    pub kind: ImportKind,
+    pub uids: Vec<String>,
+}
+
+/// 识别出的待导入内容
+#[derive(Debug, PartialEq)]
+enum Detected {
+    Subscriptions(Vec<std::string::String>),
+    ShareLinks(Vec<Mapping>),
+    Yaml(std::string::String),
+}
+
+/// 提取 http(s) 订阅地址，`clash://install-config?url=` 会被展开
+fn subscription_url(line: &str) -> Option<std::string::String> {
+    let url = Url::parse(line.trim()).ok()?;
+    match url.scheme() {
+        "http" | "https" => Some(url.to_string()),
+        "clash" | "clash-verge" => install_config_url(&url),
+        _ => None,
+    }
+}
+
+fn is_clash_yaml(text: &str) -> bool {
+    serde_yaml_ng::from_str::<Mapping>(text).is_ok_and(|map| {
+        ["proxies", "proxy-groups", "proxy-providers", "rules"]
+            .iter()
+            .any(|key| map.contains_key(*key))
+    })
+}
+
+fn detect(text: &str) -> Result<Detected> {
+    let text = text.trim();
+    if text.is_empty() {
+        bail!("nothing to import");
+    }
+    if is_clash_yaml(text) {
+        return Ok(Detected::Yaml(text.to_owned()));
+    }
+
+    let urls: Vec<_> = text.lines().filter_map(subscription_url).collect();
+    if !urls.is_empty() {
+        return Ok(Detected::Subscriptions(urls));
+    }
+
+    let proxies = sharelink::parse_share_links(text);
+    if !proxies.is_empty() {
+        return Ok(Detected::ShareLinks(proxies));
+    }
+
+    let format = SubscriptionFormat::detect(text);
+    if matches!(
+        format,
+        SubscriptionFormat::SingBox | SubscriptionFormat::Surge | SubscriptionFormat::QuantumultX
+    ) {
+        return converter::convert_subscription(text, format).map(Detected::Yaml);
+    }
+    bail!("no subscription URL, share link or Clash config found")
+}
+
+async fn save_item(item: &mut PrfItem) -> Result<String> {
+    profiles_append_item_safe(item).await?;
+    profiles_save_file_safe().await?;
+    let uid = item.uid.clone().ok_or_else(|| anyhow!("imported profile has no uid"))?;
+    handle::Handle::notify_profile_changed(uid.clone());
+    Ok(uid)
+}
+
+async fn import_local(name: &str, content: std::string::String) -> Result<String> {
+    let mut item = PrfItem::from_local(name.into(), "".into(), Some(content.into()), None).await?;
+    save_item(&mut item).await
+}
+
+/// 识别文本内容并创建相应的配置，`name` 用于本地配置的名称
+pub async fn import_profile_text(text: &str, name: Option<&str>) -> Result<ImportResult> {
+    let result = match detect(text)? {
+        Detected::Subscriptions(urls) => {
+            let mut uids = Vec::new();
+            for url in urls {
+                let mut item = PrfItem::from_url(&url, None, None, None).await?;
+                uids.push(save_item(&mut item).await?);
+            }
+            ImportResult {
+                kind: ImportKind::Subscription,
+                uids,
+            }
+        }
+        Detected::ShareLinks(proxies) => {
+            let count = proxies.len();
+            let content = converter::build_profile(proxies)?;
+            let name = name.map_or_else(|| format!("Imported Nodes ({count})"), ToOwned::to_owned);
+            ImportResult {
+                kind: ImportKind::ShareLinks,
+                uids: vec![import_local(&name, content).await?],
+            }
+        }
+        Detected::Yaml(content) => ImportResult {
+            kind: ImportKind::Yaml,
+            uids: vec![import_local(name.unwrap_or("Imported Config"), content).await?],
+        },
+    };
+
+    logging!(
+        info,
+        Type::Config,
+        "Imported {} profile(s) as {:?}",
+        result.uids.len(),
+        result.kind
+    );
+    AutoBackupManager::trigger_backup(AutoBackupTrigger::ProfileChange);
+    Ok(result)
+}
+
+/// 导入剪贴板中的订阅链接、分享链接或配置内容
+pub async fn import_profile_from_clipboard() -> Result<ImportResult> {
+    let text = handle::Handle::app_handle()
+        .clipboard()
+        .read_text()
+        .map_err(|err| anyhow!("failed to read clipboard: {err}"))?;
+    import_profile_text(&text, None).await
+}
+
+/// 从二维码图片中解码文本，支持 PNG 与 JPEG
+pub fn decode_qr_image(image_bytes: &[u8]) -> Result<std::string::String> {
+    let image = image::load_from_memory(image_bytes)?.to_luma8();
+    let mut prepared = rqrr::PreparedImage::prepare(image);
+    prepared
+        .detect_grids()
+        .into_iter()
+        .find_map(|grid| grid.decode().ok().map(|(_, content)| content))
+        .ok_or_else(|| anyhow!("no QR code found in image"))
+}
+
+/// 导入二维码图片中的订阅或节点
+pub async fn import_profile_from_qr(image_bytes: Vec<u8>) -> Result<ImportResult> {
+    let text = AsyncHandler::spawn_blocking(move || decode_qr_image(&image_bytes)).await??;
+    import_profile_text(&text, None).await
+}
+
+/// 导入拖入的文件：图片按二维码处理，其余按文本识别，文件名作为配置名称
+pub async fn import_profile_from_file(path: &Path) -> Result<ImportResult> {
+    let metadata = tokio::fs::metadata(path).await?;
+    if !metadata.is_file() || metadata.len() > MAX_IMPORT_FILE_SIZE {
+        bail!("unsupported file: {}", path.display());
+    }
+    let bytes = tokio::fs::read(path).await?;
+    let is_image = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| matches!(ext.to_ascii_lowercase().as_str(), "png" | "jpg" | "jpeg"));
+    if is_image {
+        return import_profile_from_qr(bytes).await;
+    }
+
+    let text = std::string::String::from_utf8(bytes).map_err(|_| anyhow!("file is not UTF-8 text"))?;
+    let name = path.file_stem().map(|stem| stem.to_string_lossy());
+    import_profile_text(&text, name.as_deref()).await
+}
+
+/// 拖放可能是误操作，导入前弹窗确认，关闭窗口视为取消
+async fn confirm_dropped_files(paths: &[std::path::PathBuf]) -> bool {
+    i18n::sync_locale().await;
+    let files = paths
+        .iter()
+        .map(|path| path.file_name().unwrap_or(path.as_os_str()).to_string_lossy())
+        .collect::<Vec<_>>()
+        .join(", ");
+    let (tx, rx) = oneshot::channel();
+    handle::Handle::app_handle()
+        .dialog()
+        .message(rust_i18n::t!("fileImport.confirm").replace("{files}", &files))
+        .title(rust_i18n::t!("fileImport.title").to_string())
+        .kind(MessageDialogKind::Info)
+        .buttons(MessageDialogButtons::OkCancel)
+        .show(move |confirmed| {
+            let _ = tx.send(confirmed);
+        });
+    rx.await.unwrap_or(false)
+}
+
+/// 处理主窗口的文件拖放，用户确认后逐个导入
+pub fn handle_dropped_files(paths: Vec<std::path::PathBuf>) {
+    AsyncHandler::spawn(move || async move {
+        if !confirm_dropped_files(&paths).await {
+            logging!(info, Type::Config, "Dropped file import cancelled by user");
+            return;
+        }
+        for path in paths {
+            match import_profile_from_file(&path).await {
+                Ok(_) => handle::Handle::notice_message("import_sub_url::ok", ""),
+                Err(err) => {
+                    logging!(warn, Type::Config, "Failed to import {}: {}", path.display(), err);
+                    handle::Handle::notice_message("import_sub_url::error", err.to_string());
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_import_content() {
+        assert!(matches!(
+            detect("https://example.com/sub?token=1"),
+            Ok(Detected::Subscriptions(urls)) if urls.len() == 1
+        ));
+        assert!(matches!(
+            detect("clash://install-config?url=https%3A%2F%2Fexample.com%2Fsub"),
+            Ok(Detected::Subscriptions(urls)) if urls[0] == "https://example.com/sub"
+        ));
+        assert!(matches!(
+            detect("clash://install-config?xurl=https%3A%2F%2Fevil.example%2Fsub&url=https%3A%2F%2Fexample.com%2Fsub"),
+            Ok(Detected::Subscriptions(urls)) if urls[0] == "https://example.com/sub"
+        ));
+        assert!(matches!(
+            detect("proxies: []\nrules:\n  - MATCH,DIRECT"),
+            Ok(Detected::Yaml(_))
+        ));
+        assert!(matches!(
+            detect("trojan://pw@t.example.com:443#T"),
+            Ok(Detected::ShareLinks(proxies)) if proxies.len() == 1
+        ));
+        assert!(detect("hello world").is_err());
+    }
+
+    #[test]
+    fn test_build_share_link_profile() {
+        let proxies = sharelink::parse_share_links("trojan://pw@t.example.com:443#T");
+        let yaml = converter::build_profile(proxies).unwrap_or_default();
+        assert!(is_clash_yaml(&yaml));
+        assert!(yaml.contains("MATCH,PROXY"));
+    }
+}
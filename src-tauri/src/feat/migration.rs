@@ -0,0 +1,233 @@
+//! 与上游 clash-verge-rev 之间的数据迁移
+//!
+//! 导出时生成上游可直接使用的数据目录，本分支独有的设置单独写入
+//! `verge-xpp.yaml`，上游会忽略该文件；导入时再把这些设置合并回来。
+
+use crate::{
+    config::{Config, IClashTemp, IProfiles, IVerge},
+    constants::files::DNS_CONFIG,
+    core::{CoreManager, secrets},
+    process::AsyncHandler,
+    utils::{
+        dirs::{self, CLASH_CONFIG, PROFILE_YAML, VERGE_CONFIG},
+        help,
+    },
+};
+use anyhow::{Result, bail};
+use clash_verge_logging::{Type, logging};
+use serde_yaml_ng::Mapping;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+/// 本分支独有的 verge 设置，导出到上游时剥离
+const FORK_VERGE_KEYS: &[&str] = &[
+    "enable_discord_rpc",
+    "discord_app_id",
+    "enable_connection_log",
+    "enable_kill_switch",
+    "custom_cores",
+    "enable_profile_health_delay_test",
+    "geo_mirrors",
+    "sysproxy_interfaces",
+    "enable_exit_ip_monitor",
+    "exit_ip_monitor_interval",
+    "schedules",
+    "network_rules",
+    "tray_icon_theme",
+    "notification_prefs",
+    "webhooks",
+    "enable_telegram_notify",
+    "telegram_bot_token",
+    "telegram_chat_id",
+    "enable_telegram_commands",
+    "backup_password",
+    "enable_webdav_backup_schedule",
+    "enable_lan_sync",
+    "lan_sync_port",
+    "lan_sync_device_id",
+    "lan_sync_peers",
+    "lan_sync_identity",
+    "enable_control_api",
+    "control_api_port",
+    "control_api_token",
+    "app_log_format",
+    "crash_report_url",
+    "update_channel",
+    "update_deferred_version",
+    "update_deferred_until",
+    "restrict_controller_cors",
+    "favorites",
+    "discord_state_template",
+    "selection_per_network",
+    "speedtest_servers",
+    "enable_winhttp_proxy_sync",
+    "linux_proxy_backend",
+    "auto_launch_delay",
+    "enable_tray_memory",
+    "core_memory_warn_mb",
+    "core_priority",
+    "core_cpu_affinity",
+];
+pub const FORK_SETTINGS_FILE: &str = "verge-xpp.yaml";
+
+/// 与上游格式一致、可直接互相迁移的文件
+const SHARED_FILES: [&str; 4] = [CLASH_CONFIG, PROFILE_YAML, DNS_CONFIG, ".encryption_key"];
+const SHARED_DIRS: [&str; 2] = ["profiles", "icons"];
+
+/// 从 verge 配置中移除本分支独有的键，返回被移除的部分
+pub fn split_fork_settings(verge: &mut Mapping) -> Mapping {
+    let mut fork = Mapping::new();
+    for &key in FORK_VERGE_KEYS {
+        if let Some(value) = verge.remove(key) {
+            fork.insert(key.into(), value);
+        }
+    }
+    fork
+}
+
+/// 把本分支设置合并回 verge 配置，忽略未知的键
+pub fn merge_fork_settings(verge: &mut Mapping, fork: Mapping) {
+    for (key, value) in fork {
+        if key.as_str().is_some_and(|key| FORK_VERGE_KEYS.contains(&key)) {
+            verge.insert(key, value);
+        }
+    }
+}
+
+fn copy_dir_all(src: &Path, dst: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(dst)?;
+    for entry in std::fs::read_dir(src)? {
+        let entry = entry?;
+        let target = dst.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir_all(&entry.path(), &target)?;
+        } else {
+            std::fs::copy(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
+
+async fn copy_shared_data(src: PathBuf, dst: PathBuf) -> Result<()> {
+    AsyncHandler::spawn_blocking(move || -> std::io::Result<()> {
+        std::fs::create_dir_all(&dst)?;
+        for file in SHARED_FILES {
+            let path = src.join(file);
+            if path.is_file() {
+                std::fs::copy(path, dst.join(file))?;
+            }
+        }
+        for dir in SHARED_DIRS {
+            let path = src.join(dir);
+            if path.is_dir() {
+                copy_dir_all(&path, &dst.join(dir))?;
+            }
+        }
+        Ok(())
+    })
+    .await??;
+    Ok(())
+}
+
+async fn write_mapping(path: &Path, mapping: &Mapping) -> Result<()> {
+    fs::write(path, serde_yaml_ng::to_string(mapping)?).await?;
+    Ok(())
+}
+
+/// 导出为上游兼容的数据目录，目标目录必须为空
+pub async fn export_upstream_data(target: &Path) -> Result<()> {
+    if target.exists() && std::fs::read_dir(target)?.next().is_some() {
+        bail!("target directory is not empty: {}", target.display());
+    }
+    Config::apply_all_and_save_file().await;
+
+    copy_shared_data(dirs::app_home_dir()?, target.to_path_buf()).await?;
+
+    // 文件中的敏感字段可能只是本机凭据库的引用，改为写出内存中的原值
+    write_mapping(&target.join(CLASH_CONFIG), &Config::clash().await.latest_arc().0).await?;
+    let profiles = Config::profiles().await.latest_arc();
+    help::save_yaml(
+        &target.join(PROFILE_YAML),
+        &*profiles,
+        Some("# Profiles Config for Clash Verge"),
+    )
+    .await?;
+    let mut verge = secrets::resolved_verge_mapping().await?;
+    let fork = split_fork_settings(&mut verge);
+    write_mapping(&target.join(VERGE_CONFIG), &verge).await?;
+    if !fork.is_empty() {
+        write_mapping(&target.join(FORK_SETTINGS_FILE), &fork).await?;
+    }
+
+    logging!(info, Type::Backup, "Exported upstream data dir to {}", target.display());
+    Ok(())
+}
+
+/// 从上游（或本分支导出的）数据目录导入，导入前自动创建本地备份
+pub async fn import_upstream_data(source: &Path) -> Result<()> {
+    let source_verge = source.join(VERGE_CONFIG);
+    if !source.join(PROFILE_YAML).is_file() || !source_verge.is_file() {
+        bail!("not a clash-verge data directory: {}", source.display());
+    }
+
+    let backup = super::create_local_backup_with_namer(|name| format!("pre-migration-{name}").into()).await?;
+    logging!(info, Type::Backup, "Created backup {} before migration", backup);
+
+    // 上游目录不含本分支设置时沿用当前值
+    let fork = match help::read_mapping(&source.join(FORK_SETTINGS_FILE)).await {
+        Ok(fork) => fork,
+        Err(_) => split_fork_settings(&mut help::read_mapping(&dirs::verge_path()?).await?),
+    };
+    let mut verge = help::read_mapping(&source_verge).await?;
+    merge_fork_settings(&mut verge, fork);
+
+    copy_shared_data(source.to_path_buf(), dirs::app_home_dir()?).await?;
+    write_mapping(&dirs::verge_path()?, &verge).await?;
+
+    reload_from_disk().await
+}
+
+/// 用磁盘上的文件替换内存中的配置，避免退出时被旧数据覆盖
+pub(crate) async fn reload_from_disk() -> Result<()> {
+    let clash = IClashTemp::new().await;
+    Config::clash().await.edit_draft(|d| *d = clash);
+    Config::clash().await.apply();
+
+    let profiles = IProfiles::new().await;
+    Config::profiles().await.edit_draft(|d| *d = profiles);
+    Config::profiles().await.apply();
+
+    let verge = IVerge::new().await;
+    Config::verge().await.edit_draft(|d| *d = verge.clone());
+    Config::verge().await.apply();
+    if let Err(err) = super::patch_verge(&verge, true).await {
+        logging!(error, Type::Backup, "Failed to apply migrated verge config: {err:#?}");
+    }
+
+    Config::generate().await?;
+    CoreManager::global().restart_core().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_yaml_ng::Value;
+
+    #[test]
+    fn test_split_and_merge_fork_settings() {
+        let mut verge = Mapping::new();
+        verge.insert("theme_mode".into(), "dark".into());
+        verge.insert("enable_discord_rpc".into(), Value::Bool(true));
+
+        let fork = split_fork_settings(&mut verge);
+        assert!(!verge.contains_key("enable_discord_rpc"));
+        assert!(verge.contains_key("theme_mode"));
+        assert_eq!(fork.len(), 1);
+
+        let mut extra = fork.clone();
+        extra.insert("unknown_key".into(), Value::Bool(true));
+        merge_fork_settings(&mut verge, extra);
+        assert_eq!(verge.get("enable_discord_rpc"), Some(&Value::Bool(true)));
+        assert!(!verge.contains_key("unknown_key"));
+    }
+}
@@ -1,14 +1,72 @@
+mod app_home;
+mod app_routing;
+mod autostart;
 mod backup;
+mod chain;
 mod clash;
 mod config;
+mod core_memory;
+mod diagnostics;
+mod dns;
+mod doctor;
+mod exit_ip;
+mod exposure;
+mod favorites;
+mod health;
+mod import;
+mod lan_share;
+mod migration;
+mod outbounds;
+mod pac;
+mod ports;
+mod probe;
 mod profile;
+mod profile_test;
+mod providers;
 mod proxy;
+mod quota;
+mod region;
+mod rule_provider;
+mod rules;
+mod settings_lock;
+mod setup;
+mod speedtest;
+mod tun;
 mod window;
 
 // Re-export all functions from modules
+pub use app_home::*;
+pub use app_routing::*;
+pub use autostart::*;
 pub use backup::*;
+pub use chain::*;
 pub use clash::*;
 pub use config::*;
+pub use core_memory::*;
+pub use diagnostics::*;
+pub use dns::*;
+pub use doctor::*;
+pub use exit_ip::*;
+pub use exposure::*;
+pub use favorites::*;
+pub use health::*;
+pub use import::*;
+pub use lan_share::*;
+pub use migration::*;
+pub use outbounds::*;
+pub use pac::*;
+pub use ports::*;
+pub use probe::*;
 pub use profile::*;
+pub use profile_test::*;
+pub use providers::*;
 pub use proxy::*;
+pub use quota::*;
+pub use region::*;
+pub use rule_provider::*;
+pub use rules::*;
+pub use settings_lock::*;
+pub use setup::*;
+pub use speedtest::*;
+pub use tun::*;
 pub use window::*;
@@ -0,0 +1,157 @@
+//! 负载均衡与自动测速组实际使用的出站
+//!
+//! 负载均衡组没有固定的当前节点；自动测速与故障转移组切换后，之前建立的连接仍走原来的节点。
+//! 这里按活动连接的代理链统计经过某个组的连接最终使用的节点，没有活动连接时退回该组的当前选择，
+//! 供界面与 Discord 状态显示。
+
+use crate::core::handle;
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::Value;
+use smartstring::alias::String;
+use tauri_plugin_mihomo::models::{Connections, Proxies};
+
+/// 当前节点不能代表实际出站的组类型
+const BALANCED_TYPES: [&str; 3] = ["LoadBalance", "URLTest", "Fallback"];
+/// 沿当前选择解析的最大深度，防止组之间循环引用
+const MAX_DEPTH: usize = 10;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EffectiveOutbound {
+    pub name: String,
+    pub connections: usize,
+    pub upload: u64,
+    pub download: u64,
+}
+
+fn group_type(proxies: &Proxies, name: &str) -> Option<std::string::String> {
+    let group = proxies.proxies.get(name)?;
+    let value = serde_json::to_value(group).ok()?;
+    value.get("type").and_then(Value::as_str).map(ToOwned::to_owned)
+}
+
+/// 从 `group` 沿当前选择向下，第一个负载均衡 / 自动测速 / 故障转移组
+pub fn balanced_group(proxies: &Proxies, group: &str) -> Option<String> {
+    let mut current: String = group.into();
+    for _ in 0..MAX_DEPTH {
+        if group_type(proxies, &current).is_some_and(|kind| BALANCED_TYPES.contains(&kind.as_str())) {
+            return Some(current);
+        }
+        current = proxies.proxies.get(current.as_str())?.now.as_deref()?.into();
+    }
+    None
+}
+
+/// 沿当前选择解析到的最终节点
+fn resolve_now(proxies: &Proxies, group: &str) -> Option<String> {
+    let mut current: String = proxies.proxies.get(group)?.now.as_deref()?.into();
+    for _ in 0..MAX_DEPTH {
+        match proxies
+            .proxies
+            .get(current.as_str())
+            .and_then(|proxy| proxy.now.as_deref())
+        {
+            Some(next) => current = next.into(),
+            None => break,
+        }
+    }
+    Some(current)
+}
+
+/// 代理链从最终节点排到最外层的组，经过 `group` 的连接取链上的第一个元素
+fn count_outbounds(connections: &[Value], group: &str) -> Vec<EffectiveOutbound> {
+    let mut outbounds: Vec<EffectiveOutbound> = Vec::new();
+    for conn in connections {
+        let Some(chains) = conn.get("chains").and_then(Value::as_array) else {
+            continue;
+        };
+        if !chains.iter().any(|name| name.as_str() == Some(group)) {
+            continue;
+        }
+        let Some(name) = chains.first().and_then(Value::as_str) else {
+            continue;
+        };
+        let upload = conn.get("upload").and_then(Value::as_u64).unwrap_or_default();
+        let download = conn.get("download").and_then(Value::as_u64).unwrap_or_default();
+        if let Some(outbound) = outbounds.iter_mut().find(|o| o.name == name) {
+            outbound.connections += 1;
+            outbound.upload += upload;
+            outbound.download += download;
+        } else {
+            outbounds.push(EffectiveOutbound {
+                name: name.into(),
+                connections: 1,
+                upload,
+                download,
+            });
+        }
+    }
+    outbounds.sort_by(|a, b| b.connections.cmp(&a.connections));
+    outbounds
+}
+
+/// 按连接数从多到少排列；没有经过该组的连接时返回其当前选择
+pub fn effective_outbounds(proxies: &Proxies, connections: &Connections, group: &str) -> Vec<EffectiveOutbound> {
+    let values: Vec<Value> = connections
+        .connections
+        .iter()
+        .flatten()
+        .filter_map(|conn| serde_json::to_value(conn).ok())
+        .collect();
+    let outbounds = count_outbounds(&values, group);
+    if !outbounds.is_empty() {
+        return outbounds;
+    }
+    resolve_now(proxies, group)
+        .map(|name| EffectiveOutbound {
+            name,
+            connections: 0,
+            upload: 0,
+            download: 0,
+        })
+        .into_iter()
+        .collect()
+}
+
+pub async fn get_effective_outbounds(group: &str) -> Result<Vec<EffectiveOutbound>> {
+    let mihomo = handle::Handle::mihomo().await;
+    let proxies = mihomo.get_proxies().await?;
+    let connections = mihomo.get_connections().await?;
+    drop(mihomo);
+    Ok(effective_outbounds(&proxies, &connections, group))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_count_outbounds() {
+        let connections = vec![
+            json!({ "chains": ["hk-01", "Balance", "Proxy"], "upload": 10, "download": 100 }),
+            json!({ "chains": ["hk-02", "Balance", "Proxy"], "upload": 1, "download": 1 }),
+            json!({ "chains": ["hk-01", "Balance", "Proxy"], "upload": 5, "download": 50 }),
+            json!({ "chains": ["DIRECT"], "upload": 1, "download": 1 }),
+        ];
+        let outbounds = count_outbounds(&connections, "Balance");
+        assert_eq!(
+            outbounds,
+            vec![
+                EffectiveOutbound {
+                    name: "hk-01".into(),
+                    connections: 2,
+                    upload: 15,
+                    download: 150,
+                },
+                EffectiveOutbound {
+                    name: "hk-02".into(),
+                    connections: 1,
+                    upload: 1,
+                    download: 1,
+                },
+            ]
+        );
+        assert!(count_outbounds(&connections, "Auto").is_empty());
+    }
+}
@@ -0,0 +1,82 @@
+//! PAC 模式
+//!
+//! 内置服务器在 `/commands/pac` 提供由模板生成的 PAC 文件，模板支持以下占位符：
+//! `%mixed-port%`（混合代理端口）与 `%proxy_host%`（系统代理使用的主机）。
+
+use crate::config::{Config, IVerge};
+use anyhow::{Result, bail};
+use smartstring::alias::String;
+
+const MAX_PAC_TEMPLATE_SIZE: usize = 1024 * 1024;
+
+/// 替换模板中的占位符
+pub fn render_pac(template: &str, host: &str, port: u16) -> String {
+    template
+        .replace("%mixed-port%", &port.to_string())
+        .replace("%proxy_host%", host)
+        .into()
+}
+
+/// 按当前配置生成 PAC 文件内容
+pub async fn current_pac() -> String {
+    let verge = Config::verge().await.data_arc();
+    let template = verge
+        .pac_file_content
+        .clone()
+        .unwrap_or_else(|| crate::config::DEFAULT_PAC.into());
+    let host = verge.proxy_host.clone().unwrap_or_else(|| "127.0.0.1".into());
+    let port = Config::mixed_port().await;
+    render_pac(&template, &host, port)
+}
+
+/// 开启或关闭 PAC 模式，开启后系统代理改为使用 PAC 地址
+pub async fn enable_pac_mode(enable: bool) -> Result<()> {
+    super::patch_verge(
+        &IVerge {
+            proxy_auto_config: Some(enable),
+            ..IVerge::default()
+        },
+        false,
+    )
+    .await
+}
+
+fn validate_pac_template(template: &str) -> Result<()> {
+    if template.len() > MAX_PAC_TEMPLATE_SIZE {
+        bail!("PAC template is too large");
+    }
+    if !template.contains("FindProxyForURL") {
+        bail!("PAC template must define FindProxyForURL(url, host)");
+    }
+    Ok(())
+}
+
+/// 保存 PAC 模板
+pub async fn set_pac_template(template: String) -> Result<()> {
+    validate_pac_template(&template)?;
+    super::patch_verge(
+        &IVerge {
+            pac_file_content: Some(template),
+            ..IVerge::default()
+        },
+        false,
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_pac() {
+        let pac = render_pac(crate::config::DEFAULT_PAC, "127.0.0.1", 7897);
+        assert!(pac.contains("PROXY 127.0.0.1:7897; SOCKS5 127.0.0.1:7897"));
+
+        let pac = render_pac("return \"PROXY %proxy_host%:%mixed-port%\";", "192.168.1.2", 7890);
+        assert_eq!(pac, "return \"PROXY 192.168.1.2:7890\";");
+
+        assert!(validate_pac_template("function FindProxyForURL(url, host) { return \"DIRECT\"; }").is_ok());
+        assert!(validate_pac_template("return \"DIRECT\";").is_err());
+    }
+}
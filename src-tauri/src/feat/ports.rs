@@ -0,0 +1,391 @@
+//! 端口冲突检测与自动分配
+//!
+//! 检查混合 / SOCKS / HTTP / 透明代理端口与外部控制端口是否已被其他程序占用，
+//! 并通过 `ss`（Linux）、`lsof`（macOS）或 `netstat` + `tasklist`（Windows）找出占用的进程。
+//! 内核自身占用的端口不算冲突。
+
+use crate::{
+    config::{Config, IVerge, PrfRuntime},
+    core::{
+        CoreManager, handle,
+        manager::{RunningMode, core_binary_path},
+        sysopt,
+    },
+    process::AsyncHandler,
+};
+use anyhow::{Result, bail};
+use clash_verge_logging::{Type, logging};
+use port_scanner::local_port_available;
+use serde::{Deserialize, Serialize};
+use serde_yaml_ng::{Mapping, Value};
+use smartstring::alias::String;
+use std::process::Command;
+use tauri::Emitter as _;
+
+const PORT_CONFLICT_EVENT: &str = "port-conflict";
+/// 自动分配时向后查找空闲端口的次数
+const MAX_PORT_PROBES: u16 = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PortRole {
+    Mixed,
+    Socks,
+    Http,
+    Redir,
+    Tproxy,
+    Controller,
+}
+
+impl PortRole {
+    const fn clash_key(self) -> &'static str {
+        match self {
+            Self::Mixed => "mixed-port",
+            Self::Socks => "socks-port",
+            Self::Http => "port",
+            Self::Redir => "redir-port",
+            Self::Tproxy => "tproxy-port",
+            Self::Controller => "external-controller",
+        }
+    }
+
+    /// 外部控制端口只保存在 Clash 配置中
+    const fn set_verge_port(self, verge: &mut IVerge, port: u16) {
+        match self {
+            Self::Mixed => verge.verge_mixed_port = Some(port),
+            Self::Socks => verge.verge_socks_port = Some(port),
+            Self::Http => verge.verge_port = Some(port),
+            Self::Redir => verge.verge_redir_port = Some(port),
+            Self::Tproxy => verge.verge_tproxy_port = Some(port),
+            Self::Controller => {}
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PortUsage {
+    pub role: PortRole,
+    pub port: u16,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PortConflict {
+    pub role: PortRole,
+    pub port: u16,
+    /// 占用端口的进程，无法查询时为 `None`
+    pub process: Option<String>,
+    pub pid: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PortReassignment {
+    pub role: PortRole,
+    pub from: u16,
+    pub to: u16,
+}
+
+pub(super) fn run(program: &str, args: &[&str]) -> Option<std::string::String> {
+    let mut command = Command::new(program);
+    command.args(args);
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::process::CommandExt as _;
+        command.creation_flags(0x08000000);
+    }
+    let output = command.output().ok()?;
+    Some(std::string::String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// 解析 `ss -Hltnp` 输出中的 `users:(("name",pid=123,fd=7))`
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_ss_owner(output: &str) -> Option<(String, u32)> {
+    let users = &output[output.find("users:((\"")? + 9..];
+    let name = &users[..users.find('"')?];
+    let pid = &users[users.find("pid=")? + 4..];
+    let pid = pid[..pid.find(|c: char| !c.is_ascii_digit())?].parse().ok()?;
+    Some((name.into(), pid))
+}
+
+/// 解析 `lsof -F pc` 输出，`p` 开头为进程号，`c` 开头为进程名
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+fn parse_lsof_owner(output: &str) -> Option<(String, u32)> {
+    let pid = output.lines().find_map(|line| line.strip_prefix('p')?.parse().ok())?;
+    let name = output.lines().find_map(|line| line.strip_prefix('c'))?;
+    Some((name.into(), pid))
+}
+
+/// 在 `netstat -ano -p TCP` 输出中查找监听指定端口的进程号
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+fn parse_netstat_pid(output: &str, port: u16) -> Option<u32> {
+    let suffix = format!(":{port}");
+    output.lines().find_map(|line| {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        match fields.as_slice() {
+            [_, local, _, state, pid] if local.ends_with(&suffix) && *state == "LISTENING" => pid.parse().ok(),
+            _ => None,
+        }
+    })
+}
+
+#[cfg(target_os = "linux")]
+fn port_owner(port: u16) -> Option<(String, u32)> {
+    parse_ss_owner(&run("ss", &["-Hltnp", &format!("sport = :{port}")])?)
+}
+
+#[cfg(target_os = "macos")]
+fn port_owner(port: u16) -> Option<(String, u32)> {
+    parse_lsof_owner(&run(
+        "lsof",
+        &["-nP", &format!("-iTCP:{port}"), "-sTCP:LISTEN", "-Fpc"],
+    )?)
+}
+
+#[cfg(target_os = "windows")]
+fn port_owner(port: u16) -> Option<(String, u32)> {
+    let pid = parse_netstat_pid(&run("netstat", &["-ano", "-p", "TCP"])?, port)?;
+    let output = run("tasklist", &["/FI", &format!("PID eq {pid}"), "/FO", "CSV", "/NH"])?;
+    let name = output.split(',').next()?.trim().trim_matches('"');
+    (!name.is_empty()).then(|| (name.into(), pid))
+}
+
+/// 当前配置使用的端口，未启用的端口不参与检查
+pub async fn configured_ports() -> Vec<PortUsage> {
+    let verge = Config::verge().await.latest_arc();
+    let clash = Config::clash().await.latest_arc();
+    let mut ports = vec![PortUsage {
+        role: PortRole::Mixed,
+        port: Config::mixed_port().await,
+    }];
+    if verge.verge_socks_enabled.unwrap_or_default()
+        && let Some(port) = verge.verge_socks_port
+    {
+        ports.push(PortUsage {
+            role: PortRole::Socks,
+            port,
+        });
+    }
+    if verge.verge_http_enabled.unwrap_or_default()
+        && let Some(port) = verge.verge_port
+    {
+        ports.push(PortUsage {
+            role: PortRole::Http,
+            port,
+        });
+    }
+    #[cfg(not(target_os = "windows"))]
+    if verge.verge_redir_enabled.unwrap_or_default()
+        && let Some(port) = verge.verge_redir_port
+    {
+        ports.push(PortUsage {
+            role: PortRole::Redir,
+            port,
+        });
+    }
+    #[cfg(target_os = "linux")]
+    if verge.verge_tproxy_enabled.unwrap_or_default()
+        && let Some(port) = verge.verge_tproxy_port
+    {
+        ports.push(PortUsage {
+            role: PortRole::Tproxy,
+            port,
+        });
+    }
+    if let Some(port) = clash
+        .0
+        .get("external-controller")
+        .and_then(Value::as_str)
+        .and_then(|addr| addr.rsplit_once(':'))
+        .and_then(|(_, port)| port.parse().ok())
+    {
+        ports.push(PortUsage {
+            role: PortRole::Controller,
+            port,
+        });
+    }
+    ports
+}
+
+/// 内核或应用自身的进程
+async fn is_own_process(name: &str) -> bool {
+    let verge = Config::verge().await.latest_arc();
+    let name = name.trim_end_matches(".exe");
+    let own_core = core_binary_path(&verge, &verge.get_valid_clash_core())
+        .ok()
+        .and_then(|path| path.file_stem().map(|stem| stem.to_string_lossy().into_owned()));
+    own_core.as_deref() == Some(name)
+        || name.starts_with("verge-mihomo")
+        || name.starts_with("clash-verge")
+        || std::env::current_exe()
+            .ok()
+            .and_then(|exe| exe.file_stem().map(|stem| stem.to_string_lossy() == name))
+            .unwrap_or_default()
+}
+
+/// 检查端口是否被其他程序占用；内核运行中且无法确定占用者时视为内核自身
+pub async fn check_port_conflicts(ports: &[PortUsage]) -> Vec<PortConflict> {
+    let core_running = !matches!(*CoreManager::global().get_running_mode(), RunningMode::NotRunning);
+    let mut conflicts = Vec::new();
+    for usage in ports {
+        if local_port_available(usage.port) {
+            continue;
+        }
+        let port = usage.port;
+        let owner = AsyncHandler::spawn_blocking(move || port_owner(port))
+            .await
+            .ok()
+            .flatten();
+        let is_own = match &owner {
+            Some((name, _)) => is_own_process(name).await,
+            None => core_running,
+        };
+        if is_own {
+            continue;
+        }
+        let (process, pid) = owner.unzip();
+        conflicts.push(PortConflict {
+            role: usage.role,
+            port: usage.port,
+            process,
+            pid,
+        });
+    }
+    conflicts
+}
+
+/// 检查订阅覆盖的混合端口是否与其他已启用的端口重复，`check_in_use` 时还检查是否被其他程序占用
+pub async fn check_profile_ports(runtime: &PrfRuntime, check_in_use: bool) -> Result<()> {
+    let Some(port) = runtime.mixed_port else {
+        return Ok(());
+    };
+    let ports = configured_ports().await;
+    if let Some(usage) = ports
+        .iter()
+        .find(|usage| usage.role != PortRole::Mixed && usage.port == port)
+    {
+        bail!("mixed port {port} conflicts with the {:?} port", usage.role);
+    }
+    let usage = PortUsage {
+        role: PortRole::Mixed,
+        port,
+    };
+    if check_in_use && let Some(conflict) = check_port_conflicts(&[usage]).await.into_iter().next() {
+        bail!(
+            "mixed port {port} is in use by {}",
+            conflict.process.as_deref().unwrap_or("another program")
+        );
+    }
+    Ok(())
+}
+
+/// 从 `start` 之后查找未被占用且不在 `taken` 中的端口
+fn find_free_port(start: u16, taken: &[u16]) -> Option<u16> {
+    (1..=MAX_PORT_PROBES)
+        .filter_map(|offset| start.checked_add(offset))
+        .find(|port| !taken.contains(port) && local_port_available(*port))
+}
+
+/// 为冲突的端口分配空闲端口，更新配置后重启内核并刷新系统代理
+pub async fn auto_reassign_ports() -> Result<Vec<PortReassignment>> {
+    let ports = configured_ports().await;
+    let conflicts = check_port_conflicts(&ports).await;
+    if conflicts.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let proxy_host = Config::verge()
+        .await
+        .latest_arc()
+        .proxy_host
+        .clone()
+        .unwrap_or_else(|| "127.0.0.1".into());
+    let mut taken: Vec<u16> = ports.iter().map(|usage| usage.port).collect();
+    let mut clash_patch = Mapping::new();
+    let mut verge_patch = IVerge::default();
+    let mut changes = Vec::new();
+    for conflict in &conflicts {
+        let Some(port) = find_free_port(conflict.port, &taken) else {
+            bail!("no free port found after {}", conflict.port);
+        };
+        taken.push(port);
+        let value: Value = if conflict.role == PortRole::Controller {
+            format!("{proxy_host}:{port}").into()
+        } else {
+            port.into()
+        };
+        clash_patch.insert(conflict.role.clash_key().into(), value);
+        conflict.role.set_verge_port(&mut verge_patch, port);
+        changes.push(PortReassignment {
+            role: conflict.role,
+            from: conflict.port,
+            to: port,
+        });
+    }
+
+    Config::clash().await.edit_draft(|d| d.patch_config(&clash_patch));
+    Config::clash().await.apply();
+    Config::clash().await.data_arc().save_config().await?;
+
+    let verge_changed = changes.iter().any(|c| c.role != PortRole::Controller);
+    if verge_changed {
+        // 端口变更会重新生成配置并重启内核
+        super::patch_verge(&verge_patch, false).await?;
+    } else {
+        Config::generate().await?;
+        CoreManager::global().restart_core().await?;
+    }
+    sysopt::Sysopt::global().update_sysproxy().await?;
+    handle::Handle::refresh_clash();
+    handle::Handle::refresh_verge();
+
+    for change in &changes {
+        logging!(
+            info,
+            Type::Network,
+            "Reassigned {:?} port {} -> {}",
+            change.role,
+            change.from,
+            change.to
+        );
+    }
+    Ok(changes)
+}
+
+/// 启动内核前检查端口，发现冲突时发送 `port-conflict` 事件
+pub async fn report_port_conflicts() {
+    let conflicts = check_port_conflicts(&configured_ports().await).await;
+    if conflicts.is_empty() {
+        return;
+    }
+    for conflict in &conflicts {
+        logging!(
+            warn,
+            Type::Network,
+            "Port {} ({:?}) is in use by {}",
+            conflict.port,
+            conflict.role,
+            conflict.process.as_deref().unwrap_or("unknown process")
+        );
+    }
+    let _ = handle::Handle::app_handle().emit(PORT_CONFLICT_EVENT, &conflicts);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_port_owner() {
+        let ss = "LISTEN 0 4096 127.0.0.1:7897 0.0.0.0:* users:((\"other-proxy\",pid=4242,fd=7))";
+        assert_eq!(parse_ss_owner(ss), Some(("other-proxy".into(), 4242)));
+        assert_eq!(parse_ss_owner("LISTEN 0 4096 127.0.0.1:7897 0.0.0.0:*"), None);
+
+        assert_eq!(parse_lsof_owner("p512\ncClashX\nf7\n"), Some(("ClashX".into(), 512)));
+
+        let netstat = "  Proto  Local Address  Foreign Address  State  PID\n  \
+                       TCP    0.0.0.0:135     0.0.0.0:0        LISTENING  900\n  \
+                       TCP    127.0.0.1:7897  0.0.0.0:0        LISTENING  3100\n  \
+                       TCP    127.0.0.1:50000 127.0.0.1:7897   ESTABLISHED  77\n";
+        assert_eq!(parse_netstat_pid(netstat, 7897), Some(3100));
+        assert_eq!(parse_netstat_pid(netstat, 9090), None);
+    }
+}
@@ -0,0 +1,304 @@
+//! TCP ping 与代理路径追踪
+//!
+//! 不依赖系统的 ping / traceroute 命令，也不需要原始套接字权限。`trace_route` 按代理路径分段测量：
+//! 本地 DNS 解析、到当前节点服务器的入口连接、经节点访问目标的完整延迟，用于判断慢在入口还是整条路径。
+//! 入口是单次 TCP 连接，完整延迟是内核的 HTTP(S) 测试，二者不可直接相减，因此不估算出口段。每个结果产生时通过事件推送，命令最终返回完整结果。
+
+use crate::{config::Config, core::handle};
+use anyhow::{Result, anyhow, bail};
+use serde::Serialize;
+use serde_yaml_ng::Value as YamlValue;
+use smartstring::alias::String;
+use std::{
+    net::SocketAddr,
+    time::{Duration, Instant},
+};
+use tauri::Emitter as _;
+use tauri_plugin_mihomo::models::Proxies;
+use tokio::net::TcpStream;
+
+const PING_EVENT: &str = "tcp-ping-result";
+const TRACE_EVENT: &str = "trace-route-hop";
+const PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+const PING_INTERVAL: Duration = Duration::from_secs(1);
+const MAX_PING_COUNT: u32 = 20;
+const DEFAULT_PORT: u16 = 443;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PingResult {
+    pub host: String,
+    pub port: u16,
+    pub seq: u32,
+    pub rtt_ms: Option<u64>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PingReport {
+    pub address: String,
+    pub results: Vec<PingResult>,
+    pub sent: u32,
+    pub received: u32,
+    pub min_ms: Option<u64>,
+    pub avg_ms: Option<u64>,
+    pub max_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HopKind {
+    /// 本地 DNS 解析
+    Dns,
+    /// 不经代理直接连接目标
+    Direct,
+    /// 连接当前节点的服务器
+    Entry,
+    /// 经当前节点访问目标的完整延迟
+    Proxy,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TraceHop {
+    pub index: usize,
+    pub kind: HopKind,
+    pub target: String,
+    pub rtt_ms: Option<u64>,
+    pub error: Option<String>,
+}
+
+fn elapsed_ms(start: Instant) -> u64 {
+    u64::try_from(start.elapsed().as_millis()).unwrap_or(u64::MAX)
+}
+
+/// 拆分 `host:port` / `[v6]:port`，没有端口时使用 `default_port`
+fn split_host_port(target: &str, default_port: u16) -> Result<(String, u16)> {
+    let target = target.trim();
+    let target = target
+        .split_once("://")
+        .map_or(target, |(_, rest)| rest)
+        .split('/')
+        .next()
+        .unwrap_or_default();
+    if let Some(rest) = target.strip_prefix('[') {
+        let (host, port) = rest
+            .split_once(']')
+            .ok_or_else(|| anyhow!("invalid address: {target}"))?;
+        let port = match port.strip_prefix(':') {
+            Some(port) => port.parse()?,
+            None => default_port,
+        };
+        return Ok((host.into(), port));
+    }
+    match target.rsplit_once(':') {
+        // 不带方括号的 IPv6 地址整体作为主机名
+        Some((host, port)) if !host.contains(':') => Ok((host.into(), port.parse()?)),
+        _ if target.is_empty() => bail!("host is empty"),
+        _ => Ok((target.into(), default_port)),
+    }
+}
+
+async fn resolve(host: &str, port: u16) -> Result<SocketAddr> {
+    tokio::net::lookup_host((host, port))
+        .await?
+        .next()
+        .ok_or_else(|| anyhow!("failed to resolve {host}"))
+}
+
+async fn tcp_connect(addr: SocketAddr) -> Result<u64> {
+    let start = Instant::now();
+    tokio::time::timeout(PROBE_TIMEOUT, TcpStream::connect(addr))
+        .await
+        .map_err(|_| anyhow!("timed out"))??;
+    Ok(elapsed_ms(start))
+}
+
+/// 连续建立 TCP 连接测量延迟，每次结果通过 `tcp-ping-result` 事件推送
+pub async fn tcp_ping(host: &str, port: Option<u16>, count: Option<u32>) -> Result<PingReport> {
+    let (host, port) = split_host_port(host, port.unwrap_or(DEFAULT_PORT))?;
+    let count = count.unwrap_or(4).clamp(1, MAX_PING_COUNT);
+    let addr = resolve(&host, port).await?;
+
+    let mut results = Vec::new();
+    for seq in 1..=count {
+        if seq > 1 {
+            tokio::time::sleep(PING_INTERVAL).await;
+        }
+        let outcome = tcp_connect(addr).await;
+        let result = PingResult {
+            host: host.clone(),
+            port,
+            seq,
+            rtt_ms: outcome.as_ref().ok().copied(),
+            error: outcome.err().map(|err| err.to_string().into()),
+        };
+        let _ = handle::Handle::app_handle().emit(PING_EVENT, &result);
+        results.push(result);
+    }
+    Ok(summarize(addr.to_string().into(), results))
+}
+
+fn summarize(address: String, results: Vec<PingResult>) -> PingReport {
+    let rtts: Vec<u64> = results.iter().filter_map(|result| result.rtt_ms).collect();
+    let received = u32::try_from(rtts.len()).unwrap_or_default();
+    PingReport {
+        address,
+        sent: u32::try_from(results.len()).unwrap_or_default(),
+        received,
+        min_ms: rtts.iter().min().copied(),
+        avg_ms: rtts.iter().sum::<u64>().checked_div(u64::from(received)),
+        max_ms: rtts.iter().max().copied(),
+        results,
+    }
+}
+
+/// 从主代理组沿着选中项找到实际使用的节点
+fn current_node(proxies: &Proxies, mode: &str) -> Option<String> {
+    let mut name = super::main_group(proxies, mode, None)?;
+    // 嵌套层数有限，避免配置成环时死循环
+    for _ in 0..8 {
+        let entry = proxies.proxies.get(name)?;
+        if entry.all.is_none() {
+            return Some(name.into());
+        }
+        name = entry.now.as_deref()?;
+    }
+    None
+}
+
+/// 运行配置中节点的服务器地址与端口
+async fn node_address(node: &str) -> Option<(String, u16)> {
+    let runtime = Config::runtime().await.latest_arc();
+    let proxy = runtime
+        .config
+        .as_ref()?
+        .get("proxies")?
+        .as_sequence()?
+        .iter()
+        .find(|proxy| proxy.get("name").and_then(YamlValue::as_str) == Some(node))?;
+    let server = proxy.get("server")?.as_str()?;
+    let port = match proxy.get("port")? {
+        YamlValue::Number(port) => u16::try_from(port.as_u64()?).ok()?,
+        YamlValue::String(port) => port.parse().ok()?,
+        _ => return None,
+    };
+    Some((server.into(), port))
+}
+
+struct TraceRecorder {
+    hops: Vec<TraceHop>,
+}
+
+impl TraceRecorder {
+    fn push(&mut self, kind: HopKind, target: String, outcome: Result<u64>) {
+        let hop = TraceHop {
+            index: self.hops.len() + 1,
+            kind,
+            target,
+            rtt_ms: outcome.as_ref().ok().copied(),
+            error: outcome.err().map(|err| err.to_string().into()),
+        };
+        let _ = handle::Handle::app_handle().emit(TRACE_EVENT, &hop);
+        self.hops.push(hop);
+    }
+}
+
+/// 分段测量到目标的路径，每段结果通过 `trace-route-hop` 事件推送
+pub async fn trace_route(host: &str, via_proxy: bool) -> Result<Vec<TraceHop>> {
+    let (host, port) = split_host_port(host, DEFAULT_PORT)?;
+    let mut trace = TraceRecorder { hops: Vec::new() };
+
+    let start = Instant::now();
+    let resolved = resolve(&host, port).await;
+    let dns_target = resolved
+        .as_ref()
+        .map_or_else(|_| host.clone(), |addr| addr.ip().to_string().into());
+    trace.push(
+        HopKind::Dns,
+        dns_target,
+        resolved
+            .as_ref()
+            .map(|_| elapsed_ms(start))
+            .map_err(|err| anyhow!("{err}")),
+    );
+
+    if !via_proxy {
+        let outcome = match resolved {
+            Ok(addr) => tcp_connect(addr).await,
+            Err(err) => Err(err),
+        };
+        trace.push(HopKind::Direct, format!("{host}:{port}").into(), outcome);
+        return Ok(trace.hops);
+    }
+
+    let proxies = handle::Handle::mihomo().await.get_proxies().await?;
+    let node =
+        current_node(&proxies, &super::clash_mode().await).ok_or_else(|| anyhow!("no proxy node is selected"))?;
+    drop(proxies);
+
+    match node_address(&node).await {
+        Some((server, server_port)) => {
+            let outcome = match resolve(&server, server_port).await {
+                Ok(addr) => tcp_connect(addr).await,
+                Err(err) => Err(err),
+            };
+            trace.push(
+                HopKind::Entry,
+                format!("{node} ({server}:{server_port})").into(),
+                outcome,
+            );
+        }
+        // DIRECT 等内置出站没有服务器地址
+        None => trace.push(HopKind::Entry, node.clone(), Err(anyhow!("node has no server address"))),
+    }
+
+    let scheme = if port == 80 { "http" } else { "https" };
+    let url = format!("{scheme}://{host}:{port}");
+    let timeout = u32::try_from(PROBE_TIMEOUT.as_millis()).unwrap_or(u32::MAX);
+    let outcome = handle::Handle::mihomo()
+        .await
+        .delay_proxy_by_name(&node, &url, timeout)
+        .await
+        .map_err(|err| anyhow!("{err}"))
+        .and_then(|result| match result.delay {
+            0 => Err(anyhow!("timed out")),
+            delay => Ok(u64::from(delay)),
+        });
+    trace.push(HopKind::Proxy, format!("{url} via {node}").into(), outcome);
+    Ok(trace.hops)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_host_port() {
+        let split = |target| split_host_port(target, 443).ok();
+        assert_eq!(split("example.com"), Some(("example.com".into(), 443)));
+        assert_eq!(split("example.com:8080"), Some(("example.com".into(), 8080)));
+        assert_eq!(split("https://example.com/path"), Some(("example.com".into(), 443)));
+        assert_eq!(split("[::1]:22"), Some(("::1".into(), 22)));
+        assert_eq!(split("::1"), Some(("::1".into(), 443)));
+        assert_eq!(split(""), None);
+    }
+
+    #[test]
+    fn test_summarize() {
+        let result = |rtt_ms| PingResult {
+            host: "example.com".into(),
+            port: 443,
+            seq: 1,
+            rtt_ms,
+            error: None,
+        };
+        let report = summarize(
+            "1.1.1.1:443".into(),
+            vec![result(Some(10)), result(None), result(Some(30))],
+        );
+        assert_eq!((report.sent, report.received), (3, 2));
+        assert_eq!(
+            (report.min_ms, report.avg_ms, report.max_ms),
+            (Some(10), Some(20), Some(30))
+        );
+    }
+}
@@ -1,12 +1,26 @@
 use crate::{
     cmd,
-    config::{Config, PrfItem, PrfOption, profiles::profiles_draft_update_item_safe},
-    core::{CoreManager, handle, tray},
+    config::{
+        Config, PrfItem, PrfLayer, PrfOption,
+        profiles::{profiles_draft_update_item_safe, profiles_save_file_safe, profiles_set_chain_safe},
+        versions,
+    },
+    core::{
+        CoreManager,
+        audit::AuditLog,
+        event_bus::{EventBus, StateEvent},
+        handle,
+        notify::{NotificationEvent, notify_event},
+        selection::SelectionMemory,
+    },
+    process::AsyncHandler,
+    utils::{dirs, network::ProxyType},
 };
-use anyhow::{Result, bail};
+use anyhow::{Result, anyhow, bail};
 use clash_verge_logging::{Type, logging, logging_error};
 use smartstring::alias::String;
 use tauri::Emitter as _;
+use tokio::fs;
 
 /// Toggle proxy profile
 pub async fn toggle_proxy_profile(profile_index: String) {
@@ -24,8 +38,12 @@ pub async fn switch_proxy_node(group_name: &str, proxy_name: &str) {
     {
         Ok(_) => {
             logging!(info, Type::Tray, "切换代理成功: {} -> {}", group_name, proxy_name);
+            SelectionMemory::global().record(group_name, proxy_name).await;
             let _ = handle::Handle::app_handle().emit("verge://refresh-proxy-config", ());
-            let _ = tray::Tray::global().update_menu().await;
+            EventBus::global().publish(StateEvent::NodeSwitched {
+                group: group_name.into(),
+                node: proxy_name.into(),
+            });
             return;
         }
         Err(err) => {
@@ -47,7 +65,11 @@ pub async fn switch_proxy_node(group_name: &str, proxy_name: &str) {
     {
         Ok(_) => {
             logging!(info, Type::Tray, "代理切换回退成功: {} -> {}", group_name, proxy_name);
-            let _ = tray::Tray::global().update_menu().await;
+            SelectionMemory::global().record(group_name, proxy_name).await;
+            EventBus::global().publish(StateEvent::NodeSwitched {
+                group: group_name.into(),
+                node: proxy_name.into(),
+            });
         }
         Err(err) => {
             logging!(
@@ -94,6 +116,17 @@ async fn should_update_profile(uid: &String, ignore_auto_update: bool) -> Result
     }
 }
 
+/// 先按订阅设置的方式下载，失败后改用其余方式：直连失败时经内核代理，经代理失败时直连
+fn fallback_proxy_types(configured: ProxyType) -> Vec<ProxyType> {
+    let mut order = vec![configured];
+    order.extend(
+        [ProxyType::Localhost, ProxyType::None, ProxyType::System]
+            .into_iter()
+            .filter(|proxy_type| *proxy_type != configured),
+    );
+    order
+}
+
 async fn perform_profile_update(
     uid: &String,
     url: &String,
@@ -113,67 +146,44 @@ async fn perform_profile_update(
         .cloned()
         .unwrap_or_else(|| String::from("UnKnown Profile"));
 
-    let mut last_err;
-
-    match PrfItem::from_url(url, None, None, merged_opt.as_ref()).await {
-        Ok(mut item) => {
-            logging!(info, Type::Config, "[订阅更新] 更新订阅配置成功");
-            profiles_draft_update_item_safe(uid, &mut item).await?;
-            return Ok(is_current);
-        }
-        Err(err) => {
-            logging!(
-                warn,
-                Type::Config,
-                "Warning: [订阅更新] 正常更新失败: {err}，尝试使用Clash代理更新"
-            );
-            last_err = err;
-        }
-    }
-
-    merged_opt.get_or_insert_with(PrfOption::default).self_proxy = Some(true);
-    merged_opt.get_or_insert_with(PrfOption::default).with_proxy = Some(false);
-
-    match PrfItem::from_url(url, None, None, merged_opt.as_ref()).await {
-        Ok(mut item) => {
-            logging!(info, Type::Config, "[订阅更新] 使用 Clash代理 更新订阅配置成功");
-            profiles_draft_update_item_safe(uid, &mut item).await?;
-            handle::Handle::notice_message("update_with_clash_proxy", profile_name);
-            drop(last_err);
-            return Ok(is_current);
-        }
-        Err(err) => {
-            logging!(
-                warn,
-                Type::Config,
-                "Warning: [订阅更新] 正常更新失败: {err}，尝试使用Clash代理更新"
-            );
-            last_err = err;
-        }
-    }
-
-    merged_opt.get_or_insert_with(PrfOption::default).self_proxy = Some(false);
-    merged_opt.get_or_insert_with(PrfOption::default).with_proxy = Some(true);
+    let mut last_err = None;
+    let configured = merged_opt.as_ref().map_or(ProxyType::None, PrfOption::proxy_type);
+    for proxy_type in fallback_proxy_types(configured) {
+        let opt = merged_opt.get_or_insert_with(PrfOption::default);
+        opt.self_proxy = Some(proxy_type == ProxyType::Localhost);
+        opt.with_proxy = Some(proxy_type == ProxyType::System);
 
-    match PrfItem::from_url(url, None, None, merged_opt.as_ref()).await {
-        Ok(mut item) => {
-            logging!(info, Type::Config, "[订阅更新] 使用 系统代理 更新订阅配置成功");
-            profiles_draft_update_item_safe(uid, &mut item).await?;
-            handle::Handle::notice_message("update_with_clash_proxy", profile_name);
-            drop(last_err);
-            return Ok(is_current);
-        }
-        Err(err) => {
-            logging!(
-                warn,
-                Type::Config,
-                "Warning: [订阅更新] 正常更新失败: {err}，尝试使用系统代理更新"
-            );
-            last_err = err;
+        match PrfItem::from_url(url, None, None, merged_opt.as_ref()).await {
+            Ok(mut item) => {
+                profiles_draft_update_item_safe(uid, &mut item).await?;
+                if proxy_type == configured {
+                    logging!(info, Type::Config, "[订阅更新] 更新订阅配置成功");
+                } else {
+                    logging!(info, Type::Config, "[订阅更新] 改用 {proxy_type:?} 更新订阅配置成功");
+                    if proxy_type != ProxyType::None {
+                        handle::Handle::notice_message("update_with_clash_proxy", profile_name);
+                    }
+                }
+                return Ok(is_current);
+            }
+            Err(err) => {
+                logging!(
+                    warn,
+                    Type::Config,
+                    "Warning: [订阅更新] 使用 {proxy_type:?} 更新失败: {err}"
+                );
+                last_err = Some(err);
+            }
         }
     }
 
-    handle::Handle::notice_message("update_failed_even_with_clash", format!("{profile_name} - {last_err}"));
+    let reason = last_err.map(|err| err.to_string()).unwrap_or_default();
+    notify_event(NotificationEvent::ProfileUpdateFailed {
+        name: &profile_name,
+        reason: &reason,
+    })
+    .await;
+    handle::Handle::notice_message("update_failed_even_with_clash", format!("{profile_name} - {reason}"));
     Ok(is_current)
 }
 
@@ -187,7 +197,18 @@ pub async fn update_profile(
     let url_opt = should_update_profile(uid, ignore_auto_update).await?;
 
     let should_refresh = match url_opt {
-        Some((url, opt)) => perform_profile_update(uid, &url, opt.as_ref(), option).await? && auto_refresh,
+        Some((url, opt)) => {
+            let is_current = perform_profile_update(uid, &url, opt.as_ref(), option).await?;
+            let health = super::check_profile_health(uid).await;
+            logging_error!(Type::Config, &health);
+            if health.is_ok_and(|h| h.reverted) {
+                return Ok(());
+            }
+            AuditLog::record("profile.update", Some(uid), None, None);
+            EventBus::global().publish(StateEvent::ProfileUpdated { uid: uid.clone() });
+            logging_error!(Type::Config, super::check_profile_quota(uid).await);
+            is_current && auto_refresh
+        }
         None => auto_refresh,
     };
 
@@ -197,6 +218,7 @@ pub async fn update_profile(
             Ok(_) => {
                 logging!(info, Type::Config, "[订阅更新] 更新成功");
                 handle::Handle::refresh_clash();
+                sample_delays_if_enabled(uid).await;
             }
             Err(err) => {
                 logging!(error, Type::Config, "[订阅更新] 更新失败: {}", err);
@@ -209,7 +231,91 @@ pub async fn update_profile(
     Ok(())
 }
 
+async fn sample_delays_if_enabled(uid: &String) {
+    let enabled = Config::verge()
+        .await
+        .latest_arc()
+        .enable_profile_health_delay_test
+        .unwrap_or(false);
+    if !enabled {
+        return;
+    }
+    let uid = uid.clone();
+    AsyncHandler::spawn(move || async move {
+        logging_error!(Type::Config, super::sample_profile_delays(&uid).await);
+    });
+}
+
 /// 增强配置
 pub async fn enhance_profiles() -> Result<(bool, String)> {
     crate::core::CoreManager::global().update_config().await
 }
+
+/// 将配置文件恢复到指定的历史版本，恢复前的内容同样会保存为一个版本
+pub async fn rollback_profile(uid: &String, version: &str) -> Result<()> {
+    let (file, is_current) = {
+        let profiles = Config::profiles().await.latest_arc();
+        let item = profiles.get_item(uid)?;
+        let file = item.file.clone().ok_or_else(|| anyhow!("profile {uid} has no file"))?;
+        (file, profiles.is_in_current_chain(uid))
+    };
+
+    let content = versions::read_profile_version(uid, version).await?;
+    let path = dirs::app_profiles_dir()?.join(file.as_str());
+    let previous = fs::read(&path).await?;
+    versions::snapshot_profile_file(uid, &path).await?;
+    fs::write(&path, content.as_bytes()).await?;
+    logging!(info, Type::Config, "[配置回滚] {} 已恢复到版本 {}", uid, version);
+
+    if is_current {
+        let (applied, msg) = CoreManager::global().update_config().await?;
+        if !applied {
+            fs::write(&path, previous).await?;
+            bail!("rolled back file was rejected by the core: {msg}");
+        }
+        handle::Handle::refresh_clash();
+    }
+    handle::Handle::notify_profile_changed(uid.clone());
+    Ok(())
+}
+
+/// 设置订阅的扩展链，`None` 恢复默认顺序；修改的是当前订阅时先用新的扩展链生成并验证运行配置，
+/// 内核接受后才保存
+pub async fn set_profile_chain(uid: &String, chain: Option<Vec<PrfLayer>>) -> Result<()> {
+    let is_current = Config::profiles().await.latest_arc().get_current() == Some(uid);
+    if !is_current {
+        return profiles_set_chain_safe(uid, chain).await;
+    }
+
+    if let Err(err) = Config::profiles().await.edit_draft(|d| d.set_item_chain(uid, chain)) {
+        Config::profiles().await.discard();
+        return Err(err);
+    }
+    match CoreManager::global().update_config().await {
+        Ok((true, _)) => {
+            Config::profiles().await.apply();
+            profiles_save_file_safe().await?;
+            handle::Handle::refresh_clash();
+            Ok(())
+        }
+        Ok((false, msg)) => {
+            Config::profiles().await.discard();
+            bail!("profile chain was rejected by the core: {msg}");
+        }
+        Err(err) => {
+            Config::profiles().await.discard();
+            Err(err)
+        }
+    }
+}
+
+/// 启用或停用扩展链中的某一层
+pub async fn toggle_profile_layer(uid: &String, layer_uid: &str, enabled: bool) -> Result<()> {
+    let mut chain = Config::profiles().await.latest_arc().get_item(uid)?.current_chain();
+    let layer = chain
+        .iter_mut()
+        .find(|layer| layer.uid == layer_uid)
+        .ok_or_else(|| anyhow!("{layer_uid} is not part of the chain of {uid}"))?;
+    layer.enabled = enabled;
+    set_profile_chain(uid, Some(chain)).await
+}
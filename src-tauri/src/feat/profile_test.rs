@@ -0,0 +1,395 @@
+//! 订阅试运行
+//!
+//! 切换前用临时内核验证订阅：以该订阅经 [`enhance::enhance_profile`]（合并、脚本与图层均已应用）
+//! 生成的配置单独启动一个 mihomo 进程，监听系统分配的空闲端口，关闭 TUN、DNS 监听与选择缓存，
+//! 并使用独立的临时数据目录（仅链接 GeoIP 等数据文件），代理集合、规则集合的下载与缓存不会写入
+//! 正在运行的内核的目录。先经其混合端口测试连通性，再通过其控制接口抽样测试节点延迟；
+//! 结束后停止进程并删除临时目录，当前流量不受影响。
+
+use super::NodeDelay;
+use crate::{
+    config::Config,
+    core::{backend::CoreBackend, manager::core_command},
+    enhance,
+    utils::{dirs, help},
+};
+use anyhow::{Result, bail};
+use clash_verge_logging::{Type, logging};
+use percent_encoding::{NON_ALPHANUMERIC, utf8_percent_encode};
+use reqwest::Client;
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+use serde_yaml_ng::{Mapping, Value};
+use smartstring::alias::String;
+use std::{
+    net::TcpListener,
+    path::Path,
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, Instant},
+};
+use tauri::async_runtime::Receiver;
+use tauri_plugin_shell::process::CommandEvent;
+
+const TEST_HOME: &str = "profile-test";
+const TEST_CONFIG: &str = "config.yaml";
+/// 从内核数据目录链接到临时目录的数据文件，缺失的文件由临时内核自行下载
+const DATA_FILES: [&str; 5] = ["geoip.dat", "geosite.dat", "Country.mmdb", "geoip.metadb", "ASN.mmdb"];
+const DEFAULT_TEST_URL: &str = "https://www.gstatic.com/generate_204";
+const DEFAULT_TEST_TIMEOUT_MS: u32 = 5000;
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(15);
+const POLL_INTERVAL: Duration = Duration::from_millis(300);
+/// 最多测试的节点数，按顺序均匀抽样
+const MAX_TESTED_NODES: usize = 20;
+/// 会占用固定端口、修改系统或要求认证的字段，试运行时移除
+const STRIPPED_KEYS: [&str; 10] = [
+    "port",
+    "socks-port",
+    "redir-port",
+    "tproxy-port",
+    "external-controller-unix",
+    "external-controller-pipe",
+    "external-controller-tls",
+    "external-ui",
+    "listeners",
+    "authentication",
+];
+/// 代理组与内置出站，延迟测试只测真实节点
+const NON_NODE_TYPES: [&str; 10] = [
+    "Selector",
+    "URLTest",
+    "Fallback",
+    "LoadBalance",
+    "Relay",
+    "Direct",
+    "Reject",
+    "RejectDrop",
+    "Pass",
+    "Compatible",
+];
+
+static RUNNING: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ProfileTestReport {
+    pub uid: String,
+    /// 临时内核是否成功启动
+    pub started: bool,
+    /// 经临时内核访问测试地址的耗时，失败时为 `None`
+    pub connectivity: Option<u32>,
+    /// 订阅中（含代理集合）的节点总数
+    pub nodes: usize,
+    pub reachable: usize,
+    pub delays: Vec<NodeDelay>,
+    pub error: Option<String>,
+    pub duration_ms: u64,
+}
+
+/// 由系统同时分配两个不同的空闲端口
+fn ephemeral_ports() -> Result<(u16, u16)> {
+    let first = TcpListener::bind("127.0.0.1:0")?;
+    let second = TcpListener::bind("127.0.0.1:0")?;
+    Ok((first.local_addr()?.port(), second.local_addr()?.port()))
+}
+
+/// 基于最终配置生成试运行配置
+fn sandbox_config(mut config: Mapping, mixed_port: u16, controller_port: u16, secret: &str) -> Mapping {
+    for key in STRIPPED_KEYS {
+        config.remove(key);
+    }
+    if let Some(dns) = config.get_mut("dns").and_then(Value::as_mapping_mut) {
+        dns.remove("listen");
+    }
+    config.insert("mixed-port".into(), mixed_port.into());
+    config.insert("allow-lan".into(), false.into());
+    config.insert("bind-address".into(), "127.0.0.1".into());
+    config.insert(
+        "external-controller".into(),
+        format!("127.0.0.1:{controller_port}").into(),
+    );
+    config.insert("secret".into(), secret.into());
+
+    let mut tun = Mapping::new();
+    tun.insert("enable".into(), false.into());
+    config.insert("tun".into(), tun.into());
+    let mut profile = Mapping::new();
+    profile.insert("store-selected".into(), false.into());
+    profile.insert("store-fake-ip".into(), false.into());
+    config.insert("profile".into(), profile.into());
+    config
+}
+
+/// 创建临时数据目录并链接数据文件，无法创建硬链接（如跨文件系统）时复制
+async fn prepare_home(home: &Path, test_home: &Path) -> Result<()> {
+    if test_home.exists() {
+        tokio::fs::remove_dir_all(test_home).await?;
+    }
+    tokio::fs::create_dir_all(test_home).await?;
+    for file in DATA_FILES {
+        let source = home.join(file);
+        if !source.exists() {
+            continue;
+        }
+        let target = test_home.join(file);
+        if tokio::fs::hard_link(&source, &target).await.is_err() {
+            tokio::fs::copy(&source, &target).await?;
+        }
+    }
+    Ok(())
+}
+
+/// 控制接口 `/proxies` 返回的真实节点名称
+fn node_names(proxies: &JsonValue) -> Vec<String> {
+    proxies
+        .get("proxies")
+        .and_then(JsonValue::as_object)
+        .map(|proxies| {
+            proxies
+                .iter()
+                .filter(|(_, proxy)| {
+                    proxy
+                        .get("type")
+                        .and_then(JsonValue::as_str)
+                        .is_some_and(|kind| !NON_NODE_TYPES.contains(&kind))
+                })
+                .map(|(name, _)| name.as_str().into())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// 等待控制接口可用，内核提前退出时返回最后一行输出
+async fn wait_ready(client: &Client, controller: &str, secret: &str, rx: &mut Receiver<CommandEvent>) -> Result<()> {
+    let deadline = Instant::now() + STARTUP_TIMEOUT;
+    let mut last_line = std::string::String::new();
+    loop {
+        while let Ok(event) = rx.try_recv() {
+            match event {
+                CommandEvent::Stdout(line) | CommandEvent::Stderr(line) => {
+                    last_line = std::string::String::from_utf8_lossy(&line).trim().to_owned();
+                }
+                CommandEvent::Error(err) => last_line = err,
+                CommandEvent::Terminated(_) => bail!("core exited: {last_line}"),
+                _ => {}
+            }
+        }
+        let ready = client
+            .get(format!("{controller}/version"))
+            .bearer_auth(secret)
+            .send()
+            .await
+            .is_ok_and(|response| response.status().is_success());
+        if ready {
+            return Ok(());
+        }
+        if Instant::now() >= deadline {
+            bail!("core did not start in time: {last_line}");
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+async fn check_connectivity(mixed_port: u16, test_url: &str, timeout: Duration) -> Result<u32> {
+    let client = Client::builder()
+        .proxy(reqwest::Proxy::all(format!("http://127.0.0.1:{mixed_port}"))?)
+        .timeout(timeout)
+        .build()?;
+    let start = Instant::now();
+    client.get(test_url).send().await?.error_for_status()?;
+    Ok(u32::try_from(start.elapsed().as_millis()).unwrap_or(u32::MAX))
+}
+
+async fn node_delay(
+    client: &Client,
+    controller: &str,
+    secret: &str,
+    name: String,
+    url: &str,
+    timeout: u32,
+) -> NodeDelay {
+    let timeout = timeout.to_string();
+    let delay = async {
+        let response = client
+            .get(format!(
+                "{controller}/proxies/{}/delay",
+                utf8_percent_encode(&name, NON_ALPHANUMERIC)
+            ))
+            .query(&[("url", url), ("timeout", timeout.as_str())])
+            .bearer_auth(secret)
+            .send()
+            .await?
+            .error_for_status()?;
+        response.json::<JsonValue>().await
+    }
+    .await
+    .ok()
+    .and_then(|body| body.get("delay").and_then(JsonValue::as_u64))
+    .and_then(|delay| u32::try_from(delay).ok())
+    .filter(|&delay| delay > 0);
+    NodeDelay { name, delay }
+}
+
+/// 在已启动的临时内核上执行检查
+async fn run_checks(
+    report: &mut ProfileTestReport,
+    rx: &mut Receiver<CommandEvent>,
+    mixed_port: u16,
+    controller: &str,
+    secret: &str,
+) -> Result<()> {
+    let (test_url, timeout) = {
+        let verge = Config::verge().await.latest_arc();
+        (
+            verge
+                .default_latency_test
+                .clone()
+                .unwrap_or_else(|| DEFAULT_TEST_URL.into()),
+            verge
+                .default_latency_timeout
+                .and_then(|t| u32::try_from(t).ok())
+                .filter(|&t| t > 0)
+                .unwrap_or(DEFAULT_TEST_TIMEOUT_MS),
+        )
+    };
+    let client = Client::builder()
+        .no_proxy()
+        .timeout(Duration::from_millis(u64::from(timeout)) + Duration::from_secs(5))
+        .build()?;
+
+    wait_ready(&client, controller, secret, rx).await?;
+    report.started = true;
+
+    report.connectivity = check_connectivity(mixed_port, &test_url, Duration::from_millis(u64::from(timeout)))
+        .await
+        .inspect_err(|err| logging!(info, Type::Config, "[订阅试运行] 连通性测试失败: {err}"))
+        .ok();
+
+    let proxies = client
+        .get(format!("{controller}/proxies"))
+        .bearer_auth(secret)
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<JsonValue>()
+        .await?;
+    let names = node_names(&proxies);
+    report.nodes = names.len();
+
+    let step = names.len().div_ceil(MAX_TESTED_NODES).max(1);
+    let tests = names
+        .into_iter()
+        .step_by(step)
+        .map(|name| node_delay(&client, controller, secret, name, &test_url, timeout));
+    report.delays = futures::future::join_all(tests).await;
+    report.reachable = report.delays.iter().filter(|d| d.delay.is_some()).count();
+    Ok(())
+}
+
+/// 用临时内核试运行订阅并返回检查结果，内核无法启动时报告中包含原因
+pub async fn test_profile(uid: &String) -> Result<ProfileTestReport> {
+    if RUNNING.swap(true, Ordering::SeqCst) {
+        bail!("a profile test is already running");
+    }
+    let _guard = scopeguard::guard((), |()| RUNNING.store(false, Ordering::SeqCst));
+    let start = Instant::now();
+
+    if Config::profiles().await.latest_arc().get_item(uid)?.file.is_none() {
+        bail!("profile {uid} has no file");
+    }
+
+    let verge = Config::verge().await.latest_arc();
+    if CoreBackend::of(&verge) != CoreBackend::Mihomo {
+        bail!("profile testing requires a mihomo core");
+    }
+    let (mixed_port, controller_port) = ephemeral_ports()?;
+    let secret = help::get_uid("");
+    let (enhanced, _, _) = enhance::enhance_profile(Some(uid)).await;
+    let config = sandbox_config(enhanced, mixed_port, controller_port, &secret);
+
+    let test_home = dirs::app_temp_dir().join(TEST_HOME);
+    prepare_home(&dirs::app_home_dir()?, &test_home).await?;
+    let _cleanup = scopeguard::guard(test_home.clone(), |test_home| {
+        let _ = std::fs::remove_dir_all(test_home);
+    });
+    let config_path = test_home.join(TEST_CONFIG);
+    help::save_yaml(&config_path, &config, Some("# Clash Verge Profile Test")).await?;
+
+    let (mut rx, child) = core_command(&verge, &verge.get_valid_clash_core())?
+        .args([
+            "-d",
+            dirs::path_to_str(&test_home)?,
+            "-f",
+            dirs::path_to_str(&config_path)?,
+        ])
+        .spawn()?;
+    drop(verge);
+    logging!(
+        info,
+        Type::Config,
+        "[订阅试运行] {} 临时内核已启动，混合端口 {}，控制端口 {}",
+        uid,
+        mixed_port,
+        controller_port
+    );
+
+    let mut report = ProfileTestReport {
+        uid: uid.clone(),
+        ..ProfileTestReport::default()
+    };
+    let controller = format!("http://127.0.0.1:{controller_port}");
+    if let Err(err) = run_checks(&mut report, &mut rx, mixed_port, &controller, &secret).await {
+        report.error = Some(err.to_string().into());
+    }
+
+    let _ = child.kill();
+    report.duration_ms = u64::try_from(start.elapsed().as_millis()).unwrap_or(u64::MAX);
+    logging!(
+        info,
+        Type::Config,
+        "[订阅试运行] {} 完成: {}/{} 节点可用",
+        uid,
+        report.reachable,
+        report.delays.len()
+    );
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_sandbox_config() {
+        let content = "port: 7890\ntun: {enable: true}\ndns: {enable: true, listen: 0.0.0.0:53}\nproxies: []";
+        let config = sandbox_config(serde_yaml_ng::from_str(content).unwrap_or_default(), 40001, 40002, "s");
+        assert!(config.get("port").is_none());
+        assert_eq!(config.get("mixed-port").and_then(Value::as_u64), Some(40001));
+        assert_eq!(
+            config.get("external-controller").and_then(Value::as_str),
+            Some("127.0.0.1:40002")
+        );
+        assert_eq!(
+            config
+                .get("tun")
+                .and_then(|tun| tun.get("enable"))
+                .and_then(Value::as_bool),
+            Some(false)
+        );
+        assert!(config.get("dns").and_then(|dns| dns.get("listen")).is_none());
+    }
+
+    #[test]
+    fn test_node_names() {
+        let proxies = json!({
+            "proxies": {
+                "GLOBAL": { "type": "Selector" },
+                "DIRECT": { "type": "Direct" },
+                "hk-01": { "type": "Shadowsocks" },
+                "jp-01": { "type": "Vmess" }
+            }
+        });
+        let mut names = node_names(&proxies);
+        names.sort();
+        assert_eq!(names.iter().map(String::as_str).collect::<Vec<_>>(), ["hk-01", "jp-01"]);
+    }
+}
@@ -0,0 +1,171 @@
+//! 代理集合与规则集的状态汇总
+//!
+//! 合并 mihomo `/providers/proxies` 与 `/providers/rules` 的条目数、更新时间，
+//! 并附上本次运行中最近一次更新的错误。`update_all_providers` 逐个更新远程集合，
+//! 进度通过 `providers-update-progress` 事件推送。
+
+use crate::core::handle;
+use anyhow::{Result, anyhow};
+use clash_verge_logging::{Type, logging};
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::Serialize;
+use serde_json::Value;
+use smartstring::alias::String;
+use std::collections::HashMap;
+use tauri::Emitter as _;
+
+const PROGRESS_EVENT: &str = "providers-update-progress";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProviderKind {
+    Proxy,
+    Rule,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProviderStatus {
+    pub name: String,
+    pub kind: ProviderKind,
+    pub vehicle_type: String,
+    /// 节点数或规则条目数
+    pub count: u64,
+    pub updated_at: Option<String>,
+    /// 本次运行中最近一次更新失败的原因，更新成功后清除
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProviderUpdateProgress {
+    pub name: String,
+    pub kind: ProviderKind,
+    pub done: usize,
+    pub total: usize,
+    pub error: Option<String>,
+}
+
+static UPDATE_ERRORS: Lazy<Mutex<HashMap<(ProviderKind, String), String>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// 按 mihomo 的字段名解析，跳过配置内联节点使用的 `Compatible` 集合
+fn parse_providers(kind: ProviderKind, providers: &Value) -> Vec<ProviderStatus> {
+    let errors = UPDATE_ERRORS.lock();
+    let mut list: Vec<ProviderStatus> = providers
+        .get("providers")
+        .and_then(Value::as_object)
+        .into_iter()
+        .flatten()
+        .filter_map(|(name, value)| {
+            let str_of = |key: &str| -> String { value.get(key).and_then(Value::as_str).unwrap_or_default().into() };
+            let vehicle_type = str_of("vehicleType");
+            if vehicle_type == "Compatible" {
+                return None;
+            }
+            let count = match kind {
+                ProviderKind::Proxy => value
+                    .get("proxies")
+                    .and_then(Value::as_array)
+                    .map_or(0, |proxies| u64::try_from(proxies.len()).unwrap_or_default()),
+                ProviderKind::Rule => value.get("ruleCount").and_then(Value::as_u64).unwrap_or(0),
+            };
+            Some(ProviderStatus {
+                name: name.as_str().into(),
+                kind,
+                vehicle_type,
+                count,
+                updated_at: Some(str_of("updatedAt")).filter(|t| !t.is_empty()),
+                error: errors.get(&(kind, name.as_str().into())).cloned(),
+            })
+        })
+        .collect();
+    drop(errors);
+    list.sort_by(|a, b| a.name.cmp(&b.name));
+    list
+}
+
+/// 代理集合与规则集的状态
+pub async fn get_providers_status() -> Result<Vec<ProviderStatus>> {
+    let mihomo = handle::Handle::mihomo().await;
+    let proxies = serde_json::to_value(&mihomo.get_proxy_providers().await?)?;
+    let rules = serde_json::to_value(&mihomo.get_rule_providers().await?)?;
+    drop(mihomo);
+
+    let mut list = parse_providers(ProviderKind::Proxy, &proxies);
+    list.extend(parse_providers(ProviderKind::Rule, &rules));
+    Ok(list)
+}
+
+async fn update_provider(kind: ProviderKind, name: &str) -> Result<()> {
+    let mihomo = handle::Handle::mihomo().await;
+    let result = match kind {
+        ProviderKind::Proxy => mihomo.update_proxy_provider(name).await,
+        ProviderKind::Rule => mihomo.update_rule_provider(name).await,
+    };
+    drop(mihomo);
+    result.map_err(|err| anyhow!("{err}"))
+}
+
+/// 依次更新所有远程集合，返回更新后的状态
+pub async fn update_all_providers() -> Result<Vec<ProviderStatus>> {
+    let targets: Vec<(ProviderKind, String)> = get_providers_status()
+        .await?
+        .into_iter()
+        .filter(|provider| provider.vehicle_type.eq_ignore_ascii_case("http"))
+        .map(|provider| (provider.kind, provider.name))
+        .collect();
+    let total = targets.len();
+
+    let mut failed = 0;
+    for (done, (kind, name)) in targets.into_iter().enumerate() {
+        let error = update_provider(kind, &name)
+            .await
+            .err()
+            .map(|err| String::from(err.to_string()));
+        {
+            let mut errors = UPDATE_ERRORS.lock();
+            match &error {
+                Some(error) => errors.insert((kind, name.clone()), error.clone()),
+                None => errors.remove(&(kind, name.clone())),
+            };
+        }
+        if let Some(error) = &error {
+            failed += 1;
+            logging!(warn, Type::Config, "Failed to update provider {name}: {error}");
+        }
+        let _ = handle::Handle::app_handle().emit(
+            PROGRESS_EVENT,
+            ProviderUpdateProgress {
+                name,
+                kind,
+                done: done + 1,
+                total,
+                error,
+            },
+        );
+    }
+    logging!(info, Type::Config, "Updated {} providers, {failed} failed", total);
+    handle::Handle::refresh_clash();
+    get_providers_status().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_providers() {
+        let proxies = serde_json::json!({
+            "providers": {
+                "default": { "vehicleType": "Compatible", "proxies": [{}] },
+                "sub": { "vehicleType": "HTTP", "proxies": [{}, {}], "updatedAt": "2026-01-01T00:00:00Z" }
+            }
+        });
+        let list = parse_providers(ProviderKind::Proxy, &proxies);
+        assert_eq!(list.len(), 1);
+        assert_eq!(list[0].count, 2);
+        assert_eq!(list[0].updated_at.as_deref(), Some("2026-01-01T00:00:00Z"));
+
+        let rules = serde_json::json!({ "providers": { "ads": { "vehicleType": "HTTP", "ruleCount": 42 } } });
+        assert_eq!(parse_providers(ProviderKind::Rule, &rules)[0].count, 42);
+    }
+}
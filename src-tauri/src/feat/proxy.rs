@@ -2,15 +2,22 @@ use crate::{
     config::{Config, IVerge},
     core::handle,
 };
+use anyhow::{Result, bail};
 use clash_verge_logging::{Type, logging};
 use std::env;
 use tauri_plugin_clipboard_manager::ClipboardExt as _;
+use tauri_plugin_mihomo::models::Proxies;
 
 /// Toggle system proxy on/off
 pub async fn toggle_system_proxy() {
     let verge = Config::verge().await;
     let enable = verge.latest_arc().enable_system_proxy.unwrap_or(false);
     let auto_close_connection = verge.latest_arc().auto_close_connection.unwrap_or(false);
+    if enable && let Err(err) = super::ensure_unlocked().await {
+        logging!(warn, Type::ProxyMode, "{err}");
+        handle::Handle::notice_message("settings_lock::locked", err.to_string());
+        return;
+    }
 
     // 如果当前系统代理即将关闭，且自动关闭连接设置为true，则关闭所有连接
     if enable
@@ -39,6 +46,11 @@ pub async fn toggle_system_proxy() {
 pub async fn toggle_tun_mode(not_save_file: Option<bool>) {
     let enable = Config::verge().await.latest_arc().enable_tun_mode;
     let enable = enable.unwrap_or(false);
+    if enable && let Err(err) = super::ensure_unlocked().await {
+        logging!(warn, Type::ProxyMode, "{err}");
+        handle::Handle::notice_message("settings_lock::locked", err.to_string());
+        return;
+    }
 
     match super::patch_verge(
         &IVerge {
@@ -54,20 +66,47 @@ pub async fn toggle_tun_mode(not_save_file: Option<bool>) {
     }
 }
 
-/// Copy proxy environment variables to clipboard
-pub async fn copy_clash_env() {
+/// 生成设置代理环境变量的命令，`shell` 为 `dotenv` 时生成 `.env` 片段
+pub fn proxy_env_text(shell: &str, host: &str, port: u16) -> Result<String> {
+    // IPv6 地址需要加方括号
+    let host = if host.contains(':') && !host.starts_with('[') {
+        format!("[{host}]")
+    } else {
+        host.to_owned()
+    };
+    let http_proxy = format!("http://{host}:{port}");
+    let socks5_proxy = format!("socks5://{host}:{port}");
+
+    let text = match shell {
+        "bash" | "zsh" | "sh" => {
+            format!("export https_proxy={http_proxy} http_proxy={http_proxy} all_proxy={socks5_proxy}")
+        }
+        "fish" => format!(
+            "set -gx http_proxy {http_proxy}; set -gx https_proxy {http_proxy}; set -gx all_proxy {socks5_proxy}"
+        ),
+        "powershell" | "pwsh" => format!(
+            "$env:HTTP_PROXY=\"{http_proxy}\"; $env:HTTPS_PROXY=\"{http_proxy}\"; $env:ALL_PROXY=\"{socks5_proxy}\""
+        ),
+        "cmd" => format!("set http_proxy={http_proxy}\r\nset https_proxy={http_proxy}\r\nset all_proxy={socks5_proxy}"),
+        "nushell" => format!(
+            "load-env {{ http_proxy: \"{http_proxy}\", https_proxy: \"{http_proxy}\", all_proxy: \"{socks5_proxy}\" }}"
+        ),
+        "dotenv" => format!(
+            "HTTP_PROXY={http_proxy}\nHTTPS_PROXY={http_proxy}\nALL_PROXY={socks5_proxy}\nhttp_proxy={http_proxy}\nhttps_proxy={http_proxy}\nall_proxy={socks5_proxy}\n"
+        ),
+        _ => bail!("invalid env type: {shell}"),
+    };
+    Ok(text)
+}
+
+/// 将当前混合端口的代理环境变量命令写入剪贴板并返回，`shell` 为空时使用设置中的终端类型
+pub async fn copy_proxy_env(shell: Option<&str>) -> Result<String> {
     let env_ip = env::var("CLASH_VERGE_REV_IP").ok();
     let verge_cfg = Config::verge().await.latest_arc();
     let ip = env_ip
         .as_deref()
         .unwrap_or_else(|| verge_cfg.proxy_host.as_deref().unwrap_or("127.0.0.1"));
-
-    let app_handle = handle::Handle::app_handle();
-    let port = verge_cfg.verge_mixed_port.unwrap_or(7897);
-    let http_proxy = format!("http://{ip}:{port}");
-    let socks5_proxy = format!("socks5://{ip}:{port}");
-
-    let clipboard = app_handle.clipboard();
+    let port = Config::mixed_port().await;
 
     let default_env = {
         #[cfg(not(target_os = "windows"))]
@@ -79,25 +118,71 @@ pub async fn copy_clash_env() {
             "powershell"
         }
     };
-    let env_type = verge_cfg.env_type.as_deref().unwrap_or(default_env);
+    let shell = shell.unwrap_or_else(|| verge_cfg.env_type.as_deref().unwrap_or(default_env));
+    let text = proxy_env_text(shell, ip, port)?;
 
-    let export_text = match env_type {
-        "bash" => format!("export https_proxy={http_proxy} http_proxy={http_proxy} all_proxy={socks5_proxy}"),
-        "cmd" => format!("set http_proxy={http_proxy}\r\nset https_proxy={http_proxy}"),
-        "powershell" => {
-            format!("$env:HTTP_PROXY=\"{http_proxy}\"; $env:HTTPS_PROXY=\"{http_proxy}\"")
-        }
-        "nushell" => {
-            format!("load-env {{ http_proxy: \"{http_proxy}\", https_proxy: \"{http_proxy}\" }}")
-        }
-        "fish" => format!("set -x http_proxy {http_proxy}; set -x https_proxy {http_proxy}"),
-        _ => {
-            logging!(error, Type::ProxyMode, "copy_clash_env: Invalid env type! {env_type}");
-            return;
-        }
-    };
+    handle::Handle::app_handle().clipboard().write_text(&text)?;
+    Ok(text)
+}
+
+/// Copy proxy environment variables to clipboard
+pub async fn copy_clash_env() {
+    if let Err(err) = copy_proxy_env(None).await {
+        logging!(error, Type::ProxyMode, "copy_clash_env: {err}");
+    }
+}
+
+/// 当前出站模式，缺省为 rule
+pub async fn clash_mode() -> std::string::String {
+    Config::clash()
+        .await
+        .latest_arc()
+        .0
+        .get("mode")
+        .and_then(|val| val.as_str())
+        .unwrap_or("rule")
+        .to_owned()
+}
+
+/// 主代理组：全局模式下为 GLOBAL，否则按 GLOBAL 中的顺序取第一个满足条件的代理组
+pub fn main_group<'a>(proxies: &'a Proxies, mode: &str, node: Option<&str>) -> Option<&'a str> {
+    if mode == "global" {
+        return Some("GLOBAL");
+    }
+    let order = proxies.proxies.get("GLOBAL")?.all.as_ref()?;
+    order
+        .iter()
+        .filter_map(|name| proxies.proxies.get(name).map(|group| (name, group)))
+        .find(|(_, group)| {
+            group.now.is_some()
+                && group
+                    .all
+                    .as_ref()
+                    .is_some_and(|all| node.is_none_or(|node| all.iter().any(|n| n == node)))
+        })
+        .map(|(name, _)| name.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    if clipboard.write_text(&export_text).is_err() {
-        logging!(error, Type::ProxyMode, "Failed to write to clipboard");
+    #[test]
+    fn test_proxy_env_text() {
+        assert_eq!(
+            proxy_env_text("bash", "127.0.0.1", 7897).unwrap_or_default(),
+            "export https_proxy=http://127.0.0.1:7897 http_proxy=http://127.0.0.1:7897 all_proxy=socks5://127.0.0.1:7897"
+        );
+        assert!(
+            proxy_env_text("fish", "::1", 7890)
+                .unwrap_or_default()
+                .starts_with("set -gx http_proxy http://[::1]:7890;")
+        );
+        assert!(
+            proxy_env_text("dotenv", "localhost", 7890)
+                .unwrap_or_default()
+                .contains("\nHTTPS_PROXY=http://localhost:7890\n")
+        );
+        assert!(proxy_env_text("tcsh", "127.0.0.1", 7890).is_err());
     }
 }
@@ -0,0 +1,145 @@
+use crate::{
+    config::{Config, PrfExtra},
+    core::notify::{NotificationEvent, notify_event},
+};
+use anyhow::{Result, anyhow};
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::Serialize;
+use smartstring::alias::String;
+use std::collections::HashSet;
+
+/// 已用流量达到该百分比时提醒
+const QUOTA_WARN_PERCENT: f64 = 90.0;
+/// 距离到期不足该天数时提醒
+const EXPIRY_WARN_DAYS: i64 = 3;
+const SECS_PER_DAY: i64 = 86_400;
+
+/// 已发送过提醒的 (uid, 类型)，提醒条件解除后移除，避免每次更新重复通知
+static NOTIFIED: Lazy<Mutex<HashSet<(String, QuotaAlert)>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum QuotaAlert {
+    Traffic,
+    Expiry,
+}
+
+/// 订阅的流量与到期信息，来自 `subscription-userinfo` 响应头
+#[derive(Debug, Clone, Serialize)]
+pub struct ProfileQuota {
+    pub uid: String,
+    pub name: Option<String>,
+    pub upload: u64,
+    pub download: u64,
+    pub used: u64,
+    /// 为 0 表示未提供或不限量
+    pub total: u64,
+    pub remaining: Option<u64>,
+    pub used_percent: Option<f64>,
+    pub expire: Option<i64>,
+    pub days_left: Option<i64>,
+    pub quota_warning: bool,
+    pub expiry_warning: bool,
+}
+
+impl ProfileQuota {
+    pub fn new(uid: String, name: Option<String>, extra: &PrfExtra, now: i64) -> Self {
+        let used = extra.upload.saturating_add(extra.download);
+        let limited = extra.total > 0;
+        let used_percent = limited.then(|| used as f64 * 100.0 / extra.total as f64);
+        let expire = i64::try_from(extra.expire).ok().filter(|&t| t > 0);
+        let days_left = expire.map(|t| (t - now).div_euclid(SECS_PER_DAY));
+
+        Self {
+            uid,
+            name,
+            upload: extra.upload,
+            download: extra.download,
+            used,
+            total: extra.total,
+            remaining: limited.then(|| extra.total.saturating_sub(used)),
+            used_percent,
+            expire,
+            days_left,
+            quota_warning: used_percent.is_some_and(|p| p >= QUOTA_WARN_PERCENT),
+            expiry_warning: days_left.is_some_and(|d| d < EXPIRY_WARN_DAYS),
+        }
+    }
+}
+
+/// 获取订阅的流量与到期信息，本地配置或未提供信息时返回 `None`
+pub async fn get_profile_quota(uid: &str) -> Result<Option<ProfileQuota>> {
+    let profiles = Config::profiles().await.latest_arc();
+    let item = profiles
+        .get_item(uid)
+        .map_err(|_| anyhow!("profile not found: {uid}"))?;
+    let now = chrono::Local::now().timestamp();
+    Ok(item
+        .extra
+        .as_ref()
+        .map(|extra| ProfileQuota::new(uid.into(), item.name.clone(), extra, now)))
+}
+
+/// 根据最新的订阅信息发送流量不足或即将到期的通知，同一条件只提醒一次
+pub async fn check_profile_quota(uid: &str) -> Result<()> {
+    let Some(quota) = get_profile_quota(uid).await? else {
+        return Ok(());
+    };
+    let name = quota.name.as_deref().unwrap_or(uid);
+
+    if should_alert(uid, QuotaAlert::Traffic, quota.quota_warning) {
+        let percent = format!("{:.0}", quota.used_percent.unwrap_or_default());
+        notify_event(NotificationEvent::SubscriptionQuotaLow {
+            name,
+            percent: &percent,
+        })
+        .await;
+    }
+    if should_alert(uid, QuotaAlert::Expiry, quota.expiry_warning) {
+        let days = quota.days_left.unwrap_or_default().max(0).to_string();
+        notify_event(NotificationEvent::SubscriptionExpiring { name, days: &days }).await;
+    }
+    Ok(())
+}
+
+fn should_alert(uid: &str, alert: QuotaAlert, active: bool) -> bool {
+    let key = (String::from(uid), alert);
+    let mut notified = NOTIFIED.lock();
+    if active {
+        notified.insert(key)
+    } else {
+        notified.remove(&key);
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_quota() {
+        let extra = PrfExtra {
+            upload: 10,
+            download: 85,
+            total: 100,
+            expire: 1_000_000 + 2 * SECS_PER_DAY as u64,
+        };
+        let quota = ProfileQuota::new("uid".into(), None, &extra, 1_000_000);
+        assert_eq!(quota.used, 95);
+        assert_eq!(quota.remaining, Some(5));
+        assert!(quota.quota_warning);
+        assert_eq!(quota.days_left, Some(2));
+        assert!(quota.expiry_warning);
+
+        let unlimited = PrfExtra {
+            upload: 1,
+            download: 1,
+            total: 0,
+            expire: 0,
+        };
+        let quota = ProfileQuota::new("uid".into(), None, &unlimited, 1_000_000);
+        assert_eq!(quota.remaining, None);
+        assert!(!quota.quota_warning && !quota.expiry_warning);
+    }
+}
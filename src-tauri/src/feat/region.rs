@@ -0,0 +1,296 @@
+//! 按国家/地区选择节点
+//!
+//! 先按节点名称中的旗帜 emoji、国家代码或常见地名判断地区，名称无法判断时通过内核 DNS 解析服务器地址，
+//! 再用内核目录中的 `geoip.metadb` / `Country.mmdb` 在本地查询 GeoIP（按地址缓存），不向任何第三方发送服务器地址。
+//! 最后对该地区的候选节点测速，切换到延迟最低的节点。
+//! 定时任务可通过 `select_region` 动作调用。
+
+use crate::{
+    config::Config,
+    core::handle,
+    process::AsyncHandler,
+    utils::{dirs, mmdb},
+};
+use anyhow::{Result, anyhow, bail};
+use clash_verge_logging::{Type, logging};
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::Serialize;
+use serde_yaml_ng::Value as YamlValue;
+use smartstring::alias::String;
+use std::{collections::HashMap, net::IpAddr};
+
+const DEFAULT_TEST_URL: &str = "https://www.gstatic.com/generate_204";
+const DEFAULT_TEST_TIMEOUT_MS: u32 = 5000;
+/// 按优先级尝试的本地 GeoIP 数据库
+const GEOIP_DATABASES: [&str; 2] = ["geoip.metadb", "Country.mmdb"];
+/// 单次选择最多解析的服务器域名数
+const MAX_GEOIP_LOOKUPS: usize = 30;
+/// 与 ISO 代码同形的常见节点标签（Netflix、AI 解锁、Telegram、YouTube、Shadowsocks、CN2 线路等）
+const TAG_TOKENS: [&str; 7] = ["NF", "AI", "TV", "TG", "YT", "SS", "CN2"];
+
+/// 地区代码与名称中常见的写法
+const REGION_KEYWORDS: &[(&str, &[&str])] = &[
+    ("HK", &["香港", "hong kong", "hongkong"]),
+    ("TW", &["台湾", "台灣", "taiwan", "taipei"]),
+    ("MO", &["澳门", "澳門", "macau", "macao"]),
+    ("JP", &["日本", "东京", "大阪", "japan", "tokyo", "osaka"]),
+    ("SG", &["新加坡", "狮城", "singapore"]),
+    ("KR", &["韩国", "韓國", "首尔", "korea", "seoul"]),
+    (
+        "US",
+        &[
+            "美国",
+            "美國",
+            "洛杉矶",
+            "硅谷",
+            "united states",
+            "america",
+            "los angeles",
+            "san jose",
+            "seattle",
+        ],
+    ),
+    ("GB", &["英国", "英國", "伦敦", "united kingdom", "london"]),
+    ("DE", &["德国", "德國", "germany", "frankfurt"]),
+    ("FR", &["法国", "法國", "france", "paris"]),
+    ("NL", &["荷兰", "荷蘭", "netherlands", "amsterdam"]),
+    ("CA", &["加拿大", "canada"]),
+    ("AU", &["澳大利亚", "澳洲", "australia", "sydney"]),
+    ("RU", &["俄罗斯", "俄羅斯", "russia", "moscow"]),
+    ("IN", &["印度", "india", "mumbai"]),
+    ("TR", &["土耳其", "turkey", "türkiye"]),
+    ("MY", &["马来西亚", "馬來西亞", "malaysia"]),
+    ("TH", &["泰国", "泰國", "thailand"]),
+    ("VN", &["越南", "vietnam"]),
+    ("PH", &["菲律宾", "菲律賓", "philippines"]),
+    ("ID", &["印尼", "indonesia"]),
+    ("AR", &["阿根廷", "argentina"]),
+    ("BR", &["巴西", "brazil"]),
+];
+
+/// 服务器地址 -> 国家代码，查询失败时为空
+static GEOIP_CACHE: Lazy<Mutex<HashMap<String, Option<String>>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RegionSelection {
+    pub group: String,
+    pub node: String,
+    pub country_code: String,
+    pub delay: u32,
+    /// 参与测速的候选节点数
+    pub candidates: usize,
+}
+
+/// 将 alpha-2 / alpha-3 代码统一为大写 alpha-2
+pub fn normalize_country(code: &str) -> Option<String> {
+    let code = code.trim().to_ascii_uppercase();
+    match code.as_str() {
+        "UK" => Some("GB".into()),
+        _ if code.len() == 2 => rust_iso3166::from_alpha2(&code).map(|c| c.alpha2.into()),
+        _ if code.len() == 3 => rust_iso3166::from_alpha3(&code).map(|c| c.alpha2.into()),
+        _ => None,
+    }
+}
+
+/// 名称中第一个旗帜 emoji 对应的国家代码
+fn flag_country(name: &str) -> Option<String> {
+    let indicators: Vec<char> = name
+        .chars()
+        .filter_map(|c| {
+            let offset = u32::from(c).checked_sub(0x1F1E6)?;
+            (offset < 26).then(|| char::from_u32(u32::from('A') + offset))?
+        })
+        .take(2)
+        .collect();
+    match indicators.as_slice() {
+        [a, b] => normalize_country(&format!("{a}{b}")),
+        _ => None,
+    }
+}
+
+/// 按名称判断节点所在地区
+pub fn node_country(name: &str) -> Option<String> {
+    if let Some(code) = flag_country(name) {
+        return Some(code);
+    }
+    // 大写的独立代码，如 `SG-01`、`US 02`、`HK01`
+    let from_token = name
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|token| !TAG_TOKENS.contains(token))
+        .map(|token| token.trim_end_matches(|c: char| c.is_ascii_digit()))
+        .filter(|token| (2..=3).contains(&token.len()) && token.chars().all(|c| c.is_ascii_uppercase()))
+        .find_map(normalize_country);
+    if from_token.is_some() {
+        return from_token;
+    }
+    let lower = name.to_lowercase();
+    REGION_KEYWORDS
+        .iter()
+        .find(|(_, keywords)| keywords.iter().any(|keyword| lower.contains(keyword)))
+        .map(|(code, _)| (*code).into())
+}
+
+/// 运行配置中的节点名 -> 服务器地址
+async fn node_servers() -> HashMap<String, String> {
+    let runtime = Config::runtime().await.latest_arc();
+    runtime
+        .config
+        .as_ref()
+        .and_then(|config| config.get("proxies"))
+        .and_then(YamlValue::as_sequence)
+        .into_iter()
+        .flatten()
+        .filter_map(|proxy| {
+            let name = proxy.get("name")?.as_str()?;
+            let server = proxy.get("server")?.as_str()?;
+            Some((name.into(), server.into()))
+        })
+        .collect()
+}
+
+/// 读取内核目录中的 GeoIP 数据库
+async fn load_geoip_database() -> Option<mmdb::Reader> {
+    let home = dirs::app_home_dir().ok()?;
+    AsyncHandler::spawn_blocking(move || {
+        GEOIP_DATABASES.iter().find_map(|name| {
+            let buf = std::fs::read(home.join(name)).ok()?;
+            mmdb::Reader::from_bytes(buf)
+                .inspect_err(|err| logging!(warn, Type::Core, "Failed to load {name}: {err}"))
+                .ok()
+        })
+    })
+    .await
+    .ok()
+    .flatten()
+}
+
+/// 服务器地址对应的 IP，域名通过内核 DNS 解析，解析结果为 fake-ip 时放弃
+async fn resolve_server(server: &str) -> Option<IpAddr> {
+    if let Ok(ip) = server.parse::<IpAddr>() {
+        return Some(ip);
+    }
+    let result = super::resolve_via_core(server, Some("A")).await.ok()?;
+    if result.fake_ip {
+        return None;
+    }
+    result.answers.iter().find_map(|answer| answer.data.parse().ok())
+}
+
+async fn lookup_server_country(database: &mmdb::Reader, server: &str) -> Option<String> {
+    let cached = GEOIP_CACHE.lock().get(server).cloned();
+    if let Some(cached) = cached {
+        return cached;
+    }
+    let country = resolve_server(server)
+        .await
+        .and_then(|ip| database.country(ip))
+        .and_then(|code| normalize_country(&code));
+    GEOIP_CACHE.lock().insert(server.into(), country.clone());
+    country
+}
+
+/// 在代理组中选择指定地区延迟最低的节点并切换
+pub async fn select_node_by_region(group: &str, country_code: &str) -> Result<RegionSelection> {
+    let code = normalize_country(country_code).ok_or_else(|| anyhow!("invalid country code: {country_code}"))?;
+    let (test_url, timeout) = {
+        let verge = Config::verge().await.latest_arc();
+        (
+            verge
+                .default_latency_test
+                .clone()
+                .unwrap_or_else(|| DEFAULT_TEST_URL.into()),
+            verge
+                .default_latency_timeout
+                .and_then(|t| u32::try_from(t).ok())
+                .filter(|&t| t > 0)
+                .unwrap_or(DEFAULT_TEST_TIMEOUT_MS),
+        )
+    };
+
+    let proxies = handle::Handle::mihomo().await.get_proxies().await?;
+    let members: Vec<String> = proxies
+        .proxies
+        .get(group)
+        .and_then(|entry| entry.all.as_ref())
+        .ok_or_else(|| anyhow!("proxy group not found: {group}"))?
+        .iter()
+        // 跳过嵌套的代理组
+        .filter(|name| proxies.proxies.get(name.as_str()).is_some_and(|p| p.all.is_none()))
+        .map(|name| name.as_str().into())
+        .collect();
+
+    let servers = node_servers().await;
+    let database = load_geoip_database().await;
+    let mut candidates = Vec::new();
+    let mut lookups = 0;
+    for name in members {
+        let country = match node_country(&name) {
+            Some(country) => Some(country),
+            None => match (servers.get(&name), &database) {
+                (Some(server), Some(database)) if lookups < MAX_GEOIP_LOOKUPS => {
+                    lookups += 1;
+                    lookup_server_country(database, server).await
+                }
+                _ => None,
+            },
+        };
+        if country.as_deref() == Some(code.as_str()) {
+            candidates.push(name);
+        }
+    }
+    if candidates.is_empty() {
+        bail!("no node in {group} is located in {code}");
+    }
+
+    let mihomo = handle::Handle::mihomo().await;
+    let delays = futures::future::join_all(
+        candidates
+            .iter()
+            .map(|name| mihomo.delay_proxy_by_name(name, &test_url, timeout)),
+    )
+    .await;
+    drop(mihomo);
+    let (node, delay) = candidates
+        .iter()
+        .zip(delays)
+        .filter_map(|(name, result)| Some((name, result.ok()?.delay)).filter(|(_, delay)| *delay > 0))
+        .min_by_key(|(_, delay)| *delay)
+        .ok_or_else(|| anyhow!("no node in {code} is reachable"))?;
+
+    super::switch_proxy_node(group, node).await;
+    logging!(
+        info,
+        Type::Core,
+        "Selected {node} ({delay}ms) in {group} for region {code}"
+    );
+    Ok(RegionSelection {
+        group: group.into(),
+        node: node.clone(),
+        country_code: code,
+        delay,
+        candidates: candidates.len(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_node_country() {
+        assert_eq!(normalize_country("jpn").as_deref(), Some("JP"));
+        assert_eq!(normalize_country("uk").as_deref(), Some("GB"));
+        assert_eq!(normalize_country("XX"), None);
+
+        assert_eq!(node_country("🇸🇬 Singapore 01").as_deref(), Some("SG"));
+        assert_eq!(node_country("HK01 IPLC").as_deref(), Some("HK"));
+        assert_eq!(node_country("US-02 | 1x").as_deref(), Some("US"));
+        assert_eq!(node_country("日本 东京 03").as_deref(), Some("JP"));
+        assert_eq!(node_country("Taiwan Premium").as_deref(), Some("TW"));
+        assert_eq!(node_country("made in node"), None);
+        assert_eq!(node_country("NF AI 01"), None);
+        assert_eq!(node_country("NF | JP 02").as_deref(), Some("JP"));
+        assert_eq!(node_country("CN2 GIA 美国").as_deref(), Some("US"));
+    }
+}
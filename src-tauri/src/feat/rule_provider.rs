@@ -0,0 +1,198 @@
+//! 规则集（rule-providers）管理
+//!
+//! 列出当前配置中的规则集及其条目数、更新时间，支持单独刷新，
+//! 通过 URL 添加的规则集保存在管理的规则覆盖层中。
+
+use super::rules::{RULES_LOCK, apply_managed};
+use crate::{core::handle, enhance::rules::ManagedRules};
+use anyhow::{Result, anyhow, bail};
+use clash_verge_logging::{Type, logging};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use serde_yaml_ng::Mapping;
+use smartstring::alias::String;
+use tauri::Url;
+
+/// 通过 URL 添加的规则集默认更新间隔（秒）
+const DEFAULT_PROVIDER_INTERVAL: u64 = 86400;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleBehavior {
+    Domain,
+    Ipcidr,
+    Classical,
+}
+
+impl RuleBehavior {
+    const fn as_str(self) -> &'static str {
+        match self {
+            Self::Domain => "domain",
+            Self::Ipcidr => "ipcidr",
+            Self::Classical => "classical",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RuleProviderInfo {
+    pub name: String,
+    pub behavior: String,
+    pub vehicle_type: String,
+    pub format: String,
+    pub rule_count: u64,
+    pub updated_at: Option<String>,
+    /// 是否为通过 `add_rule_provider` 添加的规则集
+    pub managed: bool,
+}
+
+impl RuleProviderInfo {
+    /// 按 mihomo `/providers/rules` 的字段名解析
+    fn from_value(name: &str, value: &Value) -> Self {
+        let str_of = |key: &str| -> String { value.get(key).and_then(Value::as_str).unwrap_or_default().into() };
+        Self {
+            name: name.into(),
+            behavior: str_of("behavior"),
+            vehicle_type: str_of("vehicleType"),
+            format: str_of("format"),
+            rule_count: value.get("ruleCount").and_then(Value::as_u64).unwrap_or(0),
+            updated_at: Some(str_of("updatedAt")).filter(|t| !t.is_empty()),
+            managed: false,
+        }
+    }
+}
+
+/// 根据链接后缀推断规则集格式
+fn provider_format(url: &Url) -> &'static str {
+    let path = url.path().to_ascii_lowercase();
+    if path.ends_with(".mrs") {
+        "mrs"
+    } else if path.ends_with(".txt") || path.ends_with(".list") {
+        "text"
+    } else {
+        "yaml"
+    }
+}
+
+fn build_provider(name: &str, url: &str, behavior: RuleBehavior) -> Result<Mapping> {
+    if name.is_empty() || name.contains([',', '/', '\\']) || name.chars().any(char::is_whitespace) {
+        bail!("invalid rule provider name: {name}");
+    }
+    let url = Url::parse(url)?;
+    if !matches!(url.scheme(), "http" | "https") {
+        bail!("rule provider URL must be http(s)");
+    }
+    let format = provider_format(&url);
+    if format == "mrs" && behavior == RuleBehavior::Classical {
+        bail!("mrs rule providers do not support classical behavior");
+    }
+
+    let extension = if format == "text" { "txt" } else { format };
+    let mut provider = Mapping::new();
+    provider.insert("type".into(), "http".into());
+    provider.insert("behavior".into(), behavior.as_str().into());
+    provider.insert("format".into(), format.into());
+    provider.insert("url".into(), url.as_str().into());
+    provider.insert("path".into(), format!("./rules/{name}.{extension}").into());
+    provider.insert("interval".into(), DEFAULT_PROVIDER_INTERVAL.into());
+    Ok(provider)
+}
+
+/// 列出内核中加载的规则集
+pub async fn list_rule_providers() -> Result<Vec<RuleProviderInfo>> {
+    let managed = ManagedRules::load().await?;
+    let providers = handle::Handle::mihomo().await.get_rule_providers().await?;
+    let providers = serde_json::to_value(&providers)?;
+
+    let mut list: Vec<RuleProviderInfo> = providers
+        .get("providers")
+        .and_then(Value::as_object)
+        .map(|providers| {
+            providers
+                .iter()
+                .map(|(name, value)| RuleProviderInfo::from_value(name, value))
+                .collect()
+        })
+        .unwrap_or_default();
+    for info in &mut list {
+        info.managed = managed.providers.contains_key(info.name.as_str());
+    }
+    list.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(list)
+}
+
+/// 强制刷新单个规则集
+pub async fn refresh_rule_provider(name: &str) -> Result<()> {
+    handle::Handle::mihomo()
+        .await
+        .update_rule_provider(name)
+        .await
+        .map_err(|err| anyhow!("failed to refresh rule provider {name}: {err}"))?;
+    logging!(info, Type::Config, "[规则集] 已刷新 {}", name);
+    Ok(())
+}
+
+/// 通过 URL 添加规则集，添加后可用 `RULE-SET,<name>,<target>` 规则引用
+pub async fn add_rule_provider(name: &str, url: &str, behavior: RuleBehavior) -> Result<()> {
+    let provider = build_provider(name, url, behavior)?;
+    let _guard = RULES_LOCK.lock().await;
+    let previous = ManagedRules::load().await?;
+    if previous.providers.contains_key(name) {
+        bail!("rule provider {name} already exists");
+    }
+    let mut managed = previous.clone();
+    managed.providers.insert(name.into(), provider.into());
+    apply_managed(&previous, managed).await?;
+    logging!(info, Type::Config, "[规则集] 已添加 {}: {}", name, url);
+    Ok(())
+}
+
+/// 删除通过 URL 添加的规则集，同时删除引用它的管理规则
+pub async fn remove_rule_provider(name: &str) -> Result<()> {
+    let _guard = RULES_LOCK.lock().await;
+    let previous = ManagedRules::load().await?;
+    let mut managed = previous.clone();
+    if managed.providers.remove(name).is_none() {
+        bail!("rule provider {name} is not managed");
+    }
+    let prefix = format!("RULE-SET,{name},");
+    managed.rules.retain(|rule| !rule.starts_with(&prefix));
+    apply_managed(&previous, managed).await?;
+    logging!(info, Type::Config, "[规则集] 已删除 {}", name);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_provider() {
+        let provider = build_provider("ads", "https://example.com/ads.mrs", RuleBehavior::Domain).unwrap_or_default();
+        assert_eq!(provider.get("format").and_then(|v| v.as_str()), Some("mrs"));
+        assert_eq!(provider.get("path").and_then(|v| v.as_str()), Some("./rules/ads.mrs"));
+
+        let provider = build_provider("cn", "https://example.com/cn.list", RuleBehavior::Ipcidr).unwrap_or_default();
+        assert_eq!(provider.get("format").and_then(|v| v.as_str()), Some("text"));
+        assert_eq!(provider.get("behavior").and_then(|v| v.as_str()), Some("ipcidr"));
+
+        assert!(build_provider("a b", "https://example.com/x.yaml", RuleBehavior::Domain).is_err());
+        assert!(build_provider("x", "ftp://example.com/x.yaml", RuleBehavior::Domain).is_err());
+        assert!(build_provider("x", "https://example.com/x.mrs", RuleBehavior::Classical).is_err());
+    }
+
+    #[test]
+    fn test_provider_info_from_value() {
+        let value = serde_json::json!({
+            "behavior": "Domain",
+            "vehicleType": "HTTP",
+            "format": "MrsRule",
+            "ruleCount": 42,
+            "updatedAt": "2024-01-01T00:00:00Z",
+        });
+        let info = RuleProviderInfo::from_value("ads", &value);
+        assert_eq!(info.rule_count, 42);
+        assert_eq!(info.vehicle_type, "HTTP");
+        assert!(info.updated_at.is_some());
+    }
+}
@@ -0,0 +1,180 @@
+use crate::{
+    core::{CoreManager, audit::AuditLog, handle},
+    enhance::rules::{ManagedRules, normalize_rule},
+};
+use anyhow::{Result, anyhow, bail};
+use clash_verge_logging::{Type, logging};
+use serde::Deserialize;
+use serde_json::Value;
+use smartstring::alias::String;
+use std::net::IpAddr;
+use tokio::sync::Mutex;
+
+/// 串行化对规则文件的修改
+pub(super) static RULES_LOCK: Mutex<()> = Mutex::const_new(());
+
+/// 保存修改后的规则并重新加载内核，内核拒绝时恢复原规则
+pub(super) async fn apply_managed(previous: &ManagedRules, managed: ManagedRules) -> Result<ManagedRules> {
+    managed.save().await?;
+
+    let (applied, msg) = CoreManager::global().update_config().await?;
+    if !applied {
+        previous.save().await?;
+        bail!("rules were rejected by the core: {msg}");
+    }
+    handle::Handle::refresh_clash();
+    Ok(managed)
+}
+
+/// 列出管理的规则，按匹配顺序排列
+pub async fn list_rules() -> Result<Vec<String>> {
+    Ok(ManagedRules::load().await?.rules)
+}
+
+/// 插入规则，`position` 为空时追加到末尾
+pub async fn insert_rule(rule: &str, position: Option<usize>) -> Result<Vec<String>> {
+    let rule = normalize_rule(rule)?;
+    let _guard = RULES_LOCK.lock().await;
+    let previous = ManagedRules::load().await?;
+    let mut managed = previous.clone();
+    let rules = &mut managed.rules;
+    let position = position.unwrap_or(rules.len());
+    if position > rules.len() {
+        bail!("rule position {position} out of range");
+    }
+    logging!(info, Type::Config, "[规则管理] 插入规则 {} 到位置 {}", rule, position);
+    rules.insert(position, rule.clone());
+    let rules = apply_managed(&previous, managed).await?.rules;
+    AuditLog::record(
+        "rule.insert",
+        Some(&position.to_string()),
+        None,
+        Some(rule.as_str().into()),
+    );
+    Ok(rules)
+}
+
+/// 删除指定位置的规则
+pub async fn delete_rule(index: usize) -> Result<Vec<String>> {
+    let _guard = RULES_LOCK.lock().await;
+    let previous = ManagedRules::load().await?;
+    let mut managed = previous.clone();
+    let rules = &mut managed.rules;
+    if index >= rules.len() {
+        bail!("rule index {index} out of range");
+    }
+    let removed = rules.remove(index);
+    logging!(info, Type::Config, "[规则管理] 删除规则 {}", removed);
+    let rules = apply_managed(&previous, managed).await?.rules;
+    AuditLog::record(
+        "rule.delete",
+        Some(&index.to_string()),
+        Some(removed.as_str().into()),
+        None,
+    );
+    Ok(rules)
+}
+
+/// 调整规则顺序
+pub async fn move_rule(from: usize, to: usize) -> Result<Vec<String>> {
+    let _guard = RULES_LOCK.lock().await;
+    let previous = ManagedRules::load().await?;
+    let mut managed = previous.clone();
+    let rules = &mut managed.rules;
+    if from >= rules.len() || to >= rules.len() {
+        bail!("rule index out of range");
+    }
+    let rule = rules.remove(from);
+    rules.insert(to, rule.clone());
+    let rules = apply_managed(&previous, managed).await?.rules;
+    AuditLog::record("rule.move", Some(&rule), Some(from.into()), Some(to.into()));
+    Ok(rules)
+}
+
+/// 根据连接生成规则时使用的匹配依据
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RuleMatch {
+    Domain,
+    Ip,
+    Process,
+}
+
+/// 按连接的 metadata 生成规则；未指定依据时优先使用域名，其次目标 IP
+fn rule_for_connection(metadata: &Value, by: Option<RuleMatch>, target: &str) -> Result<String> {
+    let field = |key: &str| metadata.get(key).and_then(Value::as_str).filter(|v| !v.is_empty());
+    let by = by.unwrap_or(if field("host").is_some() {
+        RuleMatch::Domain
+    } else {
+        RuleMatch::Ip
+    });
+
+    let rule = match by {
+        RuleMatch::Domain => {
+            let host = field("host").ok_or_else(|| anyhow!("connection has no host"))?;
+            format!("DOMAIN-SUFFIX,{host},{target}")
+        }
+        RuleMatch::Ip => {
+            let ip: IpAddr = field("destinationIP")
+                .ok_or_else(|| anyhow!("connection has no destination IP"))?
+                .parse()?;
+            let prefix = if ip.is_ipv4() { 32 } else { 128 };
+            let kind = if ip.is_ipv4() { "IP-CIDR" } else { "IP-CIDR6" };
+            format!("{kind},{ip}/{prefix},{target},no-resolve")
+        }
+        RuleMatch::Process => {
+            let process = field("process").ok_or_else(|| anyhow!("connection has no process"))?;
+            format!("PROCESS-NAME,{process},{target}")
+        }
+    };
+    normalize_rule(&rule)
+}
+
+/// 根据活动连接生成一条规则插入到最前面，并关闭该连接使其按新规则重新匹配
+pub async fn create_rule_from_connection(conn_id: &str, target: &str, by: Option<RuleMatch>) -> Result<String> {
+    let mihomo = handle::Handle::mihomo().await;
+    let connection = mihomo
+        .get_connections()
+        .await?
+        .connections
+        .unwrap_or_default()
+        .into_iter()
+        .find(|conn| conn.id == conn_id)
+        .ok_or_else(|| anyhow!("connection {conn_id} not found"))?;
+    drop(mihomo);
+
+    let value = serde_json::to_value(&connection)?;
+    let metadata = value.get("metadata").cloned().unwrap_or_default();
+    let rule = rule_for_connection(&metadata, by, target)?;
+    insert_rule(&rule, Some(0)).await?;
+
+    if let Err(err) = handle::Handle::mihomo().await.close_connection(conn_id).await {
+        logging!(warn, Type::Network, "[规则管理] 关闭连接 {} 失败: {}", conn_id, err);
+    }
+    Ok(rule)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rule_for_connection() {
+        let metadata = serde_json::json!({
+            "host": "api.example.com",
+            "destinationIP": "93.184.216.34",
+            "process": "curl",
+        });
+        let rule = |by| rule_for_connection(&metadata, by, "PROXY").unwrap_or_default();
+        assert_eq!(rule(None), "DOMAIN-SUFFIX,api.example.com,PROXY");
+        assert_eq!(rule(Some(RuleMatch::Ip)), "IP-CIDR,93.184.216.34/32,PROXY,no-resolve");
+        assert_eq!(rule(Some(RuleMatch::Process)), "PROCESS-NAME,curl,PROXY");
+
+        let metadata = serde_json::json!({ "host": "", "destinationIP": "2001:db8::1" });
+        assert_eq!(
+            rule_for_connection(&metadata, None, "DIRECT").unwrap_or_default(),
+            "IP-CIDR6,2001:db8::1/128,DIRECT,no-resolve"
+        );
+        assert!(rule_for_connection(&metadata, Some(RuleMatch::Process), "DIRECT").is_err());
+    }
+}
@@ -0,0 +1,244 @@
+//! 设置锁（访客 / 儿童模式）
+//!
+//! 设置 PIN 后即进入锁定状态，关闭系统代理或 TUN、编辑规则、切换或修改订阅等操作需要先解锁。
+//! 检查在命令层执行，前端隐藏入口只是辅助。PIN 以 PBKDF2-HMAC-SHA256 派生后保存在单独的文件中，
+//! 不会随 Verge 配置返回给前端；解锁在 [`UNLOCK_SECS`] 后自动失效，连续输错会暂时拒绝尝试。
+
+use crate::{
+    config::IVerge,
+    process::AsyncHandler,
+    utils::{crypto, dirs, help},
+};
+use anyhow::{Result, bail};
+use chrono::Local;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use smartstring::alias::String;
+use std::{fmt, path::PathBuf};
+
+const LOCK_FILE: &str = "settings_lock.json";
+const HASH_ROUNDS: u32 = 100_000;
+const MIN_PIN_LEN: usize = 4;
+const UNLOCK_SECS: i64 = 300;
+const MAX_FAILED_ATTEMPTS: u32 = 5;
+const LOCKOUT_SECS: i64 = 60;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PinHash {
+    salt: String,
+    hash: String,
+}
+
+#[derive(Debug, Default)]
+struct LockState {
+    /// 外层为 `None` 表示尚未从文件读取
+    pin: Option<Option<PinHash>>,
+    unlocked_until: i64,
+    failed_attempts: u32,
+    blocked_until: i64,
+}
+
+static STATE: Lazy<Mutex<LockState>> = Lazy::new(|| Mutex::new(LockState::default()));
+
+/// 设置已锁定，命令层据此返回 `locked` 错误
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SettingsLocked;
+
+impl fmt::Display for SettingsLocked {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("settings are locked, unlock with the PIN first")
+    }
+}
+
+impl std::error::Error for SettingsLocked {}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SettingsLockStatus {
+    /// 是否设置了 PIN
+    pub enabled: bool,
+    pub locked: bool,
+    pub unlocked_until: Option<i64>,
+    /// 输错次数过多时，可以再次尝试的时间
+    pub retry_after: Option<i64>,
+}
+
+fn lock_path() -> Result<PathBuf> {
+    Ok(dirs::app_home_dir()?.join(LOCK_FILE))
+}
+
+fn hash_pin(salt: &str, pin: &str) -> String {
+    crypto::to_hex(&crypto::pbkdf2_sha256(pin.as_bytes(), salt.as_bytes(), HASH_ROUNDS)).into()
+}
+
+/// 派生耗时较长，放到阻塞线程执行
+async fn verify_pin(stored: PinHash, pin: &str) -> Result<bool> {
+    let pin = pin.to_owned();
+    Ok(AsyncHandler::spawn_blocking(move || hash_pin(&stored.salt, &pin) == stored.hash).await?)
+}
+
+async fn load_pin() -> Result<Option<PinHash>> {
+    if let Some(pin) = STATE.lock().pin.clone() {
+        return Ok(pin);
+    }
+    let path = lock_path()?;
+    let pin = if path.exists() {
+        Some(serde_json::from_str(&tokio::fs::read_to_string(&path).await?)?)
+    } else {
+        None
+    };
+    STATE.lock().pin = Some(pin.clone());
+    Ok(pin)
+}
+
+pub async fn settings_lock_status() -> Result<SettingsLockStatus> {
+    let enabled = load_pin().await?.is_some();
+    let now = Local::now().timestamp();
+    let (unlocked_until, blocked_until) = {
+        let state = STATE.lock();
+        (state.unlocked_until, state.blocked_until)
+    };
+    let unlocked = unlocked_until > now;
+    Ok(SettingsLockStatus {
+        enabled,
+        locked: enabled && !unlocked,
+        unlocked_until: (enabled && unlocked).then_some(unlocked_until),
+        retry_after: (blocked_until > now).then_some(blocked_until),
+    })
+}
+
+/// 未设置 PIN 或已解锁时通过，否则返回 [`SettingsLocked`]
+pub async fn ensure_unlocked() -> Result<()> {
+    if load_pin().await?.is_none() || STATE.lock().unlocked_until > Local::now().timestamp() {
+        return Ok(());
+    }
+    Err(SettingsLocked.into())
+}
+
+/// 修改 Verge 设置时，只有会让流量绕过代理、改变代理方式或能间接做到这些的修改需要解锁
+pub const fn is_sensitive_patch(patch: &IVerge) -> bool {
+    matches!(patch.enable_system_proxy, Some(false))
+        || matches!(patch.enable_tun_mode, Some(false))
+        || matches!(patch.enable_proxy_guard, Some(false))
+        || patch.proxy_auto_config.is_some()
+        || patch.system_proxy_bypass.is_some()
+        || patch.sysproxy_interfaces.is_some()
+        || patch.enable_kill_switch.is_some()
+        || patch.schedules.is_some()
+        || patch.network_rules.is_some()
+        || patch.hotkeys.is_some()
+        || patch.enable_external_controller.is_some()
+        || patch.enable_control_api.is_some()
+        || patch.enable_lan_sync.is_some()
+}
+
+/// 校验 PIN，成功后在一段时间内解除锁定
+pub async fn unlock_settings(pin: &str) -> Result<bool> {
+    let Some(stored) = load_pin().await? else {
+        return Ok(true);
+    };
+    let now = Local::now().timestamp();
+    if STATE.lock().blocked_until > now {
+        bail!("too many failed attempts, try again later");
+    }
+
+    let matched = verify_pin(stored, pin).await?;
+    let mut state = STATE.lock();
+    if matched {
+        state.failed_attempts = 0;
+        state.unlocked_until = now + UNLOCK_SECS;
+    } else {
+        state.failed_attempts += 1;
+        if state.failed_attempts >= MAX_FAILED_ATTEMPTS {
+            state.failed_attempts = 0;
+            state.blocked_until = now + LOCKOUT_SECS;
+        }
+    }
+    drop(state);
+    Ok(matched)
+}
+
+/// 立即重新锁定
+pub fn lock_settings() {
+    STATE.lock().unlocked_until = 0;
+}
+
+/// 设置、修改或移除 PIN，已有 PIN 时需要处于解锁状态或提供当前 PIN
+pub async fn set_settings_pin(current: Option<&str>, pin: Option<&str>) -> Result<()> {
+    if load_pin().await?.is_some() {
+        let verified = match current {
+            Some(current) => unlock_settings(current).await?,
+            None => ensure_unlocked().await.is_ok(),
+        };
+        if !verified {
+            bail!("the current PIN is incorrect");
+        }
+    }
+
+    let path = lock_path()?;
+    let pin = match pin {
+        Some(pin) => {
+            if pin.chars().count() < MIN_PIN_LEN {
+                bail!("the PIN must be at least {MIN_PIN_LEN} characters");
+            }
+            let salt = help::get_uid("");
+            let pin = pin.to_owned();
+            let hash = {
+                let salt = salt.clone();
+                AsyncHandler::spawn_blocking(move || hash_pin(&salt, &pin)).await?
+            };
+            let pin = PinHash { hash, salt };
+            tokio::fs::write(&path, serde_json::to_string(&pin)?).await?;
+            Some(pin)
+        }
+        None => {
+            if path.exists() {
+                tokio::fs::remove_file(&path).await?;
+            }
+            None
+        }
+    };
+
+    // 设置新 PIN 后立即进入锁定状态
+    *STATE.lock() = LockState {
+        pin: Some(pin),
+        ..LockState::default()
+    };
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_pin() {
+        let hash = hash_pin("salt", "1234");
+        assert_eq!(hash.len(), 64);
+        assert_eq!(hash, hash_pin("salt", "1234"));
+        assert_ne!(hash, hash_pin("other", "1234"));
+        assert_ne!(hash, hash_pin("salt", "4321"));
+    }
+
+    #[test]
+    fn test_sensitive_patch() {
+        let patch = |patch: IVerge| is_sensitive_patch(&patch);
+        assert!(patch(IVerge {
+            enable_system_proxy: Some(false),
+            ..IVerge::default()
+        }));
+        assert!(!patch(IVerge {
+            enable_system_proxy: Some(true),
+            ..IVerge::default()
+        }));
+        assert!(patch(IVerge {
+            schedules: Some(Vec::new()),
+            ..IVerge::default()
+        }));
+        assert!(patch(IVerge {
+            enable_control_api: Some(true),
+            ..IVerge::default()
+        }));
+        assert!(!patch(IVerge::default()));
+    }
+}
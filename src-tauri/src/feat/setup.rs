@@ -0,0 +1,242 @@
+//! 首次运行引导
+//!
+//! 按顺序检查：内核文件 → 端口是否空闲 → 是否已有订阅 → 能否设置系统代理，前端据此逐步引导新用户。
+//! 每一步需要检查通过后才能完成，系统代理一步可以跳过（之后可改用 TUN 或手动设置代理）。
+//! 进度保存在 [`SETUP_FILE`]；升级前已经配置过订阅的用户视为已完成引导。
+
+use super::{CheckStatus, check_port_conflicts, configured_ports};
+use crate::{
+    config::Config,
+    core::{
+        CoreManager,
+        manager::{RunningMode, core_binary_path},
+    },
+    utils::{dirs, help},
+};
+use anyhow::{Result, bail};
+use serde::{Deserialize, Serialize};
+use smartstring::alias::String;
+use std::path::PathBuf;
+
+const SETUP_FILE: &str = "setup.yaml";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SetupStep {
+    Core,
+    Ports,
+    Profile,
+    SystemProxy,
+}
+
+impl SetupStep {
+    const ALL: [Self; 4] = [Self::Core, Self::Ports, Self::Profile, Self::SystemProxy];
+
+    /// 检查未通过时仍可完成的步骤
+    const fn optional(self) -> bool {
+        matches!(self, Self::SystemProxy)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct SetupProgress {
+    completed: Vec<SetupStep>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SetupStepStatus {
+    pub step: SetupStep,
+    pub status: CheckStatus,
+    pub detail: String,
+    pub optional: bool,
+    pub completed: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SetupStatus {
+    /// 所有步骤均已完成，前端不再显示引导
+    pub finished: bool,
+    /// 下一个待完成的步骤
+    pub current: Option<SetupStep>,
+    pub steps: Vec<SetupStepStatus>,
+}
+
+fn setup_path() -> Result<PathBuf> {
+    Ok(dirs::app_home_dir()?.join(SETUP_FILE))
+}
+
+async fn load_progress() -> Result<SetupProgress> {
+    let path = setup_path()?;
+    if path.exists() {
+        return help::read_yaml(&path).await;
+    }
+    // 没有进度文件但已有订阅，说明是升级前就在使用的安装
+    let configured = Config::profiles().await.latest_arc().current.is_some();
+    Ok(SetupProgress {
+        completed: if configured {
+            SetupStep::ALL.to_vec()
+        } else {
+            Vec::new()
+        },
+    })
+}
+
+fn current_step(completed: &[SetupStep]) -> Option<SetupStep> {
+    SetupStep::ALL.into_iter().find(|step| !completed.contains(step))
+}
+
+/// 只能完成当前步骤，必需步骤的检查必须通过；已完成的步骤重复提交时直接返回
+fn advance(completed: &mut Vec<SetupStep>, step: SetupStep, status: CheckStatus, detail: &str) -> Result<()> {
+    if completed.contains(&step) {
+        return Ok(());
+    }
+    if current_step(completed) != Some(step) {
+        bail!("complete the previous setup steps first");
+    }
+    if status == CheckStatus::Fail && !step.optional() {
+        bail!("setup step {step:?} has not passed its check yet: {detail}");
+    }
+    completed.push(step);
+    Ok(())
+}
+
+async fn check_core() -> (CheckStatus, String) {
+    if !matches!(*CoreManager::global().get_running_mode(), RunningMode::NotRunning) {
+        return (CheckStatus::Pass, "core is running".into());
+    }
+    let verge = Config::verge().await.latest_arc();
+    let core = verge.get_valid_clash_core();
+    match core_binary_path(&verge, &core) {
+        Ok(path) if path.exists() => (CheckStatus::Pass, path.display().to_string().into()),
+        Ok(path) => (
+            CheckStatus::Fail,
+            format!("core not found at {}", path.display()).into(),
+        ),
+        Err(err) => (CheckStatus::Fail, err.to_string().into()),
+    }
+}
+
+async fn check_ports() -> (CheckStatus, String) {
+    let conflicts = check_port_conflicts(&configured_ports().await).await;
+    if conflicts.is_empty() {
+        return (CheckStatus::Pass, "all configured ports are free".into());
+    }
+    let detail = conflicts
+        .iter()
+        .map(|conflict| match &conflict.process {
+            Some(process) => format!("{} ({process})", conflict.port),
+            None => conflict.port.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    (
+        CheckStatus::Fail,
+        format!("ports in use by other programs: {detail}").into(),
+    )
+}
+
+async fn check_profile() -> (CheckStatus, String) {
+    let profiles = Config::profiles().await.latest_arc();
+    match profiles.current.as_ref().and_then(|uid| profiles.get_item(uid).ok()) {
+        Some(item) => (
+            CheckStatus::Pass,
+            item.name.clone().unwrap_or_else(|| "profile is configured".into()),
+        ),
+        None => (CheckStatus::Fail, "no profile has been imported yet".into()),
+    }
+}
+
+/// 按用户写注册表，无需额外权限
+#[cfg(target_os = "windows")]
+fn check_system_proxy() -> (CheckStatus, String) {
+    (CheckStatus::Pass, "system proxy can be set for the current user".into())
+}
+
+#[cfg(target_os = "macos")]
+fn check_system_proxy() -> (CheckStatus, String) {
+    match crate::core::proxy_scope::list_proxy_interfaces() {
+        Ok(services) if !services.is_empty() => (CheckStatus::Pass, services.join(", ").into()),
+        Ok(_) => (CheckStatus::Warn, "no network services found".into()),
+        Err(err) => (CheckStatus::Fail, err.to_string().into()),
+    }
+}
+
+/// GNOME、KDE 可直接修改桌面设置，其他桌面写入 environment.d，需要重新登录才生效
+#[cfg(target_os = "linux")]
+fn check_system_proxy(backend: Option<&str>) -> (CheckStatus, String) {
+    use crate::core::linux_proxy::DesktopBackend;
+
+    match DesktopBackend::resolve(backend) {
+        DesktopBackend::Environment => (
+            CheckStatus::Warn,
+            "using environment.d, takes effect after logging in again".into(),
+        ),
+        backend => (CheckStatus::Pass, format!("using {}", backend.name()).into()),
+    }
+}
+
+async fn check_step(step: SetupStep) -> (CheckStatus, String) {
+    match step {
+        SetupStep::Core => check_core().await,
+        SetupStep::Ports => check_ports().await,
+        SetupStep::Profile => check_profile().await,
+        #[cfg(target_os = "linux")]
+        SetupStep::SystemProxy => {
+            let verge = Config::verge().await.latest_arc();
+            check_system_proxy(verge.linux_proxy_backend.as_deref())
+        }
+        #[cfg(not(target_os = "linux"))]
+        SetupStep::SystemProxy => check_system_proxy(),
+    }
+}
+
+/// 运行所有检查并返回引导进度
+pub async fn get_setup_status() -> Result<SetupStatus> {
+    let progress = load_progress().await?;
+    let mut steps = Vec::new();
+    for step in SetupStep::ALL {
+        let (status, detail) = check_step(step).await;
+        steps.push(SetupStepStatus {
+            step,
+            status,
+            detail,
+            optional: step.optional(),
+            completed: progress.completed.contains(&step),
+        });
+    }
+    let current = current_step(&progress.completed);
+    Ok(SetupStatus {
+        finished: current.is_none(),
+        current,
+        steps,
+    })
+}
+
+/// 重新检查并完成当前步骤
+pub async fn complete_setup_step(step: SetupStep) -> Result<SetupStatus> {
+    let mut progress = load_progress().await?;
+    let (status, detail) = check_step(step).await;
+    advance(&mut progress.completed, step, status, &detail)?;
+    help::save_yaml(&setup_path()?, &progress, Some("# Clash Verge Setup Progress")).await?;
+    get_setup_status().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_advance_in_order() {
+        let mut completed = Vec::new();
+        assert!(advance(&mut completed, SetupStep::Ports, CheckStatus::Pass, "").is_err());
+        assert!(advance(&mut completed, SetupStep::Core, CheckStatus::Fail, "").is_err());
+        assert!(advance(&mut completed, SetupStep::Core, CheckStatus::Pass, "").is_ok());
+        assert_eq!(current_step(&completed), Some(SetupStep::Ports));
+        assert!(advance(&mut completed, SetupStep::Core, CheckStatus::Fail, "").is_ok());
+
+        completed = vec![SetupStep::Core, SetupStep::Ports, SetupStep::Profile];
+        assert!(advance(&mut completed, SetupStep::SystemProxy, CheckStatus::Fail, "").is_ok());
+        assert_eq!(current_step(&completed), None);
+    }
+}
@@ -0,0 +1,293 @@
+//! 经代理测速
+//!
+//! 通过本机混合端口访问测速服务器，依次测量延迟、下载与上传速度，结果写入测速历史，
+//! 便于按节点对比。指定节点时临时在主代理组中切换到该节点，结束（包括出错或取消）后恢复原来的选择。
+//! 混合端口的流量按用户规则分流，测速服务器可能被规则导向其他出站或直连，
+//! 因此每个阶段都会从内核的 `/connections` 核对连接的实际代理链，不经过被测节点时放弃本次结果。
+
+use crate::{
+    config::{Config, ISpeedtestServer},
+    core::{
+        handle,
+        stats::{SpeedtestHistory, SpeedtestRecord},
+    },
+    process::AsyncHandler,
+    utils::network::{NetworkManager, ProxyType},
+};
+use anyhow::{Result, anyhow, bail};
+use clash_verge_logging::{Type, logging, logging_error};
+use reqwest::Client;
+use serde_json::Value;
+use smartstring::alias::String;
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, Instant},
+};
+
+const DEFAULT_LATENCY_URL: &str = "https://www.gstatic.com/generate_204";
+const REQUEST_TIMEOUT_SECS: u64 = 30;
+const LATENCY_SAMPLES: usize = 3;
+/// 下载超过该时长后停止，按已下载的数据计算速度
+const DOWNLOAD_DURATION: Duration = Duration::from_secs(10);
+const UPLOAD_BYTES: usize = 10 * 1024 * 1024;
+
+static RUNNING: AtomicBool = AtomicBool::new(false);
+
+fn default_server() -> ISpeedtestServer {
+    ISpeedtestServer {
+        name: "Cloudflare".into(),
+        download_url: "https://speed.cloudflare.com/__down?bytes=25000000".into(),
+        upload_url: Some("https://speed.cloudflare.com/__up".into()),
+        latency_url: None,
+    }
+}
+
+/// 按名称选择测速服务器，未指定时使用第一个
+async fn pick_server(name: Option<&str>) -> Result<ISpeedtestServer> {
+    let servers = Config::verge()
+        .await
+        .latest_arc()
+        .speedtest_servers
+        .clone()
+        .unwrap_or_default();
+    match name {
+        Some(name) => servers
+            .into_iter()
+            .chain(std::iter::once(default_server()))
+            .find(|server| server.name == name)
+            .ok_or_else(|| anyhow!("speedtest server not found: {name}")),
+        None => Ok(servers.into_iter().next().unwrap_or_else(default_server)),
+    }
+}
+
+/// 字节数与耗时换算为 bit/s
+fn bits_per_second(bytes: u64, elapsed: Duration) -> u64 {
+    let millis = elapsed.as_millis().max(1);
+    u64::try_from(u128::from(bytes) * 8000 / millis).unwrap_or(u64::MAX)
+}
+
+fn median(mut samples: Vec<u64>) -> Option<u64> {
+    samples.sort_unstable();
+    samples.get(samples.len() / 2).copied()
+}
+
+/// 连接到 `host` 的代理链，出站节点在前
+fn route_to(connections: &[Value], host: &str) -> Option<Vec<std::string::String>> {
+    connections
+        .iter()
+        .find(|conn| {
+            conn.pointer("/metadata/host")
+                .and_then(Value::as_str)
+                .is_some_and(|h| h.eq_ignore_ascii_case(host))
+        })?
+        .get("chains")?
+        .as_array()
+        .map(|chains| chains.iter().filter_map(Value::as_str).map(ToOwned::to_owned).collect())
+}
+
+/// 核对到 `url` 的连接是否经过被测节点，`required` 为假时找不到连接视为无法核对而放行
+async fn verify_route(url: &str, node: &str, required: bool) -> Result<()> {
+    let host = reqwest::Url::parse(url)?
+        .host_str()
+        .ok_or_else(|| anyhow!("invalid url: {url}"))?
+        .to_owned();
+    let connections = handle::Handle::mihomo().await.get_connections().await?;
+    let connections: Vec<Value> = connections
+        .connections
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|conn| serde_json::to_value(conn).ok())
+        .collect();
+    match route_to(&connections, &host) {
+        Some(chains) if chains.iter().any(|hop| hop == node) => Ok(()),
+        Some(chains) => bail!(
+            "traffic to {host} went through {} instead of {node}",
+            chains.join(" <- ")
+        ),
+        None if required => bail!("unable to verify the route to {host}"),
+        None => {
+            logging!(warn, Type::Network, "Speed test could not verify the route to {host}");
+            Ok(())
+        }
+    }
+}
+
+async fn measure_latency(client: &Client, url: &str) -> Result<u64> {
+    let mut samples = Vec::with_capacity(LATENCY_SAMPLES);
+    let mut last_err = None;
+    for _ in 0..LATENCY_SAMPLES {
+        let start = Instant::now();
+        match client
+            .get(url)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+        {
+            Ok(_) => samples.push(u64::try_from(start.elapsed().as_millis()).unwrap_or(u64::MAX)),
+            Err(err) => last_err = Some(err),
+        }
+    }
+    match (median(samples), last_err) {
+        (Some(latency), _) => Ok(latency),
+        (None, Some(err)) => Err(err.into()),
+        (None, None) => bail!("no latency sample"),
+    }
+}
+
+/// 下载过程中连接一定存在，在此核对实际出站
+async fn measure_download(client: &Client, url: &str, node: &str) -> Result<u64> {
+    let start = Instant::now();
+    let mut response = client.get(url).send().await?.error_for_status()?;
+    let mut bytes = 0u64;
+    let mut verified = false;
+    while let Some(chunk) = response.chunk().await? {
+        if !verified {
+            verify_route(url, node, true).await?;
+            verified = true;
+        }
+        bytes += u64::try_from(chunk.len()).unwrap_or_default();
+        if start.elapsed() >= DOWNLOAD_DURATION {
+            break;
+        }
+    }
+    Ok(bits_per_second(bytes, start.elapsed()))
+}
+
+async fn measure_upload(client: &Client, url: &str) -> Result<u64> {
+    let body = vec![0u8; UPLOAD_BYTES];
+    let start = Instant::now();
+    client.post(url).body(body).send().await?.error_for_status()?;
+    Ok(bits_per_second(
+        u64::try_from(UPLOAD_BYTES).unwrap_or_default(),
+        start.elapsed(),
+    ))
+}
+
+async fn measure(node: String, server: &ISpeedtestServer) -> Result<SpeedtestRecord> {
+    let latency_url = match &server.latency_url {
+        Some(url) => url.clone(),
+        None => Config::verge()
+            .await
+            .latest_arc()
+            .default_latency_test
+            .clone()
+            .unwrap_or_else(|| DEFAULT_LATENCY_URL.into()),
+    };
+    let client = NetworkManager::new()
+        .create_request(ProxyType::Localhost, Some(REQUEST_TIMEOUT_SECS), None, false)
+        .await?;
+
+    // 延迟测试失败说明代理不可用，不再继续；连接池保持的连接可用于核对出站
+    let latency = measure_latency(&client, &latency_url).await?;
+    verify_route(&latency_url, &node, false).await?;
+    // 下载阶段无法核对出站时结果无法归属到该节点，直接放弃
+    let download = measure_download(&client, &server.download_url, &node).await?;
+    let mut errors = Vec::new();
+    let upload = match &server.upload_url {
+        Some(url) => match measure_upload(&client, url).await {
+            Ok(bps) => {
+                verify_route(url, &node, false).await?;
+                Some(bps)
+            }
+            Err(err) => {
+                errors.push(format!("upload: {err}"));
+                None
+            }
+        },
+        None => None,
+    };
+
+    Ok(SpeedtestRecord {
+        time: chrono::Local::now().timestamp(),
+        node,
+        server: server.name.clone(),
+        latency_ms: Some(latency),
+        download_bps: Some(download),
+        upload_bps: upload,
+        error: (!errors.is_empty()).then(|| errors.join("; ").into()),
+    })
+}
+
+/// 经代理测速并记录结果，`node` 为空时测试主代理组当前选中的节点
+pub async fn run_speedtest(node: Option<&str>, server: Option<&str>) -> Result<SpeedtestRecord> {
+    if RUNNING.swap(true, Ordering::SeqCst) {
+        bail!("a speed test is already running");
+    }
+    let _guard = scopeguard::guard((), |()| RUNNING.store(false, Ordering::SeqCst));
+
+    let server = pick_server(server).await?;
+    let proxies = handle::Handle::mihomo().await.get_proxies().await?;
+    let mode = super::clash_mode().await;
+    let group: String = super::main_group(&proxies, &mode, node)
+        .ok_or_else(|| match node {
+            Some(node) => anyhow!("no proxy group contains {node}"),
+            None => anyhow!("no proxy group to test"),
+        })?
+        .into();
+    let previous: Option<String> = proxies
+        .proxies
+        .get(group.as_str())
+        .and_then(|group| group.now.as_deref())
+        .map(Into::into);
+    drop(proxies);
+
+    let switch_to = node.filter(|node| previous.as_deref() != Some(*node));
+    if let Some(node) = switch_to {
+        handle::Handle::mihomo()
+            .await
+            .select_node_for_group(&group, node)
+            .await?;
+    }
+    let tested: String = node.map(Into::into).or_else(|| previous.clone()).unwrap_or_default();
+    // 无论正常结束、出错还是任务被取消，都恢复测速前的选择
+    let _restore = scopeguard::guard((switch_to.is_some(), group.clone(), previous), |state| {
+        if let (true, group, Some(previous)) = state {
+            AsyncHandler::spawn(move || async move {
+                logging_error!(
+                    Type::Network,
+                    handle::Handle::mihomo()
+                        .await
+                        .select_node_for_group(&group, &previous)
+                        .await
+                );
+            });
+        }
+    });
+    logging!(
+        info,
+        Type::Network,
+        "Speed test via {tested} ({group}) against {}",
+        server.name
+    );
+    let result = measure(tested, &server).await;
+
+    let record = result?;
+    logging_error!(Type::Network, SpeedtestHistory::append(&record).await);
+    Ok(record)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rates() {
+        assert_eq!(bits_per_second(1_000_000, Duration::from_secs(1)), 8_000_000);
+        assert_eq!(bits_per_second(1, Duration::ZERO), 8000);
+        assert_eq!(median(vec![30, 10, 20]), Some(20));
+        assert_eq!(median(Vec::new()), None);
+    }
+
+    #[test]
+    fn test_route_to() {
+        let connections = vec![
+            serde_json::json!({ "metadata": { "host": "example.com" }, "chains": ["DIRECT"] }),
+            serde_json::json!({ "metadata": { "host": "speed.cloudflare.com" }, "chains": ["HK 01", "Auto", "Proxy"] }),
+        ];
+        assert_eq!(
+            route_to(&connections, "Speed.Cloudflare.com"),
+            Some(vec!["HK 01".to_owned(), "Auto".to_owned(), "Proxy".to_owned()])
+        );
+        assert_eq!(route_to(&connections, "other.com"), None);
+    }
+}
@@ -0,0 +1,290 @@
+//! 开启 TUN 前的预检
+//!
+//! 检查权限（管理员或服务）、可能冲突的 VPN 网卡、默认路由与防火墙状态，
+//! 生成结构化报告，避免 TUN 开启后静默失败。开启 TUN 时由 [`ensure_tun_ready`] 执行，存在失败项时拒绝开启。
+//! 命令输出可能随系统语言翻译，只解析数值与 PowerShell 返回的布尔值。
+
+use crate::{
+    config::Config,
+    core::{handle, service},
+    process::AsyncHandler,
+};
+use anyhow::{Result, bail};
+use clash_verge_logging::{Type, logging};
+use serde::Serialize;
+use smartstring::alias::String;
+use std::process::Command;
+use tauri_plugin_clash_verge_sysinfo::is_current_app_handle_admin;
+
+/// 常见 VPN / 虚拟网卡名称特征（小写）
+const VPN_ADAPTER_PATTERNS: [&str; 12] = [
+    "wg",
+    "wireguard",
+    "tailscale",
+    "zerotier",
+    "zt",
+    "openvpn",
+    "tap",
+    "ppp",
+    "ipsec",
+    "vpn",
+    "wintun",
+    "anyconnect",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+    /// 前置检查失败而未执行
+    Skip,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TunCheck {
+    pub id: &'static str,
+    pub status: CheckStatus,
+    pub detail: String,
+}
+
+impl TunCheck {
+    fn new(id: &'static str, status: CheckStatus, detail: impl Into<String>) -> Self {
+        Self {
+            id,
+            status,
+            detail: detail.into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TunReadiness {
+    /// 没有失败项时为 true，警告不影响开启
+    pub ready: bool,
+    pub checks: Vec<TunCheck>,
+}
+
+fn run(program: &str, args: &[&str]) -> Option<std::string::String> {
+    let mut command = Command::new(program);
+    command.args(args);
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::process::CommandExt as _;
+        command.creation_flags(0x08000000);
+    }
+    let output = command.output().ok()?;
+    Some(std::string::String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// 按名称判断是否为其他 VPN 的网卡，排除内核自己的 TUN 设备
+fn is_vpn_adapter(name: &str, own_device: &str) -> bool {
+    let lower = name.to_ascii_lowercase();
+    if lower == own_device.to_ascii_lowercase() {
+        return false;
+    }
+    VPN_ADAPTER_PATTERNS
+        .iter()
+        .any(|pattern| lower.starts_with(pattern) || (pattern.len() > 3 && lower.contains(pattern)))
+        || (lower.starts_with("tun") && lower != "tunl0")
+}
+
+/// 统计路由表输出中的默认路由条数
+fn count_default_routes(output: &str) -> usize {
+    output
+        .lines()
+        .map(str::trim_start)
+        .filter(|line| line.starts_with("default") || line.starts_with("0.0.0.0 "))
+        .count()
+}
+
+fn check_privilege(is_admin: bool, service_available: bool) -> TunCheck {
+    if is_admin {
+        TunCheck::new("privilege", CheckStatus::Pass, "running with administrator privileges")
+    } else if service_available {
+        TunCheck::new("privilege", CheckStatus::Pass, "system service is available")
+    } else {
+        TunCheck::new(
+            "privilege",
+            CheckStatus::Fail,
+            "neither administrator privileges nor the system service is available",
+        )
+    }
+}
+
+fn check_vpn_adapters(own_device: &str) -> TunCheck {
+    use network_interface::{Addr, NetworkInterface, NetworkInterfaceConfig as _};
+
+    let Ok(interfaces) = NetworkInterface::show() else {
+        return TunCheck::new("vpn-adapters", CheckStatus::Warn, "failed to list network interfaces");
+    };
+    let mut adapters: Vec<&str> = interfaces
+        .iter()
+        .filter(|i| {
+            i.addr
+                .iter()
+                .any(|addr| matches!(addr, Addr::V4(v4) if !v4.ip.is_loopback() && !v4.ip.is_link_local()))
+        })
+        .map(|i| i.name.as_str())
+        .filter(|name| is_vpn_adapter(name, own_device))
+        .collect();
+    adapters.sort_unstable();
+    adapters.dedup();
+
+    if adapters.is_empty() {
+        TunCheck::new("vpn-adapters", CheckStatus::Pass, "no conflicting VPN adapters")
+    } else {
+        TunCheck::new(
+            "vpn-adapters",
+            CheckStatus::Warn,
+            format!("active VPN adapters may conflict: {}", adapters.join(", ")),
+        )
+    }
+}
+
+fn check_default_route() -> TunCheck {
+    #[cfg(target_os = "windows")]
+    let output = run("route", &["print", "-4", "0.0.0.0"]);
+    #[cfg(target_os = "macos")]
+    let output = run("netstat", &["-rn", "-f", "inet"]);
+    #[cfg(target_os = "linux")]
+    let output = run("ip", &["-4", "route", "show", "default"]);
+
+    match output.as_deref().map(count_default_routes) {
+        None => TunCheck::new("default-route", CheckStatus::Warn, "failed to read the routing table"),
+        Some(0) => TunCheck::new(
+            "default-route",
+            CheckStatus::Fail,
+            "no default route, check the network connection",
+        ),
+        Some(1) => TunCheck::new("default-route", CheckStatus::Pass, "one default route"),
+        Some(count) => TunCheck::new(
+            "default-route",
+            CheckStatus::Warn,
+            format!("{count} default routes, another VPN may be routing traffic"),
+        ),
+    }
+}
+
+/// 防火墙开启时提示放行内核，不视为失败
+fn check_firewall() -> TunCheck {
+    #[cfg(target_os = "windows")]
+    let enabled = run(
+        "powershell",
+        &[
+            "-NoProfile",
+            "-NonInteractive",
+            "-Command",
+            "[bool](Get-NetFirewallProfile -PolicyStore ActiveStore | Where-Object { $_.Enabled -eq 'True' })",
+        ],
+    )
+    .and_then(|out| match out.trim() {
+        "True" => Some(true),
+        "False" => Some(false),
+        _ => None,
+    });
+    #[cfg(target_os = "macos")]
+    let enabled = run("/usr/libexec/ApplicationFirewall/socketfilterfw", &["--getglobalstate"])
+        .map(|out| out.contains("enabled"));
+    #[cfg(target_os = "linux")]
+    let enabled = run("ufw", &["status"]).map(|out| out.contains("Status: active"));
+
+    match enabled {
+        None => TunCheck::new("firewall", CheckStatus::Pass, "firewall state unknown"),
+        Some(false) => TunCheck::new("firewall", CheckStatus::Pass, "firewall is disabled"),
+        Some(true) => TunCheck::new(
+            "firewall",
+            CheckStatus::Warn,
+            "firewall is enabled, make sure the core is allowed through it",
+        ),
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn check_tun_device() -> TunCheck {
+    if std::path::Path::new("/dev/net/tun").exists() {
+        TunCheck::new("tun-device", CheckStatus::Pass, "/dev/net/tun is available")
+    } else {
+        TunCheck::new(
+            "tun-device",
+            CheckStatus::Fail,
+            "/dev/net/tun is missing, load the tun kernel module",
+        )
+    }
+}
+
+/// 运行 TUN 预检
+pub async fn check_tun_readiness() -> TunReadiness {
+    let is_admin = is_current_app_handle_admin(handle::Handle::app_handle());
+    let service_available = service::is_service_available().await.is_ok();
+    let own_device: String = Config::clash()
+        .await
+        .latest_arc()
+        .0
+        .get("tun")
+        .and_then(|tun| tun.get("device"))
+        .and_then(|device| device.as_str())
+        .unwrap_or("Mihomo")
+        .into();
+
+    let mut checks = vec![check_privilege(is_admin, service_available)];
+    match AsyncHandler::spawn_blocking(move || {
+        let mut checks = vec![check_vpn_adapters(&own_device), check_default_route(), check_firewall()];
+        #[cfg(target_os = "linux")]
+        checks.push(check_tun_device());
+        checks
+    })
+    .await
+    {
+        Ok(system_checks) => checks.extend(system_checks),
+        Err(err) => logging!(warn, Type::Network, "TUN preflight checks failed: {err}"),
+    }
+
+    let ready = checks.iter().all(|check| check.status != CheckStatus::Fail);
+    TunReadiness { ready, checks }
+}
+
+/// 开启 TUN 前运行预检，存在失败项时返回包含失败原因的错误
+pub async fn ensure_tun_ready() -> Result<()> {
+    let readiness = check_tun_readiness().await;
+    if readiness.ready {
+        return Ok(());
+    }
+    let failures: Vec<&str> = readiness
+        .checks
+        .iter()
+        .filter(|check| check.status == CheckStatus::Fail)
+        .map(|check| check.detail.as_str())
+        .collect();
+    logging!(warn, Type::Network, "TUN preflight failed: {}", failures.join("; "));
+    bail!("TUN mode is not ready: {}", failures.join("; "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_vpn_adapter() {
+        assert!(is_vpn_adapter("wg0", "Mihomo"));
+        assert!(is_vpn_adapter("tailscale0", "Mihomo"));
+        assert!(is_vpn_adapter("OpenVPN TAP-Windows6", "Mihomo"));
+        assert!(is_vpn_adapter("tun0", "Mihomo"));
+        assert!(!is_vpn_adapter("Mihomo", "Mihomo"));
+        assert!(!is_vpn_adapter("eth0", "Mihomo"));
+        assert!(!is_vpn_adapter("en0", "Mihomo"));
+        assert!(!is_vpn_adapter("Wi-Fi", "Mihomo"));
+    }
+
+    #[test]
+    fn test_count_default_routes() {
+        let linux = "default via 192.168.1.1 dev eth0 proto dhcp\ndefault via 10.8.0.1 dev tun0\n";
+        assert_eq!(count_default_routes(linux), 2);
+        let macos = "Routing tables\n\nInternet:\nDestination        Gateway\ndefault            192.168.1.1\n127        127.0.0.1\n";
+        assert_eq!(count_default_routes(macos), 1);
+        let windows = "Active Routes:\nNetwork Destination        Netmask          Gateway\n          0.0.0.0          0.0.0.0      192.168.1.1\n";
+        assert_eq!(count_default_routes(windows), 1);
+        assert_eq!(count_default_routes(""), 0);
+    }
+}
@@ -1,9 +1,9 @@
-use crate::config::Config;
+use crate::config::{Config, ConfigPersistence};
 use crate::core::{CoreManager, handle, sysopt};
 use crate::module::lightweight;
 use crate::utils;
 use crate::utils::window_manager::WindowManager;
-use clash_verge_logging::{Type, logging};
+use clash_verge_logging::{Type, logging, logging_error};
 use tokio::time::{Duration, timeout};
 
 /// Public API: open or close the dashboard
@@ -24,6 +24,8 @@ pub async fn quit() {
     handle::Handle::global().set_is_exiting();
 
     utils::server::shutdown_embedded_server();
+    // 写入防抖中尚未落盘的修改
+    logging_error!(Type::Config, ConfigPersistence::global().flush().await);
     Config::apply_all_and_save_file().await;
 
     logging!(info, Type::System, "开始异步清理资源");
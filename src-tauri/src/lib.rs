@@ -1,6 +1,7 @@
 #![allow(non_snake_case)]
 #![recursion_limit = "512"]
 
+pub mod cli;
 mod cmd;
 pub mod config;
 mod constants;
@@ -9,6 +10,7 @@ mod enhance;
 mod feat;
 mod module;
 mod process;
+pub mod sandbox;
 pub mod utils;
 use crate::constants::files;
 use crate::{
@@ -116,6 +118,8 @@ mod app_init {
                 .macos_launcher(MacosLauncher::LaunchAgent)
                 .app_name(&app.config().identifier);
         }
+        // 带上参数以区分开机自启与手动启动
+        let auto_start_plugin_builder = auto_start_plugin_builder.arg(utils::autostart::AUTOSTART_ARG);
         app.handle().plugin(auto_start_plugin_builder.build())?;
         Ok(())
     }
@@ -170,12 +174,25 @@ mod app_init {
             cmd::get_runtime_config,
             cmd::get_runtime_yaml,
             cmd::get_runtime_exists,
+            cmd::preview_config_diff,
             cmd::get_runtime_logs,
+            cmd::get_script_logs,
+            cmd::list_rules,
+            cmd::insert_rule,
+            cmd::delete_rule,
+            cmd::move_rule,
+            cmd::create_rule_from_connection,
+            cmd::list_rule_providers,
+            cmd::refresh_rule_provider,
+            cmd::add_rule_provider,
+            cmd::remove_rule_provider,
             cmd::get_runtime_proxy_chain_config,
             cmd::update_proxy_chain_config_in_runtime,
             cmd::invoke_uwp_tool,
             cmd::copy_clash_env,
             cmd::sync_tray_proxy_selection,
+            cmd::parse_share_links,
+            cmd::export_node_share_link,
             cmd::save_dns_config,
             cmd::apply_dns_config,
             cmd::check_dns_config_exists,
@@ -191,6 +208,88 @@ mod app_init {
             cmd::open_devtools,
             cmd::exit_app,
             cmd::get_network_interfaces_info,
+            cmd::check_tun_readiness,
+            cmd::get_sysproxy_interfaces,
+            cmd::set_sysproxy_interfaces,
+            cmd::enable_pac_mode,
+            cmd::set_pac_template,
+            cmd::get_proxy_bypass,
+            cmd::set_proxy_bypass,
+            cmd::get_lan_share_info,
+            cmd::set_lan_sharing,
+            cmd::list_lan_clients,
+            cmd::check_port_conflicts,
+            cmd::auto_reassign_ports,
+            cmd::run_network_doctor,
+            cmd::get_exit_ip_info,
+            cmd::get_network_state,
+            cmd::list_proxy_chains,
+            cmd::create_proxy_chain,
+            cmd::delete_proxy_chain,
+            cmd::list_installed_apps,
+            cmd::get_app_routing,
+            cmd::set_app_routing,
+            cmd::list_schedules,
+            cmd::upsert_schedule,
+            cmd::delete_schedule,
+            cmd::list_tray_icon_themes,
+            cmd::set_tray_icon_theme,
+            cmd::get_hotkeys,
+            cmd::set_hotkeys,
+            cmd::copy_proxy_env,
+            cmd::send_test_notification,
+            cmd::list_webhooks,
+            cmd::add_webhook,
+            cmd::delete_webhook,
+            cmd::backup_now,
+            cmd::list_backups,
+            cmd::restore_backup,
+            cmd::export_settings,
+            cmd::import_settings,
+            cmd::discover_peers,
+            cmd::start_lan_pairing,
+            cmd::pair_with_peer,
+            cmd::sync_with_peer,
+            cmd::regenerate_control_api_token,
+            cmd::set_log_level,
+            cmd::collect_logs,
+            cmd::subscribe_core_logs,
+            cmd::unsubscribe_core_logs,
+            cmd::generate_diagnostics_bundle,
+            cmd::list_crash_reports,
+            cmd::open_crash_reports_dir,
+            cmd::upload_crash_report,
+            cmd::check_app_update,
+            cmd::install_app_update,
+            cmd::defer_app_update,
+            cmd::migrate_app_home,
+            cmd::rotate_core_secret,
+            cmd::audit_exposure,
+            cmd::service_status,
+            cmd::get_startup_report,
+            cmd::get_favorites,
+            cmd::toggle_favorite,
+            cmd::select_node_by_region,
+            cmd::get_providers_status,
+            cmd::update_all_providers,
+            cmd::flush_config,
+            cmd::run_speedtest,
+            cmd::get_speedtest_history,
+            cmd::tcp_ping,
+            cmd::trace_route,
+            cmd::get_latency_heatmap,
+            cmd::get_settings_lock_status,
+            cmd::unlock_settings,
+            cmd::lock_settings,
+            cmd::set_settings_pin,
+            cmd::query_audit_log,
+            cmd::get_setup_status,
+            cmd::complete_setup_step,
+            cmd::get_autostart_config,
+            cmd::set_autostart_config,
+            cmd::test_profile,
+            cmd::get_effective_outbounds,
+            cmd::get_core_memory,
             cmd::get_profiles,
             cmd::enhance_profiles,
             cmd::patch_profiles_config,
@@ -204,8 +303,19 @@ mod app_init {
             cmd::read_profile_file,
             cmd::save_profile_file,
             cmd::get_next_update_time,
+            cmd::get_profile_schedule,
+            cmd::get_profile_quota,
+            cmd::import_profile_from_clipboard,
+            cmd::import_profile_from_qr,
+            cmd::list_profile_versions,
+            cmd::rollback_profile,
+            cmd::check_profile_health,
+            cmd::get_profile_chain,
+            cmd::set_profile_chain,
+            cmd::toggle_profile_layer,
             cmd::script_validate_notice,
             cmd::validate_script_file,
+            cmd::validate_config,
             cmd::create_local_backup,
             cmd::list_local_backup,
             cmd::delete_local_backup,
@@ -220,13 +330,44 @@ mod app_init {
             cmd::check_media_unlock,
             cmd::toggle_discord_rpc,
             cmd::refresh_discord_activity,
+            cmd::is_traffic_stream_healthy,
             cmd::unload_discord_rpc,
             cmd::trigger_discord_rpc_reload,
+            cmd::install_plugin,
+            cmd::list_plugins,
+            cmd::grant_plugin_permissions,
+            cmd::set_plugin_enabled,
+            cmd::uninstall_plugin,
+            cmd::query_connection_log,
+            cmd::get_plugin_health,
+            cmd::get_domain_stats,
+            cmd::get_kill_switch_status,
+            cmd::release_kill_switch,
+            cmd::export_upstream_data,
+            cmd::import_upstream_data,
+            cmd::list_cores,
+            cmd::register_core,
+            cmd::unregister_core,
+            cmd::upgrade_custom_core,
+            cmd::set_active_core,
+            cmd::check_core_update,
+            cmd::upgrade_core,
+            cmd::check_geo_updates,
+            cmd::update_geo_databases,
+            cmd::resolve_via_core,
+            cmd::flush_dns_cache,
+            cmd::get_fakeip_mappings,
+            cmd::apply_dns_preset,
+            cmd::get_nameserver_policy,
+            cmd::set_nameserver_policy,
         ]
     }
 }
 
 pub fn run() {
+    utils::crash::install_panic_hook();
+    utils::instance::init_from_args();
+
     if app_init::init_singleton_check().is_err() {
         return;
     }
@@ -259,11 +400,6 @@ pub fn run() {
             resolve::init_signal();
             resolve::resolve_done();
 
-            // Initialize Discord RPC if enabled
-            AsyncHandler::spawn(move || async move {
-                cmd::init_discord_rpc_on_startup().await;
-            });
-
             logging!(info, Type::Setup, "初始化已启动");
             Ok(())
         })
@@ -389,6 +525,7 @@ pub fn run() {
     #[cfg(not(feature = "clippy"))]
     let app = builder.build(tauri::generate_context!()).unwrap_or_else(|e| {
         logging!(error, Type::Setup, "Failed to build Tauri application: {}", e);
+        utils::crash::write_report("fatal", &e.to_string(), None);
         std::process::exit(1);
     });
 
@@ -436,6 +573,9 @@ pub fn run() {
             tauri::WindowEvent::Focused(focused) => {
                 event_handlers::handle_window_focus(focused);
             }
+            tauri::WindowEvent::DragDrop(tauri::DragDropEvent::Drop { paths, .. }) => {
+                feat::handle_dropped_files(paths);
+            }
             #[cfg(target_os = "macos")]
             tauri::WindowEvent::Destroyed => {
                 event_handlers::handle_window_destroyed();
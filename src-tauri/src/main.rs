@@ -1,5 +1,17 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 fn main() {
+    // --cli 模式只通过控制接口操作已运行的实例，不启动界面
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().is_some_and(|arg| arg == "--cli") {
+        std::process::exit(app_lib::cli::run(&args[1..]));
+    }
+    // --sandbox 模式在隔离进程中执行插件与订阅脚本，由主程序启动
+    if args.first().is_some_and(|arg| arg == app_lib::sandbox::SANDBOX_ARG) {
+        std::process::exit(app_lib::sandbox::serve(&args[1..]));
+    }
+
+    app_lib::utils::autostart::detect_autostart();
+
     #[cfg(feature = "tokio-trace")]
     console_subscriber::init();
 
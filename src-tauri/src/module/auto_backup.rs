@@ -1,6 +1,6 @@
 use crate::{
     config::{Config, IVerge},
-    feat::create_local_backup_with_namer,
+    feat::{backup_now, create_local_backup_with_namer},
     process::AsyncHandler,
     utils::dirs::local_backup_dir,
 };
@@ -57,6 +57,7 @@ struct AutoBackupSettings {
     schedule_enabled: bool,
     interval_hours: u64,
     change_enabled: bool,
+    webdav_enabled: bool,
 }
 
 impl AutoBackupSettings {
@@ -70,8 +71,14 @@ impl AutoBackupSettings {
             schedule_enabled: verge.enable_auto_backup_schedule.unwrap_or(false),
             interval_hours: interval,
             change_enabled: verge.auto_backup_on_change.unwrap_or(true),
+            webdav_enabled: verge.enable_webdav_backup_schedule.unwrap_or(false),
         }
     }
+
+    /// 本地定时备份或 WebDAV 定时备份任一开启即需要运行定时器
+    const fn schedule_active(&self) -> bool {
+        self.schedule_enabled || self.webdav_enabled
+    }
 }
 
 impl Default for AutoBackupSettings {
@@ -80,6 +87,7 @@ impl Default for AutoBackupSettings {
             schedule_enabled: false,
             interval_hours: DEFAULT_INTERVAL_HOURS,
             change_enabled: true,
+            webdav_enabled: false,
         }
     }
 }
@@ -141,7 +149,7 @@ impl AutoBackupManager {
     }
 
     fn maybe_start_runner(&self, settings: AutoBackupSettings) {
-        if settings.schedule_enabled {
+        if settings.schedule_active() {
             self.ensure_runner();
         }
     }
@@ -160,7 +168,7 @@ impl AutoBackupManager {
     async fn run_scheduler(rx: &mut watch::Receiver<AutoBackupSettings>) {
         let mut current = *rx.borrow();
         loop {
-            if !current.schedule_enabled {
+            if !current.schedule_active() {
                 if rx.changed().await.is_err() {
                     break;
                 }
@@ -198,6 +206,12 @@ impl AutoBackupManager {
     async fn execute_trigger(&self, trigger: AutoBackupTrigger) -> Result<()> {
         let snapshot = *self.settings.read();
 
+        if trigger.is_schedule() && snapshot.webdav_enabled {
+            match backup_now().await {
+                Ok(id) => logging!(info, Type::Backup, "Scheduled WebDAV backup uploaded: {}", id),
+                Err(err) => logging!(warn, Type::Backup, "Scheduled WebDAV backup failed: {err:#?}"),
+            }
+        }
         if trigger.is_schedule() && !snapshot.schedule_enabled {
             return Ok(());
         }
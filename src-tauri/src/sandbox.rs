@@ -0,0 +1,149 @@
+//! 隔离执行
+//!
+//! 插件与订阅脚本由第三方编写，boa 只能限制循环次数与调用深度，既无法限制内存，超时后也无法中断正在执行的线程。
+//! 因此它们在 `clash-verge --sandbox <任务>` 子进程中执行：输入从 stdin 读取，结果写入 stdout，
+//! 父进程定期检查子进程的常驻内存，超出内存预算或执行时间后直接结束子进程。
+
+use crate::{core::plugin, enhance, feat};
+use anyhow::{Result, anyhow, bail};
+use std::{
+    fmt,
+    io::{Read as _, Write as _},
+    process::{Command, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+pub const SANDBOX_ARG: &str = "--sandbox";
+pub const PLUGIN_TASK: &str = "plugin";
+pub const SCRIPT_TASK: &str = "script";
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+/// macOS 与 Windows 上读取 RSS 需要启动外部程序，检查间隔不宜过短
+const MEMORY_CHECK_INTERVAL: Duration = Duration::from_millis(200);
+
+#[derive(Debug, Clone, Copy)]
+pub struct Limits {
+    pub timeout: Duration,
+    /// 常驻内存上限（字节）
+    pub memory: u64,
+}
+
+/// 子进程因超出限制被结束
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitExceeded {
+    Time(Duration),
+    Memory(u64),
+}
+
+impl fmt::Display for LimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Time(timeout) => write!(f, "exceeded time limit of {}ms", timeout.as_millis()),
+            Self::Memory(limit) => write!(f, "exceeded memory limit of {} MB", limit / (1024 * 1024)),
+        }
+    }
+}
+
+impl std::error::Error for LimitExceeded {}
+
+fn read_in_background<R: std::io::Read + Send + 'static>(pipe: Option<R>) -> thread::JoinHandle<std::string::String> {
+    thread::spawn(move || {
+        let mut buf = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut buf);
+        }
+        std::string::String::from_utf8_lossy(&buf).into_owned()
+    })
+}
+
+/// 在子进程中执行任务并返回其输出，阻塞直到子进程退出或被结束，需在阻塞线程中调用
+pub fn run(task: &str, input: std::string::String, limits: Limits) -> Result<std::string::String> {
+    // 测试二进制无法以 --sandbox 启动，直接在当前进程中执行
+    if cfg!(test) {
+        return dispatch(Some(task), &input);
+    }
+
+    let mut command = Command::new(std::env::current_exe()?);
+    command
+        .args([SANDBOX_ARG, task])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::process::CommandExt as _;
+        command.creation_flags(0x08000000); // CREATE_NO_WINDOW
+    }
+    let mut child = command.spawn()?;
+
+    // 输入输出在单独的线程中读写，避免管道写满后双方互相等待
+    let stdin = child.stdin.take();
+    thread::spawn(move || {
+        if let Some(mut stdin) = stdin {
+            let _ = stdin.write_all(input.as_bytes());
+        }
+    });
+    let stdout = read_in_background(child.stdout.take());
+    let stderr = read_in_background(child.stderr.take());
+
+    let started = Instant::now();
+    let mut last_check = started;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        let exceeded = if started.elapsed() > limits.timeout {
+            Some(LimitExceeded::Time(limits.timeout))
+        } else if last_check.elapsed() >= MEMORY_CHECK_INTERVAL {
+            last_check = Instant::now();
+            feat::process_rss(child.id())
+                .filter(|&rss| rss > limits.memory)
+                .map(|_| LimitExceeded::Memory(limits.memory))
+        } else {
+            None
+        };
+        if let Some(exceeded) = exceeded {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(exceeded.into());
+        }
+        thread::sleep(POLL_INTERVAL);
+    };
+
+    let output = stdout.join().unwrap_or_default();
+    if !status.success() {
+        let message = stderr.join().unwrap_or_default();
+        if message.trim().is_empty() {
+            bail!("sandbox exited with {status}");
+        }
+        bail!("{}", message.trim());
+    }
+    Ok(output)
+}
+
+fn dispatch(task: Option<&str>, input: &str) -> Result<std::string::String> {
+    match task {
+        Some(PLUGIN_TASK) => plugin::serve_event(input),
+        Some(SCRIPT_TASK) => enhance::serve_script(input),
+        other => Err(anyhow!("unknown sandbox task {other:?}")),
+    }
+}
+
+/// 子进程入口，返回进程退出码
+pub fn serve(args: &[std::string::String]) -> i32 {
+    let mut input = std::string::String::new();
+    let result = match std::io::stdin().read_to_string(&mut input) {
+        Ok(_) => dispatch(args.first().map(std::string::String::as_str), &input),
+        Err(err) => Err(err.into()),
+    };
+    match result {
+        Ok(output) => {
+            let _ = std::io::stdout().write_all(output.as_bytes());
+            0
+        }
+        Err(err) => {
+            eprintln!("{err}");
+            1
+        }
+    }
+}
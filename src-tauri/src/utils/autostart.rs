@@ -3,9 +3,35 @@ use anyhow::{Result, anyhow};
 #[cfg(target_os = "windows")]
 use clash_verge_logging::{Type, logging};
 
+use std::sync::OnceLock;
 #[cfg(target_os = "windows")]
 use std::{os::windows::process::CommandExt as _, path::Path, path::PathBuf};
 
+/// 开机自启项的启动参数
+pub const AUTOSTART_ARG: &str = "--autostart";
+/// 重启时启动参数原样保留，由该变量标记开机自启已处理过，重启后的进程继承后忽略 [`AUTOSTART_ARG`]
+const AUTOSTART_HANDLED_ENV: &str = "CLASH_VERGE_AUTOSTART_HANDLED";
+
+static AUTOSTARTED: OnceLock<bool> = OnceLock::new();
+
+/// 判断本进程是否由开机自启项启动，需在 `main` 中、其他线程启动前调用
+pub fn detect_autostart() {
+    let autostarted =
+        std::env::var_os(AUTOSTART_HANDLED_ENV).is_none() && std::env::args().any(|arg| arg == AUTOSTART_ARG);
+    if autostarted {
+        // 此时只有主线程
+        unsafe {
+            std::env::set_var(AUTOSTART_HANDLED_ENV, "1");
+        }
+    }
+    let _ = AUTOSTARTED.set(autostarted);
+}
+
+/// 本进程是否由开机自启项启动，重启后的进程始终为 `false`
+pub fn is_autostarted() -> bool {
+    AUTOSTARTED.get().copied().unwrap_or_default()
+}
+
 /// Windows 下的开机启动文件夹路径
 #[cfg(target_os = "windows")]
 pub fn get_startup_dir() -> Result<PathBuf> {
@@ -65,6 +91,7 @@ pub async fn create_shortcut() -> Result<()> {
         "$WshShell = New-Object -ComObject WScript.Shell; \
          $Shortcut = $WshShell.CreateShortcut('{}'); \
          $Shortcut.TargetPath = '{}'; \
+         $Shortcut.Arguments = '{AUTOSTART_ARG}'; \
          $Shortcut.Save()",
         new_shortcut_path.to_string_lossy().replace("\\", "\\\\"),
         exe_path.to_string_lossy().replace("\\", "\\\\")
@@ -0,0 +1,228 @@
+//! bbolt 数据库只读访问
+//!
+//! 内核将 fake-ip 映射、节点选择等持久化到 `cache.db`（bbolt 格式），但没有提供列出映射的接口。
+//! 这里只实现读取顶层 bucket 中全部键值所需的部分。内核运行时仍在写入该文件，读到的可能是
+//! 不完整的快照，所有偏移均做越界检查，页面结构异常时返回错误而不是 panic。
+
+use anyhow::{Result, anyhow, bail};
+
+const MAGIC: u32 = 0xED0C_DAED;
+const PAGE_HEADER_SIZE: usize = 16;
+const ELEMENT_SIZE: usize = 16;
+/// bucket 值开头的 root 页号与序列号
+const BUCKET_HEADER_SIZE: usize = 16;
+const BRANCH_PAGE: u16 = 0x01;
+const LEAF_PAGE: u16 = 0x02;
+const BUCKET_LEAF: u32 = 0x01;
+/// B+ 树深度上限，防止损坏的文件形成循环
+const MAX_DEPTH: usize = 32;
+
+fn read_u16(buf: &[u8], offset: usize) -> Result<u16> {
+    let bytes = buf
+        .get(offset..offset + 2)
+        .ok_or_else(|| anyhow!("bbolt page out of bounds"))?;
+    Ok(u16::from_le_bytes(bytes.try_into()?))
+}
+
+fn read_u32(buf: &[u8], offset: usize) -> Result<u32> {
+    let bytes = buf
+        .get(offset..offset + 4)
+        .ok_or_else(|| anyhow!("bbolt page out of bounds"))?;
+    Ok(u32::from_le_bytes(bytes.try_into()?))
+}
+
+fn read_u64(buf: &[u8], offset: usize) -> Result<u64> {
+    let bytes = buf
+        .get(offset..offset + 8)
+        .ok_or_else(|| anyhow!("bbolt page out of bounds"))?;
+    Ok(u64::from_le_bytes(bytes.try_into()?))
+}
+
+fn slice(buf: &[u8], offset: usize, len: usize) -> Result<&[u8]> {
+    buf.get(offset..offset.saturating_add(len))
+        .ok_or_else(|| anyhow!("bbolt element out of bounds"))
+}
+
+struct Db<'a> {
+    buf: &'a [u8],
+    page_size: usize,
+}
+
+impl<'a> Db<'a> {
+    fn page(&self, id: u64) -> Result<&'a [u8]> {
+        let offset = usize::try_from(id)?.saturating_mul(self.page_size);
+        self.buf
+            .get(offset..)
+            .ok_or_else(|| anyhow!("bbolt page {id} out of bounds"))
+    }
+
+    /// 遍历以 `page` 为根的树的全部叶子元素，回调参数为 (flags, key, value)
+    fn walk(&self, page: &'a [u8], depth: usize, visit: &mut dyn FnMut(u32, &'a [u8], &'a [u8])) -> Result<()> {
+        if depth > MAX_DEPTH {
+            bail!("bbolt tree too deep");
+        }
+        let flags = read_u16(page, 8)?;
+        let count = usize::from(read_u16(page, 10)?);
+        for index in 0..count {
+            let element = PAGE_HEADER_SIZE + index * ELEMENT_SIZE;
+            match flags {
+                BRANCH_PAGE => {
+                    let child = read_u64(page, element + 8)?;
+                    self.walk(self.page(child)?, depth + 1, visit)?;
+                }
+                LEAF_PAGE => {
+                    let element_flags = read_u32(page, element)?;
+                    let pos = usize::try_from(read_u32(page, element + 4)?)?;
+                    let key_size = usize::try_from(read_u32(page, element + 8)?)?;
+                    let value_size = usize::try_from(read_u32(page, element + 12)?)?;
+                    let key = slice(page, element + pos, key_size)?;
+                    let value = slice(page, element + pos + key_size, value_size)?;
+                    visit(element_flags, key, value);
+                }
+                _ => bail!("unexpected bbolt page type {flags:#x}"),
+            }
+        }
+        Ok(())
+    }
+
+    /// bucket 的根页面，root 为 0 时为内联 bucket，页面紧跟在值的头部之后
+    fn bucket_root(&self, value: &'a [u8]) -> Result<&'a [u8]> {
+        match read_u64(value, 0)? {
+            0 => value
+                .get(BUCKET_HEADER_SIZE..)
+                .ok_or_else(|| anyhow!("bbolt inline bucket out of bounds")),
+            root => self.page(root),
+        }
+    }
+}
+
+/// 读取 meta 页中的 (页大小, 根 bucket 页号, 事务号)，魔数不符时为 `None`
+fn read_meta(meta: &[u8]) -> Option<(usize, u64, u64)> {
+    let body = PAGE_HEADER_SIZE;
+    if read_u32(meta, body).ok()? != MAGIC {
+        return None;
+    }
+    let page_size = usize::try_from(read_u32(meta, body + 8).ok()?).ok()?;
+    let root = read_u64(meta, body + 16).ok()?;
+    let txid = read_u64(meta, body + 48).ok()?;
+    Some((page_size, root, txid))
+}
+
+/// 读取顶层 bucket `name` 中的全部键值（不含嵌套的 bucket）
+pub fn read_bucket(buf: &[u8], name: &[u8]) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+    let first = read_meta(buf);
+    let second = first
+        .map(|(page_size, ..)| page_size)
+        .and_then(|page_size| buf.get(page_size..))
+        .and_then(read_meta);
+    // 两个 meta 页交替写入，取事务号较大的一个
+    let (page_size, root, _) = [first, second]
+        .into_iter()
+        .flatten()
+        .max_by_key(|(.., txid)| *txid)
+        .ok_or_else(|| anyhow!("not a bbolt database"))?;
+    if page_size < PAGE_HEADER_SIZE + ELEMENT_SIZE {
+        bail!("invalid bbolt page size {page_size}");
+    }
+
+    let db = Db { buf, page_size };
+    let mut bucket = None;
+    db.walk(db.page(root)?, 0, &mut |flags, key, value| {
+        if flags & BUCKET_LEAF != 0 && key == name {
+            bucket = Some(value);
+        }
+    })?;
+    let Some(bucket) = bucket else {
+        return Ok(Vec::new());
+    };
+
+    let mut entries = Vec::new();
+    db.walk(db.bucket_root(bucket)?, 0, &mut |flags, key, value| {
+        if flags & BUCKET_LEAF == 0 {
+            entries.push((key.to_vec(), value.to_vec()));
+        }
+    })?;
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGE_SIZE: usize = 256;
+
+    fn page_header(id: u64, flags: u16, count: u16) -> Vec<u8> {
+        let mut page = id.to_le_bytes().to_vec();
+        page.extend_from_slice(&flags.to_le_bytes());
+        page.extend_from_slice(&count.to_le_bytes());
+        page.extend_from_slice(&0u32.to_le_bytes());
+        page
+    }
+
+    /// 叶子页：元素头之后依次存放键值
+    fn leaf(id: u64, entries: &[(u32, &[u8], &[u8])]) -> Vec<u8> {
+        let count = u16::try_from(entries.len()).unwrap_or_default();
+        let mut page = page_header(id, LEAF_PAGE, count);
+        let mut data = Vec::new();
+        for (index, (flags, key, value)) in entries.iter().enumerate() {
+            let pos = (entries.len() - index) * ELEMENT_SIZE + data.len();
+            for n in [
+                *flags,
+                u32::try_from(pos).unwrap_or_default(),
+                u32::try_from(key.len()).unwrap_or_default(),
+                u32::try_from(value.len()).unwrap_or_default(),
+            ] {
+                page.extend_from_slice(&n.to_le_bytes());
+            }
+            data.extend_from_slice(key);
+            data.extend_from_slice(value);
+        }
+        page.extend(data);
+        page
+    }
+
+    fn meta(id: u64, root: u64, txid: u64) -> Vec<u8> {
+        let mut page = page_header(id, 0x04, 0);
+        page.extend_from_slice(&MAGIC.to_le_bytes());
+        page.extend_from_slice(&2u32.to_le_bytes());
+        page.extend_from_slice(&u32::try_from(PAGE_SIZE).unwrap_or_default().to_le_bytes());
+        page.extend_from_slice(&0u32.to_le_bytes());
+        page.extend_from_slice(&root.to_le_bytes());
+        page.extend_from_slice(&0u64.to_le_bytes());
+        page.extend_from_slice(&[0; 16]); // freelist, pgid
+        page.extend_from_slice(&txid.to_le_bytes());
+        page
+    }
+
+    #[test]
+    fn test_read_bucket() {
+        // 内联 bucket：16 字节头（root = 0）之后是叶子页
+        let mut inline = vec![0u8; BUCKET_HEADER_SIZE];
+        inline.extend(leaf(
+            0,
+            &[(0, &[198, 18, 0, 3], b"a.com"), (0, b"a.com", &[198, 18, 0, 3])],
+        ));
+        // 第二个 meta 的事务号更大，指向页 3；页 2 为旧的根页
+        let pages = [
+            meta(0, 2, 1),
+            meta(1, 3, 2),
+            leaf(2, &[]),
+            leaf(
+                3,
+                &[(BUCKET_LEAF, b"fakeip", &inline), (BUCKET_LEAF, b"selected", &inline)],
+            ),
+        ];
+        let mut db = Vec::new();
+        for page in pages {
+            let mut page = page;
+            page.resize(PAGE_SIZE, 0);
+            db.extend(page);
+        }
+
+        let entries = read_bucket(&db, b"fakeip").unwrap_or_default();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0], (vec![198, 18, 0, 3], b"a.com".to_vec()));
+        assert!(read_bucket(&db, b"missing").is_ok_and(|entries| entries.is_empty()));
+        assert!(read_bucket(b"not a database", b"fakeip").is_err());
+    }
+}
@@ -0,0 +1,195 @@
+//! 崩溃报告
+//!
+//! [`install_panic_hook`] 在 panic 时把错误信息、调用栈、版本、系统与最近的操作记录写入
+//! `crash_reports` 目录，再交给默认的 panic 处理。下次启动时提示用户查看，
+//! 用户可打开报告目录，或在设置了 `crash_report_url` 后主动上传。
+
+use crate::{APP_HANDLE, config::Config, core::handle, utils::dirs};
+use anyhow::{Result, bail};
+use clash_verge_logging::{Type, logging};
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use smartstring::alias::String;
+use std::{backtrace::Backtrace, collections::VecDeque, path::PathBuf, time::Duration};
+
+const REPORT_DIR: &str = "crash_reports";
+const PENDING_MARKER: &str = "pending";
+const MAX_ACTIONS: usize = 20;
+const UPLOAD_TIMEOUT: Duration = Duration::from_secs(30);
+
+static RECENT_ACTIONS: Lazy<Mutex<VecDeque<std::string::String>>> =
+    Lazy::new(|| Mutex::new(VecDeque::with_capacity(MAX_ACTIONS)));
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrashReport {
+    pub id: String,
+    pub time: String,
+    pub kind: String,
+    pub message: String,
+    pub location: Option<String>,
+    pub thread: Option<String>,
+    pub version: String,
+    pub os: String,
+    pub backtrace: String,
+    pub recent_actions: Vec<std::string::String>,
+}
+
+/// 列表中展示的摘要，不含调用栈
+#[derive(Debug, Clone, Serialize)]
+pub struct CrashReportSummary {
+    pub id: String,
+    pub time: String,
+    pub kind: String,
+    pub message: String,
+    pub version: String,
+}
+
+/// 记录一条最近操作，崩溃时写入报告
+pub fn record_action(action: impl Into<std::string::String>) {
+    let mut actions = RECENT_ACTIONS.lock();
+    if actions.len() >= MAX_ACTIONS {
+        actions.pop_front();
+    }
+    actions.push_back(format!("{} {}", chrono::Local::now().format("%H:%M:%S"), action.into()));
+}
+
+/// 报告目录；非便携模式下应用句柄尚未初始化时无法确定数据目录，退回系统临时目录
+pub fn report_dir() -> PathBuf {
+    let base = if APP_HANDLE.get().is_some() || dirs::is_portable() {
+        dirs::app_home_dir().unwrap_or_else(|_| std::env::temp_dir())
+    } else {
+        std::env::temp_dir()
+    };
+    base.join(REPORT_DIR)
+}
+
+fn is_valid_id(id: &str) -> bool {
+    !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// 写入一份崩溃报告，可在 panic hook 中调用，不会再次 panic
+pub fn write_report(kind: &str, message: &str, location: Option<std::string::String>) -> Option<PathBuf> {
+    let now = chrono::Local::now();
+    let id = format!("crash-{}-{}", now.format("%Y%m%d-%H%M%S"), std::process::id());
+    let report = CrashReport {
+        id: id.as_str().into(),
+        time: now.to_rfc3339().into(),
+        kind: kind.into(),
+        message: message.into(),
+        location: location.map(Into::into),
+        thread: std::thread::current().name().map(Into::into),
+        version: env!("CARGO_PKG_VERSION").into(),
+        os: format!("{} {}", std::env::consts::OS, std::env::consts::ARCH).into(),
+        backtrace: Backtrace::force_capture().to_string().into(),
+        recent_actions: RECENT_ACTIONS
+            .try_lock()
+            .map(|actions| actions.iter().cloned().collect())
+            .unwrap_or_default(),
+    };
+
+    let dir = report_dir();
+    std::fs::create_dir_all(&dir).ok()?;
+    let path = dir.join(format!("{id}.json"));
+    std::fs::write(&path, serde_json::to_vec_pretty(&report).ok()?).ok()?;
+    let _ = std::fs::write(dir.join(PENDING_MARKER), id);
+    Some(path)
+}
+
+/// 安装 panic hook，需在其他初始化之前调用
+pub fn install_panic_hook() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let payload = info.payload();
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|s| (*s).to_owned())
+            .or_else(|| payload.downcast_ref::<std::string::String>().cloned())
+            .unwrap_or_else(|| "unknown panic".to_owned());
+        let location = info
+            .location()
+            .map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column()));
+        if let Some(path) = write_report("panic", &message, location) {
+            eprintln!("crash report written to {}", path.display());
+        }
+        default_hook(info);
+    }));
+}
+
+/// 启动时若上次运行留下了新的崩溃报告，提示用户查看
+pub fn notify_pending() {
+    let marker = report_dir().join(PENDING_MARKER);
+    if let Ok(id) = std::fs::read_to_string(&marker) {
+        let _ = std::fs::remove_file(&marker);
+        logging!(warn, Type::Setup, "Found crash report from the last run: {id}");
+        handle::Handle::notice_message("crash_report::found", id);
+    }
+}
+
+fn read_report(id: &str) -> Result<CrashReport> {
+    if !is_valid_id(id) {
+        bail!("invalid crash report id: {id}");
+    }
+    let content = std::fs::read(report_dir().join(format!("{id}.json")))?;
+    Ok(serde_json::from_slice(&content)?)
+}
+
+/// 按时间倒序列出崩溃报告
+pub fn list_reports() -> Vec<CrashReportSummary> {
+    let Ok(entries) = std::fs::read_dir(report_dir()) else {
+        return Vec::new();
+    };
+    let mut reports: Vec<CrashReportSummary> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name();
+            let id = name.to_str()?.strip_suffix(".json")?.to_owned();
+            read_report(&id).ok()
+        })
+        .map(|report| CrashReportSummary {
+            id: report.id,
+            time: report.time,
+            kind: report.kind,
+            message: report.message,
+            version: report.version,
+        })
+        .collect();
+    reports.sort_by(|a, b| b.time.cmp(&a.time));
+    reports
+}
+
+/// 用户确认后上传报告到 `crash_report_url`
+pub async fn upload_report(id: &str) -> Result<()> {
+    let Some(url) = Config::verge()
+        .await
+        .latest_arc()
+        .crash_report_url
+        .clone()
+        .filter(|url| !url.is_empty())
+    else {
+        bail!("crash report upload url is not configured");
+    };
+    let report = read_report(id)?;
+    reqwest::Client::builder()
+        .timeout(UPLOAD_TIMEOUT)
+        .build()?
+        .post(url.as_str())
+        .json(&report)
+        .send()
+        .await?
+        .error_for_status()?;
+    logging!(info, Type::Setup, "Uploaded crash report {id}");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_id() {
+        assert!(is_valid_id("crash-20260101-120000-42"));
+        assert!(!is_valid_id("../verge"));
+        assert!(!is_valid_id(""));
+    }
+}
@@ -0,0 +1,175 @@
+//! 基于口令的加密、消息签名与密钥派生
+//!
+//! [`seal`] 生成的数据格式为 `MAGIC | salt(16) | nonce(12) | AES-256-GCM 密文`，
+//! 密钥由 PBKDF2-HMAC-SHA256 从口令派生，可在其他设备上用同一口令解密。
+
+use aes_gcm::{
+    Aes256Gcm, Key,
+    aead::{Aead as _, KeyInit as _},
+};
+use anyhow::{Result, anyhow, bail};
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+const MAGIC: &[u8] = b"CVBK2";
+const PBKDF2_ROUNDS: u32 = 600_000;
+const SALT_LENGTH: usize = 16;
+const NONCE_LENGTH: usize = 12;
+
+#[allow(clippy::expect_used)]
+pub fn hmac_sha256(key: &[u8], data: &[u8]) -> [u8; 32] {
+    let mac = <Hmac<Sha256> as Mac>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.chain_update(data).finalize().into_bytes().into()
+}
+
+/// 常数时间校验 [`hmac_sha256`] 生成的签名
+pub fn verify_hmac_sha256(key: &[u8], data: &[u8], tag: &[u8]) -> bool {
+    <Hmac<Sha256> as Mac>::new_from_slice(key).is_ok_and(|mac| mac.chain_update(data).verify_slice(tag).is_ok())
+}
+
+/// 从高熵的共享密钥派生 32 字节子密钥，`info` 区分用途
+#[allow(clippy::expect_used)]
+pub fn hkdf_sha256(secret: &[u8], salt: &[u8], info: &[u8]) -> [u8; 32] {
+    let mut key = [0u8; 32];
+    Hkdf::<Sha256>::new(Some(salt), secret)
+        .expand(info, &mut key)
+        .expect("32 bytes is a valid HKDF-SHA256 output length");
+    key
+}
+
+/// 只派生一个 32 字节的块，正好作为 AES-256 密钥
+pub fn pbkdf2_sha256(password: &[u8], salt: &[u8], rounds: u32) -> [u8; 32] {
+    let mut key = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<Sha256>(password, salt, rounds, &mut key);
+    key
+}
+
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+pub fn is_sealed(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+pub fn seal(data: &[u8], passphrase: &str) -> Result<Vec<u8>> {
+    seal_with(data, passphrase, PBKDF2_ROUNDS)
+}
+
+pub fn open(sealed: &[u8], passphrase: &str) -> Result<Vec<u8>> {
+    open_with(sealed, passphrase, PBKDF2_ROUNDS)
+}
+
+#[allow(deprecated)]
+fn seal_with(data: &[u8], passphrase: &str, rounds: u32) -> Result<Vec<u8>> {
+    let mut salt = [0u8; SALT_LENGTH];
+    let mut nonce = [0u8; NONCE_LENGTH];
+    getrandom::fill(&mut salt)?;
+    getrandom::fill(&mut nonce)?;
+
+    let key = pbkdf2_sha256(passphrase.as_bytes(), &salt, rounds);
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key));
+    let ciphertext = cipher
+        .encrypt(nonce.as_slice().into(), data)
+        .map_err(|e| anyhow!("Encryption failed: {e}"))?;
+
+    let mut sealed = Vec::with_capacity(MAGIC.len() + SALT_LENGTH + NONCE_LENGTH + ciphertext.len());
+    sealed.extend_from_slice(MAGIC);
+    sealed.extend_from_slice(&salt);
+    sealed.extend_from_slice(&nonce);
+    sealed.extend(ciphertext);
+    Ok(sealed)
+}
+
+#[allow(deprecated)]
+fn open_with(sealed: &[u8], passphrase: &str, rounds: u32) -> Result<Vec<u8>> {
+    let Some(body) = sealed.strip_prefix(MAGIC) else {
+        bail!("data is not encrypted");
+    };
+    if body.len() < SALT_LENGTH + NONCE_LENGTH {
+        bail!("encrypted data is truncated");
+    }
+    let (salt, rest) = body.split_at(SALT_LENGTH);
+    let (nonce, ciphertext) = rest.split_at(NONCE_LENGTH);
+
+    let key = pbkdf2_sha256(passphrase.as_bytes(), salt, rounds);
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key));
+    cipher
+        .decrypt(nonce.into(), ciphertext)
+        .map_err(|_| anyhow!("wrong password or corrupted data"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hmac_matches_rfc4231() {
+        assert_eq!(
+            to_hex(&hmac_sha256(b"Jefe", b"what do ya want for nothing?")),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn test_verify_hmac() {
+        let tag = hmac_sha256(b"key", b"data");
+        assert!(verify_hmac_sha256(b"key", b"data", &tag));
+        assert!(!verify_hmac_sha256(b"key", b"other", &tag));
+        assert!(!verify_hmac_sha256(b"key", b"data", &tag[..16]));
+    }
+
+    #[test]
+    fn test_hkdf_matches_rfc5869() {
+        let salt: Vec<u8> = (0x00..=0x0c).collect();
+        let info: Vec<u8> = (0xf0..=0xf9).collect();
+        assert_eq!(
+            to_hex(&hkdf_sha256(&[0x0b; 22], &salt, &info)),
+            "3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf"
+        );
+    }
+
+    #[test]
+    fn test_pbkdf2_matches_rfc7914() {
+        assert_eq!(
+            to_hex(&pbkdf2_sha256(b"passwd", b"salt", 1)),
+            "55ac046e56e3089fec1691c22544b605f94185216dde0465e68b9d57c20dacbc"
+        );
+        assert_eq!(
+            to_hex(&pbkdf2_sha256(b"Password", b"NaCl", 80_000)),
+            "4ddcd8f60b98be21830cee5ef22701f9641a4418d04c0414aeff08876b34ab56"
+        );
+    }
+
+    #[test]
+    fn test_pbkdf2_multiple_rounds() {
+        assert_eq!(
+            to_hex(&pbkdf2_sha256(b"password", b"salt", 2)),
+            "ae4d0c95af6b46d32d0adff928f06dd02a303f8ef3c251dfd6e2d85a95474c43"
+        );
+        assert_eq!(
+            to_hex(&pbkdf2_sha256(b"password", b"salt", 4096)),
+            "c5e478d59288c841aa530db6845c4c8d962893a001ce4e11a4963873aa98134a"
+        );
+        assert_eq!(
+            to_hex(&pbkdf2_sha256(
+                b"passwordPASSWORDpassword",
+                b"saltSALTsaltSALTsaltSALTsaltSALTsalt",
+                4096
+            )),
+            "348c89dbcbd32b2f32d814b8116e84cf2b17347ebc1800181c4e2a1fb8dd53e1"
+        );
+    }
+
+    #[test]
+    fn test_seal_roundtrip() {
+        // 实际的派生轮数在未优化的测试构建中过慢
+        let rounds = 1_000;
+        let sealed = seal_with(b"backup", "secret", rounds).unwrap_or_default();
+        assert!(is_sealed(&sealed));
+        assert_eq!(open_with(&sealed, "secret", rounds).unwrap_or_default(), b"backup");
+        assert!(open_with(&sealed, "wrong", rounds).is_err());
+        assert!(open(b"PK\x03\x04", "secret").is_err());
+    }
+}
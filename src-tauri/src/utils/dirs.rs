@@ -1,4 +1,7 @@
-use crate::core::{CoreManager, handle, manager::RunningMode};
+use crate::{
+    core::{CoreManager, handle, manager::RunningMode},
+    utils::instance,
+};
 use anyhow::Result;
 use async_trait::async_trait;
 use clash_verge_logging::{Type, logging};
@@ -24,46 +27,85 @@ pub static CLASH_CONFIG: &str = "config.yaml";
 pub static VERGE_CONFIG: &str = "verge.yaml";
 pub static PROFILE_YAML: &str = "profiles.yaml";
 
+const PORTABLE_MARKER: &str = ".portable";
+const PORTABLE_ENV: &str = "CLASH_VERGE_PORTABLE";
+
+/// 通过 `--portable` 参数或 `CLASH_VERGE_PORTABLE=1` 显式要求便携模式
+fn portable_requested<I: IntoIterator<Item = std::string::String>>(args: I, env: Option<&str>) -> bool {
+    args.into_iter().any(|arg| arg == "--portable") || matches!(env, Some("1" | "true"))
+}
+
 /// init portable flag
+///
+/// 程序目录下存在 `.portable`（或旧版的 `.config/PORTABLE`）标记文件，或通过参数、环境变量显式要求时，
+/// 所有数据都保存在程序目录的 `.config` 下
 pub fn init_portable_flag() -> Result<()> {
     use tauri::utils::platform::current_exe;
 
+    let requested = portable_requested(std::env::args().skip(1), std::env::var(PORTABLE_ENV).ok().as_deref());
     let app_exe = current_exe()?;
-    if let Some(dir) = app_exe.parent() {
-        let dir = PathBuf::from(dir).join(".config/PORTABLE");
+    let has_marker = app_exe
+        .parent()
+        .is_some_and(|dir| dir.join(PORTABLE_MARKER).exists() || dir.join(".config/PORTABLE").exists());
+    PORTABLE_FLAG.get_or_init(|| requested || has_marker);
+    Ok(())
+}
+
+pub fn is_portable() -> bool {
+    *PORTABLE_FLAG.get().unwrap_or(&false)
+}
+
+/// app id with the `--instance` suffix, used to isolate instance data dirs
+fn instance_app_id() -> std::string::String {
+    instance::suffixed(APP_ID, '.')
+}
+
+/// 迁移数据目录后，原目录中记录新位置的文件
+pub const RELOCATION_FILE: &str = "relocated-to";
+
+static RELOCATED_HOME: OnceCell<Option<PathBuf>> = OnceCell::new();
+
+/// 系统默认的数据目录，不考虑迁移记录
+pub fn default_app_home_dir() -> Result<PathBuf> {
+    // 避免在Handle未初始化时崩溃
+    let app_handle = handle::Handle::app_handle();
 
-        if dir.exists() {
-            PORTABLE_FLAG.get_or_init(|| true);
+    match app_handle.path().data_dir() {
+        Ok(dir) => Ok(dir.join(instance_app_id())),
+        Err(e) => {
+            logging!(error, Type::File, "Failed to get the app home directory: {e}");
+            Err(anyhow::anyhow!("Failed to get the app homedirectory"))
         }
     }
-    PORTABLE_FLAG.get_or_init(|| false);
-    Ok(())
 }
 
 /// get the verge app home dir
+///
+/// 优先级：便携模式 > `--data-dir` > 默认目录中的迁移记录 > 默认目录
 pub fn app_home_dir() -> Result<PathBuf> {
     use tauri::utils::platform::current_exe;
 
-    let flag = PORTABLE_FLAG.get().unwrap_or(&false);
-    if *flag {
+    if is_portable() {
         let app_exe = current_exe()?;
         let app_exe = dunce::canonicalize(app_exe)?;
         let app_dir = app_exe
             .parent()
             .ok_or_else(|| anyhow::anyhow!("failed to get the portable app dir"))?;
-        return Ok(PathBuf::from(app_dir).join(".config").join(APP_ID));
+        return Ok(PathBuf::from(app_dir).join(".config").join(instance_app_id()));
     }
 
-    // 避免在Handle未初始化时崩溃
-    let app_handle = handle::Handle::app_handle();
-
-    match app_handle.path().data_dir() {
-        Ok(dir) => Ok(dir.join(APP_ID)),
-        Err(e) => {
-            logging!(error, Type::File, "Failed to get the app home directory: {e}");
-            Err(anyhow::anyhow!("Failed to get the app homedirectory"))
-        }
+    if let Some(dir) = instance::data_dir() {
+        return Ok(dir.to_path_buf());
     }
+
+    let default_dir = default_app_home_dir()?;
+    let relocated = RELOCATED_HOME.get_or_init(|| {
+        fs::read_to_string(default_dir.join(RELOCATION_FILE))
+            .ok()
+            .map(|path| PathBuf::from(path.trim()))
+            .filter(|path| path.is_absolute() && path.is_dir())
+    });
+    Ok(relocated.clone().unwrap_or(default_dir))
 }
 
 /// get the resources dir
@@ -90,6 +132,11 @@ pub fn app_icons_dir() -> Result<PathBuf> {
     Ok(app_home_dir()?.join("icons"))
 }
 
+/// tray icon themes dir, one sub directory per theme
+pub fn app_tray_themes_dir() -> Result<PathBuf> {
+    Ok(app_home_dir()?.join("tray-themes"))
+}
+
 pub fn find_target_icons(target: &str) -> Result<Option<String>> {
     let icons_dir = app_icons_dir()?;
     let icon_path = fs::read_dir(&icons_dir)?
@@ -109,11 +156,44 @@ pub fn find_target_icons(target: &str) -> Result<Option<String>> {
     icon_path.map(|path| path_to_str(&path).map(|s| s.into())).transpose()
 }
 
+/// plugins dir
+pub fn app_plugins_dir() -> Result<PathBuf> {
+    Ok(app_home_dir()?.join("plugins"))
+}
+
+/// user registered cores dir
+pub fn app_cores_dir() -> Result<PathBuf> {
+    Ok(app_home_dir()?.join("cores"))
+}
+
 /// logs dir
 pub fn app_logs_dir() -> Result<PathBuf> {
     Ok(app_home_dir()?.join("logs"))
 }
 
+/// 临时文件目录，便携模式下放在数据目录中，避免在系统中留下文件
+pub fn app_temp_dir() -> PathBuf {
+    if is_portable()
+        && let Ok(dir) = app_home_dir().map(|dir| dir.join("tmp"))
+        && fs::create_dir_all(&dir).is_ok()
+    {
+        return dir;
+    }
+    std::env::temp_dir()
+}
+
+/// WebView 数据目录，仅便携模式下指定，其余情况使用系统默认位置
+pub fn webview_data_dir() -> Option<PathBuf> {
+    is_portable()
+        .then(|| app_home_dir().ok().map(|dir| dir.join("webview")))
+        .flatten()
+}
+
+/// connection logs dir
+pub fn app_connection_logs_dir() -> Result<PathBuf> {
+    Ok(app_logs_dir()?.join("connections"))
+}
+
 // latest verge log
 pub fn app_latest_log() -> Result<PathBuf> {
     Ok(app_logs_dir()?.join("latest.log"))
@@ -164,6 +244,13 @@ pub fn service_log_dir() -> Result<PathBuf> {
     Ok(log_dir)
 }
 
+pub fn core_crash_log_dir() -> Result<PathBuf> {
+    let log_dir = app_logs_dir()?.join("crashes");
+    let _ = std::fs::create_dir_all(&log_dir);
+
+    Ok(log_dir)
+}
+
 pub fn clash_latest_log() -> Result<PathBuf> {
     match *CoreManager::global().get_running_mode() {
         RunningMode::Service => Ok(service_log_dir()?.join("service_latest.log")),
@@ -219,21 +306,25 @@ pub fn ensure_mihomo_safe_dir() -> Option<PathBuf> {
         })
 }
 
+#[cfg(unix)]
+fn ipc_socket_name() -> std::string::String {
+    format!("{}.sock", instance::suffixed("verge-mihomo", '-'))
+}
+
 #[cfg(unix)]
 pub fn ipc_path() -> Result<PathBuf> {
     ensure_mihomo_safe_dir()
-        .map(|base_dir| base_dir.join("verge").join("verge-mihomo.sock"))
-        .or_else(|| {
-            app_home_dir()
-                .ok()
-                .map(|dir| dir.join("verge").join("verge-mihomo.sock"))
-        })
+        .map(|base_dir| base_dir.join("verge").join(ipc_socket_name()))
+        .or_else(|| app_home_dir().ok().map(|dir| dir.join("verge").join(ipc_socket_name())))
         .ok_or_else(|| anyhow::anyhow!("Failed to determine ipc path"))
 }
 
 #[cfg(target_os = "windows")]
 pub fn ipc_path() -> Result<PathBuf> {
-    Ok(PathBuf::from(r"\\.\pipe\verge-mihomo"))
+    Ok(PathBuf::from(format!(
+        r"\\.\pipe\{}",
+        instance::suffixed("verge-mihomo", '-')
+    )))
 }
 #[async_trait]
 pub trait PathBufExec {
@@ -250,3 +341,18 @@ impl PathBufExec for PathBuf {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_portable_requested() {
+        assert!(portable_requested(["--portable".to_owned()], None));
+        assert!(portable_requested(Vec::new(), Some("1")));
+        assert!(!portable_requested(
+            ["--instance".to_owned(), "work".to_owned()],
+            Some("0")
+        ));
+    }
+}
@@ -0,0 +1,219 @@
+//! 多实例支持
+//!
+//! 通过 `--instance <name>` 启动的实例使用独立的数据目录、IPC 路径与端口，
+//! 可与默认实例同时运行。`--data-dir <path>` 直接指定数据目录，未同时指定实例名时
+//! 按路径派生实例名，因此不同数据目录的实例同样互不冲突。
+//!
+//! 端口偏移由实例名的哈希决定槽位，不同实例名可能落在同一槽位，因此首次启动时在默认实例的数据目录中
+//! 登记 [`REGISTRY_FILE`]，槽位已被其他实例占用时顺延到下一个空闲槽位，之后每次启动沿用登记的槽位。
+
+use super::dirs::APP_ID;
+use once_cell::sync::OnceCell;
+use std::path::{Path, PathBuf};
+
+static INSTANCE_NAME: OnceCell<Option<String>> = OnceCell::new();
+static DATA_DIR: OnceCell<Option<PathBuf>> = OnceCell::new();
+static PORT_SLOT: OnceCell<u64> = OnceCell::new();
+
+const MAX_NAME_LEN: usize = 32;
+const PORT_STEP: u16 = 10;
+const PORT_SLOTS: u64 = 100;
+/// 每行一个 `<实例名> <槽位>`
+const REGISTRY_FILE: &str = "instances";
+
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= MAX_NAME_LEN
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// 取出 `--<flag> <value>` 或 `--<flag>=<value>` 形式的参数值
+fn flag_value<I: IntoIterator<Item = String>>(args: I, flag: &str) -> Option<String> {
+    let long = format!("--{flag}");
+    let prefix = format!("--{flag}=");
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg == long {
+            return args.next();
+        }
+        if let Some(value) = arg.strip_prefix(&prefix) {
+            return Some(value.to_owned());
+        }
+    }
+    None
+}
+
+fn parse_args<I: IntoIterator<Item = String>>(args: I) -> Option<String> {
+    flag_value(args, "instance").filter(|name| is_valid_name(name))
+}
+
+fn parse_data_dir<I: IntoIterator<Item = String>>(args: I) -> Option<PathBuf> {
+    flag_value(args, "data-dir")
+        .filter(|dir| !dir.trim().is_empty())
+        .and_then(|dir| std::path::absolute(dir.trim()).ok())
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325_u64, |acc, b| {
+        (acc ^ u64::from(*b)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// 由数据目录派生的实例名
+fn data_dir_instance_name(dir: &Path) -> String {
+    format!("data-{:08x}", fnv1a(dir.to_string_lossy().as_bytes()) & 0xffff_ffff)
+}
+
+/// 从命令行参数中解析实例名与数据目录，需在任何路径或端口解析之前调用
+pub fn init_from_args() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let data_dir = DATA_DIR.get_or_init(|| parse_data_dir(args.iter().cloned()));
+    let name =
+        INSTANCE_NAME.get_or_init(|| parse_args(args).or_else(|| data_dir.as_deref().map(data_dir_instance_name)));
+    if let Some(name) = name.as_deref() {
+        PORT_SLOT.get_or_init(|| register_slot(name));
+    }
+}
+
+/// 默认实例的数据目录，与 tauri 的 `data_dir` 一致；此时 AppHandle 尚未创建，按平台约定推导
+fn registry_dir() -> Option<PathBuf> {
+    #[cfg(target_os = "windows")]
+    let base = std::env::var_os("APPDATA").map(PathBuf::from);
+    #[cfg(target_os = "macos")]
+    let base = std::env::var_os("HOME").map(|home| PathBuf::from(home).join("Library/Application Support"));
+    #[cfg(target_os = "linux")]
+    let base = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")));
+    Some(base?.join(APP_ID))
+}
+
+/// 读取登记表并返回实例的槽位，需要新登记时一并返回更新后的内容
+fn assign_slot(registry: &str, name: &str) -> (u64, Option<String>) {
+    let entries: Vec<(&str, u64)> = registry
+        .lines()
+        .filter_map(|line| {
+            let (entry, slot) = line.trim().split_once(' ')?;
+            Some((entry, slot.trim().parse().ok()?))
+        })
+        .collect();
+    if let Some((_, slot)) = entries.iter().find(|(entry, _)| *entry == name) {
+        return (*slot, None);
+    }
+    let start = fnv1a(name.as_bytes()) % PORT_SLOTS;
+    let slot = (0..PORT_SLOTS)
+        .map(|step| (start + step) % PORT_SLOTS)
+        .find(|slot| !entries.iter().any(|(_, used)| used == slot))
+        .unwrap_or(start);
+    let existing = registry.trim_end();
+    let separator = if existing.is_empty() { "" } else { "\n" };
+    (slot, Some(format!("{existing}{separator}{name} {slot}\n")))
+}
+
+/// 登记失败（如目录不可写）时退回到哈希槽位
+fn register_slot(name: &str) -> u64 {
+    let Some(dir) = registry_dir() else {
+        return fnv1a(name.as_bytes()) % PORT_SLOTS;
+    };
+    let path = dir.join(REGISTRY_FILE);
+    let registry = std::fs::read_to_string(&path).unwrap_or_default();
+    let (slot, updated) = assign_slot(&registry, name);
+    if let Some(updated) = updated {
+        let _ = std::fs::create_dir_all(&dir).and_then(|()| std::fs::write(&path, updated));
+    }
+    slot
+}
+
+/// `--data-dir` 指定的数据目录
+pub fn data_dir() -> Option<&'static Path> {
+    DATA_DIR.get().and_then(|dir| dir.as_deref())
+}
+
+/// 当前实例名，默认实例返回 `None`
+pub fn name() -> Option<&'static str> {
+    INSTANCE_NAME.get().and_then(|name| name.as_deref())
+}
+
+/// 为标识符追加实例后缀，默认实例保持不变
+pub fn suffixed(base: &str, separator: char) -> String {
+    match name() {
+        Some(name) => format!("{base}{separator}{name}"),
+        None => base.to_owned(),
+    }
+}
+
+fn port_offset_for(slot: u64) -> u16 {
+    let slot = u16::try_from(slot % PORT_SLOTS).unwrap_or(0) + 1;
+    slot * PORT_STEP
+}
+
+/// 按实例偏移默认端口，默认实例保持原端口
+pub fn offset_port(port: u16) -> u16 {
+    name().map_or(port, |name| {
+        // 未经 init_from_args 登记时按哈希计算
+        let slot = PORT_SLOT
+            .get()
+            .copied()
+            .unwrap_or_else(|| fnv1a(name.as_bytes()) % PORT_SLOTS);
+        port.saturating_add(port_offset_for(slot))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| (*s).to_owned()).collect()
+    }
+
+    #[test]
+    fn test_parse_instance_args() {
+        assert_eq!(parse_args(args(&["--instance", "work"])), Some("work".into()));
+        assert_eq!(
+            parse_args(args(&["--no-tray", "--instance=beta_1"])),
+            Some("beta_1".into())
+        );
+        assert_eq!(parse_args(args(&["--instance", "../evil"])), None);
+        assert_eq!(parse_args(args(&["--instance"])), None);
+        assert_eq!(parse_args(args(&["clash://install-config?url=x"])), None);
+    }
+
+    #[test]
+    fn test_parse_data_dir() {
+        let dir = parse_data_dir(args(&["--data-dir", "/tmp/work"]));
+        assert_eq!(dir, Some(PathBuf::from("/tmp/work")));
+        assert_eq!(parse_data_dir(args(&["--data-dir="])), None);
+        assert_eq!(parse_data_dir(args(&["--instance", "work"])), None);
+
+        let name = data_dir_instance_name(Path::new("/tmp/work"));
+        assert!(is_valid_name(&name));
+        assert_ne!(name, data_dir_instance_name(Path::new("/tmp/personal")));
+    }
+
+    #[test]
+    fn test_port_offset_is_spaced() {
+        let offset = port_offset_for(fnv1a(b"work"));
+        assert_eq!(offset % PORT_STEP, 0);
+        assert!((PORT_STEP..=PORT_STEP * 100).contains(&offset));
+        assert_eq!(port_offset_for(0), PORT_STEP);
+    }
+
+    #[test]
+    fn test_assign_slot_avoids_collisions() {
+        let (slot, updated) = assign_slot("", "work");
+        assert_eq!(slot, fnv1a(b"work") % PORT_SLOTS);
+        let registry = updated.unwrap_or_default();
+        assert_eq!(registry, format!("work {slot}\n"));
+
+        // 已登记的实例沿用原槽位，不再改写登记表
+        assert_eq!(assign_slot(&registry, "work"), (slot, None));
+
+        // 哈希到同一槽位的其他实例顺延
+        let taken = format!("other {}\n", fnv1a(b"home") % PORT_SLOTS);
+        let (home, updated) = assign_slot(&taken, "home");
+        assert_eq!(home, (fnv1a(b"home") + 1) % PORT_SLOTS);
+        assert!(updated.is_some_and(|updated| updated.ends_with(&format!("home {home}\n"))));
+    }
+}
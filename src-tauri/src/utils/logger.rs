@@ -0,0 +1,134 @@
+//! 应用日志的运行时控制
+//!
+//! 日志由 flexi_logger 写入 `logs` 目录并按大小轮转。`app_log_format` 为 `json` 时每行输出一个
+//! JSON 对象，便于外部工具解析，修改格式需重启生效；日志等级可通过 [`set_level`] 随时调整。
+
+use crate::{process::AsyncHandler, utils::dirs};
+use anyhow::{Result, bail};
+use flexi_logger::{DeferredNow, LogSpecBuilder, LogSpecification, LoggerHandle};
+use log::{LevelFilter, Record};
+use once_cell::sync::OnceCell;
+use std::{
+    io::Write as _,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+use zip::write::SimpleFileOptions;
+
+static LOGGER: OnceCell<LoggerHandle> = OnceCell::new();
+
+/// 保存 logger 句柄，之后才能在运行时修改等级
+pub fn register(handle: LoggerHandle) {
+    let _ = LOGGER.set(handle);
+}
+
+pub fn parse_level(level: &str) -> Option<LevelFilter> {
+    match level.to_lowercase().as_str() {
+        "silent" => Some(LevelFilter::Off),
+        "error" => Some(LevelFilter::Error),
+        "warn" => Some(LevelFilter::Warn),
+        "info" => Some(LevelFilter::Info),
+        "debug" => Some(LevelFilter::Debug),
+        "trace" => Some(LevelFilter::Trace),
+        _ => None,
+    }
+}
+
+pub fn build_spec(level: LevelFilter) -> LogSpecification {
+    let mut spec = LogSpecBuilder::new();
+    spec.default(level);
+    #[cfg(feature = "tracing")]
+    spec.module("tauri", LevelFilter::Debug)
+        .module("wry", LevelFilter::Off)
+        .module("tauri_plugin_mihomo", LevelFilter::Off);
+    spec.build()
+}
+
+/// 立即应用新的日志等级；未初始化 logger（如 tauri-dev）时只做校验
+pub fn set_level(level: &str) -> Result<()> {
+    let Some(filter) = parse_level(level) else {
+        bail!("unsupported log level: {level}");
+    };
+    if let Some(handle) = LOGGER.get() {
+        handle.set_new_spec(build_spec(filter));
+    }
+    Ok(())
+}
+
+/// 每行一个 JSON 对象的文件格式
+pub fn json_format(w: &mut dyn std::io::Write, now: &mut DeferredNow, record: &Record) -> std::io::Result<()> {
+    let line = serde_json::json!({
+        "time": now.format("%Y-%m-%dT%H:%M:%S%.3f%:z").to_string(),
+        "level": record.level().as_str(),
+        "target": record.target(),
+        "message": record.args().to_string(),
+    });
+    write!(w, "{line}")
+}
+
+fn recent_logs(dir: &Path, since: SystemTime) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter(|entry| entry.file_type().is_ok_and(|t| t.is_file()))
+        .filter(|entry| {
+            entry
+                .metadata()
+                .and_then(|m| m.modified())
+                .is_ok_and(|modified| modified >= since)
+        })
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "log"))
+        .collect()
+}
+
+/// 将最近 `range_hours` 小时内写入的应用、内核与服务日志打包为 zip，返回文件路径
+///
+/// 连接记录含有访问历史，不会被收集
+pub async fn collect_logs(range_hours: u32) -> Result<PathBuf> {
+    // 范围超出系统时间起点时收集全部日志
+    let since = SystemTime::now()
+        .checked_sub(Duration::from_secs(u64::from(range_hours.max(1)) * 3600))
+        .unwrap_or(SystemTime::UNIX_EPOCH);
+    let sources = [
+        ("app", dirs::app_logs_dir()?),
+        ("sidecar", dirs::sidecar_log_dir()?),
+        ("service", dirs::service_log_dir()?),
+    ];
+    let now = chrono::Local::now().format("%Y-%m-%d_%H-%M-%S");
+    let zip_path = dirs::app_temp_dir().join(format!("clash-verge-logs-{now}.zip"));
+
+    let target = zip_path.clone();
+    AsyncHandler::spawn_blocking(move || -> Result<()> {
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(&target)?);
+        let options = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+        for (prefix, dir) in sources {
+            for path in recent_logs(&dir, since) {
+                let Some(file_name) = path.file_name().and_then(|name| name.to_str()) else {
+                    continue;
+                };
+                zip.start_file(format!("{prefix}/{file_name}"), options)?;
+                zip.write_all(&std::fs::read(&path)?)?;
+            }
+        }
+        zip.finish()?;
+        Ok(())
+    })
+    .await??;
+    Ok(zip_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_level() {
+        assert_eq!(parse_level("silent"), Some(LevelFilter::Off));
+        assert_eq!(parse_level("DEBUG"), Some(LevelFilter::Debug));
+        assert_eq!(parse_level("verbose"), None);
+        assert!(set_level("verbose").is_err());
+    }
+}
@@ -0,0 +1,307 @@
+//! MaxMind DB 只读查询
+//!
+//! 仅实现按 IP 查询国家代码所需的部分，用于读取内核目录中的 `Country.mmdb`（MaxMind 格式，
+//! 数据为 `country.iso_code`）与 `geoip.metadb`（sing-geoip / Meta-geoip0 格式，数据为代码字符串或其数组）。
+//! 数据库文件来自第三方镜像，所有偏移均做越界检查，解析失败时返回 `None`。
+
+use anyhow::{Result, anyhow, bail};
+use std::net::IpAddr;
+
+const METADATA_MARKER: &[u8] = b"\xAB\xCD\xEFMaxMind.com";
+/// 数据段与搜索树之间的 16 字节分隔
+const DATA_SEPARATOR: usize = 16;
+/// 指针与容器的嵌套上限，防止构造的数据库形成循环
+const MAX_DEPTH: usize = 16;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Record {
+    String(std::string::String),
+    Uint(u64),
+    Map(Vec<(std::string::String, Self)>),
+    Array(Vec<Self>),
+    Other,
+}
+
+impl Record {
+    fn get(&self, key: &str) -> Option<&Self> {
+        match self {
+            Self::Map(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    const fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(s) => Some(s.as_str()),
+            _ => None,
+        }
+    }
+
+    const fn as_uint(&self) -> Option<u64> {
+        match self {
+            Self::Uint(n) => Some(*n),
+            _ => None,
+        }
+    }
+}
+
+struct Decoder<'a> {
+    /// 数据段（指针相对于数据段起始）
+    data: &'a [u8],
+}
+
+impl Decoder<'_> {
+    fn bytes(&self, offset: usize, len: usize) -> Result<&[u8]> {
+        self.data
+            .get(offset..offset.saturating_add(len))
+            .ok_or_else(|| anyhow!("mmdb data out of bounds"))
+    }
+
+    fn uint(&self, offset: usize, len: usize) -> Result<u64> {
+        if len > 8 {
+            bail!("mmdb integer too large");
+        }
+        Ok(self
+            .bytes(offset, len)?
+            .iter()
+            .fold(0u64, |acc, &b| (acc << 8) | u64::from(b)))
+    }
+
+    /// 解码 `offset` 处的值，返回值与其后的偏移
+    fn decode(&self, offset: usize, depth: usize) -> Result<(Record, usize)> {
+        if depth > MAX_DEPTH {
+            bail!("mmdb data nested too deep");
+        }
+        let ctrl = *self.bytes(offset, 1)?.first().unwrap_or(&0);
+        let mut offset = offset + 1;
+        let mut kind = ctrl >> 5;
+        if kind == 1 {
+            let size = usize::from((ctrl >> 3) & 0x3);
+            let base = u64::from(ctrl & 0x7);
+            let pointer = match size {
+                0 => (base << 8) | self.uint(offset, 1)?,
+                1 => ((base << 16) | self.uint(offset, 2)?) + 2048,
+                2 => ((base << 24) | self.uint(offset, 3)?) + 526_336,
+                _ => self.uint(offset, 4)?,
+            };
+            let (record, _) = self.decode(usize::try_from(pointer)?, depth + 1)?;
+            return Ok((record, offset + size + 1));
+        }
+        if kind == 0 {
+            kind = 7 + *self.bytes(offset, 1)?.first().unwrap_or(&0);
+            offset += 1;
+        }
+        let size = usize::from(ctrl & 0x1f);
+        let (size, offset) = match size {
+            29 => (29 + usize::try_from(self.uint(offset, 1)?)?, offset + 1),
+            30 => (285 + usize::try_from(self.uint(offset, 2)?)?, offset + 2),
+            31 => (65_821 + usize::try_from(self.uint(offset, 3)?)?, offset + 3),
+            _ => (size, offset),
+        };
+        match kind {
+            2 => {
+                let text = std::str::from_utf8(self.bytes(offset, size)?)?;
+                Ok((Record::String(text.into()), offset + size))
+            }
+            5 | 6 | 9 => Ok((Record::Uint(self.uint(offset, size)?), offset + size)),
+            7 => {
+                let mut entries = Vec::with_capacity(size.min(64));
+                let mut next = offset;
+                for _ in 0..size {
+                    let (key, after_key) = self.decode(next, depth + 1)?;
+                    let (value, after_value) = self.decode(after_key, depth + 1)?;
+                    let Record::String(key) = key else {
+                        bail!("mmdb map key is not a string");
+                    };
+                    entries.push((key, value));
+                    next = after_value;
+                }
+                Ok((Record::Map(entries), next))
+            }
+            11 => {
+                let mut items = Vec::with_capacity(size.min(64));
+                let mut next = offset;
+                for _ in 0..size {
+                    let (item, after) = self.decode(next, depth + 1)?;
+                    items.push(item);
+                    next = after;
+                }
+                Ok((Record::Array(items), next))
+            }
+            // 布尔值的大小即其值，不占用数据
+            14 => Ok((Record::Other, offset)),
+            3 => Ok((Record::Other, offset + 8)),
+            15 => Ok((Record::Other, offset + 4)),
+            4 | 8 | 10 => Ok((Record::Other, offset + size)),
+            _ => bail!("unsupported mmdb data type {kind}"),
+        }
+    }
+}
+
+/// 已加载的数据库
+pub struct Reader {
+    buf: Vec<u8>,
+    node_count: usize,
+    record_size: usize,
+    ip_version: u64,
+}
+
+impl Reader {
+    pub fn from_bytes(buf: Vec<u8>) -> Result<Self> {
+        let marker = buf
+            .windows(METADATA_MARKER.len())
+            .rposition(|window| window == METADATA_MARKER)
+            .ok_or_else(|| anyhow!("mmdb metadata not found"))?;
+        let metadata_start = marker + METADATA_MARKER.len();
+        let decoder = Decoder {
+            data: buf.get(metadata_start..).unwrap_or_default(),
+        };
+        let (metadata, _) = decoder.decode(0, 0)?;
+        let field = |key: &str| {
+            metadata
+                .get(key)
+                .and_then(Record::as_uint)
+                .ok_or_else(|| anyhow!("mmdb metadata missing {key}"))
+        };
+        let node_count = usize::try_from(field("node_count")?)?;
+        let record_size = usize::try_from(field("record_size")?)?;
+        let ip_version = field("ip_version")?;
+        if ![24, 28, 32].contains(&record_size) {
+            bail!("unsupported mmdb record size {record_size}");
+        }
+        if node_count.saturating_mul(record_size / 4) > marker {
+            bail!("mmdb search tree out of bounds");
+        }
+        Ok(Self {
+            buf,
+            node_count,
+            record_size,
+            ip_version,
+        })
+    }
+
+    fn read_node(&self, node: usize, bit: u8) -> Option<usize> {
+        let node_bytes = self.record_size / 4;
+        let b = self.buf.get(node * node_bytes..(node + 1) * node_bytes)?;
+        let be = |bytes: &[u8]| bytes.iter().fold(0usize, |acc, &x| (acc << 8) | usize::from(x));
+        Some(match (self.record_size, bit) {
+            (24, 0) => be(b.get(0..3)?),
+            (24, _) => be(b.get(3..6)?),
+            (28, 0) => (usize::from(*b.get(3)? & 0xF0) << 20) | be(b.get(0..3)?),
+            (28, _) => (usize::from(*b.get(3)? & 0x0F) << 24) | be(b.get(4..7)?),
+            (_, 0) => be(b.get(0..4)?),
+            (_, _) => be(b.get(4..8)?),
+        })
+    }
+
+    fn lookup(&self, ip: IpAddr) -> Option<Record> {
+        let bytes: Vec<u8> = match (ip, self.ip_version) {
+            (IpAddr::V4(v4), 4) => v4.octets().to_vec(),
+            // IPv6 数据库中 IPv4 地址位于 ::/96 之下
+            (IpAddr::V4(v4), _) => v4.to_ipv6_compatible().octets().to_vec(),
+            (IpAddr::V6(v6), 6) => v6.octets().to_vec(),
+            (IpAddr::V6(v6), _) => v6.to_ipv4_mapped()?.octets().to_vec(),
+        };
+        let mut node = 0;
+        for index in 0..bytes.len() * 8 {
+            if node >= self.node_count {
+                break;
+            }
+            let bit = (bytes.get(index / 8)? >> (7 - index % 8)) & 1;
+            node = self.read_node(node, bit)?;
+        }
+        if node <= self.node_count {
+            return None;
+        }
+        let data_start = self.node_count * self.record_size / 4 + DATA_SEPARATOR;
+        let decoder = Decoder {
+            data: self.buf.get(data_start..)?,
+        };
+        let offset = node - self.node_count - DATA_SEPARATOR;
+        decoder.decode(offset, 0).ok().map(|(record, _)| record)
+    }
+
+    /// 查询 IP 所属国家的 alpha-2 代码（大写）
+    pub fn country(&self, ip: IpAddr) -> Option<std::string::String> {
+        let record = self.lookup(ip)?;
+        let code = match &record {
+            Record::String(code) => Some(code.as_str()),
+            Record::Array(codes) => codes.iter().find_map(Record::as_str),
+            Record::Map(_) => ["country", "registered_country"]
+                .iter()
+                .find_map(|key| record.get(key)?.get("iso_code")?.as_str()),
+            _ => None,
+        }?;
+        (!code.is_empty()).then(|| code.to_ascii_uppercase())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 控制字节 + 内容
+    fn string(s: &str) -> Vec<u8> {
+        let mut out = vec![(2 << 5) | u8::try_from(s.len()).unwrap_or_default()];
+        out.extend_from_slice(s.as_bytes());
+        out
+    }
+
+    fn uint16(key: &str, n: u16) -> Vec<u8> {
+        let mut out = string(key);
+        out.push((5 << 5) | 2);
+        out.extend_from_slice(&n.to_be_bytes());
+        out
+    }
+
+    /// 构造一个 IPv4、24 位记录的数据库：1.0.0.0/8 -> 字符串 "cn"，
+    /// 2.0.0.0/8 -> {"country": {"iso_code": "JP"}}，其余地址无数据
+    fn build_db() -> Vec<u8> {
+        let mut data = string("cn");
+        let map_offset = data.len();
+        data.push((7 << 5) | 1);
+        data.extend(string("country"));
+        data.push((7 << 5) | 1);
+        data.extend(string("iso_code"));
+        data.extend(string("JP"));
+
+        // 前 7 位均为 0，第 8 位区分 1.x 与 0.x；2.x 为 0000_0010
+        let node_count = 9u32;
+        let empty = node_count;
+        let cn = node_count + 16;
+        let jp = node_count + 16 + u32::try_from(map_offset).unwrap_or_default();
+        let mut tree = Vec::new();
+        let mut push = |left: u32, right: u32| {
+            tree.extend_from_slice(&left.to_be_bytes()[1..]);
+            tree.extend_from_slice(&right.to_be_bytes()[1..]);
+        };
+        for node in 0..6 {
+            push(node + 1, empty);
+        }
+        push(7, 8); // 第 7 位：0 -> 0.x/1.x，1 -> 2.x/3.x
+        push(empty, cn);
+        push(jp, empty);
+
+        let mut db = tree;
+        db.extend_from_slice(&[0; 16]);
+        db.extend(data);
+        db.extend_from_slice(METADATA_MARKER);
+        db.push((7 << 5) | 3);
+        db.extend(uint16("node_count", u16::try_from(node_count).unwrap_or_default()));
+        db.extend(uint16("record_size", 24));
+        db.extend(uint16("ip_version", 4));
+        db
+    }
+
+    #[test]
+    #[allow(clippy::expect_used)]
+    fn test_country_lookup() {
+        let reader = Reader::from_bytes(build_db()).expect("valid database");
+        let lookup = |ip: &str| reader.country(ip.parse().unwrap_or_else(|_| IpAddr::from([0, 0, 0, 0])));
+        assert_eq!(lookup("1.2.3.4").as_deref(), Some("CN"));
+        assert_eq!(lookup("2.2.3.4").as_deref(), Some("JP"));
+        assert_eq!(lookup("0.2.3.4"), None);
+        assert_eq!(lookup("3.2.3.4"), None);
+        assert!(Reader::from_bytes(b"not a database".to_vec()).is_err());
+    }
+}
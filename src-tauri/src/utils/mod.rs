@@ -1,13 +1,18 @@
 pub mod autostart;
+pub mod bbolt;
+pub mod crash;
+pub mod crypto;
 pub mod dirs;
 pub mod format;
 pub mod help;
 pub mod i18n;
 pub mod init;
+pub mod instance;
 #[cfg(target_os = "linux")]
 pub mod linux;
+pub mod logger;
+pub mod mmdb;
 pub mod network;
-pub mod notification;
 pub mod resolve;
 pub mod server;
 pub mod singleton;
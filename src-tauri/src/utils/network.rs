@@ -1,15 +1,26 @@
-use crate::config::Config;
-use anyhow::Result;
+use crate::{
+    config::Config,
+    utils::{crypto, dirs},
+};
+use anyhow::{Context as _, Result, bail};
 use base64::{Engine as _, engine::general_purpose};
 use reqwest::{
-    Client, Proxy, StatusCode,
-    header::{HeaderMap, HeaderValue, USER_AGENT},
+    Client, Proxy, RequestBuilder, StatusCode,
+    header::{CONTENT_RANGE, ETAG, HeaderMap, HeaderName, HeaderValue, IF_RANGE, LAST_MODIFIED, RANGE, USER_AGENT},
 };
+use sha2::{Digest as _, Sha256};
 use smartstring::alias::String;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
 use std::time::{Duration, Instant};
 use sysproxy::Sysproxy;
 use tauri::Url;
-use tokio::sync::Mutex;
+use tokio::{fs, io::AsyncWriteExt as _, sync::Mutex};
+
+/// 未完成的下载保存在临时目录的此子目录下
+const PARTIAL_DIR: &str = "partial-downloads";
+/// 每下载这么多字节回报一次进度
+const PROGRESS_STEP: u64 = 64 * 1024;
 
 #[derive(Debug)]
 pub struct HttpResponse {
@@ -36,7 +47,24 @@ impl HttpResponse {
     }
 }
 
+/// 下载进度，`total` 为空表示服务器未返回长度
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DownloadProgress {
+    pub downloaded: u64,
+    pub total: Option<u64>,
+}
+
+/// 请求参数，`headers` 中的同名请求头覆盖默认的 User-Agent
 #[derive(Debug, Clone, Copy)]
+pub struct RequestParams<'a> {
+    pub proxy_type: ProxyType,
+    pub timeout_secs: Option<u64>,
+    pub user_agent: Option<&'a str>,
+    pub accept_invalid_certs: bool,
+    pub headers: Option<&'a BTreeMap<String, String>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ProxyType {
     None,
     Localhost,
@@ -149,13 +177,7 @@ impl NetworkManager {
         let proxy_url: Option<std::string::String> = match proxy_type {
             ProxyType::None => None,
             ProxyType::Localhost => {
-                let port = {
-                    let verge_port = Config::verge().await.data_arc().verge_mixed_port;
-                    match verge_port {
-                        Some(port) => port,
-                        None => Config::clash().await.data_arc().get_mixed_port(),
-                    }
-                };
+                let port = Config::mixed_port().await;
                 Some(format!("http://127.0.0.1:{port}"))
             }
             ProxyType::System => {
@@ -191,7 +213,45 @@ impl NetworkManager {
         timeout_secs: Option<u64>,
         user_agent: Option<String>,
         accept_invalid_certs: bool,
+        custom_headers: Option<&BTreeMap<String, String>>,
     ) -> Result<HttpResponse> {
+        let params = RequestParams {
+            proxy_type,
+            timeout_secs,
+            user_agent: user_agent.as_deref(),
+            accept_invalid_certs,
+            headers: custom_headers,
+        };
+        let (request_builder, _) = self.prepare_get(url, &params).await?;
+
+        let response = match request_builder.send().await {
+            Ok(resp) => resp,
+            Err(e) => {
+                self.record_connection_error(&format!("Request failed: {}", e)).await;
+                return Err(anyhow::anyhow!("Request failed: {}", e));
+            }
+        };
+
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = match response.text().await {
+            Ok(text) => text.into(),
+            Err(e) => {
+                self.record_connection_error(&format!("Failed to read response body: {}", e))
+                    .await;
+                return Err(anyhow::anyhow!("Failed to read response body: {}", e));
+            }
+        };
+
+        Ok(HttpResponse::new(status, headers, body))
+    }
+
+    /// 构造 GET 请求，地址中的账号密码转为 Basic 鉴权，返回请求与去掉账号密码的地址
+    async fn prepare_get(
+        &self,
+        url: &str,
+        params: &RequestParams<'_>,
+    ) -> Result<(RequestBuilder, std::string::String)> {
         if self.should_reset_clients().await {
             self.reset_clients().await;
         }
@@ -207,6 +267,13 @@ impl NetworkManager {
             extra_headers.insert("Authorization", HeaderValue::from_str(&format!("Basic {}", encoded))?);
         }
 
+        // 订阅自定义请求头，同名时覆盖默认的 User-Agent
+        for (key, value) in params.headers.into_iter().flatten() {
+            let name = HeaderName::from_bytes(key.as_bytes()).with_context(|| format!("invalid header name: {key}"))?;
+            let value = HeaderValue::from_str(value).with_context(|| format!("invalid value for header {key}"))?;
+            extra_headers.insert(name, value);
+        }
+
         let clean_url = {
             let mut no_auth = parsed.clone();
             no_auth.set_username("").ok();
@@ -216,7 +283,12 @@ impl NetworkManager {
 
         // 创建请求
         let client = self
-            .create_request(proxy_type, timeout_secs, user_agent, accept_invalid_certs)
+            .create_request(
+                params.proxy_type,
+                params.timeout_secs,
+                params.user_agent.map(String::from),
+                params.accept_invalid_certs,
+            )
             .await?;
 
         let mut request_builder = client.get(&clean_url);
@@ -225,7 +297,29 @@ impl NetworkManager {
             request_builder = request_builder.header(key, value);
         }
 
-        let response = match request_builder.send().await {
+        Ok((request_builder, clean_url))
+    }
+
+    /// 下载订阅等较大的文件并回报进度
+    ///
+    /// 服务器返回 `ETag` 或 `Last-Modified` 时，中断的下载保留在临时目录，
+    /// 下次请求同一地址时带上 `Range` 与 `If-Range` 续传；内容已变化时服务器返回完整内容，重新下载
+    pub async fn download_resumable(
+        &self,
+        url: &str,
+        params: &RequestParams<'_>,
+        mut on_progress: impl FnMut(DownloadProgress) + Send,
+    ) -> Result<HttpResponse> {
+        let (mut request_builder, clean_url) = self.prepare_get(url, params).await?;
+        let partial = PartialDownload::new(&clean_url);
+        let resume = partial.resume_point().await;
+        if let Some((offset, validator)) = &resume {
+            request_builder = request_builder
+                .header(RANGE, format!("bytes={offset}-"))
+                .header(IF_RANGE, validator.as_str());
+        }
+
+        let mut response = match request_builder.send().await {
             Ok(resp) => resp,
             Err(e) => {
                 self.record_connection_error(&format!("Request failed: {}", e)).await;
@@ -235,15 +329,136 @@ impl NetworkManager {
 
         let status = response.status();
         let headers = response.headers().clone();
-        let body = match response.text().await {
-            Ok(text) => text.into(),
-            Err(e) => {
-                self.record_connection_error(&format!("Failed to read response body: {}", e))
-                    .await;
-                return Err(anyhow::anyhow!("Failed to read response body: {}", e));
+        if !status.is_success() {
+            if status == StatusCode::RANGE_NOT_SATISFIABLE {
+                partial.clear().await;
+            }
+            let body = response.text().await.unwrap_or_default().into();
+            return Ok(HttpResponse::new(status, headers, body));
+        }
+
+        // 只有服务器确认从断点继续时才追加，否则从头下载
+        let content_range = headers
+            .get(CONTENT_RANGE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(parse_content_range);
+        let (mut downloaded, total) = match (&resume, content_range) {
+            (Some((offset, _)), Some((start, total))) if status == StatusCode::PARTIAL_CONTENT && start == *offset => {
+                (start, total)
             }
+            _ => (0, response.content_length()),
         };
+        // If-Range 只接受强校验值
+        let validator = headers
+            .get(ETAG)
+            .and_then(|value| value.to_str().ok())
+            .filter(|etag| !etag.starts_with("W/"))
+            .or_else(|| headers.get(LAST_MODIFIED).and_then(|value| value.to_str().ok()))
+            .map(String::from);
+        let mut file = partial.open(downloaded > 0, validator.as_deref()).await?;
+
+        on_progress(DownloadProgress { downloaded, total });
+        let mut reported = downloaded;
+        loop {
+            match response.chunk().await {
+                Ok(Some(chunk)) => {
+                    file.write_all(&chunk).await?;
+                    downloaded += u64::try_from(chunk.len()).unwrap_or_default();
+                    if downloaded - reported >= PROGRESS_STEP {
+                        reported = downloaded;
+                        on_progress(DownloadProgress { downloaded, total });
+                    }
+                }
+                Ok(None) => break,
+                Err(e) => {
+                    file.flush().await?;
+                    drop(file);
+                    // 没有校验值时无法确认续传内容一致，丢弃已下载的部分
+                    if validator.is_none() {
+                        partial.clear().await;
+                    }
+                    self.record_connection_error(&format!("Failed to read response body: {}", e))
+                        .await;
+                    bail!("Failed to read response body after {downloaded} bytes: {e}");
+                }
+            }
+        }
+        file.flush().await?;
+        drop(file);
+        on_progress(DownloadProgress {
+            downloaded,
+            total: total.or(Some(downloaded)),
+        });
+
+        let data = fs::read(&partial.data).await?;
+        partial.clear().await;
+        let body = String::from(&*std::string::String::from_utf8_lossy(&data));
+        // 续传得到的是完整内容，对调用方而言与一次完整下载相同
+        Ok(HttpResponse::new(StatusCode::OK, headers, body))
+    }
+}
 
-        Ok(HttpResponse::new(status, headers, body))
+/// 未完成的下载：数据文件与记录服务器校验值的文件
+struct PartialDownload {
+    data: PathBuf,
+    validator: PathBuf,
+}
+
+impl PartialDownload {
+    fn new(url: &str) -> Self {
+        let dir = dirs::app_temp_dir().join(PARTIAL_DIR);
+        let name = crypto::to_hex(&Sha256::digest(url.as_bytes()));
+        Self {
+            data: dir.join(format!("{name}.part")),
+            validator: dir.join(format!("{name}.validator")),
+        }
+    }
+
+    /// 已下载的字节数与服务器校验值
+    async fn resume_point(&self) -> Option<(u64, String)> {
+        let offset = fs::metadata(&self.data).await.ok()?.len();
+        let validator = fs::read_to_string(&self.validator).await.ok()?;
+        (offset > 0 && !validator.is_empty()).then(|| (offset, validator.into()))
+    }
+
+    async fn open(&self, append: bool, validator: Option<&str>) -> Result<fs::File> {
+        if let Some(dir) = self.data.parent() {
+            fs::create_dir_all(dir).await?;
+        }
+        if append {
+            return Ok(fs::OpenOptions::new().append(true).open(&self.data).await?);
+        }
+        match validator {
+            Some(validator) => fs::write(&self.validator, validator).await?,
+            None => {
+                let _ = fs::remove_file(&self.validator).await;
+            }
+        }
+        Ok(fs::File::create(&self.data).await?)
+    }
+
+    async fn clear(&self) {
+        let _ = fs::remove_file(&self.data).await;
+        let _ = fs::remove_file(&self.validator).await;
+    }
+}
+
+/// 解析 `Content-Range: bytes 100-999/1000`，返回起始位置与总长度
+fn parse_content_range(value: &str) -> Option<(u64, Option<u64>)> {
+    let (range, total) = value.strip_prefix("bytes ")?.split_once('/')?;
+    let start = range.split_once('-')?.0.trim().parse().ok()?;
+    Some((start, total.trim().parse().ok()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_content_range() {
+        assert_eq!(parse_content_range("bytes 100-999/1000"), Some((100, Some(1000))));
+        assert_eq!(parse_content_range("bytes 0-99/*"), Some((0, None)));
+        assert_eq!(parse_content_range("bytes */1000"), None);
+        assert_eq!(parse_content_range("items 0-9/10"), None);
     }
 }
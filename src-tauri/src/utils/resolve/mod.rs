@@ -6,22 +6,36 @@ use flexi_logger::LoggerHandle;
 use crate::{
     config::Config,
     core::{
-        CoreManager, Timer, handle,
+        CoreManager, Timer,
+        control_api::ControlApi,
+        handle,
         hotkey::Hotkey,
-        service::{SERVICE_MANAGER, ServiceManager, is_service_ipc_path_exists},
+        kill_switch::KillSwitch,
+        lan_sync::LanSync,
+        manager::CoreWatchdog,
+        network_monitor::NetworkMonitor,
+        plugin::PluginManager,
+        resume::ResumeWatcher,
+        scheduler::Scheduler,
+        secrets,
+        service::{self, SERVICE_MANAGER, ServiceManager, is_service_ipc_path_exists},
+        stats::StatsCollector,
         sysopt,
+        telegram::TelegramBot,
         tray::Tray,
+        webhook::WebhookManager,
     },
     feat,
     module::{auto_backup::AutoBackupManager, lightweight::auto_lightweight_boot},
     process::AsyncHandler,
-    utils::{init, server, window_manager::WindowManager},
+    utils::{crash, init, server, window_manager::WindowManager},
 };
 use clash_verge_logging::{Type, logging, logging_error};
 use clash_verge_signal;
 
 pub mod dns;
 pub mod scheme;
+pub mod startup;
 pub mod ui;
 pub mod window;
 pub mod window_script;
@@ -30,6 +44,7 @@ static RESOLVE_DONE: AtomicBool = AtomicBool::new(false);
 
 pub fn init_work_dir_and_logger() -> Option<LoggerHandle> {
     AsyncHandler::block_on(async {
+        feat::cleanup_previous_app_home();
         init_work_config().await;
         init_resources().await;
 
@@ -61,21 +76,66 @@ pub fn resolve_setup_async() {
         logging!(info, Type::ClashVergeRev, "Version: {}", env!("CARGO_PKG_VERSION"));
 
         init_startup_script().await;
-        init_verge_config().await;
-        Config::verify_config_initialization().await;
+        startup::begin();
+        startup::run_stage(&startup::CONFIG, || async {
+            Config::init_config().await?;
+            Config::verify_config_initialization().await;
+            secrets::migrate().await;
+            Ok(())
+        })
+        .await;
+        crash::notify_pending();
+        init_event_subscribers();
         init_window().await;
 
         let core_init = AsyncHandler::spawn(|| async {
-            init_service_manager().await;
-            init_core_manager().await;
-            init_system_proxy().await;
-            init_system_proxy_guard().await;
+            // 只推迟内核相关的阶段，托盘与窗口不受开机延迟影响
+            feat::wait_autostart_delay().await;
+            startup::run_stage(&startup::CORE, || async {
+                init_service_manager().await;
+                init_port_check().await;
+                CoreManager::global().init().await
+            })
+            .await;
+            init_core_watchdog();
+            startup::run_stage(&startup::API_READY, || async {
+                let mihomo = handle::Handle::mihomo().await;
+                let result = mihomo.get_version().await;
+                drop(mihomo);
+                result.map(|_| ()).map_err(|err| anyhow::anyhow!("{err}"))
+            })
+            .await;
+            startup::run_stage(&startup::SYSTEM_PROXY, || async {
+                sysopt::Sysopt::global().update_sysproxy().await?;
+                init_system_proxy_guard().await;
+                Ok(())
+            })
+            .await;
+            init_stats();
+            init_exit_ip_monitor();
+            init_core_memory_monitor();
+            init_scheduler();
+            init_network_monitor();
+            init_resume_watcher();
+            init_webhooks();
+            init_telegram_bot();
+            init_lan_sync();
+            init_control_api();
+            startup::run_stage(&startup::DISCORD_RPC, || async {
+                if crate::cmd::init_discord_rpc_on_startup().await {
+                    Ok(())
+                } else {
+                    anyhow::bail!("Discord is not reachable")
+                }
+            })
+            .await;
         });
 
-        let tray_init = async {
-            init_tray().await;
+        let tray_init = startup::run_stage(&startup::TRAY, || async {
+            init_tray().await?;
             refresh_tray_menu().await;
-        };
+            Ok(())
+        });
 
         let _ = futures::join!(
             core_init,
@@ -84,13 +144,17 @@ pub fn resolve_setup_async() {
             init_hotkey(),
             init_auto_lightweight_boot(),
             init_auto_backup(),
+            init_plugins(),
+            init_autostart_entries(),
         );
+        startup::finish();
     });
 }
 
 pub async fn resolve_reset_async() -> Result<(), anyhow::Error> {
     sysopt::Sysopt::global().reset_sysproxy().await?;
     CoreManager::global().stop_core().await?;
+    logging_error!(Type::Core, KillSwitch::global().release().await);
 
     #[cfg(target_os = "macos")]
     {
@@ -136,6 +200,10 @@ pub(super) async fn init_hotkey() {
     logging_error!(Type::Setup, Hotkey::global().init(skip_register_hotkeys).await);
 }
 
+pub(super) async fn init_autostart_entries() {
+    logging_error!(Type::Setup, feat::migrate_autostart_entries().await);
+}
+
 pub(super) async fn init_auto_lightweight_boot() {
     logging_error!(Type::Setup, auto_lightweight_boot().await);
 }
@@ -144,6 +212,10 @@ pub(super) async fn init_auto_backup() {
     logging_error!(Type::Setup, AutoBackupManager::global().init().await);
 }
 
+pub(super) async fn init_plugins() {
+    logging_error!(Type::Setup, PluginManager::global().init().await);
+}
+
 pub fn init_signal() {
     logging!(info, Type::Setup, "Initializing signal handlers...");
     clash_verge_signal::register(feat::quit);
@@ -153,19 +225,16 @@ pub async fn init_work_config() {
     logging_error!(Type::Setup, init::init_config().await);
 }
 
-pub(super) async fn init_tray() {
+pub(super) async fn init_tray() -> Result<()> {
     if std::env::var("CLASH_VERGE_DISABLE_TRAY").unwrap_or_default() == "1" {
-        return;
+        return Ok(());
     }
-    logging_error!(Type::Setup, Tray::global().init().await);
-}
-
-pub(super) async fn init_verge_config() {
-    logging_error!(Type::Setup, Config::init_config().await);
+    Tray::global().init().await
 }
 
 pub(super) async fn init_service_manager() {
     clash_verge_service_ipc::set_config(Some(ServiceManager::config())).await;
+    service::init_health_check();
     if !is_service_ipc_path_exists() {
         return;
     }
@@ -174,18 +243,66 @@ pub(super) async fn init_service_manager() {
     }
 }
 
-pub(super) async fn init_core_manager() {
-    logging_error!(Type::Setup, CoreManager::global().init().await);
-}
-
-pub(super) async fn init_system_proxy() {
-    logging_error!(Type::Setup, sysopt::Sysopt::global().update_sysproxy().await);
+pub(super) async fn init_port_check() {
+    feat::report_port_conflicts().await;
 }
 
 pub(super) async fn init_system_proxy_guard() {
     sysopt::Sysopt::global().refresh_guard().await;
 }
 
+pub(super) fn init_core_watchdog() {
+    CoreWatchdog::global().init();
+}
+
+pub(super) fn init_stats() {
+    StatsCollector::global().init();
+}
+
+pub(super) fn init_exit_ip_monitor() {
+    feat::ExitIpMonitor::global().init();
+}
+
+pub(super) fn init_core_memory_monitor() {
+    feat::CoreMemoryMonitor::global().init();
+}
+
+pub(super) fn init_scheduler() {
+    Scheduler::global().init();
+}
+
+pub(super) fn init_network_monitor() {
+    NetworkMonitor::global().init();
+}
+
+pub(super) fn init_resume_watcher() {
+    ResumeWatcher::global().init();
+}
+
+/// 托盘、Discord、回调与插件订阅状态事件
+pub(super) fn init_event_subscribers() {
+    Tray::global().listen_state_events();
+    crate::cmd::discord::listen_state_events();
+    WebhookManager::global().listen_state_events();
+    PluginManager::global().listen_state_events();
+}
+
+pub(super) fn init_webhooks() {
+    WebhookManager::global().init();
+}
+
+pub(super) fn init_telegram_bot() {
+    TelegramBot::global().init();
+}
+
+pub(super) fn init_lan_sync() {
+    LanSync::global().init();
+}
+
+pub(super) fn init_control_api() {
+    ControlApi::global().init();
+}
+
 pub(super) async fn refresh_tray_menu() {
     logging_error!(Type::Setup, Tray::global().update_part().await);
 }
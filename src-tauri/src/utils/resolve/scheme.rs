@@ -4,16 +4,91 @@ use anyhow::{Result, bail};
 use percent_encoding::percent_decode_str;
 use smartstring::alias::String;
 use tauri::Url;
+use tauri_plugin_dialog::{DialogExt as _, MessageDialogButtons, MessageDialogKind};
+use tokio::sync::oneshot;
 
 use crate::{
     config::{Config, PrfItem, profiles},
     core::handle,
+    feat,
+    utils::{crash, i18n},
 };
 use clash_verge_logging::{Type, logging};
 
-pub(super) async fn resolve_scheme(param: &str) -> Result<()> {
-    logging!(info, Type::Config, "received deep link: {param}");
+const MAX_LINK_LENGTH: usize = 8192;
+const MAX_URL_LENGTH: usize = 4096;
+const MAX_NAME_LENGTH: usize = 64;
+const MAX_NODE_LENGTH: usize = 256;
+
+/// 深层链接支持的操作
+///
+/// - `clash://install-config?url=<订阅地址>&name=<名称>` 导入订阅
+/// - `clash-verge://select-node?group=<代理组>&node=<节点>` 切换节点
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum DeepLinkAction {
+    InstallConfig { url: String, name: Option<String> },
+    SelectNode { group: String, node: String },
+}
+
+/// 去掉控制字符与首尾空白，超长时截断
+fn sanitize_text(value: &str, max_chars: usize) -> String {
+    value
+        .chars()
+        .filter(|c| !c.is_control())
+        .take(max_chars)
+        .collect::<std::string::String>()
+        .trim()
+        .into()
+}
 
+fn validate_subscription_url(raw: &str) -> Result<String> {
+    let raw = raw.trim();
+    if raw.len() > MAX_URL_LENGTH {
+        bail!("subscription url is too long");
+    }
+    if raw.chars().any(|c| c.is_control() || c.is_whitespace()) {
+        bail!("subscription url contains invalid characters");
+    }
+    let url = Url::parse(raw)?;
+    if !matches!(url.scheme(), "http" | "https") || url.host_str().is_none_or(str::is_empty) {
+        bail!("subscription url must be an http(s) address");
+    }
+    Ok(raw.into())
+}
+
+/// 取 `install-config` 链接中的订阅地址，参数名需完全等于 `url`（`xurl=` 等不会被误认）。
+/// 订阅地址自身可能带有未编码的 `&`，因此取该参数之后的全部内容
+pub fn install_config_url(link: &Url) -> Option<std::string::String> {
+    let query = link.query()?;
+    let mut offset = 0;
+    for pair in query.split('&') {
+        let (key, _) = pair.split_once('=').unwrap_or((pair, ""));
+        if percent_decode_str(key).decode_utf8_lossy() == "url" {
+            let value = query.get(offset + key.len() + 1..).unwrap_or_default();
+            return Some(percent_decode_str(value).decode_utf8_lossy().into_owned());
+        }
+        offset += pair.len() + 1;
+    }
+    None
+}
+
+fn required_param(link: &Url, key: &str) -> Result<String> {
+    let value = link
+        .query_pairs()
+        .find(|(k, _)| k == key)
+        .map(|(_, v)| sanitize_text(&v, MAX_NODE_LENGTH))
+        .unwrap_or_default();
+    if value.is_empty() {
+        bail!("missing {key} parameter in deep link");
+    }
+    Ok(value)
+}
+
+/// 解析并校验深层链接，不执行任何操作
+fn parse_deep_link(param: &str) -> Result<DeepLinkAction> {
+    if param.len() > MAX_LINK_LENGTH {
+        bail!("deep link is too long");
+    }
     let param_str = if param.starts_with("[") && param.len() > 4 {
         param
             .get(2..param.len() - 2)
@@ -22,44 +97,63 @@ pub(super) async fn resolve_scheme(param: &str) -> Result<()> {
         param
     };
 
-    // 解析 URL
-    let link_parsed = match Url::parse(param_str) {
+    let link = match Url::parse(param_str) {
         Ok(url) => url,
         Err(e) => {
             bail!("failed to parse deep link: {:?}, param: {:?}", e, param);
         }
     };
+    if !matches!(link.scheme(), "clash" | "clash-verge") {
+        bail!("unsupported deep link scheme: {}", link.scheme());
+    }
 
-    let (url_param, name) = if link_parsed.scheme() == "clash" || link_parsed.scheme() == "clash-verge" {
-        let name_owned: Option<String> = link_parsed
-            .query_pairs()
-            .find(|(key, _)| key == "name")
-            .map(|(_, value)| value.into_owned().into());
-
-        let url_param = if let Some(query) = link_parsed.query() {
-            let prefix = "url=";
-            if let Some(pos) = query.find(prefix) {
-                let raw_url = &query[pos + prefix.len()..];
-                Some(percent_decode_str(raw_url).decode_utf8_lossy().to_string())
-            } else {
-                None
-            }
-        } else {
-            None
-        };
-        (url_param, name_owned)
-    } else {
-        (None, None)
-    };
+    match link.host_str().unwrap_or_default() {
+        "install-config" => {
+            let Some(raw_url) = install_config_url(&link) else {
+                bail!("missing url parameter in deep link");
+            };
+            let url = validate_subscription_url(&raw_url)?;
+            let name = link
+                .query_pairs()
+                .find(|(key, _)| key == "name")
+                .map(|(_, value)| sanitize_text(&value, MAX_NAME_LENGTH))
+                .filter(|name| !name.is_empty());
+            Ok(DeepLinkAction::InstallConfig { url, name })
+        }
+        "select-node" => Ok(DeepLinkAction::SelectNode {
+            group: required_param(&link, "group")?,
+            node: required_param(&link, "node")?,
+        }),
+        other => bail!("unsupported deep link action: {other}"),
+    }
+}
 
-    let url = if let Some(ref url) = url_param {
-        url
-    } else {
-        logging!(error, Type::Config, "missing url parameter in deep link: {}", param_str);
-        return Ok(());
+/// 弹窗请求用户确认，关闭窗口视为取消
+async fn confirm(action: &DeepLinkAction) -> bool {
+    i18n::sync_locale().await;
+    let message = match action {
+        DeepLinkAction::InstallConfig { url, name } => rust_i18n::t!("deepLink.installConfig")
+            .replace("{name}", name.as_deref().unwrap_or("-"))
+            .replace("{url}", url),
+        DeepLinkAction::SelectNode { group, node } => rust_i18n::t!("deepLink.selectNode")
+            .replace("{group}", group)
+            .replace("{node}", node),
     };
+    let (tx, rx) = oneshot::channel();
+    handle::Handle::app_handle()
+        .dialog()
+        .message(message)
+        .title(rust_i18n::t!("deepLink.title").to_string())
+        .kind(MessageDialogKind::Warning)
+        .buttons(MessageDialogButtons::OkCancel)
+        .show(move |confirmed| {
+            let _ = tx.send(confirmed);
+        });
+    rx.await.unwrap_or(false)
+}
 
-    let mut item = match PrfItem::from_url(url, name.as_ref(), None, None).await {
+async fn install_config(url: &str, name: Option<&String>) -> Result<()> {
+    let mut item = match PrfItem::from_url(url, name, None, None).await {
         Ok(item) => item,
         Err(e) => {
             logging!(error, Type::Config, "failed to parse profile from url: {:?}", e);
@@ -92,3 +186,76 @@ pub(super) async fn resolve_scheme(param: &str) -> Result<()> {
 
     Ok(())
 }
+
+pub(super) async fn resolve_scheme(param: &str) -> Result<()> {
+    logging!(info, Type::Config, "received deep link: {param}");
+
+    let action = parse_deep_link(param)?;
+    crash::record_action(match action {
+        DeepLinkAction::InstallConfig { .. } => "deep link install-config",
+        DeepLinkAction::SelectNode { .. } => "deep link select-node",
+    });
+    if !confirm(&action).await {
+        logging!(info, Type::Config, "deep link cancelled by user: {:?}", action);
+        return Ok(());
+    }
+
+    match action {
+        DeepLinkAction::InstallConfig { url, name } => install_config(&url, name.as_ref()).await,
+        DeepLinkAction::SelectNode { group, node } => {
+            let proxies = handle::Handle::mihomo().await.get_proxies().await?;
+            let contains = proxies
+                .proxies
+                .get(group.as_str())
+                .and_then(|g| g.all.as_ref())
+                .is_some_and(|all| all.iter().any(|n| n == node.as_str()));
+            if !contains {
+                bail!("{group} does not contain {node}");
+            }
+            feat::switch_proxy_node(&group, &node).await;
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_install_config() {
+        assert_eq!(
+            parse_deep_link("clash://install-config?name=My%20Sub&url=https://example.com/sub?a=1&b=2").ok(),
+            Some(DeepLinkAction::InstallConfig {
+                url: "https://example.com/sub?a=1&b=2".into(),
+                name: Some("My Sub".into()),
+            })
+        );
+        assert!(parse_deep_link("clash://install-config?url=file:///etc/passwd").is_err());
+        assert!(parse_deep_link("clash://install-config?url=javascript:alert(1)").is_err());
+        assert_eq!(
+            parse_deep_link("clash://install-config?xurl=https://evil.example&url=https%3A%2F%2Fexample.com%2Fsub")
+                .ok(),
+            Some(DeepLinkAction::InstallConfig {
+                url: "https://example.com/sub".into(),
+                name: None,
+            })
+        );
+        assert!(parse_deep_link("clash://install-config?curl=https://example.com").is_err());
+        assert!(parse_deep_link("clash://install-config").is_err());
+        assert!(parse_deep_link("https://install-config?url=https://example.com").is_err());
+    }
+
+    #[test]
+    fn test_parse_select_node() {
+        assert_eq!(
+            parse_deep_link("clash-verge://select-node?group=Proxy&node=HK%2001%0A").ok(),
+            Some(DeepLinkAction::SelectNode {
+                group: "Proxy".into(),
+                node: "HK 01".into(),
+            })
+        );
+        assert!(parse_deep_link("clash-verge://select-node?group=Proxy").is_err());
+        assert!(parse_deep_link("clash-verge://unknown?group=Proxy").is_err());
+    }
+}
@@ -0,0 +1,293 @@
+//! 启动编排
+//!
+//! 按依赖顺序执行：加载配置 → 启动内核 → 等待内核 API 就绪 → 应用系统代理 → Discord RPC；
+//! 托盘不依赖其他阶段，与内核并行创建，内核启动缓慢或失败时托盘仍然可用。
+//! 每个阶段有独立的超时与重试次数，依赖的阶段未成功时直接跳过，不再依靠固定的等待时间。
+//! 超时的尝试会被丢弃，但它可能已经启动了进程或创建了托盘，只有可重复执行的阶段才会在超时后重试。
+//! 执行结果通过 [`report`] 查询，用于排查启动失败的原因。
+
+use anyhow::Result;
+use clash_verge_logging::{Type, logging};
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use serde::Serialize;
+use smartstring::alias::String;
+use std::{
+    future::Future,
+    time::{Duration, Instant},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum StageStatus {
+    Running,
+    Ok,
+    Failed,
+    Skipped,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StageReport {
+    pub id: &'static str,
+    pub status: StageStatus,
+    pub attempts: u32,
+    pub elapsed_ms: u64,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StartupReport {
+    pub started_at: Option<String>,
+    pub finished: bool,
+    pub total_ms: u64,
+    pub stages: Vec<StageReport>,
+}
+
+pub struct Stage {
+    pub id: &'static str,
+    /// 单次尝试的超时
+    pub timeout: Duration,
+    pub attempts: u32,
+    pub retry_delay: Duration,
+    /// 超时后是否重试，超时的尝试可能已产生副作用，非幂等的阶段应为 `false`
+    pub retry_on_timeout: bool,
+    /// 这些阶段全部成功后才会执行
+    pub depends_on: &'static [&'static str],
+}
+
+pub const CONFIG: Stage = Stage {
+    id: "config",
+    timeout: Duration::from_secs(30),
+    attempts: 1,
+    retry_delay: Duration::ZERO,
+    retry_on_timeout: false,
+    depends_on: &[],
+};
+
+pub const CORE: Stage = Stage {
+    id: "core",
+    timeout: Duration::from_secs(60),
+    attempts: 2,
+    retry_delay: Duration::from_secs(2),
+    retry_on_timeout: false,
+    depends_on: &["config"],
+};
+
+pub const API_READY: Stage = Stage {
+    id: "api-ready",
+    timeout: Duration::from_secs(3),
+    attempts: 10,
+    retry_delay: Duration::from_millis(500),
+    retry_on_timeout: true,
+    depends_on: &["core"],
+};
+
+pub const SYSTEM_PROXY: Stage = Stage {
+    id: "system-proxy",
+    timeout: Duration::from_secs(10),
+    attempts: 2,
+    retry_delay: Duration::from_secs(1),
+    retry_on_timeout: true,
+    depends_on: &["api-ready"],
+};
+
+pub const DISCORD_RPC: Stage = Stage {
+    id: "discord-rpc",
+    timeout: Duration::from_secs(10),
+    attempts: 1,
+    retry_delay: Duration::ZERO,
+    retry_on_timeout: false,
+    depends_on: &["api-ready"],
+};
+
+pub const TRAY: Stage = Stage {
+    id: "tray",
+    timeout: Duration::from_secs(15),
+    attempts: 2,
+    retry_delay: Duration::from_secs(1),
+    retry_on_timeout: false,
+    depends_on: &[],
+};
+
+static REPORT: Lazy<RwLock<StartupReport>> = Lazy::new(|| RwLock::new(StartupReport::default()));
+static STARTED: Lazy<RwLock<Option<Instant>>> = Lazy::new(|| RwLock::new(None));
+
+fn elapsed_ms(start: Instant) -> u64 {
+    u64::try_from(start.elapsed().as_millis()).unwrap_or(u64::MAX)
+}
+
+fn upsert(stage: StageReport) {
+    let mut report = REPORT.write();
+    match report.stages.iter_mut().find(|s| s.id == stage.id) {
+        Some(existing) => *existing = stage,
+        None => report.stages.push(stage),
+    }
+}
+
+fn failed_dependency(stage: &Stage) -> Option<&'static str> {
+    let report = REPORT.read();
+    stage.depends_on.iter().copied().find(|dep| {
+        !report
+            .stages
+            .iter()
+            .any(|s| s.id == *dep && s.status == StageStatus::Ok)
+    })
+}
+
+pub fn begin() {
+    *STARTED.write() = Some(Instant::now());
+    *REPORT.write() = StartupReport {
+        started_at: Some(chrono::Local::now().to_rfc3339().into()),
+        ..StartupReport::default()
+    };
+}
+
+pub fn finish() {
+    let total_ms = STARTED.read().map(elapsed_ms).unwrap_or_default();
+    let failed: Vec<&str> = {
+        let mut report = REPORT.write();
+        report.finished = true;
+        report.total_ms = total_ms;
+        report
+            .stages
+            .iter()
+            .filter(|s| s.status != StageStatus::Ok)
+            .map(|s| s.id)
+            .collect()
+    };
+    if failed.is_empty() {
+        logging!(info, Type::Setup, "Startup finished in {total_ms} ms");
+    } else {
+        logging!(
+            warn,
+            Type::Setup,
+            "Startup finished in {total_ms} ms, incomplete stages: {failed:?}"
+        );
+    }
+}
+
+/// 执行一个阶段，返回是否成功
+pub async fn run_stage<F, Fut>(stage: &Stage, mut run: F) -> bool
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<()>>,
+{
+    let start = Instant::now();
+    if let Some(dep) = failed_dependency(stage) {
+        logging!(
+            warn,
+            Type::Setup,
+            "Skipping startup stage {}: {dep} did not succeed",
+            stage.id
+        );
+        upsert(StageReport {
+            id: stage.id,
+            status: StageStatus::Skipped,
+            attempts: 0,
+            elapsed_ms: 0,
+            error: Some(format!("dependency {dep} did not succeed").into()),
+        });
+        return false;
+    }
+
+    let mut error = None;
+    for attempt in 1..=stage.attempts.max(1) {
+        upsert(StageReport {
+            id: stage.id,
+            status: StageStatus::Running,
+            attempts: attempt,
+            elapsed_ms: elapsed_ms(start),
+            error: error.clone(),
+        });
+        let (result, timed_out) = match tokio::time::timeout(stage.timeout, run()).await {
+            Ok(result) => (result, false),
+            Err(_) => (
+                Err(anyhow::anyhow!("timed out after {} ms", stage.timeout.as_millis())),
+                true,
+            ),
+        };
+        match result {
+            Ok(()) => {
+                logging!(
+                    info,
+                    Type::Setup,
+                    "Startup stage {} finished in {} ms",
+                    stage.id,
+                    elapsed_ms(start)
+                );
+                upsert(StageReport {
+                    id: stage.id,
+                    status: StageStatus::Ok,
+                    attempts: attempt,
+                    elapsed_ms: elapsed_ms(start),
+                    error: None,
+                });
+                return true;
+            }
+            Err(err) => {
+                logging!(
+                    warn,
+                    Type::Setup,
+                    "Startup stage {} attempt {attempt} failed: {err}",
+                    stage.id
+                );
+                error = Some(String::from(err.to_string()));
+                if timed_out && !stage.retry_on_timeout {
+                    upsert(StageReport {
+                        id: stage.id,
+                        status: StageStatus::Failed,
+                        attempts: attempt,
+                        elapsed_ms: elapsed_ms(start),
+                        error,
+                    });
+                    return false;
+                }
+                if attempt < stage.attempts {
+                    tokio::time::sleep(stage.retry_delay).await;
+                }
+            }
+        }
+    }
+    upsert(StageReport {
+        id: stage.id,
+        status: StageStatus::Failed,
+        attempts: stage.attempts.max(1),
+        elapsed_ms: elapsed_ms(start),
+        error,
+    });
+    false
+}
+
+pub fn report() -> StartupReport {
+    REPORT.read().clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stage(id: &'static str, status: StageStatus) -> StageReport {
+        StageReport {
+            id,
+            status,
+            attempts: 1,
+            elapsed_ms: 0,
+            error: None,
+        }
+    }
+
+    #[test]
+    fn test_dependencies() {
+        begin();
+        upsert(stage("core", StageStatus::Running));
+        assert_eq!(failed_dependency(&API_READY), Some("core"));
+        upsert(stage("core", StageStatus::Ok));
+        assert_eq!(failed_dependency(&API_READY), None);
+        assert_eq!(failed_dependency(&SYSTEM_PROXY), Some("api-ready"));
+        assert_eq!(failed_dependency(&TRAY), None);
+        assert_eq!(failed_dependency(&CORE), Some("config"));
+        upsert(stage("config", StageStatus::Ok));
+        assert_eq!(failed_dependency(&CORE), None);
+        assert_eq!(report().stages.len(), 2);
+    }
+}
@@ -5,7 +5,10 @@ use tauri::{Theme, WebviewWindow};
 use crate::{
     config::Config,
     core::handle,
-    utils::resolve::window_script::{INITIAL_LOADING_OVERLAY, build_window_initial_script},
+    utils::{
+        dirs,
+        resolve::window_script::{INITIAL_LOADING_OVERLAY, build_window_initial_script},
+    },
 };
 use clash_verge_logging::{Type, logging_error};
 
@@ -75,6 +78,10 @@ pub async fn build_new_window() -> Result<WebviewWindow, String> {
 
     builder = builder.background_color(background_color);
 
+    if let Some(dir) = dirs::webview_data_dir() {
+        builder = builder.data_directory(dir);
+    }
+
     match builder.build() {
         Ok(window) => {
             logging_error!(Type::Window, window.set_background_color(Some(background_color)));
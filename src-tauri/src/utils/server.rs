@@ -1,10 +1,5 @@
 use super::resolve;
-use crate::{
-    config::{Config, DEFAULT_PAC, IVerge},
-    module::lightweight,
-    process::AsyncHandler,
-    utils::window_manager::WindowManager,
-};
+use crate::{config::IVerge, feat, module::lightweight, process::AsyncHandler, utils::window_manager::WindowManager};
 use anyhow::{Result, bail};
 use clash_verge_logging::{Type, logging, logging_error};
 use once_cell::sync::OnceCell;
@@ -21,29 +16,61 @@ struct QueryParam {
     param: String,
 }
 
+const MAX_FORWARDED_BYTES: u64 = 64 * 1024;
+
 // 关闭 embedded server 的信号发送端
 static SHUTDOWN_SENDER: OnceCell<Mutex<Option<oneshot::Sender<()>>>> = OnceCell::new();
 
+/// 转发给已运行实例的启动参数
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+struct ForwardedArgs {
+    args: Vec<String>,
+}
+
+const DEEP_LINK_SCHEMES: [&str; 2] = ["clash:", "clash-verge:"];
+
+/// 从启动参数中取出深层链接，忽略 `--instance` 等选项
+fn deep_links(args: &[String]) -> Vec<String> {
+    args.iter()
+        .filter(|arg| DEEP_LINK_SCHEMES.iter().any(|scheme| arg.starts_with(scheme)))
+        .cloned()
+        .collect()
+}
+
+/// 恢复窗口并依次处理转发过来的深层链接
+async fn handle_forwarded_args(args: Vec<String>) {
+    logging!(info, Type::Window, "收到其他实例转发的启动参数: {:?}", args);
+    if !lightweight::exit_lightweight_mode().await {
+        WindowManager::show_main_window().await;
+    } else {
+        logging!(error, Type::Window, "轻量模式退出失败，无法恢复应用窗口");
+    }
+    for link in deep_links(&args) {
+        logging_error!(Type::Setup, resolve::resolve_scheme(&link).await);
+    }
+}
+
 /// check whether there is already exists
+///
+/// 已有实例运行时把本次的启动参数（包括深层链接）转发过去，由其恢复窗口并处理，
+/// 当前进程随即退出，不再启动第二个内核
 pub async fn check_singleton() -> Result<()> {
     let port = IVerge::get_singleton_port();
     if !local_port_available(port) {
-        let client = ClientBuilder::new().timeout(Duration::from_millis(500)).build()?;
-        // 需要确保 Send
-        #[allow(clippy::needless_collect)]
-        let argvs: Vec<std::string::String> = std::env::args().collect();
-        if argvs.len() > 1 {
-            #[cfg(not(target_os = "macos"))]
-            {
-                let param = argvs[1].as_str();
-                if param.starts_with("clash:") {
-                    client
-                        .get(format!("http://127.0.0.1:{port}/commands/scheme?param={param}"))
-                        .send()
-                        .await?;
-                }
-            }
-        } else {
+        let client = ClientBuilder::new()
+            .timeout(Duration::from_millis(500))
+            .no_proxy()
+            .build()?;
+        let forwarded = ForwardedArgs {
+            args: std::env::args().skip(1).map(Into::into).collect(),
+        };
+        let response = client
+            .post(format!("http://127.0.0.1:{port}/commands/args"))
+            .json(&forwarded)
+            .send()
+            .await;
+        // 兼容只提供 visible 接口的旧版本
+        if !response.is_ok_and(|r| r.status().is_success()) {
             client
                 .get(format!("http://127.0.0.1:{port}/commands/visible"))
                 .send()
@@ -79,24 +106,11 @@ pub fn embed_server() {
     });
 
     let pac = warp::path!("commands" / "pac").and_then(|| async move {
-        let verge_config = Config::verge().await;
-        let clash_config = Config::clash().await;
-
-        let pac_content = verge_config
-            .data_arc()
-            .pac_file_content
-            .clone()
-            .unwrap_or_else(|| DEFAULT_PAC.into());
-
-        let pac_port = verge_config
-            .data_arc()
-            .verge_mixed_port
-            .unwrap_or_else(|| clash_config.data_arc().get_mixed_port());
-        let processed_content = pac_content.replace("%mixed-port%", &format!("{pac_port}"));
+        let processed_content = feat::current_pac().await;
         Ok::<_, warp::Rejection>(
             warp::http::Response::builder()
                 .header("Content-Type", "application/x-ns-proxy-autoconfig")
-                .body(processed_content)
+                .body(std::string::String::from(processed_content))
                 .unwrap_or_default(),
         )
     });
@@ -114,7 +128,19 @@ pub fn embed_server() {
             ))
         });
 
-    let commands = visible.or(scheme).or(pac);
+    let args = warp::path!("commands" / "args")
+        .and(warp::post())
+        .and(warp::body::content_length_limit(MAX_FORWARDED_BYTES))
+        .and(warp::body::json())
+        .and_then(|forwarded: ForwardedArgs| async move {
+            AsyncHandler::spawn(|| handle_forwarded_args(forwarded.args));
+            Ok::<_, warp::Rejection>(warp::reply::with_status::<std::string::String>(
+                "ok".to_string(),
+                warp::http::StatusCode::OK,
+            ))
+        });
+
+    let commands = visible.or(scheme).or(args).or(pac);
 
     AsyncHandler::spawn(move || async move {
         warp::serve(commands)
@@ -136,3 +162,27 @@ pub fn shutdown_embedded_server() {
         sender.send(()).ok();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deep_links() {
+        let args: Vec<String> = vec![
+            "--instance".into(),
+            "work".into(),
+            "clash://install-config?url=https://example.com/sub".into(),
+            "clash-verge://select-node?group=Proxy&node=HK".into(),
+            "https://example.com".into(),
+        ];
+        assert_eq!(
+            deep_links(&args),
+            vec![
+                String::from("clash://install-config?url=https://example.com/sub"),
+                String::from("clash-verge://select-node?group=Proxy&node=HK"),
+            ]
+        );
+        assert!(deep_links(&[]).is_empty());
+    }
+}
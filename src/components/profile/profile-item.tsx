@@ -16,6 +16,7 @@ import {
   Menu,
   CircularProgress,
 } from "@mui/material";
+import { listen } from "@tauri-apps/api/event";
 import { open } from "@tauri-apps/plugin-shell";
 import { useLockFn } from "ahooks";
 import dayjs from "dayjs";
@@ -238,6 +239,28 @@ export const ProfileItem = (props: Props) => {
 
   const loading = loadingCache[itemData.uid] ?? false;
 
+  // 更新订阅时用进度条显示下载进度
+  const [download, setDownload] = useState<IProfileDownloadProgress | null>(
+    null,
+  );
+  useEffect(() => {
+    if (!loading || !itemData.url) return;
+
+    const unlisten = listen<IProfileDownloadProgress>(
+      "profile-download-progress",
+      (event) => {
+        if (event.payload.url === itemData.url) setDownload(event.payload);
+      },
+    );
+    return () => {
+      void unlisten.then((fn) => fn());
+      setDownload(null);
+    };
+  }, [loading, itemData.url]);
+  const downloadPercent = download?.total
+    ? Math.min(Math.round((download.downloaded * 100) / download.total), 100)
+    : null;
+
   // interval update fromNow field
   const [, forceRefresh] = useReducer((value: number) => value + 1, 0);
   useEffect(() => {
@@ -786,8 +809,9 @@ export const ProfileItem = (props: Props) => {
         )}
         <LinearProgress
           variant="determinate"
-          value={progress}
-          style={{ opacity: total > 0 ? 1 : 0 }}
+          value={downloadPercent ?? progress}
+          color={downloadPercent === null ? "primary" : "secondary"}
+          style={{ opacity: downloadPercent !== null || total > 0 ? 1 : 0 }}
         />
       </ProfileBox>
 
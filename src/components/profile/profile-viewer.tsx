@@ -109,6 +109,27 @@ export function ProfileViewer({ onChange, ref }: ProfileViewerProps) {
         if (form.option?.user_agent === "") {
           delete form.option.user_agent;
         }
+        if (form.option?.runtime) {
+          const { mixed_port, ...rest } = form.option.runtime;
+          const runtime: IProfileRuntime = mixed_port
+            ? { ...rest, mixed_port: +mixed_port }
+            : rest;
+          if (Object.keys(runtime).length > 0) {
+            form.option.runtime = runtime;
+          } else {
+            delete form.option.runtime;
+          }
+        }
+        if (form.option?.mirrors) {
+          const mirrors = form.option.mirrors
+            .map((mirror) => mirror.trim())
+            .filter(Boolean);
+          if (mirrors.length > 0) {
+            form.option.mirrors = mirrors;
+          } else {
+            delete form.option.mirrors;
+          }
+        }
 
         const name = form.name || `${form.type} file`;
         const item = { ...form, name };
@@ -286,6 +307,20 @@ export function ProfileViewer({ onChange, ref }: ProfileViewerProps) {
             )}
           />
 
+          <Controller
+            name="option.mirrors"
+            control={control}
+            render={({ field }) => (
+              <TextField
+                {...text}
+                multiline
+                value={field.value?.join("\n") ?? ""}
+                onChange={(e) => field.onChange(e.target.value.split("\n"))}
+                label={t("profiles.modals.profileForm.fields.mirrorUrls")}
+              />
+            )}
+          />
+
           <Controller
             name="option.user_agent"
             control={control}
@@ -348,6 +383,21 @@ export function ProfileViewer({ onChange, ref }: ProfileViewerProps) {
         />
       )}
 
+      {(isRemote || isLocal) && (
+        <Controller
+          name="option.runtime.mixed_port"
+          control={control}
+          render={({ field }) => (
+            <TextField
+              {...text}
+              {...field}
+              type="number"
+              label={t("profiles.modals.profileForm.fields.mixedPort")}
+            />
+          )}
+        />
+      )}
+
       {isLocal && openType === "new" && (
         <FileInput
           onChange={(file, val) => {
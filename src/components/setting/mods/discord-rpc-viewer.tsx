@@ -2,11 +2,16 @@ import { List, ListItem, ListItemText, TextField, Button } from "@mui/material";
 import { useLockFn } from "ahooks";
 import { forwardRef, useImperativeHandle, useState } from "react";
 import { useTranslation } from "react-i18next";
+import useSWR from "swr";
 
 import { BaseDialog, DialogRef } from "@/components/base";
 import { useVerge } from "@/hooks/use-verge";
 import { showNotice } from "@/services/notice-service";
-import { triggerDiscordRpcReload, unloadDiscordRpc } from "@/services/cmds";
+import {
+    isTrafficStreamHealthy,
+    triggerDiscordRpcReload,
+    unloadDiscordRpc,
+} from "@/services/cmds";
 
 export const DiscordRpcViewer = forwardRef<DialogRef>((props, ref) => {
     const { t } = useTranslation();
@@ -14,6 +19,11 @@ export const DiscordRpcViewer = forwardRef<DialogRef>((props, ref) => {
 
     const [open, setOpen] = useState(false);
     const [appId, setAppId] = useState("");
+    const { data: trafficHealthy } = useSWR(
+        open && verge?.enable_discord_rpc ? "isTrafficStreamHealthy" : null,
+        isTrafficStreamHealthy,
+        { refreshInterval: 2000 },
+    );
 
     useImperativeHandle(ref, () => ({
         open: () => {
@@ -64,6 +74,25 @@ export const DiscordRpcViewer = forwardRef<DialogRef>((props, ref) => {
                     />
                 </ListItem>
 
+                {verge?.enable_discord_rpc && (
+                    <ListItem sx={{ padding: "5px 2px" }}>
+                        <ListItemText
+                            primary={t(
+                                "settings.modals.discordRpc.fields.trafficStream",
+                            )}
+                            secondary={
+                                trafficHealthy
+                                    ? t(
+                                          "settings.modals.discordRpc.trafficStatus.live",
+                                      )
+                                    : t(
+                                          "settings.modals.discordRpc.trafficStatus.reconnecting",
+                                      )
+                            }
+                        />
+                    </ListItem>
+                )}
+
                 <ListItem sx={{ padding: "5px 2px", justifyContent: "flex-end", gap: 1 }}>
                     <Button
                         variant="outlined"
@@ -5,6 +5,7 @@ import { useTranslation } from "react-i18next";
 
 import { BaseDialog, DialogRef, Switch } from "@/components/base";
 import { useVerge } from "@/hooks/use-verge";
+import { setHotkeys } from "@/services/cmds";
 import { showNotice } from "@/services/notice-service";
 
 import { HotkeyInput } from "./hotkey-input";
@@ -25,6 +26,7 @@ const HOTKEY_FUNC = [
   "toggle_tun_mode",
   "entry_lightweight_mode",
   "reactivate_profiles",
+  "copy_proxy_env",
 ] as const;
 
 const HOTKEY_FUNC_LABELS: Record<(typeof HOTKEY_FUNC)[number], string> = {
@@ -38,6 +40,7 @@ const HOTKEY_FUNC_LABELS: Record<(typeof HOTKEY_FUNC)[number], string> = {
   entry_lightweight_mode:
     "settings.modals.hotkey.functions.entryLightweightMode",
   reactivate_profiles: "settings.modals.hotkey.functions.reactivateProfiles",
+  copy_proxy_env: "settings.modals.hotkey.functions.copyProxyEnv",
 };
 
 export const HotkeyViewer = forwardRef<DialogRef>((props, ref) => {
@@ -90,10 +93,8 @@ export const HotkeyViewer = forwardRef<DialogRef>((props, ref) => {
       .filter(Boolean);
 
     try {
-      await patchVerge({
-        hotkeys,
-        enable_global_hotkey: enableGlobalHotkey,
-      });
+      await setHotkeys(hotkeys);
+      await patchVerge({ enable_global_hotkey: enableGlobalHotkey });
       setOpen(false);
     } catch (err) {
       showNotice.error(err);
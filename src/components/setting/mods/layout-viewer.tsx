@@ -330,21 +330,19 @@ export const LayoutViewer = forwardRef<DialogRef>((_, ref) => {
             </GuardState>
           </Item>
         )}
-        {/* {OS === "macos" && (
-          <Item>
-            <ListItemText primary={t("settings.components.verge.layout.fields.enableTraySpeed")} />
-            <GuardState
-              value={verge?.enable_tray_speed ?? false}
-              valueProps="checked"
-              onCatch={onError}
-              onFormat={onSwitchFormat}
-              onChange={(e) => onChangeData({ enable_tray_speed: e })}
-              onGuard={(e) => patchVerge({ enable_tray_speed: e })}
-            >
-              <Switch edge="end" />
-            </GuardState>
-          </Item>
-        )} */}
+        <Item>
+          <ListItemText primary={t("settings.components.verge.layout.fields.enableTraySpeed")} />
+          <GuardState
+            value={verge?.enable_tray_speed ?? false}
+            valueProps="checked"
+            onCatch={onError}
+            onFormat={onSwitchFormat}
+            onChange={(e) => onChangeData({ enable_tray_speed: e })}
+            onGuard={(e) => patchVerge({ enable_tray_speed: e })}
+          >
+            <Switch edge="end" />
+          </GuardState>
+        </Item>
         {/* {OS === "macos" && (
           <Item>
             <ListItemText primary={t("settings.components.verge.layout.fields.enableTrayIcon")} />
@@ -18,6 +18,7 @@ import {
 } from "@/services/cmds";
 import { showNotice } from "@/services/notice-service";
 import { checkUpdateSafe as checkUpdate } from "@/services/update";
+import { getErrorMessage, isRecoverableError } from "@/utils/cmd-error";
 import { version } from "@root/package.json";
 import { Settings } from "@mui/icons-material";
 
@@ -87,7 +88,12 @@ const SettingVergeAdvanced = ({ onError: _ }: Props) => {
       await patchVerge({ enable_discord_rpc: enabled });
       await toggleDiscordRpc(enabled);
     } catch (err: any) {
-      showNotice.error(err);
+      // Discord not running yet: the setting is saved, toggling again connects
+      if (isRecoverableError(err)) {
+        showNotice.info(getErrorMessage(err));
+      } else {
+        showNotice.error(err);
+      }
     }
   });
 
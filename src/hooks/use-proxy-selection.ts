@@ -77,7 +77,7 @@ export const useProxySelection = (options: ProxySelectionOptions = {}) => {
         }
 
         await selectNodeForGroup(groupName, proxyName);
-        await syncTrayProxySelection();
+        await syncTrayProxySelection(groupName, proxyName);
         debugLog(
           `[ProxySelection] 代理和状态同步完成: ${groupName} -> ${proxyName}`,
         );
@@ -99,7 +99,7 @@ export const useProxySelection = (options: ProxySelectionOptions = {}) => {
 
         try {
           await selectNodeForGroup(groupName, proxyName);
-          await syncTrayProxySelection();
+          await syncTrayProxySelection(groupName, proxyName);
           onSuccess?.();
           debugLog(
             `[ProxySelection] 代理切换回退成功: ${groupName} -> ${proxyName}`,
@@ -24,6 +24,7 @@ export const handleNoticeMessage = (
       showNotice.error(msg);
     },
     "set_config::error": () => showNotice.error(msg),
+    "settings_lock::locked": () => showNotice.error(msg),
     update_with_clash_proxy: () =>
       showNotice.success(
         "settings.feedback.notifications.updater.withClashProxySuccess",
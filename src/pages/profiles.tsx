@@ -60,6 +60,7 @@ import {
 } from "@/services/cmds";
 import { showNotice } from "@/services/notice-service";
 import { useSetLoadingCache, useThemeMode } from "@/services/states";
+import { getErrorMessage } from "@/utils/cmd-error";
 import { debugLog } from "@/utils/debug";
 
 // 记录profile切换状态
@@ -245,7 +246,7 @@ const ProfilePage = () => {
       console.error("[紧急刷新] 失败:", error);
       showNotice.error(
         "profiles.page.feedback.notices.emergencyRefreshFailed",
-        { message: String(error) },
+        { message: getErrorMessage(error) },
         4000,
       );
     }
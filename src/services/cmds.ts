@@ -9,6 +9,36 @@ export async function copyClashEnv() {
   return invoke<void>("copy_clash_env");
 }
 
+export async function copyProxyEnv(
+  shell?: "bash" | "fish" | "powershell" | "cmd" | "nushell" | "dotenv",
+) {
+  return invoke<string>("copy_proxy_env", { shell });
+}
+
+export async function listWebhooks() {
+  return invoke<IWebhook[]>("list_webhooks");
+}
+
+export async function addWebhook(webhook: IWebhook) {
+  return invoke<IWebhook[]>("add_webhook", { webhook });
+}
+
+export async function deleteWebhook(id: string) {
+  return invoke<IWebhook[]>("delete_webhook", { id });
+}
+
+export async function sendTestNotification() {
+  return invoke<void>("send_test_notification");
+}
+
+export async function getHotkeys() {
+  return invoke<IHotkeyBinding[]>("get_hotkeys");
+}
+
+export async function setHotkeys(hotkeys: string[]) {
+  return invoke<IHotkeyBinding[]>("set_hotkeys", { hotkeys });
+}
+
 export async function getProfiles() {
   return invoke<IProfilesConfig>("get_profiles");
 }
@@ -73,6 +103,10 @@ export async function getClashInfo() {
   return invoke<IClashInfo | null>("get_clash_info");
 }
 
+export async function rotateCoreSecret() {
+  return invoke<string>("rotate_core_secret");
+}
+
 // Get runtime config which controlled by verge
 export async function getRuntimeConfig() {
   return invoke<IConfigData | null>("get_runtime_config");
@@ -110,8 +144,11 @@ export async function patchClashMode(payload: string) {
   return invoke<void>("patch_clash_mode", { payload });
 }
 
-export async function syncTrayProxySelection() {
-  return invoke<void>("sync_tray_proxy_selection");
+export async function syncTrayProxySelection(
+  group?: string,
+  node?: string,
+) {
+  return invoke<void>("sync_tray_proxy_selection", { group, node });
 }
 
 export async function calcuProxies(): Promise<{
@@ -255,6 +292,10 @@ export async function patchVergeConfig(payload: IVergeConfig) {
   return invoke<void>("patch_verge_config", { payload });
 }
 
+export async function flushConfig() {
+  return invoke<boolean>("flush_config");
+}
+
 export async function getSystemProxy() {
   return invoke<{
     enable: boolean;
@@ -326,6 +367,154 @@ export async function openLogsDir() {
   return invoke<void>("open_logs_dir").catch((err) => showNotice.error(err));
 }
 
+export async function setLogLevel(level: string) {
+  return invoke<void>("set_log_level", { level });
+}
+
+export async function collectLogs(rangeHours: number) {
+  return invoke<string>("collect_logs", { rangeHours });
+}
+
+export async function migrateAppHome(newPath: string) {
+  return invoke<void>("migrate_app_home", { newPath });
+}
+
+export async function checkAppUpdate(channel?: IVergeConfig["update_channel"]) {
+  return invoke<IAppUpdateInfo | null>("check_app_update", { channel });
+}
+
+export async function installAppUpdate() {
+  return invoke<void>("install_app_update");
+}
+
+export async function deferAppUpdate(hours: number) {
+  return invoke<void>("defer_app_update", { hours });
+}
+
+export async function listCrashReports() {
+  return invoke<ICrashReportSummary[]>("list_crash_reports");
+}
+
+export async function getStartupReport() {
+  return invoke<IStartupReport>("get_startup_report");
+}
+
+export async function getFavorites() {
+  return invoke<IFavorite[]>("get_favorites");
+}
+
+export async function toggleFavorite(name: string) {
+  return invoke<boolean>("toggle_favorite", { name });
+}
+
+export async function getProvidersStatus() {
+  return invoke<IProviderStatus[]>("get_providers_status");
+}
+
+export async function updateAllProviders() {
+  return invoke<IProviderStatus[]>("update_all_providers");
+}
+
+export async function runSpeedtest(node?: string, server?: string) {
+  return invoke<ISpeedtestRecord>("run_speedtest", { node, server });
+}
+
+export async function getSpeedtestHistory(node?: string, limit?: number) {
+  return invoke<ISpeedtestRecord[]>("get_speedtest_history", { node, limit });
+}
+
+export async function tcpPing(host: string, port?: number, count?: number) {
+  return invoke<IPingReport>("tcp_ping", { host, port, count });
+}
+
+export async function traceRoute(host: string, viaProxy?: boolean) {
+  return invoke<ITraceHop[]>("trace_route", { host, viaProxy });
+}
+
+export async function getLatencyHeatmap(
+  node?: string,
+  range?: "1h" | "24h" | "7d",
+) {
+  return invoke<ILatencyBucket[]>("get_latency_heatmap", { node, range });
+}
+
+export async function getSettingsLockStatus() {
+  return invoke<ISettingsLockStatus>("get_settings_lock_status");
+}
+
+export async function unlockSettings(pin: string) {
+  return invoke<boolean>("unlock_settings", { pin });
+}
+
+export async function lockSettings() {
+  return invoke<void>("lock_settings");
+}
+
+export async function setSettingsPin(current?: string, pin?: string) {
+  return invoke<void>("set_settings_pin", { current, pin });
+}
+
+export async function queryAuditLog(filter?: IAuditLogFilter, limit?: number) {
+  return invoke<IAuditEntry[]>("query_audit_log", { filter, limit });
+}
+
+export async function getSetupStatus() {
+  return invoke<ISetupStatus>("get_setup_status");
+}
+
+export async function completeSetupStep(step: ISetupStep) {
+  return invoke<ISetupStatus>("complete_setup_step", { step });
+}
+
+export async function getAutostartConfig() {
+  return invoke<IAutostartConfig>("get_autostart_config");
+}
+
+export async function setAutostartConfig(config: IAutostartConfig) {
+  return invoke<void>("set_autostart_config", { config });
+}
+
+export async function testProfile(uid: string) {
+  return invoke<IProfileTestReport>("test_profile", { uid });
+}
+
+export async function getEffectiveOutbounds(group: string) {
+  return invoke<IEffectiveOutbound[]>("get_effective_outbounds", { group });
+}
+
+export async function getCoreMemory() {
+  return invoke<ICoreMemory>("get_core_memory");
+}
+
+export async function selectNodeByRegion(group: string, countryCode: string) {
+  return invoke<IRegionSelection>("select_node_by_region", {
+    group,
+    countryCode,
+  });
+}
+
+export async function openCrashReportsDir() {
+  return invoke<void>("open_crash_reports_dir").catch((err) =>
+    showNotice.error(err),
+  );
+}
+
+export async function uploadCrashReport(id: string) {
+  return invoke<void>("upload_crash_report", { id });
+}
+
+export async function generateDiagnosticsBundle() {
+  return invoke<string>("generate_diagnostics_bundle");
+}
+
+export async function subscribeCoreLogs(filter: ICoreLogFilter) {
+  return invoke<ICoreLogLine[]>("subscribe_core_logs", { filter });
+}
+
+export async function unsubscribeCoreLogs() {
+  return invoke<void>("unsubscribe_core_logs");
+}
+
 export const openWebUrl = async (url: string) => {
   try {
     await invoke("open_web_url", { url });
@@ -483,6 +672,54 @@ export async function listLocalBackup() {
   return invoke<ILocalBackupFile[]>("list_local_backup");
 }
 
+export async function backupNow() {
+  return invoke<string>("backup_now");
+}
+
+export async function listBackups() {
+  return invoke<IRemoteBackupFile[]>("list_backups");
+}
+
+export async function restoreBackup(id: string) {
+  return invoke<void>("restore_backup", { id });
+}
+
+export async function exportSettings(
+  path: string,
+  password: string,
+  includeSubscriptions: boolean,
+) {
+  return invoke<void>("export_settings", {
+    path,
+    password,
+    includeSubscriptions,
+  });
+}
+
+export async function importSettings(path: string, password: string) {
+  return invoke<void>("import_settings", { path, password });
+}
+
+export async function discoverPeers() {
+  return invoke<ILanPeer[]>("discover_peers");
+}
+
+export async function startLanPairing() {
+  return invoke<string>("start_lan_pairing");
+}
+
+export async function pairWithPeer(id: string, pin: string) {
+  return invoke<ILanPeer>("pair_with_peer", { id, pin });
+}
+
+export async function syncWithPeer(id: string, direction: "push" | "pull") {
+  return invoke<void>("sync_with_peer", { id, direction });
+}
+
+export async function regenerateControlApiToken() {
+  return invoke<string>("regenerate_control_api_token");
+}
+
 export async function scriptValidateNotice(status: string, msg: string) {
   return invoke<void>("script_validate_notice", { status, msg });
 }
@@ -521,6 +758,11 @@ export const repairService = async () => {
   return invoke<void>("repair_service");
 };
 
+// 系统服务的安装、版本与运行状态
+export const getServiceStatus = async () => {
+  return invoke<IServiceInfo>("service_status");
+};
+
 // 系统服务是否可用
 export const isServiceAvailable = async () => {
   try {
@@ -560,6 +802,10 @@ export async function refreshDiscordActivity() {
   return invoke<void>("refresh_discord_activity");
 }
 
+export async function isTrafficStreamHealthy() {
+  return invoke<boolean>("is_traffic_stream_healthy");
+}
+
 export async function unloadDiscordRpc() {
   return invoke<void>("unload_discord_rpc");
 }
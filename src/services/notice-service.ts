@@ -1,6 +1,8 @@
 import i18n from "i18next";
 import { ReactNode, isValidElement } from "react";
 
+import { isCmdError } from "@/utils/cmd-error";
+
 type NoticeType = "success" | "error" | "info";
 
 export interface NoticeTranslationDescriptor {
@@ -143,6 +145,7 @@ function isPlainRecord(value: unknown): value is Record<string, unknown> {
     value === null ||
     Array.isArray(value) ||
     value instanceof Error ||
+    isCmdError(value) ||
     isValidElement(value)
   ) {
     return false;
@@ -167,6 +167,8 @@ export const translationKeys = [
   "profiles.modals.profileForm.fields.type",
   "profiles.modals.profileForm.fields.description",
   "profiles.modals.profileForm.fields.subscriptionUrl",
+  "profiles.modals.profileForm.fields.mirrorUrls",
+  "profiles.modals.profileForm.fields.mixedPort",
   "profiles.modals.profileForm.fields.httpTimeout",
   "profiles.modals.profileForm.fields.updateInterval",
   "profiles.modals.profileForm.fields.useSystemProxy",
@@ -455,6 +457,9 @@ export const translationKeys = [
   "settings.modals.clashCore.variants.alpha",
   "settings.modals.discordRpc.title",
   "settings.modals.discordRpc.fields.appId",
+  "settings.modals.discordRpc.fields.trafficStream",
+  "settings.modals.discordRpc.trafficStatus.live",
+  "settings.modals.discordRpc.trafficStatus.reconnecting",
   "settings.modals.liteMode.title",
   "settings.modals.liteMode.actions.enterNow",
   "settings.modals.liteMode.toggles.autoEnter",
@@ -630,6 +635,7 @@ export const translationKeys = [
   "settings.modals.hotkey.functions.entryLightweightMode",
   "settings.modals.hotkey.functions.direct",
   "settings.modals.hotkey.functions.reactivateProfiles",
+  "settings.modals.hotkey.functions.copyProxyEnv",
   "settings.modals.password.prompts.enterRoot",
   "settings.modals.networkInterface.title",
   "settings.modals.networkInterface.fields.ipAddress",
@@ -318,6 +318,8 @@ export interface TranslationResources {
             description: string;
             httpTimeout: string;
             subscriptionUrl: string;
+            mirrorUrls: string;
+            mixedPort: string;
             type: string;
             updateInterval: string;
             useClashProxy: string;
@@ -771,8 +773,13 @@ export interface TranslationResources {
         discordRpc: {
           fields: {
             appId: string;
+            trafficStream: string;
           };
           title: string;
+          trafficStatus: {
+            live: string;
+            reconnecting: string;
+          };
         };
         clashPort: {
           actions: {
@@ -888,6 +895,7 @@ export interface TranslationResources {
         };
         hotkey: {
           functions: {
+            copyProxyEnv: string;
             direct: string;
             entryLightweightMode: string;
             global: string;
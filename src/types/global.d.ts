@@ -292,6 +292,44 @@ interface IProfileOption {
   rules?: string;
   proxies?: string;
   groups?: string;
+  chain?: IProfileLayer[];
+  headers?: Record<string, string>;
+  mirrors?: string[];
+  runtime?: IProfileRuntime;
+  nodes?: IProfileNodeFilter;
+  subscription_format?:
+    | "auto"
+    | "clash"
+    | "v2ray"
+    | "sing-box"
+    | "surge"
+    | "quantumult-x";
+}
+
+interface IProfileNodeFilter {
+  dedupe?: boolean;
+  include?: string;
+  exclude?: string;
+  regions?: string[];
+  rename?: string;
+}
+
+interface IProfileRuntime {
+  mixed_port?: number;
+  log_level?: "debug" | "info" | "warning" | "error" | "silent";
+  ipv6?: boolean;
+  tun_stack?: "system" | "gvisor" | "mixed";
+}
+
+interface IProfileDownloadProgress {
+  url: string;
+  downloaded: number;
+  total?: number | null;
+}
+
+interface IProfileLayer {
+  uid: string;
+  enabled: boolean;
 }
 
 interface IProfilesConfig {
@@ -793,6 +831,191 @@ interface IProxyConfig
     | "vless";
 }
 
+interface IHotkeyBinding {
+  function: string;
+  key: string;
+  registered: boolean;
+}
+
+type WebhookEventKind =
+  | "profile_updated"
+  | "node_switched"
+  | "core_restarted"
+  | "traffic_threshold";
+
+interface IWebhook {
+  id: string;
+  url: string;
+  events?: WebhookEventKind[];
+  secret?: string;
+  traffic_threshold?: number;
+  enabled?: boolean;
+}
+
+interface ISpeedtestServer {
+  name: string;
+  download_url: string;
+  upload_url?: string;
+  latency_url?: string;
+}
+
+interface ISpeedtestRecord {
+  time: number;
+  node: string;
+  server: string;
+  latency_ms?: number | null;
+  download_bps?: number | null;
+  upload_bps?: number | null;
+  error?: string;
+}
+
+interface IPingResult {
+  host: string;
+  port: number;
+  seq: number;
+  rtt_ms?: number | null;
+  error?: string | null;
+}
+
+interface IPingReport {
+  address: string;
+  results: IPingResult[];
+  sent: number;
+  received: number;
+  min_ms?: number | null;
+  avg_ms?: number | null;
+  max_ms?: number | null;
+}
+
+interface ITraceHop {
+  index: number;
+  kind: "dns" | "direct" | "entry" | "proxy";
+  target: string;
+  rtt_ms?: number | null;
+  error?: string | null;
+}
+
+interface ILatencyBucket {
+  node: string;
+  start: number;
+  samples: number;
+  timeouts: number;
+  avg_ms?: number | null;
+  min_ms?: number | null;
+  max_ms?: number | null;
+  p90_ms?: number | null;
+}
+
+interface ISettingsLockStatus {
+  enabled: boolean;
+  locked: boolean;
+  unlocked_until?: number | null;
+  retry_after?: number | null;
+}
+
+type IAuditSource =
+  | "ui"
+  | "tray"
+  | "hotkey"
+  | "scheduler"
+  | "api"
+  | "telegram"
+  | "system";
+
+interface IAuditEntry {
+  time: number;
+  source: IAuditSource;
+  action: string;
+  target?: string;
+  old?: unknown;
+  new?: unknown;
+}
+
+interface IAuditLogFilter {
+  source?: IAuditSource;
+  action?: string;
+  since?: number;
+  until?: number;
+}
+
+type ISetupStep = "core" | "ports" | "profile" | "system-proxy";
+
+interface ISetupStepStatus {
+  step: ISetupStep;
+  status: "pass" | "warn" | "fail" | "skip";
+  detail: string;
+  optional: boolean;
+  completed: boolean;
+}
+
+interface ISetupStatus {
+  finished: boolean;
+  current?: ISetupStep | null;
+  steps: ISetupStepStatus[];
+}
+
+interface IAutostartConfig {
+  enabled: boolean;
+  silent: boolean;
+  delay_secs: number;
+}
+
+interface IProfileTestReport {
+  uid: string;
+  started: boolean;
+  connectivity?: number | null;
+  nodes: number;
+  reachable: number;
+  delays: { name: string; delay?: number | null }[];
+  error?: string | null;
+  duration_ms: number;
+}
+
+interface IEffectiveOutbound {
+  name: string;
+  connections: number;
+  upload: number;
+  download: number;
+}
+
+interface ICoreMemory {
+  inuse: number;
+  oslimit: number;
+  rss?: number;
+  checked_at: number;
+}
+
+interface INotificationPrefs {
+  core_crash?: boolean;
+  profile_update_failed?: boolean;
+  subscription_expiring?: boolean;
+  data_cap?: boolean;
+  exit_ip_change?: boolean;
+}
+
+interface ISchedule {
+  id: string;
+  name?: string;
+  cron: string;
+  action:
+    | { type: "clash_mode"; value: "rule" | "global" | "direct" }
+    | { type: "system_proxy"; value: boolean }
+    | { type: "tun_mode"; value: boolean }
+    | { type: "select_region"; value: { group: string; country: string } };
+  enabled?: boolean;
+}
+
+interface INetworkRule {
+  name?: string;
+  ssid?: string;
+  interface?: string;
+  kind?: "wifi" | "wired" | "offline";
+  vpn?: boolean;
+  captive_portal?: boolean;
+  actions: ISchedule["action"][];
+  enabled?: boolean;
+}
+
 interface IVergeConfig {
   app_log_level?: "trace" | "debug" | "info" | "warn" | "error" | string;
   app_log_max_size?: number; // KB
@@ -818,7 +1041,7 @@ interface IVergeConfig {
   common_tray_icon?: boolean;
   sysproxy_tray_icon?: boolean;
   tun_tray_icon?: boolean;
-  // enable_tray_speed?: boolean;
+  enable_tray_speed?: boolean;
   // enable_tray_icon?: boolean;
   tray_inline_proxy_groups?: boolean;
   enable_tun_mode?: boolean;
@@ -885,6 +1108,54 @@ interface IVergeConfig {
   enable_external_controller?: boolean;
   enable_discord_rpc?: boolean;
   discord_app_id?: string;
+  enable_connection_log?: boolean;
+  enable_kill_switch?: boolean;
+  custom_cores?: {
+    name: string;
+    path: string;
+    backend?: "mihomo" | "sing-box";
+  }[];
+  enable_profile_health_delay_test?: boolean;
+  geo_mirrors?: string[];
+  sysproxy_interfaces?: string[];
+  enable_exit_ip_monitor?: boolean;
+  exit_ip_monitor_interval?: number;
+  schedules?: ISchedule[];
+  network_rules?: INetworkRule[];
+  tray_icon_theme?: string;
+  notification_prefs?: INotificationPrefs;
+  webhooks?: IWebhook[];
+  enable_telegram_notify?: boolean;
+  telegram_bot_token?: string;
+  telegram_chat_id?: string;
+  enable_telegram_commands?: boolean;
+  backup_password?: string;
+  enable_webdav_backup_schedule?: boolean;
+  enable_lan_sync?: boolean;
+  lan_sync_port?: number;
+  lan_sync_device_id?: string;
+  lan_sync_peers?: ILanSyncPeer[];
+  lan_sync_identity?: ILanSyncIdentity;
+  enable_control_api?: boolean;
+  control_api_port?: number;
+  control_api_token?: string;
+  app_log_format?: "text" | "json" | string;
+  crash_report_url?: string;
+  update_channel?: "stable" | "beta";
+  update_deferred_version?: string;
+  update_deferred_until?: number;
+  restrict_controller_cors?: boolean;
+  favorites?: string[];
+  discord_state_template?: string;
+  selection_per_network?: boolean;
+  speedtest_servers?: ISpeedtestServer[];
+  enable_winhttp_proxy_sync?: boolean;
+  linux_proxy_backend?: "auto" | "gnome" | "kde" | "environment";
+  auto_launch_delay?: number;
+  enable_tray_memory?: boolean;
+  core_memory_warn_mb?: number;
+  core_priority?: "low" | "below_normal" | "normal" | "above_normal" | "high";
+  core_cpu_affinity?: number[];
 }
 
 interface IWebDavFile {
@@ -896,6 +1167,135 @@ interface IWebDavFile {
   tag: string;
 }
 
+interface ILanSyncPeer {
+  id: string;
+  name: string;
+  fingerprint: string;
+}
+
+interface ILanSyncIdentity {
+  certificate: string;
+  private_key: string;
+}
+
+interface ILanPeer {
+  id: string;
+  name: string;
+  address: string;
+  port: number;
+  paired: boolean;
+}
+
+interface IAppUpdateInfo {
+  channel: "stable" | "beta";
+  current_version: string;
+  version: string;
+  date?: string;
+  changelog?: string;
+  deferred: boolean;
+}
+
+interface IServiceInfo {
+  installed: boolean;
+  available: boolean;
+  version?: string | null;
+  expected_version: string;
+  needs_reinstall: boolean;
+  in_use: boolean;
+  error?: string | null;
+}
+
+interface ICrashReportSummary {
+  id: string;
+  time: string;
+  kind: "panic" | "fatal" | string;
+  message: string;
+  version: string;
+}
+
+interface IStartupStage {
+  id: string;
+  status: "running" | "ok" | "failed" | "skipped";
+  attempts: number;
+  elapsed_ms: number;
+  error: string | null;
+}
+
+interface IFavorite {
+  name: string;
+  is_group: boolean;
+  group: string | null;
+  now: string | null;
+  selected: boolean;
+  available: boolean;
+}
+
+interface IProviderStatus {
+  name: string;
+  kind: "proxy" | "rule";
+  vehicle_type: string;
+  count: number;
+  updated_at: string | null;
+  error: string | null;
+}
+
+interface IProviderUpdateProgress {
+  name: string;
+  kind: "proxy" | "rule";
+  done: number;
+  total: number;
+  error: string | null;
+}
+
+interface IRegionSelection {
+  group: string;
+  node: string;
+  country_code: string;
+  delay: number;
+  candidates: number;
+}
+
+type CmdErrorKind =
+  | "invalid_input"
+  | "not_found"
+  | "unavailable"
+  | "network"
+  | "timeout"
+  | "locked"
+  | "internal";
+
+interface ICmdError {
+  kind: CmdErrorKind;
+  message: string;
+  recoverable: boolean;
+}
+
+interface IStartupReport {
+  started_at: string | null;
+  finished: boolean;
+  total_ms: number;
+  stages: IStartupStage[];
+}
+
+interface ICoreLogLine {
+  type: string;
+  payload: string;
+  time: number;
+}
+
+interface ICoreLogFilter {
+  level?: "debug" | "info" | "warning" | "error";
+  keyword?: string;
+  connection?: string;
+}
+
+interface IRemoteBackupFile {
+  id: string;
+  last_modified: string;
+  content_length: number;
+  encrypted: boolean;
+}
+
 interface ILocalBackupFile {
   filename: string;
   path: string;
@@ -0,0 +1,16 @@
+/** Errors rejected by Tauri commands are serialized `ICmdError` objects. */
+export const isCmdError = (error: unknown): error is ICmdError =>
+  typeof error === "object" &&
+  error !== null &&
+  typeof (error as ICmdError).kind === "string" &&
+  typeof (error as ICmdError).message === "string" &&
+  typeof (error as ICmdError).recoverable === "boolean";
+
+export const getErrorMessage = (error: unknown): string => {
+  if (isCmdError(error) || error instanceof Error) return error.message;
+  return String(error);
+};
+
+/** Whether retrying the same command later may succeed. */
+export const isRecoverableError = (error: unknown) =>
+  isCmdError(error) && error.recoverable;